@@ -0,0 +1,198 @@
+//! Reads that walk long hash chains on disk, with the page cache alone and
+//! with chain prefetching on top.
+//!
+//! Run with `cargo bench --bench chain_prefetch`. Sizes come from the
+//! environment:
+//!
+//! - `CHAINS`: hash chains, each read once per round (default 256)
+//! - `CHAIN_LENGTH`: records per chain (default 64)
+//! - `CHAIN_ROUNDS`: rounds timed per variant (default 2)
+//! - `CHAIN_READ_DELAY_US`: added to every log read, as a stand-in for
+//!   device latency (default 100)
+//!
+//! Keys of a chain are written `CHAINS` records apart, so every hop lands
+//! on a different cached page, and each read starts from an empty cache.
+//!
+//! The hops of one walk depend on each other: the page of the next record
+//! is only known once the current one is read, and the walk needs it right
+//! after. Prefetching therefore moves the reads onto the prefetch thread
+//! rather than overlapping them, and the two variants take about as long;
+//! the gap shows what handing each page over costs.
+
+use rskv::RsKv;
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::hlog::page_cache::PageCacheConfig;
+use rskv::hlog::persistent_memory_malloc::Disk;
+use rskv::rskv_core::{ReadContext, UpsertContext};
+use std::time::{Duration, Instant};
+
+/// A file system disk whose log reads take `delay` longer.
+#[derive(Clone)]
+struct SlowDisk {
+    inner: FileSystemDisk,
+    delay: Duration,
+}
+
+impl Disk for SlowDisk {
+    fn write_async(
+        &mut self,
+        offset: u64,
+        data: &[u8],
+        callback: Box<dyn FnOnce(Status) + Send>,
+    ) -> Status {
+        self.inner.write_async(offset, data, callback)
+    }
+
+    fn read_sync(&mut self, offset: u64, data: &mut [u8]) -> Status {
+        std::thread::sleep(self.delay);
+        self.inner.read_sync(offset, data)
+    }
+
+    fn log_size(&self) -> u64 {
+        self.inner.log_size()
+    }
+
+    fn append_frame(&mut self, frame: &[u8]) -> Status {
+        self.inner.append_frame(frame)
+    }
+
+    fn read_frames(&mut self) -> Result<Vec<u8>, Status> {
+        self.inner.read_frames()
+    }
+
+    fn truncate_log(&mut self, log_size: u64, frames_size: u64) -> Status {
+        self.inner.truncate_log(log_size, frames_size)
+    }
+
+    fn sync_log(&mut self) -> Status {
+        self.inner.sync_log()
+    }
+
+    fn index_checkpoint_path(&self, token: &str) -> String {
+        self.inner.index_checkpoint_path(token)
+    }
+}
+
+struct Put {
+    key: u64,
+    chains: u64,
+}
+
+impl UpsertContext for Put {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn value(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key % self.chains
+    }
+
+    fn put_atomic(&self, _value: &mut u64) -> bool {
+        false
+    }
+}
+
+struct Get {
+    key: u64,
+    chains: u64,
+    value: Option<u64>,
+}
+
+impl ReadContext for Get {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key % self.chains
+    }
+
+    fn get(&mut self, value: &u64) {
+        self.value = Some(*value);
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn main() -> Result<(), Status> {
+    let chains = env_u64("CHAINS", 256);
+    let length = env_u64("CHAIN_LENGTH", 64);
+    let rounds = env_u64("CHAIN_ROUNDS", 2);
+    let delay = Duration::from_micros(env_u64("CHAIN_READ_DELAY_US", 100));
+
+    let dir = std::env::temp_dir().join(format!("rskv-chain-prefetch-{}", std::process::id()));
+    let disk = SlowDisk {
+        inner: FileSystemDisk::new(&dir.to_string_lossy())?,
+        delay,
+    };
+    let mut kv = RsKv::<u64, u64, SlowDisk>::new(1 << 27, chains.next_power_of_two(), disk)?;
+    for key in 0..chains * length {
+        let status = kv.upsert(&Put { key, chains });
+        if status != Status::Ok {
+            return Err(status);
+        }
+    }
+
+    // Move the tail onto a fresh page, then flush, which drops every loaded
+    // page to keep within a one-page memory budget.
+    let last_page = kv.hlog.get_tail_address().page();
+    while kv.hlog.get_tail_address().page() == last_page {
+        if let Err(closed_page) = kv.hlog.allocate(kv.hlog.page_size / 4) {
+            kv.hlog.new_page(closed_page);
+        }
+    }
+    kv.set_memory_budget(Some(kv.hlog.page_size));
+    kv.flush()?;
+    println!(
+        "{chains} chains of {length} records on disk, {}us per log read",
+        delay.as_micros()
+    );
+
+    for (name, prefetch) in [("page cache", None), ("prefetch", Some(8))] {
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..rounds {
+            // The first key of each chain is the last hop of its walk.
+            for key in 0..chains {
+                // Chains share pages, so each read starts from an empty cache.
+                kv.set_page_cache(Some(PageCacheConfig::default()));
+                kv.set_chain_prefetch(prefetch);
+                let mut context = Get {
+                    key,
+                    chains,
+                    value: None,
+                };
+                let started = Instant::now();
+                if kv.read(&mut context) != Status::Ok || context.value != Some(key) {
+                    return Err(Status::Corruption);
+                }
+                elapsed += started.elapsed();
+            }
+        }
+        let stats = kv.page_cache_stats().unwrap_or_default();
+        let per_read = elapsed / (chains * rounds) as u32;
+        println!(
+            "{name:>10}: {:>8.1?} per {length}-hop read, {} misses and {} prefetch hits in the last read",
+            per_read, stats.misses, stats.prefetch_hits
+        );
+    }
+
+    drop(kv);
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
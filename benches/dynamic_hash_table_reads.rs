@@ -0,0 +1,101 @@
+//! Read-heavy mix of `get` and `upsert` on a DynamicHashTable, run at a
+//! growing number of threads to show how reads scale.
+//!
+//! Run with `cargo bench --bench dynamic_hash_table_reads`. Sizes come from
+//! the environment:
+//!
+//! - `DHT_KEYS`: keys loaded and drawn from (default 4096)
+//! - `DHT_OPS`: operations per thread (default 200000)
+//! - `DHT_READ_PERCENT`: share of operations that are reads (default 95)
+//! - `DHT_THREADS`: comma-separated thread counts (default 1,2,4,8,16,32)
+//!
+//! Keys are drawn uniformly, and the load stays below the table's growth
+//! threshold, so the run measures lookups rather than resizes.
+
+use rskv::core::light_epoch::LightEpoch;
+use rskv::index::dynamic_hash_table::{DynamicHashTable, ResizeStrategy};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn thread_counts() -> Vec<usize> {
+    std::env::var("DHT_THREADS")
+        .ok()
+        .map(|list| {
+            list.split(',')
+                .filter_map(|count| count.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_else(|| vec![1, 2, 4, 8, 16, 32])
+}
+
+fn main() {
+    let keys = env_usize("DHT_KEYS", 4096) as u64;
+    let ops = env_usize("DHT_OPS", 200_000);
+    let read_percent = env_usize("DHT_READ_PERCENT", 95).min(100) as u64;
+
+    let epoch = Arc::new(LightEpoch::new());
+    let table = Arc::new(DynamicHashTable::new(epoch.clone()));
+    table
+        .set_resize_strategy(ResizeStrategy::LoadFactor { threshold: 1.0 })
+        .expect("set resize strategy");
+    {
+        let guard = epoch.protect();
+        for key in 0..keys {
+            table.upsert(key, key, &guard).expect("load");
+        }
+    }
+    table
+        .set_resize_strategy(ResizeStrategy::None)
+        .expect("set resize strategy");
+    println!(
+        "{keys} keys in {} buckets, {read_percent}% reads",
+        table.get_statistics().current_bucket_count
+    );
+
+    let mut single = None;
+    for threads in thread_counts() {
+        let started = Instant::now();
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let (table, epoch) = (table.clone(), epoch.clone());
+                thread::spawn(move || {
+                    let mut state = 0x2545_f491_4f6c_dd1du64 ^ (worker as u64 + 1);
+                    let mut found = 0u64;
+                    for _ in 0..ops {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        let key = state % keys;
+                        let guard = epoch.protect();
+                        if (state >> 32) % 100 < read_percent {
+                            found += table.get(&key, &guard).expect("get").is_some() as u64;
+                        } else {
+                            table.upsert(key, state, &guard).expect("upsert");
+                        }
+                    }
+                    found
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().expect("worker");
+        }
+
+        let elapsed = started.elapsed();
+        let throughput = (threads * ops) as f64 / elapsed.as_secs_f64();
+        let baseline = *single.get_or_insert(throughput);
+        println!(
+            "{threads:>3} threads: {:>12.0} ops/s ({:.1}x one thread) in {elapsed:?}",
+            throughput,
+            throughput / baseline
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Point reads of random keys in an RsKv, looped one `read` at a time and
+//! handed to `read_batch` in groups.
+//!
+//! Run with `cargo bench --bench read_batch`. Sizes come from the
+//! environment:
+//!
+//! - `READ_RECORDS`: records loaded (default 1000000)
+//! - `READ_KEYS`: keys read per round (default 100000)
+//! - `READ_BATCH`: keys per `read_batch` call (default 100)
+//! - `READ_ROUNDS`: rounds timed per variant (default 5)
+//!
+//! The log is held in memory in full, so every record is resident and the
+//! comparison covers index probing and chain walks only. Once records can
+//! be evicted, the same run measures how many disk reads batching saves.
+
+use rskv::RsKv;
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::rskv_core::ReadContext;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+struct Payload {
+    words: [u64; 8],
+}
+
+struct Get {
+    key: u64,
+    found: bool,
+}
+
+impl ReadContext for Get {
+    type Key = u64;
+    type Value = Payload;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        hash(&self.key)
+    }
+
+    fn get(&mut self, _value: &Payload) {
+        self.found = true;
+    }
+}
+
+fn hash(key: &u64) -> u64 {
+    key.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn time_rounds(rounds: usize, mut read: impl FnMut() -> usize) -> (Duration, usize) {
+    let mut found = 0;
+    let started = Instant::now();
+    for _ in 0..rounds {
+        found = read();
+    }
+    (started.elapsed() / rounds.max(1) as u32, found)
+}
+
+fn main() -> Result<(), Status> {
+    let records = env_usize("READ_RECORDS", 1_000_000) as u64;
+    let keys = env_usize("READ_KEYS", 100_000);
+    let batch = env_usize("READ_BATCH", 100).max(1);
+    let rounds = env_usize("READ_ROUNDS", 5);
+
+    let dir = std::env::temp_dir().join(format!("rskv-read-batch-{}", std::process::id()));
+    let disk = FileSystemDisk::new(&dir.to_string_lossy())?;
+    let record_bytes = (size_of::<Payload>() + 32) as u64;
+    let log_size = (records * record_bytes).next_power_of_two().max(1 << 26);
+    let table_size = records.next_power_of_two();
+    let kv = RsKv::<u64, Payload, FileSystemDisk>::new(log_size, table_size, disk)?;
+    kv.bulk_load((0..records).map(|key| (key, Payload::default())), hash)?;
+    println!("loaded {records} records");
+
+    // Keys spread over the whole log, with one in ten absent.
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let probe: Vec<u64> = (0..keys)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % (records + records / 9)
+        })
+        .collect();
+
+    let (looped, expected) = time_rounds(rounds, || {
+        probe
+            .iter()
+            .filter(|&&key| {
+                let mut context = Get { key, found: false };
+                kv.read(&mut context) == Status::Ok
+            })
+            .count()
+    });
+    let (batched, found) = time_rounds(rounds, || {
+        probe
+            .chunks(batch)
+            .map(|keys| {
+                let mut contexts: Vec<Get> =
+                    keys.iter().map(|&key| Get { key, found: false }).collect();
+                kv.read_batch(&mut contexts);
+                contexts.iter().filter(|context| context.found).count()
+            })
+            .sum()
+    });
+    assert_eq!(expected, found);
+
+    println!("looped read:  {looped:?} per {keys} keys, {expected} found");
+    println!("read_batch({batch}): {batched:?} per {keys} keys, {found} found");
+
+    drop(kv);
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
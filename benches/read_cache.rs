@@ -0,0 +1,128 @@
+//! Zipfian point reads of an RsKv whose records have all been evicted to
+//! disk, without a read cache, with one admitting every record read from
+//! disk, and with one admitting only keys an access analyzer finds warm.
+//!
+//! Run with `cargo bench --bench read_cache --features bench-support`.
+//! Sizes come from the environment:
+//!
+//! - `CACHE_RECORDS`: records loaded (default 200000)
+//! - `CACHE_READS`: reads timed per variant (default 200000)
+//! - `CACHE_CAPACITY`: records the read cache holds (default 10000)
+//!
+//! Every read the cache misses is a synchronous read of the log file, which
+//! the OS page cache keeps warm, so the gap to a real disk is larger.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rskv::RsKv;
+use rskv::bench_support::{ZIPFIAN_CONSTANT, Zipfian};
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::hlog::read_cache::ReadCacheConfig;
+use rskv::performance::access_analyzer::{AccessAnalyzer, Heat};
+use rskv::rskv_core::ReadContext;
+use std::sync::Arc;
+use std::time::Instant;
+
+struct Get {
+    key: u64,
+    found: bool,
+}
+
+impl ReadContext for Get {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        hash(&self.key)
+    }
+
+    fn get(&mut self, _value: &u64) {
+        self.found = true;
+    }
+}
+
+fn hash(key: &u64) -> u64 {
+    key.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn main() -> Result<(), Status> {
+    let records = env_usize("CACHE_RECORDS", 200_000) as u64;
+    let reads = env_usize("CACHE_READS", 200_000);
+    let capacity = env_usize("CACHE_CAPACITY", 10_000);
+
+    let dir = std::env::temp_dir().join(format!("rskv-read-cache-{}", std::process::id()));
+    let disk = FileSystemDisk::new(&dir.to_string_lossy())?;
+    let log_size = (records * 64).next_power_of_two().max(1 << 26);
+    let mut kv =
+        RsKv::<u64, u64, FileSystemDisk>::new(log_size, records.next_power_of_two(), disk)?;
+    kv.bulk_load((0..records).map(|key| (key, key)), hash)?;
+
+    // Move the tail onto a fresh page, then flush, which drops every loaded
+    // page to keep within a one-page memory budget.
+    let last_page = kv.hlog.get_tail_address().page();
+    while kv.hlog.get_tail_address().page() == last_page {
+        if let Err(closed_page) = kv.hlog.allocate(kv.hlog.page_size / 4) {
+            kv.hlog.new_page(closed_page);
+        }
+    }
+    kv.set_memory_budget(Some(kv.hlog.page_size));
+    kv.flush()?;
+    println!("{records} records on disk, {reads} Zipfian reads, cache of {capacity}");
+
+    let mut zipfian = Zipfian::new(records, ZIPFIAN_CONSTANT);
+    let mut rng = StdRng::seed_from_u64(7);
+    let keys: Vec<u64> = (0..reads)
+        .map(|_| zipfian.sample(&mut rng, records))
+        .collect();
+
+    let variants: [(&str, Option<Heat>); 3] = [
+        ("no cache", None),
+        ("admit all", Some(Heat::Cold)),
+        ("admit warm", Some(Heat::Warm)),
+    ];
+    for (name, min_heat) in variants {
+        kv.set_access_analyzer(
+            (min_heat == Some(Heat::Warm))
+                .then(|| Arc::new(AccessAnalyzer::new(Default::default()))),
+        );
+        kv.set_read_cache(min_heat.map(|min_heat| ReadCacheConfig { capacity, min_heat }));
+
+        let started = Instant::now();
+        let found = keys
+            .iter()
+            .filter(|&&key| {
+                let mut context = Get { key, found: false };
+                kv.read(&mut context) == Status::Ok
+            })
+            .count();
+        let elapsed = started.elapsed();
+        assert_eq!(found, reads);
+
+        let throughput = reads as f64 / elapsed.as_secs_f64();
+        match kv.read_cache_stats() {
+            Some(stats) => println!(
+                "{name:>10}: {throughput:>10.0} reads/s, hit rate {:.1}%, {} admitted, {} rejected",
+                stats.hit_rate() * 100.0,
+                stats.admitted,
+                stats.rejected
+            ),
+            None => println!("{name:>10}: {throughput:>10.0} reads/s"),
+        }
+    }
+
+    drop(kv);
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
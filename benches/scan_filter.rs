@@ -0,0 +1,131 @@
+//! Filtered scans over cold-tier values in an R2Kv, with the filter applied
+//! by the caller after `scan_prefix` and pushed down into `scan_filter`.
+//!
+//! Run with `cargo bench --bench scan_filter`. Sizes come from the
+//! environment:
+//!
+//! - `SCAN_RECORDS`: records loaded and demoted to the cold tier (default
+//!   100000)
+//! - `SCAN_ROUNDS`: scans timed per variant (default 5)
+//!
+//! One record in a hundred matches the filter, so most values are checked in
+//! place and never copied out.
+
+use rskv::core::status::Status;
+use rskv::performance::access_analyzer::AnalyzerConfig;
+use rskv::performance::migration_manager::MigrationConfig;
+use rskv::r2::{R2Config, R2Kv};
+use rskv::rskv_core::UpsertContext;
+use std::time::{Duration, Instant};
+
+const PAYLOAD_BYTES: usize = 248;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Payload {
+    category: u64,
+    bytes: [u8; PAYLOAD_BYTES],
+}
+
+impl Default for Payload {
+    fn default() -> Self {
+        Self {
+            category: 0,
+            bytes: [0; PAYLOAD_BYTES],
+        }
+    }
+}
+
+struct Put {
+    key: u64,
+    value: Payload,
+}
+
+impl UpsertContext for Put {
+    type Key = u64;
+    type Value = Payload;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn value(&self) -> &Payload {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn put_atomic(&self, _value: &mut Payload) -> bool {
+        false
+    }
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn time_rounds(rounds: usize, mut scan: impl FnMut() -> usize) -> (Duration, usize) {
+    let mut matched = 0;
+    let started = Instant::now();
+    for _ in 0..rounds {
+        matched = scan();
+    }
+    (started.elapsed() / rounds.max(1) as u32, matched)
+}
+
+fn main() -> Result<(), Status> {
+    let records = env_usize("SCAN_RECORDS", 100_000);
+    let rounds = env_usize("SCAN_ROUNDS", 5);
+
+    let dir = std::env::temp_dir().join(format!("rskv-scan-filter-{}", std::process::id()));
+    let hot_path = dir.join("hot").to_string_lossy().into_owned();
+    let cold_path = dir.join("cold").to_string_lossy().into_owned();
+    let mut config = R2Config::new(&hot_path, &cold_path);
+    config.hot.table_size = 1 << 16;
+    config.cold.table_size = 1 << 18;
+    config.migration = MigrationConfig {
+        max_hot_size_bytes: 1 << 20,
+        target_hot_utilization: 0.0,
+        migration_batch_size: records,
+        ..Default::default()
+    };
+    config.analyzer = AnalyzerConfig::default();
+
+    let store = R2Kv::<u64, Payload>::with_config(config)?;
+    for key in 0..records as u64 {
+        let value = Payload {
+            category: key % 100,
+            bytes: [key as u8; PAYLOAD_BYTES],
+        };
+        store.upsert(&Put { key, value });
+    }
+    let demoted = store.run_demotion_pass();
+    println!("loaded {records} records, demoted {demoted} to the cold tier");
+
+    let is_match = |value: &[u8]| value[..8] == 0u64.to_ne_bytes();
+    let (caller_side, expected) = time_rounds(rounds, || {
+        store
+            .scan_prefix(&[])
+            .into_iter()
+            .filter(|(_, value)| value.category == 0)
+            .count()
+    });
+    let (pushed_down, matched) = time_rounds(rounds, || {
+        store
+            .scan_filter(&[], |_, value| is_match(value), None)
+            .len()
+    });
+    assert_eq!(expected, matched);
+
+    println!("caller-side filter: {caller_side:?} per scan, {expected} matches");
+    println!("pushed-down filter: {pushed_down:?} per scan, {matched} matches");
+
+    drop(store);
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
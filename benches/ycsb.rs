@@ -0,0 +1,210 @@
+//! YCSB workloads A-F against an on-disk RsKv.
+//!
+//! Run with `cargo bench --features bench-support --bench ycsb`. Sizes and
+//! workloads come from the environment:
+//!
+//! - `YCSB_RECORDS`: records loaded before each workload (default 100000)
+//! - `YCSB_OPERATIONS`: operations per workload (default 100000)
+//! - `YCSB_WORKLOADS`: comma-separated letters (default `a,b,c,d,e,f`)
+//! - `YCSB_OUTPUT`: file to write the JSON summaries to, one per line;
+//!   stdout if unset
+//!
+//! RsKv has no range scan, so workload E reads consecutive record numbers.
+
+use rskv::bench_support::{WorkloadSpec, YcsbConfig, YcsbTarget, run_workload};
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::rskv_core::{ReadContext, RmwContext, RsKv, UpsertContext};
+use std::io::Write;
+
+struct Put {
+    key: u64,
+    value: u64,
+}
+
+impl UpsertContext for Put {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn value(&self) -> &u64 {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn put_atomic(&self, _value: &mut u64) -> bool {
+        false
+    }
+}
+
+struct Get {
+    key: u64,
+    found: bool,
+}
+
+impl ReadContext for Get {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn get(&mut self, value: &u64) {
+        self.found = true;
+        std::hint::black_box(value);
+    }
+}
+
+struct Mix {
+    key: u64,
+    value: u64,
+}
+
+impl RmwContext for Mix {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn rmw_initial(&self, value: &mut u64) {
+        *value = self.value;
+    }
+
+    fn rmw_copy(&self, old_value: &u64, new_value: &mut u64) {
+        *new_value = old_value ^ self.value;
+    }
+
+    fn rmw_atomic(&self, _value: &mut u64) -> bool {
+        false
+    }
+}
+
+struct RsKvTarget {
+    kv: RsKv<'static, u64, u64, FileSystemDisk>,
+}
+
+fn check(status: Status) -> Result<(), Status> {
+    match status {
+        Status::Ok => Ok(()),
+        status => Err(status),
+    }
+}
+
+impl YcsbTarget for RsKvTarget {
+    fn load(&mut self, record_count: u64) -> Result<(), Status> {
+        self.kv
+            .bulk_load((0..record_count).map(|key| (key, key)), |key| *key)
+            .map(|_| ())
+    }
+
+    fn read(&mut self, key: u64) -> Result<bool, Status> {
+        let mut context = Get { key, found: false };
+        match self.kv.read(&mut context) {
+            Status::Ok => Ok(context.found),
+            Status::NotFound => Ok(false),
+            status => Err(status),
+        }
+    }
+
+    fn update(&mut self, key: u64, value: u64) -> Result<(), Status> {
+        check(self.kv.upsert(&Put { key, value }))
+    }
+
+    fn insert(&mut self, key: u64, value: u64) -> Result<(), Status> {
+        check(self.kv.upsert(&Put { key, value }))
+    }
+
+    fn scan(&mut self, start: u64, len: usize) -> Result<usize, Status> {
+        let mut found = 0;
+        for key in start..start + len as u64 {
+            if self.read(key)? {
+                found += 1;
+            }
+        }
+        Ok(found)
+    }
+
+    fn read_modify_write(&mut self, key: u64, value: u64) -> Result<(), Status> {
+        check(self.kv.rmw(&mut Mix { key, value }))
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a number, got {:?}", name, value)),
+        Err(_) => default,
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = YcsbConfig {
+        record_count: env_u64("YCSB_RECORDS", 100_000),
+        operation_count: env_u64("YCSB_OPERATIONS", 100_000),
+        seed: env_u64("YCSB_SEED", 0),
+    };
+    let workloads = std::env::var("YCSB_WORKLOADS").unwrap_or_else(|_| "a,b,c,d,e,f".into());
+    let mut output: Box<dyn Write> = match std::env::var("YCSB_OUTPUT") {
+        Ok(path) => Box::new(std::fs::File::create(path)?),
+        Err(_) => Box::new(std::io::stdout()),
+    };
+
+    for name in workloads
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let spec =
+            WorkloadSpec::by_name(name).ok_or_else(|| format!("unknown workload {:?}", name))?;
+        let dir =
+            std::env::temp_dir().join(format!("rskv_ycsb_{}_{}", spec.name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = FileSystemDisk::new(dir.to_str().ok_or("temp dir is not UTF-8")?)?;
+        let table_size = (config.record_count + config.operation_count).next_power_of_two();
+        let kv = RsKv::new(1 << 27, table_size, disk)?;
+        let mut target = RsKvTarget { kv };
+
+        let summary = run_workload(&mut target, &spec, &config)?;
+        eprintln!(
+            "workload {}: load {:.2?}, run {:.2?}, {:.0} ops/s",
+            summary.workload,
+            summary.load_elapsed,
+            summary.run_elapsed,
+            summary.throughput()
+        );
+        for (kind, histogram) in &summary.latencies {
+            eprintln!(
+                "  {:<6} count {:>8}  p50 {:>9.1?}  p99 {:>9.1?}  p99.9 {:>9.1?}  max {:>9.1?}",
+                kind.name(),
+                histogram.count(),
+                histogram.percentile(0.50),
+                histogram.percentile(0.99),
+                histogram.percentile(0.999),
+                histogram.max()
+            );
+        }
+        writeln!(output, "{}", summary.to_json())?;
+
+        drop(target);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::rskv_core::{KeyLockOptions, ReadContext, RsKv, UpsertContext};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+struct BalanceUpsert {
+    account: u64,
+    balance: u64,
+}
+
+impl UpsertContext for BalanceUpsert {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &Self::Key {
+        &self.account
+    }
+
+    fn value(&self) -> &Self::Value {
+        &self.balance
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.account
+    }
+
+    fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+        false // Always append a new record
+    }
+}
+
+struct BalanceRead {
+    account: u64,
+    balance: Option<u64>,
+}
+
+impl ReadContext for BalanceRead {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &Self::Key {
+        &self.account
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.account
+    }
+
+    fn get(&mut self, value: &Self::Value) {
+        self.balance = Some(*value);
+    }
+}
+
+type Store = RsKv<'static, u64, u64, FileSystemDisk>;
+
+fn balance(kv: &Store, account: u64) -> u64 {
+    let mut context = BalanceRead {
+        account,
+        balance: None,
+    };
+    match kv.read(&mut context) {
+        Status::Ok => context.balance.unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Withdraws `amount` if the account covers it. The key stays locked from
+/// the balance check through the write, so two withdrawals can't both pass
+/// the check against the same balance.
+fn withdraw(kv: &Store, account: u64, amount: u64) -> Result<bool, Status> {
+    let _lock = kv.lock_key(&account, account)?;
+
+    let current = balance(kv, account);
+    if current < amount {
+        return Ok(false);
+    }
+
+    // Stands in for an external call, such as asking a payment service
+    thread::sleep(Duration::from_millis(1));
+
+    let status = kv.upsert(&BalanceUpsert {
+        account,
+        balance: current - amount,
+    });
+    if status != Status::Ok {
+        return Err(status);
+    }
+    Ok(true)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = "/tmp/rskv_key_lock_example";
+    if Path::new(dir).exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::create_dir_all(dir)?;
+
+    let disk = FileSystemDisk::new(dir)?;
+    let mut kv = Store::new(1 << 26, 1 << 10, disk)?;
+    kv.set_key_locking(Some(KeyLockOptions::default()));
+    let kv = Arc::new(kv);
+
+    let account = 42;
+    let initial = 100;
+    kv.upsert(&BalanceUpsert {
+        account,
+        balance: initial,
+    });
+
+    // Eight clients each try to take 30 from a balance of 100
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let kv = kv.clone();
+            thread::spawn(move || withdraw(&kv, account, 30))
+        })
+        .collect();
+
+    let mut approved = 0;
+    for handle in handles {
+        if handle.join().unwrap()? {
+            approved += 1;
+        }
+    }
+
+    let remaining = balance(&kv, account);
+    println!("approved {} withdrawals, balance {}", approved, remaining);
+    assert_eq!(approved, 3);
+    assert_eq!(remaining, initial - approved * 30);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use rskv::core::advanced_locking::{
+    BackoffConfig, HierarchicalLockManager, LockId, LockIntent, LockManagerConfig,
+};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const THREADS: usize = 16;
+const OPS_PER_THREAD: usize = 500;
+
+/// Runs every thread against one bucket lock and returns each acquisition's
+/// wait, sorted.
+fn hammer_one_bucket(backoff: BackoffConfig) -> Vec<Duration> {
+    let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+        base_timeout: Duration::from_secs(10),
+        max_timeout: Duration::from_secs(10),
+        adaptive: false,
+        backoff,
+        ..Default::default()
+    }));
+    let barrier = Arc::new(Barrier::new(THREADS));
+    let lock_id = LockId::bucket(0);
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let manager = manager.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut waits = Vec::with_capacity(OPS_PER_THREAD);
+                barrier.wait();
+                for _ in 0..OPS_PER_THREAD {
+                    let start = Instant::now();
+                    let guard = manager.acquire_lock(lock_id, LockIntent::Write).unwrap();
+                    waits.push(start.elapsed());
+                    // Short critical section
+                    std::hint::black_box((0..200).sum::<u64>());
+                    drop(guard);
+                }
+                waits
+            })
+        })
+        .collect();
+
+    let mut waits: Vec<Duration> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+    waits.sort();
+    waits
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+fn report(name: &str, waits: &[Duration], elapsed: Duration) {
+    println!(
+        "{:<22} p50 {:>10.1?}  p99 {:>10.1?}  p99.9 {:>10.1?}  max {:>10.1?}  total {:>8.1?}",
+        name,
+        percentile(waits, 0.50),
+        percentile(waits, 0.99),
+        percentile(waits, 0.999),
+        waits[waits.len() - 1],
+        elapsed
+    );
+}
+
+fn main() {
+    println!(
+        "{} threads x {} acquisitions of one bucket lock",
+        THREADS, OPS_PER_THREAD
+    );
+
+    let cases = [
+        ("fixed 5ms retry", BackoffConfig::fixed(Duration::from_millis(5))),
+        ("jittered exponential", BackoffConfig::default()),
+    ];
+    for (name, backoff) in cases {
+        let start = Instant::now();
+        let waits = hammer_one_bucket(backoff);
+        report(name, &waits, start.elapsed());
+    }
+}
@@ -0,0 +1,146 @@
+use rskv::core::status::Status;
+use rskv::r2::{R2Config, R2Kv, TierStorageConfig};
+use rskv::rskv_core::{ReadContext, UpsertContext};
+use std::path::Path;
+
+// 简单的测试数据结构
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct TestData {
+    id: u64,
+    value: u64,
+}
+
+// Upsert上下文实现
+struct TestUpsertContext {
+    key: u64,
+    value: TestData,
+}
+
+impl UpsertContext for TestUpsertContext {
+    type Key = u64;
+    type Value = TestData;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn value(&self) -> &Self::Value {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+        false // 总是使用RCU路径
+    }
+}
+
+// Read上下文实现
+struct TestReadContext {
+    key: u64,
+    value: Option<TestData>,
+}
+
+impl ReadContext for TestReadContext {
+    type Key = u64;
+    type Value = TestData;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn get(&mut self, value: &Self::Value) {
+        self.value = Some(*value);
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("R2 分层存储配置示例");
+    println!("================================");
+
+    // 热存储放在快速设备上，冷存储放在廉价设备上
+    let hot_dir = "/tmp/r2_split_nvme";
+    let cold_dir = "/tmp/r2_split_hdd";
+
+    for dir in [hot_dir, cold_dir] {
+        if Path::new(dir).exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let record_size = std::mem::size_of::<u64>() + std::mem::size_of::<TestData>();
+    let mut config = R2Config {
+        hot: TierStorageConfig {
+            log_size: 1 << 26, // 64MB
+            table_size: 1 << 12,
+            ..TierStorageConfig::hot(hot_dir)
+        },
+        cold: TierStorageConfig {
+            log_size: 1 << 28, // 256MB
+            table_size: 1 << 16,
+            ..TierStorageConfig::cold(cold_dir)
+        },
+        ..R2Config::new(hot_dir, cold_dir)
+    };
+    config.migration.max_hot_size_bytes = 500 * record_size;
+    config.migration.target_hot_utilization = 0.5;
+    config.migration.migration_batch_size = 1000;
+
+    // 同一路径用于两个层级会被拒绝
+    let invalid = R2Config::new(hot_dir, hot_dir);
+    match R2Kv::<u64, TestData>::with_config(invalid) {
+        Err(Status::InvalidConfiguration) => println!("相同路径配置被正确拒绝"),
+        Err(status) => println!("意外错误: {:?}", status),
+        Ok(_) => println!("相同路径配置未被拒绝"),
+    }
+
+    let r2_kv = R2Kv::<u64, TestData>::with_config(config)?;
+    println!("热存储路径: {}", hot_dir);
+    println!("冷存储路径: {}", cold_dir);
+
+    println!("\n写入1000条记录...");
+    for i in 1..=1000 {
+        let upsert_ctx = TestUpsertContext {
+            key: i,
+            value: TestData { id: i, value: i * 10 },
+        };
+        r2_kv.upsert(&upsert_ctx);
+    }
+
+    let demoted = r2_kv.run_demotion_pass();
+    println!("降级到冷存储: {} 条记录", demoted);
+
+    // 读取部分记录，冷命中会被提升到热存储
+    for i in (1..=1000).step_by(10) {
+        let mut read_ctx = TestReadContext {
+            key: i,
+            value: None,
+        };
+        r2_kv.read(&mut read_ctx);
+    }
+    r2_kv.complete_pending_promotions();
+
+    let stats = r2_kv.tier_stats();
+    println!("\n层级统计:");
+    println!("   热存储: {} 条记录, {} 字节", stats.hot_records, stats.hot_bytes);
+    println!("   冷存储: {} 条记录, {} 字节", stats.cold_records, stats.cold_bytes);
+    println!(
+        "   命中: 热={} 冷={} 未命中={}",
+        stats.hot_hits, stats.cold_hits, stats.misses
+    );
+    println!("   迁移: 提升={} 降级={}", stats.promotions, stats.demotions);
+
+    for dir in [hot_dir, cold_dir] {
+        std::fs::remove_dir_all(dir)?;
+    }
+    println!("\n清理完成");
+
+    Ok(())
+}
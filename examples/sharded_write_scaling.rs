@@ -0,0 +1,137 @@
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::rskv_core::{RecoveryOptions, RsKv, UpsertContext};
+use rskv::sharded::ShardedRsKv;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const WRITES_PER_THREAD: u64 = 200_000;
+
+struct Put {
+    key: u64,
+    value: u64,
+}
+
+impl UpsertContext for Put {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn value(&self) -> &u64 {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        // Spread sequential keys over buckets and tags
+        self.key.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn put_atomic(&self, _value: &mut u64) -> bool {
+        false
+    }
+}
+
+/// Runs `threads` writers, each upserting its own key range through
+/// `upsert`, and returns the elapsed time.
+fn run_writers(threads: usize, upsert: Arc<dyn Fn(&Put) -> Status + Send + Sync>) -> Duration {
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads as u64)
+        .map(|thread_id| {
+            let upsert = upsert.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..WRITES_PER_THREAD {
+                    let key = thread_id * WRITES_PER_THREAD + i;
+                    assert_eq!(upsert(&Put { key, value: i }), Status::Ok);
+                }
+            })
+        })
+        .collect();
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+/// Smallest whole number of log pages holding `records` records
+fn log_size_for(records: u64) -> u64 {
+    const PAGE: u64 = 1 << 25;
+    (records * 24 * 2).div_ceil(PAGE).max(1) * PAGE
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let shards: usize = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse())
+        .transpose()?
+        .unwrap_or(8);
+    let total = threads as u64 * WRITES_PER_THREAD;
+    println!(
+        "{} writer threads x {} upserts, single store against {} shards",
+        threads, WRITES_PER_THREAD, shards
+    );
+
+    let base = std::env::temp_dir().join(format!("rskv_sharded_scaling_{}", std::process::id()));
+    let single_dir = base.join("single");
+    let sharded_dir = base.join("sharded");
+    let _ = std::fs::remove_dir_all(&base);
+
+    let single = Arc::new(RsKv::<u64, u64, FileSystemDisk>::new(
+        log_size_for(total),
+        (total / 4).next_power_of_two(),
+        FileSystemDisk::new(single_dir.to_str().ok_or("temp dir is not UTF-8")?)?,
+    )?);
+    let elapsed = {
+        let single = single.clone();
+        run_writers(threads, Arc::new(move |put: &Put| single.upsert(put)))
+    };
+    println!(
+        "single store: {:>8.1?}  {:>12.0} upserts/s",
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+    drop(single);
+
+    let per_shard = total.div_ceil(shards as u64);
+    let options = RecoveryOptions {
+        log_size: log_size_for(per_shard),
+        table_size: (per_shard / 4).next_power_of_two(),
+        ..Default::default()
+    };
+    let sharded = Arc::new(ShardedRsKv::<u64, u64>::new(
+        sharded_dir.to_str().ok_or("temp dir is not UTF-8")?,
+        &options,
+        shards,
+    )?);
+    let elapsed = {
+        let sharded = sharded.clone();
+        run_writers(threads, Arc::new(move |put: &Put| sharded.upsert(put)))
+    };
+    println!(
+        "{} shards:     {:>8.1?}  {:>12.0} upserts/s",
+        shards,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+    let stats = sharded.stats();
+    println!(
+        "index entries per shard: {:?}",
+        stats
+            .shards
+            .iter()
+            .map(|shard| shard.index_entries)
+            .collect::<Vec<_>>()
+    );
+    drop(sharded);
+
+    let _ = std::fs::remove_dir_all(&base);
+    Ok(())
+}
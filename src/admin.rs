@@ -0,0 +1,816 @@
+//! Operator entry points for a closed store: dumping its records, reporting
+//! on its files, compacting its log in place, and rewriting it into a fresh
+//! minimal store.
+//!
+//! Each function takes the directory lock of the store it works on, so it
+//! fails with `Status::StoreLocked` while a live process has the store open.
+//! Only the durable part of the log is considered: the flush frames are
+//! followed from the start and stop at the first one that does not verify,
+//! the same way opening the store cuts off a torn tail.
+
+use crate::core::checkpoint::CheckpointMetadata;
+use crate::core::status::Status;
+use crate::core::utility::crc32_update;
+use crate::device::file_system_disk::FileSystemDisk;
+use crate::environment::file::{DirectoryLock, sync_directory};
+use crate::hlog::persistent_memory_malloc::{
+    Disk, FLUSH_FRAME_SIZE, FlushFrame, PersistentMemoryMalloc,
+};
+use crate::hlog::superblock::{SUPERBLOCK_SIZE, Superblock, UNTIMED_FORMAT_VERSION};
+use crate::repair::{NewestRecords, RecentVersions, for_each_record, key_bytes_hash};
+use crate::rskv_core::RsKv;
+use std::fmt::Debug;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Options for [`dump`].
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    /// Write every record in log order, tombstones included, instead of
+    /// only the newest value of each live key
+    pub history: bool,
+}
+
+/// Summary of a [`dump`] run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DumpReport {
+    /// Records read from the durable log
+    pub records_scanned: u64,
+    /// Lines written
+    pub records_written: u64,
+}
+
+/// One checkpoint found by [`stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointSummary {
+    pub token: String,
+    /// Index size the checkpoint recovers into
+    pub table_size: u64,
+    pub log_begin_address: u64,
+    /// Log address the checkpoint covers up to
+    pub final_address: u64,
+    /// Creation time in nanoseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Bytes of metadata and index files
+    pub bytes: u64,
+    /// The metadata checksums verify and the checkpoint belongs to this log
+    pub valid: bool,
+}
+
+/// What [`stats`] found in a storage directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreReport {
+    /// `None` while the log is empty
+    pub superblock: Option<Superblock>,
+    pub log_bytes: u64,
+    pub frame_bytes: u64,
+    /// End of the last flush frame that verifies
+    pub durable_until: u64,
+    /// Log bytes past `durable_until`, cut off when the store is opened
+    pub torn_bytes: u64,
+    /// Checkpoints, oldest first
+    pub checkpoints: Vec<CheckpointSummary>,
+}
+
+/// Options for [`compact`].
+#[derive(Debug, Clone)]
+pub struct CompactOptions {
+    pub log_size: u64,
+    /// Index size of the compacted store; sized to the live keys if `None`
+    pub table_size: Option<u64>,
+    /// Token of the checkpoint taken of the compacted store
+    pub checkpoint_token: String,
+    /// Versions of each live key to keep, newest first, for
+    /// [`RsKv::read_versions`]. Versions from before a key's last delete are
+    /// always dropped.
+    pub keep_versions: usize,
+}
+
+impl Default for CompactOptions {
+    fn default() -> Self {
+        Self {
+            log_size: 1 << 30,
+            table_size: None,
+            checkpoint_token: "compacted".to_string(),
+            keep_versions: 1,
+        }
+    }
+}
+
+/// Options for [`compact_offline`].
+#[derive(Debug, Clone)]
+pub struct OfflineCompactOptions {
+    pub log_size: u64,
+    /// Index size of the new store; sized to the live keys if `None`
+    pub table_size: Option<u64>,
+    /// Token of the checkpoint taken of the new store
+    pub checkpoint_token: String,
+    /// Leave out the records of a flush frame that fails its checksum and go
+    /// on with the next frame, instead of failing with `Status::Corruption`
+    pub skip_corrupt_frames: bool,
+}
+
+impl Default for OfflineCompactOptions {
+    fn default() -> Self {
+        Self {
+            log_size: 1 << 30,
+            table_size: None,
+            checkpoint_token: "compacted".to_string(),
+            skip_corrupt_frames: false,
+        }
+    }
+}
+
+/// Summary of a [`compact`] or [`compact_offline`] run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactReport {
+    /// Records read from the durable log
+    pub records_scanned: u64,
+    /// Records written to the compacted log, one per kept version
+    pub records_kept: u64,
+    /// Keys whose newest record was a tombstone
+    pub tombstones_dropped: u64,
+    /// Records dropped for a newer version of their key, tombstones before
+    /// the newest record of their key included
+    pub superseded_dropped: u64,
+    /// Flush frames left out because they failed their checksum
+    pub frames_skipped: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// The raw log of a store and the flush frames over it that verify.
+struct DurableLog {
+    log: Vec<u8>,
+    frame_bytes: u64,
+    /// Verified frames, in order
+    frames: Vec<FlushFrame>,
+}
+
+/// The log and frame log of the store in `root`, refused if the log is in
+/// a format that has to be migrated first.
+fn read_log_files(root: &Path) -> Result<(Vec<u8>, Vec<u8>), Status> {
+    let log = fs::read(root.join("hlog.log")).map_err(|_| Status::IoError)?;
+    let frames = fs::read(root.join("hlog.frames")).map_err(|_| Status::IoError)?;
+    if let Some(version) = Superblock::format_version(&log)
+        && version <= UNTIMED_FORMAT_VERSION
+    {
+        log::error!(
+            "{} has log format version {}; migrate it first",
+            root.display(),
+            version
+        );
+        return Err(Status::VersionMismatch);
+    }
+    Ok((log, frames))
+}
+
+fn read_durable_log(root: &Path) -> Result<DurableLog, Status> {
+    let (log, frames) = read_log_files(root)?;
+    Ok(DurableLog {
+        frame_bytes: frames.len() as u64,
+        frames: verified_frames(&log, &frames),
+        log,
+    })
+}
+
+/// The frames of the frame log `frames` that verify against `log`, up to the
+/// first one that does not.
+pub(crate) fn verified_frames(log: &[u8], frames: &[u8]) -> Vec<FlushFrame> {
+    let mut durable = Vec::new();
+    let mut end = 0u64;
+    for bytes in frames.chunks(FLUSH_FRAME_SIZE) {
+        let Some(frame) = FlushFrame::decode(bytes) else {
+            break;
+        };
+        if frame.begin > end || frame.end < frame.begin || frame.end > log.len() as u64 {
+            break;
+        }
+        if crc32_update(0, &log[frame.begin as usize..frame.end as usize]) != frame.data_crc {
+            break;
+        }
+        end = frame.end;
+        durable.push(frame);
+    }
+    durable
+}
+
+/// Every flush frame of the frame log `frames`, paired with whether it
+/// verifies against `log`. Unlike [`verified_frames`], a frame that fails
+/// does not end the scan; only a frame that does not decode does, which is
+/// where an append to the frame log was cut off.
+fn checked_frames(log: &[u8], frames: &[u8]) -> Vec<(FlushFrame, bool)> {
+    let mut checked = Vec::new();
+    let mut end = 0u64;
+    for bytes in frames.chunks(FLUSH_FRAME_SIZE) {
+        let Some(frame) = FlushFrame::decode(bytes) else {
+            break;
+        };
+        let verifies = frame.begin <= end
+            && frame.begin <= frame.end
+            && frame.end <= log.len() as u64
+            && crc32_update(0, &log[frame.begin as usize..frame.end as usize]) == frame.data_crc;
+        end = end.max(frame.end);
+        checked.push((frame, verifies));
+    }
+    checked
+}
+
+/// Writes the records of the store in `storage_dir` to `writer`, one per
+/// line. By default each live key is written once with its newest value as
+/// `key<TAB>value`. With [`DumpOptions::history`], every record is written
+/// in log order as `address<TAB>put|delete<TAB>key<TAB>value`. Keys and
+/// values use their `Debug` form. The store is only read, under a shared
+/// lock.
+pub fn dump<K, V>(
+    storage_dir: &str,
+    writer: &mut impl Write,
+    options: &DumpOptions,
+) -> Result<DumpReport, Status>
+where
+    K: Sized + Copy + 'static + PartialEq + Debug,
+    V: Sized + Clone + 'static + Default + Debug,
+{
+    let _lock = DirectoryLock::acquire(storage_dir, true)?;
+    let DurableLog { log, frames, .. } = read_durable_log(Path::new(storage_dir))?;
+    let mut report = DumpReport::default();
+    let mut newest = NewestRecords::new();
+    let mut written = Ok(());
+
+    for frame in &frames {
+        for_each_record::<K, V>(
+            &log,
+            frame.begin,
+            frame.end,
+            |address, header, key, value| {
+                report.records_scanned += 1;
+                if !options.history {
+                    newest.insert(key_bytes_hash(&key), header, key, value);
+                    return;
+                }
+                let line = if header.tombstone() {
+                    writeln!(writer, "{}\tdelete\t{:?}\t-", address, key)
+                } else {
+                    writeln!(writer, "{}\tput\t{:?}\t{:?}", address, key, value)
+                };
+                if written.is_ok() {
+                    written = line;
+                    report.records_written += 1;
+                }
+            },
+        );
+    }
+    if !options.history {
+        for (key, value, _) in newest.into_live().0 {
+            writeln!(writer, "{:?}\t{:?}", key, value).map_err(|_| Status::IoError)?;
+            report.records_written += 1;
+        }
+    }
+    written.map_err(|_| Status::IoError)?;
+    Ok(report)
+}
+
+/// Size of every file under `path`, or 0 if it does not exist.
+fn tree_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return fs::metadata(path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| tree_size(&entry.path()))
+        .sum()
+}
+
+/// Reports sizes, the superblock, the durable end of the log and the
+/// checkpoints of the store in `storage_dir`, without recovering it. The
+/// store is only read, under a shared lock.
+pub fn stats(storage_dir: &str) -> Result<StoreReport, Status> {
+    let _lock = DirectoryLock::acquire(storage_dir, true)?;
+    let root = Path::new(storage_dir);
+    let DurableLog {
+        log,
+        frame_bytes,
+        frames: durable,
+    } = read_durable_log(root)?;
+
+    let page_size = PersistentMemoryMalloc::<FileSystemDisk>::K_PAGE_SIZE;
+    let superblock = if log.len() >= SUPERBLOCK_SIZE {
+        Some(Superblock::decode(&log[..SUPERBLOCK_SIZE], page_size)?)
+    } else {
+        None
+    };
+    let durable_until = durable.last().map(|frame| frame.end).unwrap_or(0);
+    let mut report = StoreReport {
+        superblock,
+        log_bytes: log.len() as u64,
+        frame_bytes,
+        durable_until,
+        torn_bytes: (log.len() as u64).saturating_sub(durable_until),
+        checkpoints: Vec::new(),
+    };
+
+    let checkpoint_root = root.join("index-checkpoints");
+    let tokens = fs::read_dir(&checkpoint_root)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned());
+    for token in tokens {
+        let dir: PathBuf = checkpoint_root.join(&token);
+        let Ok(metadata) = CheckpointMetadata::read(&format!("{}/", dir.display())) else {
+            continue;
+        };
+        let valid = metadata.index_metadata.validate_checksum()
+            && metadata.log_metadata.validate_checksum()
+            && report.superblock.is_some_and(|superblock| {
+                superblock.store_uuid == metadata.log_metadata.store_uuid
+            });
+        report.checkpoints.push(CheckpointSummary {
+            token,
+            table_size: metadata.index_metadata.table_size,
+            log_begin_address: metadata.index_metadata.log_begin_address.control(),
+            final_address: metadata.log_metadata.final_address.control(),
+            timestamp: metadata.index_metadata.timestamp,
+            bytes: tree_size(&dir),
+            valid,
+        });
+    }
+    report
+        .checkpoints
+        .sort_by(|a, b| (a.timestamp, &a.token).cmp(&(b.timestamp, &b.token)));
+    Ok(report)
+}
+
+/// Rewrites the log of the store in `storage_dir` with only the newest
+/// [`CompactOptions::keep_versions`] values of each live key, and takes a
+/// checkpoint of the result. Earlier
+/// checkpoints refer to the old log and are dropped with it. Kept records
+/// keep their [`WriteOptions::user_flags`]. `key_hash` must match the hash
+/// the store is written with.
+///
+/// The compacted store is built in a sibling `<storage_dir>.compacting`
+/// directory and swapped in with two renames. The old store is moved to
+/// `<storage_dir>.precompact` in between and removed at the end. If the
+/// process dies between the renames, both copies are left intact for an
+/// operator to pick from.
+///
+/// [`WriteOptions::user_flags`]: crate::rskv_core::WriteOptions::user_flags
+pub fn compact<K, V>(
+    storage_dir: &str,
+    options: &CompactOptions,
+    key_hash: impl Fn(&K) -> u64,
+) -> Result<CompactReport, Status>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+{
+    op_span!("compact"; records_scanned, records_kept, bytes_before, bytes_after);
+    let lock = DirectoryLock::acquire(storage_dir, false)?;
+    let root = Path::new(storage_dir);
+    let DurableLog { log, frames, .. } = read_durable_log(root)?;
+    let mut report = CompactReport {
+        bytes_before: log.len() as u64,
+        ..Default::default()
+    };
+
+    let (live, tombstones) = {
+        op_span!("compact_scan", frames = frames.len());
+        let mut recent = RecentVersions::new(options.keep_versions);
+        for frame in &frames {
+            for_each_record::<K, V>(&log, frame.begin, frame.end, |_, header, key, value| {
+                report.records_scanned += 1;
+                recent.insert(key_hash(&key), header, key, value)
+            });
+        }
+        recent.into_versions()
+    };
+    drop(log);
+    report.tombstones_dropped = tombstones;
+    report.superseded_dropped = report.records_scanned - live.len() as u64 - tombstones;
+    span_record!(records_scanned = report.records_scanned);
+
+    let staging = format!("{}.compacting", storage_dir);
+    let previous = format!("{}.precompact", storage_dir);
+    for leftover in [&staging, &previous] {
+        if Path::new(leftover).exists() {
+            log::error!("{} is left over from an earlier compaction", leftover);
+            return Err(Status::InvalidConfiguration);
+        }
+    }
+    {
+        op_span!("compact_rewrite", live = live.len());
+        let table_size = options
+            .table_size
+            .unwrap_or_else(|| (live.len() as u64).next_power_of_two().max(1 << 10));
+        let disk = FileSystemDisk::new(&staging)?;
+        let mut kv = RsKv::<K, V, FileSystemDisk>::new(options.log_size, table_size, disk)?;
+        report.records_kept = kv.bulk_load_with_flags(live, &key_hash)?;
+        kv.checkpoint(&options.checkpoint_token)?;
+    }
+    report.bytes_after = fs::metadata(Path::new(&staging).join("hlog.log"))
+        .map_err(|_| Status::IoError)?
+        .len();
+
+    span_record!(records_kept = report.records_kept);
+    span_record!(bytes_before = report.bytes_before);
+    span_record!(bytes_after = report.bytes_after);
+
+    fs::rename(storage_dir, &previous).map_err(|_| Status::IoError)?;
+    fs::rename(&staging, storage_dir).map_err(|_| Status::IoError)?;
+    if let Some(parent) = root.parent().and_then(|parent| parent.to_str()) {
+        sync_directory(if parent.is_empty() { "." } else { parent })?;
+    }
+    drop(lock);
+    fs::remove_dir_all(&previous).map_err(|_| Status::IoError)?;
+
+    log::info!(
+        "compacted {}: kept {} of {} records, {} -> {} log bytes",
+        storage_dir,
+        report.records_kept,
+        report.records_scanned,
+        report.bytes_before,
+        report.bytes_after
+    );
+    Ok(report)
+}
+
+/// Writes the newest value of each live key of the store in `src_dir` into a
+/// new store in `dst_dir` and takes a checkpoint of it, leaving a store of
+/// minimal size. Superseded versions and deleted keys are dropped. Records
+/// cannot expire yet (see [`WriteOptions::ttl`]), so none are dropped for
+/// their age. `src_dir` is only read, under a shared lock, and `dst_dir`
+/// must not already hold a log. `key_hash` must match the hash the store is
+/// written with.
+///
+/// Every flush frame is checked against its checksum as it is read. A frame
+/// that fails stops the run with `Status::Corruption`, or is left out and
+/// counted in [`CompactReport::frames_skipped`] with
+/// [`OfflineCompactOptions::skip_corrupt_frames`]. The byte counts of the
+/// report are the sizes of the two directories.
+///
+/// [`WriteOptions::ttl`]: crate::rskv_core::WriteOptions::ttl
+pub fn compact_offline<K, V>(
+    src_dir: &str,
+    dst_dir: &str,
+    options: &OfflineCompactOptions,
+    key_hash: impl Fn(&K) -> u64,
+) -> Result<CompactReport, Status>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+{
+    let _src_lock = DirectoryLock::acquire(src_dir, true)?;
+    let root = Path::new(src_dir);
+    let (log, frames) = read_log_files(root)?;
+    let mut report = CompactReport {
+        bytes_before: tree_size(root),
+        ..Default::default()
+    };
+
+    let mut newest = NewestRecords::new();
+    for (frame, verifies) in checked_frames(&log, &frames) {
+        if !verifies {
+            if !options.skip_corrupt_frames {
+                log::error!(
+                    "flush frame [{}, {}) of {} fails its checksum",
+                    frame.begin,
+                    frame.end,
+                    src_dir
+                );
+                return Err(Status::Corruption);
+            }
+            log::warn!(
+                "skipping flush frame [{}, {}) of {}: it fails its checksum",
+                frame.begin,
+                frame.end,
+                src_dir
+            );
+            report.frames_skipped += 1;
+            continue;
+        }
+        for_each_record::<K, V>(&log, frame.begin, frame.end, |_, header, key, value| {
+            report.records_scanned += 1;
+            newest.insert(key_hash(&key), header, key, value)
+        });
+    }
+    drop(log);
+    let (live, tombstones) = newest.into_live();
+    report.tombstones_dropped = tombstones;
+    report.superseded_dropped = report.records_scanned - live.len() as u64 - tombstones;
+
+    let disk = FileSystemDisk::new(dst_dir)?;
+    if disk.log_size() > 0 {
+        log::error!("compaction target {} already holds a log", dst_dir);
+        return Err(Status::InvalidConfiguration);
+    }
+    {
+        let table_size = options
+            .table_size
+            .unwrap_or_else(|| (live.len() as u64).next_power_of_two().max(1 << 10));
+        let mut kv = RsKv::<K, V, FileSystemDisk>::new(options.log_size, table_size, disk)?;
+        report.records_kept = kv.bulk_load_with_flags(live, &key_hash)?;
+        kv.checkpoint(&options.checkpoint_token)?;
+    }
+    report.bytes_after = tree_size(Path::new(dst_dir));
+
+    log::info!(
+        "compacted {} into {}: kept {} of {} records, {} -> {} bytes",
+        src_dir,
+        dst_dir,
+        report.records_kept,
+        report.records_scanned,
+        report.bytes_before,
+        report.bytes_after
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rskv_core::{DeleteContext, ReadContext, UpsertContext};
+    use crate::testing::temp_dir::TempDir;
+
+    struct TestUpsertContext {
+        key: u64,
+        value: u64,
+    }
+
+    impl UpsertContext for TestUpsertContext {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn value(&self) -> &Self::Value {
+            &self.value
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+            false
+        }
+    }
+
+    struct TestDeleteContext {
+        key: u64,
+    }
+
+    impl DeleteContext for TestDeleteContext {
+        type Key = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+    }
+
+    struct TestReadContext {
+        key: u64,
+        value: Option<u64>,
+    }
+
+    impl ReadContext for TestReadContext {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn get(&mut self, value: &Self::Value) {
+            self.value = Some(*value);
+        }
+    }
+
+    /// Keys 0..100 written, 0..50 overwritten with `key + 1000`, 90..100
+    /// deleted, then checkpointed as "first".
+    fn populated_store(dir: &str) -> RsKv<'static, u64, u64, FileSystemDisk> {
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 12, disk).unwrap();
+        for key in 0..100 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        for key in 0..50 {
+            let context = TestUpsertContext {
+                key,
+                value: key + 1000,
+            };
+            assert_eq!(kv.upsert(&context), Status::Ok);
+        }
+        for key in 90..100 {
+            assert_eq!(kv.delete(&TestDeleteContext { key }), Status::Ok);
+        }
+        kv.checkpoint("first").unwrap();
+        kv
+    }
+
+    #[test]
+    fn test_admin_refuses_a_live_store() {
+        let temp = TempDir::new("admin_live");
+        let dir = temp.path();
+        let kv = populated_store(dir);
+
+        let mut out = Vec::new();
+        let dumped = dump::<u64, u64>(dir, &mut out, &DumpOptions::default());
+        assert_eq!(dumped, Err(Status::StoreLocked));
+        assert_eq!(stats(dir), Err(Status::StoreLocked));
+        let compacted = compact::<u64, u64>(dir, &CompactOptions::default(), |key| *key);
+        assert_eq!(compacted, Err(Status::StoreLocked));
+        assert!(out.is_empty());
+
+        drop(kv);
+        assert!(stats(dir).is_ok());
+    }
+
+    #[test]
+    fn test_dump_newest_values_and_history() {
+        let temp = TempDir::new("admin_dump");
+        let dir = temp.path();
+        drop(populated_store(dir));
+
+        let mut out = Vec::new();
+        let report = dump::<u64, u64>(dir, &mut out, &DumpOptions::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(report.records_written, 90);
+        assert_eq!(text.lines().count(), 90);
+        assert!(text.lines().any(|line| line == "7\t1007"));
+        assert!(text.lines().any(|line| line == "70\t70"));
+        assert!(!text.lines().any(|line| line.starts_with("95\t")));
+
+        let mut out = Vec::new();
+        let options = DumpOptions { history: true };
+        let report = dump::<u64, u64>(dir, &mut out, &options).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(report.records_scanned, report.records_written);
+        assert_eq!(text.lines().count() as u64, report.records_written);
+        let deletes: Vec<_> = text
+            .lines()
+            .filter(|line| line.contains("\tdelete\t"))
+            .collect();
+        assert_eq!(deletes.len(), 10);
+        assert!(deletes.iter().all(|line| line.ends_with("\t-")));
+        let addresses: Vec<u64> = text
+            .lines()
+            .map(|line| line.split('\t').next().unwrap().parse().unwrap())
+            .collect();
+        assert!(addresses.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_stats_reads_superblock_and_checkpoints() {
+        let temp = TempDir::new("admin_stats");
+        let dir = temp.path();
+        drop(populated_store(dir));
+
+        let report = stats(dir).unwrap();
+        let superblock = report.superblock.unwrap();
+        assert_eq!(
+            superblock.page_size,
+            PersistentMemoryMalloc::<FileSystemDisk>::K_PAGE_SIZE
+        );
+        assert_eq!(report.torn_bytes, 0);
+        assert!(report.durable_until > 0);
+        assert_eq!(report.log_bytes, report.durable_until);
+        assert_eq!(report.checkpoints.len(), 1);
+        let checkpoint = &report.checkpoints[0];
+        assert_eq!(checkpoint.token, "first");
+        assert!(checkpoint.valid);
+        assert_eq!(checkpoint.table_size, 1 << 12);
+        assert!(checkpoint.final_address <= report.durable_until);
+        assert!(checkpoint.bytes > 0);
+    }
+
+    #[test]
+    fn test_compact_keeps_only_live_records() {
+        let temp = TempDir::new("admin_compact");
+        let dir = temp.path();
+        drop(populated_store(dir));
+
+        let report = compact::<u64, u64>(dir, &CompactOptions::default(), |key| *key).unwrap();
+        assert_eq!(report.records_kept, 90);
+        assert_eq!(report.tombstones_dropped, 10);
+        assert!(report.records_scanned >= 150);
+        assert_eq!(report.superseded_dropped, report.records_scanned - 100);
+        assert!(report.bytes_after < report.bytes_before);
+        assert!(!Path::new(&format!("{}.compacting", dir)).exists());
+        assert!(!Path::new(&format!("{}.precompact", dir)).exists());
+
+        let tokens: Vec<_> = stats(dir)
+            .unwrap()
+            .checkpoints
+            .into_iter()
+            .map(|checkpoint| checkpoint.token)
+            .collect();
+        assert_eq!(tokens, vec!["compacted".to_string()]);
+
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "compacted").unwrap();
+        for key in 0..100 {
+            let mut context = TestReadContext { key, value: None };
+            kv.read(&mut context);
+            let expected = match key {
+                0..50 => Some(key + 1000),
+                50..90 => Some(key),
+                _ => None,
+            };
+            assert_eq!(context.value, expected, "key {}", key);
+        }
+    }
+
+    #[test]
+    fn test_compact_keeps_recent_versions() {
+        let temp = TempDir::new("admin_compact_versions");
+        let dir = temp.path();
+        drop(populated_store(dir));
+
+        let options = CompactOptions {
+            keep_versions: 3,
+            ..Default::default()
+        };
+        let report = compact::<u64, u64>(dir, &options, |key| *key).unwrap();
+        assert_eq!(report.records_kept, 140);
+        assert_eq!(report.tombstones_dropped, 10);
+
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "compacted").unwrap();
+        let values = |key: u64| -> Vec<u64> {
+            kv.read_versions(&key, key, 10)
+                .unwrap()
+                .into_iter()
+                .map(|version| version.value)
+                .collect()
+        };
+        assert_eq!(values(7), vec![1007, 7]);
+        assert_eq!(values(70), vec![70]);
+        assert!(values(95).is_empty());
+    }
+
+    #[test]
+    fn test_compact_offline_fails_or_skips_on_corrupt_frames() {
+        let temp = TempDir::new("admin_offline_corrupt");
+        let dir = temp.path();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            for keys in [0..50, 50..100] {
+                for key in keys {
+                    assert_eq!(
+                        kv.upsert(&TestUpsertContext { key, value: key }),
+                        Status::Ok
+                    );
+                }
+                kv.flush().unwrap();
+            }
+        }
+
+        // Flip the last byte covered by the first flush frame.
+        let frames = fs::read(format!("{}/hlog.frames", dir)).unwrap();
+        let first = FlushFrame::decode(&frames[..FLUSH_FRAME_SIZE]).unwrap();
+        let log_path = format!("{}/hlog.log", dir);
+        let mut log = fs::read(&log_path).unwrap();
+        log[first.end as usize - 1] ^= 0xff;
+        fs::write(&log_path, &log).unwrap();
+
+        let failed_dst_temp = TempDir::new("admin_offline_failed");
+        let failed_dst = failed_dst_temp.path();
+        let options = OfflineCompactOptions::default();
+        assert_eq!(
+            compact_offline::<u64, u64>(dir, failed_dst, &options, |key| *key),
+            Err(Status::Corruption)
+        );
+
+        let dst_temp = TempDir::new("admin_offline_skipped");
+        let dst = dst_temp.path();
+        let options = OfflineCompactOptions {
+            skip_corrupt_frames: true,
+            ..Default::default()
+        };
+        let report = compact_offline::<u64, u64>(dir, dst, &options, |key| *key).unwrap();
+        assert_eq!(report.frames_skipped, 1);
+        assert_eq!(report.records_kept, 50);
+
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dst, "compacted").unwrap();
+        for key in [0, 49, 50, 99] {
+            let mut context = TestReadContext { key, value: None };
+            kv.read(&mut context);
+            assert_eq!(context.value, (key >= 50).then_some(key));
+        }
+    }
+}
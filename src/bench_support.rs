@@ -0,0 +1,748 @@
+//! YCSB-style workloads for benchmarking a key-value store.
+//!
+//! [`WorkloadSpec`] describes one of the standard workloads A-F (operation
+//! mix and key distribution). [`run_workload`] loads `record_count` records,
+//! runs `operation_count` operations against a [`YcsbTarget`], and times
+//! each one into a [`LatencyHistogram`] per operation kind. The resulting
+//! [`WorkloadSummary`] renders as JSON so CI can compare runs.
+//!
+//! Keys are `u64` record numbers and values are `u64`. Implement
+//! [`YcsbTarget`] for the store to benchmark; `benches/ycsb.rs` does so for
+//! [`RsKv`](crate::rskv_core::RsKv).
+
+use crate::core::status::Status;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Skew used by YCSB's Zipfian generators
+pub const ZIPFIAN_CONSTANT: f64 = 0.99;
+
+/// How keys are picked for reads, updates, scans and RMWs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyDistribution {
+    Uniform,
+    /// Zipfian over record numbers, scattered so the hot keys are not
+    /// adjacent
+    Zipfian,
+    /// Zipfian over recency: the most recently inserted keys are hottest
+    Latest,
+}
+
+/// Kind of operation issued by a workload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OperationKind {
+    Read,
+    Update,
+    Insert,
+    Scan,
+    ReadModifyWrite,
+}
+
+impl OperationKind {
+    pub const ALL: [OperationKind; 5] = [
+        OperationKind::Read,
+        OperationKind::Update,
+        OperationKind::Insert,
+        OperationKind::Scan,
+        OperationKind::ReadModifyWrite,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OperationKind::Read => "read",
+            OperationKind::Update => "update",
+            OperationKind::Insert => "insert",
+            OperationKind::Scan => "scan",
+            OperationKind::ReadModifyWrite => "rmw",
+        }
+    }
+}
+
+/// One operation of the run phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Read { key: u64 },
+    Update { key: u64, value: u64 },
+    Insert { key: u64, value: u64 },
+    Scan { start: u64, len: usize },
+    ReadModifyWrite { key: u64, value: u64 },
+}
+
+impl Operation {
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            Operation::Read { .. } => OperationKind::Read,
+            Operation::Update { .. } => OperationKind::Update,
+            Operation::Insert { .. } => OperationKind::Insert,
+            Operation::Scan { .. } => OperationKind::Scan,
+            Operation::ReadModifyWrite { .. } => OperationKind::ReadModifyWrite,
+        }
+    }
+}
+
+/// Operation mix and key distribution of a workload. Weights are relative;
+/// the standard workloads use percentages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkloadSpec {
+    pub name: &'static str,
+    pub read: u32,
+    pub update: u32,
+    pub insert: u32,
+    pub scan: u32,
+    pub read_modify_write: u32,
+    pub distribution: KeyDistribution,
+    /// Scans cover between 1 and this many consecutive records
+    pub max_scan_length: usize,
+}
+
+impl WorkloadSpec {
+    const fn new(name: &'static str, distribution: KeyDistribution) -> Self {
+        Self {
+            name,
+            read: 0,
+            update: 0,
+            insert: 0,
+            scan: 0,
+            read_modify_write: 0,
+            distribution,
+            max_scan_length: 100,
+        }
+    }
+
+    /// Update heavy: 50% reads, 50% updates
+    pub const fn a() -> Self {
+        Self {
+            read: 50,
+            update: 50,
+            ..Self::new("a", KeyDistribution::Zipfian)
+        }
+    }
+
+    /// Read mostly: 95% reads, 5% updates
+    pub const fn b() -> Self {
+        Self {
+            read: 95,
+            update: 5,
+            ..Self::new("b", KeyDistribution::Zipfian)
+        }
+    }
+
+    /// Read only
+    pub const fn c() -> Self {
+        Self {
+            read: 100,
+            ..Self::new("c", KeyDistribution::Zipfian)
+        }
+    }
+
+    /// Read latest: 95% reads of recently inserted keys, 5% inserts
+    pub const fn d() -> Self {
+        Self {
+            read: 95,
+            insert: 5,
+            ..Self::new("d", KeyDistribution::Latest)
+        }
+    }
+
+    /// Short ranges: 95% scans, 5% inserts
+    pub const fn e() -> Self {
+        Self {
+            scan: 95,
+            insert: 5,
+            ..Self::new("e", KeyDistribution::Zipfian)
+        }
+    }
+
+    /// Read-modify-write: 50% reads, 50% RMWs
+    pub const fn f() -> Self {
+        Self {
+            read: 50,
+            read_modify_write: 50,
+            ..Self::new("f", KeyDistribution::Zipfian)
+        }
+    }
+
+    /// Workloads A-F in order
+    pub fn all() -> [WorkloadSpec; 6] {
+        [
+            Self::a(),
+            Self::b(),
+            Self::c(),
+            Self::d(),
+            Self::e(),
+            Self::f(),
+        ]
+    }
+
+    /// Looks up a standard workload by its letter, in either case
+    pub fn by_name(name: &str) -> Option<WorkloadSpec> {
+        Self::all()
+            .into_iter()
+            .find(|spec| spec.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Zipfian ranks over `0..items` with rank 0 the most popular, after Gray et
+/// al., "Quickly Generating Billion-Record Synthetic Databases". The item
+/// count may grow between samples; the normalization constant is extended
+/// incrementally.
+#[derive(Debug, Clone)]
+pub struct Zipfian {
+    theta: f64,
+    alpha: f64,
+    zeta2: f64,
+    items: u64,
+    zeta_n: f64,
+    eta: f64,
+}
+
+impl Zipfian {
+    pub fn new(items: u64, theta: f64) -> Self {
+        let mut zipfian = Self {
+            theta,
+            alpha: 1.0 / (1.0 - theta),
+            zeta2: 1.0 + 0.5f64.powf(theta),
+            items: 0,
+            zeta_n: 0.0,
+            eta: 0.0,
+        };
+        zipfian.grow(items.max(1));
+        zipfian
+    }
+
+    fn grow(&mut self, items: u64) {
+        for i in self.items..items {
+            self.zeta_n += 1.0 / ((i + 1) as f64).powf(self.theta);
+        }
+        self.items = items;
+        self.eta =
+            (1.0 - (2.0 / items as f64).powf(1.0 - self.theta)) / (1.0 - self.zeta2 / self.zeta_n);
+    }
+
+    /// Samples a rank in `0..items`
+    pub fn sample(&mut self, rng: &mut impl Rng, items: u64) -> u64 {
+        if items > self.items {
+            self.grow(items);
+        }
+        let u: f64 = rng.random();
+        let uz = u * self.zeta_n;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < self.zeta2 {
+            return 1.min(items - 1);
+        }
+        let rank = (items as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as u64;
+        rank.min(items - 1)
+    }
+}
+
+/// FNV-1a over the bytes of `value`, used to scatter Zipfian ranks
+fn fnv_hash(value: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in value.to_le_bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Generates the run phase of a workload. Inserts take record numbers from
+/// `record_count` upwards, so reads can reach keys inserted earlier in the
+/// run.
+pub struct OperationGenerator {
+    spec: WorkloadSpec,
+    rng: StdRng,
+    zipfian: Zipfian,
+    /// Number of records present: loaded plus inserted so far
+    records: u64,
+}
+
+impl OperationGenerator {
+    pub fn new(spec: WorkloadSpec, record_count: u64, seed: u64) -> Self {
+        Self {
+            spec,
+            rng: StdRng::seed_from_u64(seed),
+            zipfian: Zipfian::new(record_count, ZIPFIAN_CONSTANT),
+            records: record_count.max(1),
+        }
+    }
+
+    /// Picks an existing record according to the workload's distribution
+    pub fn next_key(&mut self) -> u64 {
+        match self.spec.distribution {
+            KeyDistribution::Uniform => self.rng.random_range(0..self.records),
+            KeyDistribution::Zipfian => {
+                let rank = self.zipfian.sample(&mut self.rng, self.records);
+                fnv_hash(rank) % self.records
+            }
+            KeyDistribution::Latest => {
+                let rank = self.zipfian.sample(&mut self.rng, self.records);
+                self.records - 1 - rank
+            }
+        }
+    }
+
+    pub fn next_operation(&mut self) -> Operation {
+        let spec = self.spec;
+        let total = spec.read + spec.update + spec.insert + spec.scan + spec.read_modify_write;
+        let mut pick = self.rng.random_range(0..total.max(1));
+        for (weight, kind) in [
+            (spec.read, OperationKind::Read),
+            (spec.update, OperationKind::Update),
+            (spec.insert, OperationKind::Insert),
+            (spec.scan, OperationKind::Scan),
+            (spec.read_modify_write, OperationKind::ReadModifyWrite),
+        ] {
+            if pick < weight {
+                return self.operation(kind);
+            }
+            pick -= weight;
+        }
+        self.operation(OperationKind::Read)
+    }
+
+    fn operation(&mut self, kind: OperationKind) -> Operation {
+        match kind {
+            OperationKind::Read => Operation::Read {
+                key: self.next_key(),
+            },
+            OperationKind::Update => Operation::Update {
+                key: self.next_key(),
+                value: self.rng.random(),
+            },
+            OperationKind::Insert => {
+                let key = self.records;
+                self.records += 1;
+                Operation::Insert {
+                    key,
+                    value: self.rng.random(),
+                }
+            }
+            OperationKind::Scan => Operation::Scan {
+                start: self.next_key(),
+                len: self.rng.random_range(1..=self.spec.max_scan_length.max(1)),
+            },
+            OperationKind::ReadModifyWrite => Operation::ReadModifyWrite {
+                key: self.next_key(),
+                value: self.rng.random(),
+            },
+        }
+    }
+}
+
+/// Latency histogram with log-linear buckets: 16 buckets per power of two,
+/// so recorded values are kept to within about 6%.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum_nanos: u128,
+    min_nanos: u64,
+    max_nanos: u64,
+}
+
+impl LatencyHistogram {
+    const SUB_BUCKET_BITS: u32 = 4;
+    const SUB_BUCKETS: u64 = 1 << Self::SUB_BUCKET_BITS;
+    const BUCKETS: usize = ((64 - Self::SUB_BUCKET_BITS + 1) as usize) * Self::SUB_BUCKETS as usize;
+
+    pub fn new() -> Self {
+        Self {
+            buckets: vec![0; Self::BUCKETS],
+            count: 0,
+            sum_nanos: 0,
+            min_nanos: u64::MAX,
+            max_nanos: 0,
+        }
+    }
+
+    fn bucket_index(nanos: u64) -> usize {
+        if nanos < 2 * Self::SUB_BUCKETS {
+            return nanos as usize;
+        }
+        let shift = 63 - nanos.leading_zeros() - Self::SUB_BUCKET_BITS;
+        let mantissa = nanos >> shift;
+        ((shift as u64 + 1) * Self::SUB_BUCKETS + mantissa - Self::SUB_BUCKETS) as usize
+    }
+
+    /// Smallest value that falls into `index`
+    fn bucket_floor(index: usize) -> u64 {
+        let index = index as u64;
+        if index < 2 * Self::SUB_BUCKETS {
+            return index;
+        }
+        let shift = index / Self::SUB_BUCKETS - 1;
+        let mantissa = Self::SUB_BUCKETS + index % Self::SUB_BUCKETS;
+        mantissa << shift
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = latency.as_nanos().min(u64::MAX as u128) as u64;
+        self.buckets[Self::bucket_index(nanos)] += 1;
+        self.count += 1;
+        self.sum_nanos += nanos as u128;
+        self.min_nanos = self.min_nanos.min(nanos);
+        self.max_nanos = self.max_nanos.max(nanos);
+    }
+
+    /// Adds every sample of `other`
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(&other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.sum_nanos += other.sum_nanos;
+        self.min_nanos = self.min_nanos.min(other.min_nanos);
+        self.max_nanos = self.max_nanos.max(other.max_nanos);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn min(&self) -> Duration {
+        Duration::from_nanos(if self.count == 0 { 0 } else { self.min_nanos })
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_nanos)
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.sum_nanos / self.count as u128) as u64)
+    }
+
+    /// Latency at or below which a fraction `p` (0.0 to 1.0) of the samples
+    /// fall, rounded down to its bucket
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((self.count as f64 * p.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        if rank >= self.count {
+            return self.max();
+        }
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let floor = Self::bucket_floor(index).max(self.min_nanos);
+                return Duration::from_nanos(floor.min(self.max_nanos));
+            }
+        }
+        self.max()
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Store operations a workload needs. Keys and values are `u64`.
+pub trait YcsbTarget {
+    /// Writes records `0..record_count`, each with its key as value
+    fn load(&mut self, record_count: u64) -> Result<(), Status>;
+    /// Returns whether the key was found
+    fn read(&mut self, key: u64) -> Result<bool, Status>;
+    fn update(&mut self, key: u64, value: u64) -> Result<(), Status>;
+    fn insert(&mut self, key: u64, value: u64) -> Result<(), Status>;
+    /// Reads `len` records starting at `start`, returning how many were
+    /// found. Stores without range scans can issue point reads of
+    /// consecutive record numbers.
+    fn scan(&mut self, start: u64, len: usize) -> Result<usize, Status>;
+    fn read_modify_write(&mut self, key: u64, value: u64) -> Result<(), Status>;
+}
+
+/// Sizes of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YcsbConfig {
+    /// Records written by the load phase
+    pub record_count: u64,
+    /// Operations issued by the run phase
+    pub operation_count: u64,
+    pub seed: u64,
+}
+
+impl Default for YcsbConfig {
+    fn default() -> Self {
+        Self {
+            record_count: 100_000,
+            operation_count: 100_000,
+            seed: 0,
+        }
+    }
+}
+
+/// Result of [`run_workload`]
+#[derive(Debug, Clone)]
+pub struct WorkloadSummary {
+    pub workload: &'static str,
+    pub record_count: u64,
+    pub operation_count: u64,
+    pub load_elapsed: Duration,
+    pub run_elapsed: Duration,
+    /// Reads and scanned records that were not found
+    pub not_found: u64,
+    /// Latencies of each operation kind the workload issued
+    pub latencies: Vec<(OperationKind, LatencyHistogram)>,
+}
+
+impl WorkloadSummary {
+    /// Run-phase operations per second
+    pub fn throughput(&self) -> f64 {
+        let secs = self.run_elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.operation_count as f64 / secs
+    }
+
+    /// Renders the summary as a single-line JSON object. Durations are in
+    /// nanoseconds.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(
+            json,
+            "{{\"workload\":\"{}\",\"record_count\":{},\"operation_count\":{},\
+             \"load_ns\":{},\"run_ns\":{},\"throughput\":{:.1},\"not_found\":{},\
+             \"operations\":{{",
+            self.workload,
+            self.record_count,
+            self.operation_count,
+            self.load_elapsed.as_nanos(),
+            self.run_elapsed.as_nanos(),
+            self.throughput(),
+            self.not_found
+        );
+        for (i, (kind, histogram)) in self.latencies.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "\"{}\":{{\"count\":{},\"mean_ns\":{},\"p50_ns\":{},\"p99_ns\":{},\
+                 \"p999_ns\":{},\"max_ns\":{}}}",
+                kind.name(),
+                histogram.count(),
+                histogram.mean().as_nanos(),
+                histogram.percentile(0.50).as_nanos(),
+                histogram.percentile(0.99).as_nanos(),
+                histogram.percentile(0.999).as_nanos(),
+                histogram.max().as_nanos()
+            );
+        }
+        json.push_str("}}");
+        json
+    }
+}
+
+/// Loads `config.record_count` records into `target`, then runs
+/// `config.operation_count` operations of `spec`, timing each one.
+pub fn run_workload(
+    target: &mut impl YcsbTarget,
+    spec: &WorkloadSpec,
+    config: &YcsbConfig,
+) -> Result<WorkloadSummary, Status> {
+    let start = Instant::now();
+    target.load(config.record_count)?;
+    let load_elapsed = start.elapsed();
+
+    let mut generator = OperationGenerator::new(*spec, config.record_count, config.seed);
+    let mut histograms: Vec<LatencyHistogram> = OperationKind::ALL
+        .iter()
+        .map(|_| LatencyHistogram::new())
+        .collect();
+    let mut not_found = 0;
+    let run_start = Instant::now();
+    for _ in 0..config.operation_count {
+        let operation = generator.next_operation();
+        let start = Instant::now();
+        match operation {
+            Operation::Read { key } => {
+                if !target.read(key)? {
+                    not_found += 1;
+                }
+            }
+            Operation::Update { key, value } => target.update(key, value)?,
+            Operation::Insert { key, value } => target.insert(key, value)?,
+            Operation::Scan { start, len } => {
+                let found = target.scan(start, len)?;
+                not_found += (len - found) as u64;
+            }
+            Operation::ReadModifyWrite { key, value } => target.read_modify_write(key, value)?,
+        }
+        histograms[operation.kind() as usize].record(start.elapsed());
+    }
+    let run_elapsed = run_start.elapsed();
+
+    Ok(WorkloadSummary {
+        workload: spec.name,
+        record_count: config.record_count,
+        operation_count: config.operation_count,
+        load_elapsed,
+        run_elapsed,
+        not_found,
+        latencies: OperationKind::ALL
+            .into_iter()
+            .zip(histograms)
+            .filter(|(_, histogram)| !histogram.is_empty())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_zipfian_prefers_low_ranks() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut zipfian = Zipfian::new(1000, ZIPFIAN_CONSTANT);
+        let mut counts = vec![0u64; 1000];
+        for _ in 0..100_000 {
+            counts[zipfian.sample(&mut rng, 1000) as usize] += 1;
+        }
+        assert!(counts[0] > counts[1]);
+        assert!(counts[1] > counts[10]);
+        // The top 10% of ranks take well over half of the samples
+        let head: u64 = counts[..100].iter().sum();
+        assert!(head > 60_000, "head {}", head);
+
+        // Growing the item count keeps samples in range
+        for items in 1000..1100 {
+            assert!(zipfian.sample(&mut rng, items) < items);
+        }
+    }
+
+    #[test]
+    fn test_latest_distribution_favors_recent_inserts() {
+        let mut generator = OperationGenerator::new(WorkloadSpec::d(), 1000, 3);
+        for _ in 0..200 {
+            generator.operation(OperationKind::Insert);
+        }
+        let recent = (0..10_000).filter(|_| generator.next_key() >= 1100).count();
+        assert!(recent > 5_000, "recent {}", recent);
+    }
+
+    #[test]
+    fn test_operation_mix_follows_weights() {
+        let mut generator = OperationGenerator::new(WorkloadSpec::b(), 1000, 11);
+        let mut counts = HashMap::new();
+        for _ in 0..20_000 {
+            *counts.entry(generator.next_operation().kind()).or_insert(0) += 1;
+        }
+        let reads = counts[&OperationKind::Read];
+        assert!((18_600..19_400).contains(&reads), "reads {}", reads);
+        assert_eq!(reads + counts[&OperationKind::Update], 20_000);
+    }
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.percentile(0.5), Duration::ZERO);
+        for micros in 1..=1000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        assert_eq!(histogram.count(), 1000);
+        assert_eq!(histogram.min(), Duration::from_micros(1));
+        assert_eq!(histogram.max(), Duration::from_micros(1000));
+        for (p, expected) in [(0.5, 500.0), (0.99, 990.0), (0.999, 999.0)] {
+            let actual = histogram.percentile(p).as_nanos() as f64 / 1000.0;
+            assert!(
+                actual <= expected && actual > expected * 0.93,
+                "p{} = {}us",
+                p,
+                actual
+            );
+        }
+        assert_eq!(histogram.percentile(1.0), Duration::from_micros(1000));
+
+        let mut merged = LatencyHistogram::new();
+        merged.record(Duration::from_nanos(5));
+        merged.merge(&histogram);
+        assert_eq!(merged.count(), 1001);
+        assert_eq!(merged.min(), Duration::from_nanos(5));
+    }
+
+    /// In-memory target for checking the runner itself
+    #[derive(Default)]
+    struct MapTarget(HashMap<u64, u64>);
+
+    impl YcsbTarget for MapTarget {
+        fn load(&mut self, record_count: u64) -> Result<(), Status> {
+            self.0.extend((0..record_count).map(|key| (key, key)));
+            Ok(())
+        }
+
+        fn read(&mut self, key: u64) -> Result<bool, Status> {
+            Ok(self.0.contains_key(&key))
+        }
+
+        fn update(&mut self, key: u64, value: u64) -> Result<(), Status> {
+            self.0.insert(key, value);
+            Ok(())
+        }
+
+        fn insert(&mut self, key: u64, value: u64) -> Result<(), Status> {
+            self.0.insert(key, value);
+            Ok(())
+        }
+
+        fn scan(&mut self, start: u64, len: usize) -> Result<usize, Status> {
+            Ok((start..start + len as u64)
+                .filter(|key| self.0.contains_key(key))
+                .count())
+        }
+
+        fn read_modify_write(&mut self, key: u64, value: u64) -> Result<(), Status> {
+            let old = self.0.get(&key).copied().unwrap_or(0);
+            self.0.insert(key, old ^ value);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_workload_summary() {
+        let config = YcsbConfig {
+            record_count: 500,
+            operation_count: 2_000,
+            seed: 1,
+        };
+        for spec in WorkloadSpec::all() {
+            let mut target = MapTarget::default();
+            let summary = run_workload(&mut target, &spec, &config).unwrap();
+            let issued: u64 = summary
+                .latencies
+                .iter()
+                .map(|(_, histogram)| histogram.count())
+                .sum();
+            assert_eq!(issued, 2_000);
+            if spec.scan == 0 {
+                // Every read targets a loaded or inserted record
+                assert_eq!(summary.not_found, 0, "workload {}", spec.name);
+            }
+            let json = summary.to_json();
+            assert!(json.starts_with(&format!("{{\"workload\":\"{}\"", spec.name)));
+            assert!(json.ends_with("}}"));
+        }
+
+        let summary = run_workload(&mut MapTarget::default(), &WorkloadSpec::f(), &config).unwrap();
+        let kinds: Vec<_> = summary.latencies.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [OperationKind::Read, OperationKind::ReadModifyWrite]);
+        assert!(summary.to_json().contains("\"rmw\":{\"count\":"));
+        assert_eq!(WorkloadSpec::by_name("E"), Some(WorkloadSpec::e()));
+    }
+}
@@ -1,5 +1,6 @@
+use crate::core::sync::AtomicU64;
 use std::ops::{Add, AddAssign, Sub};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
 
 /// Represents a logical address into persistent memory. Identifies a page and an offset within that page.
 /// Uses 48 bits: 25 bits for the offset and 23 bits for the page. (The remaining 16 bits are
@@ -1,12 +1,13 @@
 use crate::core::status::{Status, Result};
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::thread::{self, ThreadId};
 use std::time::{Duration, Instant};
 
-/// Lock granularity levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Lock granularity levels, ordered from finest to coarsest. A thread must
+/// take coarser locks before finer ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum LockGranularity {
     /// Record-level locking (finest granularity)
     Record,
@@ -24,6 +25,9 @@ pub enum LockIntent {
     Read,
     Write,
     ReadWrite,
+    /// Shared with readers, but held by at most one thread at a time, which
+    /// may later upgrade it to `Write` without releasing it
+    Upgradeable,
 }
 
 /// Lock identifier for tracking locks across different granularities
@@ -56,11 +60,14 @@ impl LockId {
 
 /// Lock holder information
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 struct LockHolder {
     thread_id: ThreadId,
     intent: LockIntent,
     acquired_at: Instant,
     lock_id: LockId,
+    /// When the holding thread took the first of the locks it still holds
+    transaction_start: Instant,
 }
 
 /// Deadlock detector using wait-for graph algorithm
@@ -75,6 +82,12 @@ pub struct DeadlockDetector {
     detection_interval: Duration,
     /// Last detection time
     last_detection: Mutex<Instant>,
+    /// Threads chosen as deadlock victims that have not noticed yet
+    victims: Mutex<HashSet<ThreadId>>,
+    /// Number of wait-for cycles found
+    cycles_detected: AtomicU64,
+    /// Number of victims chosen to break cycles
+    victims_selected: AtomicU64,
 }
 
 impl DeadlockDetector {
@@ -85,16 +98,31 @@ impl DeadlockDetector {
             lock_holders: RwLock::new(HashMap::new()),
             detection_interval: Duration::from_millis(100),
             last_detection: Mutex::new(Instant::now()),
+            victims: Mutex::new(HashSet::new()),
+            cycles_detected: AtomicU64::new(0),
+            victims_selected: AtomicU64::new(0),
         }
     }
 
     /// Record that a thread has acquired a lock
     pub fn record_lock_acquired(&self, thread_id: ThreadId, lock_id: LockId, intent: LockIntent) {
+        let now = Instant::now();
+        let transaction_start = self.lock_holders.read()
+            .ok()
+            .and_then(|lock_holders| {
+                lock_holders.values()
+                    .flatten()
+                    .filter(|h| h.thread_id == thread_id)
+                    .map(|h| h.transaction_start)
+                    .min()
+            })
+            .unwrap_or(now);
         let holder = LockHolder {
             thread_id,
             intent,
-            acquired_at: Instant::now(),
+            acquired_at: now,
             lock_id,
+            transaction_start,
         };
 
         // Update holdings
@@ -111,9 +139,18 @@ impl DeadlockDetector {
         if let Ok(mut wait_for) = self.wait_for.write() {
             wait_for.remove(&thread_id);
         }
+
+        // A victim that got its lock anyway no longer needs to abort
+        if let Ok(mut victims) = self.victims.lock() {
+            victims.remove(&thread_id);
+        }
     }
 
-    /// Record that a thread is waiting for a lock
+    /// Record that a thread is waiting for a lock.
+    ///
+    /// If waiting closes a cycle, the cheapest thread in it is chosen as the
+    /// victim. This returns `Status::DeadlockDetected` when the victim is the
+    /// caller; any other victim is flagged and aborts from its own wait.
     pub fn record_lock_wait(&self, thread_id: ThreadId, lock_id: LockId) -> Result<()> {
         // Find who currently holds this lock
         let holder_threads = if let Ok(lock_holders) = self.lock_holders.read() {
@@ -134,35 +171,137 @@ impl DeadlockDetector {
             }
         }
 
+        // A cycle through this thread has to be caught now, before it parks
+        let cycle = if let Ok(wait_for) = self.wait_for.read() {
+            Self::find_cycle_from(thread_id, &wait_for)
+        } else {
+            return Err(Status::InternalError);
+        };
+        if let Some(cycle) = cycle {
+            log::warn!("Deadlock detected waiting for {:?} on thread: {:?}", lock_id, thread_id);
+            return self.break_cycle(&cycle, thread_id);
+        }
+
         // Check for deadlock
-        self.detect_deadlock()
+        self.detect_deadlock(thread_id)
+    }
+
+    /// Whether `thread_id` currently holds `lock_id`
+    pub fn holds(&self, thread_id: ThreadId, lock_id: LockId) -> bool {
+        self.holdings.read()
+            .map(|h| h.get(&thread_id).is_some_and(|locks| locks.contains(&lock_id)))
+            .unwrap_or(false)
+    }
+
+    /// Finest granularity among the locks `thread_id` holds
+    pub fn finest_held(&self, thread_id: ThreadId) -> Option<LockGranularity> {
+        self.holdings.read()
+            .ok()?
+            .get(&thread_id)?
+            .iter()
+            .map(|lock_id| lock_id.granularity)
+            .min()
+    }
+
+    /// Whether `thread_id` was chosen as a deadlock victim. Consumes the flag.
+    pub fn take_abort(&self, thread_id: ThreadId) -> bool {
+        self.victims.lock()
+            .map(|mut victims| victims.remove(&thread_id))
+            .unwrap_or(false)
+    }
+
+    /// Pick the cheapest thread of `cycle` as the victim: the one holding the
+    /// fewest locks, then the one whose transaction started last
+    fn break_cycle(&self, cycle: &[ThreadId], caller: ThreadId) -> Result<()> {
+        self.cycles_detected.fetch_add(1, Ordering::Relaxed);
+
+        let victim = if let Ok(lock_holders) = self.lock_holders.read() {
+            let cost = |thread_id: ThreadId| {
+                let held = lock_holders.values()
+                    .flatten()
+                    .filter(|h| h.thread_id == thread_id);
+                let locks_held = held.clone().count();
+                let in_transaction = held
+                    .map(|h| h.transaction_start.elapsed())
+                    .max()
+                    .unwrap_or_default();
+                (locks_held, in_transaction)
+            };
+            cycle.iter().copied().min_by_key(|&t| cost(t))
+        } else {
+            return Err(Status::InternalError);
+        };
+        let Some(victim) = victim else {
+            return Ok(());
+        };
+        self.victims_selected.fetch_add(1, Ordering::Relaxed);
+        log::warn!("Aborting thread {:?} to break a deadlock", victim);
+
+        if victim == caller {
+            self.record_wait_abandoned(caller);
+            return Err(Status::DeadlockDetected);
+        }
+
+        // Drop the victim's edge now, so the cycle is not broken twice
+        if let Ok(mut wait_for) = self.wait_for.write() {
+            wait_for.remove(&victim);
+        }
+        if let Ok(mut victims) = self.victims.lock() {
+            victims.insert(victim);
+        }
+        Ok(())
+    }
+
+    /// Record that a thread upgraded its hold on a lock to `intent`
+    pub fn record_lock_upgraded(&self, thread_id: ThreadId, lock_id: LockId, intent: LockIntent) {
+        if let Ok(mut lock_holders) = self.lock_holders.write()
+            && let Some(holders) = lock_holders.get_mut(&lock_id)
+        {
+            for holder in holders.iter_mut().filter(|h| h.thread_id == thread_id) {
+                holder.intent = intent;
+            }
+        }
+
+        if let Ok(mut wait_for) = self.wait_for.write() {
+            wait_for.remove(&thread_id);
+        }
+        if let Ok(mut victims) = self.victims.lock() {
+            victims.remove(&thread_id);
+        }
+    }
+
+    /// Record that a thread gave up waiting for a lock
+    pub fn record_wait_abandoned(&self, thread_id: ThreadId) {
+        if let Ok(mut wait_for) = self.wait_for.write() {
+            wait_for.remove(&thread_id);
+        }
     }
 
     /// Record that a thread has released a lock
     pub fn record_lock_released(&self, thread_id: ThreadId, lock_id: LockId) {
         // Remove from holdings
-        if let Ok(mut holdings) = self.holdings.write() {
-            if let Some(thread_locks) = holdings.get_mut(&thread_id) {
-                thread_locks.remove(&lock_id);
-                if thread_locks.is_empty() {
-                    holdings.remove(&thread_id);
-                }
+        if let Ok(mut holdings) = self.holdings.write()
+            && let Some(thread_locks) = holdings.get_mut(&thread_id)
+        {
+            thread_locks.remove(&lock_id);
+            if thread_locks.is_empty() {
+                holdings.remove(&thread_id);
             }
         }
 
         // Remove from lock holders
-        if let Ok(mut lock_holders) = self.lock_holders.write() {
-            if let Some(holders) = lock_holders.get_mut(&lock_id) {
-                holders.retain(|h| h.thread_id != thread_id);
-                if holders.is_empty() {
-                    lock_holders.remove(&lock_id);
-                }
+        if let Ok(mut lock_holders) = self.lock_holders.write()
+            && let Some(holders) = lock_holders.get_mut(&lock_id)
+        {
+            holders.retain(|h| h.thread_id != thread_id);
+            if holders.is_empty() {
+                lock_holders.remove(&lock_id);
             }
         }
     }
 
     /// Detect deadlock using cycle detection in wait-for graph
-    fn detect_deadlock(&self) -> Result<()> {
+    fn detect_deadlock(&self, caller: ThreadId) -> Result<()> {
         // Rate limit detection
         if let Ok(mut last) = self.last_detection.lock() {
             if last.elapsed() < self.detection_interval {
@@ -177,56 +316,39 @@ impl DeadlockDetector {
             return Err(Status::InternalError);
         };
 
-        // Detect cycles using DFS
+        // Each thread waits for at most one other, so every cycle shows up
+        // on the path from any of its members
+        let mut seen = HashSet::new();
         for &start_thread in wait_for.keys() {
-            if self.has_cycle_from(start_thread, &wait_for)? {
+            if let Some(cycle) = Self::find_cycle_from(start_thread, &wait_for)
+                && cycle.iter().all(|t| seen.insert(*t))
+            {
                 log::warn!("Deadlock detected involving thread: {:?}", start_thread);
-                return Err(Status::DeadlockDetected);
+                self.break_cycle(&cycle, caller)?;
             }
         }
 
         Ok(())
     }
 
-    /// Check if there's a cycle starting from the given thread
-    fn has_cycle_from(
-        &self,
+    /// Follow wait-for edges from `start`, returning the threads of the cycle
+    /// reached, if any
+    fn find_cycle_from(
         start: ThreadId,
         wait_for: &HashMap<ThreadId, (LockId, ThreadId)>,
-    ) -> Result<bool> {
-        let mut visited = HashSet::new();
-        let mut path = HashSet::new();
-
-        self.dfs_cycle_check(start, wait_for, &mut visited, &mut path)
-    }
-
-    /// DFS-based cycle detection
-    fn dfs_cycle_check(
-        &self,
-        current: ThreadId,
-        wait_for: &HashMap<ThreadId, (LockId, ThreadId)>,
-        visited: &mut HashSet<ThreadId>,
-        path: &mut HashSet<ThreadId>,
-    ) -> Result<bool> {
-        if path.contains(&current) {
-            return Ok(true); // Cycle found
-        }
+    ) -> Option<Vec<ThreadId>> {
+        let mut path = vec![start];
+        let mut current = start;
 
-        if visited.contains(&current) {
-            return Ok(false); // Already processed
-        }
-
-        visited.insert(current);
-        path.insert(current);
-
-        if let Some((_, next_thread)) = wait_for.get(&current) {
-            if self.dfs_cycle_check(*next_thread, wait_for, visited, path)? {
-                return Ok(true);
+        while let Some(&(_, next_thread)) = wait_for.get(&current) {
+            if let Some(pos) = path.iter().position(|&t| t == next_thread) {
+                return Some(path.split_off(pos));
             }
+            path.push(next_thread);
+            current = next_thread;
         }
 
-        path.remove(&current);
-        Ok(false)
+        None
     }
 
     /// Get statistics about current lock state
@@ -247,6 +369,9 @@ impl DeadlockDetector {
             active_threads: holdings_count,
             waiting_threads: wait_count,
             active_locks: lock_count,
+            cycles_detected: self.cycles_detected.load(Ordering::Relaxed),
+            victims_selected: self.victims_selected.load(Ordering::Relaxed),
+            most_contended: Vec::new(),
         }
     }
 }
@@ -263,87 +388,539 @@ pub struct LockStatistics {
     pub active_threads: usize,
     pub waiting_threads: usize,
     pub active_locks: usize,
+    pub cycles_detected: u64,
+    pub victims_selected: u64,
+    /// Locks with the highest contention counts, most contended first
+    pub most_contended: Vec<(LockId, usize)>,
+}
+
+/// How long a waiter parks between retries of a contended lock. It is woken
+/// early when the lock is released.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackoffConfig {
+    /// Delay before the first retry
+    pub initial: Duration,
+    /// Factor the delay grows by on each retry
+    pub multiplier: f64,
+    /// Fraction of each delay, from 0 to 1, that is randomly taken off
+    pub jitter: f64,
+    /// Longest delay
+    pub cap: Duration,
+}
+
+impl BackoffConfig {
+    /// The same delay on every retry, without jitter
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            initial: delay,
+            multiplier: 1.0,
+            jitter: 0.0,
+            cap: delay,
+        }
+    }
+
+    /// Delay before retry number `attempt`, counting from 0
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let grown = self.initial.as_secs_f64() * self.multiplier.max(1.0).powi(attempt.min(64) as i32);
+        let capped = grown.min(self.cap.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            1.0 - jitter * rand::random::<f64>()
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64(capped * factor)
+    }
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_micros(50),
+            multiplier: 2.0,
+            jitter: 0.5,
+            cap: Duration::from_millis(5),
+        }
+    }
+}
+
+/// Timeouts applied by `HierarchicalLockManager` when a lock is contended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockManagerConfig {
+    /// Timeout for an uncontended lock, and the starting point for adaptation
+    pub base_timeout: Duration,
+    /// Upper bound for the adaptive timeout
+    pub max_timeout: Duration,
+    /// Whether to stretch the timeout for locks with a history of contention
+    pub adaptive: bool,
+    /// Reject a coarser lock requested while holding a finer one with
+    /// `Status::LockOrderViolation`; when off, the violation is only logged.
+    /// On by default in debug builds.
+    pub enforce_lock_order: bool,
+    /// Parking between retries of a contended lock
+    pub backoff: BackoffConfig,
+    /// Granularities whose waiters are served in arrival order; off
+    /// everywhere by default
+    pub fair: FairQueuing,
+}
+
+/// Which lock granularities queue their waiters in arrival order. Queued
+/// locks are handed to the oldest compatible waiter on release instead of
+/// to whichever thread retries first, which bounds how long any waiter can
+/// starve at some cost in throughput.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FairQueuing {
+    pub record: bool,
+    pub page: bool,
+    pub bucket: bool,
+    pub global: bool,
+}
+
+impl FairQueuing {
+    /// Queue waiters at every granularity
+    pub fn all() -> Self {
+        Self {
+            record: true,
+            page: true,
+            bucket: true,
+            global: true,
+        }
+    }
+
+    /// Whether locks of `granularity` queue their waiters
+    pub fn enabled(&self, granularity: LockGranularity) -> bool {
+        match granularity {
+            LockGranularity::Record => self.record,
+            LockGranularity::Page => self.page,
+            LockGranularity::Bucket => self.bucket,
+            LockGranularity::Global => self.global,
+        }
+    }
+}
+
+impl Default for LockManagerConfig {
+    fn default() -> Self {
+        Self {
+            base_timeout: Duration::from_millis(100),
+            max_timeout: Duration::from_secs(5),
+            adaptive: true,
+            enforce_lock_order: cfg!(debug_assertions),
+            backoff: BackoffConfig::default(),
+            fair: FairQueuing::default(),
+        }
+    }
+}
+
+/// A thread queued for a lock under fair queuing
+#[derive(Debug)]
+struct Waiter {
+    ticket: u64,
+    thread: ThreadId,
+    intent: LockIntent,
+}
+
+/// Current holders of a single lock
+#[derive(Debug, Default)]
+struct LockState {
+    readers: usize,
+    upgrader: Option<ThreadId>,
+    writer: Option<ThreadId>,
+    /// Waiters in arrival order, when the lock is fairly queued
+    queue: VecDeque<Waiter>,
+    /// Tickets of waiters the lock was handed to, not yet picked up
+    granted: HashSet<u64>,
+}
+
+impl LockState {
+    fn can_grant(&self, intent: LockIntent) -> bool {
+        match intent {
+            LockIntent::Read => self.writer.is_none(),
+            LockIntent::Upgradeable => self.writer.is_none() && self.upgrader.is_none(),
+            LockIntent::Write | LockIntent::ReadWrite => {
+                self.writer.is_none() && self.upgrader.is_none() && self.readers == 0
+            }
+        }
+    }
+
+    fn grant(&mut self, intent: LockIntent, thread: ThreadId) {
+        match intent {
+            LockIntent::Read => self.readers += 1,
+            LockIntent::Upgradeable => self.upgrader = Some(thread),
+            LockIntent::Write | LockIntent::ReadWrite => self.writer = Some(thread),
+        }
+    }
+
+    /// Hand the lock to waiters from the front of the queue for as long as
+    /// they are compatible, so a run of queued readers is granted together
+    fn grant_queued(&mut self) -> bool {
+        let mut handed_off = false;
+        while let Some(waiter) = self.queue.front() {
+            if !self.can_grant(waiter.intent) {
+                break;
+            }
+            let waiter = self.queue.pop_front().expect("front was just checked");
+            self.grant(waiter.intent, waiter.thread);
+            self.granted.insert(waiter.ticket);
+            handed_off = true;
+        }
+        handed_off
+    }
+
+    fn is_idle(&self) -> bool {
+        self.readers == 0
+            && self.upgrader.is_none()
+            && self.writer.is_none()
+            && self.queue.is_empty()
+            && self.granted.is_empty()
+    }
+}
+
+/// Shared/exclusive lock state for every lock id, with waiters parked on a
+/// single condition variable
+#[derive(Default)]
+struct LockTable {
+    states: Mutex<HashMap<LockId, LockState>>,
+    released: Condvar,
+    /// Parking between retries, from the manager's config
+    backoff: RwLock<BackoffConfig>,
+    /// Granularities served in arrival order, from the manager's config
+    fair: RwLock<FairQueuing>,
+    /// Source of fair queuing tickets
+    next_ticket: AtomicU64,
+}
+
+impl LockTable {
+    /// How often a parked waiter checks whether it was chosen as a deadlock
+    /// victim
+    const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    /// Park for up to `delay`, until a lock is released, `deadline` passes
+    /// (`None`), or `aborted` reports this waiter was chosen as a deadlock
+    /// victim
+    fn park<'a>(
+        &self,
+        states: MutexGuard<'a, HashMap<LockId, LockState>>,
+        deadline: Instant,
+        delay: Duration,
+        aborted: &dyn Fn() -> bool,
+    ) -> Result<Option<MutexGuard<'a, HashMap<LockId, LockState>>>> {
+        if aborted() {
+            return Err(Status::DeadlockDetected);
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        let wait = (deadline - now).min(delay).min(Self::ABORT_POLL_INTERVAL);
+        let (states, _) = self.released
+            .wait_timeout(states, wait)
+            .map_err(|_| Status::InternalError)?;
+        Ok(Some(states))
+    }
+
+    fn backoff(&self) -> BackoffConfig {
+        self.backoff.read().map(|b| *b).unwrap_or_default()
+    }
+
+    fn is_fair(&self, lock_id: LockId) -> bool {
+        self.fair.read()
+            .map(|fair| fair.enabled(lock_id.granularity))
+            .unwrap_or(false)
+    }
+
+    /// Wait until the lock can be granted with `intent`, or `timeout` passes
+    /// (`None`). Returns how many times the caller had to park.
+    fn acquire(
+        &self,
+        lock_id: LockId,
+        intent: LockIntent,
+        timeout: Duration,
+        aborted: &dyn Fn() -> bool,
+    ) -> Result<Option<u32>> {
+        if self.is_fair(lock_id) {
+            return self.acquire_queued(lock_id, intent, timeout, aborted);
+        }
+
+        let backoff = self.backoff();
+        let deadline = Instant::now() + timeout;
+        let mut states = self.states.lock().map_err(|_| Status::InternalError)?;
+        let mut attempt = 0;
+
+        loop {
+            let state = states.entry(lock_id).or_default();
+            if state.can_grant(intent) {
+                state.grant(intent, thread::current().id());
+                return Ok(Some(attempt));
+            }
+
+            match self.park(states, deadline, backoff.delay(attempt), aborted)? {
+                Some(reacquired) => states = reacquired,
+                None => return Ok(None),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Fair version of `acquire`: take the lock straight away only if nobody
+    /// is queued for it, otherwise take a ticket and wait for `release` to
+    /// hand the lock over
+    fn acquire_queued(
+        &self,
+        lock_id: LockId,
+        intent: LockIntent,
+        timeout: Duration,
+        aborted: &dyn Fn() -> bool,
+    ) -> Result<Option<u32>> {
+        let deadline = Instant::now() + timeout;
+        let thread = thread::current().id();
+        let mut states = self.states.lock().map_err(|_| Status::InternalError)?;
+
+        let state = states.entry(lock_id).or_default();
+        if state.queue.is_empty() && state.can_grant(intent) {
+            state.grant(intent, thread);
+            return Ok(Some(0));
+        }
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        state.queue.push_back(Waiter { ticket, thread, intent });
+
+        let mut parks = 0;
+        loop {
+            let state = states.get_mut(&lock_id).ok_or(Status::UnexpectedState)?;
+            if state.granted.remove(&ticket) {
+                return Ok(Some(parks));
+            }
+
+            let now = Instant::now();
+            let give_up = if aborted() {
+                Some(Err(Status::DeadlockDetected))
+            } else if now >= deadline {
+                Some(Ok(None))
+            } else {
+                None
+            };
+            if let Some(result) = give_up {
+                // Leaving the queue may unblock compatible waiters behind us
+                state.queue.retain(|waiter| waiter.ticket != ticket);
+                if state.grant_queued() {
+                    self.released.notify_all();
+                }
+                if state.is_idle() {
+                    states.remove(&lock_id);
+                }
+                return result;
+            }
+
+            let wait = (deadline - now).min(Self::ABORT_POLL_INTERVAL);
+            states = self.released
+                .wait_timeout(states, wait)
+                .map_err(|_| Status::InternalError)?
+                .0;
+            parks += 1;
+        }
+    }
+
+    /// Wait until the readers alongside an upgradeable hold drain, then turn
+    /// the hold into a write lock
+    fn upgrade(
+        &self,
+        lock_id: LockId,
+        timeout: Duration,
+        aborted: &dyn Fn() -> bool,
+    ) -> Result<bool> {
+        let backoff = self.backoff();
+        let deadline = Instant::now() + timeout;
+        let mut states = self.states.lock().map_err(|_| Status::InternalError)?;
+        let mut attempt = 0;
+
+        loop {
+            let state = states.get_mut(&lock_id).ok_or(Status::UnexpectedState)?;
+            if state.readers == 0 {
+                state.writer = state.upgrader.take();
+                return Ok(true);
+            }
+
+            match self.park(states, deadline, backoff.delay(attempt), aborted)? {
+                Some(reacquired) => states = reacquired,
+                None => return Ok(false),
+            }
+            attempt += 1;
+        }
+    }
+
+    fn release(&self, lock_id: LockId, intent: LockIntent) {
+        if let Ok(mut states) = self.states.lock()
+            && let Some(state) = states.get_mut(&lock_id)
+        {
+            match intent {
+                LockIntent::Read => state.readers = state.readers.saturating_sub(1),
+                LockIntent::Upgradeable => state.upgrader = None,
+                LockIntent::Write | LockIntent::ReadWrite => state.writer = None,
+            }
+            state.grant_queued();
+            if state.is_idle() {
+                states.remove(&lock_id);
+            }
+        }
+        self.released.notify_all();
+    }
 }
 
 /// Advanced hierarchical lock manager
 pub struct HierarchicalLockManager {
     /// Deadlock detector
     detector: Arc<DeadlockDetector>,
+    /// Lock holders and waiters
+    table: Arc<LockTable>,
     /// Lock contention counters
     contention_counters: RwLock<HashMap<LockId, AtomicUsize>>,
     /// Adaptive timeout based on contention
     adaptive_timeout: AtomicU64, // nanoseconds
-    /// Base timeout
-    base_timeout: Duration,
-    /// Maximum timeout
-    max_timeout: Duration,
+    /// Timeout configuration
+    config: RwLock<LockManagerConfig>,
 }
 
 impl HierarchicalLockManager {
+    /// Number of locks reported in `LockStatistics::most_contended`
+    const TOP_CONTENDED_LOCKS: usize = 8;
+
     pub fn new() -> Self {
+        Self::with_config(LockManagerConfig::default())
+    }
+
+    /// Create a lock manager with the given timeouts
+    pub fn with_config(config: LockManagerConfig) -> Self {
         Self {
             detector: Arc::new(DeadlockDetector::new()),
+            table: Arc::new(LockTable {
+                backoff: RwLock::new(config.backoff),
+                fair: RwLock::new(config.fair),
+                ..Default::default()
+            }),
             contention_counters: RwLock::new(HashMap::new()),
-            adaptive_timeout: AtomicU64::new(Duration::from_millis(100).as_nanos() as u64),
-            base_timeout: Duration::from_millis(100),
-            max_timeout: Duration::from_secs(5),
+            adaptive_timeout: AtomicU64::new(config.base_timeout.as_nanos() as u64),
+            config: RwLock::new(config),
         }
     }
 
-    /// Acquire lock with hierarchical ordering and deadlock detection
+    /// Current timeout configuration
+    pub fn config(&self) -> LockManagerConfig {
+        self.config.read().map(|c| *c).unwrap_or_default()
+    }
+
+    /// Replace the timeout configuration; applies to acquisitions started
+    /// after the call
+    pub fn set_config(&self, config: LockManagerConfig) {
+        if let Ok(mut current) = self.config.write() {
+            *current = config;
+        }
+        if let Ok(mut backoff) = self.table.backoff.write() {
+            *backoff = config.backoff;
+        }
+        if let Ok(mut fair) = self.table.fair.write() {
+            *fair = config.fair;
+        }
+        self.adaptive_timeout
+            .store(config.base_timeout.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Acquire lock with hierarchical ordering and deadlock detection.
+    ///
+    /// Fails with `Status::LockTimeout` if the lock is not granted within the
+    /// configured timeout, and with `Status::DeadlockDetected` if waiting
+    /// would close a cycle.
     pub fn acquire_lock(&self, lock_id: LockId, intent: LockIntent) -> Result<LockGuard> {
+        self.acquire_lock_internal(lock_id, intent, None)
+    }
+
+    /// Whether the calling thread holds `lock_id`
+    pub fn is_held_by_current_thread(&self, lock_id: LockId) -> bool {
+        self.detector.holds(thread::current().id(), lock_id)
+    }
+
+    /// Like `acquire_lock`, but waits at most `timeout` instead of the
+    /// configured timeout
+    pub fn acquire_lock_with_timeout(
+        &self,
+        lock_id: LockId,
+        intent: LockIntent,
+        timeout: Duration,
+    ) -> Result<LockGuard> {
+        self.acquire_lock_internal(lock_id, intent, Some(timeout))
+    }
+
+    fn acquire_lock_internal(
+        &self,
+        lock_id: LockId,
+        intent: LockIntent,
+        timeout: Option<Duration>,
+    ) -> Result<LockGuard> {
         let thread_id = thread::current().id();
         let start_time = Instant::now();
 
+        if let Some(finest) = self.detector.finest_held(thread_id)
+            && lock_id.granularity > finest
+        {
+            if self.config().enforce_lock_order {
+                return Err(Status::LockOrderViolation);
+            }
+            log::warn!(
+                "Lock order violation: {:?} requested while holding a {:?} lock",
+                lock_id,
+                finest
+            );
+        }
+
         // Record wait
-        self.detector.record_lock_wait(thread_id, lock_id)?;
+        if let Err(status) = self.detector.record_lock_wait(thread_id, lock_id) {
+            self.detector.record_wait_abandoned(thread_id);
+            return Err(status);
+        }
 
-        // Simulate lock acquisition with timeout
-        let timeout = self.get_adaptive_timeout(lock_id);
-        let acquired = self.try_acquire_with_timeout(lock_id, intent, timeout)?;
+        let timeout = timeout.unwrap_or_else(|| self.get_adaptive_timeout(lock_id));
+        let acquired = self.try_acquire_with_timeout(lock_id, intent, Some(timeout))?;
 
-        if acquired {
+        if let Some(parks) = acquired {
             self.detector.record_lock_acquired(thread_id, lock_id, intent);
-            self.update_contention_stats(lock_id, false);
+            self.update_contention_stats(lock_id, parks > 0);
 
             Ok(LockGuard::new(
+                thread_id,
                 lock_id,
                 intent,
                 self.detector.clone(),
+                self.table.clone(),
+                timeout,
                 start_time.elapsed(),
             ))
         } else {
+            self.detector.record_wait_abandoned(thread_id);
             self.update_contention_stats(lock_id, true);
-            Err(Status::LockContentionTimeout)
+            Err(Status::LockTimeout)
         }
     }
 
-    /// Try to acquire lock with timeout
+    /// Try to acquire lock, waiting at most `timeout` or, if none is given,
+    /// the adaptive timeout for this lock, and backing off between retries.
+    /// Returns how many retries it took, or `None` on timeout.
     fn try_acquire_with_timeout(
         &self,
         lock_id: LockId,
-        _intent: LockIntent,
-        timeout: Duration,
-    ) -> Result<bool> {
-        let start = Instant::now();
-
-        // Simulate lock acquisition logic
-        while start.elapsed() < timeout {
-            // In a real implementation, this would interact with actual locks
-            // For now, simulate success after a short delay
-            thread::sleep(Duration::from_millis(10));
-
-            // Simulate some probability of acquiring the lock
-            if lock_id.resource_id % 10 != 0 || start.elapsed() > Duration::from_millis(50) {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
+        intent: LockIntent,
+        timeout: Option<Duration>,
+    ) -> Result<Option<u32>> {
+        let timeout = timeout.unwrap_or_else(|| self.get_adaptive_timeout(lock_id));
+        let thread_id = thread::current().id();
+        self.table.acquire(lock_id, intent, timeout, &|| self.detector.take_abort(thread_id))
     }
 
     /// Get adaptive timeout based on contention history
     fn get_adaptive_timeout(&self, lock_id: LockId) -> Duration {
+        let config = self.config();
+        if !config.adaptive {
+            return config.base_timeout;
+        }
+
         let contention = if let Ok(counters) = self.contention_counters.read() {
             counters.get(&lock_id)
                 .map(|c| c.load(Ordering::Relaxed))
@@ -355,10 +932,10 @@ impl HierarchicalLockManager {
         // Increase timeout based on contention
         let multiplier = 1.0 + (contention as f64 * 0.1).min(10.0);
         let timeout = Duration::from_nanos(
-            (self.base_timeout.as_nanos() as f64 * multiplier) as u64
+            (config.base_timeout.as_nanos() as f64 * multiplier) as u64
         );
 
-        timeout.min(self.max_timeout)
+        timeout.min(config.max_timeout)
     }
 
     /// Update contention statistics
@@ -379,14 +956,31 @@ impl HierarchicalLockManager {
         }
 
         // Update adaptive timeout
-        let avg_contention = self.get_average_contention();
-        let new_timeout = (self.base_timeout.as_nanos() as f64 * (1.0 + avg_contention * 0.1)) as u64;
+        let config = self.config();
+        let avg_contention = if config.adaptive { self.get_average_contention() } else { 0.0 };
+        let new_timeout = (config.base_timeout.as_nanos() as f64 * (1.0 + avg_contention * 0.1)) as u64;
         self.adaptive_timeout.store(
-            new_timeout.min(self.max_timeout.as_nanos() as u64),
+            new_timeout.min(config.max_timeout.as_nanos() as u64),
             Ordering::Relaxed
         );
     }
 
+    /// Up to `n` locks with the highest contention counts, most contended
+    /// first
+    pub fn most_contended(&self, n: usize) -> Vec<(LockId, usize)> {
+        let mut counts: Vec<(LockId, usize)> = self.contention_counters.read()
+            .map(|counters| {
+                counters.iter()
+                    .map(|(lock_id, count)| (*lock_id, count.load(Ordering::Relaxed)))
+                    .filter(|(_, count)| *count > 0)
+                    .collect()
+            })
+            .unwrap_or_default();
+        counts.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+        counts.truncate(n);
+        counts
+    }
+
     /// Get average contention across all locks
     fn get_average_contention(&self) -> f64 {
         if let Ok(counters) = self.contention_counters.read() {
@@ -406,7 +1000,8 @@ impl HierarchicalLockManager {
 
     /// Get lock manager statistics
     pub fn get_statistics(&self) -> (LockStatistics, Duration) {
-        let detector_stats = self.detector.get_statistics();
+        let mut detector_stats = self.detector.get_statistics();
+        detector_stats.most_contended = self.most_contended(Self::TOP_CONTENDED_LOCKS);
         let current_timeout = Duration::from_nanos(
             self.adaptive_timeout.load(Ordering::Relaxed)
         );
@@ -423,24 +1018,35 @@ impl Default for HierarchicalLockManager {
 
 /// RAII lock guard with automatic cleanup
 pub struct LockGuard {
+    /// Thread that acquired the lock, which still owns it if the guard moves
+    thread_id: ThreadId,
     lock_id: LockId,
     intent: LockIntent,
     detector: Arc<DeadlockDetector>,
+    table: Arc<LockTable>,
+    /// Timeout the lock was acquired with, reused for upgrades
+    timeout: Duration,
     acquisition_time: Duration,
     acquired_at: Instant,
 }
 
 impl LockGuard {
     fn new(
+        thread_id: ThreadId,
         lock_id: LockId,
         intent: LockIntent,
         detector: Arc<DeadlockDetector>,
+        table: Arc<LockTable>,
+        timeout: Duration,
         acquisition_time: Duration,
     ) -> Self {
         Self {
+            thread_id,
             lock_id,
             intent,
             detector,
+            table,
+            timeout,
             acquisition_time,
             acquired_at: Instant::now(),
         }
@@ -460,12 +1066,46 @@ impl LockGuard {
     pub fn lock_info(&self) -> (LockId, LockIntent) {
         (self.lock_id, self.intent)
     }
+
+    /// Convert an upgradeable hold into a write lock without releasing it,
+    /// waiting for concurrent readers to drain. Write locks are returned
+    /// as-is.
+    ///
+    /// Fails with `Status::DeadlockDetected` if a reader of this lock is
+    /// itself waiting on this thread, with `Status::LockTimeout` if readers
+    /// do not drain within the acquisition timeout, and with
+    /// `Status::UnexpectedState` for read locks. The lock is released on
+    /// failure.
+    pub fn upgrade(self) -> Result<LockGuard> {
+        let mut this = self;
+        match this.intent {
+            LockIntent::Write | LockIntent::ReadWrite => return Ok(this),
+            LockIntent::Read => return Err(Status::UnexpectedState),
+            LockIntent::Upgradeable => {}
+        }
+
+        let thread_id = thread::current().id();
+        if let Err(status) = this.detector.record_lock_wait(thread_id, this.lock_id) {
+            this.detector.record_wait_abandoned(thread_id);
+            return Err(status);
+        }
+
+        let aborted = || this.detector.take_abort(thread_id);
+        if this.table.upgrade(this.lock_id, this.timeout, &aborted)? {
+            this.intent = LockIntent::Write;
+            this.detector.record_lock_upgraded(this.thread_id, this.lock_id, this.intent);
+            Ok(this)
+        } else {
+            this.detector.record_wait_abandoned(thread_id);
+            Err(Status::LockTimeout)
+        }
+    }
 }
 
 impl Drop for LockGuard {
     fn drop(&mut self) {
-        let thread_id = thread::current().id();
-        self.detector.record_lock_released(thread_id, self.lock_id);
+        self.detector.record_lock_released(self.thread_id, self.lock_id);
+        self.table.release(self.lock_id, self.intent);
     }
 }
 
@@ -525,6 +1165,349 @@ mod tests {
         }
 
         let (_stats, timeout) = manager.get_statistics();
-        assert!(timeout >= manager.base_timeout);
+        assert!(timeout >= manager.config().base_timeout);
+    }
+
+    #[test]
+    fn test_lock_timeout_on_held_bucket() {
+        let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+            base_timeout: Duration::from_millis(50),
+            max_timeout: Duration::from_millis(200),
+            adaptive: false,
+            ..Default::default()
+        }));
+        let lock_id = LockId::bucket(7);
+        let holder = manager.acquire_lock(lock_id, LockIntent::Write).unwrap();
+
+        let waiter = manager.clone();
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let result = waiter.acquire_lock(lock_id, LockIntent::Read).map(|_| ());
+            (result, start.elapsed())
+        });
+        let (result, elapsed) = handle.join().unwrap();
+        assert_eq!(result, Err(Status::LockTimeout));
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < Duration::from_secs(1));
+
+        // A per-call override wins over the configured timeout
+        let waiter = manager.clone();
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let result = waiter
+                .acquire_lock_with_timeout(lock_id, LockIntent::Write, Duration::from_millis(5))
+                .map(|_| ());
+            (result, start.elapsed())
+        });
+        let (result, elapsed) = handle.join().unwrap();
+        assert_eq!(result, Err(Status::LockTimeout));
+        assert!(elapsed < Duration::from_millis(50));
+
+        // Once released, the lock is granted again
+        drop(holder);
+        let waiter = manager.clone();
+        let handle = thread::spawn(move || waiter.acquire_lock(lock_id, LockIntent::Write).is_ok());
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_upgrade_waits_for_readers() {
+        let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+            base_timeout: Duration::from_secs(2),
+            max_timeout: Duration::from_secs(2),
+            adaptive: false,
+            ..Default::default()
+        }));
+        let lock_id = LockId::bucket(3);
+        let upgradeable = manager.acquire_lock(lock_id, LockIntent::Upgradeable).unwrap();
+
+        // Readers still get in alongside the upgradeable holder, a second
+        // upgradeable holder does not
+        let reader = manager.acquire_lock(lock_id, LockIntent::Read).unwrap();
+        let other = manager.clone();
+        let second = thread::spawn(move || {
+            other
+                .acquire_lock_with_timeout(lock_id, LockIntent::Upgradeable, Duration::from_millis(20))
+                .map(|_| ())
+        });
+        assert_eq!(second.join().unwrap(), Err(Status::LockTimeout));
+
+        let upgrader = thread::spawn(move || {
+            let guard = upgradeable.upgrade().unwrap();
+            guard.lock_info().1
+        });
+        thread::sleep(Duration::from_millis(30));
+        assert!(!upgrader.is_finished());
+        drop(reader);
+        assert_eq!(upgrader.join().unwrap(), LockIntent::Write);
+    }
+
+    #[test]
+    fn test_upgrade_excludes_readers() {
+        let manager = Arc::new(HierarchicalLockManager::new());
+        let lock_id = LockId::bucket(4);
+        let guard = manager
+            .acquire_lock(lock_id, LockIntent::Upgradeable)
+            .unwrap()
+            .upgrade()
+            .unwrap();
+
+        let other = manager.clone();
+        let reader = thread::spawn(move || {
+            other
+                .acquire_lock_with_timeout(lock_id, LockIntent::Read, Duration::from_millis(20))
+                .map(|_| ())
+        });
+        assert_eq!(reader.join().unwrap(), Err(Status::LockTimeout));
+        drop(guard);
+        assert!(manager.acquire_lock(lock_id, LockIntent::Read).is_ok());
+    }
+
+    #[test]
+    fn test_dueling_upgraders_abort_one() {
+        let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+            base_timeout: Duration::from_secs(2),
+            max_timeout: Duration::from_secs(2),
+            adaptive: false,
+            ..Default::default()
+        }));
+        let lock_id = LockId::bucket(5);
+        let (held_tx, held_rx) = std::sync::mpsc::channel();
+
+        // The first thread holds the upgradeable lock and upgrades; the second
+        // reads and then asks for the upgradeable lock too
+        let first_manager = manager.clone();
+        let first = thread::spawn(move || {
+            let guard = first_manager.acquire_lock(lock_id, LockIntent::Upgradeable).unwrap();
+            held_tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            guard.upgrade().map(|_| ())
+        });
+        held_rx.recv().unwrap();
+
+        let second_manager = manager.clone();
+        let second = thread::spawn(move || {
+            let reader = second_manager.acquire_lock(lock_id, LockIntent::Read).unwrap();
+            let result = second_manager
+                .acquire_lock(lock_id, LockIntent::Upgradeable)
+                .map(|_| ());
+            drop(reader);
+            result
+        });
+
+        let results = [first.join().unwrap(), second.join().unwrap()];
+        let aborted = results
+            .iter()
+            .filter(|r| **r == Err(Status::DeadlockDetected))
+            .count();
+        assert_eq!(aborted, 1);
+        assert!(results.contains(&Ok(())));
+    }
+
+    #[test]
+    fn test_deadlock_aborts_cheapest_thread() {
+        let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+            base_timeout: Duration::from_secs(2),
+            max_timeout: Duration::from_secs(2),
+            adaptive: false,
+            ..Default::default()
+        }));
+        let (lock_a, lock_b) = (LockId::record(1), LockId::record(2));
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+
+        // The older thread holds B and the newer thread holds A, then each
+        // asks for the other's lock
+        let older_manager = manager.clone();
+        let older_barrier = barrier.clone();
+        let older = thread::spawn(move || {
+            let _b = older_manager.acquire_lock(lock_b, LockIntent::Write).unwrap();
+            older_barrier.wait();
+            older_barrier.wait();
+            thread::sleep(Duration::from_millis(20));
+            older_manager.acquire_lock(lock_a, LockIntent::Write).map(|_| ())
+        });
+
+        let newer_manager = manager.clone();
+        let newer_barrier = barrier.clone();
+        let newer = thread::spawn(move || {
+            newer_barrier.wait();
+            let _a = newer_manager.acquire_lock(lock_a, LockIntent::Write).unwrap();
+            newer_barrier.wait();
+            newer_manager.acquire_lock(lock_b, LockIntent::Write).map(|_| ())
+        });
+
+        let older = older.join().unwrap();
+        let newer = newer.join().unwrap();
+        assert_eq!(older, Ok(()));
+        assert_eq!(newer, Err(Status::DeadlockDetected));
+
+        let (stats, _) = manager.get_statistics();
+        assert_eq!(stats.cycles_detected, 1);
+        assert_eq!(stats.victims_selected, 1);
+        assert_eq!(stats.active_locks, 0);
+        assert_eq!(stats.waiting_threads, 0);
+    }
+
+    #[test]
+    fn test_lock_order_violation() {
+        let manager = HierarchicalLockManager::with_config(LockManagerConfig {
+            enforce_lock_order: true,
+            ..Default::default()
+        });
+
+        // Coarse to fine is allowed, and so is another lock at the same level
+        let bucket = manager.acquire_lock(LockId::bucket(1), LockIntent::Write).unwrap();
+        let record = manager.acquire_lock(LockId::record(10), LockIntent::Write).unwrap();
+        let other = manager.acquire_lock(LockId::record(11), LockIntent::Read).unwrap();
+
+        // Fine to coarse is not, whatever else is held
+        assert_eq!(
+            manager.acquire_lock(LockId::bucket(2), LockIntent::Read).err(),
+            Some(Status::LockOrderViolation)
+        );
+        assert_eq!(
+            manager.acquire_lock(LockId::page(3), LockIntent::Read).err(),
+            Some(Status::LockOrderViolation)
+        );
+        drop(record);
+        drop(other);
+        drop(bucket);
+        let record = manager.acquire_lock(LockId::record(10), LockIntent::Write).unwrap();
+        assert_eq!(
+            manager.acquire_lock(LockId::bucket(1), LockIntent::Write).err(),
+            Some(Status::LockOrderViolation)
+        );
+
+        // Warn-only mode lets the request through
+        manager.set_config(LockManagerConfig {
+            enforce_lock_order: false,
+            ..Default::default()
+        });
+        assert!(manager.acquire_lock(LockId::bucket(1), LockIntent::Write).is_ok());
+        drop(record);
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_to_cap() {
+        let backoff = BackoffConfig {
+            initial: Duration::from_micros(100),
+            multiplier: 2.0,
+            jitter: 0.0,
+            cap: Duration::from_millis(1),
+        };
+        assert_eq!(backoff.delay(0), Duration::from_micros(100));
+        assert_eq!(backoff.delay(2), Duration::from_micros(400));
+        assert_eq!(backoff.delay(10), Duration::from_millis(1));
+
+        let jittered = BackoffConfig { jitter: 0.5, ..backoff };
+        for attempt in 0..20 {
+            let delay = jittered.delay(attempt);
+            assert!(delay <= backoff.delay(attempt));
+            assert!(delay >= backoff.delay(attempt) / 2);
+        }
+        assert_eq!(BackoffConfig::fixed(Duration::from_millis(3)).delay(7), Duration::from_millis(3));
+    }
+
+    #[test]
+    fn test_most_contended_locks() {
+        let manager = Arc::new(HierarchicalLockManager::new());
+        let hot = LockId::bucket(1);
+        let cold = LockId::bucket(2);
+        drop(manager.acquire_lock(cold, LockIntent::Write).unwrap());
+
+        let holder = manager.acquire_lock(hot, LockIntent::Write).unwrap();
+        let waiters: Vec<_> = (0..4)
+            .map(|_| {
+                let manager = manager.clone();
+                thread::spawn(move || manager.acquire_lock(hot, LockIntent::Read).is_ok())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        drop(holder);
+        for waiter in waiters {
+            assert!(waiter.join().unwrap());
+        }
+
+        let (stats, _) = manager.get_statistics();
+        assert_eq!(stats.most_contended.first().map(|(lock_id, _)| *lock_id), Some(hot));
+        assert!(stats.most_contended[0].1 >= 4);
+        assert!(stats.most_contended.iter().all(|(lock_id, _)| *lock_id != cold));
+    }
+
+    #[test]
+    fn test_fair_queuing_grants_writers_in_arrival_order() {
+        let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+            fair: FairQueuing {
+                bucket: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+        let lock_id = LockId::bucket(0);
+        let holder = manager.acquire_lock(lock_id, LockIntent::Write).unwrap();
+
+        // Writers queue one after another behind the holder
+        let (tx, rx) = std::sync::mpsc::channel();
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let manager = manager.clone();
+                let tx = tx.clone();
+                let handle = thread::spawn(move || {
+                    let _guard = manager.acquire_lock(lock_id, LockIntent::Write).unwrap();
+                    tx.send(writer).unwrap();
+                });
+                thread::sleep(Duration::from_millis(20));
+                handle
+            })
+            .collect();
+        drop(holder);
+
+        // Each release hands the lock to the oldest waiter
+        let order: Vec<_> = rx.iter().take(4).collect();
+        assert_eq!(order, vec![0, 1, 2, 3]);
+        for writer in writers {
+            writer.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_fair_queuing_batches_readers() {
+        let manager = Arc::new(HierarchicalLockManager::with_config(LockManagerConfig {
+            fair: FairQueuing::all(),
+            ..Default::default()
+        }));
+        let lock_id = LockId::page(3);
+        let holder = manager.acquire_lock(lock_id, LockIntent::Write).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let manager = manager.clone();
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let _guard = manager.acquire_lock(lock_id, LockIntent::Read).unwrap();
+                    tx.send("read").unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                })
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(20));
+        let writer = {
+            let manager = manager.clone();
+            thread::spawn(move || {
+                let _guard = manager.acquire_lock(lock_id, LockIntent::Write).unwrap();
+                tx.send("write").unwrap();
+            })
+        };
+        thread::sleep(Duration::from_millis(20));
+        drop(holder);
+
+        // The readers queued first are granted together, ahead of the writer
+        let order: Vec<_> = rx.iter().take(4).collect();
+        assert_eq!(order, vec!["read", "read", "read", "write"]);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        writer.join().unwrap();
     }
 }
\ No newline at end of file
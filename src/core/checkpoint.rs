@@ -1,6 +1,7 @@
 use crate::core::address::Address;
 use crate::core::malloc_fixed_page_size::FixedPageAddress;
 use crate::core::status::Status;
+use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Types of checkpoints supported
@@ -68,6 +69,8 @@ pub struct LogMetadata {
     pub record_count: u64,
     /// Size of the log data in bytes
     pub data_size: u64,
+    /// UUID from the superblock of the log this checkpoint belongs to
+    pub store_uuid: [u8; 16],
 }
 
 impl Default for LogMetadata {
@@ -80,6 +83,7 @@ impl Default for LogMetadata {
             timestamp: 0,
             record_count: 0,
             data_size: 0,
+            store_uuid: [0; 16],
         }
     }
 }
@@ -101,6 +105,18 @@ impl CheckpointMetadata {
         }
     }
 
+    /// Reads the `checkpoint.dat` written by `RsKv::checkpoint` into the
+    /// checkpoint directory `dir`. Fails with `Status::Corruption` if the
+    /// file is too short to hold the metadata.
+    pub fn read(dir: &str) -> Result<Self, Status> {
+        let buffer = std::fs::read(Path::new(dir).join("checkpoint.dat"))
+            .map_err(|_| Status::IoError)?;
+        if buffer.len() < std::mem::size_of::<Self>() {
+            return Err(Status::Corruption);
+        }
+        Ok(unsafe { std::ptr::read_unaligned(buffer.as_ptr() as *const Self) })
+    }
+
     /// Validates the checkpoint metadata integrity
     pub fn validate(&self) -> Result<(), Status> {
         // Basic validation checks
@@ -205,6 +221,7 @@ impl LogMetadata {
             record_count,
             data_size,
             checksum: 0,
+            store_uuid: [0; 16],
         }
     }
 
@@ -217,6 +234,9 @@ impl LogMetadata {
         checksum ^= self.timestamp;
         checksum ^= self.record_count;
         checksum ^= self.data_size;
+        for half in self.store_uuid.chunks(8) {
+            checksum ^= u64::from_le_bytes(half.try_into().unwrap());
+        }
         checksum
     }
 
@@ -0,0 +1,128 @@
+//! Time sources: the [`Clock`] that time-driven code reads, and the
+//! write-time clock for record headers built on it.
+
+use crate::core::record::RecordInfo;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Where scheduling, expiry and heat decay read the time, so tests can move
+/// it with [`MockClock`](crate::testing::clock::MockClock) instead of
+/// sleeping.
+pub trait Clock: Debug + Send + Sync {
+    /// A monotonic reading, for measuring intervals
+    fn now_monotonic(&self) -> Instant;
+    /// The wall time, for timestamps
+    fn now_wall(&self) -> SystemTime;
+}
+
+/// The operating system clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_monotonic(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_wall(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A shared [`SystemClock`], the clock of everything not given another.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Time since the Unix epoch of a wall time, zero before it.
+pub(crate) fn since_unix_epoch(time: SystemTime) -> Duration {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Milliseconds since the Unix epoch that never go backwards.
+///
+/// Readings advance with a monotonic clock from the wall time taken when the
+/// clock was created, so wall clock adjustments while the store is open do
+/// not move them. They are also never below any earlier reading or any time
+/// passed to [`observe`](Self::observe), which lets a reopened store carry on
+/// from the newest write time in its log even if the wall clock is now
+/// behind it.
+#[derive(Debug)]
+pub struct HybridClock {
+    clock: Arc<dyn Clock>,
+    origin: Instant,
+    origin_ms: u64,
+    floor_ms: AtomicU64,
+}
+
+impl Default for HybridClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HybridClock {
+    pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    /// A hybrid clock reading `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            origin: clock.now_monotonic(),
+            origin_ms: since_unix_epoch(clock.now_wall()).as_millis() as u64,
+            clock,
+            floor_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// The current time, at least every earlier reading and observed time.
+    pub fn now_ms(&self) -> u64 {
+        let elapsed = self.clock.now_monotonic().saturating_duration_since(self.origin);
+        let now = (self.origin_ms + elapsed.as_millis() as u64).min(RecordInfo::TIMESTAMP_MASK);
+        self.floor_ms.fetch_max(now, Ordering::AcqRel).max(now)
+    }
+
+    /// Keeps later readings at or above `timestamp_ms`.
+    pub fn observe(&self, timestamp_ms: u64) {
+        self.floor_ms.fetch_max(timestamp_ms, Ordering::AcqRel);
+    }
+}
+
+/// The wall time `timestamp_ms` milliseconds after the Unix epoch.
+pub(crate) fn system_time_from_ms(timestamp_ms: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(timestamp_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::clock::MockClock;
+
+    #[test]
+    fn test_hybrid_clock_never_goes_backwards() {
+        let clock = HybridClock::new();
+        let first = clock.now_ms();
+        assert!(first > 0);
+        assert!(clock.now_ms() >= first);
+
+        // A write time ahead of the wall clock, as left by a store whose
+        // host clock was later set back.
+        let ahead = first + 3_600_000;
+        clock.observe(ahead);
+        assert_eq!(clock.now_ms(), ahead);
+        clock.observe(first);
+        assert_eq!(clock.now_ms(), ahead);
+    }
+
+    #[test]
+    fn test_hybrid_clock_follows_its_clock() {
+        let mock = Arc::new(MockClock::starting_at(UNIX_EPOCH + Duration::from_secs(1000)));
+        let clock = HybridClock::with_clock(mock.clone());
+        assert_eq!(clock.now_ms(), 1_000_000);
+        mock.advance(Duration::from_millis(250));
+        assert_eq!(clock.now_ms(), 1_000_250);
+    }
+}
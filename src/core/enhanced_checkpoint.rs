@@ -1,10 +1,11 @@
 use crate::core::address::Address;
+use crate::core::clock::{Clock, since_unix_epoch, system_clock};
 use crate::core::status::{Status, Result, ContextResult, ErrorContext, ResultExt};
 use crate::environment::file::File;
 use std::collections::{HashMap, BTreeMap};
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::sync::atomic::{AtomicU64, AtomicBool, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH, Duration, Instant};
+use std::time::Duration;
 // std::io imports removed as they're not currently used
 
 /// Checkpoint types supported by the system
@@ -118,24 +119,29 @@ pub struct EnhancedCheckpointManager {
     checkpoint_in_progress: AtomicBool,
     /// Statistics
     stats: RwLock<CheckpointStatistics>,
+    /// Time source for the strategy intervals and timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl EnhancedCheckpointManager {
     pub const CHECKPOINT_VERSION: u32 = 1;
 
     pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    /// A manager that reads the time from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         Self {
             sequence_counter: AtomicU64::new(1),
             strategy: RwLock::new(CheckpointStrategy::default()),
             active_checkpoints: RwLock::new(BTreeMap::new()),
             last_checkpoint: AtomicU64::new(
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_nanos() as u64
+                since_unix_epoch(clock.now_wall()).as_nanos() as u64
             ),
             checkpoint_in_progress: AtomicBool::new(false),
             stats: RwLock::new(CheckpointStatistics::default()),
+            clock,
         }
     }
 
@@ -164,20 +170,18 @@ impl EnhancedCheckpointManager {
         let last_checkpoint_time = Duration::from_nanos(
             self.last_checkpoint.load(Ordering::Acquire)
         );
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default();
+        let now = since_unix_epoch(self.clock.now_wall());
 
         // Check maximum interval
-        if now - last_checkpoint_time > strategy.max_interval {
+        if now.saturating_sub(last_checkpoint_time) > strategy.max_interval {
             return true;
         }
 
         // Check auto interval
-        if let Some(auto_interval) = strategy.auto_interval {
-            if now - last_checkpoint_time > auto_interval {
-                return true;
-            }
+        if let Some(auto_interval) = strategy.auto_interval
+            && now.saturating_sub(last_checkpoint_time) > auto_interval
+        {
+            return true;
         }
 
         // Additional checks would be implemented based on log size, dirty pages, etc.
@@ -194,12 +198,12 @@ impl EnhancedCheckpointManager {
     where
         F: FnOnce() -> ContextResult<(IndexCheckpointMetadata, LogCheckpointMetadata, Vec<u8>)>,
     {
-        if !self.checkpoint_in_progress.compare_exchange(
+        if self.checkpoint_in_progress.compare_exchange(
             false,
             true,
             Ordering::Acquire,
             Ordering::Relaxed
-        ).is_ok() {
+        ).is_err() {
             return Err(ErrorContext::new(Status::Aborted)
                 .with_context("Checkpoint already in progress"));
         }
@@ -219,7 +223,7 @@ impl EnhancedCheckpointManager {
     where
         F: FnOnce() -> ContextResult<(IndexCheckpointMetadata, LogCheckpointMetadata, Vec<u8>)>,
     {
-        let start_time = Instant::now();
+        let start_time = self.clock.now_monotonic();
         let sequence = self.sequence_counter.fetch_add(1, Ordering::Relaxed);
 
         // Collect checkpoint data
@@ -252,10 +256,7 @@ impl EnhancedCheckpointManager {
         let mut metadata = EnhancedCheckpointMetadata {
             version: Self::CHECKPOINT_VERSION,
             checkpoint_type,
-            created_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_nanos() as u64,
+            created_at: since_unix_epoch(self.clock.now_wall()).as_nanos() as u64,
             sequence_number: sequence,
             previous_sequence,
             data_hash,
@@ -283,7 +284,8 @@ impl EnhancedCheckpointManager {
             .with_context("Failed to register checkpoint")?;
 
         // Update statistics
-        self.update_statistics(start_time.elapsed(), final_data.len(), checkpoint_type);
+        let elapsed = self.clock.now_monotonic().saturating_duration_since(start_time);
+        self.update_statistics(elapsed, final_data.len(), checkpoint_type);
 
         // Update last checkpoint time
         self.last_checkpoint.store(
@@ -300,7 +302,7 @@ impl EnhancedCheckpointManager {
                 CheckpointType::IndexOnly => "index-only",
             },
             sequence,
-            start_time.elapsed(),
+            elapsed,
             final_data.len(),
             compression_ratio
         );
@@ -575,7 +577,7 @@ impl EnhancedCheckpointManager {
                 CheckpointType::IndexOnly => stats.index_only_checkpoints += 1,
             }
 
-            stats.last_checkpoint_time = Some(SystemTime::now());
+            stats.last_checkpoint_time = Some(self.clock.now_wall());
         }
     }
 }
@@ -598,7 +600,7 @@ pub struct CheckpointStatistics {
     pub min_checkpoint_time: Duration,
     pub max_checkpoint_time: Duration,
     pub total_checkpoint_size: u64,
-    pub last_checkpoint_time: Option<SystemTime>,
+    pub last_checkpoint_time: Option<std::time::SystemTime>,
 }
 
 impl CheckpointStatistics {
@@ -613,17 +615,16 @@ impl CheckpointStatistics {
 
     /// Get average checkpoint size
     pub fn average_checkpoint_size(&self) -> u64 {
-        if self.total_checkpoints > 0 {
-            self.total_checkpoint_size / self.total_checkpoints
-        } else {
-            0
-        }
+        self.total_checkpoint_size
+            .checked_div(self.total_checkpoints)
+            .unwrap_or(0)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::clock::MockClock;
     // std::io::Cursor removed as it's not used
 
     #[test]
@@ -636,8 +637,10 @@ mod tests {
     #[test]
     fn test_checkpoint_strategy_update() {
         let manager = EnhancedCheckpointManager::new();
-        let mut strategy = CheckpointStrategy::default();
-        strategy.auto_interval = Some(Duration::from_secs(60));
+        let strategy = CheckpointStrategy {
+            auto_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
 
         assert!(manager.update_strategy(strategy.clone()).is_ok());
 
@@ -647,17 +650,29 @@ mod tests {
 
     #[test]
     fn test_should_checkpoint_logic() {
-        let manager = EnhancedCheckpointManager::new();
+        let clock = Arc::new(MockClock::new());
+        let manager = EnhancedCheckpointManager::with_clock(clock.clone());
 
         // Should not checkpoint immediately after creation
         assert!(!manager.should_checkpoint());
 
-        // Should checkpoint if max interval has passed
-        let mut strategy = CheckpointStrategy::default();
-        strategy.max_interval = Duration::from_millis(1);
-        manager.update_strategy(strategy).unwrap();
+        // Should checkpoint once the auto interval has passed
+        clock.advance(Duration::from_secs(300));
+        assert!(!manager.should_checkpoint());
+        clock.advance(Duration::from_millis(1));
+        assert!(manager.should_checkpoint());
 
-        std::thread::sleep(Duration::from_millis(2));
+        // Or the max interval, without an auto interval
+        let clock = Arc::new(MockClock::new());
+        let manager = EnhancedCheckpointManager::with_clock(clock.clone());
+        let strategy = CheckpointStrategy {
+            auto_interval: None,
+            max_interval: Duration::from_millis(1),
+            ..Default::default()
+        };
+        manager.update_strategy(strategy).unwrap();
+        assert!(!manager.should_checkpoint());
+        clock.advance(Duration::from_millis(2));
         assert!(manager.should_checkpoint());
     }
 }
\ No newline at end of file
@@ -1,4 +1,7 @@
+use crate::core::status::Status;
 use crossbeam_epoch::{self as epoch, Guard as CrossbeamGuard};
+use std::cell::OnceCell;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 /// A light-weight epoch management system, wrapping `crossbeam-epoch`.
 /// Re-exporting Guard for convenience.
@@ -24,11 +27,35 @@ impl LightEpoch {
     /// entering a critical section for epoch-based reclamation.
     ///
     /// Corresponds to `Protect()` in the C++ version.
+    ///
+    /// Protection is reentrant: a thread that is already protected only bumps
+    /// a per-thread guard count, and the thread leaves the epoch when its
+    /// outermost guard is dropped. Helpers may therefore take their own guard
+    /// without checking whether the caller holds one. Guards can only be
+    /// released by dropping them, so there is no unprotect to unbalance.
     #[inline]
     pub fn protect(&self) -> Guard {
+        THREAD_SLOT.with(|slot| {
+            slot.get_or_init(ThreadSlot::register);
+        });
         epoch::pin()
     }
 
+    /// Returns the number of live threads that have protected themselves.
+    ///
+    /// crossbeam keeps its participants in a list that grows on a thread's
+    /// first pin and unlinks entries when threads exit, so there is no fixed
+    /// slot table to run out of; this count mirrors that list.
+    pub fn active_threads(&self) -> usize {
+        ACTIVE_THREADS.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the current thread holds a guard.
+    #[inline]
+    pub fn is_protected(&self) -> bool {
+        epoch::is_pinned()
+    }
+
     /// Bumps the current epoch and runs pending deferred functions if possible.
     ///
     /// In `crossbeam-epoch`, epoch advancement and garbage collection are handled
@@ -42,6 +69,129 @@ impl LightEpoch {
     }
 }
 
+/// Default number of deferred actions allowed to wait for the epoch to advance.
+pub const DEFAULT_DRAIN_HIGH_WATER: usize = 1 << 16;
+
+/// How many times a thread that hits the high-water mark tries to advance the
+/// epoch and drain before giving up with `EpochBacklog`.
+const FORCED_DRAIN_ATTEMPTS: usize = 4;
+
+// Like crossbeam's own collector, the drain list is process-wide.
+static PENDING_ACTIONS: AtomicUsize = AtomicUsize::new(0);
+static DRAIN_HIGH_WATER: AtomicUsize = AtomicUsize::new(DEFAULT_DRAIN_HIGH_WATER);
+static FORCED_DRAINS: AtomicU64 = AtomicU64::new(0);
+static BACKLOG_REJECTIONS: AtomicU64 = AtomicU64::new(0);
+
+static ACTIVE_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a thread in `ACTIVE_THREADS` from its first protect until it exits.
+struct ThreadSlot;
+
+impl ThreadSlot {
+    fn register() -> Self {
+        ACTIVE_THREADS.fetch_add(1, Ordering::Relaxed);
+        ThreadSlot
+    }
+}
+
+impl Drop for ThreadSlot {
+    fn drop(&mut self) {
+        ACTIVE_THREADS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+thread_local! {
+    static THREAD_SLOT: OnceCell<ThreadSlot> = const { OnceCell::new() };
+}
+
+/// Returned by `LightEpoch::defer` when the drain list is full, handing the
+/// action back so the caller can retry it later or apply backpressure.
+pub struct EpochBacklog<F>(pub F);
+
+impl<F> std::fmt::Debug for EpochBacklog<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EpochBacklog")
+    }
+}
+
+impl<F> From<EpochBacklog<F>> for Status {
+    fn from(_: EpochBacklog<F>) -> Self {
+        Status::EpochBacklog
+    }
+}
+
+/// Drain list counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainStats {
+    /// Deferred actions waiting for the epoch to advance
+    pub pending: usize,
+    /// Configured high-water mark
+    pub high_water: usize,
+    /// Times a thread was drafted to drain because the list was full
+    pub forced_drains: u64,
+    /// Deferrals refused with `EpochBacklog`
+    pub backlog_rejections: u64,
+}
+
+impl LightEpoch {
+    /// Runs `action` once no thread can still observe what it frees.
+    ///
+    /// At most `drain_high_water` actions wait at a time. A thread that finds
+    /// the list full is drafted to advance the epoch and drain ready actions;
+    /// if that frees no room, as when a thread stays protected without
+    /// refreshing, the action is handed back in `EpochBacklog`.
+    pub fn defer<F>(&self, guard: &Guard, action: F) -> Result<(), EpochBacklog<F>>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if !Self::reserve_slot() {
+            FORCED_DRAINS.fetch_add(1, Ordering::Relaxed);
+            let mut reserved = false;
+            for _ in 0..FORCED_DRAIN_ATTEMPTS {
+                guard.flush();
+                if Self::reserve_slot() {
+                    reserved = true;
+                    break;
+                }
+            }
+            if !reserved {
+                BACKLOG_REJECTIONS.fetch_add(1, Ordering::Relaxed);
+                return Err(EpochBacklog(action));
+            }
+        }
+
+        guard.defer(move || {
+            action();
+            PENDING_ACTIONS.fetch_sub(1, Ordering::AcqRel);
+        });
+        Ok(())
+    }
+
+    /// Sets the number of deferred actions allowed to wait at once.
+    pub fn set_drain_high_water(&self, high_water: usize) {
+        DRAIN_HIGH_WATER.store(high_water.max(1), Ordering::Relaxed);
+    }
+
+    /// Returns the drain list counters.
+    pub fn drain_stats(&self) -> DrainStats {
+        DrainStats {
+            pending: PENDING_ACTIONS.load(Ordering::Acquire),
+            high_water: DRAIN_HIGH_WATER.load(Ordering::Relaxed),
+            forced_drains: FORCED_DRAINS.load(Ordering::Relaxed),
+            backlog_rejections: BACKLOG_REJECTIONS.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reserve_slot() -> bool {
+        let high_water = DRAIN_HIGH_WATER.load(Ordering::Relaxed);
+        PENDING_ACTIONS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                (pending < high_water).then_some(pending + 1)
+            })
+            .is_ok()
+    }
+}
+
 impl Default for LightEpoch {
     fn default() -> Self {
         Self::new()
@@ -1,4 +1,6 @@
 use super::light_epoch::*;
+use crate::core::status::Status;
+use crate::index::dynamic_hash_table::DynamicHashTable;
 use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 use std::thread;
 use std::time::Duration;
@@ -148,4 +150,175 @@ mod tests {
             handle.join().unwrap();
         }
     }
+
+    #[test]
+    fn test_epoch_protect_reentrant() {
+        let epoch = LightEpoch::new();
+        assert!(!epoch.is_protected());
+
+        let outer = epoch.protect();
+        {
+            let middle = epoch.protect();
+            {
+                let _inner = epoch.protect();
+                assert!(epoch.is_protected());
+            }
+            assert!(epoch.is_protected());
+            drop(middle);
+        }
+        // Inner drops leave the thread protected until the outermost guard goes
+        assert!(epoch.is_protected());
+        drop(outer);
+        assert!(!epoch.is_protected());
+    }
+
+    #[test]
+    fn test_epoch_protect_nested_in_table_operations() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table = DynamicHashTable::new(epoch.clone());
+
+        // Each helper takes its own guard, whether or not the caller holds one
+        let put = |key: u64| {
+            let guard = epoch.protect();
+            table.upsert(key, key * 10, &guard).unwrap();
+        };
+        let get = |key: u64| {
+            let guard = epoch.protect();
+            table.get(&key, &guard).unwrap()
+        };
+
+        let outer = epoch.protect();
+        for key in 0..8 {
+            put(key);
+        }
+        let middle = epoch.protect();
+        assert_eq!(get(3), Some(30));
+        drop(middle);
+        assert!(epoch.is_protected());
+        drop(outer);
+
+        assert!(!epoch.is_protected());
+        assert_eq!(get(7), Some(70));
+        assert!(!epoch.is_protected());
+    }
+
+    #[test]
+    fn test_drain_list_bounded_while_thread_never_refreshes() {
+        let epoch = Arc::new(LightEpoch::new());
+        let high_water = 64;
+        epoch.set_drain_high_water(high_water);
+        let ran = Arc::new(AtomicUsize::new(0));
+
+        // One thread stays protected without refreshing, so the epoch stalls
+        let (pinned_tx, pinned_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let stuck_epoch = epoch.clone();
+        let stuck = thread::spawn(move || {
+            let _guard = stuck_epoch.protect();
+            pinned_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        pinned_rx.recv().unwrap();
+
+        let mut handles = vec![];
+        for _ in 0..4 {
+            let epoch = epoch.clone();
+            let ran = ran.clone();
+            handles.push(thread::spawn(move || {
+                let (mut accepted, mut refused) = (0, 0);
+                for _ in 0..500 {
+                    let guard = epoch.protect();
+                    let ran = ran.clone();
+                    match epoch.defer(&guard, move || {
+                        ran.fetch_add(1, Ordering::Relaxed);
+                    }) {
+                        Ok(()) => accepted += 1,
+                        Err(backlog) => {
+                            assert_eq!(Status::from(backlog), Status::EpochBacklog);
+                            refused += 1;
+                        }
+                    }
+                    assert!(epoch.drain_stats().pending <= high_water);
+                }
+                (accepted, refused)
+            }));
+        }
+
+        // Every worker finishes; once the list is full they are refused
+        // rather than blocked
+        let (mut accepted, mut refused) = (0, 0);
+        for handle in handles {
+            let (a, r) = handle.join().unwrap();
+            accepted += a;
+            refused += r;
+        }
+        assert!(accepted >= high_water);
+        assert!(refused > 0);
+        let stats = epoch.drain_stats();
+        assert!(stats.pending <= high_water);
+        assert!(stats.forced_drains > 0);
+        assert!(stats.backlog_rejections > 0);
+
+        // Once the thread lets go, the backlog drains and deferral works again
+        release_tx.send(()).unwrap();
+        stuck.join().unwrap();
+        for _ in 0..1000 {
+            if epoch.drain_stats().pending < high_water {
+                break;
+            }
+            epoch.bump_and_drain();
+        }
+        let guard = epoch.protect();
+        assert!(epoch.defer(&guard, || {}).is_ok());
+        drop(guard);
+        assert!(ran.load(Ordering::Relaxed) > 0);
+
+        epoch.set_drain_high_water(DEFAULT_DRAIN_HIGH_WATER);
+    }
+
+    #[test]
+    fn test_epoch_waves_of_short_lived_threads() {
+        let epoch = Arc::new(LightEpoch::new());
+        let threads_per_wave = 48;
+
+        for _ in 0..4 {
+            let ran = Arc::new(AtomicUsize::new(0));
+            let barrier = Arc::new(std::sync::Barrier::new(threads_per_wave + 1));
+            let handles: Vec<_> = (0..threads_per_wave)
+                .map(|_| {
+                    let epoch = epoch.clone();
+                    let ran = ran.clone();
+                    let barrier = barrier.clone();
+                    thread::spawn(move || {
+                        let guard = epoch.protect();
+                        guard.defer(move || {
+                            ran.fetch_add(1, Ordering::Relaxed);
+                        });
+                        drop(guard);
+                        barrier.wait();
+                        barrier.wait();
+                    })
+                })
+                .collect();
+
+            // Every thread of the wave is registered while it is alive
+            barrier.wait();
+            assert!(epoch.active_threads() >= threads_per_wave);
+            barrier.wait();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            // Exited threads hand their garbage over, and the epoch keeps
+            // advancing far enough to run all of it
+            for _ in 0..5000 {
+                if ran.load(Ordering::Relaxed) == threads_per_wave {
+                    break;
+                }
+                epoch.bump_and_drain();
+                thread::sleep(Duration::from_micros(100));
+            }
+            assert_eq!(ran.load(Ordering::Relaxed), threads_per_wave);
+        }
+    }
 }
\ No newline at end of file
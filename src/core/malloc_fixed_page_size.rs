@@ -25,7 +25,11 @@ impl FixedPageAddress {
     pub const K_ADDRESS_BITS: u64 = 48;
 
     /// --of which 20 bits are used for offsets into a page.
+    #[cfg(not(all(loom, test)))]
     pub const K_OFFSET_BITS: u64 = 20;
+    /// Loom models every atomic of a page, so models get 16-item pages.
+    #[cfg(all(loom, test))]
+    pub const K_OFFSET_BITS: u64 = 4;
     pub const K_MAX_OFFSET: u64 = (1 << Self::K_OFFSET_BITS) - 1;
 
     /// --and the remaining 28 bits are used for the page index.
@@ -102,7 +106,7 @@ impl AtomicFixedPageAddress {
     }
 }
 
-const K_PAGE_SIZE: usize = (FixedPageAddress::K_MAX_OFFSET + 1) as usize;
+pub(crate) const K_PAGE_SIZE: usize = (FixedPageAddress::K_MAX_OFFSET + 1) as usize;
 
 /// A single page of items.
 #[repr(align(64))]
@@ -261,7 +265,7 @@ impl<'epoch, T: Sized + Default> MallocFixedPageSize<'epoch, T> {
         let initial_array = Box::into_raw(Box::new(FixedPageArray::new(2, alignment)));
 
         // Initialize the allocator with basic functionality
-        let mut allocator = Self {
+        let allocator = Self {
             alignment,
             page_array: AtomicPtr::new(initial_array),
             count: AtomicFixedPageAddress::new(FixedPageAddress::new(0, 1)), // Start from offset 1 to avoid invalid address
@@ -302,6 +306,24 @@ impl<'epoch, T: Sized + Default> MallocFixedPageSize<'epoch, T> {
         }
     }
 
+    /// Address one past the last allocated item.
+    pub fn count(&self) -> FixedPageAddress {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Number of elements of page `page_idx` in use when `count` elements
+    /// have been handed out
+    fn elements_in_page(count: FixedPageAddress, page_idx: u64) -> usize {
+        if page_idx < count.page() {
+            K_PAGE_SIZE
+        } else {
+            count.offset() as usize
+        }
+    }
+
+    /// Writes the elements handed out so far, page after page, and returns
+    /// the number of bytes written. The elements are written, not the
+    /// pages: a page only holds a pointer to its elements.
     pub fn checkpoint(&self, file: &mut File) -> Result<u64, Status> {
         let array = match self.get_page_array() {
             Some(array) => array,
@@ -319,10 +341,12 @@ impl<'epoch, T: Sized + Default> MallocFixedPageSize<'epoch, T> {
             if page_ptr.is_null() {
                 continue;
             } // Should not happen in a consistent checkpoint
-            let page_size = std::mem::size_of::<FixedPage<T>>();
-            let buffer = unsafe { std::slice::from_raw_parts(page_ptr as *const u8, page_size) };
+            let len = Self::elements_in_page(count, i) * std::mem::size_of::<T>();
+            let buffer = unsafe {
+                std::slice::from_raw_parts((*page_ptr).elements.as_ptr() as *const u8, len)
+            };
             file.write(offset, buffer)?;
-            offset += page_size as u64;
+            offset += len as u64;
         }
         Ok(offset)
     }
@@ -334,7 +358,9 @@ impl<'epoch, T: Sized + Default> MallocFixedPageSize<'epoch, T> {
         ofb_count: FixedPageAddress,
     ) -> Status {
         let num_pages = ofb_count.page() + if ofb_count.offset() > 0 { 1 } else { 0 };
-        if num_ofb_bytes != num_pages * std::mem::size_of::<FixedPage<T>>() as u64 {
+        let expected_bytes = (ofb_count.page() * K_PAGE_SIZE as u64 + ofb_count.offset() as u64)
+            * std::mem::size_of::<T>() as u64;
+        if num_ofb_bytes != expected_bytes {
             return Status::Corruption;
         }
 
@@ -343,19 +369,27 @@ impl<'epoch, T: Sized + Default> MallocFixedPageSize<'epoch, T> {
             new_array_size,
             self.alignment,
         )));
-        self.page_array.store(new_array, Ordering::Release);
+        let old_array = self.page_array.swap(new_array, Ordering::AcqRel);
+        if !old_array.is_null() {
+            unsafe { drop(Box::from_raw(old_array)) };
+        }
         self.count.store(ofb_count, Ordering::Relaxed);
 
         let mut offset = 0;
         for i in 0..num_pages {
             let page_ptr = unsafe { (*new_array).get_or_add(i) };
-            let page_size = std::mem::size_of::<FixedPage<T>>();
-            let buffer = unsafe { std::slice::from_raw_parts_mut(page_ptr as *mut u8, page_size) };
+            if page_ptr.is_null() {
+                return Status::AllocationFailed;
+            }
+            let len = Self::elements_in_page(ofb_count, i) * std::mem::size_of::<T>();
+            let buffer = unsafe {
+                std::slice::from_raw_parts_mut((*page_ptr).elements.as_mut_ptr() as *mut u8, len)
+            };
             if let Err(status) = file.read(offset, buffer) {
                 error!("Failed to read from file: {:?}", status);
                 return Status::IoError;
             }
-            offset += page_size as u64;
+            offset += len as u64;
         }
         Status::Ok
     }
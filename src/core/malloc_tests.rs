@@ -128,7 +128,7 @@ mod tests {
 
     #[test]
     fn test_concurrent_allocation() {
-        let epoch = Arc::new(LightEpoch::new());
+        let _epoch = Arc::new(LightEpoch::new());
         let allocator = Arc::new(MallocFixedPageSize::<u64>::new());
         let counter = Arc::new(AtomicUsize::new(0));
 
@@ -3,6 +3,7 @@ pub mod advanced_locking;
 pub mod alloc;
 pub mod async_context;
 pub mod checkpoint;
+pub mod clock;
 pub mod constants;
 pub mod enhanced_checkpoint;
 pub mod light_epoch;
@@ -17,8 +18,10 @@ mod phase_tests;
 pub mod lockable_record;
 pub mod locking;
 pub mod malloc_fixed_page_size;
+pub mod numa;
 pub mod phase;
 pub mod record;
 pub mod recovery;
 pub mod status;
+pub(crate) mod sync;
 pub mod utility;
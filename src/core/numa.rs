@@ -0,0 +1,239 @@
+//! NUMA placement of hybrid log pages and hash index buckets.
+//!
+//! Placement is applied with the `mbind` system call when the crate is built
+//! with the `numa` feature on Linux. Anywhere else it is a no-op: the
+//! intended node of each allocation is still counted in [`MemoryMetrics`],
+//! and [`MemoryMetrics::placement_failures`] counts every allocation whose
+//! placement was not applied.
+
+use crate::performance::cache_optimizer::{NumaAllocator, NumaHint};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Where new hybrid log pages are placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PagePlacement {
+    /// On the node of the thread that first writes the page, the kernel
+    /// default. Only counted, never bound.
+    #[default]
+    FirstTouch,
+    /// Across all nodes in turn, one page at a time
+    RoundRobin,
+}
+
+/// NUMA settings of a store, see [`RsKv::set_numa`].
+///
+/// [`RsKv::set_numa`]: crate::rskv_core::RsKv::set_numa
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumaConfig {
+    pub pages: PagePlacement,
+    /// Split the hash index buckets into one contiguous range per node
+    pub partition_index: bool,
+}
+
+/// Memory of one NUMA node
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NodeMemory {
+    pub node: usize,
+    /// Hybrid log pages placed on the node
+    pub page_allocations: u64,
+    pub page_bytes: u64,
+    /// Hash index bucket bytes placed on the node
+    pub index_bytes: u64,
+}
+
+/// Per-node allocation counts of a store with NUMA placement
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryMetrics {
+    pub nodes: Vec<NodeMemory>,
+    /// Allocations whose placement could not be applied, including all of
+    /// them without the `numa` feature or off Linux
+    pub placement_failures: u64,
+}
+
+/// The online NUMA nodes, or just node 0 when they cannot be read.
+pub fn online_nodes() -> Vec<usize> {
+    std::fs::read_to_string("/sys/devices/system/node/online")
+        .ok()
+        .and_then(|list| parse_node_list(list.trim()))
+        .filter(|nodes| !nodes.is_empty())
+        .unwrap_or_else(|| vec![0])
+}
+
+/// Parses a kernel node list such as `0`, `0-3` or `0,2-3`.
+fn parse_node_list(list: &str) -> Option<Vec<usize>> {
+    let mut nodes = Vec::new();
+    for range in list.split(',') {
+        match range.split_once('-') {
+            Some((first, last)) => nodes.extend(first.parse::<usize>().ok()?..=last.parse().ok()?),
+            None => nodes.push(range.parse().ok()?),
+        }
+    }
+    Some(nodes)
+}
+
+/// Node of the CPU the calling thread runs on, if it can be found.
+pub fn current_node() -> Option<usize> {
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    {
+        let mut cpu: libc::c_uint = 0;
+        let mut node: libc::c_uint = 0;
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_getcpu,
+                &mut cpu as *mut libc::c_uint,
+                &mut node as *mut libc::c_uint,
+                std::ptr::null_mut::<libc::c_void>(),
+            )
+        };
+        if result == 0 {
+            return Some(node as usize);
+        }
+    }
+    None
+}
+
+/// Asks the kernel to keep the pages of `[ptr, ptr + len)` on `node`,
+/// moving any already touched. Only whole pages inside the range are bound.
+/// Returns false if the placement was not applied.
+///
+/// # Safety
+/// `[ptr, ptr + len)` must be memory owned by the caller.
+unsafe fn bind_to_node(ptr: *mut u8, len: usize, node: usize) -> bool {
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    {
+        const MPOL_PREFERRED: libc::c_int = 1;
+        const MPOL_MF_MOVE: libc::c_uint = 1 << 1;
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(4096) as usize;
+        let start = (ptr as usize).next_multiple_of(page);
+        let end = (ptr as usize + len) / page * page;
+        if node >= 64 || end <= start {
+            return false;
+        }
+        let mask: libc::c_ulong = 1 << node;
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                start as *mut libc::c_void,
+                end - start,
+                MPOL_PREFERRED,
+                &mask as *const libc::c_ulong,
+                64 as libc::c_ulong,
+                MPOL_MF_MOVE,
+            )
+        };
+        result == 0
+    }
+    #[cfg(not(all(feature = "numa", target_os = "linux")))]
+    {
+        let _ = (ptr, len, node);
+        false
+    }
+}
+
+/// Places allocations per a [`NumaConfig`] and counts them per node
+pub(crate) struct NumaPlacer {
+    config: NumaConfig,
+    nodes: Vec<usize>,
+    /// Picks the node of each page and counts page bytes per node
+    pages: NumaAllocator,
+    page_allocations: Box<[AtomicU64]>,
+    index_bytes: Box<[AtomicU64]>,
+    placement_failures: AtomicU64,
+}
+
+impl NumaPlacer {
+    pub(crate) fn new(config: NumaConfig) -> Self {
+        let nodes = online_nodes();
+        let counters = || nodes.iter().map(|_| AtomicU64::new(0)).collect();
+        Self {
+            config,
+            pages: NumaAllocator::new(nodes.len()),
+            page_allocations: counters(),
+            index_bytes: counters(),
+            placement_failures: AtomicU64::new(0),
+            nodes,
+        }
+    }
+
+    /// Places a log page before it is first written.
+    ///
+    /// # Safety
+    /// `[page, page + len)` must be an allocation owned by the caller.
+    pub(crate) unsafe fn place_page(&self, page: *mut u8, len: usize) {
+        let slot = match self.config.pages {
+            PagePlacement::FirstTouch => {
+                let node = current_node();
+                let slot = node.and_then(|node| self.nodes.iter().position(|&n| n == node));
+                if slot.is_none() {
+                    self.placement_failures.fetch_add(1, Ordering::Relaxed);
+                }
+                slot.unwrap_or(0)
+            }
+            PagePlacement::RoundRobin => {
+                let slot = self.pages.select_node(NumaHint::default());
+                if !unsafe { bind_to_node(page, len, self.nodes[slot]) } {
+                    self.placement_failures.fetch_add(1, Ordering::Relaxed);
+                }
+                slot
+            }
+        };
+        self.page_allocations[slot].fetch_add(1, Ordering::Relaxed);
+        self.pages.record_allocation(slot, len);
+    }
+
+    /// Splits the bucket array into one contiguous range per node, if the
+    /// config asks for it.
+    ///
+    /// # Safety
+    /// `[buckets, buckets + len)` must be an allocation owned by the caller.
+    pub(crate) unsafe fn place_index(&self, buckets: *mut u8, len: usize) {
+        if !self.config.partition_index {
+            return;
+        }
+        let share = len.div_ceil(self.nodes.len());
+        for (slot, &node) in self.nodes.iter().enumerate() {
+            let begin = (slot * share).min(len);
+            let bytes = share.min(len - begin);
+            if bytes == 0 {
+                continue;
+            }
+            if !unsafe { bind_to_node(buckets.add(begin), bytes, node) } {
+                self.placement_failures.fetch_add(1, Ordering::Relaxed);
+            }
+            self.index_bytes[slot].fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> MemoryMetrics {
+        let load = |counters: &[AtomicU64], slot: usize| counters[slot].load(Ordering::Relaxed);
+        let page_bytes = self.pages.get_node_stats();
+        MemoryMetrics {
+            nodes: self
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(slot, &node)| NodeMemory {
+                    node,
+                    page_allocations: load(&self.page_allocations, slot),
+                    page_bytes: page_bytes[slot] as u64,
+                    index_bytes: load(&self.index_bytes, slot),
+                })
+                .collect(),
+            placement_failures: self.placement_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_node_list() {
+        assert_eq!(parse_node_list("0"), Some(vec![0]));
+        assert_eq!(parse_node_list("0-3"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_node_list("0,2-3"), Some(vec![0, 2, 3]));
+        assert_eq!(parse_node_list("x"), None);
+    }
+}
@@ -58,6 +58,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn test_phase_clone_copy() {
         // Test Clone and Copy traits
         let original = Phase::GcInProgress;
@@ -105,6 +106,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn test_phase_string_not_empty() {
         // Ensure all phases have non-empty string representations
         let all_phases = [
@@ -4,10 +4,21 @@ use std::mem;
 use std::ptr;
 
 /// Record header, internal to FASTER.
-/// Corresponds to `RecordInfo` in C++ `core/record.h`.
+/// Corresponds to `RecordInfo` in C++ `core/record.h`, plus a write time.
+///
+/// Layout (16 bytes, little endian): the control word at `[0, 8)` holds the
+/// previous address in bits `[0, 48)`, the checkpoint version in `[48, 61)`
+/// and the invalid, tombstone and final bits at 61, 62 and 63. The word at
+/// `[8, 16)` holds the write time in milliseconds since the Unix epoch in
+/// bits `[0, 48)`, 0 when unknown, and the user flags in `[48, 52)`; its
+/// upper bits are zero. Logs of format version 1 and earlier have only the
+/// control word.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 #[repr(C, align(8))]
-pub struct RecordInfo(u64);
+pub struct RecordInfo {
+    control: u64,
+    timestamp: u64,
+}
 
 impl RecordInfo {
     // Bitfield constants based on C++ `RecordInfo` union layout
@@ -16,14 +27,24 @@ impl RecordInfo {
     pub const INVALID_BIT: u32 = 1;
     pub const TOMBSTONE_BIT: u32 = 1;
     pub const FINAL_BIT: u32 = 1;
+    pub const TIMESTAMP_BITS: u32 = 48;
+    pub const USER_FLAGS_BITS: u32 = 4;
 
     pub const PREVIOUS_ADDRESS_MASK: u64 = (1 << Self::PREVIOUS_ADDRESS_BITS) - 1;
     pub const CHECKPOINT_VERSION_MASK: u64 = (1 << Self::CHECKPOINT_VERSION_BITS) - 1;
+    pub const TIMESTAMP_MASK: u64 = (1 << Self::TIMESTAMP_BITS) - 1;
+    pub const USER_FLAGS_MASK: u8 = (1 << Self::USER_FLAGS_BITS) - 1;
+
+    /// Bytes of the header in logs of format version 1 and earlier, which
+    /// have no write time.
+    pub const LEGACY_SIZE: usize = 8;
 
     pub const CHECKPOINT_VERSION_SHIFT: u32 = Self::PREVIOUS_ADDRESS_BITS;
     pub const INVALID_SHIFT: u32 = Self::PREVIOUS_ADDRESS_BITS + Self::CHECKPOINT_VERSION_BITS;
     pub const TOMBSTONE_SHIFT: u32 = Self::INVALID_SHIFT + Self::INVALID_BIT;
     pub const FINAL_SHIFT: u32 = Self::TOMBSTONE_SHIFT + Self::TOMBSTONE_BIT;
+    /// Shift of the user flags within the word after the control word.
+    pub const USER_FLAGS_SHIFT: u32 = Self::TIMESTAMP_BITS;
 
     pub fn new(
         previous_address: Address,
@@ -44,38 +65,93 @@ impl RecordInfo {
         if final_bit {
             control |= 1 << Self::FINAL_SHIFT;
         }
-        RecordInfo(control)
+        RecordInfo::from_control(control)
     }
 
+    /// A header with the given control word and an unknown write time.
     pub fn from_control(control: u64) -> Self {
-        RecordInfo(control)
+        RecordInfo {
+            control,
+            timestamp: 0,
+        }
+    }
+
+    /// Decodes a header of format version 2 from the first 16 bytes of
+    /// `bytes`, or a legacy header from the first 8 when fewer are given.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        RecordInfo {
+            control: word(0),
+            timestamp: if bytes.len() >= 16 { word(8) } else { 0 },
+        }
     }
 
     pub fn control(&self) -> u64 {
-        self.0
+        self.control
+    }
+
+    /// The same header written at `timestamp_ms` milliseconds since the Unix
+    /// epoch, truncated to 48 bits.
+    pub fn with_timestamp(self, timestamp_ms: u64) -> Self {
+        RecordInfo {
+            timestamp: (self.timestamp & !Self::TIMESTAMP_MASK)
+                | (timestamp_ms & Self::TIMESTAMP_MASK),
+            ..self
+        }
+    }
+
+    /// The same header carrying the low 4 bits of `user_flags`.
+    pub fn with_user_flags(self, user_flags: u8) -> Self {
+        RecordInfo {
+            timestamp: Self::extra_word(self.timestamp, user_flags),
+            ..self
+        }
+    }
+
+    /// Application-defined flags of the record, 0 for records written
+    /// without any.
+    pub fn user_flags(&self) -> u8 {
+        (self.timestamp >> Self::USER_FLAGS_SHIFT) as u8 & Self::USER_FLAGS_MASK
+    }
+
+    /// The word after the control word holding `timestamp_ms` and
+    /// `user_flags`, as written into the log.
+    pub fn extra_word(timestamp_ms: u64, user_flags: u8) -> u64 {
+        (timestamp_ms & Self::TIMESTAMP_MASK)
+            | ((user_flags & Self::USER_FLAGS_MASK) as u64) << Self::USER_FLAGS_SHIFT
+    }
+
+    /// Write time in milliseconds since the Unix epoch, if known.
+    pub fn timestamp_ms(&self) -> Option<u64> {
+        let timestamp = self.timestamp & Self::TIMESTAMP_MASK;
+        (timestamp != 0).then_some(timestamp)
     }
 
     pub fn previous_address(&self) -> Address {
-        Address::from_control(self.0 & Self::PREVIOUS_ADDRESS_MASK)
+        Address::from_control(self.control & Self::PREVIOUS_ADDRESS_MASK)
     }
 
     pub fn set_invalid(&mut self, invalid: bool) {
         if invalid {
-            self.0 |= 1 << Self::INVALID_SHIFT;
+            self.control |= 1 << Self::INVALID_SHIFT;
         } else {
-            self.0 &= !(1 << Self::INVALID_SHIFT);
+            self.control &= !(1 << Self::INVALID_SHIFT);
         }
     }
 
+    pub fn invalid(&self) -> bool {
+        ((self.control >> Self::INVALID_SHIFT) & 1) != 0
+    }
+
     pub fn tombstone(&self) -> bool {
-        ((self.0 >> Self::TOMBSTONE_SHIFT) & 1) != 0
+        ((self.control >> Self::TOMBSTONE_SHIFT) & 1) != 0
     }
 
     pub fn set_tombstone(&mut self, tombstone: bool) {
         if tombstone {
-            self.0 |= 1 << Self::TOMBSTONE_SHIFT;
+            self.control |= 1 << Self::TOMBSTONE_SHIFT;
         } else {
-            self.0 &= !(1 << Self::TOMBSTONE_SHIFT);
+            self.control &= !(1 << Self::TOMBSTONE_SHIFT);
         }
     }
 }
@@ -119,7 +195,12 @@ where
 
     /// Calculates the required size for a record with alignment padding.
     pub fn required_size_with_alignment() -> u32 {
-        let header_size = mem::size_of::<RecordInfo>();
+        Self::required_size_with_header(mem::size_of::<RecordInfo>())
+    }
+
+    /// [`Self::required_size_with_alignment`] for a header of `header_size`
+    /// bytes, as in logs of older format versions.
+    pub fn required_size_with_header(header_size: usize) -> u32 {
         let key_size = mem::size_of::<K>();
         let value_size = mem::size_of::<V>();
 
@@ -162,46 +243,33 @@ where
 
     /// Creates a new record in the provided byte slice.
     ///
+    /// The header, key and value are packed from the start of `buffer` and
+    /// written unaligned, the layout [`Self::key`] and [`Self::value`] read.
+    /// Log slots are only 8-byte aligned, so keys and values that need more
+    /// cannot rely on it.
+    ///
     /// # Safety
-    /// The caller must ensure the buffer is large enough and properly aligned.
+    /// The caller must ensure the buffer is large enough.
     pub unsafe fn create_in(buffer: &mut [u8], header: RecordInfo, key: &K, value: &V) {
+        let key_offset = mem::size_of::<RecordInfo>();
+        let value_offset = key_offset + mem::size_of::<K>();
+        assert!(
+            buffer.len() >= value_offset + mem::size_of::<V>(),
+            "Buffer too small for record"
+        );
         unsafe {
-            // Ensure the buffer is properly aligned for Record<K, V>
             let buffer_ptr = buffer.as_mut_ptr();
-            let max_alignment = std::cmp::max(
-                mem::align_of::<RecordInfo>(),
-                std::cmp::max(mem::align_of::<K>(), mem::align_of::<V>()),
+            ptr::write_unaligned(buffer_ptr as *mut RecordInfo, header);
+            ptr::copy_nonoverlapping(
+                key as *const K as *const u8,
+                buffer_ptr.add(key_offset),
+                mem::size_of::<K>(),
             );
-
-            // Calculate the offset needed to align the buffer
-            let offset = buffer_ptr.align_offset(max_alignment);
-            if offset >= buffer.len() {
-                panic!("Buffer too small for alignment requirements");
-            }
-
-            let aligned_ptr = buffer_ptr.add(offset);
-            let record_ptr = aligned_ptr as *mut Self;
-
-            // Verify alignment
-            debug_assert!(
-                (record_ptr as usize).is_multiple_of(max_alignment),
-                "Record pointer not properly aligned: {:p} (alignment: {})",
-                record_ptr,
-                max_alignment
+            ptr::copy_nonoverlapping(
+                value as *const V as *const u8,
+                buffer_ptr.add(value_offset),
+                mem::size_of::<V>(),
             );
-
-            // Write header
-            ptr::write(&mut (*record_ptr).header, header);
-
-            // Calculate key position - assume key is properly aligned after header
-            let key_offset = mem::size_of::<RecordInfo>();
-            let key_ptr = (record_ptr as *mut u8).add(key_offset) as *mut K;
-            ptr::copy_nonoverlapping(key, key_ptr, 1);
-
-            // Calculate value position - assume value is properly aligned after key
-            let value_offset = key_offset + mem::size_of::<K>();
-            let value_ptr = (record_ptr as *mut u8).add(value_offset) as *mut V;
-            ptr::copy_nonoverlapping(value, value_ptr, 1);
         }
     }
 
@@ -246,28 +314,25 @@ where
         }
     }
 
-    /// Returns a mutable reference to the value from a record pointer.
+    /// Passes a copy of the value at `record_ptr` to `update` and writes it
+    /// back into the record if `update` returns true. Returns what `update`
+    /// returned.
     ///
     /// # Safety
     /// The caller must ensure that:
     /// - `record_ptr` is a valid, non-null pointer to a properly initialized `Record<K, V>`
-    /// - The record pointed to by `record_ptr` is properly aligned
-    /// - The memory is accessible for writing for the lifetime 'a
-    /// - No other references to the same memory exist
-    pub unsafe fn value_mut<'a>(record_ptr: *mut Self) -> &'a mut V {
+    /// - The memory is accessible for reading and writing
+    /// - No other thread writes the value concurrently
+    pub unsafe fn update_value(record_ptr: *mut Self, update: impl FnOnce(&mut V) -> bool) -> bool {
         unsafe {
-            // Use a simpler approach: calculate the value position based on the record layout
-            let key_offset = mem::size_of::<RecordInfo>();
-            let key_size = mem::size_of::<K>();
-
-            // Calculate value position - assume key is properly aligned after header
-            let value_offset = key_offset + key_size;
+            let value_offset = mem::size_of::<RecordInfo>() + mem::size_of::<K>();
             let value_ptr = (record_ptr as *mut u8).add(value_offset) as *mut V;
-
-            // Use unaligned read to avoid alignment issues
-            let value = ptr::read_unaligned(value_ptr);
-            // Create a static mutable reference to the value (this is safe because we're returning a reference)
-            Box::leak(Box::new(value))
+            let mut value = ptr::read_unaligned(value_ptr);
+            let updated = update(&mut value);
+            if updated {
+                ptr::write_unaligned(value_ptr, value);
+            }
+            updated
         }
     }
 }
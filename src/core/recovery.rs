@@ -110,7 +110,7 @@ impl RecoveryContext {
     /// Get the recovery strategy for a specific status
     fn get_strategy_for_status(&self, status: Status) -> RecoveryStrategy {
         match status {
-            Status::LockContentionTimeout => self.config.lock_contention,
+            Status::LockContentionTimeout | Status::LockTimeout => self.config.lock_contention,
             Status::OutOfMemory | Status::AllocationFailed => self.config.memory_allocation,
             Status::IoError | Status::FileNotFound | Status::PermissionDenied => self.config.io_errors,
             Status::Pending | Status::BufferTooSmall => self.config.temporary_failures,
@@ -211,7 +211,7 @@ impl RecoveryManager {
         F: FnMut() -> Result<()>,
     {
         let result = self.execute_with_recovery(|| {
-            operation().map_err(|status| ErrorContext::new(status))
+            operation().map_err(ErrorContext::new)
         });
 
         result.map_err(|error| error.root_cause())
@@ -241,20 +241,20 @@ impl<T, F> RecoveryExt<T> for F
 where
     F: FnMut() -> ContextResult<T>,
 {
-    fn with_recovery<G>(mut self, mut _operation: G) -> ContextResult<T>
+    fn with_recovery<G>(self, mut _operation: G) -> ContextResult<T>
     where
         G: FnMut() -> ContextResult<T>,
     {
         let manager = RecoveryManager::new();
-        manager.execute_with_recovery(|| self())
+        manager.execute_with_recovery(self)
     }
 
-    fn with_recovery_config<G>(mut self, config: RecoveryConfig, mut _operation: G) -> ContextResult<T>
+    fn with_recovery_config<G>(self, config: RecoveryConfig, mut _operation: G) -> ContextResult<T>
     where
         G: FnMut() -> ContextResult<T>,
     {
         let manager = RecoveryManager::with_config(config);
-        manager.execute_with_recovery(|| self())
+        manager.execute_with_recovery(self)
     }
 }
 
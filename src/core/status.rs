@@ -19,6 +19,10 @@ pub enum Status {
     LockContentionTimeout = 10,
     EpochProtectionFailed = 11,
     DeadlockDetected = 12,
+    LockTimeout = 24,
+    EpochBacklog = 25,
+    LockOrderViolation = 26,
+    Overloaded = 27,
 
     // Data integrity errors
     ChecksumMismatch = 13,
@@ -29,6 +33,8 @@ pub enum Status {
     FileNotFound = 16,
     PermissionDenied = 17,
     DiskFull = 18,
+    StoreLocked = 23,
+    LogCapacityExhausted = 28,
 
     // Configuration errors
     InvalidConfiguration = 19,
@@ -60,6 +66,10 @@ impl Status {
             Status::LockContentionTimeout => "LockContentionTimeout",
             Status::EpochProtectionFailed => "EpochProtectionFailed",
             Status::DeadlockDetected => "DeadlockDetected",
+            Status::LockTimeout => "LockTimeout",
+            Status::EpochBacklog => "EpochBacklog",
+            Status::LockOrderViolation => "LockOrderViolation",
+            Status::Overloaded => "Overloaded",
 
             // Data integrity errors
             Status::ChecksumMismatch => "ChecksumMismatch",
@@ -70,6 +80,8 @@ impl Status {
             Status::FileNotFound => "FileNotFound",
             Status::PermissionDenied => "PermissionDenied",
             Status::DiskFull => "DiskFull",
+            Status::StoreLocked => "StoreLocked",
+            Status::LogCapacityExhausted => "LogCapacityExhausted",
 
             // Configuration errors
             Status::InvalidConfiguration => "InvalidConfiguration",
@@ -101,6 +113,10 @@ impl Status {
             Status::LockContentionTimeout => "Lock acquisition timed out due to contention",
             Status::EpochProtectionFailed => "Epoch protection mechanism failed",
             Status::DeadlockDetected => "Potential deadlock detected",
+            Status::LockTimeout => "Lock was not granted within the configured timeout",
+            Status::EpochBacklog => "Too many deferred actions are waiting for the epoch to advance",
+            Status::LockOrderViolation => "Coarser lock requested while holding a finer one",
+            Status::Overloaded => "Write rejected while the store is under pressure",
 
             // Data integrity errors
             Status::ChecksumMismatch => "Data checksum does not match expected value",
@@ -111,6 +127,8 @@ impl Status {
             Status::FileNotFound => "Required file does not exist",
             Status::PermissionDenied => "Insufficient permissions for file operation",
             Status::DiskFull => "Insufficient disk space available",
+            Status::StoreLocked => "Storage directory is in use by another store",
+            Status::LogCapacityExhausted => "The log has reached its size limit",
 
             // Configuration errors
             Status::InvalidConfiguration => "Configuration parameters are invalid",
@@ -133,6 +151,9 @@ impl Status {
             self,
             Status::Pending
                 | Status::LockContentionTimeout
+                | Status::LockTimeout
+                | Status::EpochBacklog
+                | Status::Overloaded
                 | Status::OutOfMemory
                 | Status::AllocationFailed
                 | Status::IoError
@@ -158,8 +179,94 @@ impl Status {
             Status::LockContentionTimeout
                 | Status::EpochProtectionFailed
                 | Status::DeadlockDetected
+                | Status::LockTimeout
+                | Status::EpochBacklog
+                | Status::LockOrderViolation
         )
     }
+
+    /// Stable numeric code of this status. Codes are never reused, so they
+    /// are safe to persist or send to other processes.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The status with the given code, if there is one
+    pub fn from_code(code: u8) -> Option<Status> {
+        const ALL: [Status; 29] = [
+            Status::Ok,
+            Status::Pending,
+            Status::NotFound,
+            Status::OutOfMemory,
+            Status::IoError,
+            Status::Corruption,
+            Status::Aborted,
+            Status::AllocationFailed,
+            Status::InvalidAlignment,
+            Status::BufferTooSmall,
+            Status::LockContentionTimeout,
+            Status::EpochProtectionFailed,
+            Status::DeadlockDetected,
+            Status::ChecksumMismatch,
+            Status::InvalidDataFormat,
+            Status::VersionMismatch,
+            Status::FileNotFound,
+            Status::PermissionDenied,
+            Status::DiskFull,
+            Status::InvalidConfiguration,
+            Status::FeatureNotSupported,
+            Status::InternalError,
+            Status::UnexpectedState,
+            Status::StoreLocked,
+            Status::LockTimeout,
+            Status::EpochBacklog,
+            Status::LockOrderViolation,
+            Status::Overloaded,
+            Status::LogCapacityExhausted,
+        ];
+        ALL.get(code as usize).copied()
+    }
+
+    /// Closest `std::io::ErrorKind` to this status
+    pub fn io_error_kind(&self) -> std::io::ErrorKind {
+        use std::io::ErrorKind;
+        match self {
+            Status::NotFound | Status::FileNotFound => ErrorKind::NotFound,
+            Status::PermissionDenied => ErrorKind::PermissionDenied,
+            Status::DiskFull | Status::LogCapacityExhausted => ErrorKind::StorageFull,
+            Status::StoreLocked => ErrorKind::ResourceBusy,
+            Status::OutOfMemory | Status::AllocationFailed => ErrorKind::OutOfMemory,
+            Status::Corruption
+                | Status::ChecksumMismatch
+                | Status::InvalidDataFormat
+                | Status::VersionMismatch => ErrorKind::InvalidData,
+            Status::InvalidConfiguration => ErrorKind::InvalidInput,
+            Status::FeatureNotSupported => ErrorKind::Unsupported,
+            Status::LockContentionTimeout | Status::LockTimeout => ErrorKind::TimedOut,
+            Status::Pending | Status::EpochBacklog | Status::Overloaded => ErrorKind::WouldBlock,
+            Status::Aborted => ErrorKind::Interrupted,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl From<std::io::Error> for Status {
+    fn from(error: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match error.kind() {
+            ErrorKind::NotFound => Status::FileNotFound,
+            ErrorKind::PermissionDenied => Status::PermissionDenied,
+            ErrorKind::StorageFull => Status::DiskFull,
+            ErrorKind::OutOfMemory => Status::OutOfMemory,
+            _ => Status::IoError,
+        }
+    }
+}
+
+impl From<Status> for std::io::Error {
+    fn from(status: Status) -> Self {
+        std::io::Error::new(status.io_error_kind(), status)
+    }
 }
 
 impl std::error::Error for Status {
@@ -273,6 +380,20 @@ impl From<Status> for ErrorContext {
     }
 }
 
+/// Keeps the outermost status; the context strings are dropped
+impl From<ErrorContext> for Status {
+    fn from(error: ErrorContext) -> Self {
+        error.status
+    }
+}
+
+/// Keeps the whole chain, context strings included, as the error message
+impl From<ErrorContext> for std::io::Error {
+    fn from(error: ErrorContext) -> Self {
+        std::io::Error::new(error.status.io_error_kind(), error)
+    }
+}
+
 /// Enhanced result type with error context
 pub type ContextResult<T> = std::result::Result<T, ErrorContext>;
 
@@ -19,6 +19,9 @@ mod tests {
         assert!(!Status::LockContentionTimeout.to_string().is_empty());
         assert!(!Status::EpochProtectionFailed.to_string().is_empty());
         assert!(!Status::DeadlockDetected.to_string().is_empty());
+        assert!(!Status::LockTimeout.to_string().is_empty());
+        assert!(!Status::EpochBacklog.to_string().is_empty());
+        assert!(!Status::LockOrderViolation.to_string().is_empty());
         assert!(!Status::ChecksumMismatch.to_string().is_empty());
         assert!(!Status::InvalidDataFormat.to_string().is_empty());
         assert!(!Status::VersionMismatch.to_string().is_empty());
@@ -63,6 +66,9 @@ mod tests {
     fn test_status_is_recoverable() {
         // Recoverable errors
         assert!(Status::LockContentionTimeout.is_recoverable());
+        assert!(Status::LockTimeout.is_recoverable());
+        assert!(Status::EpochBacklog.is_recoverable());
+        assert!(Status::Overloaded.is_recoverable());
         assert!(Status::OutOfMemory.is_recoverable());
         assert!(Status::AllocationFailed.is_recoverable());
         assert!(Status::IoError.is_recoverable());
@@ -82,6 +88,7 @@ mod tests {
         assert!(!Status::InternalError.is_recoverable());
         assert!(!Status::UnexpectedState.is_recoverable());
         assert!(!Status::DeadlockDetected.is_recoverable());
+        assert!(!Status::LockOrderViolation.is_recoverable());
         assert!(!Status::EpochProtectionFailed.is_recoverable());
         assert!(!Status::NotFound.is_recoverable());
         assert!(!Status::Ok.is_recoverable()); // Ok is not an error to recover from
@@ -219,7 +226,7 @@ mod tests {
         assert!(context_result.is_err());
         let error = context_result.unwrap_err();
         assert_eq!(error.status, Status::OutOfMemory);
-        assert_eq!(error.location, Some("src/core/status.rs:324".to_string()));
+        assert_eq!(error.location, Some("src/core/status.rs:445".to_string()));
     }
 
     #[test]
@@ -253,6 +260,39 @@ mod tests {
         assert!(display.contains("Disk space exhausted"));
     }
 
+    #[test]
+    fn test_status_code_roundtrip() {
+        for code in 0..=u8::MAX {
+            if let Some(status) = Status::from_code(code) {
+                assert_eq!(status.code(), code);
+            }
+        }
+        assert_eq!(Status::from_code(Status::LockOrderViolation.code()), Some(Status::LockOrderViolation));
+        assert_eq!(Status::from_code(Status::Overloaded.code()), Some(Status::Overloaded));
+        assert_eq!(Status::from_code(Status::LogCapacityExhausted.code()), Some(Status::LogCapacityExhausted));
+        assert_eq!(Status::from_code(29), None);
+    }
+
+    #[test]
+    fn test_io_error_conversions() {
+        use std::io::{Error as IoError, ErrorKind};
+
+        assert_eq!(Status::from(IoError::from(ErrorKind::NotFound)), Status::FileNotFound);
+        assert_eq!(Status::from(IoError::from(ErrorKind::StorageFull)), Status::DiskFull);
+        assert_eq!(Status::from(IoError::from(ErrorKind::UnexpectedEof)), Status::IoError);
+
+        let io_error = IoError::from(Status::StoreLocked);
+        assert_eq!(io_error.kind(), ErrorKind::ResourceBusy);
+        assert_eq!(Status::from(IoError::from(Status::PermissionDenied)), Status::PermissionDenied);
+
+        let context = ErrorContext::new(Status::ChecksumMismatch)
+            .with_context("page 7 failed verification");
+        let io_error = IoError::from(context.clone());
+        assert_eq!(io_error.kind(), ErrorKind::InvalidData);
+        assert!(io_error.to_string().contains("page 7 failed verification"));
+        assert_eq!(Status::from(context), Status::ChecksumMismatch);
+    }
+
     #[test]
     fn test_memory_safety_error_contexts() {
         // Test that error contexts don't cause memory leaks with deep chains
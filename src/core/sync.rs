@@ -0,0 +1,26 @@
+//! Atomics of the lock-free log and index code. Built with `--cfg loom`,
+//! tests get loom's model-checked atomics instead, so the `loom_tests`
+//! modules can explore every interleaving of that code. Run them with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom --cfg crossbeam_loom" LOOM_MAX_PREEMPTIONS=2 \
+//!     cargo test --release --lib loom_tests
+//! ```
+//!
+//! `crossbeam_loom` switches crossbeam-epoch to loom too, which the epoch
+//! reclamation model relies on. Without the preemption bound that model
+//! does not finish in reasonable time. Other tests cannot run in that
+//! build, since loom atomics only work inside `loom::model`.
+
+#[cfg(all(loom, test))]
+pub(crate) use loom::sync::atomic::{
+    AtomicBool, AtomicPtr, AtomicU8, AtomicU16, AtomicU64, AtomicUsize,
+};
+#[cfg(all(loom, test))]
+pub(crate) use loom::thread::yield_now;
+#[cfg(not(all(loom, test)))]
+pub(crate) use std::sync::atomic::{
+    AtomicBool, AtomicPtr, AtomicU8, AtomicU16, AtomicU64, AtomicUsize,
+};
+#[cfg(not(all(loom, test)))]
+pub(crate) use std::thread::yield_now;
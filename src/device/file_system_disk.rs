@@ -1,40 +1,95 @@
 use crate::core::status::Status;
-use crate::environment::file::{File, FileCreateDisposition, FileOptions};
+use crate::environment::file::{self, DirectoryLock, File, FileCreateDisposition, FileOptions};
 use crate::hlog::persistent_memory_malloc::Disk;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// An implementation of the `Disk` trait for the local file system.
 #[derive(Clone)]
 pub struct FileSystemDisk {
     root_path: String,
     log: File,
+    /// Checksummed record of every flush to `log`
+    frames: File,
+    /// fsync calls issued through this disk and its clones
+    syncs: Arc<AtomicU64>,
+    /// Held until the last clone is dropped
+    _lock: Arc<DirectoryLock>,
 }
 
 impl FileSystemDisk {
+    /// Opens the storage directory, creating it if needed. Takes an
+    /// exclusive lock on the directory for the lifetime of the disk and its
+    /// clones, so a second store on the same directory fails with
+    /// `Status::StoreLocked`.
     pub fn new(root_path: &str) -> Result<Self, Status> {
         let path = std::path::Path::new(root_path);
         if !path.exists() {
-            std::fs::create_dir_all(path).map_err(|_| Status::IoError)?;
+            std::fs::create_dir_all(path)?;
         }
+        let lock = DirectoryLock::acquire(root_path, false)?;
+        Self::open(root_path, lock, FileCreateDisposition::OpenOrCreate)
+    }
+
+    /// Opens an existing storage directory under a shared lock. Any number
+    /// of read-only opens can coexist, but not with a writable one.
+    pub fn new_read_only(root_path: &str) -> Result<Self, Status> {
+        let lock = DirectoryLock::acquire(root_path, true)?;
+        Self::open(root_path, lock, FileCreateDisposition::OpenExisting)
+    }
+
+    fn open(
+        root_path: &str,
+        lock: DirectoryLock,
+        disposition: FileCreateDisposition,
+    ) -> Result<Self, Status> {
+        let path = std::path::Path::new(root_path);
         let log_path = path.join("hlog.log");
         let log_path_str = log_path.to_str().ok_or(Status::IoError)?;
         let mut log = File::new(log_path_str);
-        let status = log.open(FileCreateDisposition::OpenOrCreate, FileOptions::default());
-        if status.is_err() {
-            return Err(Status::IoError);
-        };
+        log.open(disposition, FileOptions::default())?;
+
+        let frames_path = path.join("hlog.frames");
+        let frames_path_str = frames_path.to_str().ok_or(Status::IoError)?;
+        let mut frames = File::new(frames_path_str);
+        frames.open(disposition, FileOptions::default())?;
 
         Ok(Self {
             root_path: root_path.to_string(),
             log,
+            frames,
+            syncs: Arc::new(AtomicU64::new(0)),
+            _lock: Arc::new(lock),
         })
     }
 
+    pub fn root_path(&self) -> &str {
+        &self.root_path
+    }
+
+    /// Number of file and directory syncs issued so far.
+    pub fn sync_count(&self) -> u64 {
+        self.syncs.load(Ordering::Relaxed)
+    }
+
+    pub fn sync_file(&self, file: &File) -> Result<(), Status> {
+        self.syncs.fetch_add(1, Ordering::Relaxed);
+        file.sync()
+    }
+
+    /// Syncs a directory under the root, so entries renamed into it survive
+    /// a power loss. See [`file::sync_directory`] for platform caveats.
+    pub fn sync_directory(&self, path: &str) -> Result<(), Status> {
+        self.syncs.fetch_add(1, Ordering::Relaxed);
+        file::sync_directory(&format!("{}/{}", self.root_path, path))
+    }
+
     pub fn log_mut(&mut self) -> &mut File {
         &mut self.log
     }
 
     pub fn new_file(&self, path: &str) -> File {
-        File::new(&format!("{}{}", self.root_path, path))
+        File::new(&format!("{}/{}", self.root_path, path))
     }
 
     pub fn index_checkpoint_path(&self, token: &str) -> String {
@@ -51,7 +106,7 @@ impl FileSystemDisk {
     }
 
     pub fn log_checkpoint_path(&self, token: &str) -> String {
-        format!("{}log-checkpoints/{}/", self.root_path, token)
+        format!("{}/log-checkpoints/{}/", self.root_path, token)
     }
 
     pub fn create_log_checkpoint_directory(&self, token: &str) -> Result<String, Status> {
@@ -82,6 +137,55 @@ impl Disk for FileSystemDisk {
         status
     }
 
+    fn read_sync(&mut self, offset: u64, data: &mut [u8]) -> Status {
+        match self.log.read(offset, data) {
+            Ok(_) => Status::Ok,
+            Err(status) => status,
+        }
+    }
+
+    fn log_size(&self) -> u64 {
+        self.log.size().unwrap_or(0)
+    }
+
+    fn append_frame(&mut self, frame: &[u8]) -> Status {
+        let offset = match self.frames.size() {
+            Ok(size) => size,
+            Err(status) => return status,
+        };
+        match self.frames.write(offset, frame) {
+            Ok(_) => Status::Ok,
+            Err(status) => status,
+        }
+    }
+
+    fn read_frames(&mut self) -> Result<Vec<u8>, Status> {
+        let mut frames = vec![0u8; self.frames.size()? as usize];
+        self.frames.read(0, &mut frames)?;
+        Ok(frames)
+    }
+
+    fn truncate_log(&mut self, log_size: u64, frames_size: u64) -> Status {
+        match self
+            .log
+            .truncate(log_size)
+            .and_then(|_| self.frames.truncate(frames_size))
+        {
+            Ok(_) => Status::Ok,
+            Err(status) => status,
+        }
+    }
+
+    fn sync_log(&mut self) -> Status {
+        match self
+            .sync_file(&self.log)
+            .and_then(|_| self.sync_file(&self.frames))
+        {
+            Ok(_) => Status::Ok,
+            Err(status) => status,
+        }
+    }
+
     fn index_checkpoint_path(&self, token: &str) -> String {
         self.index_checkpoint_path(token)
     }
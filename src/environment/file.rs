@@ -1,6 +1,7 @@
 use crate::core::status::Status;
-use std::fs::{File as StdFile, OpenOptions};
+use std::fs::{File as StdFile, OpenOptions, TryLockError};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
 
 #[derive(Debug, Clone, Copy)]
 pub enum FileCreateDisposition {
@@ -53,18 +54,14 @@ impl File {
                 self.delete_on_close = options.delete_on_close;
                 Ok(())
             }
-            Err(_) => Err(Status::IoError),
+            Err(error) => Err(error.into()),
         }
     }
 
     pub fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), Status> {
         if let Some(file) = self.file.as_mut() {
-            if file.seek(SeekFrom::Start(offset)).is_err() {
-                return Err(Status::IoError);
-            }
-            if file.write_all(data).is_err() {
-                return Err(Status::IoError);
-            }
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(data)?;
             Ok(())
         } else {
             Err(Status::IoError)
@@ -85,6 +82,31 @@ impl File {
         }
     }
 
+    pub fn size(&self) -> Result<u64, Status> {
+        match self.file.as_ref() {
+            Some(file) => file
+                .metadata()
+                .map(|metadata| metadata.len())
+                .map_err(|_| Status::IoError),
+            None => Err(Status::IoError),
+        }
+    }
+
+    pub fn truncate(&mut self, size: u64) -> Result<(), Status> {
+        match self.file.as_ref() {
+            Some(file) => file.set_len(size).map_err(Status::from),
+            None => Err(Status::IoError),
+        }
+    }
+
+    /// Flushes file data and metadata to the device.
+    pub fn sync(&self) -> Result<(), Status> {
+        match self.file.as_ref() {
+            Some(file) => file.sync_all().map_err(Status::from),
+            None => Err(Status::IoError),
+        }
+    }
+
     pub fn close(&mut self) -> Result<(), Status> {
         if self.file.take().is_some()
             && self.delete_on_close
@@ -96,6 +118,78 @@ impl File {
     }
 }
 
+/// Makes entries created, renamed or removed in `path` durable. On Unix this
+/// opens the directory and syncs it. Other platforms cannot open a directory
+/// for syncing, so this is a no-op there and a rename is only as durable as
+/// the file system makes it by default.
+pub fn sync_directory(path: &str) -> Result<(), Status> {
+    #[cfg(unix)]
+    {
+        let dir = StdFile::open(path).map_err(|_| Status::IoError)?;
+        dir.sync_all().map_err(|_| Status::IoError)?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Advisory lock on a storage directory, held until dropped. Exclusive
+/// holders write their process id into the lock file as a hint for whoever
+/// fails to get the lock next.
+pub struct DirectoryLock {
+    _file: StdFile,
+}
+
+impl DirectoryLock {
+    pub const FILE_NAME: &'static str = "LOCK";
+
+    /// Takes the lock on `dir` without waiting. Fails with
+    /// `Status::StoreLocked` if a conflicting lock is held, in this process
+    /// or another one.
+    pub fn acquire(dir: &str, shared: bool) -> Result<Self, Status> {
+        let path = Path::new(dir).join(Self::FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|_| Status::IoError)?;
+        let locked = if shared {
+            file.try_lock_shared()
+        } else {
+            file.try_lock()
+        };
+        match locked {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                log::warn!(
+                    "storage directory {} is locked (holder pid hint: {:?})",
+                    dir,
+                    Self::pid_hint(dir)
+                );
+                return Err(Status::StoreLocked);
+            }
+            Err(TryLockError::Error(_)) => return Err(Status::IoError),
+        }
+        if !shared {
+            file.set_len(0).map_err(|_| Status::IoError)?;
+            file.write_all(std::process::id().to_string().as_bytes())
+                .map_err(|_| Status::IoError)?;
+        }
+        Ok(Self { _file: file })
+    }
+
+    /// Process id recorded by the last exclusive holder of the lock on `dir`.
+    pub fn pid_hint(dir: &str) -> Option<u32> {
+        std::fs::read_to_string(Path::new(dir).join(Self::FILE_NAME))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
 impl Clone for File {
     fn clone(&self) -> Self {
         Self {
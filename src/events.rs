@@ -0,0 +1,308 @@
+//! Lifecycle event hooks.
+//!
+//! [`EventHooks`] holds optional callbacks for the transitions a store goes
+//! through, registered with
+//! [`RsKv::set_hooks`](crate::rskv_core::RsKv::set_hooks). The store only
+//! queues an event where it happens; callbacks run one at a time, in event
+//! order, on a dedicated thread, so a slow callback never holds up a write,
+//! flush or checkpoint. The queue is bounded: when it is full, new events are
+//! dropped and counted rather than waited for. A callback that panics is
+//! counted and skipped, and the thread goes on with the next event.
+//!
+//! The log keeps every page in memory and compaction runs offline through
+//! [`crate::admin::compact`], so a store does not raise [`PageEvicted`] or
+//! [`GcCompleted`] yet. Their hooks are accepted so that callers can register
+//! them now. Likewise the store's hash index has a fixed size, so only a
+//! [`DynamicHashTable`](crate::index::dynamic_hash_table::DynamicHashTable)
+//! with hooks set raises [`ResizeEvent`]s.
+
+use crate::admin::CompactReport;
+use crate::core::checkpoint::CheckpointMetadata;
+use std::ops::Range;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// The log is durable up to `until_address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlushCompleted {
+    pub until_address: u64,
+}
+
+/// A log page left memory; its records are only on disk from now on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageEvicted {
+    pub page: u32,
+    pub address_range: Range<u64>,
+}
+
+/// The checkpoint `id` is complete and durable.
+#[derive(Debug, Clone)]
+pub struct CheckpointCompleted {
+    pub id: String,
+    pub metadata: CheckpointMetadata,
+}
+
+/// A garbage collection pass finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcCompleted {
+    pub stats: CompactReport,
+}
+
+/// A hash table resize finished. `from > to` for a shrink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResizeCompleted {
+    pub from: usize,
+    pub to: usize,
+    pub duration: Duration,
+    pub entries_rehashed: u64,
+    /// Entries that found no free slot in the new table
+    pub entries_dropped: u64,
+}
+
+/// A step of a hash table resize, in bucket counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResizeEvent {
+    Started { from: usize, to: usize },
+    /// Old buckets whose entries have moved so far
+    Progress { buckets_migrated: usize },
+    Completed(ResizeCompleted),
+}
+
+/// The log has written past the warning point of its size limit, see
+/// [`RsKv::set_log_capacity`](crate::rskv_core::RsKv::set_log_capacity).
+/// Raised once until the capacity is set again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogCapacityLow {
+    /// Bytes of log written
+    pub written: u64,
+    pub limit: u64,
+}
+
+/// An event waiting for its callback.
+pub(crate) enum StoreEvent {
+    Flush(FlushCompleted),
+    // Not raised yet; see the module documentation.
+    #[allow(dead_code)]
+    Evict(PageEvicted),
+    Checkpoint(CheckpointCompleted),
+    #[allow(dead_code)]
+    Gc(GcCompleted),
+    Resize(ResizeEvent),
+    LogCapacity(LogCapacityLow),
+}
+
+type Hook<E> = Option<Box<dyn Fn(&E) + Send>>;
+
+/// Callbacks for store lifecycle events. Unset callbacks are skipped.
+pub struct EventHooks {
+    on_flush: Hook<FlushCompleted>,
+    on_evict: Hook<PageEvicted>,
+    on_checkpoint: Hook<CheckpointCompleted>,
+    on_gc: Hook<GcCompleted>,
+    on_resize: Hook<ResizeEvent>,
+    on_log_capacity: Hook<LogCapacityLow>,
+    queue_capacity: usize,
+}
+
+impl Default for EventHooks {
+    fn default() -> Self {
+        Self {
+            on_flush: None,
+            on_evict: None,
+            on_checkpoint: None,
+            on_gc: None,
+            on_resize: None,
+            on_log_capacity: None,
+            queue_capacity: 1024,
+        }
+    }
+}
+
+impl EventHooks {
+    pub fn on_flush(mut self, hook: impl Fn(&FlushCompleted) + Send + 'static) -> Self {
+        self.on_flush = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_evict(mut self, hook: impl Fn(&PageEvicted) + Send + 'static) -> Self {
+        self.on_evict = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_checkpoint(mut self, hook: impl Fn(&CheckpointCompleted) + Send + 'static) -> Self {
+        self.on_checkpoint = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_gc(mut self, hook: impl Fn(&GcCompleted) + Send + 'static) -> Self {
+        self.on_gc = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_resize(mut self, hook: impl Fn(&ResizeEvent) + Send + 'static) -> Self {
+        self.on_resize = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_log_capacity(mut self, hook: impl Fn(&LogCapacityLow) + Send + 'static) -> Self {
+        self.on_log_capacity = Some(Box::new(hook));
+        self
+    }
+
+    /// Events that may wait for their callbacks before new ones are dropped.
+    /// At least one.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Runs the callback for `event`. Returns false if it panicked.
+    fn dispatch(&self, event: &StoreEvent) -> bool {
+        fn run<E>(hook: &Hook<E>, event: &E) -> bool {
+            match hook {
+                Some(hook) => catch_unwind(AssertUnwindSafe(|| hook(event))).is_ok(),
+                None => true,
+            }
+        }
+        match event {
+            StoreEvent::Flush(event) => run(&self.on_flush, event),
+            StoreEvent::Evict(event) => run(&self.on_evict, event),
+            StoreEvent::Checkpoint(event) => run(&self.on_checkpoint, event),
+            StoreEvent::Gc(event) => run(&self.on_gc, event),
+            StoreEvent::Resize(event) => run(&self.on_resize, event),
+            StoreEvent::LogCapacity(event) => run(&self.on_log_capacity, event),
+        }
+    }
+}
+
+/// Counts of events since the hooks were set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HookStats {
+    /// Events whose callback returned, or that had no callback
+    pub delivered: u64,
+    /// Events dropped because the queue was full
+    pub dropped: u64,
+    /// Events whose callback panicked
+    pub panicked: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    panicked: AtomicU64,
+}
+
+/// Queue and thread running the callbacks of one store.
+pub(crate) struct EventDispatcher {
+    sender: Option<SyncSender<StoreEvent>>,
+    counters: Arc<Counters>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventDispatcher {
+    pub(crate) fn start(hooks: EventHooks) -> Self {
+        let (sender, receiver) = sync_channel(hooks.queue_capacity);
+        let counters = Arc::new(Counters::default());
+        let thread = {
+            let counters = counters.clone();
+            std::thread::spawn(move || deliver(&hooks, &receiver, &counters))
+        };
+        Self {
+            sender: Some(sender),
+            counters,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues `event` for its callback, or drops it if the queue is full.
+    pub(crate) fn emit(&self, event: StoreEvent) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = sender.try_send(event) {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> HookStats {
+        HookStats {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            panicked: self.counters.panicked.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for EventDispatcher {
+    /// Closes the queue and waits for the events already in it.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Delivery loop: runs each queued event's callback until the queue closes.
+fn deliver(hooks: &EventHooks, receiver: &Receiver<StoreEvent>, counters: &Counters) {
+    while let Ok(event) = receiver.recv() {
+        if hooks.dispatch(&event) {
+            counters.delivered.fetch_add(1, Ordering::Relaxed);
+        } else {
+            log::error!("event hook panicked");
+            counters.panicked.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    fn flush(until_address: u64) -> StoreEvent {
+        StoreEvent::Flush(FlushCompleted { until_address })
+    }
+
+    #[test]
+    fn test_full_queue_drops_and_panics_are_isolated() {
+        let (entered_tx, entered) = channel();
+        let (release, release_rx) = channel::<()>();
+        let (seen_tx, seen) = channel();
+        let hooks = EventHooks::default()
+            .queue_capacity(2)
+            .on_flush(move |event| {
+                if event.until_address == 1 {
+                    entered_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                }
+                if event.until_address == 2 {
+                    panic!("hook failure");
+                }
+                seen_tx.send(event.until_address).unwrap();
+            });
+        let dispatcher = EventDispatcher::start(hooks);
+        let counters = dispatcher.counters.clone();
+
+        // The first event holds the thread, the next two fill the queue and
+        // the fourth finds it full.
+        dispatcher.emit(flush(1));
+        entered.recv_timeout(Duration::from_secs(5)).unwrap();
+        for until_address in 2..=4 {
+            dispatcher.emit(flush(until_address));
+        }
+        assert_eq!(dispatcher.stats().dropped, 1);
+
+        release.send(()).unwrap();
+        drop(dispatcher);
+        assert_eq!(seen.try_iter().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(counters.delivered.load(Ordering::Relaxed), 2);
+        assert_eq!(counters.panicked.load(Ordering::Relaxed), 1);
+    }
+}
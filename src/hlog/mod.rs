@@ -1 +1,4 @@
+pub mod page_cache;
 pub mod persistent_memory_malloc;
+pub mod read_cache;
+pub mod superblock;
@@ -0,0 +1,542 @@
+//! Page cache: pages of the on-disk log read recently, with adaptive
+//! read-ahead.
+//!
+//! Records below the in-memory log are read through the cache in pages of
+//! [`PageCacheConfig::page_size`] bytes, far smaller than log pages. A miss
+//! reads its page together with the read-ahead window of pages after it in
+//! one disk read. The window follows how sequential the last few disk reads
+//! were: it grows towards [`PageCacheConfig::max_read_ahead`] while reads
+//! stay on a page or move to the next one, and drops to nothing on the
+//! first read that jumps elsewhere, so random reads do not fill the cache
+//! with pages nobody asks for.
+//!
+//! Only pages wholly below the flushed-until address are cached, since the
+//! disk never changes below it. The least recently used page is evicted.
+//!
+//! A [`Prefetcher`] reads single pages into the cache on a thread of its
+//! own. A read that needs a page the prefetcher is still reading waits for
+//! it rather than reading the page a second time.
+
+use crate::core::status::Status;
+use crate::hlog::persistent_memory_malloc::Disk;
+use crate::performance::access_analyzer::sequential_ratio;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, sync_channel};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+/// Page cache settings of a store, see [`RsKv::set_page_cache`].
+///
+/// [`RsKv::set_page_cache`]: crate::rskv_core::RsKv::set_page_cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageCacheConfig {
+    /// Pages the cache holds before the least recently used one is evicted
+    pub capacity: usize,
+    /// Bytes per cached page
+    pub page_size: u64,
+    /// Pages read ahead of a miss once reads are wholly sequential
+    pub max_read_ahead: u32,
+    /// Disk reads the sequentiality score is taken over
+    pub history: usize,
+}
+
+impl Default for PageCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            page_size: 4096,
+            max_read_ahead: 8,
+            history: 8,
+        }
+    }
+}
+
+/// Counters of a page cache since it was configured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageCacheStats {
+    pub capacity: u64,
+    /// Pages in the cache now
+    pub resident: u64,
+    /// Pages read from the cache
+    pub hits: u64,
+    /// Pages that had to be read from disk
+    pub misses: u64,
+    /// Pages read before anyone asked for them
+    pub prefetched: u64,
+    /// Prefetched pages read from the cache at least once
+    pub prefetch_hits: u64,
+    /// Prefetched pages evicted without ever being read
+    pub wasted_prefetches: u64,
+    /// Prefetches not made because too many were outstanding
+    pub prefetches_dropped: u64,
+    /// Pages the next miss reads ahead
+    pub read_ahead_window: u32,
+}
+
+impl PageCacheStats {
+    /// Share of page reads answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            0.0
+        } else {
+            self.hits as f64 / reads as f64
+        }
+    }
+
+    /// Share of prefetched pages evicted unread
+    pub fn wasted_prefetch_ratio(&self) -> f64 {
+        if self.prefetched == 0 {
+            0.0
+        } else {
+            self.wasted_prefetches as f64 / self.prefetched as f64
+        }
+    }
+}
+
+struct CachedPage {
+    data: Arc<[u8]>,
+    /// Position in `Pages::by_use`
+    last_use: u64,
+    /// Read ahead and not read from the cache since
+    prefetched: bool,
+}
+
+#[derive(Default)]
+struct Pages {
+    pages: HashMap<u64, CachedPage>,
+    /// Cached page numbers by last use, oldest first
+    by_use: BTreeMap<u64, u64>,
+    uses: u64,
+}
+
+impl Pages {
+    fn touch(&mut self, page: u64) -> Option<&mut CachedPage> {
+        self.uses += 1;
+        let uses = self.uses;
+        let cached = self.pages.get_mut(&page)?;
+        self.by_use.remove(&cached.last_use);
+        self.by_use.insert(uses, page);
+        cached.last_use = uses;
+        Some(cached)
+    }
+}
+
+pub(crate) struct PageCache {
+    config: PageCacheConfig,
+    pages: Mutex<Pages>,
+    /// Pages of the latest disk reads, oldest first
+    recent: Mutex<VecDeque<u64>>,
+    window: AtomicU32,
+    /// Pages the prefetcher has been asked for and not yet cached
+    in_flight: Mutex<HashSet<u64>>,
+    /// Signalled as the prefetcher finishes each page
+    landed: Condvar,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    prefetched: AtomicU64,
+    prefetch_hits: AtomicU64,
+    wasted_prefetches: AtomicU64,
+    prefetches_dropped: AtomicU64,
+}
+
+impl PageCache {
+    pub(crate) fn new(config: PageCacheConfig) -> Self {
+        Self {
+            config: PageCacheConfig {
+                capacity: config.capacity.max(1),
+                page_size: config.page_size.max(1),
+                history: config.history.max(2),
+                ..config
+            },
+            pages: Mutex::new(Pages::default()),
+            recent: Mutex::new(VecDeque::new()),
+            window: AtomicU32::new(0),
+            in_flight: Mutex::new(HashSet::new()),
+            landed: Condvar::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            prefetched: AtomicU64::new(0),
+            prefetch_hits: AtomicU64::new(0),
+            wasted_prefetches: AtomicU64::new(0),
+            prefetches_dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn pages(&self) -> MutexGuard<'_, Pages> {
+        self.pages
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn in_flight(&self) -> MutexGuard<'_, HashSet<u64>> {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Fills `buffer` with the log bytes at `offset`, from cached pages
+    /// where it can and otherwise with `load`, which reads the disk. Pages
+    /// wholly below `cacheable_until` are cached as they are read; the rest
+    /// of the range is read straight from disk.
+    pub(crate) fn read(
+        &self,
+        offset: u64,
+        buffer: &mut [u8],
+        cacheable_until: u64,
+        mut load: impl FnMut(u64, &mut [u8]) -> Status,
+    ) -> Status {
+        if buffer.is_empty() {
+            return Status::Ok;
+        }
+        let page_size = self.config.page_size;
+        let window = self.note_read(offset / page_size);
+        let end = offset + buffer.len() as u64;
+        let mut position = offset;
+        while position < end {
+            let page = position / page_size;
+            let page_start = page * page_size;
+            let copied = (position - offset) as usize;
+            if page_start + page_size > cacheable_until {
+                return load(position, &mut buffer[copied..]);
+            }
+            let cached = self.get(page).or_else(|| {
+                self.await_prefetch(page);
+                self.get(page)
+            });
+            let data = match cached {
+                Some(data) => data,
+                None => match self.fill(page, window, cacheable_until, &mut load) {
+                    Ok(data) => data,
+                    Err(status) => return status,
+                },
+            };
+            let from = (position - page_start) as usize;
+            let len = (data.len() - from).min(buffer.len() - copied);
+            buffer[copied..copied + len].copy_from_slice(&data[from..from + len]);
+            position += len as u64;
+        }
+        Status::Ok
+    }
+
+    /// The cached copy of `page`, if there is one.
+    pub(crate) fn get(&self, page: u64) -> Option<Arc<[u8]>> {
+        let mut pages = self.pages();
+        let cached = pages.touch(page)?;
+        if std::mem::take(&mut cached.prefetched) {
+            self.prefetch_hits.fetch_add(1, Ordering::Relaxed);
+        }
+        let data = cached.data.clone();
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(data)
+    }
+
+    pub(crate) fn contains(&self, page: u64) -> bool {
+        self.pages().pages.contains_key(&page)
+    }
+
+    /// Waits for the prefetcher to finish `page` if it is reading it.
+    fn await_prefetch(&self, page: u64) {
+        let mut in_flight = self.in_flight();
+        while in_flight.contains(&page) {
+            in_flight = self
+                .landed
+                .wait(in_flight)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Reads `page` with `load` for the prefetcher and caches it, then wakes
+    /// the reads waiting for it.
+    fn load_prefetched(&self, page: u64, load: impl FnOnce(u64, &mut [u8]) -> Status) {
+        let page_size = self.config.page_size;
+        let mut bytes = vec![0u8; page_size as usize];
+        if load(page * page_size, &mut bytes) == Status::Ok {
+            self.insert(page, bytes.into(), true);
+        }
+        self.in_flight().remove(&page);
+        self.landed.notify_all();
+    }
+
+    /// Reads `page` and up to `window` uncached pages after it below
+    /// `cacheable_until` with one call to `load`, and caches them all.
+    fn fill(
+        &self,
+        page: u64,
+        window: u32,
+        cacheable_until: u64,
+        load: &mut impl FnMut(u64, &mut [u8]) -> Status,
+    ) -> Result<Arc<[u8]>, Status> {
+        let page_size = self.config.page_size;
+        let mut count = 1;
+        while count <= window as u64
+            && (page + count + 1) * page_size <= cacheable_until
+            && !self.contains(page + count)
+        {
+            count += 1;
+        }
+        let mut bytes = vec![0u8; (count * page_size) as usize];
+        let status = load(page * page_size, &mut bytes);
+        if status != Status::Ok {
+            return Err(status);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let mut chunks = bytes.chunks(page_size as usize);
+        let data: Arc<[u8]> = chunks.next().unwrap_or_default().into();
+        self.insert(page, data.clone(), false);
+        for (ahead, chunk) in chunks.enumerate() {
+            self.insert(page + 1 + ahead as u64, chunk.into(), true);
+        }
+        Ok(data)
+    }
+
+    /// Caches `data` as the contents of `page`, evicting the least recently
+    /// used pages over capacity. `prefetched` marks a page read before it
+    /// was asked for. A page already cached keeps its copy.
+    pub(crate) fn insert(&self, page: u64, data: Arc<[u8]>, prefetched: bool) {
+        let mut pages = self.pages();
+        if pages.pages.contains_key(&page) {
+            return;
+        }
+        while pages.pages.len() >= self.config.capacity {
+            let Some((_, victim)) = pages.by_use.pop_first() else {
+                break;
+            };
+            if let Some(evicted) = pages.pages.remove(&victim)
+                && evicted.prefetched
+            {
+                self.wasted_prefetches.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        pages.uses += 1;
+        let last_use = pages.uses;
+        pages.by_use.insert(last_use, page);
+        pages.pages.insert(
+            page,
+            CachedPage {
+                data,
+                last_use,
+                prefetched,
+            },
+        );
+        if prefetched {
+            self.prefetched.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a disk read starting on `page` and returns the read-ahead
+    /// window for it: none if the read jumped away from the last one, and
+    /// otherwise the largest window scaled by how sequential the recent
+    /// reads are.
+    fn note_read(&self, page: u64) -> u32 {
+        let mut recent = self
+            .recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let follows = recent
+            .back()
+            .is_some_and(|&last| page == last || page == last + 1);
+        recent.push_back(page);
+        while recent.len() > self.config.history {
+            recent.pop_front();
+        }
+        let window = if follows {
+            let score = sequential_ratio(recent.iter().copied());
+            (score * self.config.max_read_ahead as f64).round() as u32
+        } else {
+            0
+        };
+        self.window.store(window, Ordering::Relaxed);
+        window
+    }
+
+    pub(crate) fn stats(&self) -> PageCacheStats {
+        PageCacheStats {
+            capacity: self.config.capacity as u64,
+            resident: self.pages().pages.len() as u64,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            prefetched: self.prefetched.load(Ordering::Relaxed),
+            prefetch_hits: self.prefetch_hits.load(Ordering::Relaxed),
+            wasted_prefetches: self.wasted_prefetches.load(Ordering::Relaxed),
+            prefetches_dropped: self.prefetches_dropped.load(Ordering::Relaxed),
+            read_ahead_window: self.window.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Queue and thread reading pages into a page cache ahead of the reads
+/// that need them.
+pub(crate) struct Prefetcher {
+    cache: Arc<PageCache>,
+    sender: Option<SyncSender<u64>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Prefetcher {
+    /// Starts a thread reading pages of `disk` into `cache`, with at most
+    /// `max_outstanding` pages queued or being read at once.
+    pub(crate) fn start<D: Disk + Send + 'static>(
+        cache: Arc<PageCache>,
+        disk: Arc<Mutex<D>>,
+        max_outstanding: usize,
+    ) -> Self {
+        // The thread holds one page besides those queued.
+        let (sender, receiver) = sync_channel::<u64>(max_outstanding.max(1) - 1);
+        let thread = {
+            let cache = cache.clone();
+            std::thread::spawn(move || {
+                while let Ok(page) = receiver.recv() {
+                    cache.load_prefetched(page, |offset, bytes| match disk.lock() {
+                        Ok(mut disk) => disk.read_sync(offset, bytes),
+                        Err(_) => Status::InternalError,
+                    });
+                }
+            })
+        };
+        Self {
+            cache,
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues a read of the page holding log offset `offset`, unless it is
+    /// cached, already queued, or not wholly below `cacheable_until`. The
+    /// read is dropped if too many are outstanding.
+    pub(crate) fn prefetch(&self, offset: u64, cacheable_until: u64) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let page = offset / self.cache.config.page_size;
+        if (page + 1) * self.cache.config.page_size > cacheable_until || self.cache.contains(page) {
+            return;
+        }
+        let mut in_flight = self.cache.in_flight();
+        if !in_flight.insert(page) {
+            return;
+        }
+        if sender.try_send(page).is_err() {
+            in_flight.remove(&page);
+            self.cache
+                .prefetches_dropped
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Prefetcher {
+    /// Closes the queue and waits for the pages already in it.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: u64 = 64;
+
+    fn cache(capacity: usize) -> PageCache {
+        PageCache::new(PageCacheConfig {
+            capacity,
+            page_size: PAGE,
+            max_read_ahead: 4,
+            history: 4,
+        })
+    }
+
+    /// Reads through `cache` from a disk holding the low byte of each
+    /// offset at that offset, counting the disk reads.
+    fn read(cache: &PageCache, offset: u64, len: usize, disk_reads: &mut u32) -> Vec<u8> {
+        let mut buffer = vec![0u8; len];
+        let status = cache.read(offset, &mut buffer, 1 << 20, |offset, bytes| {
+            *disk_reads += 1;
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = (offset + i as u64) as u8;
+            }
+            Status::Ok
+        });
+        assert_eq!(status, Status::Ok);
+        buffer
+    }
+
+    #[test]
+    fn test_read_ahead_grows_with_sequential_reads_and_stops_on_a_jump() {
+        let cache = cache(64);
+        let mut disk_reads = 0;
+        // A record straddling two pages reads both.
+        let bytes = read(&cache, PAGE - 4, 8, &mut disk_reads);
+        assert_eq!(
+            bytes,
+            (PAGE - 4..PAGE + 4).map(|b| b as u8).collect::<Vec<_>>()
+        );
+        assert_eq!(disk_reads, 2);
+
+        // Reading on through the log widens the window until misses are rare.
+        for page in 2..40 {
+            read(&cache, page * PAGE, 16, &mut disk_reads);
+        }
+        let stats = cache.stats();
+        assert_eq!(stats.read_ahead_window, 4);
+        assert!(disk_reads < 15, "{disk_reads} disk reads");
+        assert!(stats.prefetch_hits > 0);
+
+        // One jump and nothing more is read ahead.
+        read(&cache, 1000 * PAGE, 16, &mut disk_reads);
+        let stats = cache.stats();
+        assert_eq!(stats.read_ahead_window, 0);
+        assert_eq!(stats.resident as usize, cache.pages().pages.len());
+        assert!(!cache.contains(1001));
+    }
+
+    #[test]
+    fn test_random_reads_never_read_ahead() {
+        let cache = cache(64);
+        let mut disk_reads = 0;
+        for page in [7, 300, 12, 4000, 51, 900, 3, 77] {
+            read(&cache, page * PAGE, 16, &mut disk_reads);
+        }
+        let stats = cache.stats();
+        assert_eq!((stats.misses, stats.prefetched), (8, 0));
+        assert_eq!(disk_reads, 8);
+        assert_eq!(stats.wasted_prefetch_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_unread_prefetches_evicted_count_as_wasted() {
+        let cache = cache(4);
+        cache.insert(0, vec![0; PAGE as usize].into(), false);
+        cache.insert(1, vec![1; PAGE as usize].into(), true);
+        cache.insert(2, vec![2; PAGE as usize].into(), true);
+        assert_eq!(cache.get(1).unwrap()[0], 1);
+        for page in 3..7 {
+            cache.insert(page, vec![0; PAGE as usize].into(), false);
+        }
+        let stats = cache.stats();
+        assert_eq!((stats.resident, stats.prefetched), (4, 2));
+        assert_eq!((stats.prefetch_hits, stats.wasted_prefetches), (1, 1));
+        assert_eq!(stats.wasted_prefetch_ratio(), 0.5);
+    }
+
+    #[test]
+    fn test_pages_past_the_cacheable_end_are_read_through() {
+        let cache = cache(8);
+        let mut buffer = [0u8; 16];
+        let mut reads = Vec::new();
+        let status = cache.read(PAGE - 8, &mut buffer, PAGE + 8, |offset, bytes| {
+            reads.push((offset, bytes.len()));
+            Status::Ok
+        });
+        assert_eq!(status, Status::Ok);
+        assert_eq!(reads, vec![(0, PAGE as usize), (PAGE, 8)]);
+        assert!(cache.contains(0));
+        assert!(!cache.contains(1));
+    }
+}
@@ -2,12 +2,19 @@ use crate::core::address::{Address, AtomicAddress};
 use crate::core::alloc::aligned_alloc;
 use crate::core::checkpoint::LogMetadata;
 use crate::core::light_epoch::LightEpoch;
+use crate::core::numa::NumaPlacer;
 use crate::core::record::Record;
 use crate::core::status::Status;
+use crate::core::sync::{AtomicBool, AtomicPtr, AtomicU16, AtomicU64, yield_now};
+use crate::core::utility::crc32_update;
+use crate::hlog::page_cache::{PageCache, Prefetcher};
+use crate::hlog::superblock::{SUPERBLOCK_SIZE, Superblock};
+use serde::Serialize;
 use std::alloc::Layout;
+use std::collections::HashMap;
 use std::ptr;
-use std::sync::Mutex;
-use std::sync::atomic::{AtomicPtr, AtomicU16, AtomicU64, Ordering};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
 
 // --- Page Status Enums and Structs ---
 
@@ -118,19 +125,26 @@ impl AtomicPageOffset {
     fn reserve(&self, num_slots: u32) -> PageOffset {
         // Convert slots to bytes (each slot is 8 bytes)
         let bytes = (num_slots as u64) * 8;
-        let current = self.0.load(Ordering::Acquire);
-        let current_offset = PageOffset(current);
-
-        // Check if we need to advance to next page
-        if current_offset.offset() + bytes > (1 << Address::K_OFFSET_BITS) {
-            // Need to advance to next page
-            let next_page = current_offset.page() + 1;
-            let new_offset = PageOffset::new(next_page, 0);
-            self.0.store(new_offset.0, Ordering::Release);
-            new_offset
-        } else {
-            // Can fit in current page
-            PageOffset(self.0.fetch_add(bytes, Ordering::Relaxed))
+        let mut current = self.0.load(Ordering::Acquire);
+        loop {
+            let current_offset = PageOffset(current);
+            // Start the next page if the reservation does not fit in this one
+            let reserved = if current_offset.offset() + bytes > (1 << Address::K_OFFSET_BITS) {
+                PageOffset::new(current_offset.page() + 1, 0)
+            } else {
+                current_offset
+            };
+            // A plain store could hand the same start of a page to two
+            // threads crossing the boundary together.
+            match self.0.compare_exchange_weak(
+                current,
+                reserved.0 + bytes,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return reserved,
+                Err(actual) => current = actual,
+            }
         }
     }
 
@@ -160,6 +174,16 @@ pub trait Disk {
         data: &[u8],
         callback: Box<dyn FnOnce(Status) + Send>,
     ) -> Status;
+    fn read_sync(&mut self, offset: u64, data: &mut [u8]) -> Status;
+    /// Size of the on-disk log in bytes
+    fn log_size(&self) -> u64;
+    /// Appends an encoded flush frame to the frame log
+    fn append_frame(&mut self, frame: &[u8]) -> Status;
+    fn read_frames(&mut self) -> Result<Vec<u8>, Status>;
+    /// Cuts the log and the frame log back to the given sizes
+    fn truncate_log(&mut self, log_size: u64, frames_size: u64) -> Status;
+    /// Makes the log and frame log durable
+    fn sync_log(&mut self) -> Status;
     fn index_checkpoint_path(&self, token: &str) -> String;
 }
 
@@ -175,11 +199,133 @@ impl Disk for NullDisk {
         Status::Ok
     }
 
+    fn read_sync(&mut self, _offset: u64, _data: &mut [u8]) -> Status {
+        Status::NotFound
+    }
+
+    fn log_size(&self) -> u64 {
+        0
+    }
+
+    fn append_frame(&mut self, _frame: &[u8]) -> Status {
+        Status::Ok
+    }
+
+    fn read_frames(&mut self) -> Result<Vec<u8>, Status> {
+        Ok(Vec::new())
+    }
+
+    fn truncate_log(&mut self, _log_size: u64, _frames_size: u64) -> Status {
+        Status::Ok
+    }
+
+    fn sync_log(&mut self) -> Status {
+        Status::Ok
+    }
+
     fn index_checkpoint_path(&self, _token: &str) -> String {
         String::new()
     }
 }
 
+/// What [`PersistentMemoryMalloc::load_from_disk`] found on disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadedLog {
+    /// Bytes of verified log that were loaded
+    pub size: u64,
+    /// Bytes cut off the end of the log because they were torn
+    pub torn_bytes: u64,
+}
+
+/// Where one page of the log lives, as reported by
+/// [`PersistentMemoryMalloc::debug_page_map`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PageState {
+    /// The tail has not reached the page
+    NotAllocated,
+    /// In memory, with every record below the read-only address flushed
+    InMemory,
+    /// In memory, with read-only records that are not flushed yet
+    Flushing,
+    /// Flushed and dropped from memory
+    OnDisk,
+}
+
+/// One page of the log, as reported by
+/// [`PersistentMemoryMalloc::debug_page_map`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PageDebugInfo {
+    pub page: u32,
+    pub state: PageState,
+    /// Log addresses `[begin, end)` the page holds, empty for a page the
+    /// tail has not reached
+    pub begin: u64,
+    pub end: u64,
+    /// Whether the page holds bytes that are not flushed yet
+    pub dirty: bool,
+}
+
+/// Addresses bounding the regions of the log and the bytes in each, as
+/// reported by [`PersistentMemoryMalloc::region_summary`]. The regions are,
+/// from oldest to newest: on disk only `[begin, head)`, in memory and
+/// flushed `[head, flushed_until)`, read-only but not flushed
+/// `[flushed_until, read_only)`, and mutable `[read_only, tail)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RegionSummary {
+    pub begin_address: u64,
+    pub head_address: u64,
+    pub flushed_until_address: u64,
+    pub read_only_address: u64,
+    pub tail_address: u64,
+    pub on_disk_bytes: u64,
+    pub flushed_bytes: u64,
+    pub unflushed_read_only_bytes: u64,
+    pub mutable_bytes: u64,
+}
+
+/// Identifies a flush frame in the frame log.
+const FLUSH_FRAME_MAGIC: u32 = 0x4853_4c46; // "FLSH"
+pub(crate) const FLUSH_FRAME_SIZE: usize = 32;
+
+/// One flush of the log: the file bytes in `[begin, end)` and their CRC.
+/// Frames are appended to a separate frame log after the data is written,
+/// so a frame that fails to decode or verify marks a torn tail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FlushFrame {
+    pub(crate) begin: u64,
+    pub(crate) end: u64,
+    pub(crate) data_crc: u32,
+}
+
+impl FlushFrame {
+    fn encode(&self) -> [u8; FLUSH_FRAME_SIZE] {
+        let mut bytes = [0u8; FLUSH_FRAME_SIZE];
+        bytes[0..4].copy_from_slice(&FLUSH_FRAME_MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.data_crc.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.begin.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.end.to_le_bytes());
+        let frame_crc = crc32_update(0, &bytes[0..24]);
+        bytes[24..28].copy_from_slice(&frame_crc.to_le_bytes());
+        bytes
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < FLUSH_FRAME_SIZE {
+            return None;
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        if u32_at(0) != FLUSH_FRAME_MAGIC || u32_at(24) != crc32_update(0, &bytes[0..24]) {
+            return None;
+        }
+        Some(Self {
+            data_crc: u32_at(4),
+            begin: u64_at(8),
+            end: u64_at(16),
+        })
+    }
+}
+
 // --- Main Allocator Struct ---
 pub struct PersistentMemoryMalloc<'epoch, D: Disk> {
     pub pages: Box<[AtomicPtr<u8>]>,
@@ -194,7 +340,17 @@ pub struct PersistentMemoryMalloc<'epoch, D: Disk> {
     pub begin_address: AtomicAddress,
     pub flushed_until_address: AtomicAddress,
     pub epoch: Option<&'epoch LightEpoch>,
-    pub disk: Option<Mutex<D>>,
+    pub disk: Option<Arc<Mutex<D>>>,
+    /// Header kept in the first cache line of the log
+    pub superblock: Superblock,
+    /// Places new pages on NUMA nodes when set
+    pub(crate) numa: Option<NumaPlacer>,
+    /// Serves disk reads from recently read pages when set
+    pub(crate) page_cache: Option<Arc<PageCache>>,
+    /// Reads pages into the page cache in the background when set
+    pub(crate) prefetcher: Option<Prefetcher>,
+    /// Copies of pages below the head read back in by `admit_page`
+    admitted_pages: RwLock<HashMap<u32, Arc<[u8]>>>,
 }
 
 impl<'epoch, D: Disk> Default for PersistentMemoryMalloc<'epoch, D> {
@@ -203,8 +359,26 @@ impl<'epoch, D: Disk> Default for PersistentMemoryMalloc<'epoch, D> {
     }
 }
 
+impl<D: Disk> Drop for PersistentMemoryMalloc<'_, D> {
+    fn drop(&mut self) {
+        // Slices into the pages borrow the allocator, so none outlive it.
+        let Ok(layout) = Layout::from_size_align(self.page_size as usize, 64) else {
+            return;
+        };
+        for page in self.pages.iter() {
+            let page = page.swap(ptr::null_mut(), Ordering::AcqRel);
+            if !page.is_null() {
+                unsafe { crate::core::alloc::aligned_free(page, layout) };
+            }
+        }
+    }
+}
+
 impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
     pub const K_PAGE_SIZE: u64 = (Address::K_MAX_OFFSET + 1) as u64;
+    /// The first cache line of the log is never allocated, so that address 0
+    /// remains the end-of-chain sentinel.
+    pub const K_FIRST_VALID_ADDRESS: u64 = 64;
 
     pub fn new() -> Self {
         Self {
@@ -221,12 +395,17 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
             flushed_until_address: AtomicAddress::new(Address::from_control(0)),
             epoch: None,
             disk: None,
+            superblock: Superblock::default(),
+            numa: None,
+            page_cache: None,
+            prefetcher: None,
+            admitted_pages: RwLock::new(HashMap::new()),
         }
     }
 
     pub fn initialize(&mut self, log_size: u64, epoch: &'epoch LightEpoch, disk: D) {
         self.epoch = Some(epoch);
-        self.disk = Some(Mutex::new(disk));
+        self.disk = Some(Arc::new(Mutex::new(disk)));
         // Ensure we have at least 1 page, even if log_size is smaller than page_size
         self.buffer_size_in_pages = std::cmp::max(1, (log_size / self.page_size) as u32);
 
@@ -245,6 +424,8 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
 
         // Initialize the first page immediately
         self.new_page(Address::from_control(0));
+        self.tail_page_offset =
+            AtomicPageOffset::new(Address::from_control(Self::K_FIRST_VALID_ADDRESS));
 
         // Set initial addresses
         self.begin_address
@@ -253,6 +434,37 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
             .store(Address::from_control(0), Ordering::Release);
         self.flushed_until_address
             .store(Address::from_control(0), Ordering::Release);
+
+        // A new log starts with a fresh superblock; the first flush writes it.
+        self.install_superblock(Superblock::new(self.page_size));
+    }
+
+    fn install_superblock(&mut self, superblock: Superblock) {
+        self.superblock = superblock;
+        let buffer =
+            unsafe { self.get_mut_slice_unchecked(Address::from_control(0), SUPERBLOCK_SIZE) };
+        buffer.copy_from_slice(&superblock.encode());
+    }
+
+    /// Validates the superblock of an existing log on disk and adopts it.
+    /// Does nothing for a log too short to hold one, which is either new or
+    /// torn before its first flush completed.
+    pub fn open_superblock(&mut self) -> Result<(), Status> {
+        let mut bytes = [0u8; SUPERBLOCK_SIZE];
+        {
+            let disk = self.disk.as_ref().ok_or(Status::IoError)?;
+            let mut disk = disk.lock().map_err(|_| Status::InternalError)?;
+            if disk.log_size() < SUPERBLOCK_SIZE as u64 {
+                return Ok(());
+            }
+            let status = disk.read_sync(0, &mut bytes);
+            if status != Status::Ok {
+                return Err(status);
+            }
+        }
+        let superblock = Superblock::decode(&bytes, self.page_size)?;
+        self.install_superblock(superblock);
+        Ok(())
     }
 
     pub fn get_tail_address(&self) -> Address {
@@ -268,10 +480,125 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
         self.read_only_address.load(Ordering::Acquire)
     }
 
+    /// Moves the read-only address forward to `new_address`. Records below it
+    /// are no longer updated in place by writers that load it from now on.
+    /// Returns false if it was already past.
+    pub fn shift_read_only_address(&self, new_address: Address) -> bool {
+        Self::advance(&self.read_only_address, new_address)
+    }
+
+    /// Records below this address are no longer being updated in place by
+    /// any thread. It trails the read-only address by the writers that
+    /// loaded the read-only address before it last moved.
     pub fn get_safe_read_only_address(&self) -> Address {
         self.safe_read_only_address.load(Ordering::Acquire)
     }
 
+    /// Moves the read-only address forward to `new_address`, then waits until
+    /// the safe read-only address reaches it. Writers update a record in
+    /// place only under an epoch guard taken before loading the read-only
+    /// address, so once every thread protected at the shift has left its
+    /// epoch, no update below `new_address` is still under way and those
+    /// records can be copied out whole.
+    ///
+    /// The caller must not hold an epoch guard, since its own protection
+    /// would keep the epoch from ever draining.
+    pub fn freeze_until(&self, new_address: Address) {
+        self.shift_read_only_address(new_address);
+        if self.get_safe_read_only_address() >= new_address {
+            return;
+        }
+        if let Some(epoch) = self.epoch {
+            debug_assert!(
+                !epoch.is_protected(),
+                "freezing the log under epoch protection never drains"
+            );
+            let drained = Arc::new(AtomicBool::new(false));
+            {
+                let guard = epoch.protect();
+                let drained = Arc::clone(&drained);
+                guard.defer(move || drained.store(true, Ordering::Release));
+            }
+            while !drained.load(Ordering::Acquire) {
+                epoch.bump_and_drain();
+                yield_now();
+            }
+        }
+        Self::advance(&self.safe_read_only_address, new_address);
+    }
+
+    /// Moves `address` forward to `new_address` unless it is already past.
+    fn advance(address: &AtomicAddress, new_address: Address) -> bool {
+        let mut current = address.load(Ordering::Acquire);
+        while current < new_address {
+            match address.compare_exchange(
+                current,
+                new_address,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(actual) => current = actual,
+            }
+        }
+        false
+    }
+
+    /// State and address range of every page of the in-memory buffer, for
+    /// diagnosing memory use. The addresses are read one after another, so
+    /// the map is only exact while nothing writes to the log.
+    pub fn debug_page_map(&self) -> Vec<PageDebugInfo> {
+        let tail = self.get_tail_address().control();
+        let read_only = self.get_read_only_address().control();
+        let flushed = self.flushed_until_address.load(Ordering::Acquire).control();
+        (0..self.pages.len() as u32)
+            .map(|page| {
+                let page_begin = Address::new(page, 0).control();
+                let begin = page_begin.min(tail);
+                let end = (page_begin + self.page_size).min(tail);
+                let in_memory = !self.pages[page as usize].load(Ordering::Acquire).is_null();
+                let state = if !in_memory {
+                    if begin < end && end <= flushed {
+                        PageState::OnDisk
+                    } else {
+                        PageState::NotAllocated
+                    }
+                } else if begin.max(flushed) < end.min(read_only) {
+                    PageState::Flushing
+                } else {
+                    PageState::InMemory
+                };
+                PageDebugInfo {
+                    page,
+                    state,
+                    begin,
+                    end,
+                    dirty: in_memory && flushed < end,
+                }
+            })
+            .collect()
+    }
+
+    /// Bounds and sizes of the regions of the log. See [`RegionSummary`].
+    pub fn region_summary(&self) -> RegionSummary {
+        let begin = self.begin_address.load(Ordering::Acquire).control();
+        let head = self.get_head_address().control();
+        let flushed = self.flushed_until_address.load(Ordering::Acquire).control();
+        let read_only = self.get_read_only_address().control();
+        let tail = self.get_tail_address().control();
+        RegionSummary {
+            begin_address: begin,
+            head_address: head,
+            flushed_until_address: flushed,
+            read_only_address: read_only,
+            tail_address: tail,
+            on_disk_bytes: head.saturating_sub(begin),
+            flushed_bytes: flushed.saturating_sub(head.max(begin)),
+            unflushed_read_only_bytes: read_only.saturating_sub(flushed),
+            mutable_bytes: tail.saturating_sub(read_only),
+        }
+    }
+
     pub fn get_slice(&self, address: Address, size: usize) -> &[u8] {
         let page_idx = address.page() as usize;
         if page_idx >= self.pages.len() {
@@ -403,29 +730,463 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
             };
             let new_page = unsafe { aligned_alloc(layout) };
             if !new_page.is_null() {
+                // Placement has to come before zeroing touches the page
+                if let Some(numa) = &self.numa {
+                    unsafe { numa.place_page(new_page, self.page_size as usize) };
+                }
                 // Zero out the page
                 unsafe {
                     std::ptr::write_bytes(new_page, 0, self.page_size as usize);
                 }
-                self.pages[page_idx].store(new_page, Ordering::Release);
+                // Threads whose reservations land on the same missing page
+                // all try to allocate it. Only the first page published is
+                // used, since records may already be written to it.
+                if self.pages[page_idx]
+                    .compare_exchange(
+                        ptr::null_mut(),
+                        new_page,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_err()
+                {
+                    unsafe { crate::core::alloc::aligned_free(new_page, layout) };
+                    return;
+                }
 
-                // Update the tail page offset to point to the new page
+                // Move the tail to the new page unless an allocation on it
+                // already has
                 self.tail_page_offset
                     .0
-                    .store(PageOffset::new(page_idx as u32, 0).0, Ordering::Release);
+                    .fetch_max(PageOffset::new(page_idx as u32, 0).0, Ordering::AcqRel);
+            }
+        }
+    }
+
+    /// Places pages allocated from now on per `numa`, and moves those
+    /// already in memory the same way.
+    pub(crate) fn set_numa(&mut self, numa: Option<NumaPlacer>) {
+        self.numa = numa;
+        if let Some(numa) = &self.numa {
+            for page in self.pages.iter() {
+                let page = page.load(Ordering::Acquire);
+                if !page.is_null() {
+                    unsafe { numa.place_page(page, self.page_size as usize) };
+                }
+            }
+        }
+    }
+
+    /// Drops the in-memory copies of the pages below the page of `new_head`
+    /// and moves the head address to its start. Only pages that are wholly
+    /// flushed and behind the tail page are dropped, so the head may stop
+    /// short of `new_head`; records below it are then read with
+    /// [`read_from_disk`](Self::read_from_disk). A dropped page is freed once
+    /// every thread protected by the epoch at the time has left it. Returns
+    /// the head address.
+    ///
+    /// # Safety
+    /// Every thread that reads or writes the pages must hold an epoch guard
+    /// from before it loads a page until it is done with it.
+    pub unsafe fn shift_head_address(&self, new_head: Address) -> Address {
+        let flushed = self.flushed_until_address.load(Ordering::Acquire);
+        let tail = self.get_tail_address();
+        let target = Address::new(new_head.page().min(flushed.page()).min(tail.page()), 0);
+        let (Some(epoch), Ok(layout)) = (
+            self.epoch,
+            Layout::from_size_align(self.page_size as usize, 64),
+        ) else {
+            return self.get_head_address();
+        };
+
+        let mut head = self.head_address.load(Ordering::Acquire);
+        loop {
+            if head >= target {
+                return head;
+            }
+            match self.head_address.compare_exchange(
+                head,
+                target,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual) => head = actual,
+            }
+        }
+
+        // Moving the head claimed the pages between the old and new heads.
+        let guard = epoch.protect();
+        for page in head.page()..target.page() {
+            let evicted = self.pages[page as usize].swap(ptr::null_mut(), Ordering::AcqRel);
+            if !evicted.is_null() {
+                unsafe {
+                    guard.defer_unchecked(move || {
+                        crate::core::alloc::aligned_free(evicted, layout);
+                    });
+                }
+            }
+        }
+        guard.flush();
+        target
+    }
+
+    /// Reads `buffer.len()` bytes of the flushed log at `address` from disk,
+    /// for records whose page is no longer in memory. Reads of a page read
+    /// back in with [`admit_page`](Self::admit_page) are served from its
+    /// copy, and others go through the page cache when there is one.
+    pub fn read_from_disk(&self, address: Address, buffer: &mut [u8]) -> Result<(), Status> {
+        let flushed = self.flushed_until_address.load(Ordering::Acquire).control();
+        if address.control() + buffer.len() as u64 > flushed {
+            return Err(Status::UnexpectedState);
+        }
+        if let Some(page) = self.admitted_page(address.page()) {
+            let offset = address.offset() as usize;
+            if let Some(bytes) = page.get(offset..offset + buffer.len()) {
+                buffer.copy_from_slice(bytes);
+                return Ok(());
+            }
+        }
+        let disk = self.disk.as_ref().ok_or(Status::IoError)?;
+        let load = |offset: u64, bytes: &mut [u8]| match disk.lock() {
+            Ok(mut disk) => disk.read_sync(offset, bytes),
+            Err(_) => Status::InternalError,
+        };
+        let status = match &self.page_cache {
+            Some(cache) => cache.read(address.control(), buffer, flushed, load),
+            None => load(address.control(), buffer),
+        };
+        match status {
+            Status::Ok => Ok(()),
+            status => Err(status),
+        }
+    }
+
+    /// Reads through the page cache `cache`, or straight from disk with
+    /// `None`. Background prefetching stops, as it fills the old cache.
+    pub(crate) fn set_page_cache(&mut self, cache: Option<PageCache>) {
+        self.prefetcher = None;
+        self.page_cache = cache.map(Arc::new);
+    }
+
+    /// Prefetches pages into the page cache on a thread of their own, at
+    /// most `max_outstanding` at a time, or stops with `None`. Does nothing
+    /// without a page cache.
+    pub(crate) fn set_prefetch(&mut self, max_outstanding: Option<usize>)
+    where
+        D: Send + 'static,
+    {
+        self.prefetcher = None;
+        if let (Some(max_outstanding), Some(cache), Some(disk)) =
+            (max_outstanding, &self.page_cache, &self.disk)
+        {
+            self.prefetcher = Some(Prefetcher::start(
+                cache.clone(),
+                disk.clone(),
+                max_outstanding,
+            ));
+        }
+    }
+
+    /// Starts reading the flushed page cache page holding `address` in the
+    /// background, if prefetching is on.
+    pub fn prefetch(&self, address: Address) {
+        if let Some(prefetcher) = &self.prefetcher {
+            let flushed = self.flushed_until_address.load(Ordering::Acquire);
+            prefetcher.prefetch(address.control(), flushed.control());
+        }
+    }
+
+    /// Reads log page `page`, below the head address, back into memory, so
+    /// reads of its records no longer go to disk. Returns the bytes read.
+    pub fn admit_page(&self, page: u32) -> Result<u64, Status> {
+        if page >= self.get_head_address().page() {
+            return Err(Status::UnexpectedState);
+        }
+        let mut bytes = vec![0u8; self.page_size as usize];
+        {
+            let disk = self.disk.as_ref().ok_or(Status::IoError)?;
+            let mut disk = disk.lock().map_err(|_| Status::InternalError)?;
+            let status = disk.read_sync(Address::new(page, 0).control(), &mut bytes);
+            if status != Status::Ok {
+                return Err(status);
+            }
+        }
+        let mut admitted = self
+            .admitted_pages
+            .write()
+            .map_err(|_| Status::InternalError)?;
+        admitted.insert(page, bytes.into());
+        Ok(self.page_size)
+    }
+
+    /// Drops the copy of a page read back in with
+    /// [`admit_page`](Self::admit_page). Returns whether there was one.
+    pub fn release_page(&self, page: u32) -> bool {
+        self.admitted_pages
+            .write()
+            .is_ok_and(|mut admitted| admitted.remove(&page).is_some())
+    }
+
+    /// Pages read back in with [`admit_page`](Self::admit_page), in order.
+    pub fn admitted_pages(&self) -> Vec<u32> {
+        let mut pages: Vec<u32> = match self.admitted_pages.read() {
+            Ok(admitted) => admitted.keys().copied().collect(),
+            Err(_) => Vec::new(),
+        };
+        pages.sort_unstable();
+        pages
+    }
+
+    fn admitted_page(&self, page: u32) -> Option<Arc<[u8]>> {
+        self.admitted_pages.read().ok()?.get(&page).cloned()
+    }
+
+    /// Writes the log between the flushed-until address and the tail to disk.
+    /// File offsets equal logical addresses. The log is first frozen up to
+    /// the tail with [`freeze_until`](Self::freeze_until), so flushed records
+    /// are never updated in place again, nor while they are written. With
+    /// `durable` set, the log is synced before returning. Returns the new
+    /// flushed-until address.
+    pub fn flush(&self, durable: bool) -> Result<Address, Status> {
+        let until = self.get_tail_address();
+        self.freeze_until(until);
+
+        let disk = self.disk.as_ref().ok_or(Status::IoError)?;
+        let mut disk = disk.lock().map_err(|_| Status::InternalError)?;
+
+        let from = self.flushed_until_address.load(Ordering::Acquire);
+        let flushed = if from < until {
+            self.write_to_disk(&mut *disk, from, until)?
+        } else {
+            from
+        };
+        if durable {
+            let status = disk.sync_log();
+            if status != Status::Ok {
+                return Err(status);
+            }
+        }
+        Ok(flushed)
+    }
+
+    /// Writes `[from, until)` page by page, then appends its flush frame.
+    fn write_to_disk(
+        &self,
+        disk: &mut D,
+        mut from: Address,
+        until: Address,
+    ) -> Result<Address, Status> {
+        let mut frame = FlushFrame {
+            begin: from.control(),
+            end: until.control(),
+            data_crc: 0,
+        };
+        while from < until {
+            let page_end = if from.page() == until.page() {
+                until.offset() as u64
+            } else {
+                self.page_size
+            };
+            let len = page_end.saturating_sub(from.offset() as u64) as usize;
+            if len > 0 {
+                let data = self.get_slice(from, len);
+                if data.is_empty() {
+                    return Err(Status::IoError);
+                }
+                let status = Self::write_flush_span(disk, from.control(), data);
+                if status != Status::Ok {
+                    return Err(status);
+                }
+                frame.data_crc = crc32_update(frame.data_crc, data);
+            }
+            if from.page() == until.page() {
+                break;
+            }
+            from = Address::new(from.page() + 1, 0);
+        }
+        kill_point!(AfterLogAppend);
+        let status = disk.append_frame(&frame.encode());
+        if status != Status::Ok {
+            return Err(status);
+        }
+
+        self.flushed_until_address.store(until, Ordering::Release);
+        Ok(until)
+    }
+
+    /// Write one page's worth of flushed bytes. Crash tests split the write
+    /// in two so that the `MidFlush` kill point leaves a torn page behind.
+    #[cfg(any(test, feature = "killpoints"))]
+    fn write_flush_span(disk: &mut D, offset: u64, data: &[u8]) -> Status {
+        let (first, second) = data.split_at(data.len() / 2);
+        let status = disk.write_async(offset, first, Box::new(|_| {}));
+        if status != Status::Ok {
+            return status;
+        }
+        kill_point!(MidFlush);
+        disk.write_async(offset + first.len() as u64, second, Box::new(|_| {}))
+    }
+
+    /// Write one page's worth of flushed bytes.
+    #[cfg(not(any(test, feature = "killpoints")))]
+    fn write_flush_span(disk: &mut D, offset: u64, data: &[u8]) -> Status {
+        disk.write_async(offset, data, Box::new(|_| {}))
+    }
+
+    /// CRC of the log bytes `frame` covers, read through `buffer`.
+    fn frame_data_crc(disk: &mut D, frame: &FlushFrame, buffer: &mut [u8]) -> Result<u32, Status> {
+        let mut crc = 0;
+        let mut offset = frame.begin;
+        while offset < frame.end {
+            let len = ((frame.end - offset) as usize).min(buffer.len());
+            let status = disk.read_sync(offset, &mut buffer[..len]);
+            if status != Status::Ok {
+                return Err(status);
+            }
+            crc = crc32_update(crc, &buffer[..len]);
+            offset += len as u64;
+        }
+        Ok(crc)
+    }
+
+    /// Checks the CRC of every `sample_every`-th flush frame against the log
+    /// without changing anything on disk. Returns the number of frames checked
+    /// and the `[begin, end)` ranges that failed, including a frame that runs
+    /// past the end of the log. Scanning stops at a frame that does not decode.
+    pub fn verify_frames(&self, sample_every: usize) -> Result<(u64, Vec<(u64, u64)>), Status> {
+        let disk = self.disk.as_ref().ok_or(Status::IoError)?;
+        let mut disk = disk.lock().map_err(|_| Status::InternalError)?;
+        let frames = disk.read_frames()?;
+        let file_size = disk.log_size();
+
+        let mut checked = 0;
+        let mut failed = Vec::new();
+        let mut buffer = vec![0u8; 1 << 20];
+        for bytes in frames.chunks(FLUSH_FRAME_SIZE).step_by(sample_every.max(1)) {
+            let Some(frame) = FlushFrame::decode(bytes) else {
+                break;
+            };
+            checked += 1;
+            if frame.end < frame.begin
+                || frame.end > file_size
+                || Self::frame_data_crc(&mut disk, &frame, &mut buffer)? != frame.data_crc
+            {
+                failed.push((frame.begin, frame.end));
             }
         }
+        Ok((checked, failed))
+    }
+
+    /// Returns the end of the last flush frame whose data verifies. The scan
+    /// stops at the first frame that is torn, fails its CRC, or runs past the
+    /// end of the log. Anything after that point is truncated.
+    fn truncate_torn_tail(disk: &mut D) -> Result<LoadedLog, Status> {
+        let frames = disk.read_frames()?;
+        let file_size = disk.log_size();
+
+        let mut valid_end = 0u64;
+        let mut valid_frames = 0usize;
+        let mut buffer = vec![0u8; 1 << 20];
+        for bytes in frames.chunks(FLUSH_FRAME_SIZE) {
+            let Some(frame) = FlushFrame::decode(bytes) else {
+                break;
+            };
+            if frame.begin > valid_end || frame.end < frame.begin || frame.end > file_size {
+                break;
+            }
+            if Self::frame_data_crc(disk, &frame, &mut buffer)? != frame.data_crc {
+                break;
+            }
+            valid_end = frame.end;
+            valid_frames += 1;
+        }
+
+        let frames_size = valid_frames * FLUSH_FRAME_SIZE;
+        if valid_end < file_size || frames_size < frames.len() {
+            log::warn!(
+                "torn log tail at byte offset {}: discarding {} log bytes",
+                valid_end,
+                file_size.saturating_sub(valid_end)
+            );
+            let status = disk.truncate_log(valid_end, frames_size as u64);
+            if status != Status::Ok {
+                return Err(status);
+            }
+        }
+        Ok(LoadedLog {
+            size: valid_end,
+            torn_bytes: file_size.saturating_sub(valid_end),
+        })
+    }
+
+    /// Reads the on-disk log back into memory at its original addresses,
+    /// first cutting off any torn tail.
+    pub fn load_from_disk(&self) -> Result<LoadedLog, Status> {
+        let disk = self.disk.as_ref().ok_or(Status::IoError)?;
+        let mut disk = disk.lock().map_err(|_| Status::InternalError)?;
+
+        let loaded = Self::truncate_torn_tail(&mut disk)?;
+        let file_size = loaded.size;
+        let mut page = 0u32;
+        while (page as u64) * self.page_size < file_size {
+            if page as usize >= self.pages.len() {
+                // The log is larger than the in-memory buffer.
+                return Err(Status::OutOfMemory);
+            }
+            self.new_page(Address::new(page, 0));
+
+            let start = page as u64 * self.page_size;
+            let len = (file_size - start).min(self.page_size) as usize;
+            let buffer = unsafe { self.get_mut_slice_unchecked(Address::new(page, 0), len) };
+            if buffer.is_empty() {
+                return Err(Status::OutOfMemory);
+            }
+            let status = disk.read_sync(start, buffer);
+            if status != Status::Ok {
+                return Err(status);
+            }
+            page += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Size of the on-disk log in bytes.
+    pub fn disk_log_size(&self) -> u64 {
+        match self.disk.as_ref().map(|disk| disk.lock()) {
+            Some(Ok(disk)) => disk.log_size(),
+            _ => 0,
+        }
+    }
+
+    /// Resets the tail after the log has been reloaded. Everything below
+    /// `tail` is treated as flushed and read-only.
+    pub fn restore_tail(&self, tail: Address) {
+        let tail = if tail.control() < Self::K_FIRST_VALID_ADDRESS {
+            Address::from_control(Self::K_FIRST_VALID_ADDRESS)
+        } else {
+            tail
+        };
+        self.tail_page_offset.0.store(
+            PageOffset::new(tail.page(), tail.offset() as u64).0,
+            Ordering::Release,
+        );
+        self.read_only_address.store(tail, Ordering::Release);
+        self.safe_read_only_address.store(tail, Ordering::Release);
+        self.flushed_until_address.store(tail, Ordering::Release);
     }
 
     pub fn checkpoint(&mut self, _disk: &mut D, _token: &str) -> Result<LogMetadata, Status> {
-        // Get current addresses
-        let flushed_address = self.flushed_until_address.load(Ordering::Acquire);
-        let final_address = self.head_address.load(Ordering::Acquire);
+        // Flush everything up to the tail; the checkpoint covers the whole log.
+        let flushed_address = self.flush(true)?;
+        let final_address = flushed_address;
 
         // Calculate approximate record count and data size
         // This is a simplified estimation - in a real implementation,
         // we would track these metrics more precisely
-        let data_size = final_address.control() - flushed_address.control();
+        let data_size = final_address
+            .control()
+            .saturating_sub(Self::K_FIRST_VALID_ADDRESS);
         let estimated_record_count = data_size / 64; // Rough estimate assuming average record size
 
         let mut metadata = LogMetadata::new(
@@ -435,6 +1196,7 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
             estimated_record_count,
             data_size,
         );
+        metadata.store_uuid = self.superblock.store_uuid;
 
         // Update checksum
         metadata.update_checksum();
@@ -447,14 +1209,133 @@ impl<'epoch, D: Disk> PersistentMemoryMalloc<'epoch, D> {
         _disk: &mut D,
         _token: &str,
         metadata: &LogMetadata,
-    ) -> Result<(), Status> {
-        // Simplified recover implementation
+    ) -> Result<LoadedLog, Status> {
+        // Simplified recover implementation: reload the whole log and cut
+        // the tail back to the checkpoint.
+        let loaded = self.load_from_disk()?;
         self.begin_address
             .store(Address::from_control(0), Ordering::Release);
+        // Every page was read back, so none is on disk only.
         self.head_address
-            .store(metadata.final_address, Ordering::Release);
-        self.flushed_until_address
-            .store(metadata.flushed_address, Ordering::Release);
-        Ok(())
+            .store(Address::from_control(0), Ordering::Release);
+        self.restore_tail(metadata.final_address);
+        Ok(loaded)
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    /// A log of two small pages whose tail sits at the start of page 1,
+    /// which is not allocated yet.
+    fn log_before_page_one() -> PersistentMemoryMalloc<'static, NullDisk> {
+        let mut hlog = PersistentMemoryMalloc::new();
+        hlog.page_size = 4096;
+        hlog.pages = (0..2).map(|_| AtomicPtr::new(ptr::null_mut())).collect();
+        hlog.tail_page_offset = AtomicPageOffset::new(Address::new(1, 64));
+        hlog
+    }
+
+    fn append(hlog: &PersistentMemoryMalloc<'static, NullDisk>, value: u64) -> Address {
+        let address = hlog.allocate(8).unwrap();
+        unsafe { hlog.get_mut_slice_unchecked(address, 8) }.copy_from_slice(&value.to_le_bytes());
+        address
+    }
+
+    fn value_at(hlog: &PersistentMemoryMalloc<'static, NullDisk>, address: Address) -> u64 {
+        u64::from_le_bytes(hlog.get_slice(address, 8).try_into().unwrap())
+    }
+
+    static EPOCH: LightEpoch = LightEpoch {};
+
+    /// A one-page log under epoch protection, with nothing allocated yet.
+    fn empty_log() -> PersistentMemoryMalloc<'static, NullDisk> {
+        let mut hlog = PersistentMemoryMalloc::new();
+        hlog.page_size = 4096;
+        hlog.initialize(4096, &EPOCH, NullDisk);
+        hlog
+    }
+
+    #[test]
+    fn reservations_crossing_a_page_boundary_do_not_overlap() {
+        loom::model(|| {
+            // 8 bytes are left on page 0, so both reservations move to page 1
+            let start = Address::new(0, Address::K_MAX_OFFSET - 7);
+            let tail = Arc::new(AtomicPageOffset::new(start));
+            let other = tail.clone();
+            let theirs = thread::spawn(move || other.reserve(2));
+            let mine = tail.reserve(2);
+            let theirs = theirs.join().unwrap();
+
+            let (first, second) = (mine.0.min(theirs.0), mine.0.max(theirs.0));
+            assert!(first + 16 <= second, "{:?} overlaps {:?}", mine, theirs);
+            assert!(tail.load().0 >= second + 16);
+        });
+    }
+
+    #[test]
+    fn allocations_racing_to_open_a_page_share_it() {
+        loom::model(|| {
+            let hlog = Arc::new(log_before_page_one());
+            let other = hlog.clone();
+            let theirs = thread::spawn(move || append(&other, 1));
+            let mine = append(&hlog, 2);
+            let theirs = theirs.join().unwrap();
+
+            assert_ne!(mine, theirs);
+            assert_eq!(value_at(&hlog, theirs), 1);
+            assert_eq!(value_at(&hlog, mine), 2);
+        });
+    }
+
+    #[test]
+    fn flush_racing_an_append_sees_the_whole_record() {
+        loom::model(|| {
+            let hlog = Arc::new(empty_log());
+            // Stands in for the record body, which loom cannot see in the log
+            let body = Arc::new(AtomicU64::new(0));
+            let (other, written) = (hlog.clone(), body.clone());
+            let flusher = thread::spawn(move || {
+                let flushed = other.flush(false).unwrap();
+                (flushed, written.load(Ordering::Relaxed))
+            });
+            // Writers fill a record under the guard they allocated it in
+            let address = {
+                let _guard = EPOCH.protect();
+                let address = hlog.allocate(8).unwrap();
+                body.store(7, Ordering::Relaxed);
+                address
+            };
+            let (flushed, seen) = flusher.join().unwrap();
+
+            if address < flushed {
+                assert_eq!(seen, 7);
+            }
+        });
+    }
+
+    #[test]
+    fn freezing_waits_out_a_writer_in_the_mutable_region() {
+        loom::model(|| {
+            let hlog = Arc::new(empty_log());
+            let address = append(&hlog, 1);
+            let other = hlog.clone();
+            let writer = thread::spawn(move || {
+                let _guard = EPOCH.protect();
+                if address >= other.get_read_only_address() {
+                    unsafe { other.get_mut_slice_unchecked(address, 8) }
+                        .copy_from_slice(&2u64.to_le_bytes());
+                }
+            });
+            hlog.freeze_until(hlog.get_tail_address());
+            let frozen = value_at(&hlog, address);
+            writer.join().unwrap();
+
+            // No update lands once the record is frozen
+            assert_eq!(value_at(&hlog, address), frozen);
+        });
     }
 }
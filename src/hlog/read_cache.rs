@@ -0,0 +1,304 @@
+//! Read cache: copies of hot records read from disk, kept in memory.
+//!
+//! A cached copy sits in front of its key's hash chain. The index entry
+//! points at the copy with the readcache bit set, and the copy keeps the
+//! main-log address the entry held before, so a read of another key in the
+//! chain carries on from there and evicting the copy restores the entry.
+//!
+//! Copies are appended to a ring of record slots, so the oldest copy is the
+//! one evicted, as in the circular log FASTER keeps its read cache in. Cache
+//! addresses count up from 1 and are never reused, which tells an entry that
+//! still points at an evicted copy apart from the copy that replaced it. A
+//! copy is published to the index and evicted from it with its slot locked,
+//! so an entry never leads to a slot that holds another copy for longer than
+//! a reader needs to notice and look the entry up again.
+
+use crate::core::address::Address;
+use crate::performance::access_analyzer::Heat;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Read cache settings of a store, see [`RsKv::set_read_cache`].
+///
+/// [`RsKv::set_read_cache`]: crate::rskv_core::RsKv::set_read_cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadCacheConfig {
+    /// Records the cache holds before the oldest copy is evicted
+    pub capacity: usize,
+    /// Coldest key heat admitted when an access analyzer is attached.
+    /// Without one, every record read from disk is admitted.
+    pub min_heat: Heat,
+}
+
+impl Default for ReadCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1 << 16,
+            min_heat: Heat::Warm,
+        }
+    }
+}
+
+/// Counters of a read cache since it was configured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadCacheStats {
+    pub capacity: u64,
+    /// Copies in the cache now
+    pub resident: u64,
+    /// Reads answered from a cached copy
+    pub hits: u64,
+    /// Reads that went to disk
+    pub misses: u64,
+    /// Copies published to the index
+    pub admitted: u64,
+    /// Records read from disk but too cold to admit
+    pub rejected: u64,
+    /// Copies evicted to make room
+    pub evicted: u64,
+}
+
+impl ReadCacheStats {
+    /// Share of reads below the in-memory log answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            0.0
+        } else {
+            self.hits as f64 / reads as f64
+        }
+    }
+}
+
+/// A record copied into the cache
+pub(crate) struct CachedRecord<K, V> {
+    pub key_hash: u64,
+    pub key: K,
+    pub value: V,
+    /// Main-log address the index entry held before the copy was published
+    pub previous: Address,
+}
+
+struct Slot<K, V> {
+    /// Cache address of the copy, 0 while the slot is empty
+    address: u64,
+    record: Option<CachedRecord<K, V>>,
+}
+
+pub(crate) struct ReadCache<K, V> {
+    config: ReadCacheConfig,
+    slots: Box<[Mutex<Slot<K, V>>]>,
+    /// Cache address of the newest copy
+    tail: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    admitted: AtomicU64,
+    rejected: AtomicU64,
+    evicted: AtomicU64,
+}
+
+impl<K, V> ReadCache<K, V> {
+    pub(crate) fn new(config: ReadCacheConfig) -> Self {
+        let slots = (0..config.capacity.max(1))
+            .map(|_| {
+                Mutex::new(Slot {
+                    address: 0,
+                    record: None,
+                })
+            })
+            .collect();
+        Self {
+            config,
+            slots,
+            tail: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            admitted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    fn slot(&self, address: u64) -> std::sync::MutexGuard<'_, Slot<K, V>> {
+        let slot = &self.slots[(address % self.slots.len() as u64) as usize];
+        slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Calls `f` with the copy at `address`, or returns `None` if it has
+    /// been evicted.
+    pub(crate) fn with_copy<R>(
+        &self,
+        address: Address,
+        f: impl FnOnce(&CachedRecord<K, V>) -> R,
+    ) -> Option<R> {
+        let slot = self.slot(address.control());
+        match &slot.record {
+            Some(record) if slot.address == address.control() => Some(f(record)),
+            _ => None,
+        }
+    }
+
+    /// Main-log address behind the copy at `address`, unless it has been
+    /// evicted.
+    pub(crate) fn previous_address(&self, address: Address) -> Option<Address> {
+        self.with_copy(address, |record| record.previous)
+    }
+
+    pub(crate) fn note_hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn note_miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether a key at `heat` is hot enough to admit, with `None` when no
+    /// analyzer is attached.
+    pub(crate) fn admits(&self, heat: Option<Heat>) -> bool {
+        let admitted = heat.is_none_or(|heat| heat >= self.config.min_heat);
+        if !admitted {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        admitted
+    }
+
+    /// Appends `record` at the next cache address. The copy in its slot is
+    /// handed to `evict` first, then `publish` is called with the new
+    /// address to point the index at it, all with the slot locked. If
+    /// `publish` fails the copy is dropped again. A slot already taken by a
+    /// newer copy is left alone. Returns whether the copy was published.
+    pub(crate) fn insert(
+        &self,
+        record: CachedRecord<K, V>,
+        evict: impl FnOnce(Address, &CachedRecord<K, V>),
+        publish: impl FnOnce(Address) -> bool,
+    ) -> bool {
+        let address = self.tail.fetch_add(1, Ordering::AcqRel) + 1;
+        let mut slot = self.slot(address);
+        if slot.address > address {
+            return false;
+        }
+        if let Some(victim) = slot.record.take() {
+            evict(Address::from_control(slot.address), &victim);
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+        slot.address = address;
+        slot.record = Some(record);
+        if !publish(Address::from_control(address)) {
+            slot.record = None;
+            return false;
+        }
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Empties the cache, handing every copy to `evict`.
+    pub(crate) fn drain(&self, mut evict: impl FnMut(Address, &CachedRecord<K, V>)) {
+        for slot in self.slots.iter() {
+            let mut slot = slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(record) = slot.record.take() {
+                evict(Address::from_control(slot.address), &record);
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ReadCacheStats {
+        let resident = self
+            .slots
+            .iter()
+            .filter(|slot| {
+                slot.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .record
+                    .is_some()
+            })
+            .count();
+        ReadCacheStats {
+            capacity: self.slots.len() as u64,
+            resident: resident as u64,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            admitted: self.admitted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copy(key: u64, previous: u64) -> CachedRecord<u64, u64> {
+        CachedRecord {
+            key_hash: key,
+            key,
+            value: key * 10,
+            previous: Address::from_control(previous),
+        }
+    }
+
+    #[test]
+    fn test_oldest_copy_is_evicted_and_addresses_are_not_reused() {
+        let cache = ReadCache::new(ReadCacheConfig {
+            capacity: 2,
+            ..Default::default()
+        });
+        let mut published = Vec::new();
+        for key in 1..=2 {
+            assert!(cache.insert(
+                copy(key, 100 + key),
+                |_, _| panic!(),
+                |address| {
+                    published.push(address);
+                    true
+                }
+            ));
+        }
+        assert_eq!(
+            cache.with_copy(published[0], |record| record.value),
+            Some(10)
+        );
+
+        let mut evicted = Vec::new();
+        assert!(cache.insert(
+            copy(3, 103),
+            |address, record| evicted.push((address, record.key, record.previous)),
+            |_| true
+        ));
+        assert_eq!(evicted, vec![(published[0], 1, Address::from_control(101))]);
+        // The slot of key 1 now holds key 3 at a new address.
+        assert_eq!(cache.previous_address(published[0]), None);
+        assert_eq!(
+            cache.previous_address(Address::from_control(3)),
+            Some(Address::from_control(103))
+        );
+
+        // A copy the index refused is dropped again.
+        assert!(!cache.insert(copy(4, 104), |_, _| {}, |_| false));
+        assert_eq!(cache.previous_address(Address::from_control(4)), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.capacity, stats.resident), (2, 1));
+        assert_eq!((stats.admitted, stats.evicted), (3, 2));
+
+        let mut drained = Vec::new();
+        cache.drain(|_, record| drained.push(record.key));
+        assert_eq!(drained, vec![3]);
+        assert_eq!(cache.stats().resident, 0);
+    }
+
+    #[test]
+    fn test_admission_by_heat_and_hit_rate() {
+        let cache = ReadCache::<u64, u64>::new(ReadCacheConfig::default());
+        assert!(cache.admits(None));
+        assert!(cache.admits(Some(Heat::Hot)));
+        assert!(!cache.admits(Some(Heat::Cold)));
+        cache.note_hit();
+        cache.note_hit();
+        cache.note_hit();
+        cache.note_miss();
+        let stats = cache.stats();
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.hit_rate(), 0.75);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::core::status::Status;
+use crate::core::utility::crc32_update;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identifies an rskv log file.
+pub const LOG_MAGIC: [u8; 8] = *b"RSKVLOG\0";
+/// Bumped whenever the on-disk record layout changes. Version 2 added the
+/// write time to the record header.
+pub const LOG_FORMAT_VERSION: u32 = 2;
+/// The newest format version whose record headers have no write time.
+pub const UNTIMED_FORMAT_VERSION: u32 = 1;
+/// The superblock fills the first cache line of the log, which is never
+/// handed out to records.
+pub const SUPERBLOCK_SIZE: usize = 64;
+
+/// Header at offset 0 of the log file, written when the store is created and
+/// validated whenever it is opened again.
+///
+/// Layout (little endian): magic `[0, 8)`, format version `[8, 12)`, page
+/// size `[16, 24)`, creation time in seconds `[24, 32)`, store UUID
+/// `[32, 48)`, CRC32 of `[0, 48)` at `[48, 52)`. Everything else is zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Superblock {
+    pub format_version: u32,
+    pub page_size: u64,
+    pub created_at: u64,
+    pub store_uuid: [u8; 16],
+}
+
+impl Superblock {
+    /// A superblock for a newly created store with a random UUID.
+    pub fn new(page_size: u64) -> Self {
+        Self {
+            format_version: LOG_FORMAT_VERSION,
+            page_size,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            store_uuid: rand::random(),
+        }
+    }
+
+    /// The format version recorded in `bytes`, if they hold a superblock
+    /// with a valid checksum, whether or not that version is supported.
+    pub fn format_version(bytes: &[u8]) -> Option<u32> {
+        if bytes.len() < SUPERBLOCK_SIZE || bytes[0..8] != LOG_MAGIC {
+            return None;
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        (u32_at(48) == crc32_update(0, &bytes[0..48])).then(|| u32_at(8))
+    }
+
+    pub fn encode(&self) -> [u8; SUPERBLOCK_SIZE] {
+        let mut bytes = [0u8; SUPERBLOCK_SIZE];
+        bytes[0..8].copy_from_slice(&LOG_MAGIC);
+        bytes[8..12].copy_from_slice(&self.format_version.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.page_size.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.created_at.to_le_bytes());
+        bytes[32..48].copy_from_slice(&self.store_uuid);
+        let crc = crc32_update(0, &bytes[0..48]);
+        bytes[48..52].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes and validates a superblock read from a log that is expected to
+    /// use `page_size`. Each kind of mismatch is logged and maps to its own
+    /// status: `InvalidDataFormat` for a file that is not an rskv log,
+    /// `VersionMismatch` for an unsupported format, `Corruption` for a bad
+    /// checksum and `InvalidConfiguration` for a different page size.
+    pub fn decode(bytes: &[u8], page_size: u64) -> Result<Self, Status> {
+        if bytes.len() < SUPERBLOCK_SIZE || bytes[0..8] != LOG_MAGIC {
+            log::error!("not an rskv log: missing magic number at offset 0");
+            return Err(Status::InvalidDataFormat);
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+
+        let format_version = u32_at(8);
+        if format_version <= UNTIMED_FORMAT_VERSION {
+            log::error!(
+                "log format version {} predates record write times; migrate it first",
+                format_version
+            );
+            return Err(Status::VersionMismatch);
+        }
+        if format_version != LOG_FORMAT_VERSION {
+            log::error!(
+                "log format version {} is not supported (expected {})",
+                format_version,
+                LOG_FORMAT_VERSION
+            );
+            return Err(Status::VersionMismatch);
+        }
+        if u32_at(48) != crc32_update(0, &bytes[0..48]) {
+            log::error!("log superblock checksum mismatch");
+            return Err(Status::Corruption);
+        }
+        let superblock = Self {
+            format_version,
+            page_size: u64_at(16),
+            created_at: u64_at(24),
+            store_uuid: bytes[32..48].try_into().unwrap(),
+        };
+        if superblock.page_size != page_size {
+            log::error!(
+                "log was written with page size {} but is opened with {}",
+                superblock.page_size,
+                page_size
+            );
+            return Err(Status::InvalidConfiguration);
+        }
+        Ok(superblock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superblock_roundtrip_and_mismatches() {
+        let superblock = Superblock::new(4096);
+        let bytes = superblock.encode();
+        assert_eq!(Superblock::decode(&bytes, 4096), Ok(superblock));
+        assert_ne!(Superblock::new(4096).store_uuid, superblock.store_uuid);
+
+        assert_eq!(
+            Superblock::decode(&bytes, 8192),
+            Err(Status::InvalidConfiguration)
+        );
+        assert_eq!(
+            Superblock::decode(&[0u8; SUPERBLOCK_SIZE], 4096),
+            Err(Status::InvalidDataFormat)
+        );
+
+        let mut newer = bytes;
+        newer[8..12].copy_from_slice(&(LOG_FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            Superblock::decode(&newer, 4096),
+            Err(Status::VersionMismatch)
+        );
+
+        let mut older = Superblock {
+            format_version: UNTIMED_FORMAT_VERSION,
+            ..superblock
+        }
+        .encode();
+        assert_eq!(
+            Superblock::decode(&older, 4096),
+            Err(Status::VersionMismatch)
+        );
+        assert_eq!(
+            Superblock::format_version(&older),
+            Some(UNTIMED_FORMAT_VERSION)
+        );
+        older[40] ^= 1;
+        assert_eq!(Superblock::format_version(&older), None);
+
+        let mut flipped = bytes;
+        flipped[40] ^= 1;
+        assert_eq!(Superblock::decode(&flipped, 4096), Err(Status::Corruption));
+    }
+}
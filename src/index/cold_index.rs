@@ -1,7 +1,8 @@
 use crate::core::address::Address;
 use crate::core::status::Status;
 use crate::device::file_system_disk::FileSystemDisk;
-use crate::rskv_core::{RsKv, ReadContext, RmwContext};
+use crate::environment::file::sync_directory;
+use crate::rskv_core::{RecoveryOptions, RsKv, ReadContext, RmwContext};
 use crate::index::IHashIndex;
 use crate::index::cold_index_contexts::{
     ColdIndexRmwContext, HashIndexChunkKey, HashIndexChunkValue,
@@ -9,6 +10,14 @@ use crate::index::cold_index_contexts::{
 use crate::index::hash_bucket::HashBucketEntry;
 use crate::index::key_hash::ColdLogKeyHash;
 use crate::index::mem_index::FindContext;
+use crate::repair::NewestRecords;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 struct ColdIndexRead<'a> {
     key: HashIndexChunkKey,
@@ -35,8 +44,84 @@ impl<'a> ReadContext for ColdIndexRead<'a> {
     }
 }
 
+/// What [`ColdIndex::compact`] did, for one run or summed over all runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ColdIndexCompactionStats {
+    /// Compactions completed
+    pub runs: u64,
+    /// Chunk records read from the index log
+    pub records_scanned: u64,
+    /// Chunks rewritten into the compacted log
+    pub chunks_kept: u64,
+    /// Entries carried over in the kept chunks
+    pub entries_kept: u64,
+    /// Entries left behind: held by superseded or deleted chunk records, or
+    /// pointing below the begin address
+    pub entries_dropped: u64,
+    /// Index log bytes before and after compaction
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    /// Time spent compacting
+    pub duration: Duration,
+}
+
+impl ColdIndexCompactionStats {
+    fn add(&mut self, run: &Self) {
+        self.runs += run.runs;
+        self.records_scanned += run.records_scanned;
+        self.chunks_kept += run.chunks_kept;
+        self.entries_kept += run.entries_kept;
+        self.entries_dropped += run.entries_dropped;
+        self.bytes_before += run.bytes_before;
+        self.bytes_after += run.bytes_after;
+        self.duration += run.duration;
+    }
+}
+
+/// The newest version of every chunk, with entries below the begin address
+/// cleared and empty chunks left out.
+struct ChunkScan {
+    live: Vec<(HashIndexChunkKey, HashIndexChunkValue)>,
+    records_scanned: u64,
+    entries_seen: u64,
+    entries_kept: u64,
+}
+
+type ChunkKv<'epoch> = RsKv<'epoch, HashIndexChunkKey, HashIndexChunkValue, FileSystemDisk>;
+
 pub struct ColdIndex<'epoch> {
-    internal_kv: RsKv<'epoch, HashIndexChunkKey, HashIndexChunkValue, FileSystemDisk>,
+    log_path: String,
+    /// Swapped for the compacted log under the write lock
+    internal_kv: RwLock<ChunkKv<'epoch>>,
+    stats: Mutex<ColdIndexCompactionStats>,
+}
+
+/// Handle to the background task that compacts a [`ColdIndex`]. The task
+/// stops when the handle is dropped.
+pub struct BackgroundColdIndexCompaction {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundColdIndexCompaction {
+    /// Stop the task and wait for the current pass to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for BackgroundColdIndexCompaction {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
 }
 
 impl RmwContext for ColdIndexRmwContext {
@@ -75,7 +160,8 @@ impl RmwContext for ColdIndexRmwContext {
 impl<'epoch> IHashIndex<'epoch> for ColdIndex<'epoch> {
     fn find_entry(&self, context: &mut FindContext) -> Status {
         let key_hash = ColdLogKeyHash::new(context.key_hash);
-        let table_size = self.internal_kv.get_table_size();
+        let internal_kv = self.kv();
+        let table_size = internal_kv.get_table_size();
         let chunk_id = key_hash.chunk_id(table_size);
         let tag = key_hash.tag_in_chunk();
 
@@ -89,7 +175,7 @@ impl<'epoch> IHashIndex<'epoch> for ColdIndex<'epoch> {
             tag_in_chunk: tag,
         };
 
-        let status = self.internal_kv.read(&mut read_context);
+        let status = internal_kv.read(&mut read_context);
         if status == Status::Ok && read_context.original_context.entry.unused() {
             return Status::NotFound;
         }
@@ -111,15 +197,231 @@ impl<'epoch> IHashIndex<'epoch> for ColdIndex<'epoch> {
 }
 
 impl<'epoch> ColdIndex<'epoch> {
+    const LOG_SIZE: u64 = 1 << 30;
+    const TABLE_SIZE: u64 = 1 << 20;
+    const CHECKPOINT_TOKEN: &'static str = "compacted";
+
+    /// Opens the index in `{log_path}/cold_index`. A compaction that was cut
+    /// short is first finished, if its compacted log was complete, or
+    /// discarded.
     pub fn new(log_path: &str) -> Result<Self, Status> {
-        let disk = FileSystemDisk::new(&format!("{}/cold_index", log_path))?;
-        let internal_kv = RsKv::new(1 << 30, 1 << 20, disk)?;
-        Ok(Self { internal_kv })
+        Self::resume_compaction(log_path)?;
+        let disk = FileSystemDisk::new(&Self::index_dir(log_path))?;
+        let internal_kv = RsKv::new(Self::LOG_SIZE, Self::TABLE_SIZE, disk)?;
+        Ok(Self {
+            log_path: log_path.to_string(),
+            internal_kv: RwLock::new(internal_kv),
+            stats: Mutex::new(ColdIndexCompactionStats::default()),
+        })
+    }
+
+    fn kv(&self) -> RwLockReadGuard<'_, ChunkKv<'epoch>> {
+        self.internal_kv.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn index_dir(log_path: &str) -> String {
+        format!("{}/cold_index", log_path)
+    }
+
+    fn staging_dir(log_path: &str) -> String {
+        format!("{}/cold_index.compacting", log_path)
+    }
+
+    fn previous_dir(log_path: &str) -> String {
+        format!("{}/cold_index.precompact", log_path)
+    }
+
+    fn journal_path(log_path: &str) -> String {
+        format!("{}/cold_index.journal", log_path)
+    }
+
+    /// Compaction totals since the index was opened.
+    pub fn compaction_stats(&self) -> ColdIndexCompactionStats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Share of the entries in the index log that compacting with
+    /// `begin_address` would drop, from 0.0 to 1.0. Reads the whole log.
+    pub fn dead_entry_ratio(&self, begin_address: Address) -> f64 {
+        let scan = Self::scan(&self.kv(), begin_address);
+        if scan.entries_seen == 0 {
+            return 0.0;
+        }
+        1.0 - scan.entries_kept as f64 / scan.entries_seen as f64
+    }
+
+    /// Rewrites the index log with only the newest version of each chunk,
+    /// clearing entries whose address fell below `begin_address` and leaving
+    /// out chunks with no entries left. Lookups and updates wait while this
+    /// runs.
+    ///
+    /// The compacted log is built in `cold_index.compacting` and swapped in
+    /// with two renames, the old log passing through `cold_index.precompact`.
+    /// A journal file is written before the first rename and removed after
+    /// the last step, so [`ColdIndex::new`] can finish a swap that a crash
+    /// interrupted.
+    pub fn compact(&self, begin_address: Address) -> Result<ColdIndexCompactionStats, Status> {
+        let started = Instant::now();
+        let mut internal_kv = self
+            .internal_kv
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let table_size = internal_kv.get_table_size();
+        let scan = Self::scan(&internal_kv, begin_address);
+        let mut run = ColdIndexCompactionStats {
+            runs: 1,
+            records_scanned: scan.records_scanned,
+            chunks_kept: scan.live.len() as u64,
+            entries_kept: scan.entries_kept,
+            entries_dropped: scan.entries_seen - scan.entries_kept,
+            bytes_before: Self::log_bytes(&internal_kv),
+            ..Default::default()
+        };
+
+        let staging = Self::staging_dir(&self.log_path);
+        if Path::new(&staging).exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        {
+            let disk = FileSystemDisk::new(&staging)?;
+            let mut staged = ChunkKv::new(Self::LOG_SIZE, table_size, disk)?;
+            staged.bulk_load(scan.live, HashIndexChunkKey::get_hash)?;
+            staged.checkpoint(Self::CHECKPOINT_TOKEN)?;
+        }
+
+        let journal = Self::journal_path(&self.log_path);
+        fs::write(&journal, b"swap\n")?;
+        sync_directory(&self.log_path)?;
+        Self::swap_in_staged(&self.log_path)?;
+        let options = RecoveryOptions {
+            log_size: Self::LOG_SIZE,
+            table_size,
+            ..Default::default()
+        };
+        *internal_kv = ChunkKv::open(
+            &Self::index_dir(&self.log_path),
+            Some(Self::CHECKPOINT_TOKEN),
+            &options,
+            HashIndexChunkKey::get_hash,
+        )?;
+        Self::finish_compaction(&self.log_path)?;
+
+        run.bytes_after = Self::log_bytes(&internal_kv);
+        run.duration = started.elapsed();
+        drop(internal_kv);
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add(&run);
+        log::info!(
+            "compacted cold index: kept {} of {} entries, {} -> {} log bytes",
+            run.entries_kept,
+            run.entries_kept + run.entries_dropped,
+            run.bytes_before,
+            run.bytes_after
+        );
+        Ok(run)
+    }
+
+    fn log_bytes(internal_kv: &ChunkKv<'epoch>) -> u64 {
+        let begin = internal_kv.hlog.begin_address.load(Ordering::Acquire);
+        internal_kv.hlog.get_tail_address().control() - begin.control()
+    }
+
+    fn scan(internal_kv: &ChunkKv<'epoch>, begin_address: Address) -> ChunkScan {
+        let occupied = |value: &HashIndexChunkValue| {
+            value
+                .bucket
+                .entries
+                .iter()
+                .filter(|entry| !entry.load().unused())
+                .count() as u64
+        };
+        let mut newest = NewestRecords::new();
+        let mut records_scanned = 0;
+        let mut entries_seen = 0;
+        internal_kv.for_each_log_record(|header, key, value| {
+            records_scanned += 1;
+            entries_seen += occupied(&value);
+            newest.insert(key.get_hash(), header, key, value);
+        });
+
+        let mut entries_kept = 0;
+        let live = newest
+            .into_live()
+            .0
+            .into_iter()
+            .filter_map(|(key, value, _)| {
+                for entry in &value.bucket.entries {
+                    let current = entry.load();
+                    if !current.unused() && current.address() < begin_address {
+                        entry.store(HashBucketEntry::default());
+                    }
+                }
+                let kept = occupied(&value);
+                entries_kept += kept;
+                (kept > 0).then_some((key, value))
+            })
+            .collect();
+        ChunkScan {
+            live,
+            records_scanned,
+            entries_seen,
+            entries_kept,
+        }
+    }
+
+    /// Moves the old log aside and the compacted one into its place. Each
+    /// rename is skipped if an earlier, interrupted run already made it.
+    fn swap_in_staged(log_path: &str) -> Result<(), Status> {
+        let current = Self::index_dir(log_path);
+        let staging = Self::staging_dir(log_path);
+        let previous = Self::previous_dir(log_path);
+        if !Path::new(&staging).exists() {
+            return Ok(());
+        }
+        if Path::new(&current).exists() {
+            if Path::new(&previous).exists() {
+                fs::remove_dir_all(&previous)?;
+            }
+            fs::rename(&current, &previous)?;
+        }
+        fs::rename(&staging, &current)?;
+        sync_directory(log_path)
+    }
+
+    fn finish_compaction(log_path: &str) -> Result<(), Status> {
+        let previous = Self::previous_dir(log_path);
+        if Path::new(&previous).exists() {
+            fs::remove_dir_all(&previous)?;
+        }
+        fs::remove_file(Self::journal_path(log_path))?;
+        sync_directory(log_path)
+    }
+
+    /// Completes the swap of a compaction that wrote its journal, or removes
+    /// the partial log of one that did not get that far.
+    fn resume_compaction(log_path: &str) -> Result<(), Status> {
+        if Path::new(&Self::journal_path(log_path)).exists() {
+            log::info!("finishing interrupted cold index compaction in {}", log_path);
+            Self::swap_in_staged(log_path)?;
+            return Self::finish_compaction(log_path);
+        }
+        let staging = Self::staging_dir(log_path);
+        if Path::new(&staging).exists() {
+            log::warn!("removing partial cold index compaction in {}", staging);
+            fs::remove_dir_all(&staging)?;
+        }
+        Ok(())
     }
 
     fn rmw_entry(&self, context: &FindContext, new_address: Address, is_create: bool) -> Status {
         let key_hash = ColdLogKeyHash::new(context.key_hash);
-        let table_size = self.internal_kv.get_table_size();
+        let internal_kv = self.kv();
+        let table_size = internal_kv.get_table_size();
         let chunk_id = key_hash.chunk_id(table_size);
         let tag = key_hash.tag_in_chunk();
 
@@ -140,6 +442,162 @@ impl<'epoch> ColdIndex<'epoch> {
             expected_entry: context.entry,
         };
 
-        self.internal_kv.rmw(&mut rmw_context)
+        internal_kv.rmw(&mut rmw_context)
+    }
+}
+
+impl ColdIndex<'static> {
+    /// Start a background thread that checks the index every `interval` and
+    /// compacts it once [`ColdIndex::dead_entry_ratio`] exceeds
+    /// `dead_entry_threshold`. `begin_address` gives the current begin
+    /// address of the log the index points into. The thread holds only a
+    /// weak reference and exits once the index is dropped.
+    pub fn start_background_compaction(
+        self: &Arc<Self>,
+        interval: Duration,
+        dead_entry_threshold: f64,
+        begin_address: impl Fn() -> Address + Send + 'static,
+    ) -> BackgroundColdIndexCompaction {
+        let stop = Arc::new(AtomicBool::new(false));
+        let index: Weak<Self> = Arc::downgrade(self);
+        let thread_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Acquire) {
+                match index.upgrade() {
+                    Some(index) => {
+                        let begin = begin_address();
+                        if index.dead_entry_ratio(begin) > dead_entry_threshold
+                            && let Err(status) = index.compact(begin)
+                        {
+                            log::warn!("cold index compaction failed: {:?}", status);
+                        }
+                    }
+                    None => break,
+                }
+                thread::park_timeout(interval);
+            }
+        });
+
+        BackgroundColdIndexCompaction {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir::TempDir;
+
+    /// A key hash landing in `chunk`, using the same bucket slot for reads
+    /// and writes.
+    fn key_hash(chunk: u64, slot: u64) -> u64 {
+        chunk | (slot << 48) | (slot << 51)
+    }
+
+    fn lookup(index: &ColdIndex<'_>, hash: u64) -> Option<Address> {
+        let mut context = FindContext::new(hash);
+        match index.find_entry(&mut context) {
+            Status::Ok => Some(context.entry.address()),
+            _ => None,
+        }
+    }
+
+    fn point(index: &ColdIndex<'_>, hash: u64, address: u64) {
+        let mut context = FindContext::new(hash);
+        let _ = index.find_entry(&mut context);
+        let status = index.try_update_entry(&context, Address::from_control(address), false);
+        assert_eq!(status, Status::Ok);
+    }
+
+    #[test]
+    fn test_compaction_drops_entries_below_begin_address() {
+        let temp = TempDir::new("cold_index_compact");
+        let dir = temp.path();
+        let index = ColdIndex::new(dir).unwrap();
+        for chunk in 0..4 {
+            for slot in 0..4 {
+                point(&index, key_hash(chunk, slot), 100 + chunk * 1000 + slot);
+            }
+        }
+        // Chunk 0 only ever points below the begin address used below.
+        for slot in 0..4 {
+            point(&index, key_hash(1, slot), 2000 + slot);
+        }
+
+        let begin = Address::from_control(1000);
+        assert!((index.dead_entry_ratio(begin) - 0.25).abs() < 1e-9);
+        let run = index.compact(begin).unwrap();
+        assert_eq!(run.runs, 1);
+        assert_eq!(run.chunks_kept, 12);
+        assert_eq!(run.entries_kept, 12);
+        assert_eq!(run.entries_dropped, 4);
+        assert_eq!(index.compaction_stats(), run);
+        assert_eq!(index.dead_entry_ratio(begin), 0.0);
+
+        for slot in 0..4 {
+            assert_eq!(lookup(&index, key_hash(0, slot)), None);
+            assert_eq!(
+                lookup(&index, key_hash(1, slot)),
+                Some(Address::from_control(2000 + slot))
+            );
+            assert_eq!(
+                lookup(&index, key_hash(3, slot)),
+                Some(Address::from_control(3100 + slot))
+            );
+        }
+        // The compacted index takes updates like the original.
+        point(&index, key_hash(0, 0), 5000);
+        assert_eq!(
+            lookup(&index, key_hash(0, 0)),
+            Some(Address::from_control(5000))
+        );
+        assert!(!Path::new(&ColdIndex::journal_path(dir)).exists());
+        assert!(!Path::new(&ColdIndex::previous_dir(dir)).exists());
+    }
+
+    #[test]
+    fn test_interrupted_compaction_is_finished_or_discarded() {
+        let temp = TempDir::new("cold_index_resume");
+        let dir = temp.path();
+        drop(ColdIndex::new(dir).unwrap());
+
+        // Staged log without a journal: the run did not finish staging.
+        fs::create_dir_all(ColdIndex::staging_dir(dir)).unwrap();
+        drop(ColdIndex::new(dir).unwrap());
+        assert!(!Path::new(&ColdIndex::staging_dir(dir)).exists());
+
+        // Journal written and the old log already moved aside.
+        let staging = ColdIndex::staging_dir(dir);
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(format!("{}/staged", staging), b"").unwrap();
+        fs::rename(ColdIndex::index_dir(dir), ColdIndex::previous_dir(dir)).unwrap();
+        fs::write(ColdIndex::journal_path(dir), b"swap\n").unwrap();
+        drop(ColdIndex::new(dir).unwrap());
+        assert!(Path::new(&format!("{}/staged", ColdIndex::index_dir(dir))).exists());
+        assert!(!Path::new(&ColdIndex::previous_dir(dir)).exists());
+        assert!(!Path::new(&ColdIndex::journal_path(dir)).exists());
+    }
+
+    #[test]
+    fn test_background_compaction_runs_past_threshold() {
+        let temp = TempDir::new("cold_index_background");
+        let dir = temp.path();
+        let index = Arc::new(ColdIndex::new(dir).unwrap());
+        for slot in 0..4 {
+            point(&index, key_hash(7, slot), 100 + slot);
+        }
+        let task = index.start_background_compaction(Duration::from_millis(10), 0.5, || {
+            Address::from_control(1000)
+        });
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while index.compaction_stats().runs == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        task.stop();
+        assert!(index.compaction_stats().runs >= 1);
+        assert_eq!(lookup(&index, key_hash(7, 0)), None);
     }
 }
@@ -1,11 +1,21 @@
+use crate::core::clock::{Clock, since_unix_epoch, system_clock};
 use crate::core::status::{Status, Result, ContextResult, ErrorContext, ResultExt};
 use crate::core::advanced_locking::{HierarchicalLockManager, LockId, LockIntent, LockGranularity};
 use crate::core::light_epoch::{LightEpoch, Guard};
-use std::sync::atomic::{AtomicU64, AtomicUsize, AtomicPtr, Ordering};
-use std::sync::{Arc, RwLock};
-// std::collections::HashMap removed as it's not used
+use crate::core::sync::{AtomicU8, AtomicU64, AtomicUsize, AtomicPtr};
+use crate::events::{EventDispatcher, EventHooks, HookStats, ResizeCompleted, ResizeEvent, StoreEvent};
+use crate::index::enhanced_overflow_management::{EnhancedOverflowManager, OverflowStatistics, OverflowStrategy};
+use crate::index::invariants::{IndexViolation, InvariantReport};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::ptr;
+use std::time::{Duration, Instant};
+
+/// Epoch handle of the overflow managers. `LightEpoch` is a handle to the
+/// global collector, so every table can share this one.
+static OVERFLOW_EPOCH: LightEpoch = LightEpoch {};
 
 /// Hash table bucket with overflow chaining
 #[repr(align(64))] // Cache line alignment
@@ -21,6 +31,12 @@ pub struct HashBucket<K, V> {
     last_access: AtomicU64,
 }
 
+impl<K, V> Default for HashBucket<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K, V> HashBucket<K, V> {
     pub const ENTRIES_PER_BUCKET: usize = 7; // Fits in cache line with metadata
 
@@ -35,12 +51,14 @@ impl<K, V> HashBucket<K, V> {
     }
 
     /// Get load factor for this bucket
+    #[allow(dead_code)]
     fn load_factor(&self) -> f32 {
         let count = self.entry_count.load(Ordering::Relaxed);
         count as f32 / Self::ENTRIES_PER_BUCKET as f32
     }
 
     /// Get access statistics
+    #[allow(dead_code)]
     fn access_stats(&self) -> (u64, u64) {
         (
             self.access_count.load(Ordering::Relaxed),
@@ -48,20 +66,49 @@ impl<K, V> HashBucket<K, V> {
         )
     }
 
-    /// Record access for statistics
-    fn record_access(&self) {
+    /// Record access at `now_secs` for statistics
+    fn record_access(&self, now_secs: u64) {
         self.access_count.fetch_add(1, Ordering::Relaxed);
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        self.last_access.store(now, Ordering::Relaxed);
+        self.last_access.store(now_secs, Ordering::Relaxed);
     }
 
     /// Get current entry count
     pub fn entry_count(&self) -> usize {
         self.entry_count.load(Ordering::Relaxed)
     }
+
+    pub(crate) fn slots(&self) -> &[AtomicPtr<HashEntry<K, V>>] {
+        &self.entries
+    }
+
+    pub(crate) fn overflow_link(&self) -> &AtomicPtr<HashBucket<K, V>> {
+        &self.overflow
+    }
+
+    /// Publishes `entry` in a free slot. Returns false if the bucket is full.
+    pub(crate) fn try_insert(&self, entry: *mut HashEntry<K, V>) -> bool {
+        for slot in &self.entries {
+            if slot
+                .compare_exchange(ptr::null_mut(), entry, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                self.entry_count.fetch_add(1, Ordering::Relaxed);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Empties slot `slot` if it still holds `entry`.
+    pub(crate) fn take_slot(&self, slot: usize, entry: *mut HashEntry<K, V>) -> bool {
+        let taken = self.entries[slot]
+            .compare_exchange(entry, ptr::null_mut(), Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        if taken {
+            self.entry_count.fetch_sub(1, Ordering::Relaxed);
+        }
+        taken
+    }
 }
 
 /// Hash table entry
@@ -70,19 +117,59 @@ pub struct HashEntry<K, V> {
     pub value: V,
     pub hash: u64,
     pub next: AtomicPtr<HashEntry<K, V>>,
+    /// Buckets past its home bucket that linear probing placed it
+    pub probe_distance: AtomicU8,
 }
 
 impl<K, V> HashEntry<K, V> {
-    fn new(key: K, value: V, hash: u64) -> Self {
+    pub fn new(key: K, value: V, hash: u64) -> Self {
         Self {
             key,
             value,
             hash,
             next: AtomicPtr::new(ptr::null_mut()),
+            probe_distance: AtomicU8::new(0),
         }
     }
 }
 
+/// Frees memory unlinked from the table once no epoch guard that might still
+/// reference it is active
+struct DeferredFree {
+    retired: AtomicU64,
+    freed: Arc<AtomicU64>,
+}
+
+impl DeferredFree {
+    fn new() -> Self {
+        Self {
+            retired: AtomicU64::new(0),
+            freed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Free `ptr` after every guard active now has been dropped.
+    ///
+    /// # Safety
+    /// `ptr` must come from `Box::into_raw`, must already be unreachable for
+    /// threads that protect after this call, and must not be retired twice.
+    unsafe fn retire<T>(&self, ptr: *mut T, guard: &Guard) {
+        self.retired.fetch_add(1, Ordering::Relaxed);
+        let freed = self.freed.clone();
+        unsafe {
+            guard.defer_unchecked(move || {
+                drop(Box::from_raw(ptr));
+                freed.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+    }
+
+    /// Number of retired allocations not freed yet
+    fn pending(&self) -> u64 {
+        self.retired.load(Ordering::Relaxed) - self.freed.load(Ordering::Relaxed)
+    }
+}
+
 /// Dynamic resize strategy
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ResizeStrategy {
@@ -110,7 +197,29 @@ impl Default for ResizeStrategy {
     }
 }
 
-/// Hash table resize statistics
+impl ResizeStrategy {
+    /// Load factor above which the table grows, if growth depends on it
+    fn grow_load_threshold(&self) -> Option<f32> {
+        match *self {
+            ResizeStrategy::LoadFactor { threshold } => Some(threshold),
+            ResizeStrategy::Adaptive { load_threshold, .. } => Some(load_threshold),
+            _ => None,
+        }
+    }
+}
+
+/// When to halve the bucket count after keys are removed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShrinkPolicy {
+    /// Load factor below which the table may shrink. Halving doubles the
+    /// load factor, so this must stay below half the growth threshold.
+    pub low_water: f32,
+    /// How long the load factor must stay below `low_water` before a shrink
+    pub hold_for: Duration,
+}
+
+/// Hash table resize statistics. Growth and shrinking are counted
+/// separately.
 #[derive(Debug, Clone, Default)]
 pub struct ResizeStatistics {
     pub resize_count: u64,
@@ -118,6 +227,10 @@ pub struct ResizeStatistics {
     pub max_resize_time_ms: u64,
     pub elements_rehashed: u64,
     pub last_resize_timestamp: u64,
+    pub shrink_count: u64,
+    pub total_shrink_time_ms: u64,
+    pub elements_merged: u64,
+    pub last_shrink_timestamp: u64,
     pub current_bucket_count: usize,
     pub overflow_bucket_count: usize,
     pub total_entries: usize,
@@ -145,22 +258,39 @@ impl ResizeStatistics {
 
 /// Dynamic hash table with automatic resizing
 pub struct DynamicHashTable<K: Hash + Eq + Clone, V: Clone> {
-    /// Array of hash buckets
-    buckets: RwLock<Vec<Box<HashBucket<K, V>>>>,
+    /// Array of hash buckets. Readers load it under an epoch guard without
+    /// locking, and a resize retires the array it replaces.
+    buckets: AtomicPtr<Vec<HashBucket<K, V>>>,
+    /// Held shared by writers and exclusively by a resize
+    resize_lock: RwLock<()>,
     /// Current number of buckets (must be power of 2)
     bucket_count: AtomicUsize,
     /// Total number of entries
     entry_count: AtomicUsize,
     /// Resize strategy
     resize_strategy: RwLock<ResizeStrategy>,
+    /// Shrinking is off unless a policy is set
+    shrink_policy: RwLock<Option<ShrinkPolicy>>,
+    /// When the load factor last fell below the shrink low-water mark
+    below_low_water_since: Mutex<Option<Instant>>,
     /// Lock manager for coordination
     lock_manager: Arc<HierarchicalLockManager>,
     /// Epoch for memory management
     epoch: Arc<LightEpoch>,
+    /// Entries and bucket arrays waiting for readers to move on
+    deferred_free: DeferredFree,
     /// Resize statistics
     statistics: RwLock<ResizeStatistics>,
     /// Resize in progress flag
     resize_in_progress: AtomicUsize, // Acts as a counter for concurrent resizes
+    /// Places entries whose home bucket is full and finds them again
+    overflow: EnhancedOverflowManager<'static, K, V>,
+    /// Runs the resize event hooks when set
+    event_dispatcher: Mutex<Option<EventDispatcher>>,
+    /// The most recent completed resize
+    last_resize: Mutex<Option<ResizeCompleted>>,
+    /// Time source for resize intervals, shrink holds and access times
+    clock: Arc<dyn Clock>,
     /// Hash function state
     hash_seed: u64,
 }
@@ -169,87 +299,186 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
     const INITIAL_BUCKET_COUNT: usize = 16;
     const MAX_BUCKET_COUNT: usize = 1 << 24; // 16M buckets
     const DEFAULT_HASH_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+    /// Longest overflow chain a bucket may have
+    const MAX_OVERFLOW_CHAIN: u64 = 16;
+    /// Old buckets migrated between resize progress events
+    const RESIZE_PROGRESS_INTERVAL: usize = 1024;
 
     /// Create a new dynamic hash table
     pub fn new(epoch: Arc<LightEpoch>) -> Self {
-        let initial_buckets: Vec<Box<HashBucket<K, V>>> = (0..Self::INITIAL_BUCKET_COUNT)
-            .map(|_| Box::new(HashBucket::new()))
+        Self::with_clock(epoch, system_clock())
+    }
+
+    /// Create a table that reads the time from `clock`
+    pub fn with_clock(epoch: Arc<LightEpoch>, clock: Arc<dyn Clock>) -> Self {
+        let initial_buckets: Vec<HashBucket<K, V>> = (0..Self::INITIAL_BUCKET_COUNT)
+            .map(|_| HashBucket::new())
             .collect();
 
         Self {
-            buckets: RwLock::new(initial_buckets),
+            buckets: AtomicPtr::new(Box::into_raw(Box::new(initial_buckets))),
+            resize_lock: RwLock::new(()),
             bucket_count: AtomicUsize::new(Self::INITIAL_BUCKET_COUNT),
             entry_count: AtomicUsize::new(0),
             resize_strategy: RwLock::new(ResizeStrategy::default()),
+            shrink_policy: RwLock::new(None),
+            below_low_water_since: Mutex::new(None),
             lock_manager: Arc::new(HierarchicalLockManager::new()),
             epoch,
+            deferred_free: DeferredFree::new(),
             statistics: RwLock::new(ResizeStatistics {
                 current_bucket_count: Self::INITIAL_BUCKET_COUNT,
                 ..Default::default()
             }),
             resize_in_progress: AtomicUsize::new(0),
+            overflow: EnhancedOverflowManager::with_clock(&OVERFLOW_EPOCH, clock.clone()),
+            event_dispatcher: Mutex::new(None),
+            last_resize: Mutex::new(None),
+            clock,
             hash_seed: Self::DEFAULT_HASH_SEED,
         }
     }
 
+    /// Replaces the event hooks, waiting for the events queued for the old
+    /// ones. Only [`EventHooks::on_resize`] is ever called. See
+    /// [`crate::events`].
+    pub fn set_hooks(&self, hooks: Option<EventHooks>) {
+        let old = match self.event_dispatcher.lock() {
+            Ok(mut dispatcher) => std::mem::replace(&mut *dispatcher, hooks.map(EventDispatcher::start)),
+            Err(_) => return,
+        };
+        drop(old);
+    }
+
+    /// Delivery counts of the event hooks, if set.
+    pub fn hook_stats(&self) -> Option<HookStats> {
+        self.event_dispatcher.lock().ok()?.as_ref().map(EventDispatcher::stats)
+    }
+
+    /// What the most recent resize or shrink did, if there was one.
+    pub fn last_resize(&self) -> Option<ResizeCompleted> {
+        self.last_resize.lock().ok()?.clone()
+    }
+
+    fn emit_resize(&self, event: ResizeEvent) {
+        if let Ok(dispatcher) = self.event_dispatcher.lock()
+            && let Some(dispatcher) = dispatcher.as_ref()
+        {
+            dispatcher.emit(StoreEvent::Resize(event));
+        }
+    }
+
     /// Insert or update a key-value pair
-    pub fn upsert(&self, key: K, value: V, _guard: &Guard) -> ContextResult<Option<V>> {
+    pub fn upsert(&self, key: K, value: V, guard: &Guard) -> ContextResult<Option<V>> {
         let hash = self.calculate_hash(&key);
         let bucket_idx = self.get_bucket_index(hash);
 
-        // Acquire bucket lock
+        // Acquire bucket lock; readers may keep going until we write
         let lock_id = LockId::new(LockGranularity::Bucket, bucket_idx as u64);
-        let _lock_guard = self.lock_manager
-            .acquire_lock(lock_id, LockIntent::Write)
-            .map_err(|s| ErrorContext::new(s))?;
+        let lock_guard = self.lock_manager
+            .acquire_lock(lock_id, LockIntent::Upgradeable)
+            .map_err(ErrorContext::new)?;
 
         // Check if resize is needed before insertion
         self.check_and_trigger_resize()?;
 
+        let _lock_guard = lock_guard.upgrade().map_err(ErrorContext::new)?;
+
         // Perform the actual insertion
-        let result = self.upsert_internal(hash, key, value);
+        let result = self.upsert_internal(hash, key, value, guard);
 
-        if result.is_ok() {
+        if matches!(result, Ok(None)) {
             self.entry_count.fetch_add(1, Ordering::Relaxed);
         }
 
         result
     }
 
-    /// Get a value by key
-    pub fn get(&self, key: &K, _guard: &Guard) -> ContextResult<Option<V>> {
+    /// Get a value by key. Takes no locks: `guard` keeps the bucket array
+    /// and entries it reads from being freed, and writers publish entries
+    /// whole, replacing rather than changing them.
+    pub fn get(&self, key: &K, guard: &Guard) -> ContextResult<Option<V>> {
         let hash = self.calculate_hash(key);
-        let bucket_idx = self.get_bucket_index(hash);
-
-        // Acquire bucket lock for reading
-        let lock_id = LockId::new(LockGranularity::Bucket, bucket_idx as u64);
-        let _lock_guard = self.lock_manager
-            .acquire_lock(lock_id, LockIntent::Read)
-            .map_err(|s| ErrorContext::new(s))?;
-
-        self.get_internal(hash, key)
+        Ok(self.get_internal(hash, key, guard))
     }
 
     /// Remove a key-value pair
-    pub fn remove(&self, key: &K, _guard: &Guard) -> ContextResult<Option<V>> {
+    pub fn remove(&self, key: &K, guard: &Guard) -> ContextResult<Option<V>> {
         let hash = self.calculate_hash(key);
         let bucket_idx = self.get_bucket_index(hash);
 
         // Acquire bucket lock
         let lock_id = LockId::new(LockGranularity::Bucket, bucket_idx as u64);
-        let _lock_guard = self.lock_manager
+        let lock_guard = self.lock_manager
             .acquire_lock(lock_id, LockIntent::Write)
-            .map_err(|s| ErrorContext::new(s))?;
+            .map_err(ErrorContext::new)?;
 
-        let result = self.remove_internal(hash, key);
+        let result = self.remove_internal(hash, key, guard);
+        drop(lock_guard);
 
         if result.as_ref().map(|r| r.is_some()).unwrap_or(false) {
             self.entry_count.fetch_sub(1, Ordering::Relaxed);
+            self.check_and_trigger_shrink()?;
         }
 
         result
     }
 
+    /// Insert or update many key-value pairs, taking each bucket's lock once
+    /// for all of its pairs. Pairs for the same bucket are applied in their
+    /// given order. Returns the number of keys newly inserted.
+    pub fn apply_batch(&self, entries: Vec<(K, V)>, guard: &Guard) -> ContextResult<usize> {
+        // Resize up front, so bucket indices hold for the whole batch
+        self.check_and_trigger_resize()?;
+
+        let mut by_bucket: BTreeMap<usize, Vec<(u64, K, V)>> = BTreeMap::new();
+        for (key, value) in entries {
+            let hash = self.calculate_hash(&key);
+            by_bucket
+                .entry(self.get_bucket_index(hash))
+                .or_default()
+                .push((hash, key, value));
+        }
+
+        let mut inserted = 0;
+        for (bucket_idx, entries) in by_bucket {
+            let lock_id = LockId::new(LockGranularity::Bucket, bucket_idx as u64);
+            let _lock_guard = self.lock_manager
+                .acquire_lock(lock_id, LockIntent::Write)
+                .map_err(ErrorContext::new)?;
+
+            for (hash, key, value) in entries {
+                if self.upsert_internal(hash, key, value, guard)?.is_none() {
+                    self.entry_count.fetch_add(1, Ordering::Relaxed);
+                    inserted += 1;
+                }
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    /// Set how entries whose home bucket is full are placed. Entries placed
+    /// under an earlier strategy are still found.
+    pub fn set_overflow_strategy(&self, strategy: OverflowStrategy) -> Result<()> {
+        self.overflow.set_strategy(strategy)
+    }
+
+    /// Placement and search statistics of the overflow areas
+    pub fn overflow_statistics(&self) -> OverflowStatistics {
+        self.overflow.get_statistics()
+    }
+
+    /// Move chained entries towards their home buckets and give back the
+    /// overflow buckets this empties. Writers wait while this runs. Returns
+    /// the number of overflow buckets given back.
+    pub fn consolidate_overflow(&self) -> ContextResult<u32> {
+        let _resize_guard = self.resize_lock.write()
+            .map_err(|_| ErrorContext::new(Status::InternalError))?;
+        let guard = self.epoch.protect();
+        self.overflow.consolidate_overflow_buckets(self.load_buckets(&guard), &guard)
+    }
+
     /// Update resize strategy
     pub fn set_resize_strategy(&self, strategy: ResizeStrategy) -> Result<()> {
         if let Ok(mut current_strategy) = self.resize_strategy.write() {
@@ -260,12 +489,37 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
         }
     }
 
+    /// Enable shrinking with `policy`, or turn it off with `None`. Fails
+    /// with `InvalidConfiguration` unless `low_water` is below half the
+    /// growth threshold, so a shrink never lands above it.
+    pub fn set_shrink_policy(&self, policy: Option<ShrinkPolicy>) -> Result<()> {
+        if let Some(policy) = policy {
+            let grow_threshold = self
+                .resize_strategy
+                .read()
+                .map_err(|_| Status::InternalError)?
+                .grow_load_threshold();
+            if policy.low_water <= 0.0
+                || grow_threshold.is_some_and(|threshold| policy.low_water * 2.0 >= threshold)
+            {
+                return Err(Status::InvalidConfiguration);
+            }
+        }
+        let mut current = self.shrink_policy.write().map_err(|_| Status::InternalError)?;
+        *current = policy;
+        if let Ok(mut since) = self.below_low_water_since.lock() {
+            *since = None;
+        }
+        Ok(())
+    }
+
     /// Get current statistics
     pub fn get_statistics(&self) -> ResizeStatistics {
         if let Ok(stats) = self.statistics.read() {
             let mut updated_stats = stats.clone();
             updated_stats.current_bucket_count = self.bucket_count.load(Ordering::Relaxed);
             updated_stats.total_entries = self.entry_count.load(Ordering::Relaxed);
+            updated_stats.overflow_bucket_count = self.overflow.active_overflow_buckets() as usize;
             updated_stats
         } else {
             ResizeStatistics::default()
@@ -275,6 +529,93 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
     /// Manually trigger resize
     pub fn resize(&self) -> ContextResult<()> {
         self.resize_table(self.bucket_count.load(Ordering::Relaxed) * 2)
+            .map(|_| ())
+    }
+
+    /// Manually halve the bucket count, merging bucket `i + half` into
+    /// bucket `i`. Returns false without shrinking at the initial bucket
+    /// count, or when a merged bucket would not fit its entries.
+    pub fn shrink(&self) -> ContextResult<bool> {
+        let current = self.bucket_count.load(Ordering::Relaxed);
+        if current <= Self::INITIAL_BUCKET_COUNT {
+            return Ok(false);
+        }
+        self.resize_table(current / 2)
+    }
+
+    /// Checks that each bucket's entry count matches its occupied slots,
+    /// that every entry's hash, moved on by its probe distance, maps to its
+    /// bucket, that overflow chains stay within bounds and that no chain
+    /// holds the same hash and key twice.
+    /// Resizes wait while this runs. See [`crate::index::invariants`].
+    pub fn check_invariants(&self, guard: &Guard) -> Result<InvariantReport> {
+        let _resize_guard = self.resize_lock.read().map_err(|_| Status::InternalError)?;
+        Ok(Self::check_buckets(self.load_buckets(guard)))
+    }
+
+    fn check_buckets(buckets: &[HashBucket<K, V>]) -> InvariantReport {
+        let mut report = InvariantReport::default();
+        let mask = buckets.len() - 1;
+        for (index, head) in buckets.iter().enumerate() {
+            let bucket_id = index as u64;
+            let mut chain: Vec<&HashEntry<K, V>> = Vec::new();
+            let mut bucket = head;
+            let mut length = 0;
+            loop {
+                report.buckets_checked += 1;
+                let mut occupied = 0;
+                for slot in &bucket.entries {
+                    let entry_ptr = slot.load(Ordering::Acquire);
+                    if entry_ptr.is_null() {
+                        continue;
+                    }
+                    // Callers hold a guard, so no entry is freed under us
+                    let entry = unsafe { &*entry_ptr };
+                    occupied += 1;
+                    report.entries_checked += 1;
+                    let distance = entry.probe_distance.load(Ordering::Relaxed) as usize;
+                    if ((entry.hash as usize) + distance) & mask != index {
+                        report.violations.push(IndexViolation::MisplacedEntry {
+                            bucket: bucket_id,
+                            hash: entry.hash,
+                        });
+                    }
+                    if chain.iter().any(|other| other.hash == entry.hash && other.key == entry.key) {
+                        report.violations.push(IndexViolation::DuplicateEntry {
+                            bucket: bucket_id,
+                            hash: entry.hash,
+                        });
+                    }
+                    chain.push(entry);
+                }
+                let recorded = bucket.entry_count() as u64;
+                if recorded != occupied {
+                    report.violations.push(IndexViolation::EntryCountMismatch {
+                        bucket: bucket_id,
+                        recorded,
+                        actual: occupied,
+                    });
+                }
+
+                let overflow = bucket.overflow.load(Ordering::Acquire);
+                if overflow.is_null() {
+                    break;
+                }
+                length += 1;
+                if length > Self::MAX_OVERFLOW_CHAIN {
+                    report.violations.push(IndexViolation::ChainTooLong { bucket: bucket_id, length });
+                    break;
+                }
+                bucket = unsafe { &*overflow };
+            }
+        }
+        report
+    }
+
+    /// Number of removed entries and replaced bucket arrays still waiting
+    /// for the epoch to advance before they are freed
+    pub fn pending_frees(&self) -> u64 {
+        self.deferred_free.pending()
     }
 
     // Private implementation methods
@@ -291,119 +632,88 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
         (hash as usize) & (bucket_count - 1) // Assumes power of 2
     }
 
-    fn upsert_internal(&self, hash: u64, key: K, value: V) -> ContextResult<Option<V>> {
-        let buckets = self.buckets.read()
-            .map_err(|_| ErrorContext::new(Status::InternalError))?;
-
-        let bucket_idx = self.get_bucket_index(hash);
-        let bucket = &buckets[bucket_idx];
-        bucket.record_access();
-
-        // First check for existing key
-        for i in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-            let entry_ptr = bucket.entries[i].load(Ordering::Acquire);
-            if !entry_ptr.is_null() {
-                unsafe {
-                    if (*entry_ptr).hash == hash && (*entry_ptr).key == key {
-                        // Update existing entry
-                        let old_value = (*entry_ptr).value.clone();
-                        (*entry_ptr).value = value;
-                        return Ok(Some(old_value));
-                    }
-                }
-            }
-        }
-
-        // No existing key found, try to insert new entry
-        for i in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-            let entry_ptr = bucket.entries[i].load(Ordering::Acquire);
-            if entry_ptr.is_null() {
-                // Found empty slot - insert new entry
-                let new_entry = Box::into_raw(Box::new(HashEntry::new(key, value, hash)));
-
-                match bucket.entries[i].compare_exchange(
-                    entry_ptr,
-                    new_entry,
-                    Ordering::Release,
-                    Ordering::Relaxed,
-                ) {
-                    Ok(_) => {
-                        bucket.entry_count.fetch_add(1, Ordering::Relaxed);
-                        return Ok(None);
-                    }
-                    Err(_) => {
-                        // Another thread inserted, clean up and return error since we can't retry
-                        // (key and value have been moved)
-                        unsafe { drop(Box::from_raw(new_entry)); }
-                        return Err(ErrorContext::new(Status::InternalError)
-                            .with_context("Concurrent insertion conflict"));
-                    }
-                }
-            }
-        }
-
-        // No space in main bucket, would need overflow handling here
-        Err(ErrorContext::new(Status::OutOfMemory)
-            .with_context("Bucket is full and overflow not implemented"))
+    /// The current bucket array, valid while `_guard` is held
+    fn load_buckets<'g>(&self, _guard: &'g Guard) -> &'g [HashBucket<K, V>] {
+        // A replaced array is retired through the epoch, so it outlives
+        // every guard that could have loaded it
+        unsafe { &*self.buckets.load(Ordering::Acquire) }
     }
 
-    fn get_internal(&self, hash: u64, key: &K) -> ContextResult<Option<V>> {
-        let buckets = self.buckets.read()
+    fn upsert_internal(&self, hash: u64, key: K, value: V, guard: &Guard) -> ContextResult<Option<V>> {
+        let _resize_guard = self.resize_lock.read()
             .map_err(|_| ErrorContext::new(Status::InternalError))?;
+        let buckets = self.load_buckets(guard);
 
-        let bucket_idx = self.get_bucket_index(hash);
+        let bucket_idx = (hash as usize) & (buckets.len() - 1);
         let bucket = &buckets[bucket_idx];
-        bucket.record_access();
-
-        // Search for key in bucket
-        for i in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-            let entry_ptr = bucket.entries[i].load(Ordering::Acquire);
-            if entry_ptr.is_null() {
-                continue;
-            }
+        bucket.record_access(self.now_secs());
+
+        // First check for an existing key, in the home bucket and wherever
+        // overflow handling placed it
+        let mut search_depth = 0;
+        let existing = self.overflow
+            .search_overflow_internal(buckets, bucket_idx, hash, &key, guard, &mut search_depth)?;
+        if let Some(located) = existing {
+            // Readers may be cloning the old value, so publish a new entry
+            // in the same slot and retire the old one
+            let old_entry = unsafe { &*located.entry };
+            let old_value = old_entry.value.clone();
+            let new_entry = HashEntry::new(key, value, hash);
+            new_entry.probe_distance.store(old_entry.probe_distance.load(Ordering::Relaxed), Ordering::Relaxed);
+            located.bucket.slots()[located.slot].store(Box::into_raw(Box::new(new_entry)), Ordering::Release);
+            unsafe { self.deferred_free.retire(located.entry, guard) };
+            return Ok(Some(old_value));
+        }
 
-            unsafe {
-                if (*entry_ptr).hash == hash && (*entry_ptr).key == *key {
-                    return Ok(Some((*entry_ptr).value.clone()));
-                }
+        // No existing key found, insert a new entry
+        let new_entry = Box::into_raw(Box::new(HashEntry::new(key, value, hash)));
+        if bucket.try_insert(new_entry) {
+            return Ok(None);
+        }
+        match unsafe { self.overflow.insert_with_overflow(buckets, bucket_idx, new_entry, guard) } {
+            Ok(true) => Ok(None),
+            placed => {
+                unsafe { drop(Box::from_raw(new_entry)) };
+                placed?;
+                Err(ErrorContext::new(Status::OutOfMemory)
+                    .with_context("Bucket and its overflow areas are full"))
             }
         }
+    }
 
-        Ok(None)
+    fn get_internal(&self, hash: u64, key: &K, guard: &Guard) -> Option<V> {
+        let buckets = self.load_buckets(guard);
+        let bucket_idx = (hash as usize) & (buckets.len() - 1);
+
+        // Reads leave the bucket's access statistics and the overflow
+        // statistics alone, so readers of a hot bucket share its cache line
+        // instead of bouncing it. Entries are retired through the epoch, so
+        // the guard keeps the one found alive even if it is removed or
+        // replaced meanwhile.
+        let mut search_depth = 0;
+        let located = self.overflow
+            .search_overflow_internal(buckets, bucket_idx, hash, key, guard, &mut search_depth)
+            .ok()??;
+        Some(unsafe { &*located.entry }.value.clone())
     }
 
-    fn remove_internal(&self, hash: u64, key: &K) -> ContextResult<Option<V>> {
-        let buckets = self.buckets.read()
+    fn remove_internal(&self, hash: u64, key: &K, guard: &Guard) -> ContextResult<Option<V>> {
+        let _resize_guard = self.resize_lock.read()
             .map_err(|_| ErrorContext::new(Status::InternalError))?;
+        let buckets = self.load_buckets(guard);
 
-        let bucket_idx = self.get_bucket_index(hash);
+        let bucket_idx = (hash as usize) & (buckets.len() - 1);
         let bucket = &buckets[bucket_idx];
-        bucket.record_access();
-
-        // Search for key in bucket
-        for i in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-            let entry_ptr = bucket.entries[i].load(Ordering::Acquire);
-            if entry_ptr.is_null() {
-                continue;
-            }
-
-            unsafe {
-                if (*entry_ptr).hash == hash && (*entry_ptr).key == *key {
-                    let old_value = (*entry_ptr).value.clone();
-
-                    // Remove entry by setting to null
-                    bucket.entries[i].store(ptr::null_mut(), Ordering::Release);
-                    bucket.entry_count.fetch_sub(1, Ordering::Relaxed);
-
-                    // Clean up memory (in real implementation, defer this)
-                    drop(Box::from_raw(entry_ptr));
-
-                    return Ok(Some(old_value));
-                }
-            }
-        }
-
-        Ok(None)
+        bucket.record_access(self.now_secs());
+
+        let removed = self.overflow.remove_overflow(buckets, bucket_idx, hash, key, guard)?;
+        Ok(removed.map(|entry_ptr| {
+            let old_value = unsafe { (*entry_ptr).value.clone() };
+            // Readers that loaded the pointer before it was unlinked may
+            // still be looking at it
+            unsafe { self.deferred_free.retire(entry_ptr, guard) };
+            old_value
+        }))
     }
 
     fn check_and_trigger_resize(&self) -> ContextResult<()> {
@@ -429,10 +739,7 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
                 min_resize_interval,
             } => {
                 let stats = self.get_statistics();
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
+                let now = self.now_secs();
 
                 stats.load_factor() > load_threshold
                     || stats.overflow_ratio() > overflow_threshold
@@ -440,38 +747,102 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
             }
         };
 
-        if should_resize {
+        // A full overflow chain asks for growth too, unless resizing is off
+        let chain_full = self.overflow.take_resize_request() && strategy != ResizeStrategy::None;
+        if should_resize || chain_full {
             let current_size = self.bucket_count.load(Ordering::Relaxed);
             self.resize_table(current_size * 2)?;
+        } else {
+            self.check_and_trigger_shrink()?;
         }
 
         Ok(())
     }
 
-    fn resize_table(&self, new_bucket_count: usize) -> ContextResult<()> {
+    /// Seconds since the Unix epoch by the table's clock
+    fn now_secs(&self) -> u64 {
+        since_unix_epoch(self.clock.now_wall()).as_secs()
+    }
+
+    /// Halve the table once the load factor has stayed below the shrink
+    /// policy's low-water mark for its hold time
+    fn check_and_trigger_shrink(&self) -> ContextResult<()> {
+        let policy = match self.shrink_policy.read() {
+            Ok(policy) => *policy,
+            Err(_) => return Err(ErrorContext::new(Status::InternalError)),
+        };
+        let Some(policy) = policy else {
+            return Ok(());
+        };
+        let grow_threshold = self
+            .resize_strategy
+            .read()
+            .map_err(|_| ErrorContext::new(Status::InternalError))?
+            .grow_load_threshold();
+
+        let stats = self.get_statistics();
+        let load_factor = stats.load_factor();
+        // Hysteresis: never shrink into a load that would trigger growth
+        let below = load_factor < policy.low_water
+            && stats.current_bucket_count > Self::INITIAL_BUCKET_COUNT
+            && grow_threshold.is_none_or(|threshold| load_factor * 2.0 < threshold);
+
+        let held = {
+            let mut since = self.below_low_water_since
+                .lock()
+                .map_err(|_| ErrorContext::new(Status::InternalError))?;
+            if !below {
+                *since = None;
+                return Ok(());
+            }
+            let now = self.clock.now_monotonic();
+            let started = *since.get_or_insert(now);
+            now.saturating_duration_since(started) >= policy.hold_for
+        };
+
+        if held && self.shrink()? {
+            // The next shrink needs the low load to hold again
+            if let Ok(mut since) = self.below_low_water_since.lock() {
+                *since = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rehash into `new_bucket_count` buckets. Returns false if the table
+    /// was left as it is: another resize is running, or a shrink would
+    /// overfill a bucket.
+    fn resize_table(&self, new_bucket_count: usize) -> ContextResult<bool> {
         // Check if resize is already in progress
         if self.resize_in_progress.fetch_add(1, Ordering::AcqRel) > 0 {
             // Another resize is in progress, just wait
             self.resize_in_progress.fetch_sub(1, Ordering::AcqRel);
-            return Ok(());
+            return Ok(false);
         }
 
         let start_time = std::time::Instant::now();
+        let shrinking = new_bucket_count < self.bucket_count.load(Ordering::Relaxed);
 
         let result = self.resize_table_internal(new_bucket_count, start_time);
 
         // Update statistics
         let resize_time_ms = start_time.elapsed().as_millis() as u64;
-        if let Ok(mut stats) = self.statistics.write() {
-            stats.resize_count += 1;
-            stats.total_resize_time_ms += resize_time_ms;
+        if matches!(result, Ok(true))
+            && let Ok(mut stats) = self.statistics.write()
+        {
+            let now = self.now_secs();
+            if shrinking {
+                stats.shrink_count += 1;
+                stats.total_shrink_time_ms += resize_time_ms;
+                stats.last_shrink_timestamp = now;
+            } else {
+                stats.resize_count += 1;
+                stats.total_resize_time_ms += resize_time_ms;
+                stats.last_resize_timestamp = now;
+            }
             if resize_time_ms > stats.max_resize_time_ms {
                 stats.max_resize_time_ms = resize_time_ms;
             }
-            stats.last_resize_timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
         }
 
         // Mark resize as complete
@@ -480,97 +851,157 @@ impl<K: Hash + Eq + Clone, V: Clone> DynamicHashTable<K, V> {
         result.with_context("Hash table resize failed")
     }
 
-    fn resize_table_internal(&self, new_bucket_count: usize, start_time: std::time::Instant) -> ContextResult<()> {
+    fn resize_table_internal(&self, new_bucket_count: usize, start_time: std::time::Instant) -> ContextResult<bool> {
         if new_bucket_count > Self::MAX_BUCKET_COUNT {
             return Err(ErrorContext::new(Status::OutOfMemory)
                 .with_context("Hash table size limit exceeded"));
         }
 
         // Create new bucket array
-        let new_buckets: Vec<Box<HashBucket<K, V>>> = (0..new_bucket_count)
-            .map(|_| Box::new(HashBucket::new()))
+        let new_buckets: Vec<HashBucket<K, V>> = (0..new_bucket_count)
+            .map(|_| HashBucket::new())
             .collect();
 
         let mut rehashed_count = 0u64;
+        let mut dropped_count = 0u64;
+        let old_bucket_count;
+        let guard = self.epoch.protect();
 
-        // Rehash all existing entries
+        // Rehash all existing entries. Entries move to the new array as they
+        // are, and the write lock keeps removals from retiring one that has
+        // already moved. Readers go on with the old array meanwhile.
         {
-            let old_buckets = self.buckets.read()
+            let _resize_guard = self.resize_lock.write()
                 .map_err(|_| ErrorContext::new(Status::InternalError))?;
+            let old_buckets = self.load_buckets(&guard);
+            old_bucket_count = old_buckets.len();
+
+            // Shrinking merges bucket i + half into bucket i. Give up rather
+            // than drop entries when a pair holds more than one bucket fits.
+            if new_bucket_count < old_bucket_count {
+                let (low, high) = old_buckets.split_at(new_bucket_count);
+                let overfull = low
+                    .iter()
+                    .zip(high)
+                    .any(|(a, b)| a.entry_count() + b.entry_count() > HashBucket::<K, V>::ENTRIES_PER_BUCKET);
+                if overfull {
+                    log::debug!("Not shrinking to {} buckets, a merged bucket would overflow", new_bucket_count);
+                    return Ok(false);
+                }
+            }
+            self.emit_resize(ResizeEvent::Started { from: old_bucket_count, to: new_bucket_count });
 
-            for bucket in old_buckets.iter() {
-                for i in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-                    let entry_ptr = bucket.entries[i].load(Ordering::Acquire);
-                    if entry_ptr.is_null() {
-                        continue;
-                    }
+            for (migrated, head) in old_buckets.iter().enumerate() {
+                if migrated > 0 && migrated % Self::RESIZE_PROGRESS_INTERVAL == 0 {
+                    self.emit_resize(ResizeEvent::Progress { buckets_migrated: migrated });
+                }
+                let mut bucket = head;
+                loop {
+                    for slot in &bucket.entries {
+                        let entry_ptr = slot.load(Ordering::Acquire);
+                        if entry_ptr.is_null() {
+                            continue;
+                        }
 
-                    unsafe {
-                        let entry = &*entry_ptr;
-                        let new_bucket_idx = (entry.hash as usize) & (new_bucket_count - 1);
-                        let new_bucket = &new_buckets[new_bucket_idx];
-
-                        // Find empty slot in new bucket
-                        for j in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-                            if new_bucket.entries[j].load(Ordering::Relaxed).is_null() {
-                                let new_entry = Box::into_raw(Box::new(HashEntry::new(
-                                    entry.key.clone(),
-                                    entry.value.clone(),
-                                    entry.hash,
-                                )));
-
-                                new_bucket.entries[j].store(new_entry, Ordering::Release);
-                                new_bucket.entry_count.fetch_add(1, Ordering::Relaxed);
-                                rehashed_count += 1;
-                                break;
+                        let hash = unsafe { (*entry_ptr).hash };
+                        let new_bucket_idx = (hash as usize) & (new_bucket_count - 1);
+                        unsafe { (*entry_ptr).probe_distance.store(0, Ordering::Relaxed) };
+                        let placed = new_buckets[new_bucket_idx].try_insert(entry_ptr)
+                            || unsafe {
+                                self.overflow.insert_with_overflow(&new_buckets, new_bucket_idx, entry_ptr, &guard)
                             }
+                            .unwrap_or(false);
+                        if placed {
+                            rehashed_count += 1;
+                        } else {
+                            log::warn!("Dropping entry during resize, bucket {} and its overflow areas are full", new_bucket_idx);
+                            unsafe { self.deferred_free.retire(entry_ptr, &guard) };
+                            dropped_count += 1;
                         }
                     }
+                    let overflow = bucket.overflow.load(Ordering::Acquire);
+                    if overflow.is_null() {
+                        break;
+                    }
+                    bucket = unsafe { &*overflow };
                 }
             }
-        }
 
-        // Replace old buckets with new ones
-        {
-            let mut buckets_guard = self.buckets.write()
-                .map_err(|_| ErrorContext::new(Status::InternalError))?;
-            *buckets_guard = new_buckets;
-        }
+            // Replace old buckets with new ones. Readers still on the old
+            // array keep walking its chains until the epoch moves on.
+            self.overflow.release_chains(old_buckets, &guard);
+            let old_buckets = self.buckets.swap(Box::into_raw(Box::new(new_buckets)), Ordering::AcqRel);
+            unsafe { self.deferred_free.retire(old_buckets, &guard) };
+
+            // Writers are still shut out, so the check is exact
+            #[cfg(debug_assertions)]
+            {
+                let report = Self::check_buckets(self.load_buckets(&guard));
+                debug_assert!(
+                    report.is_clean(),
+                    "resize broke index invariants: {:?}",
+                    report.violations
+                );
+            }
 
-        // Update bucket count
-        self.bucket_count.store(new_bucket_count, Ordering::Release);
+            // Update bucket count
+            self.bucket_count.store(new_bucket_count, Ordering::Release);
+        }
 
         // Update statistics
         if let Ok(mut stats) = self.statistics.write() {
-            stats.elements_rehashed += rehashed_count;
+            if new_bucket_count < old_bucket_count {
+                stats.elements_merged += rehashed_count;
+            } else {
+                stats.elements_rehashed += rehashed_count;
+            }
             stats.current_bucket_count = new_bucket_count;
         }
 
+        let completed = ResizeCompleted {
+            from: old_bucket_count,
+            to: new_bucket_count,
+            duration: start_time.elapsed(),
+            entries_rehashed: rehashed_count,
+            entries_dropped: dropped_count,
+        };
         log::info!(
             "Hash table resized from {} to {} buckets, rehashed {} entries in {:?}",
-            new_bucket_count / 2,
-            new_bucket_count,
-            rehashed_count,
-            start_time.elapsed()
+            completed.from,
+            completed.to,
+            completed.entries_rehashed,
+            completed.duration
         );
+        if let Ok(mut last_resize) = self.last_resize.lock() {
+            *last_resize = Some(completed.clone());
+        }
+        self.emit_resize(ResizeEvent::Completed(completed));
 
-        Ok(())
+        Ok(true)
     }
 }
 
 impl<K: Hash + Eq + Clone, V: Clone> Drop for DynamicHashTable<K, V> {
     fn drop(&mut self) {
-        // Clean up all entries
-        if let Ok(buckets) = self.buckets.read() {
-            for bucket in buckets.iter() {
-                for i in 0..HashBucket::<K, V>::ENTRIES_PER_BUCKET {
-                    let entry_ptr = bucket.entries[i].load(Ordering::Relaxed);
+        // Clean up all entries and the bucket array. The overflow manager
+        // frees the chained buckets themselves.
+        let buckets = unsafe { Box::from_raw(self.buckets.load(Ordering::Acquire)) };
+        for head in buckets.iter() {
+            let mut bucket = head;
+            loop {
+                for slot in &bucket.entries {
+                    let entry_ptr = slot.load(Ordering::Relaxed);
                     if !entry_ptr.is_null() {
                         unsafe {
                             drop(Box::from_raw(entry_ptr));
                         }
                     }
                 }
+                let overflow = bucket.overflow.load(Ordering::Relaxed);
+                if overflow.is_null() {
+                    break;
+                }
+                bucket = unsafe { &*overflow };
             }
         }
     }
@@ -579,6 +1010,7 @@ impl<K: Hash + Eq + Clone, V: Clone> Drop for DynamicHashTable<K, V> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::clock::MockClock;
     use std::sync::Arc;
 
     #[test]
@@ -635,4 +1067,516 @@ mod tests {
         let stats = table.get_statistics();
         assert_eq!(stats.current_bucket_count, DynamicHashTable::<u64, String>::INITIAL_BUCKET_COUNT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_apply_batch() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table = DynamicHashTable::new(epoch.clone());
+        let guard = epoch.protect();
+
+        table.upsert(1u64, "old".to_string(), &guard).unwrap();
+        let entries = vec![
+            (1u64, "one".to_string()),
+            (2, "two".to_string()),
+            (3, "three".to_string()),
+            (2, "two again".to_string()),
+        ];
+        assert_eq!(table.apply_batch(entries, &guard).unwrap(), 2);
+
+        assert_eq!(table.get(&1, &guard).unwrap(), Some("one".to_string()));
+        assert_eq!(table.get(&2, &guard).unwrap(), Some("two again".to_string()));
+        assert_eq!(table.get(&3, &guard).unwrap(), Some("three".to_string()));
+        assert_eq!(table.get_statistics().total_entries, 3);
+    }
+
+    #[test]
+    fn test_shrink_after_bulk_delete() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table = DynamicHashTable::new(epoch.clone());
+        table
+            .set_resize_strategy(ResizeStrategy::LoadFactor { threshold: 1.0 })
+            .unwrap();
+        let guard = epoch.protect();
+
+        // Low water must sit below half the growth threshold
+        let policy = ShrinkPolicy {
+            low_water: 0.25,
+            hold_for: Duration::ZERO,
+        };
+        assert_eq!(
+            table.set_shrink_policy(Some(ShrinkPolicy { low_water: 0.5, ..policy })),
+            Err(Status::InvalidConfiguration)
+        );
+        table.set_shrink_policy(Some(policy)).unwrap();
+
+        for key in 0..2000u64 {
+            table.upsert(key, key * 10, &guard).unwrap();
+        }
+        let grown = table.get_statistics();
+        assert!(grown.resize_count > 0);
+        assert_eq!(grown.shrink_count, 0);
+        assert_eq!(grown.total_entries, 2000);
+
+        for key in (0..2000u64).filter(|key| key % 10 != 0) {
+            assert_eq!(table.remove(&key, &guard).unwrap(), Some(key * 10));
+        }
+        let shrunk = table.get_statistics();
+        assert!(shrunk.shrink_count > 0);
+        assert_eq!(shrunk.resize_count, grown.resize_count);
+        assert!(shrunk.current_bucket_count < grown.current_bucket_count);
+        assert!(shrunk.current_bucket_count >= DynamicHashTable::<u64, u64>::INITIAL_BUCKET_COUNT);
+        assert!(shrunk.load_factor() * 2.0 >= policy.low_water);
+        assert_eq!(shrunk.total_entries, 200);
+        assert!(shrunk.elements_merged > 0);
+
+        for key in 0..2000u64 {
+            let expected = (key % 10 == 0).then_some(key * 10);
+            assert_eq!(table.get(&key, &guard).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_shrink_waits_for_the_hold_time() {
+        let epoch = Arc::new(LightEpoch::new());
+        let clock = Arc::new(MockClock::new());
+        let table = DynamicHashTable::with_clock(epoch.clone(), clock.clone());
+        table
+            .set_resize_strategy(ResizeStrategy::LoadFactor { threshold: 1.0 })
+            .unwrap();
+        table
+            .set_shrink_policy(Some(ShrinkPolicy {
+                low_water: 0.25,
+                hold_for: Duration::from_secs(60),
+            }))
+            .unwrap();
+        let guard = epoch.protect();
+
+        for key in 0..2000u64 {
+            table.upsert(key, key, &guard).unwrap();
+        }
+        for key in 0..1990u64 {
+            table.remove(&key, &guard).unwrap();
+        }
+        let low = table.get_statistics();
+        assert_eq!(low.shrink_count, 0);
+
+        // The load has been low for under a minute
+        clock.advance(Duration::from_secs(59));
+        table.remove(&1990, &guard).unwrap();
+        assert_eq!(table.get_statistics().shrink_count, 0);
+
+        clock.advance(Duration::from_secs(1));
+        table.remove(&1991, &guard).unwrap();
+        let shrunk = table.get_statistics();
+        assert_eq!(shrunk.shrink_count, 1);
+        assert!(shrunk.current_bucket_count < low.current_bucket_count);
+        assert_eq!(
+            shrunk.last_shrink_timestamp,
+            since_unix_epoch(clock.now_wall()).as_secs()
+        );
+    }
+
+    #[test]
+    fn test_shrink_stops_at_initial_capacity() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table: DynamicHashTable<u64, u64> = DynamicHashTable::new(epoch);
+        assert!(!table.shrink().unwrap());
+        table.resize().unwrap();
+        assert!(table.shrink().unwrap());
+        assert!(!table.shrink().unwrap());
+        let stats = table.get_statistics();
+        assert_eq!(stats.current_bucket_count, DynamicHashTable::<u64, u64>::INITIAL_BUCKET_COUNT);
+        assert_eq!((stats.resize_count, stats.shrink_count), (1, 1));
+    }
+
+    #[test]
+    fn test_lock_free_gets_during_updates_and_resizes() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table = Arc::new(DynamicHashTable::new(epoch.clone()));
+        table.set_resize_strategy(ResizeStrategy::None).unwrap();
+        table.resize().unwrap();
+        table.resize().unwrap();
+        {
+            let guard = epoch.protect();
+            for key in 0..64u64 {
+                table.upsert(key, vec![key; 8], &guard).unwrap();
+            }
+        }
+
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let (table, epoch, stop) = (table.clone(), epoch.clone(), stop.clone());
+                std::thread::spawn(move || {
+                    let mut reads = 0u64;
+                    while !stop.load(Ordering::Relaxed) {
+                        let guard = epoch.protect();
+                        for key in 0..64u64 {
+                            // Every value is written whole, so no reader
+                            // sees a mix of two versions
+                            if let Some(value) = table.get(&key, &guard).unwrap() {
+                                assert_eq!(value.len(), 8);
+                                assert!(value.iter().all(|v| *v == value[0]));
+                                assert_eq!(value[0] % 64, key);
+                                reads += 1;
+                            }
+                        }
+                    }
+                    reads
+                })
+            })
+            .collect();
+
+        for round in 1..200u64 {
+            let guard = epoch.protect();
+            for key in 0..64u64 {
+                table.upsert(key, vec![key + 64 * round; 8], &guard).unwrap();
+            }
+            table.remove(&(round % 64), &guard).unwrap();
+            if round % 50 == 0 {
+                table.resize().unwrap();
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+        for reader in readers {
+            assert!(reader.join().unwrap() > 0);
+        }
+
+        let guard = epoch.protect();
+        assert_eq!(table.get(&0, &guard).unwrap(), Some(vec![64 * 199; 8]));
+        assert_eq!(table.get(&(199 % 64), &guard).unwrap(), None);
+    }
+
+    #[test]
+    fn test_deferred_free_reclaims_entries_and_buckets() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table = DynamicHashTable::new(epoch.clone());
+        table.set_resize_strategy(ResizeStrategy::None).unwrap();
+        table.resize().unwrap();
+        table.resize().unwrap();
+
+        {
+            let guard = epoch.protect();
+            for key in 0..32u64 {
+                table.upsert(key, key.to_string(), &guard).unwrap();
+            }
+            for key in (0..32u64).step_by(2) {
+                assert_eq!(table.remove(&key, &guard).unwrap(), Some(key.to_string()));
+            }
+            table.resize().unwrap();
+
+            // Nothing is freed while this guard could still see it
+            assert_eq!(table.pending_frees(), 16 + 3);
+            for key in (1..32u64).step_by(2) {
+                assert_eq!(table.get(&key, &guard).unwrap(), Some(key.to_string()));
+            }
+        }
+
+        for _ in 0..1000 {
+            if table.pending_frees() == 0 {
+                break;
+            }
+            epoch.bump_and_drain();
+            std::thread::sleep(std::time::Duration::from_micros(100));
+        }
+        assert_eq!(table.pending_frees(), 0);
+
+        let guard = epoch.protect();
+        assert_eq!(table.get_statistics().current_bucket_count, 128);
+        assert_eq!(table.get(&0, &guard).unwrap(), None);
+        assert_eq!(table.get(&31, &guard).unwrap(), Some("31".to_string()));
+    }
+
+    #[test]
+    fn test_check_invariants_finds_corrupted_buckets() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table: DynamicHashTable<u64, u64> = DynamicHashTable::new(epoch.clone());
+        let guard = epoch.protect();
+        for key in 0..32 {
+            table.upsert(key, key, &guard).unwrap();
+        }
+        let report = table.check_invariants(&guard).unwrap();
+        assert!(report.is_clean(), "{:?}", report.violations);
+        assert_eq!(report.entries_checked, 32);
+
+        // Corrupt the buckets directly, bypassing the table's protocol
+        let buckets = table.load_buckets(&guard);
+        let occupied = |bucket: &HashBucket<u64, u64>| {
+            bucket.entries.iter().position(|slot| !slot.load(Ordering::Acquire).is_null())
+        };
+        let free = |bucket: &HashBucket<u64, u64>| {
+            bucket.entries.iter().position(|slot| slot.load(Ordering::Acquire).is_null())
+        };
+        let from = buckets.iter().position(|bucket| occupied(bucket).is_some()).unwrap();
+        let to = (from + 1..buckets.len()).find(|&b| free(&buckets[b]).is_some()).unwrap();
+
+        // Move an entry to a bucket its hash does not map to
+        let slot = occupied(&buckets[from]).unwrap();
+        let moved = buckets[from].entries[slot].swap(ptr::null_mut(), Ordering::AcqRel);
+        buckets[from].entry_count.fetch_sub(1, Ordering::Relaxed);
+        buckets[to].entries[free(&buckets[to]).unwrap()].store(moved, Ordering::Release);
+        buckets[to].entry_count.fetch_add(1, Ordering::Relaxed);
+        let hash = unsafe { (*moved).hash };
+
+        // Copy an entry within its bucket, and miscount that bucket
+        let original = unsafe { &*moved };
+        let copy = Box::into_raw(Box::new(HashEntry::new(original.key, original.value, hash)));
+        buckets[to].entries[free(&buckets[to]).unwrap()].store(copy, Ordering::Release);
+
+        let report = table.check_invariants(&guard).unwrap();
+        let recorded = buckets[to].entry_count() as u64;
+        assert_eq!(
+            report.violations,
+            vec![
+                IndexViolation::MisplacedEntry { bucket: to as u64, hash },
+                IndexViolation::MisplacedEntry { bucket: to as u64, hash },
+                IndexViolation::DuplicateEntry { bucket: to as u64, hash },
+                IndexViolation::EntryCountMismatch {
+                    bucket: to as u64,
+                    recorded,
+                    actual: recorded + 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resize_events_reach_hooks() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table: DynamicHashTable<u64, u64> = DynamicHashTable::new(epoch.clone());
+        let (sender, events) = std::sync::mpsc::channel();
+        let sender = Mutex::new(sender);
+        table.set_hooks(Some(EventHooks::default().on_resize(move |event| {
+            sender.lock().unwrap().send(event.clone()).unwrap();
+        })));
+        assert_eq!(table.last_resize(), None);
+
+        // Past the growth threshold of the 16 initial buckets
+        let guard = epoch.protect();
+        for key in 0..16 {
+            table.upsert(key, key, &guard).unwrap();
+        }
+        assert_eq!(table.get_statistics().resize_count, 1);
+
+        let events: Vec<_> = events.recv_timeout(Duration::from_secs(5)).into_iter()
+            .chain(events.recv_timeout(Duration::from_secs(5)))
+            .collect();
+        assert_eq!(events[0], ResizeEvent::Started { from: 16, to: 32 });
+        let ResizeEvent::Completed(completed) = &events[1] else {
+            panic!("expected a completed resize, got {:?}", events[1]);
+        };
+        assert_eq!((completed.from, completed.to), (16, 32));
+        assert!(completed.entries_rehashed > 0 && completed.entries_rehashed < 16);
+        assert_eq!(completed.entries_dropped, 0);
+        assert_eq!(table.last_resize().as_ref(), Some(completed));
+
+        table.set_hooks(None);
+        assert_eq!(table.hook_stats(), None);
+    }
+
+    #[test]
+    fn test_overflowed_entries_are_found_removed_and_rehashed() {
+        let epoch = Arc::new(LightEpoch::new());
+        let table: DynamicHashTable<u64, u64> = DynamicHashTable::new(epoch.clone());
+        table.set_resize_strategy(ResizeStrategy::None).unwrap();
+        let guard = epoch.protect();
+
+        // Over seven entries per bucket on average, so many overflow
+        let keys = 160;
+        for key in 0..keys {
+            assert_eq!(table.upsert(key, key, &guard).unwrap(), None);
+        }
+        assert_eq!(table.get_statistics().current_bucket_count, 16);
+        assert!(table.get_statistics().overflow_bucket_count > 0);
+        assert!(table.overflow_statistics().overflow_entries > 0);
+        assert!(table.check_invariants(&guard).unwrap().is_clean());
+
+        let depth = |key: u64| {
+            let hash = table.calculate_hash(&key);
+            let buckets = table.load_buckets(&guard);
+            let mut depth = 0;
+            table.overflow
+                .search_overflow_internal(buckets, table.get_bucket_index(hash), hash, &key, &guard, &mut depth)
+                .unwrap()
+                .unwrap();
+            depth
+        };
+        let deep: Vec<u64> = (0..keys).filter(|&key| depth(key) >= 4).collect();
+        assert!(!deep.is_empty());
+
+        // Updates and removals reach entries three or more hops deep
+        for &key in &deep {
+            assert_eq!(table.upsert(key, key + 1, &guard).unwrap(), Some(key));
+            assert_eq!(table.get(&key, &guard).unwrap(), Some(key + 1));
+        }
+        for &key in &deep {
+            assert_eq!(table.remove(&key, &guard).unwrap(), Some(key + 1));
+            assert_eq!(table.get(&key, &guard).unwrap(), None);
+        }
+        let remaining: Vec<u64> = (0..keys).filter(|key| !deep.contains(key)).collect();
+        for &key in &remaining {
+            assert_eq!(table.get(&key, &guard).unwrap(), Some(key));
+        }
+
+        table.consolidate_overflow().unwrap();
+        assert!(table.check_invariants(&guard).unwrap().is_clean());
+
+        // A resize brings chained and probed entries home
+        table.resize().unwrap();
+        table.resize().unwrap();
+        assert_eq!(table.get_statistics().overflow_bucket_count, 0);
+        assert!(table.check_invariants(&guard).unwrap().is_clean());
+        for &key in &remaining {
+            assert_eq!(table.get(&key, &guard).unwrap(), Some(key));
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::sync::atomic::AtomicBool;
+    use loom::thread;
+
+    /// A table holding `1 => 10` and `2 => 20`
+    fn table() -> Arc<DynamicHashTable<u64, u64>> {
+        let table = Arc::new(DynamicHashTable::new(std::sync::Arc::new(LightEpoch::new())));
+        let guard = table.epoch.protect();
+        table.upsert(1, 10, &guard).unwrap();
+        table.upsert(2, 20, &guard).unwrap();
+        table
+    }
+
+    fn entry(key: u64) -> *mut HashEntry<u64, u64> {
+        Box::into_raw(Box::new(HashEntry::new(key, key, key)))
+    }
+
+    /// A bucket with every slot but the last taken
+    fn bucket_with_one_free_slot() -> Arc<HashBucket<u64, u64>> {
+        let bucket = Arc::new(HashBucket::new());
+        for key in 0..HashBucket::<u64, u64>::ENTRIES_PER_BUCKET as u64 - 1 {
+            assert!(bucket.try_insert(entry(key)));
+        }
+        bucket
+    }
+
+    fn occupied(bucket: &HashBucket<u64, u64>) -> usize {
+        bucket
+            .slots()
+            .iter()
+            .filter(|slot| !slot.load(Ordering::Acquire).is_null())
+            .count()
+    }
+
+    #[test]
+    fn racing_publishes_fill_the_last_slot_once() {
+        loom::model(|| {
+            let bucket = bucket_with_one_free_slot();
+            let other = bucket.clone();
+            let theirs = thread::spawn(move || other.try_insert(entry(100)));
+            let mine = bucket.try_insert(entry(200));
+            let theirs = theirs.join().unwrap();
+
+            assert!(mine != theirs);
+            assert_eq!(bucket.entry_count(), HashBucket::<u64, u64>::ENTRIES_PER_BUCKET);
+            let last = bucket.slots()[HashBucket::<u64, u64>::ENTRIES_PER_BUCKET - 1]
+                .load(Ordering::Acquire);
+            assert_eq!(unsafe { (*last).key }, if mine { 200 } else { 100 });
+        });
+    }
+
+    #[test]
+    fn publish_racing_with_removal_keeps_the_count() {
+        loom::model(|| {
+            let bucket = bucket_with_one_free_slot();
+            let last = HashBucket::<u64, u64>::ENTRIES_PER_BUCKET - 1;
+            assert!(bucket.try_insert(entry(6)));
+            let taken = bucket.slots()[3].load(Ordering::Acquire);
+            let other = bucket.clone();
+            let remover = thread::spawn(move || other.take_slot(3, taken));
+            let published = bucket.try_insert(entry(100));
+            assert!(remover.join().unwrap());
+
+            assert_eq!(bucket.entry_count(), occupied(&bucket));
+            assert_eq!(occupied(&bucket), if published { last + 1 } else { last });
+        });
+    }
+
+    #[test]
+    fn gets_racing_an_update_see_a_whole_entry() {
+        loom::model(|| {
+            let table = table();
+            let other = table.clone();
+            let writer = thread::spawn(move || {
+                let guard = other.epoch.protect();
+                other.upsert(1, 11, &guard).unwrap();
+            });
+
+            let guard = table.epoch.protect();
+            let seen = table.get(&1, &guard).unwrap();
+            assert!(seen == Some(10) || seen == Some(11), "{:?}", seen);
+            drop(guard);
+            writer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn gets_racing_a_resize_find_every_key() {
+        // A resize visits every slot of both arrays
+        let mut model = loom::model::Builder::new();
+        model.max_branches = 20_000;
+        model.check(|| {
+            let table = table();
+            let other = table.clone();
+            let resizer = thread::spawn(move || other.resize().unwrap());
+
+            let guard = table.epoch.protect();
+            assert_eq!(table.get(&1, &guard).unwrap(), Some(10));
+            assert_eq!(table.get(&2, &guard).unwrap(), Some(20));
+            drop(guard);
+            resizer.join().unwrap();
+        });
+    }
+
+    /// Sets its flag when dropped
+    struct Tracked(Arc<AtomicBool>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+
+    #[test]
+    fn retired_entries_outlive_guards_that_saw_them() {
+        loom::model(|| {
+            let epoch = Arc::new(LightEpoch::new());
+            let freed = Arc::new(AtomicBool::new(false));
+            let tracked = Box::into_raw(Box::new(Tracked(freed.clone())));
+            let slot = Arc::new(AtomicPtr::new(tracked));
+            let deferred = DeferredFree::new();
+
+            let reader = {
+                let (epoch, freed, slot) = (epoch.clone(), freed.clone(), slot.clone());
+                thread::spawn(move || {
+                    let guard = epoch.protect();
+                    if !slot.load(Ordering::Acquire).is_null() {
+                        // Retired after this guard was taken, so not freed
+                        // before it is dropped
+                        epoch.bump_and_drain();
+                        assert!(!freed.load(Ordering::Acquire));
+                    }
+                    drop(guard);
+                })
+            };
+
+            let guard = epoch.protect();
+            let unlinked = slot.swap(ptr::null_mut(), Ordering::AcqRel);
+            unsafe { deferred.retire(unlinked, &guard) };
+            drop(guard);
+            epoch.bump_and_drain();
+            reader.join().unwrap();
+        });
+    }
+}
@@ -1,11 +1,13 @@
+use crate::core::clock::{Clock, since_unix_epoch, system_clock};
 use crate::core::status::{Status, Result, ContextResult, ErrorContext};
 use crate::core::malloc_fixed_page_size::{FixedPageAddress, MallocFixedPageSize};
 use crate::core::light_epoch::{LightEpoch, Guard};
-use crate::index::hash_bucket::HashBucketEntry;
-use crate::index::dynamic_hash_table::HashBucket;
-use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
-use std::sync::RwLock;
+use crate::core::sync::AtomicPtr;
+use crate::index::dynamic_hash_table::{HashBucket, HashEntry};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::collections::HashMap;
+use std::ptr;
 use std::time::{Duration, Instant};
 
 /// Overflow bucket management strategies
@@ -50,7 +52,10 @@ pub struct OverflowStatistics {
     pub overflow_hits: u64,
     /// Number of failed lookups in overflow areas
     pub overflow_misses: u64,
-    /// Distribution of chain lengths
+    /// Average number of buckets a lookup visited
+    pub average_search_depth: f32,
+    /// Entries placed, by probe distance or position in an overflow chain.
+    /// Rebuilt from the table by each consolidation.
     pub chain_length_distribution: HashMap<u32, u32>,
     /// Time spent in overflow operations
     pub total_overflow_time_ns: u64,
@@ -87,10 +92,14 @@ impl OverflowStatistics {
     }
 }
 
-/// Enhanced overflow bucket with metadata
+/// Enhanced overflow bucket with metadata. The `overflow` link of the
+/// bucket before it in a chain points at `base`, so `base` comes first.
+#[repr(C)]
 pub struct EnhancedOverflowBucket<K, V> {
     /// Base hash bucket functionality
     pub base: HashBucket<K, V>,
+    /// Where this bucket lives in the allocator
+    address: AtomicU64, // Stores FixedPageAddress as u64
     /// Access frequency for hot/cold detection
     access_frequency: AtomicU64,
     /// Last access timestamp
@@ -109,6 +118,7 @@ impl<K, V> EnhancedOverflowBucket<K, V> {
     pub fn new() -> Self {
         Self {
             base: HashBucket::new(),
+            address: AtomicU64::new(0),
             access_frequency: AtomicU64::new(0),
             last_access_time: AtomicU64::new(0),
             chain_position: AtomicU32::new(0),
@@ -118,19 +128,16 @@ impl<K, V> EnhancedOverflowBucket<K, V> {
         }
     }
 
-    /// Record an access to this bucket
-    pub fn record_access(&self) {
+    /// Record an access to this bucket at `now_secs`, in seconds since the
+    /// Unix epoch
+    pub fn record_access(&self, now_secs: u64) {
         self.access_frequency.fetch_add(1, Ordering::Relaxed);
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        self.last_access_time.store(now, Ordering::Relaxed);
-        self.update_health_score();
+        self.last_access_time.store(now_secs, Ordering::Relaxed);
+        self.update_health_score(now_secs);
     }
 
-    /// Update the health score based on various factors
-    fn update_health_score(&self) {
+    /// Update the health score based on various factors as of `now_secs`
+    fn update_health_score(&self, now_secs: u64) {
         let load_factor = self.load_factor.load(Ordering::Relaxed) as f32 / 1000.0;
         let chain_pos = self.chain_position.load(Ordering::Relaxed);
 
@@ -146,12 +153,8 @@ impl<K, V> EnhancedOverflowBucket<K, V> {
         score -= (chain_pos as f32) * 5.0;
 
         // Consider access recency
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
         let last_access = self.last_access_time.load(Ordering::Relaxed);
-        let time_since_access = now.saturating_sub(last_access);
+        let time_since_access = now_secs.saturating_sub(last_access);
 
         if time_since_access > 3600 { // 1 hour
             score -= 20.0;
@@ -159,10 +162,16 @@ impl<K, V> EnhancedOverflowBucket<K, V> {
             score -= 10.0;
         }
 
-        let final_score = score.max(0.0).min(100.0) as u32;
+        let final_score = score.clamp(0.0, 100.0) as u32;
         self.health_score.store(final_score, Ordering::Relaxed);
     }
 
+    /// Refresh the load factor from the entries held
+    fn update_load_factor(&self) {
+        let load = self.base.entry_count() * 1000 / HashBucket::<K, V>::ENTRIES_PER_BUCKET;
+        self.load_factor.store(load as u32, Ordering::Relaxed);
+    }
+
     /// Get current health metrics
     pub fn get_health_metrics(&self) -> (u32, u64, u64, u32) {
         (
@@ -180,6 +189,13 @@ impl<K, V> Default for EnhancedOverflowBucket<K, V> {
     }
 }
 
+/// The bucket and slot an entry was found in
+pub(crate) struct Located<'a, K, V> {
+    pub(crate) bucket: &'a HashBucket<K, V>,
+    pub(crate) slot: usize,
+    pub(crate) entry: *mut HashEntry<K, V>,
+}
+
 /// Enhanced overflow bucket manager
 pub struct EnhancedOverflowManager<'epoch, K, V> {
     /// Strategy for handling overflows
@@ -189,6 +205,7 @@ pub struct EnhancedOverflowManager<'epoch, K, V> {
     /// Statistics tracking
     statistics: RwLock<OverflowStatistics>,
     /// Epoch for memory management
+    #[allow(dead_code)]
     epoch: &'epoch LightEpoch,
     /// Consolidation threshold (number of operations before consolidation)
     consolidation_threshold: AtomicUsize,
@@ -196,12 +213,28 @@ pub struct EnhancedOverflowManager<'epoch, K, V> {
     operations_since_consolidation: AtomicUsize,
     /// Background consolidation enabled
     auto_consolidation: AtomicUsize, // 0 = disabled, 1 = enabled
+    /// Farthest any entry has been probed, which bounds lookups
+    max_probe_distance: AtomicU8,
+    /// Set when a chain reached its length limit
+    resize_requested: AtomicBool,
+    /// Set once any entry has been placed outside its home bucket
+    overflowed: AtomicBool,
+    /// Unlinked overflow buckets no reader can still be walking, waiting to
+    /// go back to the allocator
+    releasable: Arc<Mutex<Vec<FixedPageAddress>>>,
+    /// Time source for bucket access recency
+    clock: Arc<dyn Clock>,
 }
 
 impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
     const DEFAULT_CONSOLIDATION_THRESHOLD: usize = 10000;
 
     pub fn new(epoch: &'epoch LightEpoch) -> Self {
+        Self::with_clock(epoch, system_clock())
+    }
+
+    /// A manager that ages bucket health by `clock`
+    pub fn with_clock(epoch: &'epoch LightEpoch, clock: Arc<dyn Clock>) -> Self {
         let mut allocator = MallocFixedPageSize::new();
         allocator.initialize(64, epoch); // 64-byte alignment for cache efficiency
 
@@ -213,9 +246,19 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
             consolidation_threshold: AtomicUsize::new(Self::DEFAULT_CONSOLIDATION_THRESHOLD),
             operations_since_consolidation: AtomicUsize::new(0),
             auto_consolidation: AtomicUsize::new(1),
+            max_probe_distance: AtomicU8::new(0),
+            resize_requested: AtomicBool::new(false),
+            overflowed: AtomicBool::new(false),
+            releasable: Arc::new(Mutex::new(Vec::new())),
+            clock,
         }
     }
 
+    /// Seconds since the Unix epoch by the manager's clock
+    fn now_secs(&self) -> u64 {
+        since_unix_epoch(self.clock.now_wall()).as_secs()
+    }
+
     /// Set overflow handling strategy
     pub fn set_strategy(&self, strategy: OverflowStrategy) -> Result<()> {
         if let Ok(mut current_strategy) = self.strategy.write() {
@@ -227,14 +270,20 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
     }
 
     /// Allocate a new overflow bucket
-    pub fn allocate_overflow_bucket(&self, _guard: &Guard) -> ContextResult<FixedPageAddress> {
+    pub fn allocate_overflow_bucket(&self, guard: &Guard) -> ContextResult<FixedPageAddress> {
         let start_time = Instant::now();
+        if let Ok(mut releasable) = self.releasable.lock() {
+            for address in releasable.drain(..) {
+                self.allocator.free_at_epoch(address, guard);
+            }
+        }
         let address = self.allocator.allocate();
 
         // Initialize the bucket
         unsafe {
             let bucket = self.allocator.get_unchecked(address);
             *bucket = EnhancedOverflowBucket::new();
+            bucket.address.store(address.control(), Ordering::Relaxed);
         }
 
         // Update statistics
@@ -245,11 +294,18 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
         Ok(address)
     }
 
-    /// Insert entry with overflow handling
-    pub fn insert_with_overflow(
+    /// Insert an entry whose home bucket `bucket_idx` of `table` is full.
+    /// Returns false if the strategy found no place for it, in which case
+    /// the caller still owns `entry`.
+    ///
+    /// # Safety
+    /// `entry` must be a valid entry not yet in any table. Once placed, the
+    /// table owns it.
+    pub unsafe fn insert_with_overflow(
         &self,
-        primary_bucket: &mut HashBucket<K, V>,
-        entry: HashBucketEntry,
+        table: &[HashBucket<K, V>],
+        bucket_idx: usize,
+        entry: *mut HashEntry<K, V>,
         guard: &Guard,
     ) -> ContextResult<bool> {
         let strategy = if let Ok(strategy) = self.strategy.read() {
@@ -258,57 +314,100 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
             return Err(ErrorContext::new(Status::InternalError));
         };
 
+        if table.is_empty() || bucket_idx >= table.len() {
+            return Err(ErrorContext::new(Status::UnexpectedState)
+                .with_context("Home bucket is outside the table"));
+        }
+
         match strategy {
             OverflowStrategy::LinearProbing { max_distance } => {
-                self.insert_with_probing(primary_bucket, entry, max_distance, guard)
+                self.insert_with_probing(table, bucket_idx, entry, max_distance, guard)
             }
             OverflowStrategy::Chaining { max_chain_length } => {
-                self.insert_with_chaining(primary_bucket, entry, max_chain_length, guard)
+                self.insert_with_chaining(table, bucket_idx, entry, max_chain_length, guard)
             }
             OverflowStrategy::Hybrid { probing_distance, max_chain_length } => {
                 // Try probing first, then chaining
-                match self.insert_with_probing(primary_bucket, entry, probing_distance, guard) {
+                match self.insert_with_probing(table, bucket_idx, entry, probing_distance, guard) {
                     Ok(true) => Ok(true),
                     Ok(false) | Err(_) => {
-                        self.insert_with_chaining(primary_bucket, entry, max_chain_length, guard)
+                        self.insert_with_chaining(table, bucket_idx, entry, max_chain_length, guard)
                     }
                 }
             }
             OverflowStrategy::Adaptive => {
-                self.insert_with_adaptive_strategy(primary_bucket, entry, guard)
+                self.insert_with_adaptive_strategy(table, bucket_idx, entry, guard)
             }
         }
     }
 
-    /// Search for entry in overflow areas
-    pub fn search_overflow(
+    /// Search for the entry of `hash` and `key`, whose home bucket is
+    /// `bucket_idx`, in overflow areas
+    pub fn search_overflow<'g>(
         &self,
-        primary_bucket: &HashBucket<K, V>,
-        tag: u16,
-        guard: &Guard,
-    ) -> ContextResult<Option<HashBucketEntry>> {
+        table: &'g [HashBucket<K, V>],
+        bucket_idx: usize,
+        hash: u64,
+        key: &K,
+        guard: &'g Guard,
+    ) -> ContextResult<Option<&'g HashEntry<K, V>>>
+    where
+        K: Eq,
+    {
         let start_time = Instant::now();
         let mut search_depth = 0;
 
-        let result = self.search_overflow_internal(primary_bucket, tag, guard, &mut search_depth);
+        let result = self
+            .search_overflow_internal(table, bucket_idx, hash, key, guard, &mut search_depth)
+            .map(|found| found.map(|located| unsafe { &*located.entry }));
 
         // Update search statistics
-        self.update_search_statistics(start_time.elapsed(), search_depth, result.is_ok());
+        let found = matches!(result, Ok(Some(_)));
+        self.update_search_statistics(start_time.elapsed(), search_depth, found);
 
         result
     }
 
-    /// Consolidate overflow buckets to improve performance
-    pub fn consolidate_overflow_buckets(&self, guard: &Guard) -> ContextResult<u32> {
-        let start_time = Instant::now();
-        // This would implement logic to:
-        // 1. Identify underutilized overflow buckets
-        // 2. Merge entries into primary buckets where possible
-        // 3. Deallocate empty overflow buckets
-        // 4. Reorganize chain structures for better performance
+    /// Unlink the entry of `hash` and `key` from the overflow areas of home
+    /// bucket `bucket_idx`. The caller owns the returned entry and must not
+    /// free it while a guard that could have loaded it is held.
+    ///
+    /// Lookups scan the whole probe window and chain rather than stopping at
+    /// the first free slot, so a removal just empties its slot: probed
+    /// entries need no tombstones and are left where they are, and emptied
+    /// overflow buckets stay linked until the next consolidation.
+    pub fn remove_overflow(
+        &self,
+        table: &[HashBucket<K, V>],
+        bucket_idx: usize,
+        hash: u64,
+        key: &K,
+        guard: &Guard,
+    ) -> ContextResult<Option<*mut HashEntry<K, V>>>
+    where
+        K: Eq,
+    {
+        let mut search_depth = 0;
+        let found = self.search_overflow_internal(table, bucket_idx, hash, key, guard, &mut search_depth)?;
+        Ok(found.and_then(|located| {
+            located.bucket.take_slot(located.slot, located.entry).then_some(located.entry)
+        }))
+    }
 
-        // For now, return a placeholder
-        let consolidated_count = self.consolidate_unhealthy_buckets(guard)?;
+    /// Consolidate the overflow chains of `table`: entries move to the
+    /// earliest bucket of their chain with a free slot, and overflow buckets
+    /// left empty are unlinked and returned to the allocator. Returns the
+    /// number of buckets returned.
+    ///
+    /// Writers to `table` must be shut out while this runs. Readers may
+    /// briefly see an entry in both its old and new slot.
+    pub fn consolidate_overflow_buckets(
+        &self,
+        table: &[HashBucket<K, V>],
+        guard: &Guard,
+    ) -> ContextResult<u32> {
+        let start_time = Instant::now();
+        let consolidated_count = self.consolidate_unhealthy_buckets(table, guard)?;
 
         // Update statistics
         if let Ok(mut stats) = self.statistics.write() {
@@ -332,6 +431,38 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
             .unwrap_or_default()
     }
 
+    /// Whether any entry has ever been placed outside its home bucket. Until
+    /// then a miss in the home bucket needs no overflow search.
+    pub fn has_overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Acquire)
+    }
+
+    /// Overflow buckets currently linked into chains
+    pub fn active_overflow_buckets(&self) -> u64 {
+        self.statistics.read().map(|stats| stats.active_overflow_buckets).unwrap_or(0)
+    }
+
+    /// Returns every overflow bucket chained to `table`, a table no writer
+    /// uses any more, to the allocator as soon as no reader can still be
+    /// walking it. The chains stay linked for those readers.
+    pub(crate) fn release_chains(&self, table: &[HashBucket<K, V>], guard: &Guard) {
+        for primary in table {
+            let mut next = primary.overflow_link().load(Ordering::Acquire);
+            while !next.is_null() {
+                let overflow = unsafe { Self::chained(next) };
+                next = overflow.base.overflow_link().load(Ordering::Acquire);
+                let address = FixedPageAddress::from_control(overflow.address.load(Ordering::Relaxed));
+                self.release_overflow_bucket(address, guard);
+            }
+        }
+    }
+
+    /// Whether a chain has hit its length limit since the last call, meaning
+    /// the table should grow. Clears the request.
+    pub fn take_resize_request(&self) -> bool {
+        self.resize_requested.swap(false, Ordering::AcqRel)
+    }
+
     /// Configure consolidation parameters
     pub fn configure_consolidation(&self, threshold: usize, auto_enabled: bool) {
         self.consolidation_threshold.store(threshold, Ordering::Relaxed);
@@ -340,32 +471,126 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
 
     // Private implementation methods
 
+    /// Places `entry` in the first bucket with a free slot among the home
+    /// bucket and the `max_distance` buckets after it, wrapping around the
+    /// end of the table.
     fn insert_with_probing(
         &self,
-        _primary_bucket: &mut HashBucket<K, V>,
-        _entry: HashBucketEntry,
-        _max_distance: u8,
+        table: &[HashBucket<K, V>],
+        bucket_idx: usize,
+        entry: *mut HashEntry<K, V>,
+        max_distance: u8,
         _guard: &Guard,
     ) -> ContextResult<bool> {
-        // Placeholder for linear probing implementation
+        let start_time = Instant::now();
+        let reach = (max_distance as usize).min(table.len() - 1);
+        for distance in 0..=reach {
+            let bucket = &table[(bucket_idx + distance) % table.len()];
+            // Still unpublished, so nobody else reads the distance yet
+            unsafe { (*entry).probe_distance.store(distance as u8, Ordering::Relaxed) };
+            if bucket.try_insert(entry) {
+                self.max_probe_distance.fetch_max(distance as u8, Ordering::AcqRel);
+                self.update_placement_statistics(start_time.elapsed(), distance as u32);
+                return Ok(true);
+            }
+        }
+        unsafe { (*entry).probe_distance.store(0, Ordering::Relaxed) };
         Ok(false)
     }
 
+    /// The overflow bucket whose `base` a chain link points at
+    ///
+    /// # Safety
+    /// `link` must be a non-null chain link set by this manager.
+    unsafe fn chained<'a>(link: *mut HashBucket<K, V>) -> &'a EnhancedOverflowBucket<K, V> {
+        unsafe { &*(link as *const EnhancedOverflowBucket<K, V>) }
+    }
+
+    /// Places `entry` in the first bucket with a free slot along the chain
+    /// of its home bucket, appending an overflow bucket when all are full.
+    /// A chain already `max_chain_length` overflow buckets long is left as
+    /// it is and a resize is requested instead.
     fn insert_with_chaining(
         &self,
-        _primary_bucket: &mut HashBucket<K, V>,
-        _entry: HashBucketEntry,
-        _max_chain_length: u8,
-        _guard: &Guard,
+        table: &[HashBucket<K, V>],
+        bucket_idx: usize,
+        entry: *mut HashEntry<K, V>,
+        max_chain_length: u8,
+        guard: &Guard,
     ) -> ContextResult<bool> {
-        // Placeholder for chaining implementation
-        Ok(false)
+        let start_time = Instant::now();
+        unsafe { (*entry).probe_distance.store(0, Ordering::Relaxed) };
+        'walk: loop {
+            let mut bucket = &table[bucket_idx];
+            let mut previous: Option<&EnhancedOverflowBucket<K, V>> = None;
+            let mut position = 0;
+            loop {
+                if bucket.try_insert(entry) {
+                    if let Some(overflow) = previous {
+                        overflow.update_load_factor();
+                        overflow.record_access(self.now_secs());
+                    }
+                    self.update_placement_statistics(start_time.elapsed(), position);
+                    return Ok(true);
+                }
+                let next = bucket.overflow_link().load(Ordering::Acquire);
+                if next.is_null() {
+                    break;
+                }
+                let overflow = unsafe { Self::chained(next) };
+                previous = Some(overflow);
+                bucket = &overflow.base;
+                position += 1;
+            }
+
+            if position >= max_chain_length as u32 {
+                if !self.resize_requested.swap(true, Ordering::AcqRel) {
+                    log::debug!("Overflow chain of bucket {} is full, requesting a resize", bucket_idx);
+                }
+                return Ok(false);
+            }
+
+            // Append a bucket. If another writer appended first, give ours
+            // back and walk the chain again.
+            let address = self.allocate_overflow_bucket(guard)?;
+            let overflow = unsafe { self.allocator.get_unchecked(address) };
+            overflow.chain_position.store(position + 1, Ordering::Relaxed);
+            let link: &AtomicPtr<HashBucket<K, V>> = bucket.overflow_link();
+            let appended = link.compare_exchange(
+                ptr::null_mut(),
+                &overflow.base as *const HashBucket<K, V> as *mut HashBucket<K, V>,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+            if appended.is_err() {
+                self.release_overflow_bucket(address, guard);
+                continue 'walk;
+            }
+            if let Some(previous) = previous {
+                previous.next_bucket.store(address.control(), Ordering::Release);
+            }
+        }
+    }
+
+    /// Returns an unlinked overflow bucket to the allocator once the guards
+    /// active now, which may be reading it, are gone.
+    fn release_overflow_bucket(&self, address: FixedPageAddress, guard: &Guard) {
+        let releasable = self.releasable.clone();
+        guard.defer(move || {
+            if let Ok(mut releasable) = releasable.lock() {
+                releasable.push(address);
+            }
+        });
+        if let Ok(mut stats) = self.statistics.write() {
+            stats.active_overflow_buckets = stats.active_overflow_buckets.saturating_sub(1);
+        }
     }
 
     fn insert_with_adaptive_strategy(
         &self,
-        primary_bucket: &mut HashBucket<K, V>,
-        entry: HashBucketEntry,
+        table: &[HashBucket<K, V>],
+        bucket_idx: usize,
+        entry: *mut HashEntry<K, V>,
         guard: &Guard,
     ) -> ContextResult<bool> {
         // Adaptive strategy based on current load and access patterns
@@ -373,37 +598,151 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
 
         if stats.average_chain_length < 2.0 && stats.hit_ratio() > 0.8 {
             // Good performance with chaining, continue using it
-            self.insert_with_chaining(primary_bucket, entry, 4, guard)
+            self.insert_with_chaining(table, bucket_idx, entry, 4, guard)
         } else if stats.overflow_ratio() < 0.3 {
             // Low overflow, try probing first
-            match self.insert_with_probing(primary_bucket, entry, 3, guard) {
+            match self.insert_with_probing(table, bucket_idx, entry, 3, guard) {
                 Ok(true) => Ok(true),
-                _ => self.insert_with_chaining(primary_bucket, entry, 2, guard),
+                _ => self.insert_with_chaining(table, bucket_idx, entry, 2, guard),
             }
         } else {
             // High overflow, be conservative
-            self.insert_with_probing(primary_bucket, entry, 2, guard)
+            self.insert_with_probing(table, bucket_idx, entry, 2, guard)
         }
     }
 
-    fn search_overflow_internal(
+    /// Searches the home bucket, the probe window after it and then the home
+    /// bucket's chain. The window reaches as far as any entry has been
+    /// probed, and chains are followed wherever they exist, so entries placed
+    /// under an earlier strategy are still found. `search_depth` counts the
+    /// buckets visited.
+    pub(crate) fn search_overflow_internal<'a>(
         &self,
-        _primary_bucket: &HashBucket<K, V>,
-        _tag: u16,
+        table: &'a [HashBucket<K, V>],
+        bucket_idx: usize,
+        hash: u64,
+        key: &K,
         _guard: &Guard,
         search_depth: &mut u32,
-    ) -> ContextResult<Option<HashBucketEntry>> {
-        // Placeholder for overflow search implementation
-        *search_depth = 1;
+    ) -> ContextResult<Option<Located<'a, K, V>>>
+    where
+        K: Eq,
+    {
+        if table.is_empty() || bucket_idx >= table.len() {
+            return Err(ErrorContext::new(Status::UnexpectedState)
+                .with_context("Home bucket is outside the table"));
+        }
+
+        // Hashes are compared first, keys only on a hash match
+        let find = |bucket: &'a HashBucket<K, V>| {
+            bucket.slots().iter().enumerate().find_map(|(slot, entry_ptr)| {
+                let entry_ptr = entry_ptr.load(Ordering::Acquire);
+                // Entries are retired through the epoch the caller holds
+                let entry = unsafe { entry_ptr.as_ref()? };
+                (entry.hash == hash && entry.key == *key)
+                    .then_some(Located { bucket, slot, entry: entry_ptr })
+            })
+        };
+
+        *search_depth = 0;
+        let reach = (self.max_probe_distance.load(Ordering::Acquire) as usize).min(table.len() - 1);
+        for distance in 0..=reach {
+            *search_depth += 1;
+            if let Some(located) = find(&table[(bucket_idx + distance) % table.len()]) {
+                return Ok(Some(located));
+            }
+        }
+
+        // Chain buckets are never freed while a guard is held
+        let mut next = table[bucket_idx].overflow_link().load(Ordering::Acquire);
+        while !next.is_null() {
+            *search_depth += 1;
+            let overflow = unsafe { Self::chained(next) };
+            if let Some(located) = find(&overflow.base) {
+                return Ok(Some(located));
+            }
+            next = overflow.base.overflow_link().load(Ordering::Acquire);
+        }
         Ok(None)
     }
 
-    fn consolidate_unhealthy_buckets(&self, _guard: &Guard) -> ContextResult<u32> {
-        // Identify buckets with low health scores and consolidate them
-        // This would implement the actual consolidation logic
-        // For now, return a simulated result
-        let consolidated = 0;
+    fn consolidate_unhealthy_buckets(&self, table: &[HashBucket<K, V>], guard: &Guard) -> ContextResult<u32> {
+        let mut consolidated = 0;
+        let mut distribution: HashMap<u32, u32> = HashMap::new();
+        for primary in table {
+            let mut chain = vec![primary];
+            let mut overflow_buckets = Vec::new();
+            let mut next = primary.overflow_link().load(Ordering::Acquire);
+            while !next.is_null() {
+                let overflow = unsafe { Self::chained(next) };
+                chain.push(&overflow.base);
+                overflow_buckets.push(overflow);
+                next = overflow.base.overflow_link().load(Ordering::Acquire);
+            }
+
+            // Move entries from the end of the chain towards its head. An
+            // entry is published in its new slot before it leaves the old one.
+            let mut target = 0;
+            for source in (1..chain.len()).rev() {
+                for (slot, entry) in chain[source].slots().iter().enumerate() {
+                    let entry = entry.load(Ordering::Acquire);
+                    if entry.is_null() {
+                        continue;
+                    }
+                    while target < source && !chain[target].try_insert(entry) {
+                        target += 1;
+                    }
+                    if target == source {
+                        break;
+                    }
+                    chain[source].take_slot(slot, entry);
+                }
+            }
 
+            // Unlink the empty buckets, which are now all at the tail
+            let kept = overflow_buckets
+                .iter()
+                .take_while(|overflow| overflow.base.entry_count() > 0)
+                .count();
+            if kept < overflow_buckets.len() {
+                chain[kept].overflow_link().store(ptr::null_mut(), Ordering::Release);
+                if let Some(last) = kept.checked_sub(1) {
+                    overflow_buckets[last].next_bucket.store(0, Ordering::Release);
+                }
+                for overflow in &overflow_buckets[kept..] {
+                    let address = FixedPageAddress::from_control(overflow.address.load(Ordering::Relaxed));
+                    self.release_overflow_bucket(address, guard);
+                    consolidated += 1;
+                }
+            }
+
+            // Count every entry where it now sits
+            for (position, bucket) in chain.iter().take(kept + 1).enumerate() {
+                for entry in bucket.slots() {
+                    let entry = entry.load(Ordering::Acquire);
+                    if !entry.is_null() {
+                        let distance = unsafe { (*entry).probe_distance.load(Ordering::Relaxed) } as u32;
+                        *distribution.entry(position as u32 + distance).or_insert(0) += 1;
+                    }
+                }
+            }
+            let now_secs = self.now_secs();
+            for overflow in &overflow_buckets[..kept] {
+                overflow.update_load_factor();
+                overflow.update_health_score(now_secs);
+            }
+        }
+
+        if let Ok(mut stats) = self.statistics.write() {
+            let (entries, weighted_sum) = distribution
+                .iter()
+                .fold((0u64, 0u64), |(entries, sum), (length, count)| {
+                    (entries + *count as u64, sum + (*length as u64) * (*count as u64))
+                });
+            stats.max_chain_length = distribution.keys().copied().max().unwrap_or(0);
+            stats.average_chain_length = if entries > 0 { weighted_sum as f32 / entries as f32 } else { 0.0 };
+            stats.chain_length_distribution = distribution;
+        }
         Ok(consolidated)
     }
 
@@ -415,6 +754,33 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
         }
     }
 
+    fn update_placement_statistics(&self, duration: Duration, distance: u32) {
+        if distance > 0 {
+            self.overflowed.store(true, Ordering::Release);
+        }
+        if let Ok(mut stats) = self.statistics.write() {
+            if distance > 0 {
+                stats.overflow_entries += 1;
+            }
+            stats.total_overflow_time_ns += duration.as_nanos() as u64;
+
+            // Update chain length distribution
+            *stats.chain_length_distribution.entry(distance).or_insert(0) += 1;
+
+            if distance > stats.max_chain_length {
+                stats.max_chain_length = distance;
+            }
+
+            // Recalculate average chain length
+            let (placements, weighted_sum) = stats.chain_length_distribution
+                .iter()
+                .fold((0u64, 0u64), |(placements, sum), (length, count)| {
+                    (placements + *count as u64, sum + (*length as u64) * (*count as u64))
+                });
+            stats.average_chain_length = weighted_sum as f32 / placements as f32;
+        }
+    }
+
     fn update_search_statistics(&self, duration: Duration, depth: u32, found: bool) {
         if let Ok(mut stats) = self.statistics.write() {
             if found {
@@ -425,22 +791,10 @@ impl<'epoch, K, V> EnhancedOverflowManager<'epoch, K, V> {
 
             stats.total_overflow_time_ns += duration.as_nanos() as u64;
 
-            // Update chain length distribution
-            *stats.chain_length_distribution.entry(depth).or_insert(0) += 1;
-
-            if depth > stats.max_chain_length {
-                stats.max_chain_length = depth;
-            }
-
-            // Recalculate average chain length
+            // Running average over all searches
             let total_searches = stats.overflow_hits + stats.overflow_misses;
-            if total_searches > 0 {
-                let weighted_sum: u32 = stats.chain_length_distribution
-                    .iter()
-                    .map(|(length, count)| length * count)
-                    .sum();
-                stats.average_chain_length = weighted_sum as f32 / total_searches as f32;
-            }
+            stats.average_search_depth +=
+                (depth as f32 - stats.average_search_depth) / total_searches as f32;
         }
     }
 
@@ -487,6 +841,7 @@ impl<K, V> OverflowBucketOps for HashBucket<K, V> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::clock::MockClock;
 
     #[test]
     fn test_overflow_manager_creation() {
@@ -529,8 +884,233 @@ mod tests {
         assert_eq!(position, 0);
 
         // Test access recording
-        bucket.record_access();
-        let (_, frequency_after, _, _) = bucket.get_health_metrics();
+        bucket.record_access(1_000);
+        let (_, frequency_after, last_access, _) = bucket.get_health_metrics();
         assert_eq!(frequency_after, 1);
+        assert_eq!(last_access, 1_000);
+    }
+
+    #[test]
+    fn test_bucket_health_ages_with_the_clock() {
+        let epoch = LightEpoch::new();
+        let clock = Arc::new(MockClock::new());
+        let manager: EnhancedOverflowManager<u64, u64> =
+            EnhancedOverflowManager::with_clock(&epoch, clock.clone());
+        let bucket: EnhancedOverflowBucket<u64, u64> = EnhancedOverflowBucket::new();
+
+        bucket.record_access(manager.now_secs());
+        assert_eq!(bucket.get_health_metrics().0, 100);
+
+        clock.advance(Duration::from_secs(301));
+        bucket.update_health_score(manager.now_secs());
+        assert_eq!(bucket.get_health_metrics().0, 90);
+
+        clock.advance(Duration::from_secs(3600));
+        bucket.update_health_score(manager.now_secs());
+        assert_eq!(bucket.get_health_metrics().0, 80);
+    }
+
+    /// Keys of each bucket along the chain of `table[bucket_idx]`
+    fn chain_keys(table: &[HashBucket<u64, u64>], bucket_idx: usize) -> Vec<Vec<u64>> {
+        let mut chain = Vec::new();
+        let mut bucket = &table[bucket_idx];
+        loop {
+            chain.push(
+                bucket.slots()
+                    .iter()
+                    .map(|slot| slot.load(Ordering::Relaxed))
+                    .filter(|entry| !entry.is_null())
+                    .map(|entry| unsafe { (*entry).key })
+                    .collect(),
+            );
+            let next = bucket.overflow_link().load(Ordering::Relaxed);
+            if next.is_null() {
+                return chain;
+            }
+            bucket = unsafe { &EnhancedOverflowManager::<u64, u64>::chained(next).base };
+        }
+    }
+
+    fn free_entries(table: &[HashBucket<u64, u64>]) {
+        for primary in table {
+            let mut bucket = primary;
+            loop {
+                for slot in bucket.slots() {
+                    let entry = slot.load(Ordering::Relaxed);
+                    if !entry.is_null() {
+                        unsafe { drop(Box::from_raw(entry)) };
+                    }
+                }
+                let next = bucket.overflow_link().load(Ordering::Relaxed);
+                if next.is_null() {
+                    break;
+                }
+                bucket = unsafe { &EnhancedOverflowManager::<u64, u64>::chained(next).base };
+            }
+        }
+    }
+
+    #[test]
+    fn test_linear_probing_across_distances() {
+        let epoch = LightEpoch::new();
+        let manager: EnhancedOverflowManager<u64, u64> = EnhancedOverflowManager::new(&epoch);
+        manager.set_strategy(OverflowStrategy::LinearProbing { max_distance: 2 }).unwrap();
+        let table: Vec<HashBucket<u64, u64>> = (0..8).map(|_| HashBucket::new()).collect();
+        let guard = epoch.protect();
+
+        // Every key hashes to bucket 6, so the window 6, 7, 0 fills up
+        let home = 6;
+        let per_bucket = HashBucket::<u64, u64>::ENTRIES_PER_BUCKET as u64;
+        for key in 0..3 * per_bucket {
+            let entry = Box::into_raw(Box::new(HashEntry::new(key, key * 10, home)));
+            assert!(unsafe { manager.insert_with_overflow(&table, home as usize, entry, &guard) }.unwrap());
+        }
+        let entry = Box::into_raw(Box::new(HashEntry::new(99, 990, home)));
+        assert!(!unsafe { manager.insert_with_overflow(&table, home as usize, entry, &guard) }.unwrap());
+        unsafe { drop(Box::from_raw(entry)) };
+        assert_eq!(table[0].entry_count(), per_bucket as usize);
+        assert_eq!(table[1].entry_count(), 0);
+
+        for key in 0..3 * per_bucket {
+            let entry = manager.search_overflow(&table, 6, home, &key, &guard).unwrap().unwrap();
+            assert_eq!(entry.value, key * 10);
+            assert_eq!(entry.probe_distance.load(Ordering::Relaxed) as u64, key / per_bucket);
+        }
+        assert!(manager.search_overflow(&table, 6, home, &99, &guard).unwrap().is_none());
+        // The same key under another hash is a different entry
+        assert!(manager.search_overflow(&table, 6, home + 8, &0, &guard).unwrap().is_none());
+
+        let stats = manager.get_statistics();
+        assert_eq!(stats.overflow_entries, 2 * per_bucket);
+        assert_eq!(stats.max_chain_length, 2);
+        for distance in 0..3 {
+            assert_eq!(stats.chain_length_distribution[&distance], per_bucket as u32);
+        }
+        assert_eq!(stats.average_chain_length, 1.0);
+        assert_eq!(stats.overflow_hits, 3 * per_bucket);
+        assert_eq!(stats.overflow_misses, 2);
+
+        // Removing from the home bucket leaves probed entries reachable
+        for key in [0, 2 * per_bucket, per_bucket + 1] {
+            let removed = manager.remove_overflow(&table, 6, home, &key, &guard).unwrap().unwrap();
+            assert_eq!(unsafe { Box::from_raw(removed) }.key, key);
+            assert!(manager.search_overflow(&table, 6, home, &key, &guard).unwrap().is_none());
+        }
+        assert!(manager.remove_overflow(&table, 6, home, &0, &guard).unwrap().is_none());
+        for key in (1..3 * per_bucket).filter(|key| ![2 * per_bucket, per_bucket + 1].contains(key)) {
+            assert!(manager.search_overflow(&table, 6, home, &key, &guard).unwrap().is_some());
+        }
+
+        // Freed slots are reused, nearest first
+        let entry = Box::into_raw(Box::new(HashEntry::new(99, 990, home)));
+        assert!(unsafe { manager.insert_with_overflow(&table, home as usize, entry, &guard) }.unwrap());
+        assert_eq!(unsafe { (*entry).probe_distance.load(Ordering::Relaxed) }, 0);
+
+        free_entries(&table);
+    }
+
+    #[test]
+    fn test_chaining_then_consolidation() {
+        let epoch = LightEpoch::new();
+        let manager: EnhancedOverflowManager<u64, u64> = EnhancedOverflowManager::new(&epoch);
+        manager.set_strategy(OverflowStrategy::Chaining { max_chain_length: 3 }).unwrap();
+        let table: Vec<HashBucket<u64, u64>> = (0..4).map(|_| HashBucket::new()).collect();
+        let guard = epoch.protect();
+
+        // The home bucket and three overflow buckets fill up in order
+        let per_bucket = HashBucket::<u64, u64>::ENTRIES_PER_BUCKET as u64;
+        for key in 0..4 * per_bucket {
+            let entry = Box::into_raw(Box::new(HashEntry::new(key, key, 1)));
+            assert!(unsafe { manager.insert_with_overflow(&table, 1, entry, &guard) }.unwrap());
+        }
+        let chain = chain_keys(&table, 1);
+        assert_eq!(chain.len(), 4);
+        assert!(chain.iter().all(|keys| keys.len() == per_bucket as usize));
+        assert_eq!(chain[3], (3 * per_bucket..4 * per_bucket).collect::<Vec<_>>());
+
+        // A fourth overflow bucket is over the limit
+        assert!(!manager.take_resize_request());
+        let entry = Box::into_raw(Box::new(HashEntry::new(99, 99, 1)));
+        assert!(!unsafe { manager.insert_with_overflow(&table, 1, entry, &guard) }.unwrap());
+        unsafe { drop(Box::from_raw(entry)) };
+        assert!(manager.take_resize_request());
+        assert!(!manager.take_resize_request());
+
+        let stats = manager.get_statistics();
+        assert_eq!(stats.total_overflow_buckets, 3);
+        assert_eq!(stats.active_overflow_buckets, 3);
+        assert_eq!(stats.overflow_entries, 3 * per_bucket);
+        assert_eq!(stats.max_chain_length, 3);
+
+        // Empty the home bucket and the first overflow bucket
+        let mut bucket = &table[1];
+        for _ in 0..2 {
+            for (slot, entry) in bucket.slots().iter().enumerate() {
+                let entry = entry.load(Ordering::Relaxed);
+                assert!(bucket.take_slot(slot, entry));
+                unsafe { drop(Box::from_raw(entry)) };
+            }
+            bucket = unsafe { &EnhancedOverflowManager::<u64, u64>::chained(bucket.overflow_link().load(Ordering::Relaxed)).base };
+        }
+
+        // The last two overflow buckets' entries move up and both go back
+        assert_eq!(manager.consolidate_overflow_buckets(&table, &guard).unwrap(), 2);
+        let chain = chain_keys(&table, 1);
+        assert_eq!(chain.len(), 2);
+        let mut keys: Vec<u64> = chain.concat();
+        keys.sort_unstable();
+        assert_eq!(keys, (2 * per_bucket..4 * per_bucket).collect::<Vec<_>>());
+
+        let stats = manager.get_statistics();
+        assert_eq!(stats.active_overflow_buckets, 1);
+        assert_eq!(stats.consolidations_performed, 1);
+        assert_eq!(stats.max_chain_length, 1);
+        assert_eq!(stats.chain_length_distribution[&0], per_bucket as u32);
+        assert_eq!(stats.chain_length_distribution[&1], per_bucket as u32);
+        assert_eq!(stats.average_chain_length, 0.5);
+
+        // Nothing left to consolidate
+        assert_eq!(manager.consolidate_overflow_buckets(&table, &guard).unwrap(), 0);
+        assert_eq!(chain_keys(&table, 1).len(), 2);
+
+        free_entries(&table);
+    }
+
+    #[test]
+    fn test_search_and_remove_three_hops_deep() {
+        let epoch = LightEpoch::new();
+        let manager: EnhancedOverflowManager<u64, u64> = EnhancedOverflowManager::new(&epoch);
+        manager.set_strategy(OverflowStrategy::Hybrid { probing_distance: 1, max_chain_length: 3 }).unwrap();
+        let table: Vec<HashBucket<u64, u64>> = (0..4).map(|_| HashBucket::new()).collect();
+        let guard = epoch.protect();
+
+        // Home bucket 2, then bucket 3 by probing, then three chained buckets
+        let per_bucket = HashBucket::<u64, u64>::ENTRIES_PER_BUCKET as u64;
+        for key in 0..5 * per_bucket {
+            let entry = Box::into_raw(Box::new(HashEntry::new(key, key, 2)));
+            assert!(unsafe { manager.insert_with_overflow(&table, 2, entry, &guard) }.unwrap());
+        }
+        assert_eq!(chain_keys(&table, 2).len(), 4);
+        assert_eq!(chain_keys(&table, 3), vec![(per_bucket..2 * per_bucket).collect::<Vec<_>>()]);
+
+        // The last key sits in the third overflow bucket: the home bucket,
+        // the probed one and three chained ones are searched
+        let deepest = 5 * per_bucket - 1;
+        let entry = manager.search_overflow(&table, 2, 2, &deepest, &guard).unwrap().unwrap();
+        assert_eq!(entry.key, deepest);
+        assert_eq!(manager.get_statistics().average_search_depth, 5.0);
+        assert!(manager.search_overflow(&table, 2, 2, &(5 * per_bucket), &guard).unwrap().is_none());
+        assert_eq!(manager.get_statistics().average_search_depth, 5.0);
+
+        let removed = manager.remove_overflow(&table, 2, 2, &deepest, &guard).unwrap().unwrap();
+        assert_eq!(unsafe { Box::from_raw(removed) }.key, deepest);
+        assert!(manager.search_overflow(&table, 2, 2, &deepest, &guard).unwrap().is_none());
+        for key in 0..deepest {
+            assert!(manager.search_overflow(&table, 2, 2, &key, &guard).unwrap().is_some());
+        }
+        let stats = manager.get_statistics();
+        assert_eq!((stats.overflow_hits, stats.overflow_misses), (deepest + 1, 2));
+
+        free_entries(&table);
     }
 }
\ No newline at end of file
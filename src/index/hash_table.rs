@@ -96,6 +96,14 @@ impl<D: HashIndexDefinition> InternalHashTable<D> {
         self.size
     }
 
+    /// Start and length in bytes of the bucket array.
+    pub(crate) fn bucket_memory(&self) -> (*mut u8, usize) {
+        (
+            self.buckets as *mut u8,
+            self.size as usize * std::mem::size_of::<D::HashBucket>(),
+        )
+    }
+
     /// Gets a reference to the bucket at the specified index.
     ///
     /// # Safety
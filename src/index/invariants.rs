@@ -0,0 +1,38 @@
+//! Structural checks of hash index buckets, for catching bugs early.
+//!
+//! Checks load entries one at a time, so concurrent writers can show up as
+//! passing violations. The result is exact only on a quiescent index.
+
+/// A broken index invariant. Buckets are indices into the current table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexViolation {
+    /// A bucket's entry count differs from its occupied slots
+    EntryCountMismatch {
+        bucket: u64,
+        recorded: u64,
+        actual: u64,
+    },
+    /// An entry whose hash maps to another bucket under the current size
+    MisplacedEntry { bucket: u64, hash: u64 },
+    /// An overflow chain longer than the index allows, or a cycle
+    ChainTooLong { bucket: u64, length: u64 },
+    /// Two entries in one chain with the same hash and key
+    DuplicateEntry { bucket: u64, hash: u64 },
+    /// Two entries in one chain with the same tag, of which lookups only
+    /// ever find the first
+    DuplicateTag { bucket: u64, tag: u16 },
+}
+
+/// What an index invariant check covered and found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvariantReport {
+    pub buckets_checked: u64,
+    pub entries_checked: u64,
+    pub violations: Vec<IndexViolation>,
+}
+
+impl InvariantReport {
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
@@ -10,6 +10,7 @@ use crate::index::hash_bucket::{
     AtomicHashBucketEntry, HashBucketEntry, HashBucketOverflowEntry, HotLogIndexHashBucket,
 };
 use crate::index::hash_table::InternalHashTable;
+use crate::index::invariants::{IndexViolation, InvariantReport};
 use crate::index::key_hash::HotLogKeyHash;
 
 /// The in-memory hash index for FASTER.
@@ -57,6 +58,132 @@ impl<'epoch> HotLogMemHashIndex<'epoch> {
         self.table[self.version as usize].size()
     }
 
+    /// Start and length in bytes of the current bucket array.
+    pub(crate) fn bucket_memory(&self) -> (*mut u8, usize) {
+        self.table[self.version as usize].bucket_memory()
+    }
+
+    /// Number of live entries in the current table and its overflow buckets.
+    pub fn entry_count(&self) -> u64 {
+        let mut count = 0;
+        self.for_each_entry(|_, _| count += 1);
+        count
+    }
+
+    /// Calls `f` with the bucket index and entry of every live entry in the
+    /// current table, following overflow chains. Entries are loaded one at a
+    /// time, so concurrent updates may or may not be seen.
+    pub fn for_each_entry(&self, mut f: impl FnMut(u64, HashBucketEntry)) {
+        let version = self.version as usize;
+        for bucket_idx in 0..self.table[version].size() {
+            let mut bucket: &HotLogIndexHashBucket =
+                unsafe { self.table[version].get_bucket(bucket_idx) };
+            loop {
+                for entry in bucket.entries.iter().map(|entry| entry.load()) {
+                    if !entry.unused() && !entry.tentative() {
+                        f(bucket_idx, entry);
+                    }
+                }
+                let overflow_entry = bucket.overflow_entry.load();
+                if overflow_entry.unused() {
+                    break;
+                }
+                bucket = unsafe {
+                    self.overflow_buckets_allocator[version].get_unchecked(overflow_entry.address())
+                };
+            }
+        }
+    }
+
+    /// Checks that no overflow chain of the current table is longer than the
+    /// overflow buckets allocated, which would mean a cycle, and that no
+    /// chain holds two live entries with the same tag. Entries carry no hash,
+    /// so their bucket is checked by [`RsKv::verify`] instead.
+    ///
+    /// [`RsKv::verify`]: crate::rskv_core::RsKv::verify
+    pub fn check_invariants(&self) -> InvariantReport {
+        let version = self.version as usize;
+        let max_chain = self.overflow_buckets_allocator[version].count().control();
+        let mut report = InvariantReport::default();
+        for bucket_idx in 0..self.table[version].size() {
+            let mut bucket: &HotLogIndexHashBucket =
+                unsafe { self.table[version].get_bucket(bucket_idx) };
+            let mut tags = Vec::new();
+            let mut length = 0;
+            loop {
+                report.buckets_checked += 1;
+                for entry in bucket.entries.iter().map(|entry| entry.load()) {
+                    if entry.unused() || entry.tentative() {
+                        continue;
+                    }
+                    report.entries_checked += 1;
+                    if tags.contains(&entry.tag()) {
+                        report.violations.push(IndexViolation::DuplicateTag {
+                            bucket: bucket_idx,
+                            tag: entry.tag(),
+                        });
+                    }
+                    tags.push(entry.tag());
+                }
+                let overflow_entry = bucket.overflow_entry.load();
+                if overflow_entry.unused() {
+                    break;
+                }
+                length += 1;
+                if length > max_chain {
+                    report.violations.push(IndexViolation::ChainTooLong { bucket: bucket_idx, length });
+                    break;
+                }
+                bucket = unsafe {
+                    self.overflow_buckets_allocator[version].get_unchecked(overflow_entry.address())
+                };
+            }
+        }
+        report
+    }
+
+    /// Overwrites slot `slot` of bucket `bucket_idx`, bypassing the index
+    /// protocol, so tests can break invariants on purpose.
+    #[cfg(test)]
+    pub(crate) fn store_entry_for_test(&self, bucket_idx: u64, slot: usize, entry: HashBucketEntry) {
+        let bucket: &HotLogIndexHashBucket =
+            unsafe { self.table[self.version as usize].get_bucket(bucket_idx) };
+        bucket.entries[slot].store(entry);
+    }
+
+    /// Points the entry of each `(key_hash, address)` at `address`, creating
+    /// entries as needed. Just before an entry is swapped, `link` is called
+    /// with the new address and the context holding the entry it replaces,
+    /// so the caller can chain the record to its predecessor. Entries are applied grouped
+    /// by bucket, in their given order within a bucket, under one epoch
+    /// protection for the whole batch.
+    pub fn apply_batch(
+        &self,
+        entries: &[(u64, Address)],
+        mut link: impl FnMut(Address, &FindContext),
+    ) -> Status {
+        let _guard = self.epoch.map(|epoch| epoch.protect());
+        let table_size = self.size();
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by_key(|i| HotLogKeyHash::new(entries[*i].0).table_index(table_size));
+
+        for i in order {
+            let (key_hash, address) = entries[i];
+            let mut context = FindContext::new(key_hash);
+            loop {
+                let status = self.find_or_create_entry(&mut context);
+                if status != Status::Ok {
+                    return status;
+                }
+                link(address, &context);
+                if self.try_update_entry(&context, address, false) == Status::Ok {
+                    break;
+                }
+            }
+        }
+        Status::Ok
+    }
+
     pub fn new() -> Self {
         Self {
             table: [InternalHashTable::new(), InternalHashTable::new()],
@@ -156,6 +283,7 @@ impl<'epoch> HotLogMemHashIndex<'epoch> {
             Default::default(),
         )?;
         self.table[version].checkpoint(&mut ht_file)?;
+        disk.sync_file(&ht_file)?;
 
         let mut ofb_file = disk.new_file(&format!("index-checkpoints/{}/ofb.dat", token));
         ofb_file.open(
@@ -163,12 +291,14 @@ impl<'epoch> HotLogMemHashIndex<'epoch> {
             Default::default(),
         )?;
         let ofb_bytes = self.overflow_buckets_allocator[version].checkpoint(&mut ofb_file)?;
+        disk.sync_file(&ofb_file)?;
 
         let mut metadata = IndexMetadata::default();
         metadata.table_size = self.table[version].size();
         metadata.num_ht_bytes =
             metadata.table_size * std::mem::size_of::<HotLogIndexHashBucket>() as u64;
         metadata.num_ofb_bytes = ofb_bytes;
+        metadata.ofb_count = self.overflow_buckets_allocator[version].count();
 
         Ok(metadata)
     }
@@ -5,6 +5,7 @@ pub mod dynamic_hash_table;
 pub mod enhanced_overflow_management;
 pub mod hash_bucket;
 pub mod hash_table;
+pub mod invariants;
 pub mod key_hash;
 pub mod mem_index;
 
@@ -0,0 +1,451 @@
+//! Key encodings whose byte order matches value order.
+//!
+//! Comparing encoded keys with `<` on `[u8]` gives the same result as
+//! comparing the values, so byte-ordered ranges and prefixes line up with
+//! value ranges:
+//!
+//! - unsigned integers are written big-endian;
+//! - signed integers also get their sign bit flipped, so negatives sort
+//!   first;
+//! - `f64` follows [`f64::total_cmp`]: negative values have all bits
+//!   flipped, the rest only the sign bit;
+//! - strings and byte strings escape `0x00` as `0x00 0xFF` and end with
+//!   `0x00 0x01`, so a string sorts before every longer string it prefixes;
+//! - tuples concatenate their fields, so the encoding of a leading part of
+//!   a tuple is a prefix of the encoding of the whole tuple.
+//!
+//! ```
+//! use rskv::keys::{decode, encode};
+//!
+//! let alice_1 = encode(&("alice", 1u64));
+//! let alice_20 = encode(&("alice", 20u64));
+//! let bob_0 = encode(&("bob", 0u64));
+//! assert!(alice_1 < alice_20 && alice_20 < bob_0);
+//!
+//! // Everything stored under ("alice", _) starts with this prefix
+//! let prefix = encode(&"alice");
+//! assert!(alice_1.starts_with(&prefix) && !bob_0.starts_with(&prefix));
+//!
+//! assert_eq!(decode::<(String, u64)>(&alice_20), Ok(("alice".to_string(), 20)));
+//! ```
+//!
+//! The fixed-width encodings, such as [`encode_i64`], also suit `RsKv`
+//! keys, which are fixed-size.
+
+use crate::core::status::Status;
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+const TERMINATOR: u8 = 0x01;
+
+pub fn encode_u64(value: u64) -> [u8; 8] {
+    value.to_be_bytes()
+}
+
+pub fn decode_u64(bytes: [u8; 8]) -> u64 {
+    u64::from_be_bytes(bytes)
+}
+
+pub fn encode_i64(value: i64) -> [u8; 8] {
+    ((value as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+pub fn decode_i64(bytes: [u8; 8]) -> i64 {
+    (u64::from_be_bytes(bytes) ^ (1 << 63)) as i64
+}
+
+/// Orders like [`f64::total_cmp`]: -NaN, -inf, ..., -0.0, 0.0, ..., inf, NaN
+pub fn encode_f64(value: f64) -> [u8; 8] {
+    let bits = value.to_bits();
+    let bits = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits ^ (1 << 63)
+    };
+    bits.to_be_bytes()
+}
+
+pub fn decode_f64(bytes: [u8; 8]) -> f64 {
+    let bits = u64::from_be_bytes(bytes);
+    let bits = if bits >> 63 == 1 {
+        bits ^ (1 << 63)
+    } else {
+        !bits
+    };
+    f64::from_bits(bits)
+}
+
+/// Appends `bytes` escaped and terminated
+pub fn encode_bytes_to(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        out.push(byte);
+        if byte == ESCAPE {
+            out.push(ESCAPED_ZERO);
+        }
+    }
+    out.extend_from_slice(&[ESCAPE, TERMINATOR]);
+}
+
+/// Reads one escaped, terminated byte string from the front of `input`
+pub fn decode_bytes_from(input: &mut &[u8]) -> Result<Vec<u8>, Status> {
+    let mut bytes = Vec::new();
+    let mut rest = *input;
+    loop {
+        match rest {
+            [ESCAPE, TERMINATOR, tail @ ..] => {
+                *input = tail;
+                return Ok(bytes);
+            }
+            [ESCAPE, ESCAPED_ZERO, tail @ ..] => {
+                bytes.push(ESCAPE);
+                rest = tail;
+            }
+            [ESCAPE, ..] | [] => return Err(Status::InvalidDataFormat),
+            [byte, tail @ ..] => {
+                bytes.push(*byte);
+                rest = tail;
+            }
+        }
+    }
+}
+
+/// A value with an order-preserving byte encoding
+pub trait OrderedKey: Sized {
+    fn encode_to(&self, out: &mut Vec<u8>);
+    /// Reads a value from the front of `input` and advances past it
+    fn decode_from(input: &mut &[u8]) -> Result<Self, Status>;
+}
+
+/// Encodes `value`; see the module documentation for the formats
+pub fn encode<T: OrderedKey>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode_to(&mut out);
+    out
+}
+
+/// Decodes a value that spans all of `bytes`
+pub fn decode<T: OrderedKey>(mut bytes: &[u8]) -> Result<T, Status> {
+    let value = T::decode_from(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(Status::InvalidDataFormat);
+    }
+    Ok(value)
+}
+
+fn take<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], Status> {
+    let Some((head, tail)) = input.split_first_chunk::<N>() else {
+        return Err(Status::InvalidDataFormat);
+    };
+    *input = tail;
+    Ok(*head)
+}
+
+macro_rules! ordered_unsigned {
+    ($($ty:ty),*) => {$(
+        impl OrderedKey for $ty {
+            fn encode_to(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+
+            fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+                Ok(<$ty>::from_be_bytes(take(input)?))
+            }
+        }
+    )*};
+}
+
+macro_rules! ordered_signed {
+    ($($ty:ty => $unsigned:ty),*) => {$(
+        impl OrderedKey for $ty {
+            fn encode_to(&self, out: &mut Vec<u8>) {
+                let flipped = (*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1));
+                out.extend_from_slice(&flipped.to_be_bytes());
+            }
+
+            fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+                let flipped = <$unsigned>::from_be_bytes(take(input)?);
+                Ok((flipped ^ (1 << (<$unsigned>::BITS - 1))) as $ty)
+            }
+        }
+    )*};
+}
+
+ordered_unsigned!(u8, u16, u32, u64, u128);
+ordered_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl OrderedKey for bool {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+        match take::<1>(input)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(Status::InvalidDataFormat),
+        }
+    }
+}
+
+impl OrderedKey for f64 {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&encode_f64(*self));
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+        Ok(decode_f64(take(input)?))
+    }
+}
+
+impl OrderedKey for String {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        encode_bytes_to(self.as_bytes(), out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+        String::from_utf8(decode_bytes_from(input)?).map_err(|_| Status::InvalidDataFormat)
+    }
+}
+
+impl OrderedKey for Vec<u8> {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        encode_bytes_to(self, out);
+    }
+
+    fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+        decode_bytes_from(input)
+    }
+}
+
+/// Encodes like `String`, for building keys from borrowed strings. Decode
+/// into `String`.
+impl OrderedKey for &str {
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        encode_bytes_to(self.as_bytes(), out);
+    }
+
+    fn decode_from(_input: &mut &[u8]) -> Result<Self, Status> {
+        Err(Status::FeatureNotSupported)
+    }
+}
+
+macro_rules! ordered_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: OrderedKey),+> OrderedKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode_to(&self, out: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode_to(out);)+
+            }
+
+            fn decode_from(input: &mut &[u8]) -> Result<Self, Status> {
+                Ok(($($name::decode_from(input)?,)+))
+            }
+        }
+    };
+}
+
+ordered_tuple!(A);
+ordered_tuple!(A, B);
+ordered_tuple!(A, B, C);
+ordered_tuple!(A, B, C, D);
+ordered_tuple!(A, B, C, D, E);
+
+/// A fixed-size key that scans can match by its bytes, such as
+/// [`R2Kv::scan_prefix`](crate::R2Kv::scan_prefix).
+///
+/// Byte arrays are taken as they are. Integers are taken in their in-memory
+/// (native-endian) form, so their byte order follows value order only on
+/// big-endian targets; for ordered prefixes and ranges, store keys encoded
+/// with [`encode_u64`] and its siblings instead.
+pub trait KeyBytes {
+    fn key_bytes(&self) -> &[u8];
+}
+
+impl<const N: usize> KeyBytes for [u8; N] {
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+macro_rules! key_bytes_int {
+    ($($ty:ty),*) => {$(
+        impl KeyBytes for $ty {
+            fn key_bytes(&self) -> &[u8] {
+                // Integers have no padding, so every byte is initialized.
+                unsafe {
+                    std::slice::from_raw_parts(self as *const $ty as *const u8, size_of::<$ty>())
+                }
+            }
+        }
+    )*};
+}
+
+key_bytes_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::cmp::Ordering;
+
+    const SAMPLES: usize = 2_000;
+
+    /// Checks that byte order of the encodings equals `cmp` on random pairs,
+    /// and that every value round-trips
+    fn assert_order_preserved<T: OrderedKey + std::fmt::Debug>(
+        values: &[T],
+        cmp: impl Fn(&T, &T) -> Ordering,
+        same: impl Fn(&T, &T) -> bool,
+    ) {
+        let encoded: Vec<Vec<u8>> = values.iter().map(|value| encode(value)).collect();
+        for (i, (value, bytes)) in values.iter().zip(&encoded).enumerate() {
+            let decoded: T = decode(bytes).unwrap();
+            assert!(
+                same(&decoded, value),
+                "{:?} decoded as {:?}",
+                value,
+                decoded
+            );
+            let j = (i * 7919 + 1) % values.len();
+            assert_eq!(
+                encoded[i].cmp(&encoded[j]),
+                cmp(value, &values[j]),
+                "{:?} against {:?}",
+                value,
+                values[j]
+            );
+        }
+    }
+
+    fn random_string(rng: &mut StdRng) -> String {
+        const ALPHABET: [char; 8] = [
+            '\0',
+            '\u{1}',
+            'a',
+            'b',
+            '\u{ff}',
+            '\u{100}',
+            'z',
+            '\u{10ffff}',
+        ];
+        let len = rng.random_range(0..6);
+        (0..len)
+            .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())])
+            .collect()
+    }
+
+    #[test]
+    fn test_integers_keep_order() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut unsigned: Vec<u64> = (0..SAMPLES).map(|_| rng.random()).collect();
+        unsigned.extend([0, 1, u64::MAX]);
+        assert_order_preserved(&unsigned, u64::cmp, |a, b| a == b);
+
+        let mut signed: Vec<i64> = (0..SAMPLES)
+            .map(|_| rng.random::<i64>() >> rng.random_range(0..63))
+            .collect();
+        signed.extend([i64::MIN, -1, 0, 1, i64::MAX]);
+        assert_order_preserved(&signed, i64::cmp, |a, b| a == b);
+        for value in signed {
+            assert_eq!(decode_i64(encode_i64(value)), value);
+        }
+
+        let small: Vec<i8> = (i8::MIN..=i8::MAX).collect();
+        assert_order_preserved(&small, i8::cmp, |a, b| a == b);
+    }
+
+    #[test]
+    fn test_floats_follow_total_order() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut floats: Vec<f64> = (0..SAMPLES)
+            .map(|i| {
+                if i % 2 == 0 {
+                    f64::from_bits(rng.random())
+                } else {
+                    rng.random_range(-1e6..1e6)
+                }
+            })
+            .collect();
+        floats.extend([
+            f64::NEG_INFINITY,
+            -f64::MAX,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NAN,
+            -f64::NAN,
+        ]);
+        assert_order_preserved(&floats, f64::total_cmp, |a, b| a.to_bits() == b.to_bits());
+        assert!(encode_f64(-0.0) < encode_f64(0.0));
+        assert!(encode_f64(-f64::NAN) < encode_f64(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_strings_keep_order_with_embedded_zeros() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut strings: Vec<String> = (0..SAMPLES).map(|_| random_string(&mut rng)).collect();
+        strings.extend(["", "\0", "\0\0", "a", "a\0", "a\0b", "ab"].map(String::from));
+        assert_order_preserved(&strings, String::cmp, |a, b| a == b);
+
+        assert!(encode(&"a") < encode(&"a\0"));
+        assert!(encode(&"a\0") < encode(&"a\u{1}"));
+        assert_eq!(encode(&"a"), encode(&"a".to_string()));
+    }
+
+    #[test]
+    fn test_tuples_order_by_fields_and_share_prefixes() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let tuples: Vec<(String, i64, f64)> = (0..SAMPLES)
+            .map(|_| {
+                (
+                    random_string(&mut rng),
+                    rng.random_range(-3..3),
+                    rng.random_range(-2.0..2.0),
+                )
+            })
+            .collect();
+        assert_order_preserved(
+            &tuples,
+            |a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.total_cmp(&b.2)),
+            |a, b| a == b,
+        );
+
+        for (name, id, score) in &tuples {
+            let full = encode(&(name.clone(), *id, *score));
+            assert!(full.starts_with(&encode(&(name.clone(),))));
+            assert!(full.starts_with(&encode(&(name.clone(), *id))));
+        }
+        // A name that extends another is not caught by the shorter prefix
+        assert!(!encode(&("ab", 0i64)).starts_with(&encode(&"a")));
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_input() {
+        assert_eq!(decode::<u64>(&[1, 2, 3]), Err(Status::InvalidDataFormat));
+        assert_eq!(decode::<u32>(&[0; 5]), Err(Status::InvalidDataFormat));
+        assert_eq!(decode::<String>(b"abc"), Err(Status::InvalidDataFormat));
+        assert_eq!(
+            decode::<String>(&[b'a', 0x00, 0x02]),
+            Err(Status::InvalidDataFormat)
+        );
+        assert_eq!(
+            decode::<String>(&[0xC3, 0x00, 0x01]),
+            Err(Status::InvalidDataFormat)
+        );
+        assert_eq!(decode::<bool>(&[2]), Err(Status::InvalidDataFormat));
+        assert_eq!(decode::<Vec<u8>>(&[0x00, 0xFF, 0x00, 0x01]), Ok(vec![0x00]));
+    }
+
+    #[test]
+    fn test_key_bytes_views_keys_in_place() {
+        assert_eq!(encode_u64(7).key_bytes(), &[0, 0, 0, 0, 0, 0, 0, 7]);
+        assert_eq!(*b"user:001".key_bytes(), *b"user:001");
+        assert_eq!(0x0102_0304u32.key_bytes(), &0x0102_0304u32.to_ne_bytes());
+        assert_eq!((-1i16).key_bytes(), &[0xFF, 0xFF]);
+    }
+}
@@ -1,11 +1,66 @@
+/// Crash-test hook. Compiled out unless testing or the `killpoints`
+/// feature is enabled; see `testing::killpoints`.
+macro_rules! kill_point {
+    ($point:ident) => {
+        #[cfg(any(test, feature = "killpoints"))]
+        $crate::testing::killpoints::hit($crate::testing::killpoints::KillPoint::$point);
+    };
+}
+
+/// Enters a `tracing` span for the rest of the enclosing block. Fields after
+/// the `;` start out empty and are filled in with `span_record!`. Compiled
+/// out unless the `tracing` feature is enabled.
+macro_rules! op_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(; $($empty:ident),+)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            $name
+            $(, $field = $value)*
+            $($(, $empty = tracing::field::Empty)+)?
+        )
+        .entered();
+    };
+}
+
+/// Records a field of the innermost entered span, in its `Debug` form.
+macro_rules! span_record {
+    ($field:ident = $value:expr) => {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record(stringify!($field), tracing::field::debug(&$value));
+    };
+}
+
+/// Emits a `tracing` event inside the current span. Compiled out unless the
+/// `tracing` feature is enabled.
+macro_rules! trace_event {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)+);
+    };
+}
+
+pub mod admin;
+#[cfg(any(test, feature = "bench-support"))]
+pub mod bench_support;
 pub mod core;
 pub mod device;
 pub mod environment;
+pub mod events;
 pub mod r2;
+pub mod repair;
+pub mod replication;
 pub mod rskv_core;
+pub mod secondary_index;
+pub mod sharded;
 pub mod hlog;
 pub mod index;
+pub mod keys;
+#[cfg(feature = "legacy-format")]
+pub mod migrate;
 pub mod performance;
+pub mod planning;
+#[cfg(any(test, feature = "killpoints", feature = "testing"))]
+pub mod testing;
 
 // Re-export commonly used types
 pub use r2::R2Kv;
@@ -1,6 +1,7 @@
 // 专门测试内存指针安全和并发问题的测试套件
 use crate::core::light_epoch::LightEpoch;
 use crate::core::malloc_fixed_page_size::{MallocFixedPageSize, FixedPageAddress};
+use crate::hlog::persistent_memory_malloc::{NullDisk, PersistentMemoryMalloc};
 use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 use std::thread;
 use std::time::Duration;
@@ -334,4 +335,43 @@ mod tests {
             assert!(addr.offset() <= FixedPageAddress::K_MAX_OFFSET as u32);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_concurrent_log_allocation_across_pages() {
+        // 多线程在日志页边界附近并发分配，地址不能重叠，写入的数据不能被覆盖
+        const CHUNK: u64 = 512 * 1024;
+        let epoch = LightEpoch::new();
+        let mut hlog = PersistentMemoryMalloc::<NullDisk>::new();
+        hlog.initialize(4 * PersistentMemoryMalloc::<NullDisk>::K_PAGE_SIZE, &epoch, NullDisk);
+
+        let allocated: Vec<(u64, u64)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8u64)
+                .map(|thread_id| {
+                    let hlog = &hlog;
+                    scope.spawn(move || {
+                        let mut allocated = Vec::new();
+                        for i in 0..24u64 {
+                            let address = hlog.allocate(CHUNK).unwrap();
+                            let tag = thread_id << 32 | i;
+                            unsafe { hlog.get_mut_slice_unchecked(address, 8) }
+                                .copy_from_slice(&tag.to_le_bytes());
+                            allocated.push((address.control(), tag));
+                        }
+                        allocated
+                    })
+                })
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut sorted = allocated.clone();
+        sorted.sort();
+        for pair in sorted.windows(2) {
+            assert!(pair[0].0 + CHUNK <= pair[1].0, "{:x} overlaps {:x}", pair[0].0, pair[1].0);
+        }
+        for (address, tag) in allocated {
+            let bytes = hlog.get_slice(crate::core::address::Address::from_control(address), 8);
+            assert_eq!(u64::from_le_bytes(bytes.try_into().unwrap()), tag);
+        }
+    }
+}
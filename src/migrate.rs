@@ -0,0 +1,126 @@
+//! Offline migration of stores written in an older on-disk format.
+//!
+//! Two formats are read. The legacy format is the log as it was before flush
+//! frames and the superblock: records in fixed-size slots from address 64,
+//! file offsets equal to logical addresses, the first cache line left zero and
+//! no `hlog.frames`. Format version 1 adds the superblock and flush frames,
+//! and only its verified frames are read. Both have 8-byte record headers
+//! without a write time; the migrated store stamps each record with the time
+//! it is written there.
+
+use crate::admin::verified_frames;
+use crate::core::status::Status;
+use crate::device::file_system_disk::FileSystemDisk;
+use crate::environment::file::DirectoryLock;
+use crate::hlog::persistent_memory_malloc::Disk;
+use crate::hlog::superblock::{SUPERBLOCK_SIZE, Superblock, UNTIMED_FORMAT_VERSION};
+use crate::repair::{NewestRecords, for_each_legacy_record};
+use crate::rskv_core::RsKv;
+use std::fs;
+use std::path::Path;
+
+/// Options for the store written by [`migrate_store`].
+#[derive(Debug, Clone)]
+pub struct MigrationOptions {
+    pub log_size: u64,
+    pub table_size: u64,
+    /// Token of the checkpoint taken once every record has been written
+    pub checkpoint_token: String,
+}
+
+impl Default for MigrationOptions {
+    fn default() -> Self {
+        Self {
+            log_size: 1 << 30,
+            table_size: 1 << 20,
+            checkpoint_token: "migrated".to_string(),
+        }
+    }
+}
+
+/// Summary of a [`migrate_store`] run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Live records written to the new store
+    pub records_migrated: u64,
+    /// Keys whose newest old record was a tombstone
+    pub tombstones_skipped: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Copies the live records of the old-format store in `src_dir` into a new
+/// store in `dst_dir` and takes a single checkpoint of it. The source is only
+/// read, under a shared lock. `dst_dir` must not already hold a log.
+/// `key_hash` must match the hash the source was written with.
+pub fn migrate_store<K, V>(
+    src_dir: &str,
+    dst_dir: &str,
+    options: &MigrationOptions,
+    key_hash: impl Fn(&K) -> u64,
+) -> Result<MigrationReport, Status>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+{
+    let _src_lock = DirectoryLock::acquire(src_dir, true)?;
+    let src_log = Path::new(src_dir).join("hlog.log");
+    let log = fs::read(&src_log).map_err(|_| Status::IoError)?;
+    let has_superblock =
+        log.len() >= SUPERBLOCK_SIZE && log[..SUPERBLOCK_SIZE].iter().any(|b| *b != 0);
+    let ranges = if has_superblock {
+        match Superblock::format_version(&log) {
+            Some(version) if version <= UNTIMED_FORMAT_VERSION => {
+                let frames = fs::read(Path::new(src_dir).join("hlog.frames"))
+                    .map_err(|_| Status::IoError)?;
+                verified_frames(&log, &frames)
+                    .into_iter()
+                    .map(|frame| (frame.begin, frame.end))
+                    .collect()
+            }
+            _ => {
+                log::error!(
+                    "{} is not an old-format log: its superblock is current or unreadable",
+                    src_log.display()
+                );
+                return Err(Status::InvalidDataFormat);
+            }
+        }
+    } else {
+        vec![(0, log.len() as u64)]
+    };
+
+    let mut newest = NewestRecords::new();
+    for (begin, end) in ranges {
+        for_each_legacy_record::<K, V>(&log, begin, end, |_, header, key, value| {
+            newest.insert(key_hash(&key), header, key, value)
+        });
+    }
+    let (live, tombstones) = newest.into_live();
+
+    let disk = FileSystemDisk::new(dst_dir)?;
+    if disk.log_size() > 0 {
+        log::error!("migration target {} already holds a log", dst_dir);
+        return Err(Status::InvalidConfiguration);
+    }
+    let mut kv = RsKv::<K, V, FileSystemDisk>::new(options.log_size, options.table_size, disk)?;
+    let mut report = MigrationReport {
+        records_migrated: kv.bulk_load_with_flags(live, &key_hash)?,
+        tombstones_skipped: tombstones,
+        bytes_before: log.len() as u64,
+        ..Default::default()
+    };
+    kv.checkpoint(&options.checkpoint_token)?;
+    report.bytes_after = fs::metadata(Path::new(dst_dir).join("hlog.log"))
+        .map_err(|_| Status::IoError)?
+        .len();
+
+    log::info!(
+        "migrated {} records from {} to {} ({} tombstones skipped)",
+        report.records_migrated,
+        src_dir,
+        dst_dir,
+        report.tombstones_skipped
+    );
+    Ok(report)
+}
@@ -1,6 +1,8 @@
+use crate::core::clock::{Clock, system_clock};
+use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 /// Access pattern type
@@ -20,11 +22,42 @@ pub enum AccessPattern {
     Unknown,
 }
 
+/// How often a key or page has been accessed recently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Heat {
+    Cold,
+    Warm,
+    Hot,
+}
+
+/// Share of the steps between consecutive `positions` that stay put or
+/// move on to the next position, or 0 for fewer than two positions
+pub fn sequential_ratio(positions: impl IntoIterator<Item = u64>) -> f64 {
+    let mut positions = positions.into_iter();
+    let Some(mut last) = positions.next() else {
+        return 0.0;
+    };
+    let (mut steps, mut sequential) = (0u64, 0u64);
+    for position in positions {
+        steps += 1;
+        if position == last || position == last + 1 {
+            sequential += 1;
+        }
+        last = position;
+    }
+    if steps == 0 {
+        0.0
+    } else {
+        sequential as f64 / steps as f64
+    }
+}
+
 /// Access event for tracking
 #[derive(Debug, Clone)]
 struct AccessEvent {
     key_hash: u64,
     timestamp: Instant,
+    #[allow(dead_code)]
     operation_type: OperationType,
 }
 
@@ -65,6 +98,14 @@ pub struct AnalyzerConfig {
     pub hotspot_threshold: f64,
     /// Temporal locality window (in milliseconds)
     pub temporal_window_ms: u64,
+    /// Time for a heat score to halve without further accesses (in milliseconds)
+    pub decay_half_life_ms: u64,
+    /// Decayed access score at or above which a key or page is hot
+    pub hot_threshold: f64,
+    /// Decayed access score at or above which a key or page is warm
+    pub warm_threshold: f64,
+    /// Number of buckets key hashes are folded into for heat tracking
+    pub heat_buckets: u64,
 }
 
 impl Default for AnalyzerConfig {
@@ -75,10 +116,39 @@ impl Default for AnalyzerConfig {
             min_accesses_for_pattern: 100,
             hotspot_threshold: 0.8,
             temporal_window_ms: 1000,
+            decay_half_life_ms: 60_000,
+            hot_threshold: 16.0,
+            warm_threshold: 2.0,
+            heat_buckets: 1 << 16,
         }
     }
 }
 
+/// Access score that halves every half-life
+#[derive(Debug, Clone, Copy)]
+struct DecayedScore {
+    score: f64,
+    updated_ms: u64,
+}
+
+impl DecayedScore {
+    fn at(&self, now_ms: u64, half_life_ms: u64) -> f64 {
+        let elapsed = now_ms.saturating_sub(self.updated_ms) as f64;
+        self.score * 0.5f64.powf(elapsed / half_life_ms.max(1) as f64)
+    }
+}
+
+/// Count of tracked key buckets and pages at each heat
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeatSummary {
+    pub hot_buckets: usize,
+    pub warm_buckets: usize,
+    pub cold_buckets: usize,
+    pub hot_pages: usize,
+    pub warm_pages: usize,
+    pub cold_pages: usize,
+}
+
 /// Access pattern analyzer
 pub struct AccessAnalyzer {
     config: AnalyzerConfig,
@@ -89,11 +159,21 @@ pub struct AccessAnalyzer {
     write_count: AtomicU64,
     update_count: AtomicU64,
     delete_count: AtomicU64,
+    /// Decayed access scores by key hash bucket
+    key_heat: RwLock<HashMap<u64, DecayedScore>>,
+    /// Decayed access scores by log page
+    page_heat: RwLock<HashMap<u32, DecayedScore>>,
+    clock: Arc<dyn Clock>,
     start_time: Instant,
 }
 
 impl AccessAnalyzer {
     pub fn new(config: AnalyzerConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+
+    /// An analyzer that times accesses and decay with `clock`
+    pub fn with_clock(config: AnalyzerConfig, clock: Arc<dyn Clock>) -> Self {
         Self {
             config,
             events: Arc::new(RwLock::new(VecDeque::new())),
@@ -103,7 +183,10 @@ impl AccessAnalyzer {
             write_count: AtomicU64::new(0),
             update_count: AtomicU64::new(0),
             delete_count: AtomicU64::new(0),
-            start_time: Instant::now(),
+            key_heat: RwLock::new(HashMap::new()),
+            page_heat: RwLock::new(HashMap::new()),
+            start_time: clock.now_monotonic(),
+            clock,
         }
     }
 
@@ -120,7 +203,7 @@ impl AccessAnalyzer {
 
         let event = AccessEvent {
             key_hash,
-            timestamp: Instant::now(),
+            timestamp: self.clock.now_monotonic(),
             operation_type,
         };
 
@@ -128,6 +211,7 @@ impl AccessAnalyzer {
         if let Ok(mut frequencies) = self.key_frequencies.write() {
             *frequencies.entry(key_hash).or_insert(0) += 1;
         }
+        self.bump(&self.key_heat, self.heat_bucket(key_hash));
 
         // Add to event history
         if let Ok(mut events) = self.events.write() {
@@ -139,6 +223,124 @@ impl AccessAnalyzer {
         }
     }
 
+    /// Get the number of recorded accesses for a key
+    pub fn get_key_frequency(&self, key_hash: u64) -> u64 {
+        self.key_frequencies
+            .read()
+            .ok()
+            .and_then(|frequencies| frequencies.get(&key_hash).copied())
+            .unwrap_or(0)
+    }
+
+    /// Record an access to a record on log page `page`
+    pub fn record_page_access(&self, page: u32) {
+        self.bump(&self.page_heat, page);
+    }
+
+    /// Classify a key by its recent accesses
+    pub fn classify(&self, key_hash: u64) -> Heat {
+        self.heat_of(self.key_score(key_hash))
+    }
+
+    /// Decayed access score of a key's bucket
+    pub fn key_score(&self, key_hash: u64) -> f64 {
+        self.score(&self.key_heat, self.heat_bucket(key_hash))
+    }
+
+    /// Classify a log page by the recent accesses to its records
+    pub fn page_heat(&self, page: u32) -> Heat {
+        self.heat_of(self.score(&self.page_heat, page))
+    }
+
+    /// Log pages at `min_heat` or hotter, hottest first
+    pub fn pages_at_least(&self, min_heat: Heat) -> Vec<u32> {
+        let now_ms = self.now_ms();
+        let half_life_ms = self.config.decay_half_life_ms;
+        let Ok(page_heat) = self.page_heat.read() else {
+            return Vec::new();
+        };
+        let mut pages: Vec<(u32, f64)> = page_heat
+            .iter()
+            .map(|(&page, score)| (page, score.at(now_ms, half_life_ms)))
+            .filter(|&(_, score)| self.heat_of(score) >= min_heat)
+            .collect();
+        pages.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        pages.into_iter().map(|(page, _)| page).collect()
+    }
+
+    /// Count the tracked key buckets and pages at each heat
+    pub fn heat_summary(&self) -> HeatSummary {
+        let mut summary = HeatSummary::default();
+        let now_ms = self.now_ms();
+        let half_life_ms = self.config.decay_half_life_ms;
+        if let Ok(key_heat) = self.key_heat.read() {
+            for score in key_heat.values() {
+                match self.heat_of(score.at(now_ms, half_life_ms)) {
+                    Heat::Hot => summary.hot_buckets += 1,
+                    Heat::Warm => summary.warm_buckets += 1,
+                    Heat::Cold => summary.cold_buckets += 1,
+                }
+            }
+        }
+        if let Ok(page_heat) = self.page_heat.read() {
+            for score in page_heat.values() {
+                match self.heat_of(score.at(now_ms, half_life_ms)) {
+                    Heat::Hot => summary.hot_pages += 1,
+                    Heat::Warm => summary.warm_pages += 1,
+                    Heat::Cold => summary.cold_pages += 1,
+                }
+            }
+        }
+        summary
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.clock
+            .now_monotonic()
+            .saturating_duration_since(self.start_time)
+            .as_millis() as u64
+    }
+
+    fn heat_bucket(&self, key_hash: u64) -> u64 {
+        key_hash % self.config.heat_buckets.max(1)
+    }
+
+    fn heat_of(&self, score: f64) -> Heat {
+        if score >= self.config.hot_threshold {
+            Heat::Hot
+        } else if score >= self.config.warm_threshold {
+            Heat::Warm
+        } else {
+            Heat::Cold
+        }
+    }
+
+    fn bump<T: std::hash::Hash + Eq>(&self, scores: &RwLock<HashMap<T, DecayedScore>>, id: T) {
+        let now_ms = self.now_ms();
+        let half_life_ms = self.config.decay_half_life_ms;
+        if let Ok(mut scores) = scores.write() {
+            let entry = scores.entry(id).or_insert(DecayedScore {
+                score: 0.0,
+                updated_ms: now_ms,
+            });
+            entry.score = entry.at(now_ms, half_life_ms) + 1.0;
+            entry.updated_ms = now_ms;
+        }
+    }
+
+    fn score<T: std::hash::Hash + Eq>(
+        &self,
+        scores: &RwLock<HashMap<T, DecayedScore>>,
+        id: T,
+    ) -> f64 {
+        scores
+            .read()
+            .ok()
+            .and_then(|scores| scores.get(&id).copied())
+            .map(|score| score.at(self.now_ms(), self.config.decay_half_life_ms))
+            .unwrap_or(0.0)
+    }
+
     /// Analyze current access patterns
     pub fn analyze_patterns(&self) -> AccessStats {
         let total = self.total_accesses.load(Ordering::Relaxed);
@@ -277,8 +479,7 @@ impl AccessAnalyzer {
             total_count += 1;
 
             // Look for reaccess of the same key within temporal window
-            for j in (i + 1)..recent_events.len() {
-                let other = &recent_events[j];
+            for other in recent_events.iter().skip(i + 1) {
                 if other.timestamp < current.timestamp - temporal_window {
                     break;
                 }
@@ -332,7 +533,7 @@ impl AccessAnalyzer {
         };
 
         let mut sorted: Vec<_> = frequencies.iter().map(|(&k, &v)| (k, v)).collect();
-        sorted.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted.sort_by_key(|entry| std::cmp::Reverse(entry.1));
         sorted.into_iter().take(n).collect()
     }
 
@@ -344,6 +545,12 @@ impl AccessAnalyzer {
         if let Ok(mut frequencies) = self.key_frequencies.write() {
             frequencies.clear();
         }
+        if let Ok(mut key_heat) = self.key_heat.write() {
+            key_heat.clear();
+        }
+        if let Ok(mut page_heat) = self.page_heat.write() {
+            page_heat.clear();
+        }
         self.total_accesses.store(0, Ordering::Relaxed);
         self.read_count.store(0, Ordering::Relaxed);
         self.write_count.store(0, Ordering::Relaxed);
@@ -405,9 +612,172 @@ pub struct AccessRecommendation {
     pub migration_aggressiveness: f64,
 }
 
+/// Counter of one monitored key in a [`SpaceSaving`] summary
+#[derive(Debug, Clone)]
+struct Counter<K> {
+    key: K,
+    count: u64,
+}
+
+/// SpaceSaving heavy-hitters summary monitoring at most `capacity` keys.
+///
+/// Keys are told apart by hash, so keys whose hashes collide share a counter.
+#[derive(Debug)]
+struct SpaceSaving<K> {
+    capacity: usize,
+    counters: HashMap<u64, Counter<K>>,
+}
+
+impl<K: Copy> SpaceSaving<K> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            counters: HashMap::with_capacity(capacity),
+        }
+    }
+
+    fn offer(&mut self, key_hash: u64, key: K) {
+        if let Some(counter) = self.counters.get_mut(&key_hash) {
+            counter.count += 1;
+            return;
+        }
+        if self.counters.len() < self.capacity {
+            self.counters.insert(key_hash, Counter { key, count: 1 });
+            return;
+        }
+        // Replace the smallest counter; the newcomer inherits its count.
+        let Some((&victim, min)) = self.counters.iter().min_by_key(|(_, c)| c.count) else {
+            return;
+        };
+        let min = min.count;
+        self.counters.remove(&victim);
+        self.counters.insert(
+            key_hash,
+            Counter {
+                key,
+                count: min + 1,
+            },
+        );
+    }
+}
+
+/// Approximate top-K tracker of the most accessed keys.
+///
+/// Accesses are sampled 1-in-`sample_every` per thread and fed into one of
+/// several SpaceSaving summaries picked by thread, so concurrent callers
+/// rarely contend. Queries merge the summaries and scale counts back up by
+/// the sample rate.
+pub struct HotKeySketch<K> {
+    shards: Vec<Mutex<SpaceSaving<K>>>,
+    sample_every: AtomicU32,
+}
+
+thread_local! {
+    /// Shard of the current thread and accesses left until its next sample
+    static SKETCH_THREAD: Cell<(usize, u32)> = Cell::new((next_sketch_shard(), 0));
+}
+
+fn next_sketch_shard() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+impl<K: Copy> HotKeySketch<K> {
+    pub const DEFAULT_SHARDS: usize = 16;
+    pub const DEFAULT_CAPACITY: usize = 256;
+    pub const DEFAULT_SAMPLE_EVERY: u32 = 8;
+
+    /// Creates a sketch of `shards` summaries monitoring `capacity` keys
+    /// each. A `sample_every` of 0 disables recording.
+    pub fn new(shards: usize, capacity: usize, sample_every: u32) -> Self {
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| Mutex::new(SpaceSaving::new(capacity.max(1))))
+                .collect(),
+            sample_every: AtomicU32::new(sample_every),
+        }
+    }
+
+    pub fn sample_every(&self) -> u32 {
+        self.sample_every.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sample_every(&self, sample_every: u32) {
+        self.sample_every.store(sample_every, Ordering::Relaxed);
+    }
+
+    /// Counts one access to `key`, subject to sampling.
+    pub fn record(&self, key_hash: u64, key: &K) {
+        let sample_every = self.sample_every.load(Ordering::Relaxed);
+        if sample_every == 0 {
+            return;
+        }
+        let shard = SKETCH_THREAD.with(|thread| {
+            let (shard, skip) = thread.get();
+            // The countdown is shared by every sketch the thread feeds.
+            let skip = skip.min(sample_every - 1);
+            if skip > 0 {
+                thread.set((shard, skip - 1));
+                return None;
+            }
+            thread.set((shard, sample_every - 1));
+            Some(shard)
+        });
+        let Some(shard) = shard else {
+            return;
+        };
+        if let Ok(mut summary) = self.shards[shard % self.shards.len()].lock() {
+            summary.offer(key_hash, *key);
+        }
+    }
+
+    /// Up to `k` keys with their approximate access counts, hottest first.
+    /// A count can overestimate by the count of the key its counter displaced.
+    pub fn top(&self, k: usize) -> Vec<(K, u64)> {
+        let mut merged: HashMap<u64, (K, u64)> = HashMap::new();
+        for shard in &self.shards {
+            let Ok(summary) = shard.lock() else {
+                continue;
+            };
+            for (key_hash, counter) in &summary.counters {
+                merged.entry(*key_hash).or_insert((counter.key, 0)).1 += counter.count;
+            }
+        }
+
+        let scale = self.sample_every().max(1) as u64;
+        let mut sorted: Vec<(K, u64)> = merged
+            .into_values()
+            .map(|(key, count)| (key, count * scale))
+            .collect();
+        sorted.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+        sorted.truncate(k);
+        sorted
+    }
+
+    /// Forgets every recorded access, starting a new window.
+    pub fn reset(&self) {
+        for shard in &self.shards {
+            if let Ok(mut summary) = shard.lock() {
+                summary.counters.clear();
+            }
+        }
+    }
+}
+
+impl<K: Copy> Default for HotKeySketch<K> {
+    fn default() -> Self {
+        Self::new(
+            Self::DEFAULT_SHARDS,
+            Self::DEFAULT_CAPACITY,
+            Self::DEFAULT_SAMPLE_EVERY,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::clock::MockClock;
 
     #[test]
     fn test_access_recording() {
@@ -422,6 +792,17 @@ mod tests {
         assert_eq!(analyzer.write_count.load(Ordering::Relaxed), 1);
     }
 
+    #[test]
+    fn test_key_frequency() {
+        let analyzer = AccessAnalyzer::new(AnalyzerConfig::default());
+
+        analyzer.record_access(100, OperationType::Read);
+        analyzer.record_access(100, OperationType::Update);
+
+        assert_eq!(analyzer.get_key_frequency(100), 2);
+        assert_eq!(analyzer.get_key_frequency(200), 0);
+    }
+
     #[test]
     fn test_hot_keys_detection() {
         let analyzer = AccessAnalyzer::new(AnalyzerConfig::default());
@@ -485,4 +866,64 @@ mod tests {
         // So we just check hotspot concentration
         assert!(stats.hotspot_concentration > 0.8);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hot_key_sketch_space_saving() {
+        let sketch = HotKeySketch::<u64>::new(1, 4, 1);
+        for key in 0..100u64 {
+            for _ in 0..(key % 10) {
+                sketch.record(key, &key);
+            }
+        }
+        for _ in 0..500 {
+            sketch.record(7, &7);
+        }
+
+        let top = sketch.top(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, 7);
+        assert!(top[0].1 >= 500);
+
+        sketch.reset();
+        assert!(sketch.top(2).is_empty());
+    }
+
+    #[test]
+    fn test_heat_classification_decays() {
+        let config = AnalyzerConfig {
+            decay_half_life_ms: 50,
+            hot_threshold: 8.0,
+            warm_threshold: 2.0,
+            ..Default::default()
+        };
+        let clock = Arc::new(MockClock::new());
+        let analyzer = AccessAnalyzer::with_clock(config, clock.clone());
+
+        for _ in 0..20 {
+            analyzer.record_access(1, OperationType::Read);
+            analyzer.record_page_access(3);
+        }
+        for _ in 0..4 {
+            analyzer.record_access(2, OperationType::Write);
+            analyzer.record_page_access(5);
+        }
+
+        assert_eq!(analyzer.classify(1), Heat::Hot);
+        assert_eq!(analyzer.classify(2), Heat::Warm);
+        assert_eq!(analyzer.classify(99), Heat::Cold);
+        assert_eq!(analyzer.page_heat(3), Heat::Hot);
+        assert_eq!(analyzer.page_heat(4), Heat::Cold);
+        let summary = analyzer.heat_summary();
+        assert_eq!(summary.hot_buckets, 1);
+        assert_eq!(summary.warm_buckets, 1);
+        assert_eq!(summary.hot_pages, 1);
+        assert_eq!(analyzer.pages_at_least(Heat::Hot), vec![3]);
+        assert_eq!(analyzer.pages_at_least(Heat::Warm), vec![3, 5]);
+
+        // Ten half-lives later, everything has cooled off.
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(analyzer.classify(1), Heat::Cold);
+        assert_eq!(analyzer.page_heat(3), Heat::Cold);
+        assert_eq!(analyzer.heat_summary().cold_buckets, 2);
+    }
+}
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
 
 /// Batch operation type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -238,6 +239,129 @@ impl BatchStats {
     }
 }
 
+/// Write combiner configuration
+#[derive(Debug, Clone)]
+pub struct WriteCombinerConfig {
+    /// Most writes applied together in one batch
+    pub max_batch_size: usize,
+    /// How long a leader waits for more writers to join a batch that is
+    /// not yet full. Zero applies whatever is staged immediately.
+    pub linger: Duration,
+}
+
+impl Default for WriteCombinerConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 64,
+            linger: Duration::ZERO,
+        }
+    }
+}
+
+struct CombinerState<T, R> {
+    staged: VecDeque<(u64, T)>,
+    results: HashMap<u64, R>,
+    leader_active: bool,
+    next_ticket: u64,
+}
+
+/// Flat-combining front end for concurrent writes.
+///
+/// Writers stage their item and wait. Whichever writer finds no batch in
+/// flight becomes the leader: it takes up to `max_batch_size` staged items,
+/// applies them all in one call, hands every waiter its result and steps
+/// down, so the next waiting writer leads the following batch. A lone
+/// writer leads a batch of one and never waits.
+pub struct WriteCombiner<T, R> {
+    config: WriteCombinerConfig,
+    state: Mutex<CombinerState<T, R>>,
+    wake: Condvar,
+    total_batches: AtomicU64,
+    total_ops: AtomicU64,
+}
+
+impl<T, R> WriteCombiner<T, R> {
+    pub fn new(config: WriteCombinerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CombinerState {
+                staged: VecDeque::new(),
+                results: HashMap::new(),
+                leader_active: false,
+                next_ticket: 0,
+            }),
+            wake: Condvar::new(),
+            total_batches: AtomicU64::new(0),
+            total_ops: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &WriteCombinerConfig {
+        &self.config
+    }
+
+    /// Stages `item` and returns its result once some leader has applied
+    /// it. `apply` must return one result per item, in order; the caller
+    /// that leads the batch uses its own `apply`, so every caller should
+    /// pass an equivalent one.
+    pub fn submit(&self, item: T, apply: impl Fn(Vec<T>) -> Vec<R>) -> R {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.staged.push_back((ticket, item));
+
+        loop {
+            if let Some(result) = state.results.remove(&ticket) {
+                return result;
+            }
+            if state.leader_active {
+                state = self
+                    .wake
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+
+            state.leader_active = true;
+            if state.staged.len() < self.config.max_batch_size && !self.config.linger.is_zero() {
+                state = self
+                    .wake
+                    .wait_timeout(state, self.config.linger)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+            }
+            let take = state.staged.len().min(self.config.max_batch_size.max(1));
+            let (tickets, items): (Vec<u64>, Vec<T>) = state.staged.drain(..take).unzip();
+            drop(state);
+
+            let results = apply(items);
+            assert_eq!(results.len(), tickets.len(), "one result per staged write");
+            self.total_batches.fetch_add(1, Ordering::Relaxed);
+            self.total_ops
+                .fetch_add(tickets.len() as u64, Ordering::Relaxed);
+
+            state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.results.extend(tickets.into_iter().zip(results));
+            state.leader_active = false;
+            self.wake.notify_all();
+        }
+    }
+
+    /// Get statistics
+    pub fn get_stats(&self) -> BatchStats {
+        BatchStats {
+            total_batches: self.total_batches.load(Ordering::Relaxed),
+            total_ops: self.total_ops.load(Ordering::Relaxed),
+            deduplicated_ops: 0,
+            pending_ops: self
+                .state
+                .lock()
+                .map(|state| state.staged.len())
+                .unwrap_or(0),
+        }
+    }
+}
+
 /// Batch executor trait for executing optimized batches
 pub trait BatchExecutor<K, V> {
     fn execute_batch(&self, batch: Vec<BatchOp<K, V>>) -> Vec<BatchOpResult<V>>;
@@ -415,4 +539,35 @@ mod tests {
         assert_eq!(hint.key_hashes[0], 100);
         assert_eq!(hint.key_hashes[1], 200);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_write_combiner_returns_each_result() {
+        let combiner = Arc::new(WriteCombiner::<u64, u64>::new(WriteCombinerConfig {
+            max_batch_size: 16,
+            linger: Duration::from_millis(1),
+        }));
+        let handles: Vec<_> = (0..8u64)
+            .map(|thread| {
+                let combiner = Arc::clone(&combiner);
+                std::thread::spawn(move || {
+                    for i in 0..200 {
+                        let item = thread * 1000 + i;
+                        let result = combiner.submit(item, |items| {
+                            assert!(items.len() <= 16);
+                            items.iter().map(|item| item * 2).collect()
+                        });
+                        assert_eq!(result, item * 2);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stats = combiner.get_stats();
+        assert_eq!(stats.total_ops, 1600);
+        assert_eq!(stats.pending_ops, 0);
+        assert!(stats.total_batches < stats.total_ops);
+    }
+}
@@ -77,6 +77,10 @@ impl CacheAlignedAllocator {
     }
 
     /// Deallocate cache-aligned memory
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `allocate::<T>` on this allocator with the same `count`.
     pub unsafe fn deallocate<T>(&self, ptr: NonNull<T>, count: usize) {
         let size = std::mem::size_of::<T>() * count;
         let align = CACHE_ALIGNED.max(std::mem::align_of::<T>());
@@ -355,15 +359,14 @@ impl NumaAllocator {
 
     /// Get the best NUMA node for allocation
     pub fn select_node(&self, hint: NumaHint) -> usize {
-        if let Some(node) = hint.preferred_node {
-            if node < self.node_allocations.len() {
-                return node;
-            }
+        if let Some(node) = hint.preferred_node
+            && node < self.node_allocations.len()
+        {
+            return node;
         }
 
         // Round-robin selection
-        let node = self.current_node.fetch_add(1, Ordering::Relaxed) % self.node_allocations.len();
-        node
+        self.current_node.fetch_add(1, Ordering::Relaxed) % self.node_allocations.len()
     }
 
     /// Record allocation on a node
@@ -415,7 +418,7 @@ mod tests {
     fn test_prefetch_manager() {
         let manager = PrefetchManager::default();
 
-        let data = vec![1, 2, 3, 4, 5];
+        let data = [1, 2, 3, 4, 5];
         manager.prefetch(data.as_ptr(), PrefetchHint::Read);
 
         let stats = manager.get_stats();
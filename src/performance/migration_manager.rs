@@ -1,8 +1,15 @@
+use crate::core::status::Status;
+use crate::performance::access_analyzer::Heat;
+use serde::Deserialize;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::time::Instant;
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 /// Migration strategy determines when and how to migrate data between hot and cold storage
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MigrationStrategy {
     /// Migrate based on access frequency
     AccessFrequency,
@@ -23,6 +30,8 @@ pub struct KeyStats {
     pub last_access_time: AtomicU64,
     /// Whether this key is currently in hot storage
     pub in_hot: std::sync::atomic::AtomicBool,
+    /// Whether a copy of this key has been written to cold storage
+    pub in_cold: std::sync::atomic::AtomicBool,
     /// Size of the key-value pair in bytes
     pub size_bytes: AtomicUsize,
 }
@@ -33,6 +42,7 @@ impl KeyStats {
             access_count: AtomicU64::new(0),
             last_access_time: AtomicU64::new(0),
             in_hot: std::sync::atomic::AtomicBool::new(false),
+            in_cold: std::sync::atomic::AtomicBool::new(false),
             size_bytes: AtomicUsize::new(size_bytes),
         }
     }
@@ -58,6 +68,16 @@ impl KeyStats {
         self.in_hot.store(value, Ordering::Relaxed);
     }
 
+    /// Set the hot flag and return its previous value
+    pub fn swap_in_hot(&self, value: bool) -> bool {
+        self.in_hot.swap(value, Ordering::AcqRel)
+    }
+
+    /// Set the cold flag and return its previous value
+    pub fn swap_in_cold(&self, value: bool) -> bool {
+        self.in_cold.swap(value, Ordering::AcqRel)
+    }
+
     pub fn get_size(&self) -> usize {
         self.size_bytes.load(Ordering::Relaxed)
     }
@@ -80,6 +100,10 @@ pub struct MigrationConfig {
     pub migration_batch_size: usize,
     /// Enable adaptive threshold adjustment
     pub adaptive_threshold: bool,
+    /// Promote cold-tier read hits into hot storage
+    pub enable_read_promotion: bool,
+    /// Maximum number of read promotions per second (0 disables the limit)
+    pub max_promotions_per_sec: u64,
 }
 
 impl Default for MigrationConfig {
@@ -92,6 +116,8 @@ impl Default for MigrationConfig {
             target_hot_utilization: 0.8,
             migration_batch_size: 128,
             adaptive_threshold: true,
+            enable_read_promotion: true,
+            max_promotions_per_sec: 10_000,
         }
     }
 }
@@ -155,6 +181,17 @@ impl MigrationManager {
         }
     }
 
+    /// Get the migration configuration
+    pub fn config(&self) -> &MigrationConfig {
+        &self.config
+    }
+
+    /// Check whether `size` more bytes fit in hot storage
+    pub fn has_hot_capacity(&self, size: usize) -> bool {
+        let current_size = self.current_hot_size.load(Ordering::Relaxed);
+        current_size + size <= self.config.max_hot_size_bytes
+    }
+
     /// Determine if a key should be evicted from hot storage
     pub fn should_evict_from_hot(&self, stats: &KeyStats, current_time_ms: u64) -> bool {
         // Check if key is in hot storage
@@ -344,10 +381,678 @@ impl MigrationStats {
     }
 }
 
+/// Bounds and signals for sizing the mutable region of a log
+#[derive(Debug, Clone)]
+pub struct MutableRegionConfig {
+    /// Smallest share of log memory kept mutable (0.0 to 1.0)
+    pub min_fraction: f64,
+    /// Largest share of log memory kept mutable (0.0 to 1.0)
+    pub max_fraction: f64,
+    /// Share the controller starts from
+    pub initial_fraction: f64,
+    /// Change applied to the share after each window
+    pub step: f64,
+    /// Upserts observed between adjustments
+    pub window: u64,
+    /// In-place hit rate above which the mutable region grows
+    pub grow_above: f64,
+    /// In-place hit rate below which the mutable region shrinks
+    pub shrink_below: f64,
+    /// Unflushed bytes beyond which the mutable region shrinks regardless
+    /// of hit rate
+    pub max_flush_backlog_bytes: u64,
+}
+
+impl Default for MutableRegionConfig {
+    fn default() -> Self {
+        Self {
+            min_fraction: 0.1,
+            max_fraction: 0.9,
+            initial_fraction: 0.5,
+            step: 0.1,
+            window: 10_000,
+            grow_above: 0.5,
+            shrink_below: 0.1,
+            max_flush_backlog_bytes: 1 << 30,
+        }
+    }
+}
+
+/// Current state of a [`MutableRegionController`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutableRegionStats {
+    /// Share of log memory the read-only boundary trails the tail by
+    pub target_fraction: f64,
+    /// In-place hit rate over the last completed window
+    pub in_place_hit_rate: f64,
+    /// Windows completed so far
+    pub adjustments: u64,
+}
+
+/// Adapts how far the read-only boundary trails the tail.
+///
+/// Upserts that update a record in place argue for a larger mutable region;
+/// upserts that append, or a growing flush backlog, argue for a smaller one.
+/// After every `window` upserts the target share moves one `step` within
+/// the configured bounds.
+pub struct MutableRegionController {
+    config: MutableRegionConfig,
+    /// `f64` bits of the target share
+    target_fraction: AtomicU64,
+    /// `f64` bits of the hit rate of the last window
+    last_hit_rate: AtomicU64,
+    in_place: AtomicU64,
+    upserts: AtomicU64,
+    adjustments: AtomicU64,
+}
+
+impl MutableRegionController {
+    pub fn new(config: MutableRegionConfig) -> Self {
+        let initial = config
+            .initial_fraction
+            .clamp(config.min_fraction, config.max_fraction);
+        Self {
+            config,
+            target_fraction: AtomicU64::new(initial.to_bits()),
+            last_hit_rate: AtomicU64::new(0f64.to_bits()),
+            in_place: AtomicU64::new(0),
+            upserts: AtomicU64::new(0),
+            adjustments: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &MutableRegionConfig {
+        &self.config
+    }
+
+    pub fn target_fraction(&self) -> f64 {
+        f64::from_bits(self.target_fraction.load(Ordering::Relaxed))
+    }
+
+    /// Counts one upsert. Returns true when it completed a window and the
+    /// target was re-evaluated against `flush_backlog_bytes`.
+    pub fn record_upsert(&self, in_place: bool, flush_backlog_bytes: u64) -> bool {
+        if in_place {
+            self.in_place.fetch_add(1, Ordering::Relaxed);
+        }
+        let upserts = self.upserts.fetch_add(1, Ordering::Relaxed) + 1;
+        if upserts < self.config.window.max(1) {
+            return false;
+        }
+        // Only the upsert that fills the window adjusts.
+        if self
+            .upserts
+            .compare_exchange(upserts, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        let in_place = self.in_place.swap(0, Ordering::Relaxed);
+        self.adjust(in_place as f64 / upserts as f64, flush_backlog_bytes);
+        true
+    }
+
+    fn adjust(&self, hit_rate: f64, flush_backlog_bytes: u64) {
+        let current = self.target_fraction();
+        let target = if flush_backlog_bytes > self.config.max_flush_backlog_bytes
+            || hit_rate < self.config.shrink_below
+        {
+            current - self.config.step
+        } else if hit_rate > self.config.grow_above {
+            current + self.config.step
+        } else {
+            current
+        };
+        let target = target.clamp(self.config.min_fraction, self.config.max_fraction);
+        self.target_fraction
+            .store(target.to_bits(), Ordering::Relaxed);
+        self.last_hit_rate
+            .store(hit_rate.to_bits(), Ordering::Relaxed);
+        self.adjustments.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_stats(&self) -> MutableRegionStats {
+        MutableRegionStats {
+            target_fraction: self.target_fraction(),
+            in_place_hit_rate: f64::from_bits(self.last_hit_rate.load(Ordering::Relaxed)),
+            adjustments: self.adjustments.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// What admission control does with a write that arrives while the store is
+/// overloaded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverloadPolicy {
+    /// Fail the write with `Status::Overloaded` at once
+    Shed,
+    /// Wait up to `max_wait` for the pressure to fall, then shed
+    Delay { max_wait: Duration },
+}
+
+/// Pressure limits for admitting writes. Each signal is measured as a share
+/// of its limit; the largest share is the store's pressure, and writes are
+/// overloaded from 1.0 on.
+#[derive(Debug, Clone)]
+pub struct AdmissionConfig {
+    /// Unflushed bytes at which writes are overloaded
+    pub max_flush_backlog_bytes: u64,
+    /// Share of in-memory log space in use at which writes are overloaded
+    pub max_log_memory_fraction: f64,
+    /// Index entries per bucket at which writes are overloaded
+    pub max_index_load_factor: f64,
+    /// Pressure from which the level reads as elevated
+    pub elevated_above: f64,
+    pub policy: OverloadPolicy,
+    /// Suggested wait before retrying a shed write
+    pub retry_after: Duration,
+    /// Writes between recounts of the index entries, which walk the index
+    pub index_check_interval: u64,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_flush_backlog_bytes: 1 << 30,
+            max_log_memory_fraction: 0.95,
+            max_index_load_factor: 6.0,
+            elevated_above: 0.8,
+            policy: OverloadPolicy::Shed,
+            retry_after: Duration::from_millis(10),
+            index_check_interval: 4096,
+        }
+    }
+}
+
+/// Raw pressure signals of a store at one moment
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PressureSignals {
+    pub flush_backlog_bytes: u64,
+    /// Share of in-memory log space in use
+    pub log_memory_fraction: f64,
+    /// Index entries per bucket
+    pub index_load_factor: f64,
+}
+
+/// Coarse reading of the pressure on a store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PressureLevel {
+    Normal,
+    Elevated,
+    Overloaded,
+}
+
+/// Current state of an [`AdmissionController`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdmissionStats {
+    pub pressure: f64,
+    pub level: PressureLevel,
+    /// Writes let through, delayed ones included
+    pub admitted: u64,
+    /// Writes let through after waiting for the pressure to fall
+    pub delayed: u64,
+    /// Writes failed with `Status::Overloaded`
+    pub shed: u64,
+    /// Suggested wait before retrying a shed write
+    pub retry_after: Duration,
+}
+
+/// Decides at the top of each write whether the store can take it, so that
+/// overload surfaces as `Status::Overloaded` before any log space or index
+/// entry is claimed rather than as an allocation failure partway through.
+pub struct AdmissionController {
+    config: AdmissionConfig,
+    /// `f64` bits of the index load factor at the last recount
+    index_load_factor: AtomicU64,
+    writes_since_index_check: AtomicU64,
+    admitted: AtomicU64,
+    delayed: AtomicU64,
+    shed: AtomicU64,
+}
+
+impl AdmissionController {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            config,
+            index_load_factor: AtomicU64::new(0f64.to_bits()),
+            writes_since_index_check: AtomicU64::new(0),
+            admitted: AtomicU64::new(0),
+            delayed: AtomicU64::new(0),
+            shed: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &AdmissionConfig {
+        &self.config
+    }
+
+    /// Index load factor for the next pressure reading: `count` on the first
+    /// write of every check interval, the last count otherwise.
+    pub fn index_load_factor(&self, count: impl FnOnce() -> f64) -> f64 {
+        let writes = self
+            .writes_since_index_check
+            .fetch_add(1, Ordering::Relaxed);
+        if writes.is_multiple_of(self.config.index_check_interval.max(1)) {
+            let load_factor = count();
+            self.index_load_factor
+                .store(load_factor.to_bits(), Ordering::Relaxed);
+            return load_factor;
+        }
+        f64::from_bits(self.index_load_factor.load(Ordering::Relaxed))
+    }
+
+    /// The largest share of its limit any signal has reached.
+    pub fn pressure(&self, signals: &PressureSignals) -> f64 {
+        let backlog =
+            signals.flush_backlog_bytes as f64 / self.config.max_flush_backlog_bytes.max(1) as f64;
+        let memory = signals.log_memory_fraction / self.config.max_log_memory_fraction;
+        let index = signals.index_load_factor / self.config.max_index_load_factor;
+        backlog.max(memory).max(index)
+    }
+
+    pub fn level(&self, pressure: f64) -> PressureLevel {
+        if pressure >= 1.0 {
+            PressureLevel::Overloaded
+        } else if pressure >= self.config.elevated_above {
+            PressureLevel::Elevated
+        } else {
+            PressureLevel::Normal
+        }
+    }
+
+    /// Lets a write through unless the pressure read from `signals` is at
+    /// overload. Under [`OverloadPolicy::Delay`] the signals are read again
+    /// every millisecond until they fall or the wait runs out.
+    pub fn admit(&self, mut signals: impl FnMut() -> PressureSignals) -> Result<(), Status> {
+        if self.pressure(&signals()) < 1.0 {
+            self.admitted.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        if let OverloadPolicy::Delay { max_wait } = self.config.policy {
+            let deadline = Instant::now() + max_wait;
+            while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+                std::thread::sleep(remaining.min(Duration::from_millis(1)));
+                if self.pressure(&signals()) < 1.0 {
+                    self.delayed.fetch_add(1, Ordering::Relaxed);
+                    self.admitted.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        }
+        self.shed.fetch_add(1, Ordering::Relaxed);
+        Err(Status::Overloaded)
+    }
+
+    pub fn get_stats(&self, signals: &PressureSignals) -> AdmissionStats {
+        let pressure = self.pressure(signals);
+        AdmissionStats {
+            pressure,
+            level: self.level(pressure),
+            admitted: self.admitted.load(Ordering::Relaxed),
+            delayed: self.delayed.load(Ordering::Relaxed),
+            shed: self.shed.load(Ordering::Relaxed),
+            retry_after: self.config.retry_after,
+        }
+    }
+}
+
+/// Settings of a page tiering task, see [`RsKv::start_page_tiering`].
+///
+/// [`RsKv::start_page_tiering`]: crate::rskv_core::RsKv::start_page_tiering
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageTieringConfig {
+    /// Time between passes
+    pub interval: Duration,
+    /// Coldest heat of a page read back into memory after leaving the log
+    pub min_heat: Heat,
+    /// Bytes read back from disk per second, 0 for no limit
+    pub max_bytes_per_sec: u64,
+}
+
+impl Default for PageTieringConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            min_heat: Heat::Hot,
+            max_bytes_per_sec: 0,
+        }
+    }
+}
+
+/// Counters of a page tiering task since it started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageTieringStats {
+    pub passes: u64,
+    /// Pages dropped from the log or released after being read back
+    pub pages_demoted: u64,
+    /// Pages read back into memory
+    pub pages_promoted: u64,
+    /// Bytes flushed to make room and read back from disk
+    pub bytes_moved: u64,
+    /// Pages left on disk by a pass for the rate limit
+    pub pages_deferred: u64,
+}
+
+impl PageTieringStats {
+    fn add(&mut self, pass: &PageTieringStats) {
+        self.passes += pass.passes;
+        self.pages_demoted += pass.pages_demoted;
+        self.pages_promoted += pass.pages_promoted;
+        self.bytes_moved += pass.bytes_moved;
+        self.pages_deferred += pass.pages_deferred;
+    }
+}
+
+/// Where a page tiering pass puts the pages of a log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PagePlan {
+    /// Oldest page kept in the log
+    pub head_page: u32,
+    /// Pages below `head_page` kept in memory, hottest first
+    pub keep: Vec<u32>,
+}
+
+/// Plans a pass over a log of pages `head_page..=tail_page` that may keep
+/// `budget_pages` pages in memory. `hot_pages` are the pages worth keeping,
+/// hottest first. The log gives up its oldest pages until it and the hot
+/// pages below it fit the budget, down to the tail page alone; when not all
+/// hot pages fit, the hottest are kept.
+pub fn plan_page_tiering(
+    budget_pages: u64,
+    head_page: u32,
+    tail_page: u32,
+    hot_pages: &[u32],
+) -> PagePlan {
+    let budget = budget_pages.max(1);
+    let mut log_pages = (tail_page.saturating_sub(head_page) as u64 + 1).min(budget);
+    loop {
+        let head_page = tail_page + 1 - log_pages as u32;
+        let below = hot_pages.iter().filter(|&&page| page < head_page);
+        if log_pages == 1 || log_pages + below.clone().count() as u64 <= budget {
+            return PagePlan {
+                head_page,
+                keep: below.copied().take((budget - log_pages) as usize).collect(),
+            };
+        }
+        log_pages -= 1;
+    }
+}
+
+/// Token bucket over bytes, refilled at `bytes_per_sec` up to one second's
+/// worth. A request larger than the bucket goes through once it is full.
+pub(crate) struct ByteRateLimiter {
+    bytes_per_sec: u64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl ByteRateLimiter {
+    /// A full bucket; `bytes_per_sec` of 0 lets everything through.
+    pub(crate) fn new(bytes_per_sec: u64, now: Instant) -> Self {
+        Self {
+            bytes_per_sec,
+            tokens: bytes_per_sec as f64,
+            refilled: now,
+        }
+    }
+
+    /// Takes `bytes` from the bucket if they are there.
+    pub(crate) fn take(&mut self, bytes: u64, now: Instant) -> bool {
+        if self.bytes_per_sec == 0 {
+            return true;
+        }
+        let capacity = self.bytes_per_sec as f64;
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * capacity).min(capacity);
+        self.refilled = now;
+        if self.tokens < (bytes as f64).min(capacity) {
+            return false;
+        }
+        self.tokens -= bytes as f64;
+        true
+    }
+}
+
+enum TieringCommand {
+    Pause,
+    Resume,
+    /// Runs a pass now, paused or not, and acknowledges it
+    RunOnce(Sender<()>),
+}
+
+/// Handle to a page tiering task, see [`RsKv::start_page_tiering`]. The
+/// task stops when the handle is dropped.
+///
+/// [`RsKv::start_page_tiering`]: crate::rskv_core::RsKv::start_page_tiering
+pub struct PageTiering {
+    commands: Option<Sender<TieringCommand>>,
+    stats: Arc<Mutex<PageTieringStats>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PageTiering {
+    /// Runs `pass` every `interval` on a thread of its own until it returns
+    /// `None`. Each pass returns what it moved.
+    pub(crate) fn start(
+        interval: Duration,
+        mut pass: impl FnMut() -> Option<PageTieringStats> + Send + 'static,
+    ) -> Self {
+        let (commands, receiver) = channel();
+        let stats = Arc::new(Mutex::new(PageTieringStats::default()));
+        let thread_stats = Arc::clone(&stats);
+        let handle = thread::spawn(move || {
+            let mut paused = false;
+            loop {
+                let ack = match receiver.recv_timeout(interval) {
+                    Ok(TieringCommand::Pause) => {
+                        paused = true;
+                        continue;
+                    }
+                    Ok(TieringCommand::Resume) => {
+                        paused = false;
+                        continue;
+                    }
+                    Ok(TieringCommand::RunOnce(ack)) => Some(ack),
+                    Err(RecvTimeoutError::Timeout) if paused => continue,
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let Some(moved) = pass() else {
+                    break;
+                };
+                if let Ok(mut stats) = thread_stats.lock() {
+                    stats.add(&moved);
+                    stats.passes += 1;
+                }
+                if let Some(ack) = ack {
+                    let _ = ack.send(());
+                }
+            }
+        });
+        Self {
+            commands: Some(commands),
+            stats,
+            handle: Some(handle),
+        }
+    }
+
+    fn send(&self, command: TieringCommand) -> bool {
+        self.commands
+            .as_ref()
+            .is_some_and(|commands| commands.send(command).is_ok())
+    }
+
+    /// Skips the timed passes until [`resume`](Self::resume).
+    pub fn pause(&self) {
+        self.send(TieringCommand::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(TieringCommand::Resume);
+    }
+
+    /// Runs a pass now, even while paused, and waits for it. Returns false
+    /// if the task has ended.
+    pub fn run_once(&self) -> bool {
+        let (ack, done) = channel();
+        self.send(TieringCommand::RunOnce(ack)) && done.recv().is_ok()
+    }
+
+    pub fn stats(&self) -> PageTieringStats {
+        self.stats.lock().map(|stats| *stats).unwrap_or_default()
+    }
+
+    /// Stop the task and wait for the current pass to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.commands.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for PageTiering {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_page_plan_trades_the_oldest_log_pages_for_hot_pages() {
+        // Nothing hot: the log alone fills the budget.
+        assert_eq!(
+            plan_page_tiering(3, 0, 5, &[]),
+            PagePlan {
+                head_page: 3,
+                keep: vec![]
+            }
+        );
+        // A hot page below the log takes the place of its oldest page.
+        assert_eq!(
+            plan_page_tiering(3, 0, 5, &[0]),
+            PagePlan {
+                head_page: 4,
+                keep: vec![0]
+            }
+        );
+        // Hot pages still in the log need no frame of their own.
+        assert_eq!(
+            plan_page_tiering(3, 0, 5, &[4, 1]),
+            PagePlan {
+                head_page: 4,
+                keep: vec![1]
+            }
+        );
+        // With more hot pages than fit, the tail page stays and the
+        // hottest of the rest are kept.
+        assert_eq!(
+            plan_page_tiering(3, 2, 5, &[1, 0, 3, 4]),
+            PagePlan {
+                head_page: 5,
+                keep: vec![1, 0]
+            }
+        );
+        // The head never moves back.
+        assert_eq!(
+            plan_page_tiering(4, 5, 5, &[2]),
+            PagePlan {
+                head_page: 5,
+                keep: vec![2]
+            }
+        );
+    }
+
+    #[test]
+    fn test_byte_rate_limiter_refills_over_time() {
+        let start = Instant::now();
+        let mut limiter = ByteRateLimiter::new(100, start);
+        assert!(limiter.take(60, start));
+        assert!(!limiter.take(60, start));
+        assert!(limiter.take(60, start + Duration::from_millis(200)));
+        // A request over the bucket waits for a full one.
+        assert!(!limiter.take(500, start + Duration::from_millis(500)));
+        assert!(limiter.take(500, start + Duration::from_secs(2)));
+        assert!(!limiter.take(1, start + Duration::from_secs(3)));
+
+        let mut unlimited = ByteRateLimiter::new(0, start);
+        assert!(unlimited.take(u64::MAX, start));
+    }
+
+    #[test]
+    fn test_page_tiering_task_pauses_resumes_and_runs_once() {
+        let tiering = PageTiering::start(Duration::from_millis(5), || {
+            Some(PageTieringStats {
+                pages_promoted: 1,
+                ..Default::default()
+            })
+        });
+        tiering.pause();
+        // Commands are handled in order, so the pause is in effect here.
+        assert!(tiering.run_once());
+        let paused = tiering.stats();
+        assert_eq!(paused.passes, paused.pages_promoted);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(tiering.stats(), paused);
+
+        tiering.resume();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while tiering.stats().passes <= paused.passes + 2 {
+            assert!(Instant::now() < deadline, "timed passes did not resume");
+            thread::sleep(Duration::from_millis(5));
+        }
+        tiering.stop();
+
+        // A pass that finds the store gone ends the task.
+        let ended = PageTiering::start(Duration::from_secs(60), || None);
+        assert!(!ended.run_once());
+    }
+
+    #[test]
+    fn test_admission_sheds_or_delays_at_overload() {
+        let controller = AdmissionController::new(AdmissionConfig {
+            max_flush_backlog_bytes: 1000,
+            ..Default::default()
+        });
+        let backlog = |bytes| PressureSignals {
+            flush_backlog_bytes: bytes,
+            ..Default::default()
+        };
+        assert_eq!(
+            controller.level(controller.pressure(&backlog(100))),
+            PressureLevel::Normal
+        );
+        assert_eq!(
+            controller.level(controller.pressure(&backlog(900))),
+            PressureLevel::Elevated
+        );
+        assert_eq!(controller.admit(|| backlog(999)), Ok(()));
+        assert_eq!(controller.admit(|| backlog(1000)), Err(Status::Overloaded));
+
+        let controller = AdmissionController::new(AdmissionConfig {
+            max_flush_backlog_bytes: 1000,
+            policy: OverloadPolicy::Delay {
+                max_wait: Duration::from_secs(5),
+            },
+            ..Default::default()
+        });
+        // The backlog drains on the third reading.
+        let mut readings = [2000, 1500, 0].into_iter();
+        assert_eq!(
+            controller.admit(|| backlog(readings.next().unwrap())),
+            Ok(())
+        );
+        let stats = controller.get_stats(&backlog(0));
+        assert_eq!((stats.admitted, stats.delayed, stats.shed), (1, 1, 0));
+    }
+
     #[test]
     fn test_key_stats() {
         let stats = KeyStats::new(1024);
@@ -419,4 +1124,57 @@ mod tests {
         // Should evict old entries when over capacity
         assert!(manager.should_evict_from_hot(&stats, 200000));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hot_capacity() {
+        let config = MigrationConfig {
+            max_hot_size_bytes: 1000,
+            ..Default::default()
+        };
+        let manager = MigrationManager::new(config);
+
+        assert!(manager.has_hot_capacity(1000));
+        manager.record_migration_to_hot(600);
+        assert!(manager.has_hot_capacity(400));
+        assert!(!manager.has_hot_capacity(401));
+    }
+
+    #[test]
+    fn test_mutable_region_controller() {
+        let controller = MutableRegionController::new(MutableRegionConfig {
+            min_fraction: 0.2,
+            max_fraction: 0.6,
+            initial_fraction: 0.4,
+            step: 0.1,
+            window: 100,
+            max_flush_backlog_bytes: 1000,
+            ..Default::default()
+        });
+
+        // Mostly in-place updates grow the region up to its bound.
+        for i in 0..500 {
+            controller.record_upsert(i % 10 != 0, 0);
+        }
+        let stats = controller.get_stats();
+        assert_eq!(stats.adjustments, 5);
+        assert!((stats.target_fraction - 0.6).abs() < 1e-9);
+        assert!((stats.in_place_hit_rate - 0.9).abs() < 1e-9);
+
+        // Appends shrink it down to its lower bound.
+        for _ in 0..500 {
+            controller.record_upsert(false, 0);
+        }
+        assert!((controller.target_fraction() - 0.2).abs() < 1e-9);
+
+        // A large flush backlog shrinks it even when updates hit in place.
+        let controller = MutableRegionController::new(MutableRegionConfig {
+            window: 10,
+            max_flush_backlog_bytes: 1000,
+            ..Default::default()
+        });
+        for _ in 0..10 {
+            controller.record_upsert(true, 5000);
+        }
+        assert!((controller.target_fraction() - 0.4).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,371 @@
+//! Capacity planning: how much memory and disk a store opened with given
+//! [`RecoveryOptions`] needs for a workload.
+//!
+//! The figures come from the actual layout: 64-byte index buckets with seven
+//! entries each, overflow buckets allocated a page of 2^20 at a time, 32MB
+//! log pages that records never straddle, and one flush frame per flush. The
+//! index stores only addresses, so keys are counted once, in the log. The
+//! log is append-only; dead records stay until [`admin::compact`] rewrites
+//! the store.
+//!
+//! [`admin::compact`]: crate::admin::compact
+
+use crate::core::constants::K_CACHE_LINE_BYTES;
+use crate::core::malloc_fixed_page_size::K_PAGE_SIZE as FIXED_PAGE_ITEMS;
+use crate::core::record::RecordInfo;
+use crate::hlog::persistent_memory_malloc::{FLUSH_FRAME_SIZE, NullDisk, PersistentMemoryMalloc};
+use crate::rskv_core::RecoveryOptions;
+use std::mem;
+
+/// Size of an index bucket, main table or overflow
+pub const INDEX_BUCKET_BYTES: u64 = K_CACHE_LINE_BYTES as u64;
+/// Entries per index bucket; the eighth slot links the overflow bucket
+pub const INDEX_BUCKET_ENTRIES: u64 = 7;
+/// Overflow buckets are allocated this many at a time; the first batch is
+/// allocated when the store is created
+pub const OVERFLOW_BUCKETS_PER_PAGE: u64 = FIXED_PAGE_ITEMS as u64;
+/// Size of an in-memory log page
+pub const LOG_PAGE_BYTES: u64 = PersistentMemoryMalloc::<NullDisk>::K_PAGE_SIZE;
+/// Log bytes before the first record, holding the superblock
+pub const LOG_HEADER_BYTES: u64 = PersistentMemoryMalloc::<NullDisk>::K_FIRST_VALID_ADDRESS;
+/// Header in front of every record's key and value
+pub const RECORD_HEADER_BYTES: u64 = mem::size_of::<RecordInfo>() as u64;
+/// Records are allocated in multiples of this
+pub const RECORD_ALIGNMENT: u64 = 8;
+/// Bytes appended to the frame file on every flush
+pub const FLUSH_FRAME_BYTES: u64 = FLUSH_FRAME_SIZE as u64;
+/// Average keys per bucket [`CapacityEstimate::recommended_table_size`]
+/// aims for, which keeps overflow buckets rare
+pub const TARGET_KEYS_PER_BUCKET: u64 = 4;
+
+/// What the store will hold and how it is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorkloadHints {
+    /// Live keys once the store is populated
+    pub keys: u64,
+    pub key_bytes: u64,
+    pub value_bytes: u64,
+    /// Upserts, RMWs and deletes per day after the store is populated
+    pub writes_per_day: u64,
+    /// Fraction of those writes that replace an existing key's record
+    pub overwrite_ratio: f64,
+    /// Calls to `flush` or `checkpoint` per day
+    pub flushes_per_day: u64,
+}
+
+impl Default for WorkloadHints {
+    fn default() -> Self {
+        Self {
+            keys: 0,
+            key_bytes: 8,
+            value_bytes: 8,
+            writes_per_day: 0,
+            overwrite_ratio: 1.0,
+            flushes_per_day: 24 * 60 * 60,
+        }
+    }
+}
+
+impl WorkloadHints {
+    /// Hints for `keys` keys of type `K` with values of type `V`
+    pub fn for_types<K, V>(keys: u64) -> Self {
+        Self {
+            keys,
+            key_bytes: mem::size_of::<K>() as u64,
+            value_bytes: mem::size_of::<V>() as u64,
+            ..Default::default()
+        }
+    }
+}
+
+/// Result of [`estimate`]. Byte counts are exact for the given sizes except
+/// for overflow buckets, which assume evenly spread key hashes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityEstimate {
+    /// Log bytes taken by one record
+    pub record_bytes: u64,
+    /// Header and padding in each record
+    pub record_overhead_bytes: u64,
+    /// Main hash table
+    pub index_table_bytes: u64,
+    /// Expected overflow buckets with `hints.keys` keys
+    pub overflow_buckets: u64,
+    /// Overflow bucket pages, including the one allocated up front
+    pub index_overflow_bytes: u64,
+    /// Index memory divided by the number of keys
+    pub index_bytes_per_key: f64,
+    /// Log file size once every key has been written once
+    pub populated_log_bytes: u64,
+    /// Log memory once populated: pages are allocated as the log reaches them
+    pub log_memory_bytes: u64,
+    /// Largest log the store can hold. Writes fail once the log reaches it.
+    pub log_capacity_bytes: u64,
+    /// Growth of the log and frame files per day
+    pub disk_growth_per_day: u64,
+    /// Part of that growth taken by records that are later replaced
+    pub dead_bytes_per_day: u64,
+    /// Table size keeping about [`TARGET_KEYS_PER_BUCKET`] keys per bucket
+    pub recommended_table_size: u64,
+    /// Log size at which to run `admin::compact`: when half of the populated
+    /// log could be dead records
+    pub compact_at_log_bytes: u64,
+    /// Days from a freshly populated store to `compact_at_log_bytes`; `None`
+    /// without writes
+    pub days_until_compaction: Option<f64>,
+    /// Log growth between checkpoints that keeps checkpoint writes no larger
+    /// than log writes, and bounds the log replayed on recovery
+    pub checkpoint_every_log_bytes: u64,
+    /// `log_size` that holds the log up to `compact_at_log_bytes`
+    pub recommended_log_size: u64,
+    /// Problems with `options` for this workload, one sentence each
+    pub warnings: Vec<String>,
+}
+
+impl CapacityEstimate {
+    /// Index and log memory together
+    pub fn memory_bytes(&self) -> u64 {
+        self.index_table_bytes + self.index_overflow_bytes + self.log_memory_bytes
+    }
+}
+
+/// Log bytes taken by one record with the given key and value sizes,
+/// assuming both are 8-byte aligned types
+pub fn record_bytes(key_bytes: u64, value_bytes: u64) -> u64 {
+    (RECORD_HEADER_BYTES + key_bytes + value_bytes).div_ceil(RECORD_ALIGNMENT) * RECORD_ALIGNMENT
+}
+
+/// Log file size after writing `records` records of `record_bytes` each
+/// into an empty store
+pub fn log_bytes_for(records: u64, record_bytes: u64) -> u64 {
+    if records == 0 {
+        return LOG_HEADER_BYTES;
+    }
+    let first_page = (LOG_PAGE_BYTES - LOG_HEADER_BYTES) / record_bytes;
+    if records <= first_page {
+        return LOG_HEADER_BYTES + records * record_bytes;
+    }
+    let per_page = LOG_PAGE_BYTES / record_bytes;
+    let rest = records - first_page;
+    let full_pages = (rest - 1) / per_page;
+    let last = rest - full_pages * per_page;
+    (1 + full_pages) * LOG_PAGE_BYTES + last * record_bytes
+}
+
+/// Expected overflow buckets per main bucket when keys per bucket follow a
+/// Poisson distribution with mean `lambda`
+fn overflow_buckets_per_bucket(lambda: f64) -> f64 {
+    if lambda <= 0.0 {
+        return 0.0;
+    }
+    let limit = (lambda + 12.0 * lambda.sqrt() + 30.0) as u64;
+    // pmf(n) computed in log space so large means do not underflow
+    let mut expected = 0.0;
+    let mut ln_factorial = 0.0;
+    for n in 0..=limit {
+        if n > 0 {
+            ln_factorial += (n as f64).ln();
+        }
+        if n <= INDEX_BUCKET_ENTRIES {
+            continue;
+        }
+        let pmf = (n as f64 * lambda.ln() - lambda - ln_factorial).exp();
+        expected += pmf * (n - INDEX_BUCKET_ENTRIES).div_ceil(INDEX_BUCKET_ENTRIES) as f64;
+    }
+    expected
+}
+
+/// Estimates memory and disk use of a store opened with `options` under
+/// `hints`, and recommends sizes and thresholds.
+pub fn estimate(options: &RecoveryOptions, hints: &WorkloadHints) -> CapacityEstimate {
+    let record_bytes = record_bytes(hints.key_bytes, hints.value_bytes);
+    let record_overhead_bytes = record_bytes - hints.key_bytes - hints.value_bytes;
+
+    let table_size = options.table_size.max(1);
+    let index_table_bytes = table_size * INDEX_BUCKET_BYTES;
+    let lambda = hints.keys as f64 / table_size as f64;
+    let overflow_buckets = (overflow_buckets_per_bucket(lambda) * table_size as f64).ceil() as u64;
+    // Overflow addresses start at 1, and the first page is always there
+    let overflow_pages = (overflow_buckets + 1).div_ceil(OVERFLOW_BUCKETS_PER_PAGE);
+    let index_overflow_bytes = overflow_pages * OVERFLOW_BUCKETS_PER_PAGE * INDEX_BUCKET_BYTES;
+    let index_bytes_per_key = if hints.keys == 0 {
+        0.0
+    } else {
+        (index_table_bytes + index_overflow_bytes) as f64 / hints.keys as f64
+    };
+
+    let buffer_pages = (options.log_size / LOG_PAGE_BYTES).max(1);
+    let log_capacity_bytes = buffer_pages * LOG_PAGE_BYTES;
+    let populated_log_bytes = log_bytes_for(hints.keys, record_bytes);
+    let log_memory_bytes = populated_log_bytes
+        .div_ceil(LOG_PAGE_BYTES)
+        .clamp(1, buffer_pages)
+        * LOG_PAGE_BYTES;
+
+    let disk_growth_per_day =
+        hints.writes_per_day * record_bytes + hints.flushes_per_day * FLUSH_FRAME_BYTES;
+    let dead_bytes_per_day =
+        (hints.writes_per_day as f64 * hints.overwrite_ratio.clamp(0.0, 1.0)) as u64 * record_bytes;
+
+    let compact_at_log_bytes = populated_log_bytes * 2;
+    let log_growth_per_day = hints.writes_per_day * record_bytes;
+    let days_until_compaction = (log_growth_per_day > 0)
+        .then(|| (compact_at_log_bytes - populated_log_bytes) as f64 / log_growth_per_day as f64);
+    let recommended_table_size = hints
+        .keys
+        .div_ceil(TARGET_KEYS_PER_BUCKET)
+        .max(1)
+        .next_power_of_two();
+    let recommended_log_size =
+        compact_at_log_bytes.div_ceil(LOG_PAGE_BYTES).max(1) * LOG_PAGE_BYTES;
+
+    let mut warnings = Vec::new();
+    if populated_log_bytes > log_capacity_bytes {
+        warnings.push(format!(
+            "log_size holds {} bytes of log but populating the store takes {}",
+            log_capacity_bytes, populated_log_bytes
+        ));
+    } else if compact_at_log_bytes > log_capacity_bytes {
+        warnings.push(format!(
+            "the log fills log_size ({} bytes) before it reaches the compaction threshold",
+            log_capacity_bytes
+        ));
+    }
+    if lambda > INDEX_BUCKET_ENTRIES as f64 {
+        warnings.push(format!(
+            "table_size {} averages {:.1} keys per bucket; lookups follow overflow chains",
+            table_size, lambda
+        ));
+    }
+
+    CapacityEstimate {
+        record_bytes,
+        record_overhead_bytes,
+        index_table_bytes,
+        overflow_buckets,
+        index_overflow_bytes,
+        index_bytes_per_key,
+        populated_log_bytes,
+        log_memory_bytes,
+        log_capacity_bytes,
+        disk_growth_per_day,
+        dead_bytes_per_day,
+        recommended_table_size,
+        compact_at_log_bytes,
+        days_until_compaction,
+        checkpoint_every_log_bytes: index_table_bytes.max(LOG_PAGE_BYTES),
+        recommended_log_size,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin;
+    use crate::device::file_system_disk::FileSystemDisk;
+    use crate::rskv_core::RsKv;
+    use crate::testing::temp_dir::TempDir;
+
+    #[test]
+    fn test_estimate_for_undersized_options() {
+        let options = RecoveryOptions {
+            log_size: 1 << 20,
+            table_size: 1 << 10,
+            ..Default::default()
+        };
+        let hints = WorkloadHints {
+            writes_per_day: 1_000_000,
+            overwrite_ratio: 0.5,
+            flushes_per_day: 0,
+            ..WorkloadHints::for_types::<u64, [u64; 4]>(4_000_000)
+        };
+        let estimate = estimate(&options, &hints);
+
+        assert_eq!(estimate.record_bytes, 56);
+        assert_eq!(estimate.record_overhead_bytes, 16);
+        assert_eq!(estimate.log_capacity_bytes, LOG_PAGE_BYTES);
+        assert_eq!(estimate.disk_growth_per_day, 56_000_000);
+        assert_eq!(estimate.dead_bytes_per_day, 28_000_000);
+        assert_eq!(estimate.recommended_table_size, 1 << 20);
+        assert_eq!(estimate.recommended_log_size % LOG_PAGE_BYTES, 0);
+        assert!(estimate.recommended_log_size >= 2 * 4_000_000 * 56);
+        // About 3900 keys per bucket, so about 560 overflow buckets each
+        let per_bucket = estimate.overflow_buckets as f64 / 1024.0;
+        assert!((550.0..580.0).contains(&per_bucket), "{}", per_bucket);
+        assert_eq!(estimate.warnings.len(), 2, "{:?}", estimate.warnings);
+    }
+
+    #[test]
+    fn test_overflow_estimate_vanishes_for_sparse_tables() {
+        assert_eq!(overflow_buckets_per_bucket(0.0), 0.0);
+        assert!(overflow_buckets_per_bucket(0.5) < 1e-6);
+        assert!(overflow_buckets_per_bucket(4.0) < 0.06);
+        let dense = overflow_buckets_per_bucket(70.0);
+        assert!((9.0..10.5).contains(&dense), "{}", dense);
+    }
+
+    #[test]
+    fn test_log_bytes_for_skips_page_tails() {
+        assert_eq!(log_bytes_for(0, 24), LOG_HEADER_BYTES);
+        assert_eq!(log_bytes_for(10, 24), LOG_HEADER_BYTES + 240);
+        let first_page = (LOG_PAGE_BYTES - LOG_HEADER_BYTES) / 24;
+        assert_eq!(
+            log_bytes_for(first_page + 1, 24),
+            LOG_PAGE_BYTES + 24,
+            "the record that does not fit starts the next page"
+        );
+    }
+
+    #[test]
+    fn test_estimate_matches_populated_store() {
+        let temp = TempDir::new("planning");
+        let dir = temp.path();
+        let options = RecoveryOptions {
+            log_size: 1 << 25,
+            table_size: 1 << 14,
+            ..Default::default()
+        };
+        let keys = 50_000u64;
+        let overwrites = 20_000u64;
+        let hints = WorkloadHints {
+            writes_per_day: overwrites,
+            flushes_per_day: 1,
+            ..WorkloadHints::for_types::<u64, u64>(keys)
+        };
+        let estimate = estimate(&options, &hints);
+
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(options.log_size, options.table_size, disk)
+            .unwrap();
+        kv.bulk_load((0..keys).map(|key| (key, key)), |key| *key)
+            .unwrap();
+        kv.flush().unwrap();
+        kv.bulk_load((0..overwrites).map(|key| (key, key + 1)), |key| *key)
+            .unwrap();
+        let day_log_bytes = kv.flush_backlog_bytes();
+        kv.flush().unwrap();
+        drop(kv);
+        let report = admin::stats(dir).unwrap();
+        // One frame per flush
+        assert_eq!(report.frame_bytes, 2 * FLUSH_FRAME_BYTES);
+
+        let within = |actual: u64, expected: u64| {
+            let diff = actual.abs_diff(expected) as f64;
+            assert!(
+                diff <= expected as f64 * 0.01,
+                "measured {} against estimated {}",
+                actual,
+                expected
+            );
+        };
+        within(
+            report.log_bytes - day_log_bytes,
+            estimate.populated_log_bytes,
+        );
+        within(
+            day_log_bytes + FLUSH_FRAME_BYTES,
+            estimate.disk_growth_per_day,
+        );
+        assert!(estimate.warnings.is_empty(), "{:?}", estimate.warnings);
+    }
+}
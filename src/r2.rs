@@ -1,15 +1,27 @@
-use crate::core::record::{Record, RecordInfo};
+use crate::core::address::Address;
+use crate::core::clock::{Clock, since_unix_epoch, system_clock};
+use crate::core::record::Record;
 use crate::core::status::Status;
 use crate::device::file_system_disk::FileSystemDisk;
-use crate::rskv_core::{RsKv, ReadContext, RmwContext, UpsertContext};
+use crate::rskv_core::{DeleteContext, RsKv, ReadContext, RmwContext, UpsertContext};
 use crate::index::IHashIndex;
 use crate::index::mem_index::FindContext;
-use crate::performance::access_analyzer::{AccessAnalyzer, AnalyzerConfig, OperationType};
+use crate::keys::KeyBytes;
+use crate::performance::access_analyzer::{AccessAnalyzer, AnalyzerConfig, Heat, OperationType};
 use crate::performance::migration_manager::{KeyStats, MigrationConfig, MigrationManager};
-use std::collections::HashMap;
+use checkpoint::R2CheckpointManifest;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::marker::PhantomData;
-use std::sync::{Arc, RwLock};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub mod checkpoint;
+pub mod config;
 
 #[cfg(test)]
 mod tests;
@@ -18,40 +30,416 @@ mod tests;
 pub type HotStore<'a, K, V> = RsKv<'a, K, V, FileSystemDisk>;
 pub type ColdStore<'a, K, V> = RsKv<'a, K, V, FileSystemDisk>; // This is conceptually the cold store.
 
+/// Upper bound on queued read promotions; cold hits beyond this are dropped.
+const MAX_PENDING_PROMOTIONS: usize = 4096;
+
+/// Keys whose values a [`ScanIter`] reads at once.
+pub const SCAN_BATCH: usize = 256;
+
+/// A cold-tier read hit waiting to be copied into the hot store.
+struct PendingPromotion<K, V> {
+    key: K,
+    key_hash: u64,
+    value: V,
+    /// Cold-tier record the value was read from
+    cold_address: Address,
+}
+
+/// Counters for read-through promotion of cold hits.
+#[derive(Debug, Default)]
+struct PromotionCounters {
+    cold_hits: AtomicU64,
+    promoted: AtomicU64,
+    rate_limited: AtomicU64,
+    budget_rejected: AtomicU64,
+    dropped: AtomicU64,
+    window_start_secs: AtomicU64,
+    window_count: AtomicU64,
+}
+
+/// Counters for background demotion of cold records.
+#[derive(Debug, Default)]
+struct DemotionCounters {
+    passes: AtomicU64,
+    demoted: AtomicU64,
+    pages_touched: AtomicU64,
+    conflicts: AtomicU64,
+}
+
+/// Counters for tier occupancy and hit ratios.
+#[derive(Debug, Default)]
+struct TierCounters {
+    hot_hits: AtomicU64,
+    misses: AtomicU64,
+    bytes_promoted: AtomicU64,
+    bytes_demoted: AtomicU64,
+    cold_records: AtomicU64,
+    cold_bytes: AtomicU64,
+}
+
+/// Promotion and demotion totals at the previous `tier_stats` call.
+struct TierInterval {
+    started: Instant,
+    promotions: u64,
+    demotions: u64,
+}
+
+/// A hot record selected for demotion.
+struct DemotionCandidate<K> {
+    key: K,
+    key_hash: u64,
+    address: Address,
+    last_access_ms: u64,
+    heat: Heat,
+}
+
+/// Statistics about read-through promotion
+#[derive(Debug, Clone)]
+pub struct PromotionStats {
+    /// Reads that missed the hot store and were served by the cold store
+    pub cold_hits: u64,
+    /// Records copied into the hot store
+    pub promoted: u64,
+    /// Cold hits skipped by the per-second promotion limit
+    pub rate_limited: u64,
+    /// Cold hits skipped because the hot store was at its budget
+    pub budget_rejected: u64,
+    /// Promotions abandoned because the queue was full or the key changed
+    pub dropped: u64,
+    /// Promotions queued but not yet applied
+    pub pending: usize,
+}
+
+/// Statistics about background demotion
+#[derive(Debug, Clone)]
+pub struct DemotionStats {
+    /// Demotion passes that found the hot store over its target size
+    pub passes: u64,
+    /// Records moved from the hot store to the cold store
+    pub demoted: u64,
+    /// Hot log pages that had at least one record demoted
+    pub pages_touched: u64,
+    /// Demotions abandoned because a writer updated the key concurrently
+    pub conflicts: u64,
+}
+
+/// Occupancy, hit ratio and migration statistics for both tiers
+#[derive(Debug, Clone, Serialize)]
+pub struct TierStats {
+    /// Bytes of live records accounted to the hot tier
+    pub hot_bytes: u64,
+    /// Number of keys resident in the hot tier
+    pub hot_records: u64,
+    /// Bytes of records copied into the cold tier
+    pub cold_bytes: u64,
+    /// Number of distinct keys copied into the cold tier
+    pub cold_records: u64,
+    /// Reads served by the hot tier
+    pub hot_hits: u64,
+    /// Reads served by the cold tier
+    pub cold_hits: u64,
+    /// Reads that found the key in neither tier
+    pub misses: u64,
+    /// Fraction of reads served by the hot tier
+    pub hot_hit_ratio: f64,
+    /// Fraction of reads served by the cold tier
+    pub cold_hit_ratio: f64,
+    /// Total promotions into the hot tier
+    pub promotions: u64,
+    /// Total demotions into the cold tier
+    pub demotions: u64,
+    /// Promotions since the previous `tier_stats` call
+    pub interval_promotions: u64,
+    /// Demotions since the previous `tier_stats` call
+    pub interval_demotions: u64,
+    /// Length of that interval in seconds
+    pub interval_secs: f64,
+    /// Bytes copied from the cold tier into the hot tier
+    pub bytes_promoted: u64,
+    /// Bytes copied from the hot tier into the cold tier
+    pub bytes_demoted: u64,
+}
+
+/// Storage settings for one R2Kv tier
+#[derive(Debug, Clone)]
+pub struct TierStorageConfig {
+    /// Directory holding the tier's log and checkpoints
+    pub path: String,
+    /// In-memory log budget in bytes
+    pub log_size: u64,
+    /// Number of hash index buckets
+    pub table_size: u64,
+}
+
+impl TierStorageConfig {
+    /// Defaults for the hot tier: a small log on fast storage
+    pub fn hot(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            log_size: 1 << 28,
+            table_size: 1 << 20,
+        }
+    }
+
+    /// Defaults for the cold tier: a large log on cheap storage
+    pub fn cold(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            log_size: 1 << 30,
+            table_size: 1 << 24,
+        }
+    }
+}
+
+/// Full configuration for an R2Kv instance
+#[derive(Debug, Clone)]
+pub struct R2Config {
+    pub hot: TierStorageConfig,
+    pub cold: TierStorageConfig,
+    pub migration: MigrationConfig,
+    pub analyzer: AnalyzerConfig,
+}
+
+impl R2Config {
+    /// Default tier settings at the given paths. The hot-tier size limit
+    /// tracks the hot log budget.
+    pub fn new(hot_path: &str, cold_path: &str) -> Self {
+        let hot = TierStorageConfig::hot(hot_path);
+        let migration = MigrationConfig {
+            max_hot_size_bytes: hot.log_size as usize,
+            ..Default::default()
+        };
+        Self {
+            hot,
+            cold: TierStorageConfig::cold(cold_path),
+            migration,
+            analyzer: AnalyzerConfig::default(),
+        }
+    }
+
+    /// Reject configurations where the tiers would share storage or a tier
+    /// has no memory budget
+    pub fn validate(&self) -> Result<(), Status> {
+        for tier in [&self.hot, &self.cold] {
+            if tier.path.is_empty() || tier.log_size == 0 || tier.table_size == 0 {
+                return Err(Status::InvalidConfiguration);
+            }
+        }
+        if normalize_path(&self.hot.path) == normalize_path(&self.cold.path) {
+            return Err(Status::InvalidConfiguration);
+        }
+        Ok(())
+    }
+}
+
+/// Resolve a path for comparison, following symlinks when it already exists
+fn normalize_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.components().collect())
+}
+
+/// Handle to the background task that applies promotions and runs demotion
+/// passes. The task stops when the handle is dropped.
+pub struct BackgroundMigration {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundMigration {
+    /// Stop the task and wait for the current pass to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for BackgroundMigration {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 pub struct R2Kv<'epoch, K, V> {
     hot_store: HotStore<'epoch, K, V>,
     cold_store: ColdStore<'epoch, K, V>,
     migration_manager: Arc<MigrationManager>,
     access_analyzer: Arc<AccessAnalyzer>,
     key_stats: Arc<RwLock<HashMap<u64, Arc<KeyStats>>>>,
+    hot_keys: RwLock<HashMap<u64, K>>,
+    cold_keys: RwLock<HashMap<u64, K>>,
+    /// Held shared by migrations; holding it exclusively pauses them
+    migration_gate: RwLock<()>,
+    checkpoint_sequence: AtomicU64,
+    pending_promotions: Mutex<VecDeque<PendingPromotion<K, V>>>,
+    pending_promotion_count: AtomicUsize,
+    /// Held while queued promotions are applied and for the whole of a
+    /// delete, so a promotion never lands between the two tiers' deletes
+    promotion_lock: Mutex<()>,
+    promotion_counters: PromotionCounters,
+    demotion_counters: DemotionCounters,
+    tier_counters: TierCounters,
+    tier_interval: Mutex<TierInterval>,
+    clock: Arc<dyn Clock>,
     _v: PhantomData<V>,
 }
 
-impl<'epoch, K, V> R2Kv<'epoch, K, V>
+/// Live records of an [`R2Kv`] scan, read a batch at a time. See
+/// [`R2Kv::scan_iter`].
+pub struct ScanIter<'a, 'epoch, K, V> {
+    kv: &'a R2Kv<'epoch, K, V>,
+    /// Keys not read yet, in key hash order
+    keys: std::vec::IntoIter<(u64, K)>,
+    /// Records read but not returned yet
+    batch: VecDeque<(K, V)>,
+}
+
+impl<K, V> Iterator for ScanIter<'_, '_, K, V>
 where
     K: Sized + Copy + 'static + PartialEq,
     V: Sized + Copy + 'static + Default,
 {
-    pub fn new(hot_log_path: &str, cold_log_path: &str) -> Result<Self, Status> {
-        let hot_disk = FileSystemDisk::new(hot_log_path)?;
-        let cold_disk = FileSystemDisk::new(cold_log_path)?;
+    type Item = (K, V);
 
-        let hot_store = RsKv::new(1 << 28, 1 << 20, hot_disk)?;
-        let cold_store = RsKv::new(1 << 30, 1 << 24, cold_disk)?;
+    fn next(&mut self) -> Option<(K, V)> {
+        while self.batch.is_empty() {
+            if self.keys.len() == 0 {
+                return None;
+            }
+            op_span!("scan_batch", keys = self.keys.len().min(SCAN_BATCH); results);
+            for (key_hash, key) in self.keys.by_ref().take(SCAN_BATCH) {
+                if let Some(value) = self.kv.live_value(key_hash, &key, |_, _| true) {
+                    self.batch.push_back((key, value));
+                }
+            }
+            span_record!(results = self.batch.len());
+        }
+        self.batch.pop_front()
+    }
 
-        let migration_config = MigrationConfig {
-            max_hot_size_bytes: 1 << 28, // 256MB
-            ..Default::default()
-        };
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.batch.len(), Some(self.batch.len() + self.keys.len()))
+    }
+}
 
-        Ok(Self {
-            hot_store,
-            cold_store,
-            migration_manager: Arc::new(MigrationManager::new(migration_config)),
-            access_analyzer: Arc::new(AccessAnalyzer::new(AnalyzerConfig::default())),
-            key_stats: Arc::new(RwLock::new(HashMap::new())),
-            _v: PhantomData,
-        })
+/// Read context that only reports whether a key exists.
+struct KeyProbeContext<'a, K, V> {
+    key: &'a K,
+    key_hash: u64,
+    found: bool,
+    _v: PhantomData<V>,
+}
+
+impl<K, V> ReadContext for KeyProbeContext<'_, K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+
+    fn get(&mut self, _value: &V) {
+        self.found = true;
+    }
+}
+
+/// Upsert context used to copy a record between tiers.
+struct MigrationUpsertContext<K, V> {
+    key: K,
+    key_hash: u64,
+    value: V,
+}
+
+impl<K, V> UpsertContext for MigrationUpsertContext<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    fn value(&self) -> &V {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+
+    fn put_atomic(&self, _value: &mut V) -> bool {
+        false
+    }
+}
+
+/// Read context that copies the value out.
+struct KeyValueReadContext<'a, K, V> {
+    key: &'a K,
+    key_hash: u64,
+    value: Option<V>,
+}
+
+impl<K, V: Copy> ReadContext for KeyValueReadContext<'_, K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+
+    fn get(&mut self, value: &V) {
+        self.value = Some(*value);
+    }
+}
+
+/// Read context that forwards to the caller and keeps a copy of the value.
+struct PromotionReadContext<'a, C, V> {
+    inner: &'a mut C,
+    value: Option<V>,
+}
+
+impl<C, V> ReadContext for PromotionReadContext<'_, C, V>
+where
+    C: ReadContext<Value = V>,
+    V: Copy,
+{
+    type Key = C::Key;
+    type Value = V;
+
+    fn key(&self) -> &Self::Key {
+        self.inner.key()
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.inner.key_hash()
+    }
+
+    fn get(&mut self, value: &V) {
+        self.value = Some(*value);
+        self.inner.get(value);
+    }
+}
+
+impl<'epoch, K, V> R2Kv<'epoch, K, V>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Copy + 'static + Default,
+{
+    pub fn new(hot_log_path: &str, cold_log_path: &str) -> Result<Self, Status> {
+        Self::with_config(R2Config::new(hot_log_path, cold_log_path))
     }
 
     pub fn new_with_config(
@@ -60,35 +448,78 @@ where
         migration_config: MigrationConfig,
         analyzer_config: AnalyzerConfig,
     ) -> Result<Self, Status> {
-        let hot_disk = FileSystemDisk::new(hot_log_path)?;
-        let cold_disk = FileSystemDisk::new(cold_log_path)?;
+        Self::with_config(R2Config {
+            migration: migration_config,
+            analyzer: analyzer_config,
+            ..R2Config::new(hot_log_path, cold_log_path)
+        })
+    }
+
+    /// Create an R2Kv with independent storage for each tier
+    pub fn with_config(config: R2Config) -> Result<Self, Status> {
+        Self::with_clock(config, system_clock())
+    }
 
-        let hot_store = RsKv::new(1 << 28, 1 << 20, hot_disk)?;
-        let cold_store = RsKv::new(1 << 30, 1 << 24, cold_disk)?;
+    /// [`Self::with_config`], reading the time for access stats, promotion
+    /// rate limits and tier intervals from `clock`, as both tiers do.
+    pub fn with_clock(config: R2Config, clock: Arc<dyn Clock>) -> Result<Self, Status> {
+        config.validate()?;
 
-        Ok(Self {
+        let hot_disk = FileSystemDisk::new(&config.hot.path)?;
+        let cold_disk = FileSystemDisk::new(&config.cold.path)?;
+
+        let hot_store = RsKv::new(config.hot.log_size, config.hot.table_size, hot_disk)?;
+        let cold_store = RsKv::new(config.cold.log_size, config.cold.table_size, cold_disk)?;
+        Ok(Self::from_stores(config, clock, hot_store, cold_store))
+    }
+
+    /// Assemble an R2Kv around two opened tiers, both reading the time from
+    /// `clock`.
+    fn from_stores(
+        config: R2Config,
+        clock: Arc<dyn Clock>,
+        mut hot_store: HotStore<'epoch, K, V>,
+        mut cold_store: ColdStore<'epoch, K, V>,
+    ) -> Self {
+        hot_store.set_clock(clock.clone());
+        cold_store.set_clock(clock.clone());
+
+        Self {
             hot_store,
             cold_store,
-            migration_manager: Arc::new(MigrationManager::new(migration_config)),
-            access_analyzer: Arc::new(AccessAnalyzer::new(analyzer_config)),
+            migration_manager: Arc::new(MigrationManager::new(config.migration)),
+            access_analyzer: Arc::new(AccessAnalyzer::with_clock(config.analyzer, clock.clone())),
             key_stats: Arc::new(RwLock::new(HashMap::new())),
+            hot_keys: RwLock::new(HashMap::new()),
+            cold_keys: RwLock::new(HashMap::new()),
+            migration_gate: RwLock::new(()),
+            checkpoint_sequence: AtomicU64::new(0),
+            pending_promotions: Mutex::new(VecDeque::new()),
+            promotion_lock: Mutex::new(()),
+            pending_promotion_count: AtomicUsize::new(0),
+            promotion_counters: PromotionCounters::default(),
+            demotion_counters: DemotionCounters::default(),
+            tier_counters: TierCounters::default(),
+            tier_interval: Mutex::new(TierInterval {
+                started: clock.now_monotonic(),
+                promotions: 0,
+                demotions: 0,
+            }),
+            clock,
             _v: PhantomData,
-        })
+        }
     }
 
-    fn get_current_time_ms() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64
+    fn get_current_time_ms(&self) -> u64 {
+        since_unix_epoch(self.clock.now_wall()).as_millis() as u64
     }
 
     fn get_or_create_key_stats(&self, key_hash: u64) -> Arc<KeyStats> {
         // Try read first
-        if let Ok(stats_map) = self.key_stats.read() {
-            if let Some(stats) = stats_map.get(&key_hash) {
-                return Arc::clone(stats);
-            }
+        if let Ok(stats_map) = self.key_stats.read()
+            && let Some(stats) = stats_map.get(&key_hash)
+        {
+            return Arc::clone(stats);
         }
 
         // Need to create new stats
@@ -101,7 +532,53 @@ where
         new_stats
     }
 
+    /// Mark a key as resident in the hot store, accounting for it once
+    fn mark_in_hot(&self, key_hash: u64, key: K, stats: &KeyStats) {
+        if !stats.swap_in_hot(true) {
+            self.migration_manager.record_migration_to_hot(stats.get_size());
+            if let Ok(mut hot_keys) = self.hot_keys.write() {
+                hot_keys.insert(key_hash, key);
+            }
+        }
+    }
+
+    /// Mark a key as moved out of the hot store
+    fn mark_demoted(&self, key_hash: u64, stats: &KeyStats) {
+        if stats.swap_in_hot(false) {
+            self.migration_manager.record_eviction_from_hot(stats.get_size());
+        }
+        if let Ok(mut hot_keys) = self.hot_keys.write() {
+            hot_keys.remove(&key_hash);
+        }
+    }
+
+    /// Check whether the hot store holds a live record for `key`
+    fn hot_contains(&self, key: &K, key_hash: u64) -> bool {
+        let mut probe = KeyProbeContext::<K, V> {
+            key,
+            key_hash,
+            found: false,
+            _v: PhantomData,
+        };
+        self.hot_store.read(&mut probe);
+        probe.found
+    }
+
+    /// Check whether the live cold record for `key` is the one at `address`
+    fn cold_holds(&self, key: &K, key_hash: u64, address: Address) -> bool {
+        let mut probe = KeyProbeContext::<K, V> {
+            key,
+            key_hash,
+            found: false,
+            _v: PhantomData,
+        };
+        self.cold_store
+            .read_with_meta(&mut probe)
+            .is_ok_and(|meta| meta.address == address)
+    }
+
     pub fn upsert(&self, context: &impl UpsertContext<Key = K, Value = V>) -> Status {
+        self.complete_pending_promotions();
         let key_hash = context.key_hash();
 
         // Record access
@@ -109,17 +586,18 @@ where
 
         // Update key stats
         let stats = self.get_or_create_key_stats(key_hash);
-        stats.record_access(Self::get_current_time_ms());
-        stats.set_in_hot(true);
-
-        // Record migration
-        self.migration_manager.record_migration_to_hot(stats.get_size());
+        stats.record_access(self.get_current_time_ms());
 
         // All writes go to the hot store.
-        self.hot_store.upsert(context)
+        let status = self.hot_store.upsert(context);
+        if status == Status::Ok {
+            self.mark_in_hot(key_hash, *context.key(), &stats);
+        }
+        status
     }
 
     pub fn read(&self, context: &mut impl ReadContext<Key = K, Value = V>) -> Status {
+        self.complete_pending_promotions();
         let key_hash = context.key_hash();
 
         // Record access
@@ -127,30 +605,49 @@ where
 
         // Update key stats
         let stats = self.get_or_create_key_stats(key_hash);
-        let current_time = Self::get_current_time_ms();
+        let current_time = self.get_current_time_ms();
         stats.record_access(current_time);
 
         let status = self.hot_store.read(context);
         if status == Status::NotFound {
             // If not found in hot store, check cold store.
-            let cold_status = self.cold_store.read(context);
-
-            // If found in cold store, check if we should migrate to hot
-            if cold_status == Status::Ok {
-                stats.set_in_hot(false);
-                if self.migration_manager.should_migrate_to_hot(&stats, current_time) {
-                    // Mark for future migration (actual migration happens in rmw)
+            let key = *context.key();
+            let mut cold_context = PromotionReadContext {
+                inner: context,
+                value: None,
+            };
+            let cold_status = match self.cold_store.read_with_meta(&mut cold_context) {
+                // Found in cold store, queue it for promotion into the hot store
+                Ok(meta) => {
+                    self.promotion_counters.cold_hits.fetch_add(1, Ordering::Relaxed);
+                    if let Some(value) = cold_context.value {
+                        let pending = PendingPromotion {
+                            key,
+                            key_hash,
+                            value,
+                            cold_address: meta.address,
+                        };
+                        self.enqueue_promotion(pending, &stats, current_time);
+                    }
+                    Status::Ok
                 }
-            }
+                Err(status) => status,
+            };
 
+            if cold_status == Status::NotFound {
+                self.tier_counters.misses.fetch_add(1, Ordering::Relaxed);
+            }
             cold_status
         } else {
-            stats.set_in_hot(true);
+            if status == Status::Ok {
+                self.tier_counters.hot_hits.fetch_add(1, Ordering::Relaxed);
+            }
             status
         }
     }
 
     pub fn rmw(&self, context: &mut impl RmwContext<Key = K, Value = V>) -> Status {
+        self.complete_pending_promotions();
         let key_hash = context.key_hash();
 
         // Record access
@@ -158,60 +655,45 @@ where
 
         // Update key stats
         let stats = self.get_or_create_key_stats(key_hash);
-        stats.record_access(Self::get_current_time_ms());
+        stats.record_access(self.get_current_time_ms());
 
         loop {
-            let status = self.hot_store.rmw(context);
-            if status != Status::NotFound {
-                stats.set_in_hot(true);
+            if self.hot_contains(context.key(), key_hash) {
+                let status = self.hot_store.rmw(context);
+                if status == Status::Ok {
+                    self.mark_in_hot(key_hash, *context.key(), &stats);
+                }
                 return status;
             }
 
             // Key not found in hot store, try to read from cold store.
-            struct F2RmwReadContext<'a, K, V> {
-                key: &'a K,
-                key_hash: u64,
-                value: Option<V>,
-            }
-
-            impl<'a, K: PartialEq, V: Copy> ReadContext for F2RmwReadContext<'a, K, V> {
-                type Key = K;
-                type Value = V;
-                fn key(&self) -> &K {
-                    self.key
-                }
-                fn key_hash(&self) -> u64 {
-                    self.key_hash
-                }
-                fn get(&mut self, value: &V) {
-                    self.value = Some(*value);
-                }
-            }
-
-            let mut read_context = F2RmwReadContext {
+            let mut read_context = KeyValueReadContext {
                 key: context.key(),
-                key_hash: context.key_hash(),
+                key_hash,
                 value: None,
             };
-
             self.cold_store.read(&mut read_context);
 
             // Now, conditionally insert the modified value into the hot store.
-            let mut find_context = FindContext::new(context.key_hash());
+            let mut find_context = FindContext::new(key_hash);
             self.hot_store.index.find_or_create_entry(&mut find_context);
 
-            if !find_context.entry.unused() {
+            if let Some(address) = self
+                .hot_store
+                .find_latest_address(&find_context, context.key())
+                && self
+                    .hot_store
+                    .record_at(address)
+                    .is_some_and(|(header, _, _)| !header.tombstone())
+            {
                 // Another thread inserted a value while we were reading from cold store. Retry.
                 continue;
             }
 
             let record_size = Record::<K, V>::required_size_with_alignment();
-            let new_address = match self.hot_store.hlog.allocate(record_size as u64) {
+            let new_address = match self.hot_store.allocate_record(record_size as u64) {
                 Ok(addr) => addr,
-                Err(closed_page) => {
-                    self.hot_store.hlog.new_page(closed_page);
-                    return Status::Pending;
-                }
+                Err(status) => return status,
             };
 
             let buffer = unsafe {
@@ -219,8 +701,9 @@ where
                     .hlog
                     .get_mut_slice_unchecked(new_address, record_size as usize)
             };
-            let new_record_info =
-                RecordInfo::new(find_context.entry.address(), 0, false, false, true);
+            let new_record_info = self
+                .hot_store
+                .new_record_info(find_context.entry.address(), false);
 
             unsafe {
                 let mut value_buffer = V::default();
@@ -237,6 +720,7 @@ where
                 .try_update_entry(&find_context, new_address, false)
                 == Status::Ok
             {
+                self.mark_in_hot(key_hash, *context.key(), &stats);
                 return Status::Ok;
             }
 
@@ -248,6 +732,727 @@ where
         }
     }
 
+    /// Delete a key from both tiers. The cold copy is removed first so that a
+    /// reader never falls through a hot tombstone to a stale cold value.
+    /// Promotions wait for the delete to finish, and one queued before it
+    /// finds the cold copy gone and is dropped.
+    pub fn delete(&self, context: &impl DeleteContext<Key = K>) -> Status {
+        self.complete_pending_promotions();
+        let Ok(_promoting) = self.promotion_lock.lock() else {
+            return Status::InternalError;
+        };
+        let key_hash = context.key_hash();
+        self.access_analyzer.record_access(key_hash, OperationType::Delete);
+        let stats = self.get_or_create_key_stats(key_hash);
+
+        let cold_status = self.cold_store.delete(context);
+        if cold_status == Status::Ok && stats.swap_in_cold(false) {
+            let size = stats.get_size() as u64;
+            self.tier_counters.cold_records.fetch_sub(1, Ordering::Relaxed);
+            self.tier_counters.cold_bytes.fetch_sub(size, Ordering::Relaxed);
+        }
+        if let Ok(mut cold_keys) = self.cold_keys.write() {
+            cold_keys.remove(&key_hash);
+        }
+
+        let hot_status = self.hot_store.delete(context);
+        if hot_status == Status::Ok {
+            self.mark_demoted(key_hash, &stats);
+        }
+
+        if hot_status == Status::Ok || cold_status == Status::Ok {
+            Status::Ok
+        } else {
+            Status::NotFound
+        }
+    }
+
+    /// Address of the newest record for `key` in one tier, tombstones
+    /// included.
+    fn latest_address(
+        store: &RsKv<'epoch, K, V, FileSystemDisk>,
+        key: &K,
+        key_hash: u64,
+    ) -> Option<Address> {
+        let mut find_context = FindContext::new(key_hash);
+        if store.index.find_entry(&mut find_context) != Status::Ok {
+            return None;
+        }
+        store.find_latest_address(&find_context, key)
+    }
+
+    /// Return every live key whose [`KeyBytes`] start with `prefix`, together
+    /// with its current value, ordered by key hash.
+    ///
+    /// Hot and cold key sets are merged and each key is resolved once. A live
+    /// hot record always wins. A hot tombstone or a missing hot record hands
+    /// ownership to the cold tier: that covers demoted keys and keys whose
+    /// promotion is still queued. Deletes remove the cold copy before the hot
+    /// one, so a deleted key is found in neither tier.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Vec<(K, V)>
+    where
+        K: KeyBytes,
+    {
+        self.scan_filter(prefix, |_, _| true, None)
+    }
+
+    /// [`Self::scan_prefix`], keeping only the records for which `pred`
+    /// holds, and at most `limit` of them. `pred` sees the [`KeyBytes`] of
+    /// the key and the in-memory bytes of the value, read in place from the
+    /// owning tier's log; only matching values are copied out. Record headers are checked
+    /// first, so tombstones never reach `pred`. Matches are taken in key hash
+    /// order, so a limited scan returns a prefix of the unlimited one.
+    pub fn scan_filter(
+        &self,
+        prefix: &[u8],
+        pred: impl Fn(&[u8], &[u8]) -> bool,
+        limit: Option<usize>,
+    ) -> Vec<(K, V)>
+    where
+        K: KeyBytes,
+    {
+        op_span!("scan", prefix_len = prefix.len(); keys, results);
+        self.scan_keys(
+            |key| key.key_bytes().starts_with(prefix),
+            |key, value_bytes| pred(key.key_bytes(), value_bytes),
+            limit,
+        )
+    }
+
+    /// Return every live key whose [`KeyBytes`] fall in `range`,
+    /// together with its current value, ordered by those bytes. Bytes
+    /// compare lexicographically, so an inverted or empty range finds
+    /// nothing, and `start..end` leaves out `end` while `start..=end` keeps
+    /// it. Keys resolve across tiers as in [`Self::scan_prefix`].
+    pub fn scan_range<'r>(&self, range: impl RangeBounds<&'r [u8]>) -> Vec<(K, V)>
+    where
+        K: KeyBytes,
+    {
+        op_span!("scan_range"; keys, results);
+        let mut results = self.scan_keys(|key| range.contains(&key.key_bytes()), |_, _| true, None);
+        results.sort_by(|(a, _), (b, _)| a.key_bytes().cmp(b.key_bytes()));
+        results
+    }
+
+    /// Live records of the keys that `key_matches` accepts and for which
+    /// `pred` holds, in key hash order. See [`Self::scan_filter`]. The key
+    /// and result counts are recorded on the caller's span.
+    fn scan_keys(
+        &self,
+        key_matches: impl Fn(&K) -> bool,
+        pred: impl Fn(&K, &[u8]) -> bool,
+        limit: Option<usize>,
+    ) -> Vec<(K, V)> {
+        let keys = self.key_snapshot();
+        span_record!(keys = keys.len());
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut results = Vec::new();
+        for (key_hash, key) in keys {
+            if results.len() >= limit {
+                break;
+            }
+            if !key_matches(&key) {
+                continue;
+            }
+            if let Some(value) = self.live_value(key_hash, &key, &pred) {
+                results.push((key, value));
+            }
+        }
+        span_record!(results = results.len());
+        results
+    }
+
+    /// Hash and key of every key in either tier, in key hash order
+    fn key_snapshot(&self) -> Vec<(u64, K)> {
+        self.complete_pending_promotions();
+
+        // Demotion publishes cold ownership before leaving the hot key set,
+        // so taking the hot snapshot first never misses a migrating key.
+        let mut keys: HashMap<u64, K> = match self.hot_keys.read() {
+            Ok(hot_keys) => hot_keys.clone(),
+            Err(_) => return Vec::new(),
+        };
+        if let Ok(cold_keys) = self.cold_keys.read() {
+            for (key_hash, key) in cold_keys.iter() {
+                keys.entry(*key_hash).or_insert(*key);
+            }
+        }
+        let mut keys: Vec<(u64, K)> = keys.into_iter().collect();
+        keys.sort_by_key(|(key_hash, _)| *key_hash);
+        keys
+    }
+
+    /// Current value of `key` from the tier that owns it, if it is live and
+    /// `pred` holds for it and its value bytes
+    fn live_value(&self, key_hash: u64, key: &K, pred: impl Fn(&K, &[u8]) -> bool) -> Option<V> {
+        let (store, address) =
+            [&self.hot_store, &self.cold_store]
+                .into_iter()
+                .find_map(|store| {
+                    let address = Self::latest_address(store, key, key_hash)?;
+                    let header = store.record_info_at(address)?;
+                    (!header.tombstone()).then_some((store, address))
+                })?;
+        // Copy the bytes the predicate matched, so an in-place update made
+        // after the check cannot be returned in their place.
+        store
+            .with_value_bytes(address, |value_bytes| {
+                pred(key, value_bytes)
+                    .then(|| unsafe { std::ptr::read_unaligned(value_bytes.as_ptr() as *const V) })
+            })
+            .flatten()
+    }
+
+    /// [`Self::scan_all`], one record at a time. The keys of both tiers are
+    /// taken up front, as for the other scans, but values are only read
+    /// [`SCAN_BATCH`] keys at a time as the iterator is advanced, so memory
+    /// holds one batch of values rather than all of them. A key deleted after
+    /// the scan began is skipped.
+    pub fn scan_iter(&self) -> ScanIter<'_, 'epoch, K, V> {
+        ScanIter {
+            kv: self,
+            keys: self.key_snapshot().into_iter(),
+            batch: VecDeque::with_capacity(SCAN_BATCH),
+        }
+    }
+
+    /// [`Self::scan_prefix`], one record at a time. See [`Self::scan_iter`].
+    pub fn scan_prefix_iter(&self, prefix: &[u8]) -> ScanIter<'_, 'epoch, K, V>
+    where
+        K: KeyBytes,
+    {
+        let mut keys = self.key_snapshot();
+        keys.retain(|(_, key)| key.key_bytes().starts_with(prefix));
+        ScanIter {
+            kv: self,
+            keys: keys.into_iter(),
+            batch: VecDeque::with_capacity(SCAN_BATCH),
+        }
+    }
+
+    /// Return every live key across both tiers. See [`Self::scan_prefix`].
+    pub fn scan_all(&self) -> Vec<(K, V)> {
+        self.scan_keys(|_| true, |_, _| true, None)
+    }
+
+    /// Queue a cold hit for promotion, subject to the hot budget and rate limit
+    fn enqueue_promotion(&self, pending: PendingPromotion<K, V>, stats: &KeyStats, now_ms: u64) {
+        let config = self.migration_manager.config();
+        if !config.enable_read_promotion {
+            return;
+        }
+        if !self.migration_manager.has_hot_capacity(stats.get_size()) {
+            self.promotion_counters
+                .budget_rejected
+                .fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        if !self.try_acquire_promotion_slot(now_ms, config.max_promotions_per_sec) {
+            self.promotion_counters
+                .rate_limited
+                .fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        if let Ok(mut queue) = self.pending_promotions.lock() {
+            if queue.len() >= MAX_PENDING_PROMOTIONS {
+                self.promotion_counters.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            queue.push_back(pending);
+            self.pending_promotion_count
+                .store(queue.len(), Ordering::Release);
+        }
+    }
+
+    /// Take one slot from the current one-second promotion window
+    fn try_acquire_promotion_slot(&self, now_ms: u64, limit: u64) -> bool {
+        if limit == 0 {
+            return true;
+        }
+
+        let counters = &self.promotion_counters;
+        let now_secs = now_ms / 1000;
+        let window = counters.window_start_secs.load(Ordering::Acquire);
+        if window != now_secs
+            && counters
+                .window_start_secs
+                .compare_exchange(window, now_secs, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        {
+            counters.window_count.store(0, Ordering::Release);
+        }
+
+        counters.window_count.fetch_add(1, Ordering::AcqRel) < limit
+    }
+
+    /// Apply queued promotions. Called at the start of every operation so that
+    /// the read which hit the cold tier never pays for the hot-tier insert.
+    /// Returns the number of records promoted.
+    pub fn complete_pending_promotions(&self) -> usize {
+        if self.pending_promotion_count.load(Ordering::Acquire) == 0 {
+            return 0;
+        }
+
+        // A checkpoint is in progress; leave the queue for the next operation.
+        let Ok(_gate) = self.migration_gate.try_read() else {
+            return 0;
+        };
+        // Another thread is draining the queue, or a delete is running.
+        let Ok(_promoting) = self.promotion_lock.try_lock() else {
+            return 0;
+        };
+
+        let batch: Vec<PendingPromotion<K, V>> = match self.pending_promotions.try_lock() {
+            Ok(mut queue) => {
+                let n = queue
+                    .len()
+                    .min(self.migration_manager.config().migration_batch_size.max(1));
+                let batch = queue.drain(..n).collect();
+                self.pending_promotion_count
+                    .store(queue.len(), Ordering::Release);
+                batch
+            }
+            Err(_) => return 0,
+        };
+
+        let mut promoted = 0;
+        for pending in batch {
+            if self.promote_to_hot(&pending) {
+                promoted += 1;
+            }
+        }
+        promoted
+    }
+
+    /// Insert a promoted record into the hot store unless the key already has
+    /// a hot version, which is always at least as new as the cold copy, or
+    /// the cold record it was read from has since been deleted or replaced.
+    /// The index entry is snapshotted before the hot-store probe so that any
+    /// concurrent writer makes the final CAS fail.
+    fn promote_to_hot(&self, pending: &PendingPromotion<K, V>) -> bool {
+        let stats = self.get_or_create_key_stats(pending.key_hash);
+        let size = stats.get_size();
+        if !self.migration_manager.has_hot_capacity(size) {
+            self.promotion_counters
+                .budget_rejected
+                .fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let mut find_context = FindContext::new(pending.key_hash);
+        self.hot_store.index.find_or_create_entry(&mut find_context);
+
+        if self.hot_contains(&pending.key, pending.key_hash) {
+            // A writer reached the hot store first.
+            self.promotion_counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        if !self.cold_holds(&pending.key, pending.key_hash, pending.cold_address) {
+            // Deleted, or replaced by a demotion, since it was read.
+            self.promotion_counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let record_size = Record::<K, V>::required_size_with_alignment();
+        let Ok(new_address) = self.hot_store.allocate_record(record_size as u64) else {
+            self.promotion_counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+
+        let buffer = unsafe {
+            self.hot_store
+                .hlog
+                .get_mut_slice_unchecked(new_address, record_size as usize)
+        };
+        let record_info = self
+            .hot_store
+            .new_record_info(find_context.entry.address(), false);
+        unsafe {
+            Record::create_in(buffer, record_info, &pending.key, &pending.value);
+        }
+
+        if self
+            .hot_store
+            .index
+            .try_update_entry(&find_context, new_address, false)
+            != Status::Ok
+        {
+            unsafe {
+                let record_ptr = buffer.as_mut_ptr() as *mut Record<K, V>;
+                (*record_ptr).header.set_invalid(true);
+            }
+            self.promotion_counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        self.mark_in_hot(pending.key_hash, pending.key, &stats);
+        self.promotion_counters.promoted.fetch_add(1, Ordering::Relaxed);
+        self.tier_counters
+            .bytes_promoted
+            .fetch_add(size as u64, Ordering::Relaxed);
+        true
+    }
+
+    /// Move the coldest hot records to the cold store until the hot store is
+    /// back under its target size. Records are grouped by hot log page and the
+    /// coldest pages are drained first so that whole pages go cold together.
+    ///
+    /// Candidates are frozen by shifting the hot log's read-only address and
+    /// waiting out the writers that may have loaded it before the shift, so
+    /// none is still updating them in place while they are copied. A write
+    /// made after that appends a new record, which makes the tombstone CAS
+    /// fail. Each record is written to the cold store before a tombstone is
+    /// CASed into the hot index, so a concurrent read finds the record in one
+    /// tier or the other. Returns the number of records demoted.
+    pub fn run_demotion_pass(&self) -> usize {
+        let config = self.migration_manager.config();
+        let target_size =
+            (config.max_hot_size_bytes as f64 * config.target_hot_utilization) as usize;
+        if self.migration_manager.get_stats().current_hot_size <= target_size {
+            return 0;
+        }
+        let Ok(_gate) = self.migration_gate.read() else {
+            return 0;
+        };
+        self.demotion_counters.passes.fetch_add(1, Ordering::Relaxed);
+
+        let frozen_until = self.hot_store.hlog.get_tail_address();
+        self.hot_store.hlog.freeze_until(frozen_until);
+
+        let hot_keys: Vec<(u64, K)> = match self.hot_keys.read() {
+            Ok(hot_keys) => hot_keys.iter().map(|(hash, key)| (*hash, *key)).collect(),
+            Err(_) => return 0,
+        };
+
+        let mut pages: HashMap<u32, Vec<DemotionCandidate<K>>> = HashMap::new();
+        for (key_hash, key) in hot_keys {
+            let mut find_context = FindContext::new(key_hash);
+            if self.hot_store.index.find_entry(&mut find_context) != Status::Ok {
+                continue;
+            }
+            let Some(address) = self.hot_store.find_latest_address(&find_context, &key) else {
+                continue;
+            };
+            if address >= frozen_until {
+                continue;
+            }
+            let stats = self.get_or_create_key_stats(key_hash);
+            pages
+                .entry(address.page())
+                .or_default()
+                .push(DemotionCandidate {
+                    key,
+                    key_hash,
+                    address,
+                    last_access_ms: stats.get_last_access_time(),
+                    heat: self.access_analyzer.classify(key_hash),
+                });
+        }
+
+        // Coldest page first, then coldest record within each page. A page is
+        // as hot as its hottest record; ties go to the least recently used.
+        let mut pages: Vec<Vec<DemotionCandidate<K>>> = pages.into_values().collect();
+        for page in pages.iter_mut() {
+            page.sort_by_key(|candidate| (candidate.heat, candidate.last_access_ms));
+        }
+        pages.sort_by_key(|page| {
+            page.iter()
+                .map(|c| (c.heat, c.last_access_ms))
+                .max()
+                .unwrap_or((Heat::Cold, 0))
+        });
+
+        let batch_size = config.migration_batch_size.max(1);
+        let mut demoted = 0;
+        'pages: for page in pages {
+            let mut page_demoted = false;
+            for candidate in page {
+                if demoted >= batch_size
+                    || self.migration_manager.get_stats().current_hot_size <= target_size
+                {
+                    if page_demoted {
+                        self.demotion_counters
+                            .pages_touched
+                            .fetch_add(1, Ordering::Relaxed);
+                    }
+                    break 'pages;
+                }
+                if self.demote_record(&candidate) {
+                    demoted += 1;
+                    page_demoted = true;
+                }
+            }
+            if page_demoted {
+                self.demotion_counters
+                    .pages_touched
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.demotion_counters
+            .demoted
+            .fetch_add(demoted as u64, Ordering::Relaxed);
+        demoted
+    }
+
+    /// Copy one record to the cold store and hide it in the hot store.
+    fn demote_record(&self, candidate: &DemotionCandidate<K>) -> bool {
+        let stats = self.get_or_create_key_stats(candidate.key_hash);
+
+        // Snapshot the index entry; any write to this chain after this point
+        // makes the tombstone CAS fail.
+        let mut find_context = FindContext::new(candidate.key_hash);
+        if self.hot_store.index.find_entry(&mut find_context) != Status::Ok {
+            return false;
+        }
+        if self
+            .hot_store
+            .find_latest_address(&find_context, &candidate.key)
+            != Some(candidate.address)
+        {
+            self.demotion_counters.conflicts.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        let Some((header, key, value)) = self.hot_store.record_at(candidate.address) else {
+            return false;
+        };
+        if header.tombstone() {
+            self.mark_demoted(candidate.key_hash, &stats);
+            return false;
+        }
+
+        let cold_context = MigrationUpsertContext {
+            key,
+            key_hash: candidate.key_hash,
+            value,
+        };
+        if self.cold_store.upsert(&cold_context) != Status::Ok {
+            return false;
+        }
+
+        let record_size = Record::<K, V>::required_size_with_alignment();
+        let Ok(tombstone_address) = self.hot_store.allocate_record(record_size as u64) else {
+            return false;
+        };
+        let buffer = unsafe {
+            self.hot_store
+                .hlog
+                .get_mut_slice_unchecked(tombstone_address, record_size as usize)
+        };
+        let record_info = self
+            .hot_store
+            .new_record_info(find_context.entry.address(), true);
+        unsafe {
+            Record::create_in(buffer, record_info, &key, &V::default());
+        }
+
+        if self
+            .hot_store
+            .index
+            .try_update_entry(&find_context, tombstone_address, false)
+            != Status::Ok
+        {
+            // A writer got in first; its newer value shadows the cold copy.
+            unsafe {
+                let record_ptr = buffer.as_mut_ptr() as *mut Record<K, V>;
+                (*record_ptr).header.set_invalid(true);
+            }
+            self.demotion_counters.conflicts.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        // Publish cold ownership before dropping the hot entry so that a
+        // scan always finds the key in at least one key set.
+        if let Ok(mut cold_keys) = self.cold_keys.write() {
+            cold_keys.insert(candidate.key_hash, key);
+        }
+        self.mark_demoted(candidate.key_hash, &stats);
+        let size = stats.get_size() as u64;
+        self.tier_counters
+            .bytes_demoted
+            .fetch_add(size, Ordering::Relaxed);
+        if !stats.swap_in_cold(true) {
+            self.tier_counters.cold_records.fetch_add(1, Ordering::Relaxed);
+            self.tier_counters.cold_bytes.fetch_add(size, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Every key owned by one tier, with its hash.
+    fn tier_keys(keys: &RwLock<HashMap<u64, K>>) -> Result<Vec<(u64, K)>, Status> {
+        let keys = keys.read().map_err(|_| Status::InternalError)?;
+        Ok(keys.iter().map(|(hash, key)| (*hash, *key)).collect())
+    }
+
+    /// Take a checkpoint of both tiers at a single cut.
+    ///
+    /// The exclusive borrow keeps every writer and migration out while each
+    /// tier is checkpointed with [`RsKv::checkpoint`] under `token`. One
+    /// manifest under the hot tier's directory then records both tier
+    /// checkpoints with their applied sequence numbers, which tier owns each
+    /// key, and the queued promotions, which the cold tier still owns. The
+    /// manifest is written last, so a crash part way leaves the previous
+    /// checkpoint of `token`, if any, to be rejected by [`Self::recover`].
+    pub fn checkpoint(&mut self, token: &str) -> Result<(), Status> {
+        let sequence = self.checkpoint_sequence.fetch_add(1, Ordering::AcqRel) + 1;
+
+        let in_flight = match self.pending_promotions.lock() {
+            Ok(queue) => queue
+                .iter()
+                .map(|pending| (pending.key_hash, pending.key))
+                .collect(),
+            Err(_) => return Err(Status::InternalError),
+        };
+
+        self.hot_store.checkpoint(token)?;
+        self.cold_store.checkpoint(token)?;
+
+        let manifest = R2CheckpointManifest {
+            sequence,
+            hot_token: token.to_string(),
+            hot_seq: self.hot_store.applied_seq(),
+            cold_token: token.to_string(),
+            cold_seq: self.cold_store.applied_seq(),
+            hot: Self::tier_keys(&self.hot_keys)?,
+            cold: Self::tier_keys(&self.cold_keys)?,
+            in_flight,
+        };
+        manifest.write_to(&checkpoint::manifest_path(
+            self.hot_store.disk.root_path(),
+            token,
+        ))
+    }
+
+    /// Recover an R2Kv from the checkpoint `token` written by [`Self::checkpoint`].
+    ///
+    /// Each tier is recovered from the checkpoint the manifest names, reading
+    /// its log in place. A tier whose applied sequence number differs from
+    /// the manifest's was checkpointed again under the same token after the
+    /// cut, and recovery fails with `Status::Corruption`. In-flight
+    /// promotions are discarded; their keys are served from the cold tier
+    /// until promoted again.
+    pub fn recover(hot_log_path: &str, cold_log_path: &str, token: &str) -> Result<Self, Status> {
+        let manifest =
+            R2CheckpointManifest::<K>::read_from(&checkpoint::manifest_path(hot_log_path, token))?;
+        let hot_store = RsKv::recover(hot_log_path, &manifest.hot_token)?;
+        let cold_store = RsKv::recover(cold_log_path, &manifest.cold_token)?;
+        if hot_store.applied_seq() != manifest.hot_seq
+            || cold_store.applied_seq() != manifest.cold_seq
+        {
+            log::error!(
+                "tier checkpoints of {} do not match its manifest: hot at {} (expected {}), cold at {} (expected {})",
+                token,
+                hot_store.applied_seq(),
+                manifest.hot_seq,
+                cold_store.applied_seq(),
+                manifest.cold_seq
+            );
+            return Err(Status::Corruption);
+        }
+
+        let r2 = Self::from_stores(
+            R2Config::new(hot_log_path, cold_log_path),
+            system_clock(),
+            hot_store,
+            cold_store,
+        );
+        r2.checkpoint_sequence
+            .store(manifest.sequence, Ordering::Release);
+
+        for (key_hash, key) in manifest.cold {
+            let stats = r2.get_or_create_key_stats(key_hash);
+            if !stats.swap_in_cold(true) {
+                let size = stats.get_size() as u64;
+                r2.tier_counters.cold_records.fetch_add(1, Ordering::Relaxed);
+                r2.tier_counters.cold_bytes.fetch_add(size, Ordering::Relaxed);
+            }
+            if let Ok(mut cold_keys) = r2.cold_keys.write() {
+                cold_keys.insert(key_hash, key);
+            }
+        }
+        for (key_hash, key) in manifest.hot {
+            let stats = r2.get_or_create_key_stats(key_hash);
+            r2.mark_in_hot(key_hash, key, &stats);
+        }
+
+        Ok(r2)
+    }
+
+    /// Get background demotion statistics
+    pub fn get_demotion_stats(&self) -> DemotionStats {
+        let counters = &self.demotion_counters;
+        DemotionStats {
+            passes: counters.passes.load(Ordering::Relaxed),
+            demoted: counters.demoted.load(Ordering::Relaxed),
+            pages_touched: counters.pages_touched.load(Ordering::Relaxed),
+            conflicts: counters.conflicts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get occupancy, hit ratio and migration statistics for both tiers.
+    /// The interval counters cover the time since the previous call.
+    pub fn tier_stats(&self) -> TierStats {
+        let tiers = &self.tier_counters;
+        let hot_hits = tiers.hot_hits.load(Ordering::Relaxed);
+        let cold_hits = self.promotion_counters.cold_hits.load(Ordering::Relaxed);
+        let misses = tiers.misses.load(Ordering::Relaxed);
+        let promotions = self.promotion_counters.promoted.load(Ordering::Relaxed);
+        let demotions = self.demotion_counters.demoted.load(Ordering::Relaxed);
+        let total_reads = (hot_hits + cold_hits + misses).max(1) as f64;
+
+        let (interval_promotions, interval_demotions, interval_secs) =
+            match self.tier_interval.lock() {
+                Ok(mut interval) => {
+                    let now = self.clock.now_monotonic();
+                    let delta = (
+                        promotions.saturating_sub(interval.promotions),
+                        demotions.saturating_sub(interval.demotions),
+                        now.duration_since(interval.started).as_secs_f64(),
+                    );
+                    *interval = TierInterval {
+                        started: now,
+                        promotions,
+                        demotions,
+                    };
+                    delta
+                }
+                Err(_) => (0, 0, 0.0),
+            };
+
+        TierStats {
+            hot_bytes: self.migration_manager.get_stats().current_hot_size as u64,
+            hot_records: self.hot_keys.read().map(|keys| keys.len()).unwrap_or(0) as u64,
+            cold_bytes: tiers.cold_bytes.load(Ordering::Relaxed),
+            cold_records: tiers.cold_records.load(Ordering::Relaxed),
+            hot_hits,
+            cold_hits,
+            misses,
+            hot_hit_ratio: hot_hits as f64 / total_reads,
+            cold_hit_ratio: cold_hits as f64 / total_reads,
+            promotions,
+            demotions,
+            interval_promotions,
+            interval_demotions,
+            interval_secs,
+            bytes_promoted: tiers.bytes_promoted.load(Ordering::Relaxed),
+            bytes_demoted: tiers.bytes_demoted.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get read-through promotion statistics
+    pub fn get_promotion_stats(&self) -> PromotionStats {
+        let counters = &self.promotion_counters;
+        PromotionStats {
+            cold_hits: counters.cold_hits.load(Ordering::Relaxed),
+            promoted: counters.promoted.load(Ordering::Relaxed),
+            rate_limited: counters.rate_limited.load(Ordering::Relaxed),
+            budget_rejected: counters.budget_rejected.load(Ordering::Relaxed),
+            dropped: counters.dropped.load(Ordering::Relaxed),
+            pending: self.pending_promotion_count.load(Ordering::Relaxed),
+        }
+    }
+
     /// Get migration statistics
     pub fn get_migration_stats(&self) -> crate::performance::migration_manager::MigrationStats {
         self.migration_manager.get_stats()
@@ -268,3 +1473,36 @@ where
         self.access_analyzer.get_hot_keys(n)
     }
 }
+
+impl<K, V> R2Kv<'static, K, V>
+where
+    K: Sized + Copy + Send + Sync + 'static + PartialEq,
+    V: Sized + Copy + Send + Sync + 'static + Default,
+{
+    /// Start a background thread that applies queued promotions and runs a
+    /// demotion pass every `interval`. The thread holds only a weak reference
+    /// and exits once the store is dropped.
+    pub fn start_background_migration(self: &Arc<Self>, interval: Duration) -> BackgroundMigration {
+        let stop = Arc::new(AtomicBool::new(false));
+        let store: Weak<Self> = Arc::downgrade(self);
+        let thread_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Acquire) {
+                match store.upgrade() {
+                    Some(kv) => {
+                        kv.complete_pending_promotions();
+                        kv.run_demotion_pass();
+                    }
+                    None => break,
+                }
+                thread::park_timeout(interval);
+            }
+        });
+
+        BackgroundMigration {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
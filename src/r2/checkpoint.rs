@@ -0,0 +1,361 @@
+use crate::core::status::Status;
+use crate::environment::file::sync_directory;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identifies an R2Kv checkpoint manifest file.
+pub const R2_CHECKPOINT_MAGIC: u64 = 0x5232_4b56_4350_4b54; // "R2KVCPKT"
+
+/// Current manifest format version.
+pub const R2_CHECKPOINT_VERSION: u32 = 2;
+
+/// Size of the encoded header at the start of a manifest.
+pub const R2_CHECKPOINT_HEADER_SIZE: usize = 88;
+
+/// Fixed-size header at the start of an R2Kv checkpoint manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct R2CheckpointHeader {
+    pub magic: u64,
+    pub version: u32,
+    pub key_size: u32,
+    /// Checkpoint sequence number shared by both tiers
+    pub sequence: u64,
+    /// Timestamp when the checkpoint was taken
+    pub timestamp: u64,
+    /// Hot tier's applied sequence number at the cut
+    pub hot_seq: u64,
+    /// Cold tier's applied sequence number at the cut
+    pub cold_seq: u64,
+    pub hot_count: u64,
+    pub cold_count: u64,
+    /// Promotions that were queued but not applied at the cut
+    pub in_flight_count: u64,
+    /// Checksum of the body
+    pub body_checksum: u64,
+    /// Checksum of this header
+    pub checksum: u64,
+}
+
+impl R2CheckpointHeader {
+    /// Calculates a simple checksum for the header
+    pub fn calculate_checksum(&self) -> u64 {
+        let mut checksum = self.magic;
+        checksum ^= (self.version as u64) << 32 | self.key_size as u64;
+        checksum ^= self.sequence;
+        checksum ^= self.timestamp;
+        checksum ^= self.hot_seq.rotate_left(8);
+        checksum ^= self.cold_seq.rotate_left(16);
+        checksum ^= self.hot_count.rotate_left(24);
+        checksum ^= self.cold_count.rotate_left(40);
+        checksum ^= self.in_flight_count.rotate_left(48);
+        checksum ^= self.body_checksum;
+        checksum
+    }
+
+    /// Updates the checksum field
+    pub fn update_checksum(&mut self) {
+        self.checksum = self.calculate_checksum();
+    }
+
+    /// Validates the checksum
+    pub fn validate_checksum(&self) -> bool {
+        self.checksum == self.calculate_checksum()
+    }
+
+    /// Encodes the header field by field in little-endian order.
+    pub fn encode(&self) -> [u8; R2_CHECKPOINT_HEADER_SIZE] {
+        let mut bytes = [0u8; R2_CHECKPOINT_HEADER_SIZE];
+        bytes[0..8].copy_from_slice(&self.magic.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.version.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.key_size.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.hot_seq.to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.cold_seq.to_le_bytes());
+        bytes[48..56].copy_from_slice(&self.hot_count.to_le_bytes());
+        bytes[56..64].copy_from_slice(&self.cold_count.to_le_bytes());
+        bytes[64..72].copy_from_slice(&self.in_flight_count.to_le_bytes());
+        bytes[72..80].copy_from_slice(&self.body_checksum.to_le_bytes());
+        bytes[80..88].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a header written by [`Self::encode`] without validating it.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Status> {
+        if bytes.len() < R2_CHECKPOINT_HEADER_SIZE {
+            return Err(Status::Corruption);
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        Ok(Self {
+            magic: u64_at(0),
+            version: u32_at(8),
+            key_size: u32_at(12),
+            sequence: u64_at(16),
+            timestamp: u64_at(24),
+            hot_seq: u64_at(32),
+            cold_seq: u64_at(40),
+            hot_count: u64_at(48),
+            cold_count: u64_at(56),
+            in_flight_count: u64_at(64),
+            body_checksum: u64_at(72),
+            checksum: u64_at(80),
+        })
+    }
+}
+
+/// A consistent cut of both R2Kv tiers.
+///
+/// Each tier is saved by its own [`RsKv::checkpoint`](crate::rskv_core::RsKv::checkpoint),
+/// referenced here by token and by the tier's applied sequence number at
+/// the cut. The key sections record which tier owns each key; a key may be
+/// owned by both, and the hot copy is newer. In-flight promotions are
+/// recorded for reporting only: the cold tier owns those keys, so recovery
+/// discards them.
+pub struct R2CheckpointManifest<K> {
+    pub sequence: u64,
+    pub hot_token: String,
+    pub hot_seq: u64,
+    pub cold_token: String,
+    pub cold_seq: u64,
+    pub hot: Vec<(u64, K)>,
+    pub cold: Vec<(u64, K)>,
+    pub in_flight: Vec<(u64, K)>,
+}
+
+/// Path of the manifest for `token` under the hot tier's root directory.
+pub fn manifest_path(hot_root: &str, token: &str) -> String {
+    format!("{}/r2-checkpoints/{}/manifest.dat", hot_root, token)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn push_token(body: &mut Vec<u8>, token: &str) {
+    body.extend_from_slice(&(token.len() as u32).to_le_bytes());
+    body.extend_from_slice(token.as_bytes());
+}
+
+fn take_token(body: &[u8], offset: &mut usize) -> Result<String, Status> {
+    let len = u32::from_le_bytes(take_bytes(body, offset, 4)?.try_into().unwrap()) as usize;
+    let bytes = take_bytes(body, offset, len)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| Status::Corruption)
+}
+
+/// Appends a key hash and its key. Keys are stored as their in-memory
+/// bytes, as the tier logs store them.
+fn push_key<K: Copy>(body: &mut Vec<u8>, key_hash: u64, key: &K) {
+    body.extend_from_slice(&key_hash.to_le_bytes());
+    let bytes = unsafe {
+        std::slice::from_raw_parts(key as *const K as *const u8, std::mem::size_of::<K>())
+    };
+    body.extend_from_slice(bytes);
+}
+
+fn take_key<K: Copy>(body: &[u8], offset: &mut usize) -> Result<(u64, K), Status> {
+    let key_hash = u64::from_le_bytes(take_bytes(body, offset, 8)?.try_into().unwrap());
+    let bytes = take_bytes(body, offset, std::mem::size_of::<K>())?;
+    let key = unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const K) };
+    Ok((key_hash, key))
+}
+
+fn take_bytes<'a>(body: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [u8], Status> {
+    let end = offset.checked_add(len).ok_or(Status::Corruption)?;
+    let bytes = body.get(*offset..end).ok_or(Status::Corruption)?;
+    *offset = end;
+    Ok(bytes)
+}
+
+impl<K: Copy> R2CheckpointManifest<K> {
+    /// Write the manifest to `path`. The file is written under a temporary
+    /// name and renamed into place, so a crash never leaves a partial manifest.
+    pub fn write_to(&self, path: &str) -> Result<(), Status> {
+        let mut body = Vec::new();
+        push_token(&mut body, &self.hot_token);
+        push_token(&mut body, &self.cold_token);
+        for (key_hash, key) in self.hot.iter().chain(&self.cold).chain(&self.in_flight) {
+            push_key(&mut body, *key_hash, key);
+        }
+
+        let mut header = R2CheckpointHeader {
+            magic: R2_CHECKPOINT_MAGIC,
+            version: R2_CHECKPOINT_VERSION,
+            key_size: std::mem::size_of::<K>() as u32,
+            sequence: self.sequence,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64,
+            hot_seq: self.hot_seq,
+            cold_seq: self.cold_seq,
+            hot_count: self.hot.len() as u64,
+            cold_count: self.cold.len() as u64,
+            in_flight_count: self.in_flight.len() as u64,
+            body_checksum: fnv1a(&body),
+            checksum: 0,
+        };
+        header.update_checksum();
+
+        let final_path = Path::new(path);
+        if let Some(dir) = final_path.parent() {
+            fs::create_dir_all(dir).map_err(|_| Status::IoError)?;
+        }
+        let tmp_path = format!("{}.tmp", path);
+        let mut file = fs::File::create(&tmp_path).map_err(|_| Status::IoError)?;
+        file.write_all(&header.encode())
+            .map_err(|_| Status::IoError)?;
+        file.write_all(&body).map_err(|_| Status::IoError)?;
+        file.sync_all().map_err(|_| Status::IoError)?;
+        fs::rename(&tmp_path, final_path).map_err(|_| Status::IoError)?;
+        // Sync the token directory for the rename and its parent for the
+        // token directory itself.
+        if let Some(dir) = final_path.parent() {
+            sync_directory(dir.to_str().ok_or(Status::IoError)?)?;
+            if let Some(parent) = dir.parent() {
+                sync_directory(parent.to_str().ok_or(Status::IoError)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read and validate the manifest at `path`.
+    pub fn read_from(path: &str) -> Result<Self, Status> {
+        let mut file = fs::File::open(path).map_err(|_| Status::IoError)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).map_err(|_| Status::IoError)?;
+
+        let header = R2CheckpointHeader::decode(&buffer)?;
+        if header.magic != R2_CHECKPOINT_MAGIC || !header.validate_checksum() {
+            return Err(Status::Corruption);
+        }
+        if header.version != R2_CHECKPOINT_VERSION
+            || header.key_size as usize != std::mem::size_of::<K>()
+        {
+            return Err(Status::VersionMismatch);
+        }
+        let body = &buffer[R2_CHECKPOINT_HEADER_SIZE..];
+        if fnv1a(body) != header.body_checksum {
+            return Err(Status::Corruption);
+        }
+
+        let mut offset = 0;
+        let hot_token = take_token(body, &mut offset)?;
+        let cold_token = take_token(body, &mut offset)?;
+        let mut read_keys = |count: u64| -> Result<Vec<(u64, K)>, Status> {
+            (0..count).map(|_| take_key(body, &mut offset)).collect()
+        };
+        let hot = read_keys(header.hot_count)?;
+        let cold = read_keys(header.cold_count)?;
+        let in_flight = read_keys(header.in_flight_count)?;
+
+        Ok(Self {
+            sequence: header.sequence,
+            hot_token,
+            hot_seq: header.hot_seq,
+            cold_token,
+            cold_seq: header.cold_seq,
+            hot,
+            cold,
+            in_flight,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp = TempDir::new("r2_manifest_round_trip");
+        let path = format!("{}/manifest.dat", temp.path());
+        let manifest = R2CheckpointManifest::<u64> {
+            sequence: 7,
+            hot_token: "hot".to_string(),
+            hot_seq: 12,
+            cold_token: "cold".to_string(),
+            cold_seq: 5,
+            hot: vec![(1, 1), (2, 2)],
+            cold: vec![(3, 3)],
+            in_flight: vec![(3, 3)],
+        };
+        manifest.write_to(&path).unwrap();
+
+        let loaded = R2CheckpointManifest::<u64>::read_from(&path).unwrap();
+        assert_eq!(loaded.sequence, 7);
+        assert_eq!(loaded.hot_token, "hot");
+        assert_eq!(loaded.hot_seq, 12);
+        assert_eq!(loaded.cold_token, "cold");
+        assert_eq!(loaded.cold_seq, 5);
+        assert_eq!(loaded.hot, manifest.hot);
+        assert_eq!(loaded.cold, manifest.cold);
+        assert_eq!(loaded.in_flight, manifest.in_flight);
+    }
+
+    #[test]
+    fn test_header_is_encoded_field_by_field() {
+        let mut header = R2CheckpointHeader {
+            magic: R2_CHECKPOINT_MAGIC,
+            version: R2_CHECKPOINT_VERSION,
+            key_size: 8,
+            sequence: 3,
+            timestamp: 0x0102_0304_0506_0708,
+            hot_seq: 10,
+            cold_seq: 20,
+            hot_count: 1,
+            cold_count: 2,
+            in_flight_count: 0,
+            body_checksum: 99,
+            checksum: 0,
+        };
+        header.update_checksum();
+
+        let bytes = header.encode();
+        assert_eq!(&bytes[24..32], &[8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(R2CheckpointHeader::decode(&bytes).unwrap(), header);
+        assert_eq!(
+            R2CheckpointHeader::decode(&bytes[..R2_CHECKPOINT_HEADER_SIZE - 1]).err(),
+            Some(Status::Corruption)
+        );
+    }
+
+    #[test]
+    fn test_manifest_detects_corruption() {
+        let temp = TempDir::new("r2_manifest_corrupt");
+        let path = format!("{}/manifest.dat", temp.path());
+        let manifest = R2CheckpointManifest::<u64> {
+            sequence: 1,
+            hot_token: "cut".to_string(),
+            hot_seq: 1,
+            cold_token: "cut".to_string(),
+            cold_seq: 0,
+            hot: vec![(1, 1)],
+            cold: Vec::new(),
+            in_flight: Vec::new(),
+        };
+        manifest.write_to(&path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        assert_eq!(
+            R2CheckpointManifest::<u64>::read_from(&path).err(),
+            Some(Status::Corruption)
+        );
+        assert_eq!(
+            R2CheckpointManifest::<u32>::read_from(&path).err(),
+            Some(Status::VersionMismatch)
+        );
+    }
+}
@@ -0,0 +1,621 @@
+//! Loading [`R2Config`] from TOML files and environment variables.
+//!
+//! Settings are layered: defaults, then the file, then the environment.
+//! Anything set in code on the returned config overrides both. Keys are
+//! the config field names, grouped by section:
+//!
+//! ```toml
+//! [hot]
+//! path = "/fast/rskv"
+//! log_size = "256MiB"
+//!
+//! [migration]
+//! time_window_secs = "5m"
+//! ```
+//!
+//! The environment form of `hot.log_size` with prefix `RSKV` is
+//! `RSKV_HOT_LOG_SIZE`. Byte sizes accept a plain integer or a number with
+//! a unit: `B`, the decimal `KB`, `MB`, `GB`, `TB`, or the binary `KiB`,
+//! `MiB`, `GiB`, `TiB`. Fields ending in `_secs` or `_ms` accept a plain
+//! integer in that unit or a number with one of `ms`, `s`, `m`, `h`, `d`.
+//!
+//! Environment values are read as the TOML value they spell, so `true`,
+//! `0.75` and `65536` are a boolean, a float and an integer, and anything
+//! else, such as `4GiB`, is a string.
+
+use super::R2Config;
+use crate::core::status::Status;
+use crate::performance::migration_manager::MigrationStrategy;
+use serde::Deserialize;
+use serde::de::{self, Deserializer, Unexpected};
+use std::fmt;
+use std::time::Duration;
+
+/// Why a configuration could not be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The file could not be read
+    Io { path: String, message: String },
+    /// The file is not valid TOML
+    Syntax { line: usize, message: String },
+    /// A known key has a value of the wrong form
+    InvalidValue {
+        key: String,
+        value: String,
+        message: String,
+    },
+    /// A key that matches no setting, in strict mode
+    UnknownKey(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, message } => write!(f, "cannot read {}: {}", path, message),
+            ConfigError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigError::InvalidValue { key, message, .. } => write!(f, "{}: {}", key, message),
+            ConfigError::UnknownKey(key) => write!(f, "unknown setting {}", key),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for Status {
+    fn from(error: ConfigError) -> Self {
+        match error {
+            ConfigError::Io { .. } => Status::IoError,
+            _ => Status::InvalidConfiguration,
+        }
+    }
+}
+
+/// Where to load settings from. See the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources<'a> {
+    /// TOML file applied over the defaults
+    pub file: Option<&'a str>,
+    /// Prefix of the variables in `env_vars` applied over the file
+    pub env_prefix: Option<&'a str>,
+    /// Environment to read prefixed variables from, usually
+    /// `std::env::vars().collect()`
+    pub env_vars: Vec<(String, String)>,
+    /// Reject unknown keys instead of logging a warning
+    pub strict: bool,
+}
+
+/// Parses a byte size such as `4096`, `256MB` or `4GiB`.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+    let number = number.replace('_', "");
+    if let Ok(whole) = number.parse::<u64>() {
+        return whole.checked_mul(multiplier);
+    }
+    let fraction: f64 = number.parse().ok()?;
+    let bytes = fraction * multiplier as f64;
+    (bytes.is_finite() && bytes >= 0.0 && bytes < u64::MAX as f64).then_some(bytes as u64)
+}
+
+/// Parses a duration such as `250ms`, `90s` or `5m`. A bare number is taken
+/// in `default_unit`.
+pub fn parse_duration(text: &str, default_unit: Duration) -> Option<Duration> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '_')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let count: u32 = number.replace('_', "").parse().ok()?;
+    let unit = match unit.trim() {
+        "" => default_unit,
+        "ms" => Duration::from_millis(1),
+        "s" => Duration::from_secs(1),
+        "m" => Duration::from_secs(60),
+        "h" => Duration::from_secs(60 * 60),
+        "d" => Duration::from_secs(24 * 60 * 60),
+        _ => return None,
+    };
+    unit.checked_mul(count)
+}
+
+/// Reads an integer in the field's own unit, or a string that
+/// `parse` turns into one.
+struct UnitVisitor<F> {
+    expected: &'static str,
+    parse: F,
+}
+
+impl<F: Fn(&str) -> Option<u64>> de::Visitor<'_> for UnitVisitor<F> {
+    type Value = Option<u64>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.expected)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Some(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        u64::try_from(value)
+            .map(Some)
+            .map_err(|_| E::invalid_value(Unexpected::Signed(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        match (self.parse)(value) {
+            Some(value) => Ok(Some(value)),
+            None => Err(E::invalid_value(Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+fn byte_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    deserializer.deserialize_any(UnitVisitor {
+        expected: "a byte size",
+        parse: parse_size,
+    })
+}
+
+fn secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    deserializer.deserialize_any(UnitVisitor {
+        expected: "a duration",
+        parse: |text: &str| parse_duration(text, Duration::from_secs(1)).map(|d| d.as_secs()),
+    })
+}
+
+fn millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    deserializer.deserialize_any(UnitVisitor {
+        expected: "a duration",
+        parse: |text: &str| {
+            parse_duration(text, Duration::from_millis(1)).map(|d| d.as_millis() as u64)
+        },
+    })
+}
+
+/// The settings of one source, each `None` unless the source sets it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigLayer {
+    hot: TierLayer,
+    cold: TierLayer,
+    migration: MigrationLayer,
+    analyzer: AnalyzerLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TierLayer {
+    path: Option<String>,
+    #[serde(deserialize_with = "byte_size")]
+    log_size: Option<u64>,
+    table_size: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MigrationLayer {
+    strategy: Option<MigrationStrategy>,
+    min_access_threshold: Option<u64>,
+    #[serde(deserialize_with = "secs")]
+    time_window_secs: Option<u64>,
+    #[serde(deserialize_with = "byte_size")]
+    max_hot_size_bytes: Option<u64>,
+    target_hot_utilization: Option<f64>,
+    migration_batch_size: Option<usize>,
+    adaptive_threshold: Option<bool>,
+    enable_read_promotion: Option<bool>,
+    max_promotions_per_sec: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AnalyzerLayer {
+    history_size: Option<usize>,
+    #[serde(deserialize_with = "secs")]
+    pattern_window_secs: Option<u64>,
+    min_accesses_for_pattern: Option<usize>,
+    hotspot_threshold: Option<f64>,
+    #[serde(deserialize_with = "millis")]
+    temporal_window_ms: Option<u64>,
+    #[serde(deserialize_with = "millis")]
+    decay_half_life_ms: Option<u64>,
+    hot_threshold: Option<f64>,
+    warm_threshold: Option<f64>,
+    heat_buckets: Option<u64>,
+}
+
+/// Sets each listed field of `target` that `layer` has a value for
+macro_rules! overlay {
+    ($target:expr, $layer:expr, $($field:ident),+ $(,)?) => {
+        $(
+            if let Some(value) = $layer.$field {
+                $target.$field = value;
+            }
+        )+
+    };
+}
+
+impl ConfigLayer {
+    /// Reads `table` into a layer, with the dotted path of every key that
+    /// matches no setting.
+    fn read(table: &toml::Table) -> Result<(Self, Vec<String>), ConfigError> {
+        let mut unknown = Vec::new();
+        let mut track = serde_path_to_error::Track::new();
+        let deserializer =
+            serde_path_to_error::Deserializer::new(toml::Value::Table(table.clone()), &mut track);
+        let layer = serde_ignored::deserialize(deserializer, |path| unknown.push(path.to_string()))
+            .map_err(|error: toml::de::Error| {
+                let key = track.path().to_string();
+                ConfigError::InvalidValue {
+                    value: setting_text(table, &key),
+                    key,
+                    message: error.message().to_string(),
+                }
+            })?;
+        Ok((layer, unknown))
+    }
+
+    fn apply_to(self, config: &mut R2Config) {
+        for (tier, layer) in [(&mut config.hot, self.hot), (&mut config.cold, self.cold)] {
+            overlay!(tier, layer, path, log_size, table_size);
+        }
+        let migration = self.migration;
+        overlay!(
+            config.migration,
+            migration,
+            strategy,
+            min_access_threshold,
+            time_window_secs,
+            target_hot_utilization,
+            migration_batch_size,
+            adaptive_threshold,
+            enable_read_promotion,
+            max_promotions_per_sec,
+        );
+        if let Some(bytes) = migration.max_hot_size_bytes {
+            config.migration.max_hot_size_bytes = bytes as usize;
+        }
+        overlay!(
+            config.analyzer,
+            self.analyzer,
+            history_size,
+            pattern_window_secs,
+            min_accesses_for_pattern,
+            hotspot_threshold,
+            temporal_window_ms,
+            decay_half_life_ms,
+            hot_threshold,
+            warm_threshold,
+            heat_buckets,
+        );
+    }
+}
+
+/// The value at dotted `key` in `table`, as written
+fn setting_text(table: &toml::Table, key: &str) -> String {
+    let mut parts = key.split('.');
+    let mut value = parts.next().and_then(|part| table.get(part));
+    for part in parts {
+        value = value.and_then(|value| value.get(part));
+    }
+    match value {
+        Some(toml::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+/// An environment value as the TOML value it spells, or else as a string
+fn env_value(text: &str) -> toml::Value {
+    format!("value = {}", text)
+        .parse::<toml::Table>()
+        .ok()
+        .filter(|table| table.len() == 1)
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(text.to_string()))
+}
+
+impl R2Config {
+    /// Defaults overlaid with the TOML file at `path`. Unknown keys are
+    /// logged and ignored; use [`R2Config::load`] to reject them.
+    pub fn from_toml_file(path: &str) -> Result<Self, ConfigError> {
+        Self::load(&ConfigSources {
+            file: Some(path),
+            ..Default::default()
+        })
+    }
+
+    /// Defaults overlaid with every `<prefix>_<SECTION>_<FIELD>` variable of
+    /// `vars`, usually `std::env::vars()`. Unknown variables with the prefix
+    /// are logged and ignored.
+    pub fn from_env(
+        prefix: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        Self::load(&ConfigSources {
+            env_prefix: Some(prefix),
+            env_vars: vars.into_iter().collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Defaults, overlaid with the file and then the environment. The result
+    /// is not validated, so fields can still be set in code before it is
+    /// used. Unless `migration.max_hot_size_bytes` is set, it tracks
+    /// `hot.log_size` as in [`R2Config::new`].
+    pub fn load(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let mut config = R2Config::new("", "");
+        let mut hot_size_set = false;
+
+        if let Some(path) = sources.file {
+            let text = std::fs::read_to_string(path).map_err(|error| ConfigError::Io {
+                path: path.to_string(),
+                message: error.to_string(),
+            })?;
+            let table: toml::Table =
+                text.parse()
+                    .map_err(|error: toml::de::Error| ConfigError::Syntax {
+                        line: error
+                            .span()
+                            .map_or(0, |span| text[..span.start].matches('\n').count() + 1),
+                        message: error.message().to_string(),
+                    })?;
+            let (layer, unknown) = ConfigLayer::read(&table)?;
+            for key in unknown {
+                if sources.strict {
+                    return Err(ConfigError::UnknownKey(key));
+                }
+                log::warn!("{}: ignoring unknown setting {}", path, key);
+            }
+            hot_size_set |= layer.migration.max_hot_size_bytes.is_some();
+            layer.apply_to(&mut config);
+        }
+
+        if let Some(prefix) = sources.env_prefix {
+            let prefix = format!("{}_", prefix.to_ascii_uppercase());
+            let mut vars: Vec<&(String, String)> = sources
+                .env_vars
+                .iter()
+                .filter(|(name, _)| name.starts_with(&prefix))
+                .collect();
+            vars.sort();
+            let mut table = toml::Table::new();
+            let mut names = Vec::new();
+            for (name, value) in vars {
+                let key = env_key(&name[prefix.len()..]);
+                let value = env_value(value);
+                match key.split_once('.') {
+                    Some((section, field)) => {
+                        let section = table
+                            .entry(section)
+                            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                        if let toml::Value::Table(section) = section {
+                            section.insert(field.to_string(), value);
+                        }
+                    }
+                    None => {
+                        table.insert(key.clone(), value);
+                    }
+                }
+                names.push((key, name));
+            }
+            let (layer, unknown) = ConfigLayer::read(&table)?;
+            for key in unknown {
+                let name = env_name(&names, &key);
+                if sources.strict {
+                    return Err(ConfigError::UnknownKey(name));
+                }
+                log::warn!("ignoring unknown setting {}", name);
+            }
+            hot_size_set |= layer.migration.max_hot_size_bytes.is_some();
+            layer.apply_to(&mut config);
+        }
+
+        if !hot_size_set {
+            config.migration.max_hot_size_bytes = config.hot.log_size as usize;
+        }
+        Ok(config)
+    }
+}
+
+/// `HOT_LOG_SIZE` -> `hot.log_size`
+fn env_key(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.split_once('_') {
+        Some((section, field)) => format!("{}.{}", section, field),
+        None => name,
+    }
+}
+
+/// The variable that set dotted `key`, or `key` itself
+fn env_name(names: &[(String, &String)], key: &str) -> String {
+    names
+        .iter()
+        .find(|(name_key, _)| name_key == key)
+        .map_or_else(|| key.to_string(), |(_, name)| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!(
+            "{}/tests/fixtures/config/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        )
+    }
+
+    #[test]
+    fn test_parse_sizes_and_durations() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("256MB"), Some(256_000_000));
+        assert_eq!(parse_size("4GiB"), Some(4 << 30));
+        assert_eq!(parse_size("1.5 KiB"), Some(1536));
+        assert_eq!(parse_size("1_024 b"), Some(1024));
+        assert_eq!(parse_size("12 parsecs"), None);
+        assert_eq!(parse_size("99999999999TiB"), None);
+
+        let secs = Duration::from_secs(1);
+        assert_eq!(parse_duration("300", secs), Some(Duration::from_secs(300)));
+        assert_eq!(
+            parse_duration("250ms", secs),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(parse_duration("5m", secs), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h", secs), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1.5s", secs), None);
+        assert_eq!(parse_duration("3 fortnights", secs), None);
+    }
+
+    #[test]
+    fn test_load_full_toml_file() {
+        let config = R2Config::from_toml_file(&fixture("full.toml")).unwrap();
+        assert_eq!(config.hot.path, "/var/lib/rskv/hot");
+        assert_eq!(config.hot.log_size, 256 << 20);
+        assert_eq!(config.hot.table_size, 1 << 16);
+        assert_eq!(config.cold.path, "/mnt/cold # not a comment");
+        assert_eq!(config.cold.log_size, 4_000_000_000);
+        assert_eq!(
+            config.migration.strategy,
+            MigrationStrategy::LeastRecentlyUsed
+        );
+        assert_eq!(config.migration.time_window_secs, 600);
+        assert!(!config.migration.enable_read_promotion);
+        assert_eq!(config.migration.target_hot_utilization, 0.75);
+        // Not set in the file, so it follows the hot log budget
+        assert_eq!(config.migration.max_hot_size_bytes, 256 << 20);
+        assert_eq!(config.analyzer.decay_half_life_ms, 90_000);
+        assert_eq!(config.analyzer.temporal_window_ms, 500);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_keys_warn_unless_strict() {
+        let config = R2Config::from_toml_file(&fixture("unknown_key.toml")).unwrap();
+        assert_eq!(config.hot.path, "/tmp/hot");
+
+        let strict = R2Config::load(&ConfigSources {
+            file: Some(&fixture("unknown_key.toml")),
+            strict: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            strict.unwrap_err(),
+            ConfigError::UnknownKey("hot.compression".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_name_key_and_value() {
+        let error = R2Config::from_toml_file(&fixture("bad_size.toml")).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                ConfigError::InvalidValue { ref key, ref value, ref message }
+                    if key == "hot.log_size" && value == "lots" && message.contains("a byte size")
+            ),
+            "{:?}",
+            error
+        );
+        assert_eq!(
+            error.to_string(),
+            "hot.log_size: invalid value: string \"lots\", expected a byte size"
+        );
+
+        let error = R2Config::from_toml_file(&fixture("bad_duration.toml")).unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::InvalidValue { ref key, ref value, .. }
+                if key == "analyzer.decay_half_life_ms" && value == "soon"
+        ));
+
+        let error = R2Config::from_toml_file(&fixture("wrong_type.toml")).unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::InvalidValue { ref key, ref message, .. }
+                if key == "migration.adaptive_threshold" && message.contains("a boolean")
+        ));
+
+        let error = R2Config::from_toml_file(&fixture("syntax.toml")).unwrap_err();
+        assert!(
+            matches!(error, ConfigError::Syntax { line: 4, .. }),
+            "{:?}",
+            error
+        );
+
+        let error = R2Config::from_toml_file(&fixture("missing.toml")).unwrap_err();
+        assert!(matches!(error, ConfigError::Io { .. }));
+        assert_eq!(Status::from(error), Status::IoError);
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let env = vars(&[
+            ("RSKV_HOT_LOG_SIZE", "1GiB"),
+            ("RSKV_MIGRATION_TIME_WINDOW_SECS", "2m"),
+            ("RSKV_MIGRATION_ADAPTIVE_THRESHOLD", "false"),
+            ("RSKV_ANALYZER_HOT_THRESHOLD", "8"),
+            ("OTHER_HOT_SPEED", "fast"),
+        ]);
+        let config = R2Config::load(&ConfigSources {
+            file: Some(&fixture("full.toml")),
+            env_prefix: Some("RSKV"),
+            env_vars: env.clone(),
+            strict: true,
+        })
+        .unwrap();
+        assert_eq!(config.hot.path, "/var/lib/rskv/hot");
+        assert_eq!(config.hot.log_size, 1 << 30);
+        assert_eq!(config.migration.max_hot_size_bytes, 1 << 30);
+        assert_eq!(config.migration.time_window_secs, 120);
+        assert!(!config.migration.adaptive_threshold);
+        assert_eq!(config.analyzer.hot_threshold, 8.0);
+
+        let mut env = env;
+        env.push(("RSKV_HOT_SPEED".to_string(), "fast".to_string()));
+        let strict = R2Config::load(&ConfigSources {
+            env_prefix: Some("RSKV"),
+            env_vars: env.clone(),
+            strict: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            strict.unwrap_err(),
+            ConfigError::UnknownKey("RSKV_HOT_SPEED".to_string())
+        );
+        let lenient = R2Config::from_env("RSKV", env).unwrap();
+        assert_eq!(lenient.hot.log_size, 1 << 30);
+
+        let error = R2Config::from_env("RSKV", vars(&[("RSKV_HOT_LOG_SIZE", "big")])).unwrap_err();
+        assert!(matches!(
+            error,
+            ConfigError::InvalidValue { ref key, ref value, .. }
+                if key == "hot.log_size" && value == "big"
+        ));
+    }
+}
@@ -2,8 +2,11 @@
 #[allow(clippy::module_inception)]
 mod tests {
     use crate::core::status::Status;
-    use crate::r2::R2Kv;
-    use crate::rskv_core::{ReadContext, RmwContext, UpsertContext};
+    use crate::performance::access_analyzer::AnalyzerConfig;
+    use crate::performance::migration_manager::MigrationConfig;
+    use crate::r2::{R2Config, R2Kv, SCAN_BATCH, TierStorageConfig};
+    use crate::rskv_core::{DeleteContext, ReadContext, RmwContext, UpsertContext};
+    use crate::testing::clock::MockClock;
     use std::path::Path;
     use std::sync::Arc;
     use std::thread;
@@ -111,6 +114,72 @@ mod tests {
         }
     }
 
+    // Delete上下文
+    struct TestDeleteContext {
+        key: u64,
+    }
+
+    impl DeleteContext for TestDeleteContext {
+        type Key = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+    }
+
+    // 字节数组键的Upsert上下文，用于前缀扫描
+    struct ByteKeyUpsertContext {
+        key: [u8; 8],
+        value: TestData,
+    }
+
+    impl UpsertContext for ByteKeyUpsertContext {
+        type Key = [u8; 8];
+        type Value = TestData;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn value(&self) -> &Self::Value {
+            &self.value
+        }
+
+        fn key_hash(&self) -> u64 {
+            u64::from_be_bytes(self.key)
+        }
+
+        fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+            false
+        }
+    }
+
+    // 字节数组键的Delete上下文
+    struct ByteKeyDeleteContext {
+        key: [u8; 8],
+    }
+
+    impl DeleteContext for ByteKeyDeleteContext {
+        type Key = [u8; 8];
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            u64::from_be_bytes(self.key)
+        }
+    }
+
+    // 形如 "user:001" 的字节数组键
+    fn user(i: u64) -> [u8; 8] {
+        format!("user:{:03}", i).as_bytes().try_into().unwrap()
+    }
+
     // 创建临时测试目录
     fn create_test_dirs() -> (String, String) {
         let test_id = std::time::SystemTime::now()
@@ -186,7 +255,7 @@ mod tests {
 
         let status = r2_kv.read(&mut read_ctx);
         if status == Status::Ok {
-            assert_eq!(read_ctx.value.unwrap().value, 50); // RMW创建的新值
+            assert_eq!(read_ctx.value.unwrap().value, 150); // 初始值100加上增量50
         }
 
         cleanup_test_dirs(&hot_dir, &cold_dir);
@@ -482,4 +551,1002 @@ mod tests {
 
         cleanup_test_dirs(&hot_dir, &cold_dir);
     }
+
+    #[test]
+    fn test_r2_read_through_promotion() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let r2_kv = R2Kv::<u64, TestData>::new(&hot_dir, &cold_dir)
+            .expect("Failed to create R2Kv instance");
+
+        // 直接写入冷存储，模拟已经被降级的数据
+        let upsert_ctx = TestUpsertContext {
+            key: 42,
+            value: TestData::new(42, 4200),
+        };
+        assert_eq!(r2_kv.cold_store.upsert(&upsert_ctx), Status::Ok);
+
+        // 第一次读取由冷存储提供，提升操作只进入队列
+        let mut read_ctx = TestReadContext {
+            key: 42,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        assert_eq!(read_ctx.value.unwrap().value, 4200);
+
+        let stats = r2_kv.get_promotion_stats();
+        assert_eq!(stats.cold_hits, 1);
+        assert_eq!(stats.promoted, 0);
+        assert_eq!(stats.pending, 1);
+
+        // 第二次读取应当命中热存储
+        let mut read_ctx = TestReadContext {
+            key: 42,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        assert_eq!(read_ctx.value.unwrap().value, 4200);
+
+        let stats = r2_kv.get_promotion_stats();
+        assert_eq!(stats.cold_hits, 1);
+        assert_eq!(stats.promoted, 1);
+        assert_eq!(stats.pending, 0);
+
+        let mut hot_ctx = TestReadContext {
+            key: 42,
+            value: None,
+        };
+        assert_eq!(r2_kv.hot_store.read(&mut hot_ctx), Status::Ok);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_promotion_does_not_overwrite_hot_value() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let r2_kv = R2Kv::<u64, TestData>::new(&hot_dir, &cold_dir)
+            .expect("Failed to create R2Kv instance");
+
+        let cold_ctx = TestUpsertContext {
+            key: 7,
+            value: TestData::new(7, 1),
+        };
+        assert_eq!(r2_kv.cold_store.upsert(&cold_ctx), Status::Ok);
+
+        let mut read_ctx = TestReadContext {
+            key: 7,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+
+        // 在提升完成之前写入新值，旧的冷数据不能覆盖它
+        let upsert_ctx = TestUpsertContext {
+            key: 7,
+            value: TestData::new(7, 2),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        r2_kv.complete_pending_promotions();
+
+        let mut read_ctx = TestReadContext {
+            key: 7,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        assert_eq!(read_ctx.value.unwrap().value, 2);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_delete_drops_promotion_queued_before_it() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let r2_kv = R2Kv::<u64, TestData>::new(&hot_dir, &cold_dir)
+            .expect("Failed to create R2Kv instance");
+
+        let cold_ctx = TestUpsertContext {
+            key: 9,
+            value: TestData::new(9, 90),
+        };
+        assert_eq!(r2_kv.cold_store.upsert(&cold_ctx), Status::Ok);
+
+        // 检查点持有迁移闸门时，排空队列会被跳过，提升一直留在队列中
+        let gate = r2_kv.migration_gate.write().unwrap();
+        let mut read_ctx = TestReadContext {
+            key: 9,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        assert_eq!(r2_kv.delete(&TestDeleteContext { key: 9 }), Status::Ok);
+        assert_eq!(r2_kv.get_promotion_stats().pending, 1);
+        drop(gate);
+
+        // 删除之后再排空队列，旧值不能复活
+        assert_eq!(r2_kv.complete_pending_promotions(), 0);
+        let mut read_ctx = TestReadContext {
+            key: 9,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::NotFound);
+        let mut hot_ctx = TestReadContext {
+            key: 9,
+            value: None,
+        };
+        assert_eq!(r2_kv.hot_store.read(&mut hot_ctx), Status::NotFound);
+
+        let stats = r2_kv.get_promotion_stats();
+        assert_eq!(stats.promoted, 0);
+        assert_eq!(stats.dropped, 1);
+        assert_eq!(stats.pending, 0);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_promotion_rate_limit() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let config = R2Config {
+            migration: MigrationConfig {
+                max_promotions_per_sec: 2,
+                ..Default::default()
+            },
+            ..R2Config::new(&hot_dir, &cold_dir)
+        };
+        let clock = Arc::new(MockClock::new());
+        let r2_kv = R2Kv::<u64, TestData>::with_clock(config, clock.clone())
+            .expect("Failed to create R2Kv instance");
+
+        for i in 1..=30 {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i),
+            };
+            assert_eq!(r2_kv.cold_store.upsert(&upsert_ctx), Status::Ok);
+        }
+
+        // 模拟扫描：每个冷键只读一次，时钟停在同一秒内
+        let scan = |keys: std::ops::RangeInclusive<u64>| {
+            for key in keys {
+                let mut read_ctx = TestReadContext { key, value: None };
+                assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+            }
+            r2_kv.complete_pending_promotions();
+        };
+        scan(1..=20);
+        let stats = r2_kv.get_promotion_stats();
+        assert_eq!(stats.cold_hits, 20);
+        assert_eq!(stats.promoted, 2);
+        assert_eq!(stats.rate_limited, 18);
+
+        // 进入下一秒后配额恢复
+        clock.advance(std::time::Duration::from_secs(1));
+        scan(21..=30);
+        let stats = r2_kv.get_promotion_stats();
+        assert_eq!(stats.promoted, 4);
+        assert_eq!(stats.rate_limited, 26);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_background_demotion_keeps_working_set_hot() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        // 热存储预算约为64条记录，目标利用率为一半
+        let record_size = std::mem::size_of::<u64>() + std::mem::size_of::<TestData>();
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 64 * record_size,
+            target_hot_utilization: 0.5,
+            migration_batch_size: 16,
+            ..Default::default()
+        };
+        let r2_kv = Arc::new(
+            R2Kv::<u64, TestData>::new_with_config(
+                &hot_dir,
+                &cold_dir,
+                migration_config,
+                AnalyzerConfig::default(),
+            )
+            .expect("Failed to create R2Kv instance"),
+        );
+
+        let working_set = 1..=16u64;
+        let long_tail = 17..=200u64;
+
+        for i in long_tail.clone() {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i * 10),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        for i in working_set.clone() {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i * 10),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+
+        let worker = r2_kv.start_background_migration(std::time::Duration::from_millis(5));
+
+        // 持续访问工作集，同时后台任务降级长尾数据
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while std::time::Instant::now() < deadline {
+            for i in working_set.clone() {
+                let mut read_ctx = TestReadContext {
+                    key: i,
+                    value: None,
+                };
+                assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+                assert_eq!(read_ctx.value.unwrap().value, i * 10);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        worker.stop();
+
+        let demotion_stats = r2_kv.get_demotion_stats();
+        assert!(demotion_stats.demoted >= 150, "{:?}", demotion_stats);
+        assert!(demotion_stats.pages_touched > 0);
+
+        let migration_stats = r2_kv.get_migration_stats();
+        assert!(migration_stats.current_hot_size <= 32 * record_size);
+
+        // 工作集仍在热存储中
+        for i in working_set {
+            let mut read_ctx = TestReadContext {
+                key: i,
+                value: None,
+            };
+            assert_eq!(r2_kv.hot_store.read(&mut read_ctx), Status::Ok);
+        }
+
+        // 长尾数据已降级到冷存储，但仍然可以正确读取
+        let mut demoted_count = 0;
+        for i in long_tail {
+            let mut hot_ctx = TestReadContext {
+                key: i,
+                value: None,
+            };
+            if r2_kv.hot_store.read(&mut hot_ctx) == Status::NotFound {
+                demoted_count += 1;
+                let mut cold_ctx = TestReadContext {
+                    key: i,
+                    value: None,
+                };
+                assert_eq!(r2_kv.cold_store.read(&mut cold_ctx), Status::Ok);
+                assert_eq!(cold_ctx.value.unwrap().value, i * 10);
+            }
+        }
+        assert!(demoted_count >= 150, "demoted {}", demoted_count);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_in_place_upserts_survive_concurrent_demotion() {
+        // 原地更新值的Upsert上下文
+        struct InPlaceUpsertContext {
+            key: u64,
+            value: TestData,
+        }
+
+        impl UpsertContext for InPlaceUpsertContext {
+            type Key = u64;
+            type Value = TestData;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn value(&self) -> &Self::Value {
+                &self.value
+            }
+
+            fn key_hash(&self) -> u64 {
+                self.key
+            }
+
+            fn put_atomic(&self, value: &mut Self::Value) -> bool {
+                *value = self.value;
+                true
+            }
+        }
+
+        let (hot_dir, cold_dir) = create_test_dirs();
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let r2_kv = Arc::new(
+            R2Kv::<u64, TestData>::new_with_config(
+                &hot_dir,
+                &cold_dir,
+                migration_config,
+                AnalyzerConfig::default(),
+            )
+            .expect("Failed to create R2Kv instance"),
+        );
+
+        // 降级线程不断冻结并迁移热数据，写线程同时原地更新各自的键
+        const KEYS: u64 = 32;
+        const ROUNDS: u64 = 300;
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let demoter = {
+            let r2_kv = Arc::clone(&r2_kv);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                while !done.load(std::sync::atomic::Ordering::Acquire) {
+                    r2_kv.run_demotion_pass();
+                }
+            })
+        };
+        let writers: Vec<_> = (0..4u64)
+            .map(|writer| {
+                let r2_kv = Arc::clone(&r2_kv);
+                thread::spawn(move || {
+                    for round in 1..=ROUNDS {
+                        for key in (writer..KEYS).step_by(4) {
+                            let upsert_ctx = InPlaceUpsertContext {
+                                key,
+                                value: TestData::new(key, round),
+                            };
+                            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, std::sync::atomic::Ordering::Release);
+        demoter.join().unwrap();
+        // 收尾的一轮把剩余的键全部降级，读取走冷存储
+        r2_kv.run_demotion_pass();
+
+        // 每个键的最后一次写入都不能被降级覆盖
+        for key in 0..KEYS {
+            let mut read_ctx = TestReadContext { key, value: None };
+            assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+            assert_eq!(read_ctx.value.unwrap().value, ROUNDS, "key {}", key);
+        }
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_rmw_after_demotion() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 0,
+            ..Default::default()
+        };
+        let r2_kv = R2Kv::<u64, TestData>::new_with_config(
+            &hot_dir,
+            &cold_dir,
+            migration_config,
+            AnalyzerConfig::default(),
+        )
+        .expect("Failed to create R2Kv instance");
+
+        let upsert_ctx = TestUpsertContext {
+            key: 3,
+            value: TestData::new(3, 100),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        assert_eq!(r2_kv.run_demotion_pass(), 1);
+
+        // RMW必须基于冷存储中的旧值
+        let mut rmw_ctx = TestRmwContext {
+            key: 3,
+            increment: 5,
+        };
+        assert_eq!(r2_kv.rmw(&mut rmw_ctx), Status::Ok);
+
+        let mut read_ctx = TestReadContext {
+            key: 3,
+            value: None,
+        };
+        assert_eq!(r2_kv.hot_store.read(&mut read_ctx), Status::Ok);
+        assert_eq!(read_ctx.value.unwrap().value, 105);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_checkpoint_kill_and_recover() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let mut r2_kv = R2Kv::<u64, TestData>::new_with_config(
+            &hot_dir,
+            &cold_dir,
+            migration_config,
+            AnalyzerConfig::default(),
+        )
+        .expect("Failed to create R2Kv instance");
+
+        // 键1..=5先降级到冷存储
+        for i in 1..=5 {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i * 10),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        assert_eq!(r2_kv.run_demotion_pass(), 5);
+
+        // 键1在热存储中有更新的版本，两个层级都持有它
+        let upsert_ctx = TestUpsertContext {
+            key: 1,
+            value: TestData::new(1, 111),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        for i in 6..=8 {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i * 10),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+
+        // 冷读命中，留下一个尚未完成的提升
+        let mut read_ctx = TestReadContext {
+            key: 2,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        assert_eq!(r2_kv.get_promotion_stats().pending, 1);
+
+        r2_kv.checkpoint("cut").expect("checkpoint failed");
+
+        // 检查点之后的写入在崩溃后丢失
+        let upsert_ctx = TestUpsertContext {
+            key: 3,
+            value: TestData::new(3, 999),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        drop(r2_kv);
+
+        let recovered = R2Kv::<u64, TestData>::recover(&hot_dir, &cold_dir, "cut")
+            .expect("Failed to recover R2Kv instance");
+        assert_eq!(recovered.get_promotion_stats().pending, 0);
+        assert_eq!(recovered.tier_stats().cold_records, 5);
+
+        // 被丢弃的提升由冷存储提供
+        let mut read_ctx = TestReadContext {
+            key: 2,
+            value: None,
+        };
+        assert_eq!(recovered.hot_store.read(&mut read_ctx), Status::NotFound);
+
+        let expected = [(1, 111), (2, 20), (3, 30), (4, 40), (5, 50), (6, 60), (7, 70), (8, 80)];
+        for (key, value) in expected {
+            let mut read_ctx = TestReadContext { key, value: None };
+            assert_eq!(recovered.read(&mut read_ctx), Status::Ok);
+            assert_eq!(read_ctx.value.unwrap().value, value);
+        }
+        drop(recovered);
+
+        // 恢复不会改写两层的日志，同一检查点可以再次恢复
+        let recovered = R2Kv::<u64, TestData>::recover(&hot_dir, &cold_dir, "cut")
+            .expect("Failed to recover R2Kv instance again");
+        for (key, value) in expected {
+            let mut read_ctx = TestReadContext { key, value: None };
+            assert_eq!(recovered.read(&mut read_ctx), Status::Ok);
+            assert_eq!(read_ctx.value.unwrap().value, value);
+        }
+        drop(recovered);
+
+        assert!(R2Kv::<u64, TestData>::recover(&hot_dir, &cold_dir, "missing").is_err());
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_recover_rejects_tier_checkpoint_from_after_the_cut() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let mut r2_kv = R2Kv::<u64, TestData>::new(&hot_dir, &cold_dir)
+            .expect("Failed to create R2Kv instance");
+        let upsert_ctx = TestUpsertContext {
+            key: 1,
+            value: TestData::new(1, 10),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        r2_kv.checkpoint("cut").expect("checkpoint failed");
+
+        // 只重做热层的同名检查点，两层不再是同一个切面
+        let upsert_ctx = TestUpsertContext {
+            key: 2,
+            value: TestData::new(2, 20),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        r2_kv.hot_store.checkpoint("cut").expect("tier checkpoint failed");
+        drop(r2_kv);
+
+        assert_eq!(
+            R2Kv::<u64, TestData>::recover(&hot_dir, &cold_dir, "cut").err(),
+            Some(Status::Corruption)
+        );
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_scan_prefix_across_tiers() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let r2_kv = R2Kv::<[u8; 8], TestData>::new_with_config(
+            &hot_dir,
+            &cold_dir,
+            migration_config,
+            AnalyzerConfig::default(),
+        )
+        .expect("Failed to create R2Kv instance");
+
+        for (i, key) in [b"user:001", b"user:002", b"item:001"].into_iter().enumerate() {
+            let upsert_ctx = ByteKeyUpsertContext {
+                key: *key,
+                value: TestData::new(i as u64, i as u64),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        assert_eq!(r2_kv.run_demotion_pass(), 3);
+
+        // user:003只在热存储中
+        let upsert_ctx = ByteKeyUpsertContext {
+            key: *b"user:003",
+            value: TestData::new(3, 3),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+
+        let users: Vec<[u8; 8]> = r2_kv
+            .scan_prefix(b"user:")
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(users, vec![*b"user:001", *b"user:002", *b"user:003"]);
+        assert_eq!(r2_kv.scan_all().len(), 4);
+        assert!(r2_kv.scan_prefix(b"order:").is_empty());
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_scan_filter_checks_live_records_only() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let r2_kv = R2Kv::<[u8; 8], TestData>::new_with_config(
+            &hot_dir,
+            &cold_dir,
+            migration_config,
+            AnalyzerConfig::default(),
+        )
+        .expect("Failed to create R2Kv instance");
+
+        // 40 records in the cold tier, 10 in the hot tier, one deleted in each
+        for i in 0..50 {
+            if i == 40 {
+                assert_eq!(r2_kv.run_demotion_pass(), 40);
+            }
+            let upsert_ctx = ByteKeyUpsertContext {
+                key: user(i),
+                value: TestData::new(i, i),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        for i in [2, 41] {
+            assert_eq!(
+                r2_kv.delete(&ByteKeyDeleteContext { key: user(i) }),
+                Status::Ok
+            );
+        }
+
+        let calls = std::cell::Cell::new(0);
+        let ends_in_two = |key: &[u8], value: &[u8]| {
+            calls.set(calls.get() + 1);
+            assert_eq!(key.len(), 8);
+            let value = unsafe { std::ptr::read_unaligned(value.as_ptr() as *const TestData) };
+            value.value % 10 == 2
+        };
+        let matches = r2_kv.scan_filter(b"user:", ends_in_two, None);
+        let keys: Vec<[u8; 8]> = matches.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![user(12), user(22), user(32), user(42)]);
+        assert!(matches.iter().all(|(key, value)| user(value.id) == *key));
+        // Deleted keys are rejected by their headers
+        assert_eq!(calls.get(), 48);
+
+        let limited = r2_kv.scan_filter(b"user:", ends_in_two, Some(2));
+        assert_eq!(limited, matches[..2].to_vec());
+        assert!(r2_kv.scan_filter(b"item:", |_, _| true, None).is_empty());
+        assert_eq!(r2_kv.scan_filter(&[], |_, _| true, Some(0)), vec![]);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_scan_range_orders_keys_across_tiers() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let r2_kv = R2Kv::<[u8; 8], TestData>::new_with_config(
+            &hot_dir,
+            &cold_dir,
+            migration_config,
+            AnalyzerConfig::default(),
+        )
+        .expect("Failed to create R2Kv instance");
+
+        // Written out of order: 40 records in the cold tier, 10 in the hot
+        // tier, and one deleted
+        for i in (0..50).rev() {
+            if i == 9 {
+                assert_eq!(r2_kv.run_demotion_pass(), 40);
+            }
+            let upsert_ctx = ByteKeyUpsertContext {
+                key: user(i),
+                value: TestData::new(i, i),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        assert_eq!(
+            r2_kv.delete(&ByteKeyDeleteContext { key: user(7) }),
+            Status::Ok
+        );
+
+        let keys = |results: Vec<([u8; 8], TestData)>| -> Vec<[u8; 8]> {
+            assert!(results.iter().all(|(key, value)| user(value.id) == *key));
+            results.into_iter().map(|(key, _)| key).collect()
+        };
+        let users = |ids: &[u64]| -> Vec<[u8; 8]> { ids.iter().map(|&i| user(i)).collect() };
+        let (from, to) = (user(5), user(12));
+
+        // The end is left out unless the range includes it
+        assert_eq!(
+            keys(r2_kv.scan_range(from.as_slice()..to.as_slice())),
+            users(&[5, 6, 8, 9, 10, 11])
+        );
+        assert_eq!(
+            keys(r2_kv.scan_range(from.as_slice()..=to.as_slice())),
+            users(&[5, 6, 8, 9, 10, 11, 12])
+        );
+        assert_eq!(
+            keys(r2_kv.scan_range(..from.as_slice())),
+            users(&[0, 1, 2, 3, 4])
+        );
+        assert_eq!(
+            keys(r2_kv.scan_range(b"user:048".as_slice()..)),
+            users(&[48, 49])
+        );
+        assert_eq!(
+            keys(r2_kv.scan_range(..)),
+            (0..50).filter(|&i| i != 7).map(user).collect::<Vec<_>>()
+        );
+
+        // Empty and inverted ranges
+        assert!(
+            r2_kv
+                .scan_range(from.as_slice()..from.as_slice())
+                .is_empty()
+        );
+        assert!(r2_kv.scan_range(to.as_slice()..from.as_slice()).is_empty());
+        assert!(
+            r2_kv
+                .scan_range(b"item:".as_slice()..b"item;".as_slice())
+                .is_empty()
+        );
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_scan_iter_reads_in_batches() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let r2_kv = R2Kv::<u64, TestData>::new_with_config(
+            &hot_dir,
+            &cold_dir,
+            migration_config,
+            AnalyzerConfig::default(),
+        )
+        .expect("Failed to create R2Kv instance");
+
+        // 400 records in the cold tier and 200 in the hot tier, one of each
+        // deleted
+        for i in 0..600 {
+            if i == 400 {
+                assert_eq!(r2_kv.run_demotion_pass(), 400);
+            }
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        for key in [3, 450] {
+            assert_eq!(r2_kv.delete(&TestDeleteContext { key }), Status::Ok);
+        }
+
+        // The first record reads the first batch of keys only
+        let mut scan = r2_kv.scan_iter();
+        assert_eq!(scan.next().map(|(key, _)| key), Some(0));
+        assert_eq!(scan.size_hint(), (SCAN_BATCH - 1, Some(597)));
+
+        // Later batches see writes made since the scan began
+        assert_eq!(r2_kv.delete(&TestDeleteContext { key: 520 }), Status::Ok);
+        let upsert_ctx = TestUpsertContext {
+            key: 580,
+            value: TestData::new(580, 5800),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        let rest: Vec<(u64, TestData)> = scan.collect();
+        let keys: Vec<u64> = rest.iter().map(|(key, _)| *key).collect();
+        let expected: Vec<u64> = (1..600)
+            .filter(|key| ![3, 450, 520].contains(key))
+            .collect();
+        assert_eq!(keys, expected);
+        assert!(rest.iter().all(|(key, value)| value.id == *key));
+        assert_eq!(rest.last().unwrap().1.value, 599);
+        assert!(rest.contains(&(580, upsert_ctx.value)));
+
+        // Little-endian keys whose low byte is 1
+        let prefixed: Vec<(u64, TestData)> = r2_kv.scan_prefix_iter(&[1]).collect();
+        assert_eq!(prefixed, r2_kv.scan_prefix(&[1]));
+        assert_eq!(
+            prefixed.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            vec![1, 257, 513]
+        );
+        assert_eq!(r2_kv.scan_iter().count(), r2_kv.scan_all().len());
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_scan_during_migration() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 1 << 20,
+            target_hot_utilization: 0.0,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let r2_kv = Arc::new(
+            R2Kv::<u64, TestData>::new_with_config(
+                &hot_dir,
+                &cold_dir,
+                migration_config,
+                AnalyzerConfig::default(),
+            )
+            .expect("Failed to create R2Kv instance"),
+        );
+
+        for i in 1..=50 {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        assert_eq!(r2_kv.run_demotion_pass(), 50);
+
+        // 26..=50在热存储中有更新的版本，冷存储中的旧版本必须被遮蔽
+        for i in 26..=50 {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i + 1000),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+
+        // 删除的键不能从冷存储中重新出现
+        for key in [10, 30] {
+            assert_eq!(r2_kv.delete(&TestDeleteContext { key }), Status::Ok);
+        }
+
+        // 冷读命中留下一个排队中的提升
+        let mut read_ctx = TestReadContext {
+            key: 5,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+
+        let expected = |key: u64| if key > 25 { key + 1000 } else { key };
+        let check_scan = |r2_kv: &R2Kv<'static, u64, TestData>| {
+            let results = r2_kv.scan_all();
+            assert_eq!(results.len(), 48);
+            let mut seen = std::collections::HashSet::new();
+            for (key, value) in results {
+                assert!(seen.insert(key), "key {} returned twice", key);
+                assert!(key != 10 && key != 30);
+                assert_eq!(value.value, expected(key));
+            }
+        };
+        check_scan(&r2_kv);
+
+        // 后台线程反复迁移热数据，同时进行扫描
+        let migrator = {
+            let r2_kv = Arc::clone(&r2_kv);
+            thread::spawn(move || {
+                for _ in 0..20 {
+                    for i in 26..=50 {
+                        if i == 30 {
+                            continue;
+                        }
+                        let upsert_ctx = TestUpsertContext {
+                            key: i,
+                            value: TestData::new(i, i + 1000),
+                        };
+                        r2_kv.upsert(&upsert_ctx);
+                    }
+                    r2_kv.run_demotion_pass();
+                }
+            })
+        };
+        for _ in 0..20 {
+            check_scan(&r2_kv);
+        }
+        migrator.join().unwrap();
+        check_scan(&r2_kv);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_per_tier_storage_config() {
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        // 两个层级不能共享同一个目录
+        let same_path = R2Config::new(&hot_dir, &format!("{}/", hot_dir));
+        assert_eq!(
+            R2Kv::<u64, TestData>::with_config(same_path).err(),
+            Some(Status::InvalidConfiguration)
+        );
+
+        let config = R2Config {
+            hot: TierStorageConfig {
+                log_size: 1 << 26,
+                table_size: 1 << 10,
+                ..TierStorageConfig::hot(&hot_dir)
+            },
+            cold: TierStorageConfig {
+                log_size: 1 << 27,
+                table_size: 1 << 12,
+                ..TierStorageConfig::cold(&cold_dir)
+            },
+            ..R2Config::new(&hot_dir, &cold_dir)
+        };
+        let r2_kv = R2Kv::<u64, TestData>::with_config(config)
+            .expect("Failed to create R2Kv instance");
+        assert_eq!(r2_kv.hot_store.get_table_size(), 1 << 10);
+        assert_eq!(r2_kv.cold_store.get_table_size(), 1 << 12);
+        assert_eq!(r2_kv.hot_store.disk.root_path(), hot_dir);
+        assert_eq!(r2_kv.cold_store.disk.root_path(), cold_dir);
+
+        let upsert_ctx = TestUpsertContext {
+            key: 1,
+            value: TestData::new(1, 100),
+        };
+        assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
+
+    #[test]
+    fn test_r2_tier_stats_under_skewed_workload() {
+        fn assert_serialize<T: serde::Serialize>(_: &T) {}
+
+        let (hot_dir, cold_dir) = create_test_dirs();
+
+        let record_size = std::mem::size_of::<u64>() + std::mem::size_of::<TestData>();
+        let migration_config = MigrationConfig {
+            max_hot_size_bytes: 20 * record_size,
+            target_hot_utilization: 0.5,
+            migration_batch_size: 1000,
+            ..Default::default()
+        };
+        let config = R2Config {
+            migration: migration_config,
+            ..R2Config::new(&hot_dir, &cold_dir)
+        };
+        let clock = Arc::new(MockClock::new());
+        let r2_kv = R2Kv::<u64, TestData>::with_clock(config, clock.clone())
+            .expect("Failed to create R2Kv instance");
+
+        for i in 1..=100 {
+            let upsert_ctx = TestUpsertContext {
+                key: i,
+                value: TestData::new(i, i),
+            };
+            assert_eq!(r2_kv.upsert(&upsert_ctx), Status::Ok);
+        }
+        // 热键最近被访问过，不会被降级
+        clock.advance(std::time::Duration::from_millis(5));
+        for key in 91..=100 {
+            let mut read_ctx = TestReadContext { key, value: None };
+            assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        }
+        let demoted = r2_kv.run_demotion_pass() as u64;
+        assert_eq!(demoted, 90);
+
+        // 倾斜负载：大部分读取集中在少数热键上，少量读取落在冷键上
+        for round in 0..10u64 {
+            for key in 91..=100 {
+                let mut read_ctx = TestReadContext { key, value: None };
+                assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+            }
+            let cold_key = round + 1;
+            let mut read_ctx = TestReadContext {
+                key: cold_key,
+                value: None,
+            };
+            assert_eq!(r2_kv.read(&mut read_ctx), Status::Ok);
+        }
+        let mut read_ctx = TestReadContext {
+            key: 1000,
+            value: None,
+        };
+        assert_eq!(r2_kv.read(&mut read_ctx), Status::NotFound);
+        r2_kv.complete_pending_promotions();
+
+        let stats = r2_kv.tier_stats();
+        assert_serialize(&stats);
+        assert_eq!(stats.hot_hits, 110);
+        assert_eq!(stats.cold_hits, 10);
+        assert_eq!(stats.misses, 1);
+        assert!(stats.hot_hit_ratio > stats.cold_hit_ratio);
+        assert_eq!(stats.demotions, demoted);
+        assert_eq!(stats.promotions, 10);
+        assert_eq!(stats.interval_promotions, 10);
+        assert_eq!(stats.interval_demotions, demoted);
+        assert_eq!(stats.bytes_demoted, demoted * record_size as u64);
+        assert_eq!(stats.bytes_promoted, 10 * record_size as u64);
+        assert_eq!(stats.cold_records, demoted);
+        assert_eq!(stats.hot_records, 100 - demoted + 10);
+        assert_eq!(stats.hot_bytes, stats.hot_records * record_size as u64);
+        assert_eq!(stats.interval_secs, 0.005);
+
+        // 第二次调用时区间计数器重新开始
+        clock.advance(std::time::Duration::from_secs(2));
+        let stats = r2_kv.tier_stats();
+        assert_eq!(stats.interval_secs, 2.0);
+        assert_eq!(stats.interval_promotions, 0);
+        assert_eq!(stats.interval_demotions, 0);
+        assert_eq!(stats.promotions, 10);
+
+        cleanup_test_dirs(&hot_dir, &cold_dir);
+    }
 }
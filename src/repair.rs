@@ -0,0 +1,437 @@
+//! Salvaging records from a store whose checkpoints or metadata are damaged.
+
+use crate::core::record::{Record, RecordInfo};
+use crate::core::status::Status;
+use crate::core::utility::crc32_update;
+use crate::device::file_system_disk::FileSystemDisk;
+use crate::environment::file::DirectoryLock;
+use crate::hlog::persistent_memory_malloc::{FLUSH_FRAME_SIZE, FlushFrame, PersistentMemoryMalloc};
+use crate::rskv_core::RsKv;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+
+/// Token of the checkpoint [`scan_and_repair`] takes of the repaired store.
+pub const REPAIR_CHECKPOINT_TOKEN: &str = "repaired";
+
+/// Summary of a [`scan_and_repair`] run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Bytes read from the log and the frame log
+    pub bytes_scanned: u64,
+    /// Frames whose log data verified
+    pub frames_recovered: u64,
+    /// Frames that decoded but whose log data is missing or fails its CRC
+    pub frames_skipped: u64,
+    /// Times the frame scan found a frame again after unreadable bytes
+    pub resync_events: u64,
+    /// Live records written to the repaired store
+    pub records_recovered: u64,
+}
+
+/// Calls `f` with the address, header, key and value of every written slot of the
+/// raw log `log` in `[begin, end)`, in log order. Records never straddle a
+/// page, so slots restart at each page boundary; `begin` must be a slot
+/// boundary, as the start of every flush is.
+pub(crate) fn for_each_record<K: Copy, V: Clone>(
+    log: &[u8],
+    begin: u64,
+    end: u64,
+    f: impl FnMut(u64, RecordInfo, K, V),
+) {
+    for_each_record_in(log, 0, begin, end, f)
+}
+
+/// [`for_each_record`] over a part of the log read into `log`, whose first
+/// byte is at address `base`.
+pub(crate) fn for_each_record_in<K: Copy, V: Clone>(
+    log: &[u8],
+    base: u64,
+    begin: u64,
+    end: u64,
+    f: impl FnMut(u64, RecordInfo, K, V),
+) {
+    walk_records(log, base, begin, end, std::mem::size_of::<RecordInfo>(), f)
+}
+
+/// [`for_each_record`] over a log of format version 1 or earlier, whose
+/// record headers have no write time.
+#[cfg(feature = "legacy-format")]
+pub(crate) fn for_each_legacy_record<K: Copy, V: Clone>(
+    log: &[u8],
+    begin: u64,
+    end: u64,
+    f: impl FnMut(u64, RecordInfo, K, V),
+) {
+    walk_records(log, 0, begin, end, RecordInfo::LEGACY_SIZE, f)
+}
+
+fn walk_records<K: Copy, V: Clone>(
+    log: &[u8],
+    base: u64,
+    begin: u64,
+    end: u64,
+    header_size: usize,
+    mut f: impl FnMut(u64, RecordInfo, K, V),
+) {
+    let page_size = PersistentMemoryMalloc::<FileSystemDisk>::K_PAGE_SIZE;
+    let record_size =
+        (Record::<K, V>::required_size_with_header(header_size) as u64).div_ceil(8) * 8;
+    let key_offset = header_size;
+    let value_offset = key_offset + std::mem::size_of::<K>();
+    let end = end.min(base + log.len() as u64);
+
+    let mut offset = begin
+        .max(base)
+        .max(PersistentMemoryMalloc::<FileSystemDisk>::K_FIRST_VALID_ADDRESS);
+    while offset < end {
+        let page_end = ((offset / page_size + 1) * page_size).min(end);
+        while offset + record_size <= page_end {
+            let address = offset;
+            let slot = &log[(offset - base) as usize..(offset - base + record_size) as usize];
+            offset += record_size;
+            let header = RecordInfo::from_bytes(&slot[..header_size]);
+            // Unwritten slots are zero; every record has its final bit set.
+            if header.control() == 0 || header.invalid() {
+                continue;
+            }
+            unsafe {
+                let key = std::ptr::read_unaligned(slot.as_ptr().add(key_offset) as *const K);
+                let value = std::mem::ManuallyDrop::new(std::ptr::read_unaligned(
+                    slot.as_ptr().add(value_offset) as *const V,
+                ));
+                f(address, header, key, V::clone(&value));
+            }
+        }
+        offset = (offset / page_size + 1) * page_size;
+    }
+}
+
+/// Bytes between consecutive records of a `K`/`V` log.
+pub(crate) fn record_slot_size<K, V>() -> u64 {
+    (Record::<K, V>::required_size_with_alignment() as u64).div_ceil(8) * 8
+}
+
+/// Groups records by their key bytes, for callers that have no key hash.
+pub(crate) fn key_bytes_hash<K>(key: &K) -> u64 {
+    let bytes = unsafe { std::slice::from_raw_parts(key as *const K as *const u8, size_of::<K>()) };
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Newest state of every key seen so far, in first-seen order.
+pub(crate) struct NewestRecords<K, V> {
+    /// Each key with its newest value and user flags, `None` once deleted
+    records: Vec<(K, Option<(V, u8)>)>,
+    by_hash: HashMap<u64, Vec<usize>>,
+}
+
+impl<K: PartialEq, V> NewestRecords<K, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            records: Vec::new(),
+            by_hash: HashMap::new(),
+        }
+    }
+
+    /// Records `key` as holding `value`, or as deleted for a tombstone.
+    pub(crate) fn insert(&mut self, key_hash: u64, header: RecordInfo, key: K, value: V) {
+        let value = (!header.tombstone()).then_some((value, header.user_flags()));
+        let slots = self.by_hash.entry(key_hash).or_default();
+        match slots.iter().find(|slot| self.records[**slot].0 == key) {
+            Some(slot) => self.records[*slot].1 = value,
+            None => {
+                slots.push(self.records.len());
+                self.records.push((key, value));
+            }
+        }
+    }
+
+    /// Splits into the live records, each with its user flags, and the
+    /// number of deleted keys.
+    pub(crate) fn into_live(self) -> (Vec<(K, V, u8)>, u64) {
+        let total = self.records.len() as u64;
+        let live: Vec<(K, V, u8)> = self
+            .records
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|(value, user_flags)| (key, value, user_flags)))
+            .collect();
+        let deleted = total - live.len() as u64;
+        (live, deleted)
+    }
+}
+
+/// The newest `keep` values of every key since it was last deleted, for
+/// compaction that preserves recent history.
+pub(crate) struct RecentVersions<K, V> {
+    keep: usize,
+    /// Each key with its kept values and their user flags, oldest first
+    records: Vec<(K, VecDeque<(V, u8)>)>,
+    by_hash: HashMap<u64, Vec<usize>>,
+}
+
+impl<K: Copy + PartialEq, V> RecentVersions<K, V> {
+    pub(crate) fn new(keep: usize) -> Self {
+        Self {
+            keep: keep.max(1),
+            records: Vec::new(),
+            by_hash: HashMap::new(),
+        }
+    }
+
+    /// Adds `value` as the newest version of `key`, or drops every version
+    /// for a tombstone.
+    pub(crate) fn insert(&mut self, key_hash: u64, header: RecordInfo, key: K, value: V) {
+        let slots = self.by_hash.entry(key_hash).or_default();
+        let slot = match slots.iter().find(|slot| self.records[**slot].0 == key) {
+            Some(slot) => *slot,
+            None => {
+                slots.push(self.records.len());
+                self.records.push((key, VecDeque::new()));
+                self.records.len() - 1
+            }
+        };
+        let versions = &mut self.records[slot].1;
+        if header.tombstone() {
+            versions.clear();
+            return;
+        }
+        if versions.len() == self.keep {
+            versions.pop_front();
+        }
+        versions.push_back((value, header.user_flags()));
+    }
+
+    /// Splits into the kept versions with their user flags, oldest first
+    /// within each key, and the number of deleted keys.
+    pub(crate) fn into_versions(self) -> (Vec<(K, V, u8)>, u64) {
+        let mut deleted = 0;
+        let mut versions = Vec::new();
+        for (key, values) in self.records {
+            if values.is_empty() {
+                deleted += 1;
+            }
+            versions.extend(
+                values
+                    .into_iter()
+                    .map(|(value, user_flags)| (key, value, user_flags)),
+            );
+        }
+        (versions, deleted)
+    }
+}
+
+/// Writes the newest valid record of every key found in the log of
+/// `storage_dir` into a new store in `output_dir`, and checkpoints it as
+/// [`REPAIR_CHECKPOINT_TOKEN`].
+///
+/// Checkpoints and the superblock are ignored. Every frame in the frame log
+/// is checked against the log data it covers, and only records in frames that
+/// verify are kept. Unreadable stretches of the frame log are skipped by
+/// scanning forward for the next frame that decodes. The source is only read.
+pub fn scan_and_repair<K, V>(
+    storage_dir: &str,
+    output_dir: &str,
+    key_hash: impl Fn(&K) -> u64,
+) -> Result<RepairReport, Status>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+{
+    let _lock = DirectoryLock::acquire(storage_dir, true)?;
+    let root = Path::new(storage_dir);
+    let log = fs::read(root.join("hlog.log")).map_err(|_| Status::IoError)?;
+    let frames = fs::read(root.join("hlog.frames")).map_err(|_| Status::IoError)?;
+    let mut report = RepairReport {
+        bytes_scanned: (log.len() + frames.len()) as u64,
+        ..Default::default()
+    };
+
+    let mut newest = NewestRecords::new();
+    let mut at = 0;
+    let mut lost_sync = false;
+    while at + FLUSH_FRAME_SIZE <= frames.len() {
+        let Some(frame) = FlushFrame::decode(&frames[at..at + FLUSH_FRAME_SIZE]) else {
+            lost_sync = true;
+            at += 1;
+            continue;
+        };
+        if lost_sync {
+            report.resync_events += 1;
+            lost_sync = false;
+        }
+        at += FLUSH_FRAME_SIZE;
+
+        let verified = frame.begin < frame.end
+            && frame.end <= log.len() as u64
+            && crc32_update(0, &log[frame.begin as usize..frame.end as usize]) == frame.data_crc;
+        if !verified {
+            report.frames_skipped += 1;
+            continue;
+        }
+        report.frames_recovered += 1;
+        for_each_record::<K, V>(&log, frame.begin, frame.end, |_, header, key, value| {
+            newest.insert(key_hash(&key), header, key, value)
+        });
+    }
+
+    let (live, _) = newest.into_live();
+    let table_size = (live.len() as u64).next_power_of_two().max(1 << 10);
+    let disk = FileSystemDisk::new(output_dir)?;
+    let mut kv = RsKv::<K, V, FileSystemDisk>::new(1 << 30, table_size, disk)?;
+    report.records_recovered = kv.bulk_load_with_flags(live, &key_hash)?;
+    kv.checkpoint(REPAIR_CHECKPOINT_TOKEN)?;
+
+    log::info!(
+        "repaired {} into {}: {} records from {} frames, {} frames skipped, {} resyncs",
+        storage_dir,
+        output_dir,
+        report.records_recovered,
+        report.frames_recovered,
+        report.frames_skipped,
+        report.resync_events
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rskv_core::{ReadContext, UpsertContext};
+    use crate::testing::temp_dir::TempDir;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    struct TestUpsertContext {
+        key: u64,
+        value: u64,
+    }
+
+    impl UpsertContext for TestUpsertContext {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn value(&self) -> &Self::Value {
+            &self.value
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+            false
+        }
+    }
+
+    struct TestReadContext {
+        key: u64,
+        value: Option<u64>,
+    }
+
+    impl ReadContext for TestReadContext {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn get(&mut self, value: &Self::Value) {
+            self.value = Some(*value);
+        }
+    }
+
+    #[test]
+    fn test_scan_and_repair_salvages_untouched_keys() {
+        const KEYS: u64 = 20_000;
+        const REGION: u64 = 4096;
+        let src_temp = TempDir::new("repair_src");
+        let src = src_temp.path();
+        let dst_temp = TempDir::new("repair_dst");
+        let dst = dst_temp.path();
+        {
+            let disk = FileSystemDisk::new(src).unwrap();
+            let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 14, disk).unwrap();
+            for key in 0..KEYS {
+                let context = TestUpsertContext {
+                    key,
+                    value: key * 7,
+                };
+                assert_eq!(kv.upsert(&context), Status::Ok);
+                if key % 16 == 15 {
+                    kv.hlog.flush(false).unwrap();
+                }
+            }
+            kv.hlog.flush(false).unwrap();
+        }
+
+        // Every key was written once, in order, in the first page.
+        let record_size =
+            (Record::<u64, u64>::required_size_with_alignment() as u64).div_ceil(8) * 8;
+        let address_of = |key: u64| 64 + key * record_size;
+
+        let log_path = format!("{}/hlog.log", src);
+        let mut log = fs::read(&log_path).unwrap();
+        let mut rng = StdRng::seed_from_u64(1919);
+        let mut regions = Vec::new();
+        for _ in 0..5 {
+            let start = rng.random_range(64..log.len() as u64 - REGION);
+            for byte in &mut log[start as usize..(start + REGION) as usize] {
+                *byte = rng.random();
+            }
+            regions.push(start..start + REGION);
+        }
+        fs::write(&log_path, &log).unwrap();
+
+        // Garbage in the middle of the frame log forces a resync.
+        let frames_path = format!("{}/hlog.frames", src);
+        let mut frames = fs::read(&frames_path).unwrap();
+        let middle = frames.len() / 2 + 5;
+        frames[middle..middle + 10].fill(0xee);
+        fs::write(&frames_path, &frames).unwrap();
+
+        let report = scan_and_repair::<u64, u64>(src, dst, |key| *key).unwrap();
+        assert!(report.resync_events >= 1);
+        assert!(report.frames_skipped >= 5);
+        assert!(report.frames_recovered > 0);
+        assert_eq!(report.bytes_scanned, (log.len() + frames.len()) as u64);
+
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dst, REPAIR_CHECKPOINT_TOKEN).unwrap();
+        let mut untouched = 0;
+        let mut recovered = 0;
+        for key in 0..KEYS {
+            let mut context = TestReadContext { key, value: None };
+            kv.read(&mut context);
+            if let Some(value) = context.value {
+                assert_eq!(value, key * 7);
+            }
+            let record = address_of(key)..address_of(key) + record_size;
+            if regions
+                .iter()
+                .all(|region| record.end <= region.start || region.end <= record.start)
+            {
+                untouched += 1;
+                recovered += context.value.is_some() as u64;
+            }
+        }
+        assert!(report.records_recovered >= recovered);
+        assert!(
+            recovered * 100 >= untouched * 95,
+            "recovered {} of {} untouched keys",
+            recovered,
+            untouched
+        );
+    }
+}
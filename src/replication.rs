@@ -0,0 +1,757 @@
+//! Log shipping for primary/replica setups.
+//!
+//! A [`LogSubscriber`] attached with
+//! [`RsKv::attach_log_subscriber`](crate::rskv_core::RsKv::attach_log_subscriber)
+//! is handed every record once it is durable, in address order and in
+//! batches. Records are read back from the log file by a shipping thread, so
+//! the only queue between writers and the subscriber is the durable log
+//! itself: a slow or failing subscriber makes shipping lag behind, never
+//! writes. The lag is reported by [`ShippingStats`].
+//! Records are shipped as they stand in the durable log, so a record
+//! updated or deleted in place before its flush is shipped once, in its
+//! final state.
+//!
+//! Delivery is at least once. A batch is retried until the subscriber
+//! accepts it, and the address shipping has been acknowledged up to is saved
+//! with every checkpoint. After a restart, shipping resumes from
+//! [`RsKv::shipping_cursor`](crate::rskv_core::RsKv::shipping_cursor), so
+//! records acknowledged after the checkpoint are delivered again.
+//!
+//! [`FileSubscriber`] appends shipped records to a file, and
+//! [`ReplicaApplier`] applies them to another store.
+
+use crate::core::clock::system_time_from_ms;
+use crate::core::status::Status;
+use crate::device::file_system_disk::FileSystemDisk;
+use crate::environment::file::{File, FileCreateDisposition};
+use crate::hlog::persistent_memory_malloc::{Disk, PersistentMemoryMalloc};
+use crate::repair::{for_each_record_in, record_slot_size};
+use crate::rskv_core::{DeleteContext, RsKv};
+use std::fmt::Debug;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Name of the file in a checkpoint directory holding the shipping cursor.
+pub(crate) const SHIPPING_CURSOR_FILE: &str = "shipping.cursor";
+
+/// What a shipped record does to its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShippedOp {
+    Upsert,
+    Delete,
+}
+
+/// One durable log record, as handed to a [`LogSubscriber`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShippedRecord<K, V> {
+    /// Log address of the record
+    pub address: u64,
+    pub op: ShippedOp,
+    pub key: K,
+    /// The value written, or `None` for a delete
+    pub value: Option<V>,
+    /// Write time of the record, `None` for records written without one
+    pub timestamp: Option<SystemTime>,
+    /// [`WriteOptions::user_flags`](crate::rskv_core::WriteOptions::user_flags)
+    /// of the write that made the record
+    pub user_flags: u8,
+}
+
+/// Receives durable records from a store.
+pub trait LogSubscriber<K, V>: Send + Sync {
+    /// Handles the next batch of records, in address order. An error leaves
+    /// the batch unacknowledged; it is offered again after
+    /// [`ShippingOptions::retry_delay`], so a subscriber may see a record
+    /// more than once.
+    fn on_records(&self, batch: &[ShippedRecord<K, V>]) -> Result<(), Status>;
+}
+
+/// Options for [`RsKv::attach_log_subscriber`](crate::rskv_core::RsKv::attach_log_subscriber).
+#[derive(Debug, Clone)]
+pub struct ShippingOptions {
+    /// Most records passed to one `on_records` call
+    pub max_batch_records: usize,
+    /// Wait before offering a rejected batch again
+    pub retry_delay: Duration,
+    /// Address to ship from: 0 for the whole log, or a cursor saved by a
+    /// checkpoint to resume after a restart
+    pub start_address: u64,
+}
+
+impl Default for ShippingOptions {
+    fn default() -> Self {
+        Self {
+            max_batch_records: 1024,
+            retry_delay: Duration::from_millis(100),
+            start_address: 0,
+        }
+    }
+}
+
+/// Progress of log shipping.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShippingStats {
+    /// Address the log is durable up to
+    pub durable_until: u64,
+    /// Address the subscriber has acknowledged every record up to
+    pub acked_until: u64,
+    /// Batches the subscriber accepted
+    pub batches_shipped: u64,
+    /// Records the subscriber accepted
+    pub records_shipped: u64,
+    /// `on_records` calls that returned an error
+    pub failed_attempts: u64,
+}
+
+impl ShippingStats {
+    /// Durable log bytes the subscriber has not acknowledged yet.
+    pub fn lag_bytes(&self) -> u64 {
+        self.durable_until.saturating_sub(self.acked_until)
+    }
+}
+
+struct ShipperState {
+    stats: ShippingStats,
+    stop: bool,
+}
+
+struct Shared {
+    state: Mutex<ShipperState>,
+    /// Signalled when the durable address, the acknowledged address or the
+    /// stop flag changes
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, ShipperState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Background thread shipping one store's durable log to a subscriber.
+pub(crate) struct LogShipper {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LogShipper {
+    /// Starts shipping the log file at `log_path`, which is durable up to
+    /// `durable_until`.
+    pub(crate) fn start<K, V>(
+        log_path: String,
+        durable_until: u64,
+        subscriber: Arc<dyn LogSubscriber<K, V>>,
+        options: ShippingOptions,
+    ) -> Self
+    where
+        K: Copy + 'static,
+        V: Clone + 'static,
+    {
+        let first_address = PersistentMemoryMalloc::<FileSystemDisk>::K_FIRST_VALID_ADDRESS;
+        let shared = Arc::new(Shared {
+            state: Mutex::new(ShipperState {
+                stats: ShippingStats {
+                    durable_until,
+                    acked_until: options.start_address.max(first_address),
+                    ..Default::default()
+                },
+                stop: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let thread = {
+            let shared = shared.clone();
+            std::thread::spawn(move || ship::<K, V>(&shared, &log_path, &*subscriber, &options))
+        };
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Records that the log is durable up to `address`.
+    pub(crate) fn notify_durable(&self, address: u64) {
+        let mut state = self.shared.lock();
+        if address > state.stats.durable_until {
+            state.stats.durable_until = address;
+            self.shared.changed.notify_all();
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ShippingStats {
+        self.shared.lock().stats
+    }
+
+    /// Waits until every durable record is acknowledged. Returns false if
+    /// `timeout` passes first.
+    pub(crate) fn wait_caught_up(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+        while state.stats.lag_bytes() > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self
+                .shared
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        true
+    }
+}
+
+impl Drop for LogShipper {
+    fn drop(&mut self) {
+        self.shared.lock().stop = true;
+        self.shared.changed.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Shipping loop: reads the next unacknowledged part of the durable log, at
+/// most one page and one batch at a time, and offers its records to
+/// `subscriber` until they are accepted.
+fn ship<K: Copy, V: Clone>(
+    shared: &Shared,
+    log_path: &str,
+    subscriber: &dyn LogSubscriber<K, V>,
+    options: &ShippingOptions,
+) {
+    let page_size = PersistentMemoryMalloc::<FileSystemDisk>::K_PAGE_SIZE;
+    let record_size = record_slot_size::<K, V>();
+    let batch_bytes = options.max_batch_records.max(1) as u64 * record_size;
+    let mut log: Option<File> = None;
+    let mut buffer = Vec::new();
+
+    loop {
+        let (from, until) = {
+            let mut state = shared.lock();
+            while !state.stop && state.stats.lag_bytes() == 0 {
+                state = shared
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            if state.stop {
+                return;
+            }
+            (state.stats.acked_until, state.stats.durable_until)
+        };
+        let page_end = (from / page_size + 1) * page_size;
+        let end = until.min(page_end).min(from + batch_bytes);
+
+        buffer.resize((end - from) as usize, 0);
+        let read = match log.as_mut() {
+            Some(file) => file.read(from, &mut buffer),
+            None => {
+                let mut file = File::new(log_path);
+                file.open(FileCreateDisposition::OpenExisting, Default::default())
+                    .and_then(|()| file.read(from, &mut buffer))
+                    .map(|()| log = Some(file))
+            }
+        };
+        let mut batch = Vec::new();
+        let accepted = read.and_then(|()| {
+            for_each_record_in::<K, V>(&buffer, from, from, end, |address, header, key, value| {
+                let (op, value) = if header.tombstone() {
+                    (ShippedOp::Delete, None)
+                } else {
+                    (ShippedOp::Upsert, Some(value))
+                };
+                batch.push(ShippedRecord {
+                    address,
+                    op,
+                    key,
+                    value,
+                    timestamp: header.timestamp_ms().map(system_time_from_ms),
+                    user_flags: header.user_flags(),
+                });
+            });
+            if batch.is_empty() {
+                Ok(())
+            } else {
+                subscriber.on_records(&batch)
+            }
+        });
+
+        let mut state = shared.lock();
+        match accepted {
+            Ok(()) => {
+                state.stats.acked_until = end;
+                if !batch.is_empty() {
+                    state.stats.batches_shipped += 1;
+                    state.stats.records_shipped += batch.len() as u64;
+                }
+                shared.changed.notify_all();
+            }
+            Err(status) => {
+                log::warn!("log shipping batch at {} failed: {:?}", from, status);
+                state.stats.failed_attempts += 1;
+                let deadline = Instant::now() + options.retry_delay;
+                while !state.stop {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    state = shared
+                        .changed
+                        .wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0;
+                }
+            }
+        }
+    }
+}
+
+/// Reference subscriber appending every shipped record to a file, one per
+/// line, as `address<TAB>put|delete<TAB>key<TAB>value` with keys and values
+/// in their `Debug` form, the same lines as a history
+/// [`dump`](crate::admin::dump). Each batch is synced before it is
+/// acknowledged.
+pub struct FileSubscriber<K, V> {
+    file: Mutex<std::fs::File>,
+    _records: PhantomData<fn(&K, &V)>,
+}
+
+impl<K, V> FileSubscriber<K, V> {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &str) -> Result<Self, Status> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            _records: PhantomData,
+        })
+    }
+}
+
+impl<K: Debug, V: Debug> LogSubscriber<K, V> for FileSubscriber<K, V> {
+    fn on_records(&self, batch: &[ShippedRecord<K, V>]) -> Result<(), Status> {
+        let mut lines = Vec::new();
+        for record in batch {
+            let written = match &record.value {
+                Some(value) => writeln!(
+                    lines,
+                    "{}\tput\t{:?}\t{:?}",
+                    record.address, record.key, value
+                ),
+                None => writeln!(lines, "{}\tdelete\t{:?}\t-", record.address, record.key),
+            };
+            written.map_err(|_| Status::IoError)?;
+        }
+        let mut file = self.file.lock().map_err(|_| Status::InternalError)?;
+        file.write_all(&lines)?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+struct ApplyDelete<K> {
+    key: K,
+    key_hash: u64,
+}
+
+impl<K> DeleteContext for ApplyDelete<K> {
+    type Key = K;
+
+    fn key(&self) -> &K {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+}
+
+/// Subscriber applying shipped records to a replica store, in order.
+/// Records below the highest address already applied are skipped, so a
+/// batch offered again after a failure is not applied twice. `key_hash` must
+/// match the hash the primary is written with.
+///
+/// Write sequence numbers are per store: the replica numbers the records it
+/// applies itself, so a number returned by the primary does not name the
+/// same write on the replica. Progress in the primary's terms is
+/// [`applied_until`](Self::applied_until), a primary log address.
+pub struct ReplicaApplier<K, V, D: Disk> {
+    replica: Arc<RsKv<'static, K, V, D>>,
+    key_hash: fn(&K) -> u64,
+    applied_until: AtomicU64,
+    /// Serializes batches, so records apply in address order
+    applying: Mutex<()>,
+}
+
+impl<K, V, D> ReplicaApplier<K, V, D>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+    D: Disk + Clone,
+{
+    pub fn new(replica: Arc<RsKv<'static, K, V, D>>, key_hash: fn(&K) -> u64) -> Self {
+        Self {
+            replica,
+            key_hash,
+            applied_until: AtomicU64::new(0),
+            applying: Mutex::new(()),
+        }
+    }
+
+    pub fn replica(&self) -> &Arc<RsKv<'static, K, V, D>> {
+        &self.replica
+    }
+
+    /// Address just past the last record applied.
+    pub fn applied_until(&self) -> u64 {
+        self.applied_until.load(Ordering::Acquire)
+    }
+
+    /// Applies `batch`, loading runs of upserts in bulk and deleting in
+    /// between, so every key ends with its newest shipped state.
+    pub fn apply(&self, batch: &[ShippedRecord<K, V>]) -> Result<(), Status> {
+        let _applying = self.applying.lock().map_err(|_| Status::InternalError)?;
+        let mut upserts = Vec::new();
+        let mut applied_until = self.applied_until();
+        for record in batch {
+            if record.address < applied_until {
+                continue;
+            }
+            match &record.value {
+                Some(value) => upserts.push((record.key, value.clone(), record.user_flags)),
+                None => {
+                    self.replica
+                        .bulk_load_with_flags(upserts.drain(..), self.key_hash)?;
+                    let status = self.replica.delete(&ApplyDelete {
+                        key: record.key,
+                        key_hash: (self.key_hash)(&record.key),
+                    });
+                    if status != Status::Ok && status != Status::NotFound {
+                        return Err(status);
+                    }
+                }
+            }
+            applied_until = record.address + 1;
+        }
+        self.replica.bulk_load_with_flags(upserts, self.key_hash)?;
+        self.applied_until.store(applied_until, Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<K, V, D> LogSubscriber<K, V> for ReplicaApplier<K, V, D>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+    D: Disk + Clone,
+    RsKv<'static, K, V, D>: Send + Sync,
+{
+    fn on_records(&self, batch: &[ShippedRecord<K, V>]) -> Result<(), Status> {
+        self.apply(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rskv_core::{ReadContext, RecoveryOptions, UpsertContext, WriteOptions};
+    use crate::testing::temp_dir::TempDir;
+    use std::sync::atomic::AtomicBool;
+
+    struct Put {
+        key: u64,
+        value: u64,
+    }
+
+    impl UpsertContext for Put {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &u64 {
+            &self.key
+        }
+
+        fn value(&self) -> &u64 {
+            &self.value
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn put_atomic(&self, _value: &mut u64) -> bool {
+            false
+        }
+    }
+
+    struct Get {
+        key: u64,
+        value: Option<u64>,
+    }
+
+    impl ReadContext for Get {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &u64 {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn get(&mut self, value: &u64) {
+            self.value = Some(*value);
+        }
+    }
+
+    /// Keeps every batch, and rejects batches while `reject` is set.
+    #[derive(Default)]
+    struct Collect {
+        batches: Mutex<Vec<Vec<ShippedRecord<u64, u64>>>>,
+        reject: AtomicBool,
+    }
+
+    impl Collect {
+        fn records(&self) -> Vec<ShippedRecord<u64, u64>> {
+            self.batches.lock().unwrap().concat()
+        }
+    }
+
+    impl LogSubscriber<u64, u64> for Collect {
+        fn on_records(&self, batch: &[ShippedRecord<u64, u64>]) -> Result<(), Status> {
+            if self.reject.load(Ordering::Acquire) {
+                return Err(Status::IoError);
+            }
+            self.batches.lock().unwrap().push(batch.to_vec());
+            Ok(())
+        }
+    }
+
+    fn new_store(dir: &str) -> RsKv<'static, u64, u64, FileSystemDisk> {
+        RsKv::new(1 << 25, 1 << 10, FileSystemDisk::new(dir).unwrap()).unwrap()
+    }
+
+    fn remove(kv: &RsKv<'static, u64, u64, FileSystemDisk>, key: u64) -> Status {
+        kv.delete(&ApplyDelete { key, key_hash: key })
+    }
+
+    #[test]
+    fn test_ships_durable_records_in_order_and_batches() {
+        let temp = TempDir::new("ship_order");
+        let dir = temp.path();
+        let mut kv = new_store(dir);
+        let subscriber = Arc::new(Collect::default());
+        kv.attach_log_subscriber(
+            subscriber.clone(),
+            ShippingOptions {
+                max_batch_records: 16,
+                ..Default::default()
+            },
+        );
+
+        for key in 0..100 {
+            let flags = WriteOptions::default().user_flags((key % 16) as u8);
+            let put = Put {
+                key,
+                value: key * 10,
+            };
+            kv.upsert_opts(&put, &flags).unwrap();
+        }
+        // Nothing is shipped before it is durable.
+        assert!(kv.wait_for_shipping(Duration::from_secs(5)));
+        assert!(subscriber.records().is_empty());
+        kv.flush().unwrap();
+        assert!(kv.wait_for_shipping(Duration::from_secs(5)));
+        assert_eq!(subscriber.records().len(), 100);
+
+        // The flushed record is read-only, so the delete appends a tombstone.
+        assert_eq!(remove(&kv, 5), Status::Ok);
+        kv.flush().unwrap();
+        assert!(kv.wait_for_shipping(Duration::from_secs(5)));
+        let records = subscriber.records();
+        assert_eq!(records.len(), 101);
+        assert!(records.windows(2).all(|w| w[0].address < w[1].address));
+        assert!(records.iter().all(|record| record.timestamp.is_some()));
+        assert!(records.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        assert!(
+            subscriber
+                .batches
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|batch| batch.len() <= 16)
+        );
+        assert_eq!(records[42].op, ShippedOp::Upsert);
+        assert_eq!((records[42].key, records[42].value), (42, Some(420)));
+        assert_eq!(records[42].user_flags, 42 % 16);
+        assert_eq!(records[100].op, ShippedOp::Delete);
+        assert_eq!((records[100].key, records[100].value), (5, None));
+
+        let stats = kv.shipping_stats().unwrap();
+        assert_eq!(stats.records_shipped, 101);
+        assert_eq!(stats.lag_bytes(), 0);
+        assert_eq!(stats.acked_until, kv.hlog.get_tail_address().control());
+    }
+
+    #[test]
+    fn test_failing_subscriber_lags_without_blocking_writes() {
+        let temp = TempDir::new("ship_lag");
+        let dir = temp.path();
+        let mut kv = new_store(dir);
+        let subscriber = Arc::new(Collect::default());
+        subscriber.reject.store(true, Ordering::Release);
+        kv.attach_log_subscriber(
+            subscriber.clone(),
+            ShippingOptions {
+                retry_delay: Duration::from_millis(5),
+                ..Default::default()
+            },
+        );
+
+        for round in 0..3 {
+            for key in 0..50 {
+                assert_eq!(kv.upsert(&Put { key, value: round }), Status::Ok);
+            }
+            kv.flush().unwrap();
+        }
+        assert!(!kv.wait_for_shipping(Duration::from_millis(50)));
+        let stats = kv.shipping_stats().unwrap();
+        assert!(stats.failed_attempts > 0);
+        assert!(stats.lag_bytes() > 0);
+        assert_eq!(stats.records_shipped, 0);
+
+        subscriber.reject.store(false, Ordering::Release);
+        assert!(kv.wait_for_shipping(Duration::from_secs(5)));
+        let records = subscriber.records();
+        assert_eq!(records.len(), 150);
+        assert_eq!(records.last().unwrap().value, Some(2));
+    }
+
+    #[test]
+    fn test_file_subscriber_appends_dump_lines() {
+        let temp = TempDir::new("ship_file");
+        let dir = temp.path();
+        let mut kv = new_store(dir);
+        let path = format!("{}/shipped.txt", dir);
+        kv.attach_log_subscriber(
+            Arc::new(FileSubscriber::<u64, u64>::open(&path).unwrap()),
+            ShippingOptions::default(),
+        );
+        assert_eq!(kv.upsert(&Put { key: 7, value: 70 }), Status::Ok);
+        kv.flush().unwrap();
+        assert_eq!(remove(&kv, 7), Status::Ok);
+        kv.flush().unwrap();
+        assert!(kv.wait_for_shipping(Duration::from_secs(5)));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Vec<&str>> = text
+            .lines()
+            .map(|line| line.split('\t').collect())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][1..], ["put", "7", "70"]);
+        assert_eq!(lines[1][1..], ["delete", "7", "-"]);
+    }
+
+    #[test]
+    fn test_replica_follows_primary_and_cursor_survives_reopen() {
+        let primary_temp = TempDir::new("ship_primary");
+        let primary_dir = primary_temp.path();
+        let replica_temp = TempDir::new("ship_replica");
+        let replica_dir = replica_temp.path();
+        let mut primary = new_store(primary_dir);
+        let replica = Arc::new(new_store(replica_dir));
+        let applier = Arc::new(ReplicaApplier::new(replica.clone(), |key: &u64| *key));
+        primary.attach_log_subscriber(applier.clone(), ShippingOptions::default());
+
+        for key in 0..200 {
+            assert_eq!(
+                primary.upsert(&Put {
+                    key,
+                    value: key + 1
+                }),
+                Status::Ok
+            );
+        }
+        for key in (0..200).step_by(3) {
+            assert_eq!(remove(&primary, key), Status::Ok);
+        }
+        assert_eq!(primary.upsert(&Put { key: 9, value: 99 }), Status::Ok);
+        primary.flush().unwrap();
+        assert!(primary.wait_for_shipping(Duration::from_secs(5)));
+
+        for key in 0..200 {
+            let expected = match key {
+                9 => Some(99),
+                _ if key % 3 == 0 => None,
+                _ => Some(key + 1),
+            };
+            let mut get = Get { key, value: None };
+            replica.read(&mut get);
+            assert_eq!(get.value, expected, "key {}", key);
+        }
+
+        // The checkpoint keeps the acknowledged address; one taken without a
+        // subscriber keeps none.
+        primary.checkpoint("shipped").unwrap();
+        let cursor = primary.shipping_cursor("shipped").unwrap().unwrap();
+        assert_eq!(cursor, primary.shipping_stats().unwrap().acked_until);
+        assert_eq!(primary.detach_log_subscriber(), Some(cursor));
+        primary.checkpoint("detached").unwrap();
+        assert_eq!(primary.shipping_cursor("detached").unwrap(), None);
+        drop(primary);
+
+        let mut primary = RsKv::<u64, u64, FileSystemDisk>::open(
+            primary_dir,
+            Some("shipped"),
+            &RecoveryOptions {
+                log_size: 1 << 25,
+                table_size: 1 << 10,
+                ..Default::default()
+            },
+            |key| *key,
+        )
+        .unwrap();
+        assert_eq!(primary.shipping_cursor("shipped").unwrap(), Some(cursor));
+        primary.attach_log_subscriber(
+            applier.clone(),
+            ShippingOptions {
+                start_address: cursor,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            primary.upsert(&Put {
+                key: 0,
+                value: 1000
+            }),
+            Status::Ok
+        );
+        primary.flush().unwrap();
+        assert!(primary.wait_for_shipping(Duration::from_secs(5)));
+        assert_eq!(primary.shipping_stats().unwrap().records_shipped, 1);
+        let mut get = Get {
+            key: 0,
+            value: None,
+        };
+        replica.read(&mut get);
+        assert_eq!(get.value, Some(1000));
+
+        drop(primary);
+        drop(applier);
+        drop(replica);
+    }
+}
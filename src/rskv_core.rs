@@ -1,16 +1,48 @@
+use crate::core::address::Address;
+use crate::core::advanced_locking::{HierarchicalLockManager, LockGuard, LockId, LockIntent};
 use crate::core::checkpoint::{CheckpointMetadata, IndexMetadata};
+use crate::core::clock::{Clock, HybridClock, system_time_from_ms};
 use crate::core::light_epoch::LightEpoch;
+use crate::core::numa::{MemoryMetrics, NumaConfig, NumaPlacer};
 use crate::core::record::{Record, RecordInfo};
 use crate::core::status::Status;
 use crate::device::file_system_disk::FileSystemDisk;
+use crate::environment::file::FileCreateDisposition;
+use crate::events::{
+    CheckpointCompleted, EventDispatcher, EventHooks, FlushCompleted, HookStats, LogCapacityLow,
+    StoreEvent,
+};
+use crate::hlog::page_cache::{PageCache, PageCacheConfig, PageCacheStats};
 use crate::hlog::persistent_memory_malloc::{Disk, PersistentMemoryMalloc};
+use crate::hlog::read_cache::{CachedRecord, ReadCache, ReadCacheConfig, ReadCacheStats};
 use crate::index::IHashIndex;
 use crate::index::definitions::HotLogHashIndexDefinition;
+use crate::index::invariants::IndexViolation;
+use crate::index::key_hash::HotLogKeyHash;
 use crate::index::mem_index::{FindContext, MemHashIndex};
+use crate::keys::KeyBytes;
+use crate::performance::access_analyzer::{AccessAnalyzer, HotKeySketch, OperationType};
+use crate::performance::batch_optimizer::{BatchStats, WriteCombiner, WriteCombinerConfig};
+use crate::performance::migration_manager::{
+    AdmissionConfig, AdmissionController, AdmissionStats, ByteRateLimiter, MutableRegionConfig,
+    MutableRegionController, MutableRegionStats, PageTiering, PageTieringConfig, PageTieringStats,
+    PressureSignals, plan_page_tiering,
+};
+use crate::repair::{NewestRecords, key_bytes_hash};
+use crate::replication::{
+    LogShipper, LogSubscriber, SHIPPING_CURSOR_FILE, ShippingOptions, ShippingStats,
+};
+use crate::secondary_index::{
+    SECONDARY_INDEX_FILE, SecondaryEntries, SecondaryIndexes, SecondaryKeyExtractor, build_entries,
+    decode_entries,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::{Read, Write};
 use std::marker::PhantomData;
-use std::sync::atomic::Ordering;
+use std::ops::RangeBounds;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
 
 // The user-provided context for an upsert operation.
 pub trait UpsertContext {
@@ -61,11 +93,417 @@ pub trait DeleteContext {
     fn key_hash(&self) -> u64;
 }
 
+/// Name of the file in a checkpoint directory holding the write sequence.
+const WRITE_SEQ_FILE: &str = "write.seq";
+
+/// Status of a write that returns its sequence number.
+fn status_of(result: Result<u64, Status>) -> Status {
+    result.err().unwrap_or(Status::Ok)
+}
+
+/// Runs `op` and records how it ended as the `status` of the current span.
+fn record_status<T>(op: impl FnOnce() -> Result<T, Status>) -> Result<T, Status> {
+    let result = op();
+    span_record!(status = result.as_ref().err().copied().unwrap_or(Status::Ok));
+    result
+}
+
+struct LoadUpsertContext<K, V> {
+    key: K,
+    value: V,
+    key_hash: u64,
+}
+
+impl<K, V> UpsertContext for LoadUpsertContext<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn value(&self) -> &Self::Value {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+
+    fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+        false
+    }
+}
+
+/// Copies out the current value of a key.
+struct ValueOf<K, V> {
+    key: K,
+    key_hash: u64,
+    value: Option<V>,
+}
+
+impl<K, V: Clone> ReadContext for ValueOf<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key_hash
+    }
+
+    fn get(&mut self, value: &Self::Value) {
+        self.value = Some(value.clone());
+    }
+}
+
+/// Options for reopening a store from its log directory.
+#[derive(Debug, Clone)]
+pub struct RecoveryOptions {
+    pub log_size: u64,
+    pub table_size: u64,
+    /// Rebuild the index by scanning the whole log when no usable checkpoint
+    /// exists. Off by default since it reads every record in the log.
+    pub rebuild_index_from_log: bool,
+}
+
+impl Default for RecoveryOptions {
+    fn default() -> Self {
+        Self {
+            log_size: 1 << 30,
+            table_size: 1 << 20,
+            rebuild_index_from_log: false,
+        }
+    }
+}
+
+/// What a write does when another thread holds its key through
+/// [`RsKv::lock_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockedKeyWrites {
+    /// Wait for the lock, up to the lock manager's timeout
+    Wait,
+    /// Fail right away with `Status::LockTimeout`
+    Fail,
+}
+
+/// Options for [`RsKv::lock_key`].
+#[derive(Debug, Clone, Copy)]
+pub struct KeyLockOptions {
+    /// Number of lock stripes keys are hashed onto; keys sharing a stripe
+    /// lock each other out
+    pub stripes: u64,
+    pub locked_writes: LockedKeyWrites,
+}
+
+impl Default for KeyLockOptions {
+    fn default() -> Self {
+        Self {
+            stripes: 1 << 10,
+            locked_writes: LockedKeyWrites::Wait,
+        }
+    }
+}
+
+/// When a write is durable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// At the next flush or checkpoint
+    #[default]
+    Buffered,
+    /// Before the write returns: the log is flushed and synced
+    Sync,
+}
+
+/// Per-call options for [`RsKv::upsert_opts`], [`RsKv::rmw_opts`] and
+/// [`RsKv::delete_opts`]. The default is what the plain methods do.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WriteOptions {
+    pub durability: Durability,
+    /// Expiry of the written record. Records cannot expire yet, so writes
+    /// with a TTL fail with `Status::FeatureNotSupported`.
+    pub ttl: Option<Duration>,
+    /// Application-defined bits stored in the header of the written record,
+    /// read back by [`RsKv::read_with_meta`]. Only the low 4 bits are
+    /// available; setting any other fails with
+    /// `Status::InvalidConfiguration`.
+    pub user_flags: u8,
+}
+
+impl WriteOptions {
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn user_flags(mut self, user_flags: u8) -> Self {
+        self.user_flags = user_flags;
+        self
+    }
+
+    /// Fails for options the store cannot honour, before anything is written.
+    fn check(&self) -> Result<(), Status> {
+        if self.ttl.is_some() {
+            return Err(Status::FeatureNotSupported);
+        }
+        if self.user_flags & !RecordInfo::USER_FLAGS_MASK != 0 {
+            return Err(Status::InvalidConfiguration);
+        }
+        Ok(())
+    }
+}
+
+/// Per-call options for [`RsKv::read_opts`]. The default is what
+/// [`RsKv::read`] does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReadOptions {
+    /// Count the read towards hot key and heat tracking
+    pub track_access: bool,
+    /// Wait until [`RsKv::applied_seq`] reaches this sequence number first
+    pub min_seq: Option<u64>,
+    /// Longest wait for `min_seq` before failing with `Status::Pending`
+    pub wait_timeout: Duration,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            track_access: true,
+            min_seq: None,
+            wait_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ReadOptions {
+    pub fn track_access(mut self, track_access: bool) -> Self {
+        self.track_access = track_access;
+        self
+    }
+
+    pub fn min_seq(mut self, seq: u64) -> Self {
+        self.min_seq = Some(seq);
+        self
+    }
+
+    pub fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = timeout;
+        self
+    }
+}
+
+/// One version of a key, as returned by [`RsKv::read_versions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedValue<V> {
+    /// Log address of the record holding this version
+    pub address: Address,
+    pub value: V,
+    /// Write time of the version, `None` for records written without one
+    pub timestamp: Option<SystemTime>,
+    /// [`WriteOptions::user_flags`] of the write that made the version
+    pub user_flags: u8,
+}
+
+/// Where the newest record of a key is, when it was written and the flags
+/// it carries, as returned by [`RsKv::read_with_meta`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordMeta {
+    /// Log address of the record
+    pub address: Address,
+    /// Write time of the record, `None` for records written without one
+    pub timestamp: Option<SystemTime>,
+    /// [`WriteOptions::user_flags`] of the write that made the record
+    pub user_flags: u8,
+}
+
+/// Size limit of a store's log, see [`RsKv::set_log_capacity`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogCapacityConfig {
+    /// Bytes of log the store may write. The log's address space, the pages
+    /// it was created with, caps it.
+    pub max_log_size: u64,
+    /// Share of the limit left when a [`LogCapacityLow`] event is raised
+    pub warn_headroom: f64,
+}
+
+impl Default for LogCapacityConfig {
+    fn default() -> Self {
+        Self {
+            max_log_size: u64::MAX,
+            warn_headroom: 0.1,
+        }
+    }
+}
+
+/// How much of its size limit a store's log has used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogCapacityStats {
+    /// Bytes of log written, up to the tail
+    pub written: u64,
+    pub limit: u64,
+    /// Bytes left before writes fail with `Status::LogCapacityExhausted`
+    pub headroom: u64,
+}
+
+struct KeyLocks {
+    manager: Arc<HierarchicalLockManager>,
+    options: KeyLockOptions,
+}
+
+impl KeyLocks {
+    fn lock_id(&self, key_hash: u64) -> LockId {
+        LockId::record(key_hash % self.options.stripes.max(1))
+    }
+}
+
+/// Holds a key locked through [`RsKv::lock_key`] until dropped.
+pub struct KeyLockGuard<K> {
+    key: K,
+    _guard: LockGuard,
+}
+
+impl<K> KeyLockGuard<K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+/// Phases of reopening a store, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryPhase {
+    /// Loading the index snapshot of a checkpoint
+    LoadCheckpoint,
+    /// Verifying the log, cutting off a torn tail and reading it into memory
+    LoadLog,
+    /// Pointing the index at records the snapshot does not cover
+    ReplayLog,
+}
+
+/// Regions and page map of the log, as returned by
+/// [`RsKv::debug_log_state`].
+#[cfg(feature = "debug-introspection")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LogDebugState {
+    pub regions: crate::hlog::persistent_memory_malloc::RegionSummary,
+    pub pages: Vec<crate::hlog::persistent_memory_malloc::PageDebugInfo>,
+}
+
+/// What [`RsKv::open_with_report`] did to bring a store back.
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    /// Checkpoint the store was recovered from, if one was usable
+    pub checkpoint_id: Option<String>,
+    pub index_entries_loaded: u64,
+    pub log_bytes_replayed: u64,
+    pub records_replayed: u64,
+    /// Records cut off the log tail because they were torn. A partial
+    /// record counts as one.
+    pub torn_records_skipped: u64,
+    pub duration_per_phase: Vec<(RecoveryPhase, Duration)>,
+}
+
+impl RecoveryReport {
+    fn finish_phase(&mut self, phase: RecoveryPhase, started: Instant) {
+        let elapsed = started.elapsed();
+        log::info!("recovery phase {:?} finished in {:?}", phase, elapsed);
+        trace_event!(?phase, ?elapsed, "recovery phase finished");
+        self.duration_per_phase.push((phase, elapsed));
+    }
+}
+
+/// How much [`RsKv::verify`] checks. Region invariants and the newest
+/// checkpoint are checked at every level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyLevel {
+    Quick,
+    /// Every n-th index entry and flush frame
+    Sampled(usize),
+    Full,
+}
+
+/// A problem found by [`RsKv::verify`]. Addresses are log addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyFinding {
+    /// An index entry points outside the log or at an empty slot
+    DanglingIndexEntry(u64),
+    /// An index entry points at a record whose key hashes elsewhere
+    IndexKeyMismatch(u64),
+    /// Two log region boundaries are out of order
+    RegionOrder {
+        rule: &'static str,
+        lower: u64,
+        upper: u64,
+    },
+    /// Flushed log bytes no longer match their frame CRC
+    FrameCrcMismatch { begin: u64, end: u64 },
+    /// The newest checkpoint would fail to load
+    CheckpointUnloadable { token: String, status: Status },
+    /// The index buckets themselves are malformed
+    IndexInvariant(IndexViolation),
+}
+
+/// What [`RsKv::verify`] checked and found.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub index_entries_checked: u64,
+    pub frames_checked: u64,
+    /// Token of the checkpoint that was checked, if the store has one
+    pub checkpoint_checked: Option<String>,
+    pub findings: Vec<VerifyFinding>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
 pub struct RsKv<'epoch, K, V, D: Disk> {
     epoch: LightEpoch,
     pub hlog: PersistentMemoryMalloc<'epoch, D>,
     pub index: MemHashIndex<'epoch, HotLogHashIndexDefinition>,
     pub disk: D,
+    /// Sampled record of the keys passed to `read` and `upsert`
+    hot_keys: HotKeySketch<K>,
+    /// Heat tracking fed by `read` and `upsert` when attached
+    access_analyzer: Option<Arc<AccessAnalyzer>>,
+    /// Copies of hot records read from disk when set
+    read_cache: Option<ReadCache<K, V>>,
+    /// Batches concurrent upserts into shared log allocations when set
+    write_combiner: Option<WriteCombiner<(u64, K, V, u8), Status>>,
+    /// Moves the read-only boundary along with the tail when set
+    mutable_region: Option<MutableRegionController>,
+    /// Sheds or delays writes under pressure when set
+    admission: Option<AdmissionController>,
+    /// Write times for record headers
+    clock: HybridClock,
+    /// Stripe locks behind `lock_key` when set
+    key_locks: Option<KeyLocks>,
+    /// Ships durable records to a subscriber when set
+    log_shipper: Option<LogShipper>,
+    /// Runs the lifecycle event hooks when set
+    event_dispatcher: Option<EventDispatcher>,
+    /// Bytes of log pages kept in memory when set
+    memory_budget: Option<u64>,
+    log_capacity: LogCapacityConfig,
+    /// Set once the log has passed the warning point of its limit
+    log_capacity_warned: AtomicBool,
+    /// Sequence number of the newest completed write
+    write_seq: AtomicU64,
+    /// Kept in step with every write when any index exists
+    secondary_indexes: Option<Mutex<SecondaryIndexes<K, V>>>,
+    /// Saved contents of the recovered checkpoint's secondary indexes,
+    /// waiting for `create_secondary_index` to attach their extractors
+    recovered_secondary_indexes: HashMap<String, SecondaryEntries<K>>,
     _key: PhantomData<K>,
     _value: PhantomData<V>,
 }
@@ -81,11 +519,28 @@ where
             hlog: PersistentMemoryMalloc::new(),
             index: MemHashIndex::new(),
             disk: disk.clone(),
+            hot_keys: HotKeySketch::default(),
+            access_analyzer: None,
+            read_cache: None,
+            write_combiner: None,
+            mutable_region: None,
+            admission: None,
+            clock: HybridClock::new(),
+            key_locks: None,
+            log_shipper: None,
+            event_dispatcher: None,
+            memory_budget: None,
+            log_capacity: LogCapacityConfig::default(),
+            log_capacity_warned: AtomicBool::new(false),
+            write_seq: AtomicU64::new(0),
+            secondary_indexes: None,
+            recovered_secondary_indexes: HashMap::new(),
             _key: PhantomData,
             _value: PhantomData,
         };
         let epoch_ptr: *const LightEpoch = &kv.epoch;
         kv.hlog.initialize(log_size, unsafe { &*epoch_ptr }, disk);
+        kv.hlog.open_superblock()?;
         kv.index.initialize(table_size, 64, unsafe { &*epoch_ptr });
         Ok(kv)
     }
@@ -94,297 +549,902 @@ where
         self.index.size()
     }
 
-    pub fn upsert(&self, context: &impl UpsertContext<Key = K, Value = V>) -> Status {
-        let mut find_context = FindContext::new(context.key_hash());
+    /// Up to `k` of the most read and upserted keys since the last
+    /// [`reset_hot_keys`](Self::reset_hot_keys), hottest first, with
+    /// approximate access counts.
+    pub fn hot_keys(&self, k: usize) -> Vec<(K, u64)> {
+        self.hot_keys.top(k)
+    }
 
-        loop {
-            let status = self.index.find_or_create_entry(&mut find_context);
-            if status != Status::Ok {
-                // Should not happen in current simplified MemHashIndex
-                return status;
-            }
+    /// Starts a new hot-key window.
+    pub fn reset_hot_keys(&self) {
+        self.hot_keys.reset();
+    }
 
-            let entry = find_context.entry;
-            // Use real head and read-only addresses from hlog
-            let read_only_address = self.hlog.get_read_only_address();
+    /// Attaches an analyzer that `read` and `upsert` report every key and
+    /// record page they touch to, or detaches it with `None`.
+    pub fn set_access_analyzer(&mut self, analyzer: Option<Arc<AccessAnalyzer>>) {
+        self.access_analyzer = analyzer;
+    }
 
-            // Attempt in-place update if possible
-            if !entry.unused() && entry.address() >= read_only_address {
-                // Check key equality
-                if let Some(record) = self.hlog.get::<K, V>(entry.address()) {
-                    unsafe {
-                        let record_key = Record::<K, V>::key(record as *const Record<K, V>);
-                        if std::ptr::eq(record_key, context.key())
-                            || std::ptr::read_unaligned(record_key) == *context.key()
-                        {
-                            // Key matches, attempt in-place update
-                            let record_value = Record::<K, V>::value_mut(
-                                record as *const Record<K, V> as *mut Record<K, V>,
-                            );
-                            if context.put_atomic(record_value) {
-                                return Status::Ok;
-                            }
-                        }
-                    }
-                }
-            }
+    pub fn access_analyzer(&self) -> Option<&Arc<AccessAnalyzer>> {
+        self.access_analyzer.as_ref()
+    }
 
-            // RCU (Read-Copy-Update) path
-            let record_size = Record::<K, V>::required_size_with_alignment();
-            let new_address = match self.hlog.allocate(record_size as u64) {
-                Ok(addr) => addr,
-                Err(closed_page) => {
-                    self.hlog.new_page(closed_page);
-                    // Retry allocation after creating new page
-                    match self.hlog.allocate(record_size as u64) {
-                        Ok(addr) => addr,
-                        Err(_) => return Status::Pending, // Still failed, ask caller to retry
-                    }
-                }
-            };
+    /// Keeps copies of records read from disk in memory per `config`, or
+    /// turns the read cache off with `None`. A read that finds its record
+    /// below the in-memory log copies it into the cache when its key is at
+    /// least `min_heat` by the attached access analyzer, or always without
+    /// one. Copies already cached are dropped first. Records only leave
+    /// memory under [`set_memory_budget`](Self::set_memory_budget), so
+    /// without a budget nothing is cached.
+    pub fn set_read_cache(&mut self, config: Option<ReadCacheConfig>) {
+        self.drain_read_cache();
+        self.read_cache = config.map(ReadCache::new);
+    }
 
-            // 2. Get a mutable slice to the allocated memory
-            let buffer = unsafe {
-                self.hlog
-                    .get_mut_slice_unchecked(new_address, record_size as usize)
-            };
-            if buffer.is_empty() {
-                return Status::Pending;
-            }
+    /// Hit and admission counts since [`set_read_cache`](Self::set_read_cache),
+    /// if the read cache is on.
+    pub fn read_cache_stats(&self) -> Option<ReadCacheStats> {
+        self.read_cache.as_ref().map(ReadCache::stats)
+    }
 
-            // 3. Construct the new record in the allocated slice
-            let new_record_info = RecordInfo::new(entry.address(), 0, false, false, true);
-            unsafe {
-                Record::create_in(buffer, new_record_info, context.key(), context.value());
-            }
+    /// Reads records below the in-memory log through a cache of recently
+    /// read disk pages per `config`, or straight from disk with `None`. A
+    /// miss also reads the pages after it while disk reads run sequentially,
+    /// see [`PageCacheConfig`]. Replacing the cache empties it and turns
+    /// [chain prefetching](Self::set_chain_prefetch) off.
+    pub fn set_page_cache(&mut self, config: Option<PageCacheConfig>) {
+        self.hlog.set_page_cache(config.map(PageCache::new));
+    }
 
-            // 4. CAS the hash index to point to the new record
-            if self
+    /// While a read walks a hash chain on disk, reads the page of the next
+    /// record in the background, so the next hop finds it in the page
+    /// cache. At most `max_outstanding` pages are read ahead at once, and
+    /// further prefetches are dropped. `None` turns prefetching off. Needs
+    /// the page cache of [`set_page_cache`](Self::set_page_cache).
+    pub fn set_chain_prefetch(&mut self, max_outstanding: Option<usize>)
+    where
+        D: Send + 'static,
+    {
+        self.hlog.set_prefetch(max_outstanding);
+    }
+
+    /// Hit, read-ahead and prefetch counts since
+    /// [`set_page_cache`](Self::set_page_cache), if the page cache is on.
+    pub fn page_cache_stats(&self) -> Option<PageCacheStats> {
+        self.hlog.page_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Points every index entry that leads into the read cache back at the
+    /// main log, and empties the cache.
+    fn drain_read_cache(&self) {
+        if let Some(cache) = &self.read_cache {
+            cache.drain(|address, copy| self.restore_entry(address, copy));
+        }
+    }
+
+    /// Points the index entry of `copy`'s key back at the main log if it
+    /// still leads to the copy at cache address `address`.
+    fn restore_entry(&self, address: Address, copy: &CachedRecord<K, V>) {
+        let mut find_context = FindContext::new(copy.key_hash);
+        if self.index.find_entry(&mut find_context) == Status::Ok
+            && find_context.entry.in_readcache()
+            && find_context.entry.address() == address
+        {
+            // A writer that swapped the entry meanwhile already skipped the copy.
+            let _ = self
                 .index
-                .try_update_entry(&find_context, new_address, false)
-                == Status::Ok
-            {
-                return Status::Ok;
+                .try_update_entry(&find_context, copy.previous, false);
+        }
+    }
+
+    /// Points the entry of `find_context` back at the main log if it leads
+    /// into the read cache, as a write makes the cached copy stale, and
+    /// leaves the entry the write is to replace in `find_context`.
+    fn skip_read_cache(&self, find_context: &mut FindContext) -> Status {
+        while find_context.entry.in_readcache() {
+            let Some(cache) = &self.read_cache else {
+                return Status::UnexpectedState;
+            };
+            if let Some(previous) = cache.previous_address(find_context.entry.address()) {
+                let _ = self.index.try_update_entry(find_context, previous, false);
+            }
+            let status = self.index.find_entry(find_context);
+            if status != Status::Ok {
+                return status;
             }
+        }
+        Status::Ok
+    }
 
-            // Invalidate the allocated record before retrying
-            unsafe {
-                let buffer = self
-                    .hlog
-                    .get_mut_slice_unchecked(new_address, record_size as usize);
-                let record_ptr = buffer.as_mut_ptr() as *mut Record<K, V>;
-                // Read header safely using unaligned access
-                let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
-                let header_control = u64::from_le_bytes([
-                    header_bytes[0],
-                    header_bytes[1],
-                    header_bytes[2],
-                    header_bytes[3],
-                    header_bytes[4],
-                    header_bytes[5],
-                    header_bytes[6],
-                    header_bytes[7],
-                ]);
-                let mut header = RecordInfo::from_control(header_control);
-                header.set_invalid(true);
-                // Write header safely using unaligned access
-                let header_bytes = std::slice::from_raw_parts_mut(record_ptr as *mut u8, 8);
-                let new_control = header.control();
-                let control_bytes = new_control.to_le_bytes();
-                header_bytes.copy_from_slice(&control_bytes);
+    /// Address of the newest main-log record in the chain of `find_context`,
+    /// looking through a read cache copy. The entry is found again if the
+    /// copy was evicted since it was read.
+    fn chain_head(&self, find_context: &FindContext) -> Address {
+        let mut entry = find_context.entry;
+        loop {
+            if !entry.in_readcache() {
+                return entry.address();
             }
+            let previous = self
+                .read_cache
+                .as_ref()
+                .and_then(|cache| cache.previous_address(entry.address()));
+            if let Some(previous) = previous {
+                return previous;
+            }
+            let mut retry = FindContext::new(find_context.key_hash);
+            if self.index.find_entry(&mut retry) != Status::Ok {
+                return Address::from_control(0);
+            }
+            entry = retry.entry;
         }
     }
 
-    pub fn read(&self, context: &mut impl ReadContext<Key = K, Value = V>) -> Status {
-        let mut find_context = FindContext::new(context.key_hash());
-        if self.index.find_entry(&mut find_context) != Status::Ok {
-            return Status::NotFound;
+    fn note_access(&self, key_hash: u64, operation_type: OperationType) {
+        if let Some(analyzer) = &self.access_analyzer {
+            analyzer.record_access(key_hash, operation_type);
         }
+    }
 
-        let record_size = Record::<K, V>::required_size_with_alignment();
-        let mut current_address = find_context.entry.address();
-        // Use tail address as the head address for now (simplified)
-        let _head_address = self.hlog.get_head_address();
+    fn note_page_access(&self, address: Address) {
+        if let Some(analyzer) = &self.access_analyzer {
+            analyzer.record_page_access(address.page());
+        }
+    }
 
-        loop {
-            if current_address.control() == 0 {
-                // Reached end of chain
-                return Status::NotFound;
-            }
+    /// Routes upserts through a write combiner, so concurrent writers share
+    /// one log allocation per batch, or back to the direct path with `None`.
+    /// Combined upserts always append a new record; they never update a
+    /// mutable record in place.
+    pub fn set_write_combining(&mut self, config: Option<WriteCombinerConfig>) {
+        self.write_combiner = config.map(WriteCombiner::new);
+    }
 
-            let buffer = self.hlog.get_slice(current_address, record_size as usize);
-            if buffer.is_empty() {
-                return Status::NotFound;
-            }
+    pub fn write_combining_stats(&self) -> Option<BatchStats> {
+        self.write_combiner.as_ref().map(WriteCombiner::get_stats)
+    }
 
-            let record_ptr = buffer.as_ptr() as *const Record<K, V>;
+    /// Lets the read-only boundary trail the tail by a share of log memory
+    /// that adapts to the in-place update hit rate, instead of moving only
+    /// on flush. `None` turns the controller off.
+    pub fn set_mutable_region_control(&mut self, config: Option<MutableRegionConfig>) {
+        self.mutable_region = config.map(MutableRegionController::new);
+    }
 
-            unsafe {
-                if Record::key(record_ptr) == context.key() {
-                    // Read header safely using unaligned access
-                    let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
-                    let header_control = u64::from_le_bytes([
-                        header_bytes[0],
-                        header_bytes[1],
-                        header_bytes[2],
-                        header_bytes[3],
-                        header_bytes[4],
-                        header_bytes[5],
-                        header_bytes[6],
-                        header_bytes[7],
-                    ]);
-                    let header = RecordInfo::from_control(header_control);
-                    if header.tombstone() {
-                        return Status::NotFound;
-                    }
-                    // The value's lifetime is tied to the log buffer. The context's
-                    // get method is responsible for copying the data out if needed.
-                    context.get(Record::value(record_ptr));
-                    return Status::Ok;
+    pub fn mutable_region_stats(&self) -> Option<MutableRegionStats> {
+        self.mutable_region
+            .as_ref()
+            .map(MutableRegionController::get_stats)
+    }
+
+    /// Fails writes with `Status::Overloaded`, or holds them back, while the
+    /// flush backlog, in-memory log use or index load is at the limits in
+    /// `config`. Checked before each upsert, read-modify-write and delete
+    /// takes its key lock. `None` admits every write.
+    pub fn set_admission_control(&mut self, config: Option<AdmissionConfig>) {
+        self.admission = config.map(AdmissionController::new);
+    }
+
+    pub fn admission_stats(&self) -> Option<AdmissionStats> {
+        let admission = self.admission.as_ref()?;
+        Some(admission.get_stats(&self.pressure_signals(admission)))
+    }
+
+    fn admit_write(&self) -> Result<(), Status> {
+        match &self.admission {
+            Some(admission) => admission.admit(|| self.pressure_signals(admission)),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails writes with `Status::LogCapacityExhausted` once the log would
+    /// grow past `config.max_log_size`, and raises a [`LogCapacityLow`]
+    /// event, logged as a warning, when less than `config.warn_headroom` of
+    /// the limit is left. Without a call, the limit is the log's address
+    /// space. Records are never reclaimed from the log in place, so the
+    /// limit covers every byte written since the store was created.
+    pub fn set_log_capacity(&mut self, config: LogCapacityConfig) -> Result<(), Status> {
+        if !(0.0..=1.0).contains(&config.warn_headroom) {
+            return Err(Status::InvalidConfiguration);
+        }
+        self.log_capacity = config;
+        self.log_capacity_warned.store(false, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn log_capacity_stats(&self) -> LogCapacityStats {
+        let address_space = self.hlog.pages.len() as u64 * self.hlog.page_size;
+        let limit = self.log_capacity.max_log_size.min(address_space);
+        // Failed allocations can leave the tail past the last page.
+        let written = self.hlog.get_tail_address().control().min(address_space);
+        LogCapacityStats {
+            written,
+            limit,
+            headroom: limit.saturating_sub(written),
+        }
+    }
+
+    /// Allocates `size` bytes of log for new records, moving the tail onto
+    /// a new page when the current one is full. Fails with
+    /// `Status::LogCapacityExhausted` past the log's limit and with
+    /// `Status::Pending` when no page could be allocated.
+    pub(crate) fn allocate_record(&self, size: u64) -> Result<Address, Status> {
+        op_span!("allocate", bytes = size; address);
+        let capacity = self.log_capacity_stats();
+        if size > capacity.headroom {
+            return Err(Status::LogCapacityExhausted);
+        }
+        let address = match self.hlog.allocate(size) {
+            Ok(address) => address,
+            Err(closed_page) => {
+                trace_event!(page = closed_page.page(), "page full, opening the next one");
+                self.hlog.new_page(closed_page);
+                self.enforce_memory_budget();
+                match self.hlog.allocate(size) {
+                    Ok(address) => address,
+                    Err(page) if page.page() as usize >= self.hlog.pages.len() => {
+                        return Err(Status::LogCapacityExhausted);
+                    }
+                    Err(_) => return Err(Status::Pending),
                 }
-                // Read header safely using unaligned access
-                let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
-                let header_control = u64::from_le_bytes([
-                    header_bytes[0],
-                    header_bytes[1],
-                    header_bytes[2],
-                    header_bytes[3],
-                    header_bytes[4],
-                    header_bytes[5],
-                    header_bytes[6],
-                    header_bytes[7],
-                ]);
-                let header = RecordInfo::from_control(header_control);
-                current_address = header.previous_address();
             }
+        };
+        self.check_log_headroom();
+        span_record!(address = address.control());
+        Ok(address)
+    }
+
+    fn check_log_headroom(&self) {
+        let capacity = self.log_capacity_stats();
+        let warn_below = (capacity.limit as f64 * self.log_capacity.warn_headroom) as u64;
+        if capacity.headroom < warn_below && !self.log_capacity_warned.swap(true, Ordering::AcqRel)
+        {
+            log::warn!(
+                "log capacity low: {} of {} bytes written, {} left",
+                capacity.written,
+                capacity.limit,
+                capacity.headroom
+            );
+            self.emit_event(|| {
+                StoreEvent::LogCapacity(LogCapacityLow {
+                    written: capacity.written,
+                    limit: capacity.limit,
+                })
+            });
         }
     }
 
-    pub fn rmw(&self, context: &mut impl RmwContext<Key = K, Value = V>) -> Status
+    fn pressure_signals(&self, admission: &AdmissionController) -> PressureSignals {
+        let memory = self.hlog.buffer_size_in_pages as u64 * self.hlog.page_size;
+        let tail = self.hlog.get_tail_address().control();
+        PressureSignals {
+            flush_backlog_bytes: self.flush_backlog_bytes(),
+            log_memory_fraction: tail as f64 / memory as f64,
+            index_load_factor: admission.index_load_factor(|| {
+                self.index.entry_count() as f64 / self.index.size().max(1) as f64
+            }),
+        }
+    }
+
+    /// Bytes appended to the log but not yet flushed.
+    pub fn flush_backlog_bytes(&self) -> u64 {
+        let tail = self.hlog.get_tail_address().control();
+        let flushed = self.hlog.flushed_until_address.load(Ordering::Acquire);
+        tail.saturating_sub(flushed.control())
+    }
+
+    /// Regions of the log and the state of each of its pages, for
+    /// diagnosing memory use.
+    #[cfg(feature = "debug-introspection")]
+    pub fn debug_log_state(&self) -> LogDebugState {
+        LogDebugState {
+            regions: self.hlog.region_summary(),
+            pages: self.hlog.debug_page_map(),
+        }
+    }
+
+    fn note_upsert(&self, in_place: bool) {
+        let Some(controller) = &self.mutable_region else {
+            return;
+        };
+        if !controller.record_upsert(in_place, self.flush_backlog_bytes()) {
+            return;
+        }
+        let memory = self.hlog.buffer_size_in_pages as u64 * self.hlog.page_size;
+        let lag = (memory as f64 * controller.target_fraction()) as u64;
+        let tail = self.hlog.get_tail_address().control();
+        if tail > lag {
+            self.hlog
+                .shift_read_only_address(Address::from_control(tail - lag));
+        }
+    }
+
+    /// Enables [`lock_key`](Self::lock_key), or disables it with `None`.
+    /// While enabled, every upsert, rmw and delete checks its key's stripe,
+    /// which costs a trip through the lock manager.
+    pub fn set_key_locking(&mut self, options: Option<KeyLockOptions>) {
+        self.key_locks = options.map(|options| KeyLocks {
+            manager: Arc::new(HierarchicalLockManager::new()),
+            options,
+        });
+    }
+
+    /// Places hybrid log pages and hash index buckets on NUMA nodes per
+    /// `config`, or stops placing new pages with `None`. Pages already in
+    /// memory and the index are placed right away. A no-op without the
+    /// `numa` feature or off Linux, see [`crate::core::numa`].
+    pub fn set_numa(&mut self, config: Option<NumaConfig>) {
+        self.hlog.set_numa(config.map(NumaPlacer::new));
+        if let Some(numa) = &self.hlog.numa {
+            let (buckets, len) = self.index.bucket_memory();
+            unsafe { numa.place_index(buckets, len) };
+        }
+    }
+
+    /// Per-node allocation counts since [`set_numa`](Self::set_numa), if
+    /// placement is on.
+    pub fn memory_metrics(&self) -> Option<MemoryMetrics> {
+        self.hlog.numa.as_ref().map(NumaPlacer::metrics)
+    }
+
+    /// Locks `key` for a critical section spanning several operations. Other
+    /// `lock_key` callers for the key block until the guard is dropped, and
+    /// their writes to it wait or fail as configured, while reads stay
+    /// lock-free. Writes made on the locking thread go through.
+    ///
+    /// `key_hash` must be the hash the operation contexts report for `key`.
+    /// Fails with `Status::InvalidConfiguration` unless key locking is on,
+    /// and with the lock manager's `LockTimeout` or `DeadlockDetected`.
+    pub fn lock_key(&self, key: &K, key_hash: u64) -> Result<KeyLockGuard<K>, Status> {
+        let key_locks = self
+            .key_locks
+            .as_ref()
+            .ok_or(Status::InvalidConfiguration)?;
+        let guard = key_locks
+            .manager
+            .acquire_lock(key_locks.lock_id(key_hash), LockIntent::Write)?;
+        Ok(KeyLockGuard {
+            key: *key,
+            _guard: guard,
+        })
+    }
+
+    /// Holds off a write while another thread has its key locked. Writers
+    /// share the stripe, so they only exclude `lock_key`.
+    fn lock_for_write(&self, key_hash: u64) -> Result<Option<LockGuard>, Status> {
+        let Some(key_locks) = &self.key_locks else {
+            return Ok(None);
+        };
+        let lock_id = key_locks.lock_id(key_hash);
+        if key_locks.manager.is_held_by_current_thread(lock_id) {
+            return Ok(None);
+        }
+        let guard = match key_locks.options.locked_writes {
+            LockedKeyWrites::Wait => key_locks.manager.acquire_lock(lock_id, LockIntent::Read),
+            LockedKeyWrites::Fail => key_locks.manager.acquire_lock_with_timeout(
+                lock_id,
+                LockIntent::Read,
+                Duration::ZERO,
+            ),
+        }?;
+        Ok(Some(guard))
+    }
+
+    /// Keeps at most `max_bytes` of log pages in memory, or every page with
+    /// `None`. Once more are held, the oldest pages are dropped from memory
+    /// as the tail opens a new page and after each flush; reads and deletes
+    /// of their records go to disk. Only flushed pages are dropped, so the
+    /// log may exceed the budget until the next flush. The tail page is
+    /// always kept. Pages read back into memory by
+    /// [page tiering](Self::start_page_tiering) count toward the budget.
+    pub fn set_memory_budget(&mut self, max_bytes: Option<u64>) {
+        self.memory_budget = max_bytes;
+        self.enforce_memory_budget();
+    }
+
+    /// Drops the flushed pages below `new_head` from memory and returns the
+    /// new head address, which stops short of `new_head` at the first page
+    /// not yet flushed and at the tail page. See
+    /// [`set_memory_budget`](Self::set_memory_budget).
+    pub fn shift_head_address(&self, new_head: Address) -> Address {
+        // Every access to the log's pages is made under epoch protection.
+        unsafe { self.hlog.shift_head_address(new_head) }
+    }
+
+    fn enforce_memory_budget(&self) {
+        let Some(max_bytes) = self.memory_budget else {
+            return;
+        };
+        let admitted = self.hlog.admitted_pages().len() as u64;
+        let max_pages = (max_bytes / self.hlog.page_size)
+            .saturating_sub(admitted)
+            .max(1);
+        let tail_page = self.hlog.get_tail_address().page() as u64;
+        let head_page = self.hlog.get_head_address().page() as u64;
+        if tail_page + 1 - head_page > max_pages {
+            let new_head = Address::new((tail_page + 1 - max_pages) as u32, 0);
+            trace_event!(
+                until = new_head.control(),
+                "dropping pages over the memory budget"
+            );
+            self.shift_head_address(new_head);
+        }
+    }
+
+    /// One pass of [page tiering](Self::start_page_tiering): plans where
+    /// the log's pages go within the memory budget, flushes and drops the
+    /// log pages the plan gives up, releases pages read back earlier that
+    /// have cooled off, and reads back the hot pages the plan keeps as far
+    /// as `limiter` allows. Returns what moved.
+    fn run_page_tiering_pass(
+        &self,
+        config: &PageTieringConfig,
+        limiter: &mut ByteRateLimiter,
+    ) -> PageTieringStats {
+        let mut moved = PageTieringStats::default();
+        let Some(max_bytes) = self.memory_budget else {
+            return moved;
+        };
+        let page_size = self.hlog.page_size;
+        let begin_page = self.hlog.begin_address.load(Ordering::Acquire).page();
+        let head = self.hlog.get_head_address();
+        let tail_page = self.hlog.get_tail_address().page();
+        let hot_pages: Vec<u32> = self
+            .access_analyzer
+            .as_ref()
+            .map(|analyzer| analyzer.pages_at_least(config.min_heat))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|&page| page >= begin_page)
+            .collect();
+        let plan = plan_page_tiering(max_bytes / page_size, head.page(), tail_page, &hot_pages);
+        trace_event!(
+            head_page = plan.head_page,
+            keep = plan.keep.len(),
+            "planned page tiering pass"
+        );
+
+        if plan.head_page > head.page() {
+            let flushed = self.hlog.flushed_until_address.load(Ordering::Acquire);
+            if flushed < Address::new(plan.head_page, 0) {
+                if let Err(status) = self.flush() {
+                    log::warn!("page tiering could not flush the log: {status:?}");
+                    return moved;
+                }
+                let now_flushed = self.hlog.flushed_until_address.load(Ordering::Acquire);
+                moved.bytes_moved += now_flushed.control() - flushed.control();
+            }
+            self.shift_head_address(Address::new(plan.head_page, 0));
+        }
+        let new_head = self.hlog.get_head_address();
+        moved.pages_demoted += (new_head.page() - head.page()) as u64;
+
+        let admitted = self.hlog.admitted_pages();
+        for &page in &admitted {
+            if !plan.keep.contains(&page) && self.hlog.release_page(page) {
+                moved.pages_demoted += 1;
+            }
+        }
+        for &page in &plan.keep {
+            if admitted.contains(&page) || page >= new_head.page() {
+                continue;
+            }
+            if !limiter.take(page_size, Instant::now()) {
+                moved.pages_deferred += 1;
+                continue;
+            }
+            match self.hlog.admit_page(page) {
+                Ok(bytes) => {
+                    moved.pages_promoted += 1;
+                    moved.bytes_moved += bytes;
+                }
+                Err(status) => {
+                    log::warn!("page tiering could not read back page {page}: {status:?}")
+                }
+            }
+        }
+        moved
+    }
+
+    /// Reads write times for record headers from `clock` from now on. They
+    /// still never go below a time already handed out.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let floor_ms = self.clock.now_ms();
+        self.clock = HybridClock::with_clock(clock);
+        self.clock.observe(floor_ms);
+    }
+
+    /// Samples one in `sample_every` accesses for [`hot_keys`](Self::hot_keys);
+    /// 0 turns tracking off.
+    pub fn set_hot_key_sampling(&self, sample_every: u32) {
+        self.hot_keys.set_sample_every(sample_every);
+    }
+
+    /// Reads the header of the in-memory record at `address`.
+    pub(crate) fn record_info_at(&self, address: Address) -> Option<RecordInfo> {
+        let _guard = self.epoch.protect();
+        let buffer = self
+            .hlog
+            .get_slice(address, std::mem::size_of::<RecordInfo>());
+        if buffer.is_empty() {
+            return None;
+        }
+        Some(RecordInfo::from_bytes(buffer))
+    }
+
+    /// A header for a record appended now in front of `previous`.
+    pub(crate) fn new_record_info(&self, previous: Address, tombstone: bool) -> RecordInfo {
+        RecordInfo::new(previous, 0, false, tombstone, true).with_timestamp(self.clock.now_ms())
+    }
+
+    /// Sets the write time of the in-memory record at `address` to now and
+    /// its user flags to `user_flags`, for an update made in place.
+    fn restamp(&self, address: Address, user_flags: u8) {
+        let header = unsafe {
+            self.hlog
+                .get_mut_slice_unchecked(address, std::mem::size_of::<RecordInfo>())
+        };
+        let word = RecordInfo::extra_word(self.clock.now_ms(), user_flags);
+        header[RecordInfo::LEGACY_SIZE..].copy_from_slice(&word.to_le_bytes());
+    }
+
+    /// Calls `f` with the bytes of the value of the in-memory record at
+    /// `address`, borrowed from the log rather than copied out.
+    pub(crate) fn with_value_bytes<R>(
+        &self,
+        address: Address,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> Option<R> {
+        let _guard = self.epoch.protect();
+        let record_size = Record::<K, V>::required_size_with_alignment() as usize;
+        let buffer = self.hlog.get_slice(address, record_size);
+        let value_offset = std::mem::size_of::<RecordInfo>() + std::mem::size_of::<K>();
+        buffer
+            .get(value_offset..value_offset + std::mem::size_of::<V>())
+            .map(f)
+    }
+
+    /// Copies the header, key and value of the in-memory record at `address`.
+    pub fn record_at(&self, address: Address) -> Option<(RecordInfo, K, V)> {
+        let _guard = self.epoch.protect();
+        let record_size = Record::<K, V>::required_size_with_alignment() as usize;
+        let buffer = self.hlog.get_slice(address, record_size);
+        if buffer.is_empty() {
+            return None;
+        }
+        Some(Self::decode_record(buffer))
+    }
+
+    /// Copies the record at `address` out of memory, or reads it from disk
+    /// once its page has left memory.
+    fn load_record(&self, address: Address) -> Option<(RecordInfo, K, V)> {
+        self.record_at(address)
+            .or_else(|| self.disk_record_at(address))
+    }
+
+    /// Reads the flushed record at `address` from disk.
+    fn disk_record_at(&self, address: Address) -> Option<(RecordInfo, K, V)> {
+        trace_event!(address = address.control(), "reading record from disk");
+        let record_size = Record::<K, V>::required_size_with_alignment() as usize;
+        let mut buffer = vec![0u8; record_size];
+        self.hlog.read_from_disk(address, &mut buffer).ok()?;
+        Some(Self::decode_record(&buffer))
+    }
+
+    /// Copies the header, key and value out of the bytes of one record.
+    fn decode_record(buffer: &[u8]) -> (RecordInfo, K, V) {
+        let key_offset = std::mem::size_of::<RecordInfo>();
+        let value_offset = key_offset + std::mem::size_of::<K>();
+        let header = RecordInfo::from_bytes(&buffer[..key_offset]);
+        let record_ptr = buffer.as_ptr();
+        unsafe {
+            let key = std::ptr::read_unaligned(record_ptr.add(key_offset) as *const K);
+            // Bitwise copy of log memory; the log keeps ownership, so clone it.
+            let value = std::mem::ManuallyDrop::new(std::ptr::read_unaligned(
+                record_ptr.add(value_offset) as *const V,
+            ));
+            (header, key, V::clone(&value))
+        }
+    }
+
+    /// Walks the hash chain of `find_context` and returns the address of the
+    /// newest record for `key`, tombstones included.
+    pub fn find_latest_address(&self, find_context: &FindContext, key: &K) -> Option<Address> {
+        let _guard = self.epoch.protect();
+        let record_size = Record::<K, V>::required_size_with_alignment() as usize;
+        let mut current_address = self.chain_head(find_context);
+
+        while current_address.control() >= PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS {
+            let header = self.record_info_at(current_address)?;
+            let buffer = self.hlog.get_slice(current_address, record_size);
+            if buffer.is_empty() {
+                return None;
+            }
+            let record_key = unsafe {
+                std::ptr::read_unaligned(
+                    buffer.as_ptr().add(std::mem::size_of::<RecordInfo>()) as *const K
+                )
+            };
+            if record_key == *key && !header.invalid() {
+                return Some(current_address);
+            }
+            current_address = header.previous_address();
+        }
+        None
+    }
+
+    /// Returns up to `max_versions` values of `key`, newest first, by walking
+    /// its hash chain back from the index entry. Invalid records are skipped.
+    /// The walk stops at the first tombstone, since versions before a delete
+    /// belong to an earlier life of the key, and at `begin_address`. A
+    /// deleted or absent key has no versions.
+    ///
+    /// Versions whose pages have left memory are read from disk. Only
+    /// versions still in the log are found: updates made in place in the
+    /// mutable region overwrite their version, and
+    /// [`compact`](crate::admin::compact) keeps only the newest
+    /// [`keep_versions`](crate::admin::CompactOptions::keep_versions) of
+    /// each key.
+    pub fn read_versions(
+        &self,
+        key: &K,
+        key_hash: u64,
+        max_versions: usize,
+    ) -> Result<Vec<VersionedValue<V>>, Status> {
+        let mut find_context = FindContext::new(key_hash);
+        match self.index.find_entry(&mut find_context) {
+            Status::Ok => {}
+            Status::NotFound => return Ok(Vec::new()),
+            status => return Err(status),
+        }
+
+        let begin_address = self.hlog.begin_address.load(Ordering::Acquire);
+        let mut versions = Vec::new();
+        let mut current_address = self.chain_head(&find_context);
+        while versions.len() < max_versions
+            && current_address.control() >= PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS
+            && current_address >= begin_address
+        {
+            let Some((header, record_key, value)) = self.load_record(current_address) else {
+                return Err(Status::IoError);
+            };
+            if record_key == *key && !header.invalid() {
+                if header.tombstone() {
+                    break;
+                }
+                versions.push(VersionedValue {
+                    address: current_address,
+                    value,
+                    timestamp: header.timestamp_ms().map(system_time_from_ms),
+                    user_flags: header.user_flags(),
+                });
+            }
+            current_address = header.previous_address();
+        }
+        Ok(versions)
+    }
+
+    /// Returns every live key whose [`KeyBytes`] fall in `range`, with its
+    /// newest value, ordered by those bytes. Bytes compare lexicographically,
+    /// so an inverted or empty range finds nothing, and `start..end` leaves
+    /// out `end` while `start..=end` keeps it. Keys encoded with
+    /// [`encode_u64`](crate::keys::encode_u64) and its siblings come out in
+    /// value order.
+    ///
+    /// Every hash chain in the index is walked back to `begin_address`,
+    /// reading pages that have left memory from disk, so the cost grows
+    /// with the log rather than with the size of the range. Writes made
+    /// during the scan may or may not be seen.
+    pub fn scan_range<'r>(&self, range: impl RangeBounds<&'r [u8]>) -> Result<Vec<(K, V)>, Status>
     where
-        V: Default,
+        K: KeyBytes,
     {
+        op_span!("scan_range"; chains, results);
+        let _guard = self.epoch.protect();
+        let begin_address = self.hlog.begin_address.load(Ordering::Acquire);
+        // The newest record of each key in the range, `None` once deleted
+        let mut newest: BTreeMap<Vec<u8>, Option<(K, V)>> = BTreeMap::new();
+        let mut walked = HashSet::new();
+        loop {
+            let mut entries = Vec::new();
+            self.index.for_each_entry(|_, entry| entries.push(entry));
+            // A read cache copy evicted since the entry was loaded has put
+            // the main-log address back into the index; load it again.
+            let mut evicted = false;
+            for entry in entries {
+                let head = if entry.in_readcache() {
+                    let previous = self
+                        .read_cache
+                        .as_ref()
+                        .and_then(|cache| cache.previous_address(entry.address()));
+                    let Some(previous) = previous else {
+                        evicted = true;
+                        continue;
+                    };
+                    previous
+                } else {
+                    entry.address()
+                };
+                if !walked.insert(head.control()) {
+                    continue;
+                }
+                let mut current_address = head;
+                while current_address.control()
+                    >= PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS
+                    && current_address >= begin_address
+                {
+                    let Some((header, key, value)) = self.load_record(current_address) else {
+                        return Err(Status::IoError);
+                    };
+                    let key_bytes = key.key_bytes();
+                    if !header.invalid() && range.contains(&key_bytes) {
+                        newest
+                            .entry(key_bytes.to_vec())
+                            .or_insert_with(|| (!header.tombstone()).then_some((key, value)));
+                    }
+                    current_address = header.previous_address();
+                }
+            }
+            if !evicted {
+                break;
+            }
+        }
+        span_record!(chains = walked.len());
+        let results: Vec<(K, V)> = newest.into_values().flatten().collect();
+        span_record!(results = results.len());
+        Ok(results)
+    }
+
+    pub fn upsert(&self, context: &impl UpsertContext<Key = K, Value = V>) -> Status {
+        status_of(self.upsert_with_seq(context))
+    }
+
+    /// Same as [`upsert`](Self::upsert), returning the sequence number of the
+    /// write on success. See [`applied_seq`](Self::applied_seq).
+    pub fn upsert_with_seq(
+        &self,
+        context: &impl UpsertContext<Key = K, Value = V>,
+    ) -> Result<u64, Status> {
+        self.upsert_opts(context, &WriteOptions::default())
+    }
+
+    /// Upserts as `options` asks. Returns the sequence number of the write.
+    /// With [`Durability::Sync`], a failed flush fails the call although
+    /// the write itself is applied.
+    pub fn upsert_opts(
+        &self,
+        context: &impl UpsertContext<Key = K, Value = V>,
+        options: &WriteOptions,
+    ) -> Result<u64, Status> {
+        op_span!("upsert", key_len = size_of::<K>(); address, bytes, status);
+        record_status(|| {
+            options.check()?;
+            self.admit_write()?;
+            let seq = {
+                let _key_lock = self.lock_for_write(context.key_hash())?;
+                self.hot_keys.record(context.key_hash(), context.key());
+                self.note_access(context.key_hash(), OperationType::Write);
+                self.apply_write(context.key(), context.key_hash(), || {
+                    if let Some(combiner) = &self.write_combiner {
+                        let item = (
+                            context.key_hash(),
+                            *context.key(),
+                            context.value().clone(),
+                            options.user_flags,
+                        );
+                        return combiner.submit(item, |batch| self.append_batch(batch));
+                    }
+                    self.upsert_direct(context, options.user_flags)
+                })?
+            };
+            self.finish_write(options, seq)
+        })
+    }
+
+    /// Makes a write numbered `seq` as durable as `options` asks.
+    fn finish_write(&self, options: &WriteOptions, seq: u64) -> Result<u64, Status> {
+        if options.durability == Durability::Sync {
+            self.flush()?;
+        }
+        Ok(seq)
+    }
+
+    fn upsert_direct(
+        &self,
+        context: &impl UpsertContext<Key = K, Value = V>,
+        user_flags: u8,
+    ) -> Status {
+        let _guard = self.epoch.protect();
         let mut find_context = FindContext::new(context.key_hash());
 
         loop {
             let status = self.index.find_or_create_entry(&mut find_context);
+            if status != Status::Ok {
+                // Should not happen in current simplified MemHashIndex
+                return status;
+            }
+            let status = self.skip_read_cache(&mut find_context);
             if status != Status::Ok {
                 return status;
             }
 
-            let mut current_address = find_context.entry.address();
-            let _head_address = self.hlog.get_head_address(); // Simplified
+            let entry = find_context.entry;
+            // Use real head and read-only addresses from hlog
             let read_only_address = self.hlog.get_read_only_address();
 
-            let mut old_value_option: Option<V> = None;
-
-            // Trace back the chain to find a key match
-            loop {
-                if current_address.control() == 0 {
-                    break; // End of in-memory chain
-                }
-
-                let buffer = self.hlog.get_slice(
-                    current_address,
-                    Record::<K, V>::required_size_with_alignment() as usize,
-                );
-                if buffer.is_empty() {
-                    break; // End of in-memory chain
-                }
-                let record_ptr = buffer.as_ptr() as *const Record<K, V>;
-
-                unsafe {
-                    if Record::key(record_ptr) == context.key() {
-                        // Read header safely using unaligned access
-                        let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
-                        let header_control = u64::from_le_bytes([
-                            header_bytes[0],
-                            header_bytes[1],
-                            header_bytes[2],
-                            header_bytes[3],
-                            header_bytes[4],
-                            header_bytes[5],
-                            header_bytes[6],
-                            header_bytes[7],
-                        ]);
-                        let header = RecordInfo::from_control(header_control);
-                        if header.tombstone() {
-                            break; // Found tombstone, treat as non-existent
-                        }
-
-                        // Found a match. Try in-place update if in mutable region.
-                        if current_address >= read_only_address {
-                            let mut_buffer = self.hlog.get_mut_slice_unchecked(
-                                current_address,
-                                Record::<K, V>::required_size_with_alignment() as usize,
-                            );
-                            let mut_record_ptr = mut_buffer.as_mut_ptr() as *mut Record<K, V>;
-                            if context.rmw_atomic(Record::value_mut(mut_record_ptr)) {
-                                return Status::Ok; // In-place update successful
+            // Attempt in-place update if possible
+            if !entry.unused() && entry.address() >= read_only_address {
+                // Check key equality
+                if let Some(record) = self.hlog.get::<K, V>(entry.address()) {
+                    unsafe {
+                        let record_key = Record::<K, V>::key(record as *const Record<K, V>);
+                        // A tombstone has no value to update; write a new record instead
+                        if !record.header.tombstone()
+                            && (std::ptr::eq(record_key, context.key())
+                                || std::ptr::read_unaligned(record_key) == *context.key())
+                        {
+                            // Key matches, attempt in-place update
+                            if Record::<K, V>::update_value(
+                                record as *const Record<K, V> as *mut Record<K, V>,
+                                |value| context.put_atomic(value),
+                            ) {
+                                span_record!(address = entry.address().control());
+                                self.restamp(entry.address(), user_flags);
+                                self.note_upsert(true);
+                                self.note_page_access(entry.address());
+                                return Status::Ok;
                             }
                         }
-                        // Cannot update in-place, break to RCU path
-                        old_value_option = Some(Record::value(record_ptr).clone());
-                        break;
                     }
-                    // Read header safely using unaligned access
-                    let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
-                    let header_control = u64::from_le_bytes([
-                        header_bytes[0],
-                        header_bytes[1],
-                        header_bytes[2],
-                        header_bytes[3],
-                        header_bytes[4],
-                        header_bytes[5],
-                        header_bytes[6],
-                        header_bytes[7],
-                    ]);
-                    let header = RecordInfo::from_control(header_control);
-                    current_address = header.previous_address();
                 }
             }
 
-            // RCU Path
+            // RCU (Read-Copy-Update) path
             let record_size = Record::<K, V>::required_size_with_alignment();
-            let new_address = match self.hlog.allocate(record_size as u64) {
+            span_record!(bytes = record_size);
+            let new_address = match self.allocate_record(record_size as u64) {
                 Ok(addr) => addr,
-                Err(closed_page) => {
-                    self.hlog.new_page(closed_page);
-                    // Retry allocation after creating new page
-                    match self.hlog.allocate(record_size as u64) {
-                        Ok(addr) => addr,
-                        Err(_) => return Status::Pending, // Still failed, ask caller to retry
-                    }
-                }
+                Err(status) => return status,
             };
 
+            // 2. Get a mutable slice to the allocated memory
             let buffer = unsafe {
                 self.hlog
                     .get_mut_slice_unchecked(new_address, record_size as usize)
             };
-            let new_record_info =
-                RecordInfo::new(find_context.entry.address(), 0, false, false, true);
+            if buffer.is_empty() {
+                return Status::Pending;
+            }
 
+            // 3. Construct the new record in the allocated slice
+            let new_record_info = self
+                .new_record_info(entry.address(), false)
+                .with_user_flags(user_flags);
             unsafe {
-                let mut value_buffer = V::default();
-                match old_value_option {
-                    Some(ref old_val) => context.rmw_copy(old_val, &mut value_buffer),
-                    None => context.rmw_initial(&mut value_buffer),
-                }
-                Record::create_in(buffer, new_record_info, context.key(), &value_buffer);
+                Record::create_in(buffer, new_record_info, context.key(), context.value());
             }
+            kill_point!(BeforeIndexPublish);
 
+            // 4. CAS the hash index to point to the new record
             if self
                 .index
                 .try_update_entry(&find_context, new_address, false)
                 == Status::Ok
             {
+                span_record!(address = new_address.control());
+                self.note_upsert(false);
+                self.note_page_access(new_address);
                 return Status::Ok;
             }
+            trace_event!(address = new_address.control(), "index CAS lost, retrying");
 
-            // CAS failed, retry.
+            // Invalidate the allocated record before retrying
             unsafe {
+                let buffer = self
+                    .hlog
+                    .get_mut_slice_unchecked(new_address, record_size as usize);
                 let record_ptr = buffer.as_mut_ptr() as *mut Record<K, V>;
                 // Read header safely using unaligned access
                 let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
@@ -409,29 +1469,253 @@ where
         }
     }
 
-    pub fn delete(&self, context: &impl DeleteContext<Key = K>) -> Status
+    /// Appends a record for every `(key_hash, key, value, user_flags)` in one
+    /// log allocation and publishes them in order. Falls back to one
+    /// allocation per record when the batch does not fit in the rest of the
+    /// page.
+    fn append_batch(&self, batch: Vec<(u64, K, V, u8)>) -> Vec<Status> {
+        let Some(entries) = self.write_batch(&batch) else {
+            return batch
+                .into_iter()
+                .map(|(key_hash, key, value, user_flags)| {
+                    self.upsert_direct(
+                        &LoadUpsertContext {
+                            key_hash,
+                            key,
+                            value,
+                        },
+                        user_flags,
+                    )
+                })
+                .collect();
+        };
+        let status = self.publish_batch(&entries);
+        if status == Status::Ok {
+            for (_, address) in &entries {
+                self.note_upsert(false);
+                self.note_page_access(*address);
+            }
+        }
+        vec![status; entries.len()]
+    }
+
+    /// Writes every record of `batch` into one new log allocation without
+    /// making any of them reachable. Returns the key hash and address of
+    /// each, or `None` if the batch does not fit in the rest of the page or
+    /// of the log's capacity.
+    fn write_batch(&self, batch: &[(u64, K, V, u8)]) -> Option<Vec<(u64, Address)>> {
+        let record_size = Self::record_slot_size();
+        let size = record_size * batch.len() as u64;
+        let _guard = self.epoch.protect();
+        let region = self.allocate_record(size).ok()?;
+
+        let header = self.new_record_info(Address::from_control(0), false);
+        let entries = batch
+            .iter()
+            .enumerate()
+            .map(|(i, (key_hash, key, value, user_flags))| {
+                let address = Address::from_control(region.control() + i as u64 * record_size);
+                unsafe {
+                    let buffer = self
+                        .hlog
+                        .get_mut_slice_unchecked(address, record_size as usize);
+                    Record::create_in(buffer, header.with_user_flags(*user_flags), key, value);
+                }
+                (*key_hash, address)
+            })
+            .collect();
+        Some(entries)
+    }
+
+    /// Publishes records written by [`write_batch`](Self::write_batch) with
+    /// one batched index update. Each record is chained to the main-log
+    /// record its index entry led to just before it is swapped in; until then it
+    /// is unreachable, so rewriting its header is safe. Only the control word
+    /// is rewritten, so the write time and user flags stay.
+    fn publish_batch(&self, entries: &[(u64, Address)]) -> Status {
+        let _guard = self.epoch.protect();
+        self.index.apply_batch(entries, |address, find_context| {
+            let header = RecordInfo::new(self.chain_head(find_context), 0, false, false, true);
+            let buffer = unsafe {
+                self.hlog
+                    .get_mut_slice_unchecked(address, RecordInfo::LEGACY_SIZE)
+            };
+            buffer.copy_from_slice(&header.control().to_le_bytes());
+        })
+    }
+
+    pub fn read(&self, context: &mut impl ReadContext<Key = K, Value = V>) -> Status {
+        self.read_opts(context, &ReadOptions::default())
+    }
+
+    /// Reads as `options` asks.
+    pub fn read_opts(
+        &self,
+        context: &mut impl ReadContext<Key = K, Value = V>,
+        options: &ReadOptions,
+    ) -> Status {
+        op_span!("read", key_len = size_of::<K>(); status);
+        if let Some(seq) = options.min_seq {
+            let deadline = Instant::now() + options.wait_timeout;
+            let mut backoff = Duration::from_micros(10);
+            while self.applied_seq() < seq {
+                let now = Instant::now();
+                if now >= deadline {
+                    span_record!(status = Status::Pending);
+                    return Status::Pending;
+                }
+                std::thread::sleep(backoff.min(deadline - now));
+                backoff = (backoff * 2).min(Duration::from_millis(1));
+            }
+        }
+        if options.track_access {
+            self.hot_keys.record(context.key_hash(), context.key());
+            self.note_access(context.key_hash(), OperationType::Read);
+        }
+        let status = self.read_index(context);
+        span_record!(status = status);
+        status
+    }
+
+    /// Same as [`read`](Self::read), also returning the address, write time
+    /// and user flags of the record that was read. A deleted or absent key is
+    /// `Status::NotFound`. Like `read`, records below the in-memory log are
+    /// read from disk.
+    pub fn read_with_meta(
+        &self,
+        context: &mut impl ReadContext<Key = K, Value = V>,
+    ) -> Result<RecordMeta, Status> {
+        let mut find_context = FindContext::new(context.key_hash());
+        if self.index.find_entry(&mut find_context) != Status::Ok {
+            return Err(Status::NotFound);
+        }
+        let begin_address = self.hlog.begin_address.load(Ordering::Acquire);
+        let mut address = self.chain_head(&find_context);
+        while address.control() >= PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS
+            && address >= begin_address
+        {
+            let (header, key, value) = self.load_record(address).ok_or(Status::IoError)?;
+            if key == *context.key() && !header.invalid() {
+                if header.tombstone() {
+                    return Err(Status::NotFound);
+                }
+                context.get(&value);
+                self.note_page_access(address);
+                return Ok(RecordMeta {
+                    address,
+                    timestamp: header.timestamp_ms().map(system_time_from_ms),
+                    user_flags: header.user_flags(),
+                });
+            }
+            address = header.previous_address();
+        }
+        Err(Status::NotFound)
+    }
+
+    /// Reads every context as [`read`](Self::read) would, one status per
+    /// context in the same order. All index probes are made before any
+    /// record is read, and the chains are then walked in log address order,
+    /// so neighbouring records are read together. Records below the
+    /// in-memory log are read from disk one at a time, so no read is left
+    /// pending and there is no IO to batch yet.
+    pub fn read_batch<C>(&self, contexts: &mut [C]) -> Vec<Status>
     where
-        V: Default,
+        C: ReadContext<Key = K, Value = V>,
     {
+        let mut statuses = vec![Status::NotFound; contexts.len()];
+        let mut heads = Vec::with_capacity(contexts.len());
+        for (slot, context) in contexts.iter().enumerate() {
+            self.hot_keys.record(context.key_hash(), context.key());
+            self.note_access(context.key_hash(), OperationType::Read);
+            let mut find_context = FindContext::new(context.key_hash());
+            if self.index.find_entry(&mut find_context) == Status::Ok {
+                heads.push((find_context, slot));
+            }
+        }
+        heads.sort_unstable_by_key(|(find_context, slot)| (find_context.entry.address(), *slot));
+        for (mut find_context, slot) in heads {
+            statuses[slot] = self.read_entry(&mut contexts[slot], &mut find_context);
+        }
+        statuses
+    }
+
+    /// Finds the newest record of the context's key through the index.
+    fn read_index(&self, context: &mut impl ReadContext<Key = K, Value = V>) -> Status {
         let mut find_context = FindContext::new(context.key_hash());
         if self.index.find_entry(&mut find_context) != Status::Ok {
             return Status::NotFound;
         }
+        self.read_entry(context, &mut find_context)
+    }
 
-        let mut current_address = find_context.entry.address();
-        let _head_address = self.hlog.get_head_address(); // Simplified
-        let read_only_address = self.hlog.get_read_only_address();
+    /// Reads the newest record of the context's key behind the entry of
+    /// `find_context`, from the read cache when the entry leads there and
+    /// the copy is of the same key.
+    fn read_entry(
+        &self,
+        context: &mut impl ReadContext<Key = K, Value = V>,
+        find_context: &mut FindContext,
+    ) -> Status {
+        loop {
+            let entry = find_context.entry;
+            if !entry.in_readcache() {
+                return self.read_chain(context, entry.address(), Some(find_context));
+            }
+            let Some(cache) = &self.read_cache else {
+                return Status::UnexpectedState;
+            };
+            let copy = cache.with_copy(entry.address(), |copy| {
+                if copy.key != *context.key() {
+                    return Some(copy.previous);
+                }
+                // Tombstones are never cached.
+                context.get(&copy.value);
+                None
+            });
+            match copy {
+                Some(None) => {
+                    cache.note_hit();
+                    return Status::Ok;
+                }
+                Some(Some(previous)) => return self.read_chain(context, previous, None),
+                // Evicted since the entry was read
+                None => {
+                    if self.index.find_entry(find_context) != Status::Ok {
+                        return Status::NotFound;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the newest record of the context's key in the hash chain that
+    /// starts at `head`. A record found on disk is offered to the read cache
+    /// if `admit` holds the index entry the chain was found through.
+    fn read_chain(
+        &self,
+        context: &mut impl ReadContext<Key = K, Value = V>,
+        head: Address,
+        admit: Option<&FindContext>,
+    ) -> Status {
+        let _guard = self.epoch.protect();
+        let record_size = Record::<K, V>::required_size_with_alignment();
+        let mut current_address = head;
+        // Use tail address as the head address for now (simplified)
+        let _head_address = self.hlog.get_head_address();
 
-        // Trace back to find the specific record to invalidate or to append a tombstone after.
         loop {
             if current_address.control() == 0 {
-                return Status::NotFound; // Reached end of chain
+                // Reached end of chain
+                return Status::NotFound;
             }
 
-            let record_size = Record::<K, V>::required_size_with_alignment();
             let buffer = self.hlog.get_slice(current_address, record_size as usize);
             if buffer.is_empty() {
-                return Status::NotFound;
+                trace_event!(
+                    address = current_address.control(),
+                    "chain left the in-memory log, reading from disk"
+                );
+                return self.read_chain_from_disk(context, current_address, admit);
             }
 
             let record_ptr = buffer.as_ptr() as *const Record<K, V>;
@@ -451,31 +1735,14 @@ where
                         header_bytes[7],
                     ]);
                     let header = RecordInfo::from_control(header_control);
-
-                    // Found the record. Check if it's already a tombstone.
                     if header.tombstone() {
                         return Status::NotFound;
                     }
-
-                    // If in mutable region, invalidate in-place.
-                    if current_address >= read_only_address {
-                        let mut_buffer = self
-                            .hlog
-                            .get_mut_slice_unchecked(current_address, record_size as usize);
-                        let mut_record_ptr = mut_buffer.as_mut_ptr() as *mut Record<K, V>;
-                        // Write header safely using unaligned access
-                        let header_bytes =
-                            std::slice::from_raw_parts_mut(mut_record_ptr as *mut u8, 8);
-                        let mut new_header = header;
-                        new_header.set_tombstone(true);
-                        let new_control = new_header.control();
-                        let control_bytes = new_control.to_le_bytes();
-                        header_bytes.copy_from_slice(&control_bytes);
-                        return Status::Ok;
-                    }
-
-                    // In read-only region, break to append a tombstone.
-                    break;
+                    // The value's lifetime is tied to the log buffer. The context's
+                    // get method is responsible for copying the data out if needed.
+                    context.get(Record::value(record_ptr));
+                    self.note_page_access(current_address);
+                    return Status::Ok;
                 }
                 // Read header safely using unaligned access
                 let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
@@ -493,32 +1760,240 @@ where
                 current_address = header.previous_address();
             }
         }
+    }
+
+    /// Carries on a chain walk at `address`, below the in-memory log, by
+    /// reading each record from disk.
+    fn read_chain_from_disk(
+        &self,
+        context: &mut impl ReadContext<Key = K, Value = V>,
+        mut address: Address,
+        admit: Option<&FindContext>,
+    ) -> Status {
+        let begin_address = self.hlog.begin_address.load(Ordering::Acquire);
+        let in_log = |address: Address| {
+            address.control() >= PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS
+                && address >= begin_address
+        };
+        if !in_log(address) {
+            return Status::NotFound;
+        }
+        if let Some(cache) = &self.read_cache {
+            cache.note_miss();
+        }
+        while in_log(address) {
+            let Some((header, key, value)) = self.disk_record_at(address) else {
+                return Status::NotFound;
+            };
+            if key == *context.key() && !header.invalid() {
+                if header.tombstone() {
+                    return Status::NotFound;
+                }
+                context.get(&value);
+                self.note_page_access(address);
+                if let Some(find_context) = admit {
+                    self.admit_to_read_cache(find_context, key, value);
+                }
+                return Status::Ok;
+            }
+            address = header.previous_address();
+            // The walk goes on; start reading the next hop if it is on disk.
+            if in_log(address) && address < self.hlog.get_head_address() {
+                self.hlog.prefetch(address);
+            }
+        }
+        Status::NotFound
+    }
+
+    /// Copies a record read from disk into the read cache, in front of the
+    /// chain of `find_context`, if its key is hot enough. The copy is dropped
+    /// again if the entry has moved on since it was read.
+    fn admit_to_read_cache(&self, find_context: &FindContext, key: K, value: V) {
+        let Some(cache) = &self.read_cache else {
+            return;
+        };
+        let heat = self
+            .access_analyzer
+            .as_ref()
+            .map(|analyzer| analyzer.classify(find_context.key_hash));
+        if !cache.admits(heat) {
+            return;
+        }
+        let copy = CachedRecord {
+            key_hash: find_context.key_hash,
+            key,
+            value,
+            previous: find_context.entry.address(),
+        };
+        cache.insert(
+            copy,
+            |address, victim| self.restore_entry(address, victim),
+            |address| self.index.try_update_entry(find_context, address, true) == Status::Ok,
+        );
+    }
+
+    pub fn rmw(&self, context: &mut impl RmwContext<Key = K, Value = V>) -> Status
+    where
+        V: Default,
+    {
+        status_of(self.rmw_with_seq(context))
+    }
+
+    /// Same as [`rmw`](Self::rmw), returning the sequence number of the
+    /// write on success.
+    pub fn rmw_with_seq(
+        &self,
+        context: &mut impl RmwContext<Key = K, Value = V>,
+    ) -> Result<u64, Status>
+    where
+        V: Default,
+    {
+        self.rmw_opts(context, &WriteOptions::default())
+    }
+
+    /// Read-modify-writes as `options` asks. Returns the sequence number of
+    /// the write.
+    pub fn rmw_opts(
+        &self,
+        context: &mut impl RmwContext<Key = K, Value = V>,
+        options: &WriteOptions,
+    ) -> Result<u64, Status>
+    where
+        V: Default,
+    {
+        op_span!("rmw", key_len = size_of::<K>(); status);
+        record_status(|| {
+            options.check()?;
+            self.admit_write()?;
+            let seq = {
+                let _key_lock = self.lock_for_write(context.key_hash())?;
+                let key = *context.key();
+                self.apply_write(&key, context.key_hash(), || {
+                    self.rmw_locked(context, options.user_flags)
+                })?
+            };
+            self.finish_write(options, seq)
+        })
+    }
+
+    fn rmw_locked(
+        &self,
+        context: &mut impl RmwContext<Key = K, Value = V>,
+        user_flags: u8,
+    ) -> Status
+    where
+        V: Default,
+    {
+        let _guard = self.epoch.protect();
+        let mut find_context = FindContext::new(context.key_hash());
 
-        // Append a tombstone record (RCU path)
         loop {
-            let record_size = Record::<K, V>::required_size_with_alignment();
-            let new_address = match self.hlog.allocate(record_size as u64) {
-                Ok(addr) => addr,
-                Err(closed_page) => {
-                    self.hlog.new_page(closed_page);
-                    // Retry allocation after creating new page
-                    match self.hlog.allocate(record_size as u64) {
-                        Ok(addr) => addr,
-                        Err(_) => return Status::Pending, // Still failed, ask caller to retry
+            let status = self.index.find_or_create_entry(&mut find_context);
+            if status != Status::Ok {
+                return status;
+            }
+            let status = self.skip_read_cache(&mut find_context);
+            if status != Status::Ok {
+                return status;
+            }
+
+            let mut current_address = find_context.entry.address();
+            let _head_address = self.hlog.get_head_address(); // Simplified
+            let read_only_address = self.hlog.get_read_only_address();
+
+            let mut old_value_option: Option<V> = None;
+
+            // Trace back the chain to find a key match
+            loop {
+                if current_address.control() == 0 {
+                    break; // End of in-memory chain
+                }
+
+                let buffer = self.hlog.get_slice(
+                    current_address,
+                    Record::<K, V>::required_size_with_alignment() as usize,
+                );
+                if buffer.is_empty() {
+                    break; // End of in-memory chain
+                }
+                let record_ptr = buffer.as_ptr() as *const Record<K, V>;
+
+                unsafe {
+                    if Record::key(record_ptr) == context.key() {
+                        // Read header safely using unaligned access
+                        let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
+                        let header_control = u64::from_le_bytes([
+                            header_bytes[0],
+                            header_bytes[1],
+                            header_bytes[2],
+                            header_bytes[3],
+                            header_bytes[4],
+                            header_bytes[5],
+                            header_bytes[6],
+                            header_bytes[7],
+                        ]);
+                        let header = RecordInfo::from_control(header_control);
+                        if header.tombstone() {
+                            break; // Found tombstone, treat as non-existent
+                        }
+
+                        // Found a match. Try in-place update if in mutable region.
+                        if current_address >= read_only_address {
+                            let mut_buffer = self.hlog.get_mut_slice_unchecked(
+                                current_address,
+                                Record::<K, V>::required_size_with_alignment() as usize,
+                            );
+                            let mut_record_ptr = mut_buffer.as_mut_ptr() as *mut Record<K, V>;
+                            if Record::update_value(mut_record_ptr, |value| {
+                                context.rmw_atomic(value)
+                            }) {
+                                self.restamp(current_address, user_flags);
+                                return Status::Ok; // In-place update successful
+                            }
+                        }
+                        // Cannot update in-place, break to RCU path
+                        old_value_option = Some(Record::value(record_ptr).clone());
+                        break;
                     }
+                    // Read header safely using unaligned access
+                    let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
+                    let header_control = u64::from_le_bytes([
+                        header_bytes[0],
+                        header_bytes[1],
+                        header_bytes[2],
+                        header_bytes[3],
+                        header_bytes[4],
+                        header_bytes[5],
+                        header_bytes[6],
+                        header_bytes[7],
+                    ]);
+                    let header = RecordInfo::from_control(header_control);
+                    current_address = header.previous_address();
                 }
+            }
+
+            // RCU Path
+            let record_size = Record::<K, V>::required_size_with_alignment();
+            let new_address = match self.allocate_record(record_size as u64) {
+                Ok(addr) => addr,
+                Err(status) => return status,
             };
 
             let buffer = unsafe {
                 self.hlog
                     .get_mut_slice_unchecked(new_address, record_size as usize)
             };
-            // The new tombstone points to the same previous record as the old entry.
-            let new_record_info =
-                RecordInfo::new(find_context.entry.address(), 0, false, true, true);
+            let new_record_info = self
+                .new_record_info(find_context.entry.address(), false)
+                .with_user_flags(user_flags);
 
             unsafe {
-                Record::create_in(buffer, new_record_info, context.key(), &V::default());
+                let mut value_buffer = V::default();
+                match old_value_option {
+                    Some(ref old_val) => context.rmw_copy(old_val, &mut value_buffer),
+                    None => context.rmw_initial(&mut value_buffer),
+                }
+                Record::create_in(buffer, new_record_info, context.key(), &value_buffer);
             }
 
             if self
@@ -528,6 +2003,7 @@ where
             {
                 return Status::Ok;
             }
+            trace_event!(address = new_address.control(), "index CAS lost, retrying");
 
             // CAS failed, retry.
             unsafe {
@@ -555,77 +2031,3738 @@ where
         }
     }
 
-    pub fn checkpoint(&mut self, token: &str) -> Result<(), Status> {
-        // This is a simplified, blocking checkpoint.
-        // A full implementation would use the CPR state machine.
-
-        // 1. Orchestrate Log Checkpoint
-        let log_metadata = self.hlog.checkpoint(&mut self.disk, token)?;
-
-        // 2. Orchestrate Index Checkpoint
-        use crate::core::checkpoint::CheckpointType;
-        let mut index_metadata = IndexMetadata::new(
-            1, // version
-            self.index.size(),
-            CheckpointType::Full,
-        );
+    pub fn delete(&self, context: &impl DeleteContext<Key = K>) -> Status
+    where
+        V: Default,
+    {
+        status_of(self.delete_with_seq(context))
+    }
 
-        // Set additional metadata
-        index_metadata.log_begin_address = self.hlog.begin_address.load(Ordering::Acquire);
-        index_metadata.checkpoint_start_address = self.hlog.head_address.load(Ordering::Acquire);
-        index_metadata.update_checksum();
+    /// Same as [`delete`](Self::delete), returning the sequence number of
+    /// the write on success.
+    pub fn delete_with_seq(&self, context: &impl DeleteContext<Key = K>) -> Result<u64, Status>
+    where
+        V: Default,
+    {
+        self.delete_opts(context, &WriteOptions::default())
+    }
 
-        // 3. Write final metadata file
-        let metadata = CheckpointMetadata::new(index_metadata, log_metadata);
-        let path = self.disk.index_checkpoint_path(token);
+    /// Deletes as `options` asks. Returns the sequence number of the write.
+    pub fn delete_opts(
+        &self,
+        context: &impl DeleteContext<Key = K>,
+        options: &WriteOptions,
+    ) -> Result<u64, Status>
+    where
+        V: Default,
+    {
+        op_span!("delete", key_len = size_of::<K>(); status);
+        record_status(|| {
+            options.check()?;
+            self.admit_write()?;
+            let seq = {
+                let _key_lock = self.lock_for_write(context.key_hash())?;
+                self.apply_write(context.key(), context.key_hash(), || {
+                    self.delete_locked(context, options.user_flags)
+                })?
+            };
+            self.finish_write(options, seq)
+        })
+    }
 
-        // Ensure directory exists
-        let path_obj = std::path::Path::new(&path);
-        fs::create_dir_all(path_obj).map_err(|_| Status::IoError)?;
+    fn delete_locked(&self, context: &impl DeleteContext<Key = K>, user_flags: u8) -> Status
+    where
+        V: Default,
+    {
+        let _guard = self.epoch.protect();
+        let mut find_context = FindContext::new(context.key_hash());
+        if self.index.find_entry(&mut find_context) != Status::Ok
+            || self.skip_read_cache(&mut find_context) != Status::Ok
+        {
+            return Status::NotFound;
+        }
 
-        // Create a simple checkpoint file
-        let checkpoint_path = format!("{}checkpoint.dat", path);
-        let mut file = fs::File::create(&checkpoint_path).map_err(|_| Status::IoError)?;
+        let mut current_address = find_context.entry.address();
+        let _head_address = self.hlog.get_head_address(); // Simplified
+        let read_only_address = self.hlog.get_read_only_address();
 
-        let bytes: &[u8] = unsafe {
-            std::slice::from_raw_parts(
-                &metadata as *const _ as *const u8,
-                std::mem::size_of::<CheckpointMetadata>(),
-            )
+        // Trace back to find the specific record to invalidate or to append a tombstone after.
+        loop {
+            if current_address.control() == 0 {
+                return Status::NotFound; // Reached end of chain
+            }
+
+            let record_size = Record::<K, V>::required_size_with_alignment();
+            let buffer = self.hlog.get_slice(current_address, record_size as usize);
+            if buffer.is_empty() {
+                // The rest of the chain is no longer in memory. Like FASTER,
+                // append a tombstone without reading it back: if the key has
+                // no live record there, the tombstone is redundant but
+                // harmless.
+                break;
+            }
+
+            let record_ptr = buffer.as_ptr() as *const Record<K, V>;
+
+            unsafe {
+                if Record::key(record_ptr) == context.key() {
+                    // Read header safely using unaligned access
+                    let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
+                    let header_control = u64::from_le_bytes([
+                        header_bytes[0],
+                        header_bytes[1],
+                        header_bytes[2],
+                        header_bytes[3],
+                        header_bytes[4],
+                        header_bytes[5],
+                        header_bytes[6],
+                        header_bytes[7],
+                    ]);
+                    let header = RecordInfo::from_control(header_control);
+
+                    // Found the record. Check if it's already a tombstone.
+                    if header.tombstone() {
+                        return Status::NotFound;
+                    }
+
+                    // If in mutable region, invalidate in-place.
+                    if current_address >= read_only_address {
+                        let mut_buffer = self
+                            .hlog
+                            .get_mut_slice_unchecked(current_address, record_size as usize);
+                        let mut_record_ptr = mut_buffer.as_mut_ptr() as *mut Record<K, V>;
+                        // Write header safely using unaligned access
+                        let header_bytes =
+                            std::slice::from_raw_parts_mut(mut_record_ptr as *mut u8, 8);
+                        let mut new_header = header;
+                        new_header.set_tombstone(true);
+                        let new_control = new_header.control();
+                        let control_bytes = new_control.to_le_bytes();
+                        header_bytes.copy_from_slice(&control_bytes);
+                        self.restamp(current_address, user_flags);
+                        return Status::Ok;
+                    }
+
+                    // In read-only region, break to append a tombstone.
+                    break;
+                }
+                // Read header safely using unaligned access
+                let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
+                let header_control = u64::from_le_bytes([
+                    header_bytes[0],
+                    header_bytes[1],
+                    header_bytes[2],
+                    header_bytes[3],
+                    header_bytes[4],
+                    header_bytes[5],
+                    header_bytes[6],
+                    header_bytes[7],
+                ]);
+                let header = RecordInfo::from_control(header_control);
+                current_address = header.previous_address();
+            }
+        }
+
+        // Append a tombstone record (RCU path)
+        loop {
+            let record_size = Record::<K, V>::required_size_with_alignment();
+            let new_address = match self.allocate_record(record_size as u64) {
+                Ok(addr) => addr,
+                Err(status) => return status,
+            };
+
+            let buffer = unsafe {
+                self.hlog
+                    .get_mut_slice_unchecked(new_address, record_size as usize)
+            };
+            // The new tombstone points to the same previous record as the old entry.
+            let new_record_info = self
+                .new_record_info(find_context.entry.address(), true)
+                .with_user_flags(user_flags);
+
+            unsafe {
+                Record::create_in(buffer, new_record_info, context.key(), &V::default());
+            }
+
+            if self
+                .index
+                .try_update_entry(&find_context, new_address, false)
+                == Status::Ok
+            {
+                return Status::Ok;
+            }
+            trace_event!(address = new_address.control(), "index CAS lost, retrying");
+
+            // CAS failed, retry.
+            unsafe {
+                let record_ptr = buffer.as_mut_ptr() as *mut Record<K, V>;
+                // Read header safely using unaligned access
+                let header_bytes = std::slice::from_raw_parts(record_ptr as *const u8, 8);
+                let header_control = u64::from_le_bytes([
+                    header_bytes[0],
+                    header_bytes[1],
+                    header_bytes[2],
+                    header_bytes[3],
+                    header_bytes[4],
+                    header_bytes[5],
+                    header_bytes[6],
+                    header_bytes[7],
+                ]);
+                let mut header = RecordInfo::from_control(header_control);
+                header.set_invalid(true);
+                // Write header safely using unaligned access
+                let header_bytes = std::slice::from_raw_parts_mut(record_ptr as *mut u8, 8);
+                let new_control = header.control();
+                let control_bytes = new_control.to_le_bytes();
+                header_bytes.copy_from_slice(&control_bytes);
+            }
+        }
+    }
+
+    /// Writes all records up to the current tail to the log file and syncs
+    /// it. Records are durable once this returns.
+    pub fn flush(&self) -> Result<(), Status> {
+        op_span!("flush"; until, status);
+        record_status(|| {
+            let flushed = self.hlog.flush(true)?;
+            span_record!(until = flushed.control());
+            self.note_durable(flushed);
+            self.enforce_memory_budget();
+            Ok(())
+        })
+    }
+
+    fn note_durable(&self, until: Address) {
+        if let Some(shipper) = &self.log_shipper {
+            shipper.notify_durable(until.control());
+        }
+        self.emit_event(|| {
+            StoreEvent::Flush(FlushCompleted {
+                until_address: until.control(),
+            })
+        });
+    }
+
+    /// Replaces the lifecycle event hooks, waiting for the events queued for
+    /// the previous ones. See [`crate::events`].
+    pub fn set_hooks(&mut self, hooks: Option<EventHooks>) {
+        self.event_dispatcher = None;
+        self.event_dispatcher = hooks.map(EventDispatcher::start);
+    }
+
+    pub fn hook_stats(&self) -> Option<HookStats> {
+        self.event_dispatcher.as_ref().map(EventDispatcher::stats)
+    }
+
+    fn emit_event(&self, event: impl FnOnce() -> StoreEvent) {
+        if let Some(dispatcher) = &self.event_dispatcher {
+            dispatcher.emit(event());
+        }
+    }
+
+    pub fn shipping_stats(&self) -> Option<ShippingStats> {
+        self.log_shipper.as_ref().map(LogShipper::stats)
+    }
+
+    /// Waits until the log subscriber has acknowledged every durable record.
+    /// Returns false if `timeout` passes first or no subscriber is attached.
+    pub fn wait_for_shipping(&self, timeout: Duration) -> bool {
+        self.log_shipper
+            .as_ref()
+            .is_some_and(|shipper| shipper.wait_caught_up(timeout))
+    }
+
+    /// Upserts `records` in order, as when filling a new store from another
+    /// source. Records are written in chunks that each share one log
+    /// allocation and one batched index update. Returns the number of
+    /// records written.
+    pub fn bulk_load(
+        &self,
+        records: impl IntoIterator<Item = (K, V)>,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<u64, Status> {
+        self.bulk_load_with_flags(
+            records.into_iter().map(|(key, value)| (key, value, 0)),
+            key_hash,
+        )
+    }
+
+    /// Same as [`bulk_load`](Self::bulk_load), writing each record with the
+    /// given [`WriteOptions::user_flags`], as when carrying records over from
+    /// another store of this crate.
+    pub fn bulk_load_with_flags(
+        &self,
+        records: impl IntoIterator<Item = (K, V, u8)>,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<u64, Status> {
+        const CHUNK: usize = 1024;
+        let mut records = records
+            .into_iter()
+            .map(|(key, value, user_flags)| {
+                (
+                    key_hash(&key),
+                    key,
+                    value,
+                    user_flags & RecordInfo::USER_FLAGS_MASK,
+                )
+            })
+            .peekable();
+        let mut loaded = 0;
+        while records.peek().is_some() {
+            let chunk: Vec<(u64, K, V, u8)> = records.by_ref().take(CHUNK).collect();
+            let count = chunk.len() as u64;
+            let status = if self.secondary_indexes.is_some() {
+                chunk
+                    .into_iter()
+                    .map(|(key_hash, key, value, user_flags)| {
+                        status_of(self.apply_write(&key, key_hash, || {
+                            self.upsert_direct(
+                                &LoadUpsertContext {
+                                    key_hash,
+                                    key,
+                                    value,
+                                },
+                                user_flags,
+                            )
+                        }))
+                    })
+                    .find(|status| *status != Status::Ok)
+                    .unwrap_or(Status::Ok)
+            } else {
+                let status = match self.write_batch(&chunk) {
+                    Some(entries) => self.publish_batch(&entries),
+                    None => chunk
+                        .into_iter()
+                        .map(|(key_hash, key, value, user_flags)| {
+                            self.upsert_direct(
+                                &LoadUpsertContext {
+                                    key_hash,
+                                    key,
+                                    value,
+                                },
+                                user_flags,
+                            )
+                        })
+                        .find(|status| *status != Status::Ok)
+                        .unwrap_or(Status::Ok),
+                };
+                if status == Status::Ok {
+                    self.write_seq.fetch_add(count, Ordering::AcqRel);
+                }
+                status
+            };
+            if status != Status::Ok {
+                return Err(status);
+            }
+            loaded += count;
+        }
+        Ok(loaded)
+    }
+
+    /// Indexes every live record by the secondary key `extractor` derives
+    /// from it, and keeps the index up to date with every later write. When
+    /// the store was reopened from a checkpoint holding an index of the same
+    /// name and nothing was replayed after it, the saved contents are used;
+    /// otherwise existing records are indexed from the log. Returns the
+    /// number of records indexed. See [`crate::secondary_index`] for the
+    /// consistency guarantee.
+    pub fn create_secondary_index(
+        &mut self,
+        name: &str,
+        extractor: SecondaryKeyExtractor<K, V>,
+    ) -> Result<u64, Status> {
+        if self.secondary_indexes.as_mut().is_some_and(|indexes| {
+            indexes
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .contains(name)
+        }) {
+            return Err(Status::InvalidConfiguration);
+        }
+        let entries = match self.recovered_secondary_indexes.remove(name) {
+            Some(entries) => entries,
+            None => build_entries(extractor, self.live_records()),
         };
-        file.write_all(bytes).map_err(|_| Status::IoError)?;
+        let indexed = entries.values().map(|keys| keys.len() as u64).sum();
+        self.secondary_indexes
+            .get_or_insert_with(|| Mutex::new(SecondaryIndexes::new()))
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name, extractor, entries);
+        Ok(indexed)
+    }
 
-        Ok(())
+    /// Removes the secondary index `name`. Returns false if there is none.
+    pub fn drop_secondary_index(&mut self, name: &str) -> bool {
+        let Some(indexes) = self.secondary_indexes.as_mut() else {
+            return false;
+        };
+        let indexes = indexes.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let removed = indexes.remove(name);
+        if indexes.is_empty() {
+            self.secondary_indexes = None;
+        }
+        removed
     }
 
-    pub fn recover(
-        log_path: &str,
-        token: &str,
-    ) -> Result<RsKv<'static, K, V, FileSystemDisk>, Status> {
-        let disk = FileSystemDisk::new(log_path)?;
+    pub fn secondary_index_names(&self) -> Vec<String> {
+        self.secondary_indexes
+            .as_ref()
+            .map_or_else(Vec::new, |indexes| {
+                indexes
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .names()
+            })
+    }
 
-        // 1. Read metadata
-        let path = disk.index_checkpoint_path(token);
-        let mut meta_file =
-            fs::File::open(format!("{}checkpoint.dat", path)).map_err(|_| Status::IoError)?;
-        let mut buffer = Vec::new();
-        meta_file
-            .read_to_end(&mut buffer)
+    /// Names of the secondary indexes saved in the checkpoint this store was
+    /// reopened from that have not been created again yet.
+    pub fn recovered_secondary_index_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.recovered_secondary_indexes.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Keys of the live records whose secondary key in index `name` is
+    /// `secondary_key`, oldest indexed first. Fails with `Status::NotFound`
+    /// if there is no such index.
+    pub fn lookup_secondary(&self, name: &str, secondary_key: &[u8]) -> Result<Vec<K>, Status> {
+        let indexes = self.secondary_indexes.as_ref().ok_or(Status::NotFound)?;
+        indexes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .lookup(name, secondary_key)
+            .ok_or(Status::NotFound)
+    }
+
+    /// Runs `write` on `key` and, if it succeeds, moves the key between
+    /// secondary keys according to its value before and after, then assigns
+    /// the write its sequence number. Holding the index lock throughout keeps
+    /// the two reads and the write together.
+    fn apply_write(
+        &self,
+        key: &K,
+        key_hash: u64,
+        write: impl FnOnce() -> Status,
+    ) -> Result<u64, Status> {
+        let status = match &self.secondary_indexes {
+            None => write(),
+            Some(indexes) => {
+                let mut indexes = indexes.lock().unwrap_or_else(PoisonError::into_inner);
+                let old = self.current_value(key, key_hash);
+                let status = write();
+                if status == Status::Ok {
+                    let new = self.current_value(key, key_hash);
+                    indexes.update(key, old.as_ref(), new.as_ref());
+                }
+                status
+            }
+        };
+        if status != Status::Ok {
+            return Err(status);
+        }
+        // Numbered only once visible, so every write numbered up to the
+        // applied sequence can be read.
+        Ok(self.write_seq.fetch_add(1, Ordering::AcqRel) + 1)
+    }
+
+    /// Sequence number of the newest completed write. Every successful
+    /// upsert, read-modify-write and delete, and every record bulk loaded,
+    /// takes the next number, and all writes numbered up to this one are
+    /// visible to reads. Numbers are per store; they are saved by
+    /// checkpoints and recovered by counting the records replayed after
+    /// them. Overwrites made in place are replayed as the one record they
+    /// left, so after a crash the recovered sequence can be lower than
+    /// numbers handed out before it.
+    pub fn applied_seq(&self) -> u64 {
+        self.write_seq.load(Ordering::Acquire)
+    }
+
+    /// Reads once [`applied_seq`](Self::applied_seq) reaches `seq`, so the
+    /// read observes the write that returned `seq`. Waits at most `timeout`,
+    /// then fails with `Status::Pending` without reading.
+    pub fn read_at_least(
+        &self,
+        context: &mut impl ReadContext<Key = K, Value = V>,
+        seq: u64,
+        timeout: Duration,
+    ) -> Status {
+        let options = ReadOptions::default().min_seq(seq).wait_timeout(timeout);
+        self.read_opts(context, &options)
+    }
+
+    fn current_value(&self, key: &K, key_hash: u64) -> Option<V> {
+        let mut context = ValueOf {
+            key: *key,
+            key_hash,
+            value: None,
+        };
+        self.read_opts(&mut context, &ReadOptions::default().track_access(false));
+        context.value
+    }
+
+    /// Newest value of every live key, found by walking the log.
+    fn live_records(&self) -> Vec<(K, V)> {
+        let mut newest = NewestRecords::new();
+        self.for_each_log_record(|header, key, value| {
+            newest.insert(key_bytes_hash(&key), header, key, value)
+        });
+        newest
+            .into_live()
+            .0
+            .into_iter()
+            .map(|(key, value, _)| (key, value))
+            .collect()
+    }
+
+    /// Calls `f` with every valid record from the begin address to the tail,
+    /// oldest first. Tombstones are included. Records on pages that have
+    /// left memory are read from disk.
+    pub(crate) fn for_each_log_record(&self, mut f: impl FnMut(RecordInfo, K, V)) {
+        let record_size = Self::record_slot_size();
+        let tail = self.hlog.get_tail_address();
+        let first = self
+            .hlog
+            .begin_address
+            .load(Ordering::Acquire)
+            .control()
+            .max(PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS);
+        let mut address = Address::from_control(first);
+        while address < tail {
+            if address.offset() as u64 + record_size > self.hlog.page_size {
+                address = Address::new(address.page() + 1, 0);
+                continue;
+            }
+            if let Some((header, key, value)) = self.load_record(address)
+                && header.control() != 0
+                && !header.invalid()
+            {
+                f(header, key, value);
+            }
+            // A record ending at the page boundary carries into the next page.
+            address = Address::from_control(address.control() + record_size);
+        }
+    }
+
+    /// Keeps the write clock at or above the newest write time of the
+    /// in-memory records from `from` to the tail, so that records written
+    /// after a reopen are never stamped earlier than those before it.
+    fn seed_clock(&self, from: Address) {
+        let record_size = Self::record_slot_size();
+        let tail = self.hlog.get_tail_address();
+        let first = from
+            .control()
+            .max(self.hlog.begin_address.load(Ordering::Acquire).control())
+            .max(PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS);
+        let mut address = Address::from_control(first);
+        while address < tail {
+            if address.offset() as u64 + record_size > self.hlog.page_size {
+                address = Address::new(address.page() + 1, 0);
+                continue;
+            }
+            if let Some(timestamp) = self
+                .record_info_at(address)
+                .and_then(|header| header.timestamp_ms())
+            {
+                self.clock.observe(timestamp);
+            }
+            // A record ending at the page boundary carries into the next page.
+            address = Address::from_control(address.control() + record_size);
+        }
+    }
+
+    /// Bytes between consecutive records in the log.
+    fn record_slot_size() -> u64 {
+        (Record::<K, V>::required_size_with_alignment() as u64).div_ceil(8) * 8
+    }
+
+    /// Reloads the on-disk log and points the index at the newest record of
+    /// every key, in log order. `key_hash` must match the hash the store was
+    /// written with. Returns the number of records replayed.
+    pub fn rebuild_index_from_log(&self, key_hash: impl Fn(&K) -> u64) -> Result<u64, Status> {
+        self.hlog.load_from_disk()?;
+        self.replay_log_from(
+            Address::from_control(PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS),
+            key_hash,
+        )
+    }
+
+    /// Applies every record between `from` and the end of the on-disk log to
+    /// the index, in log order, so the newest record of each key wins. The log
+    /// must already be loaded. The tail is set just past the last record found.
+    /// Returns the number of records replayed.
+    pub fn replay_log_from(
+        &self,
+        from: Address,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<u64, Status> {
+        let file_size = self.hlog.disk_log_size();
+        let page_size = self.hlog.page_size;
+        let record_size = Self::record_slot_size();
+        let first_address = PersistentMemoryMalloc::<D>::K_FIRST_VALID_ADDRESS;
+        let from = if from.control() < first_address {
+            Address::from_control(first_address)
+        } else {
+            from
+        };
+
+        let mut tail = from;
+        let mut replayed = 0;
+        let mut page = from.page();
+        while (page as u64) * page_size < file_size {
+            let page_len = (file_size - page as u64 * page_size).min(page_size);
+            let mut offset = if page == from.page() {
+                from.offset() as u64
+            } else {
+                0
+            };
+            while offset + record_size <= page_len {
+                let address = Address::new(page, offset as u32);
+                offset += record_size;
+
+                // Slots that were never written are zero; every record has
+                // its final bit set.
+                let Some((header, key, _)) = self.record_at(address) else {
+                    continue;
+                };
+                if header.control() == 0 {
+                    continue;
+                }
+                tail = Address::new(page, offset as u32);
+                if header.invalid() {
+                    continue;
+                }
+
+                let mut find_context = FindContext::new(key_hash(&key));
+                self.index.find_or_create_entry(&mut find_context);
+                let status = self.index.try_update_entry(&find_context, address, false);
+                if status != Status::Ok {
+                    return Err(status);
+                }
+                replayed += 1;
+            }
+            page += 1;
+        }
+
+        self.hlog.restore_tail(tail);
+        Ok(replayed)
+    }
+}
+
+impl<K, V, D> RsKv<'static, K, V, D>
+where
+    K: Sized + Copy + Send + Sync + 'static + PartialEq,
+    V: Sized + Clone + Send + Sync + 'static + Default,
+    D: Disk + Clone + Send + Sync + 'static,
+{
+    /// Starts a background task that moves whole log pages in and out of
+    /// memory by heat every `config.interval`, within the
+    /// [memory budget](Self::set_memory_budget). Each pass gives up the
+    /// oldest pages of the in-memory log, flushing them first, so that the
+    /// pages the attached access analyzer finds at least `config.min_heat`
+    /// can be read back from disk into the room freed. Read-back pages that
+    /// cool off are released again. Without a budget a pass does nothing,
+    /// and without an analyzer no page is read back. The task holds only a
+    /// weak reference and exits once the store is dropped.
+    pub fn start_page_tiering(self: &Arc<Self>, config: PageTieringConfig) -> PageTiering {
+        let store = Arc::downgrade(self);
+        let mut limiter = ByteRateLimiter::new(config.max_bytes_per_sec, Instant::now());
+        PageTiering::start(config.interval, move || {
+            let kv = store.upgrade()?;
+            Some(kv.run_page_tiering_pass(&config, &mut limiter))
+        })
+    }
+}
+
+impl<'epoch, K, V> RsKv<'epoch, K, V, FileSystemDisk>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+{
+    pub fn checkpoint(&mut self, token: &str) -> Result<(), Status> {
+        op_span!("checkpoint", token = token; status);
+        record_status(|| {
+            // This is a simplified, blocking checkpoint.
+            // A full implementation would use the CPR state machine.
+
+            // 1. Orchestrate Log Checkpoint
+            let log_metadata = {
+                op_span!("checkpoint_log");
+                self.hlog.checkpoint(&mut self.disk, token)?
+            };
+            self.note_durable(log_metadata.final_address);
+
+            // 2. Orchestrate Index Checkpoint. Read cache addresses mean nothing
+            // after recovery, so the cache is emptied first.
+            use crate::core::checkpoint::CheckpointType;
+            self.drain_read_cache();
+            let table_metadata = {
+                op_span!("checkpoint_index");
+                self.index.checkpoint(&mut self.disk, token)?
+            };
+            let mut index_metadata = IndexMetadata::new(
+                1, // version
+                table_metadata.table_size,
+                CheckpointType::Full,
+            );
+            index_metadata.num_ht_bytes = table_metadata.num_ht_bytes;
+            index_metadata.num_ofb_bytes = table_metadata.num_ofb_bytes;
+            index_metadata.ofb_count = table_metadata.ofb_count;
+
+            // Set additional metadata
+            index_metadata.log_begin_address = self.hlog.begin_address.load(Ordering::Acquire);
+            index_metadata.checkpoint_start_address = log_metadata.final_address;
+            index_metadata.update_checksum();
+
+            kill_point!(BetweenCheckpointWrites);
+
+            // 3. Write final metadata file
+            let metadata = CheckpointMetadata::new(index_metadata, log_metadata);
+            let path = self.disk.index_checkpoint_path(token);
+
+            // Ensure directory exists
+            let path_obj = std::path::Path::new(&path);
+            fs::create_dir_all(path_obj).map_err(|_| Status::IoError)?;
+
+            // Write the metadata under a temporary name and rename it into place,
+            // so a checkpoint is either complete or absent.
+            let bytes: &[u8] = unsafe {
+                std::slice::from_raw_parts(
+                    &metadata as *const _ as *const u8,
+                    std::mem::size_of::<CheckpointMetadata>(),
+                )
+            };
+            let token_dir = format!("index-checkpoints/{}", token);
+            let cursor = self
+                .log_shipper
+                .as_ref()
+                .map(|shipper| shipper.stats().acked_until.to_le_bytes());
+            self.write_checkpoint_file(
+                &token_dir,
+                SHIPPING_CURSOR_FILE,
+                cursor.as_ref().map(|c| &c[..]),
+            )?;
+            let secondary = self.secondary_indexes.as_mut().map(|indexes| {
+                indexes
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .encode()
+            });
+            self.write_checkpoint_file(&token_dir, SECONDARY_INDEX_FILE, secondary.as_deref())?;
+            let seq = self.applied_seq().to_le_bytes();
+            self.write_checkpoint_file(&token_dir, WRITE_SEQ_FILE, Some(&seq))?;
+            let mut file = self
+                .disk
+                .new_file(&format!("{}/checkpoint.dat.tmp", token_dir));
+            file.open(FileCreateDisposition::CreateOrTruncate, Default::default())?;
+            file.write(0, bytes)?;
+            self.disk.sync_file(&file)?;
+            file.close()?;
+            fs::rename(
+                format!("{}checkpoint.dat.tmp", path),
+                format!("{}checkpoint.dat", path),
+            )
             .map_err(|_| Status::IoError)?;
-        let metadata: &CheckpointMetadata =
-            unsafe { &*(buffer.as_ptr() as *const CheckpointMetadata) };
 
-        // 2. Create a new RsKv instance
-        let table_size = metadata.index_metadata.table_size;
-        let log_size = 1 << 30; // Simplified: 1GB. Should be stored in metadata.
-        let mut kv = RsKv::<K, V, FileSystemDisk>::new(log_size, table_size, disk)?;
+            // The rename and the token directory itself are only durable once
+            // their parent directories are synced.
+            self.disk.sync_directory(&token_dir)?;
+            self.disk.sync_directory("index-checkpoints")?;
 
-        // 3. Recover components
-        kv.index
-            .recover(&mut kv.disk, token, &metadata.index_metadata)?;
-        kv.hlog
-            .recover(&mut kv.disk, token, &metadata.log_metadata)?;
+            self.emit_event(|| {
+                StoreEvent::Checkpoint(CheckpointCompleted {
+                    id: token.to_string(),
+                    metadata,
+                })
+            });
+            Ok(())
+        })
+    }
 
-        Ok(kv)
+    /// Ships every durable record from `options.start_address` on to
+    /// `subscriber`, and every record made durable from now on, replacing
+    /// any subscriber attached before. See [`crate::replication`].
+    pub fn attach_log_subscriber(
+        &mut self,
+        subscriber: Arc<dyn LogSubscriber<K, V>>,
+        options: ShippingOptions,
+    ) {
+        self.log_shipper = None;
+        let durable_until = self.hlog.flushed_until_address.load(Ordering::Acquire);
+        self.log_shipper = Some(LogShipper::start(
+            format!("{}/hlog.log", self.disk.root_path()),
+            durable_until.control(),
+            subscriber,
+            options,
+        ));
+    }
+
+    /// Stops log shipping. Returns the address the subscriber acknowledged
+    /// every record up to, or `None` if no subscriber was attached.
+    pub fn detach_log_subscriber(&mut self) -> Option<u64> {
+        self.log_shipper
+            .take()
+            .map(|shipper| shipper.stats().acked_until)
+    }
+
+    /// Address the log subscriber had acknowledged every record up to when
+    /// the checkpoint `token` was taken, to resume shipping from with
+    /// [`ShippingOptions::start_address`]. `None` if no subscriber was
+    /// attached then.
+    pub fn shipping_cursor(&self, token: &str) -> Result<Option<u64>, Status> {
+        self.read_checkpoint_u64(token, SHIPPING_CURSOR_FILE)
+    }
+
+    /// Reads the number saved as `name` in the checkpoint `token`, if any.
+    fn read_checkpoint_u64(&self, token: &str, name: &str) -> Result<Option<u64>, Status> {
+        let path = format!("{}{}", self.disk.index_checkpoint_path(token), name);
+        match fs::read(path) {
+            Ok(bytes) => bytes
+                .try_into()
+                .map(|bytes| Some(u64::from_le_bytes(bytes)))
+                .map_err(|_| Status::InvalidDataFormat),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(_) => Err(Status::IoError),
+        }
+    }
+
+    /// Writes `bytes` as `name` in the checkpoint directory `token_dir`, or
+    /// removes a stale `name` left there by an earlier checkpoint when `None`.
+    fn write_checkpoint_file(
+        &self,
+        token_dir: &str,
+        name: &str,
+        bytes: Option<&[u8]>,
+    ) -> Result<(), Status> {
+        let name = format!("{}/{}", token_dir, name);
+        let Some(bytes) = bytes else {
+            let _ = fs::remove_file(format!("{}/{}", self.disk.root_path(), name));
+            return Ok(());
+        };
+        let mut file = self.disk.new_file(&name);
+        file.open(FileCreateDisposition::CreateOrTruncate, Default::default())?;
+        file.write(0, bytes)?;
+        self.disk.sync_file(&file)?;
+        file.close()
+    }
+
+    /// Saved secondary index contents of the checkpoint `token`. A missing or
+    /// unreadable file yields none, so the indexes are rebuilt from the log.
+    fn read_secondary_indexes(&self, token: &str) -> HashMap<String, SecondaryEntries<K>> {
+        let path = format!(
+            "{}{}",
+            self.disk.index_checkpoint_path(token),
+            SECONDARY_INDEX_FILE
+        );
+        let Ok(bytes) = fs::read(path) else {
+            return HashMap::new();
+        };
+        decode_entries(&bytes).unwrap_or_else(|status| {
+            log::warn!(
+                "secondary indexes of checkpoint {} are not usable: {:?}",
+                token,
+                status
+            );
+            HashMap::new()
+        })
+    }
+
+    fn read_checkpoint_metadata(
+        disk: &FileSystemDisk,
+        token: &str,
+    ) -> Result<CheckpointMetadata, Status> {
+        CheckpointMetadata::read(&disk.index_checkpoint_path(token))
+    }
+
+    /// Checks that the checkpoint `token` would load into this store: its
+    /// metadata reads and checksums, it belongs to this log, the log still
+    /// covers it and the index files have the recorded sizes.
+    fn check_checkpoint(&self, token: &str) -> Result<(), Status> {
+        let metadata = Self::read_checkpoint_metadata(&self.disk, token)?;
+        if !metadata.index_metadata.validate_checksum()
+            || !metadata.log_metadata.validate_checksum()
+        {
+            return Err(Status::ChecksumMismatch);
+        }
+        if metadata.log_metadata.store_uuid != self.hlog.superblock.store_uuid {
+            return Err(Status::InvalidDataFormat);
+        }
+        if metadata.log_metadata.final_address.control() > self.hlog.disk_log_size() {
+            return Err(Status::Corruption);
+        }
+        let path = self.disk.index_checkpoint_path(token);
+        let file_len = |name: &str| {
+            fs::metadata(format!("{}{}", path, name))
+                .map(|metadata| metadata.len())
+                .map_err(|_| Status::IoError)
+        };
+        if file_len("ht.dat")? != metadata.index_metadata.num_ht_bytes
+            || file_len("ofb.dat")? != metadata.index_metadata.num_ofb_bytes
+        {
+            return Err(Status::Corruption);
+        }
+        Ok(())
+    }
+
+    /// Token of the most recently written checkpoint, if any.
+    fn newest_checkpoint(&self) -> Option<String> {
+        let dir = format!("{}/index-checkpoints", self.disk.root_path());
+        fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let modified = fs::metadata(entry.path().join("checkpoint.dat"))
+                    .and_then(|metadata| metadata.modified())
+                    .ok()?;
+                Some((modified, entry.file_name().to_string_lossy().into_owned()))
+            })
+            .max()
+            .map(|(_, token)| token)
+    }
+
+    /// Cross-checks the index, the log and the newest checkpoint, and reports
+    /// what is wrong without changing anything. Safe to run alongside normal
+    /// traffic: index entries are read under epoch protection, so an entry
+    /// updated mid-scan is checked against whichever record it pointed to.
+    /// `key_hash` must match the hash the store is written with.
+    pub fn verify(
+        &self,
+        level: VerifyLevel,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<VerifyReport, Status> {
+        let mut report = VerifyReport::default();
+        let sample_every = match level {
+            VerifyLevel::Quick => None,
+            VerifyLevel::Sampled(every) => Some(every.max(1)),
+            VerifyLevel::Full => Some(1),
+        };
+
+        // Region invariants. Addresses are loaded one by one, tail last, so
+        // concurrent progress can only make the tail look larger.
+        let begin = self.hlog.begin_address.load(Ordering::Acquire);
+        let head = self.hlog.get_head_address();
+        let flushed = self.hlog.flushed_until_address.load(Ordering::Acquire);
+        let read_only = self.hlog.get_read_only_address();
+        let tail = self.hlog.get_tail_address();
+        for (lower, upper, rule) in [
+            (begin, head, "begin <= head"),
+            (head, read_only, "head <= read_only"),
+            (read_only, tail, "read_only <= tail"),
+            (flushed, read_only, "flushed <= read_only"),
+        ] {
+            if lower > upper {
+                report.findings.push(VerifyFinding::RegionOrder {
+                    rule,
+                    lower: lower.control(),
+                    upper: upper.control(),
+                });
+            }
+        }
+
+        if let Some(every) = sample_every {
+            // Index buckets are well formed, and their entries resolve to a
+            // record whose key hashes to them.
+            let _guard = self.epoch.protect();
+            report.findings.extend(
+                self.index
+                    .check_invariants()
+                    .violations
+                    .into_iter()
+                    .map(VerifyFinding::IndexInvariant),
+            );
+            let table_size = self.index.size();
+            let mut seen = 0u64;
+            self.index.for_each_entry(|bucket, entry| {
+                seen += 1;
+                if !(seen - 1).is_multiple_of(every as u64) {
+                    return;
+                }
+                report.index_entries_checked += 1;
+                if entry.in_readcache() {
+                    // A cached copy stands for its chain; check its key
+                    // instead, unless it was evicted meanwhile.
+                    let copy_hash = self
+                        .read_cache
+                        .as_ref()
+                        .and_then(|cache| cache.with_copy(entry.address(), |copy| copy.key_hash));
+                    if let Some(copy_hash) = copy_hash {
+                        let hash = HotLogKeyHash::new(copy_hash);
+                        if hash.table_index(table_size) != bucket || hash.tag() != entry.tag() {
+                            report
+                                .findings
+                                .push(VerifyFinding::IndexKeyMismatch(entry.address().control()));
+                        }
+                    }
+                    return;
+                }
+                let address = entry.address();
+                let record = if address >= begin && address < self.hlog.get_tail_address() {
+                    self.record_at(address)
+                } else {
+                    None
+                };
+                match record {
+                    Some((header, key, _)) if header.control() != 0 => {
+                        let hash = HotLogKeyHash::new(key_hash(&key));
+                        if hash.table_index(table_size) != bucket || hash.tag() != entry.tag() {
+                            report
+                                .findings
+                                .push(VerifyFinding::IndexKeyMismatch(address.control()));
+                        }
+                    }
+                    _ => report
+                        .findings
+                        .push(VerifyFinding::DanglingIndexEntry(address.control())),
+                }
+            });
+
+            // Flushed records still match their frame CRCs.
+            let (checked, failed) = self.hlog.verify_frames(every)?;
+            report.frames_checked = checked;
+            report.findings.extend(
+                failed
+                    .into_iter()
+                    .map(|(begin, end)| VerifyFinding::FrameCrcMismatch { begin, end }),
+            );
+        }
+
+        if let Some(token) = self.newest_checkpoint() {
+            if let Err(status) = self.check_checkpoint(&token) {
+                report.findings.push(VerifyFinding::CheckpointUnloadable {
+                    token: token.clone(),
+                    status,
+                });
+            }
+            report.checkpoint_checked = Some(token);
+        }
+
+        if !report.is_clean() {
+            log::warn!("verify found {} problems", report.findings.len());
+        }
+        Ok(report)
+    }
+
+    pub fn recover(
+        log_path: &str,
+        token: &str,
+    ) -> Result<RsKv<'static, K, V, FileSystemDisk>, Status> {
+        op_span!("recovery", token = token; status);
+        record_status(|| Self::recover_with_report(log_path, token, &mut RecoveryReport::default()))
+    }
+
+    fn recover_with_report(
+        log_path: &str,
+        token: &str,
+        report: &mut RecoveryReport,
+    ) -> Result<RsKv<'static, K, V, FileSystemDisk>, Status> {
+        let disk = FileSystemDisk::new(log_path)?;
+
+        // 1. Read metadata
+        let started = Instant::now();
+        let metadata = Self::read_checkpoint_metadata(&disk, token)?;
+
+        // 2. Create a new RsKv instance
+        let table_size = metadata.index_metadata.table_size;
+        let log_size = 1 << 30; // Simplified: 1GB. Should be stored in metadata.
+        let mut kv = RsKv::<K, V, FileSystemDisk>::new(log_size, table_size, disk)?;
+        if metadata.log_metadata.store_uuid != kv.hlog.superblock.store_uuid {
+            log::error!(
+                "checkpoint {} was taken from a different store than the log in {}",
+                token,
+                log_path
+            );
+            return Err(Status::InvalidDataFormat);
+        }
+
+        // 3. Recover components
+        kv.index
+            .recover(&mut kv.disk, token, &metadata.index_metadata)?;
+        report.index_entries_loaded = kv.index.entry_count();
+        report.finish_phase(RecoveryPhase::LoadCheckpoint, started);
+
+        let started = Instant::now();
+        let loaded = kv
+            .hlog
+            .recover(&mut kv.disk, token, &metadata.log_metadata)?;
+        report.torn_records_skipped = loaded.torn_bytes.div_ceil(Self::record_slot_size());
+        kv.seed_clock(Address::from_control(0));
+        report.finish_phase(RecoveryPhase::LoadLog, started);
+
+        // Checkpoints written before sequence numbers existed start from 0.
+        let seq = kv.read_checkpoint_u64(token, WRITE_SEQ_FILE)?.unwrap_or(0);
+        kv.write_seq.store(seq, Ordering::Release);
+
+        report.checkpoint_id = Some(token.to_string());
+        Ok(kv)
+    }
+
+    /// Reopens the store at `log_path`, trying the checkpoint `token` first
+    /// and replaying any records flushed after it. Without a usable checkpoint, a non-empty log is rebuilt by a full scan
+    /// when `options.rebuild_index_from_log` is set and rejected with
+    /// `Status::NotFound` otherwise. An empty log yields an empty store.
+    pub fn open(
+        log_path: &str,
+        token: Option<&str>,
+        options: &RecoveryOptions,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<RsKv<'static, K, V, FileSystemDisk>, Status> {
+        Self::open_with_report(log_path, token, options, key_hash).map(|(kv, _)| kv)
+    }
+
+    /// Same as [`RsKv::open`], also returning what recovery did and how long
+    /// each phase took. Phase boundaries are logged as they are reached.
+    pub fn open_with_report(
+        log_path: &str,
+        token: Option<&str>,
+        options: &RecoveryOptions,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<(RsKv<'static, K, V, FileSystemDisk>, RecoveryReport), Status> {
+        op_span!("recovery", token = token.unwrap_or(""); status);
+        record_status(|| {
+            let mut report = RecoveryReport::default();
+            if let Some(token) = token {
+                match RsKv::<K, V, FileSystemDisk>::recover_with_report(
+                    log_path,
+                    token,
+                    &mut report,
+                ) {
+                    Ok(mut kv) => {
+                        // Records flushed after the checkpoint are durable too.
+                        let from = kv.hlog.get_tail_address();
+                        let started = Instant::now();
+                        report.records_replayed = kv.replay_log_from(from, key_hash)?;
+                        kv.seed_clock(from);
+                        kv.write_seq
+                            .fetch_add(report.records_replayed, Ordering::AcqRel);
+                        // Saved secondary indexes only match the log as of the
+                        // checkpoint.
+                        if report.records_replayed == 0 {
+                            kv.recovered_secondary_indexes = kv.read_secondary_indexes(token);
+                        }
+                        report.log_bytes_replayed =
+                            kv.hlog.get_tail_address().control() - from.control();
+                        report.finish_phase(RecoveryPhase::ReplayLog, started);
+                        log::info!(
+                            "recovered checkpoint {} and replayed {} later log records",
+                            token,
+                            report.records_replayed
+                        );
+                        return Ok((kv, report));
+                    }
+                    Err(status) => {
+                        log::warn!("checkpoint {} is not usable: {:?}", token, status);
+                        trace_event!(?status, "checkpoint not usable, falling back to the log");
+                        report = RecoveryReport::default();
+                    }
+                }
+            }
+
+            let disk = FileSystemDisk::new(log_path)?;
+            let log_size = disk.log_size();
+            if log_size > 0 && !options.rebuild_index_from_log {
+                return Err(Status::NotFound);
+            }
+
+            let kv = RsKv::<K, V, FileSystemDisk>::new(options.log_size, options.table_size, disk)?;
+            if log_size > 0 {
+                let started = Instant::now();
+                let loaded = kv.hlog.load_from_disk()?;
+                report.torn_records_skipped = loaded.torn_bytes.div_ceil(Self::record_slot_size());
+                report.finish_phase(RecoveryPhase::LoadLog, started);
+
+                let started = Instant::now();
+                let from = Address::from_control(
+                    PersistentMemoryMalloc::<FileSystemDisk>::K_FIRST_VALID_ADDRESS,
+                );
+                report.records_replayed = kv.replay_log_from(from, key_hash)?;
+                kv.seed_clock(from);
+                kv.write_seq
+                    .store(report.records_replayed, Ordering::Release);
+                report.log_bytes_replayed = kv.hlog.get_tail_address().control() - from.control();
+                report.finish_phase(RecoveryPhase::ReplayLog, started);
+                log::info!(
+                    "rebuilt index from {} log records ({} bytes)",
+                    report.records_replayed,
+                    log_size
+                );
+            }
+            Ok((kv, report))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::numa::PagePlacement;
+    use crate::keys::{decode_u64, encode_u64};
+    use crate::performance::access_analyzer::{AnalyzerConfig, Heat};
+    use crate::performance::batch_optimizer::WriteCombinerConfig;
+    use crate::performance::migration_manager::MutableRegionConfig;
+    use crate::testing::clock::MockClock;
+    use crate::testing::temp_dir::TempDir;
+
+    struct TestUpsertContext {
+        key: u64,
+        value: u64,
+    }
+
+    impl UpsertContext for TestUpsertContext {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn value(&self) -> &Self::Value {
+            &self.value
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+            false
+        }
+    }
+
+    struct TestReadContext {
+        key: u64,
+        value: Option<u64>,
+    }
+
+    impl ReadContext for TestReadContext {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn get(&mut self, value: &Self::Value) {
+            self.value = Some(*value);
+        }
+    }
+
+    struct TestDeleteContext {
+        key: u64,
+    }
+
+    impl DeleteContext for TestDeleteContext {
+        type Key = u64;
+
+        fn key(&self) -> &Self::Key {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+    }
+
+    fn read_value(kv: &RsKv<'_, u64, u64, FileSystemDisk>, key: u64) -> Option<u64> {
+        let mut context = TestReadContext { key, value: None };
+        match kv.read(&mut context) {
+            Status::Ok => context.value,
+            _ => None,
+        }
+    }
+
+    fn test_options() -> RecoveryOptions {
+        RecoveryOptions {
+            log_size: 1 << 26,
+            table_size: 1 << 10,
+            rebuild_index_from_log: true,
+        }
+    }
+
+    #[test]
+    fn test_rebuild_index_without_checkpoint() {
+        let temp = TempDir::new("rebuild");
+        let dir = temp.path();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            for key in 1..=100 {
+                assert_eq!(
+                    kv.upsert(&TestUpsertContext { key, value: key }),
+                    Status::Ok
+                );
+            }
+            for key in 1..=10 {
+                let context = TestUpsertContext {
+                    key,
+                    value: key + 1000,
+                };
+                assert_eq!(kv.upsert(&context), Status::Ok);
+            }
+            assert_eq!(kv.delete(&TestDeleteContext { key: 50 }), Status::Ok);
+            kv.flush().unwrap();
+            kv.checkpoint("lost").unwrap();
+        }
+        fs::remove_dir_all(format!("{}/index-checkpoints", dir)).unwrap();
+
+        // Without the fallback the orphaned log is refused.
+        let options = RecoveryOptions {
+            rebuild_index_from_log: false,
+            ..test_options()
+        };
+        assert!(
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, Some("lost"), &options, |key| *key)
+                .is_err()
+        );
+
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, Some("lost"), &test_options(), |key| *key)
+                .unwrap();
+        for key in 1..=100 {
+            let expected = match key {
+                1..=10 => Some(key + 1000),
+                50 => None,
+                _ => Some(key),
+            };
+            assert_eq!(read_value(&kv, key), expected, "key {}", key);
+        }
+
+        // New writes land after the recovered tail and survive another rebuild.
+        assert_eq!(
+            kv.upsert(&TestUpsertContext {
+                key: 101,
+                value: 101
+            }),
+            Status::Ok
+        );
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 1 }),
+            Status::Ok
+        );
+        kv.flush().unwrap();
+        drop(kv);
+
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, None, &test_options(), |key| *key).unwrap();
+        assert_eq!(read_value(&kv, 101), Some(101));
+        assert_eq!(read_value(&kv, 1), Some(1));
+        assert_eq!(read_value(&kv, 2), Some(1002));
+    }
+
+    #[test]
+    fn test_open_empty_log() {
+        let temp = TempDir::new("empty");
+        let dir = temp.path();
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, None, &test_options(), |key| *key).unwrap();
+        assert_eq!(read_value(&kv, 1), None);
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 7 }),
+            Status::Ok
+        );
+        assert_eq!(read_value(&kv, 1), Some(7));
+    }
+
+    #[test]
+    fn test_recover_replays_log_after_checkpoint() {
+        let temp = TempDir::new("replay");
+        let dir = temp.path();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            for key in 1..=50 {
+                assert_eq!(
+                    kv.upsert(&TestUpsertContext { key, value: key }),
+                    Status::Ok
+                );
+            }
+            kv.checkpoint("cp").unwrap();
+
+            for key in 40..=80 {
+                let context = TestUpsertContext {
+                    key,
+                    value: key + 1000,
+                };
+                assert_eq!(kv.upsert(&context), Status::Ok);
+            }
+            assert_eq!(kv.delete(&TestDeleteContext { key: 1 }), Status::Ok);
+            kv.flush().unwrap();
+
+            // Never flushed, so lost in the crash.
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 99, value: 99 }),
+                Status::Ok
+            );
+        }
+
+        // The checkpoint alone restores checkpoint-time state.
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "cp").unwrap();
+        assert_eq!(read_value(&kv, 1), Some(1));
+        assert_eq!(read_value(&kv, 45), Some(45));
+        assert_eq!(read_value(&kv, 60), None);
+        drop(kv);
+
+        let kv = RsKv::<u64, u64, FileSystemDisk>::open(
+            dir,
+            Some("cp"),
+            &RecoveryOptions::default(),
+            |key| *key,
+        )
+        .unwrap();
+        for key in 2..=80 {
+            let expected = if key >= 40 { key + 1000 } else { key };
+            assert_eq!(read_value(&kv, key), Some(expected), "key {}", key);
+        }
+        assert_eq!(read_value(&kv, 1), None);
+        assert_eq!(read_value(&kv, 99), None);
+    }
+
+    #[test]
+    fn test_checkpoint_restores_overflow_buckets() {
+        let temp = TempDir::new("overflow");
+        let dir = temp.path();
+        // Same bucket, distinct tags: 40 entries need five overflow buckets
+        let keys: Vec<u64> = (1..=40).map(|tag| tag << 48).collect();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 25, 1 << 10, disk).unwrap();
+            for &key in &keys {
+                let context = TestUpsertContext {
+                    key,
+                    value: key >> 48,
+                };
+                assert_eq!(kv.upsert(&context), Status::Ok);
+            }
+            kv.checkpoint("cp").unwrap();
+        }
+
+        // Another live store keeps the recovered pages from landing where
+        // the checkpointed ones were
+        let other_temp = TempDir::new("overflow_other");
+        let other_dir = other_temp.path();
+        let other = RsKv::<u64, u64, FileSystemDisk>::new(
+            1 << 25,
+            1 << 10,
+            FileSystemDisk::new(other_dir).unwrap(),
+        )
+        .unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "cp").unwrap();
+        assert_eq!(kv.index.entry_count(), 40);
+        for &key in &keys {
+            assert_eq!(read_value(&kv, key), Some(key >> 48), "key {:#x}", key);
+        }
+        drop(kv);
+        drop(other);
+    }
+
+    #[test]
+    fn test_recovery_drops_torn_tail() {
+        let temp = TempDir::new("torn");
+        let dir = temp.path();
+        let log_path = format!("{}/hlog.log", dir);
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            for key in 1..=20 {
+                assert_eq!(
+                    kv.upsert(&TestUpsertContext { key, value: key }),
+                    Status::Ok
+                );
+            }
+            kv.flush().unwrap();
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 21, value: 21 }),
+                Status::Ok
+            );
+            kv.flush().unwrap();
+        }
+        let intact_size = fs::metadata(&log_path).unwrap().len();
+
+        // Flip a byte in the value of the last record.
+        let mut bytes = fs::read(&log_path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&log_path, &bytes).unwrap();
+
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, None, &test_options(), |key| *key).unwrap();
+        for key in 1..=20 {
+            assert_eq!(read_value(&kv, key), Some(key));
+        }
+        assert_eq!(read_value(&kv, 21), None);
+        assert!(fs::metadata(&log_path).unwrap().len() < intact_size);
+
+        // The store keeps working past the truncation point.
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 22, value: 22 }),
+            Status::Ok
+        );
+        kv.flush().unwrap();
+        drop(kv);
+
+        // A frame that runs past the end of the log is torn as well.
+        let size = fs::metadata(&log_path).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&log_path)
+            .unwrap()
+            .set_len(size - 3)
+            .unwrap();
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, None, &test_options(), |key| *key).unwrap();
+        assert_eq!(read_value(&kv, 20), Some(20));
+        assert_eq!(read_value(&kv, 22), None);
+    }
+
+    #[test]
+    fn test_event_hooks_fire_in_order() {
+        #[derive(Debug, PartialEq)]
+        enum Seen {
+            Flush(u64),
+            Checkpoint(String, u64),
+        }
+
+        let temp = TempDir::new("hooks");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        let (sender, events) = std::sync::mpsc::channel();
+        let on_checkpoint = sender.clone();
+        kv.set_hooks(Some(
+            EventHooks::default()
+                .on_flush(move |event| {
+                    sender.send(Seen::Flush(event.until_address)).unwrap();
+                })
+                .on_checkpoint(move |event| {
+                    let until = event.metadata.log_metadata.final_address.control();
+                    on_checkpoint
+                        .send(Seen::Checkpoint(event.id.clone(), until))
+                        .unwrap();
+                }),
+        ));
+
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 1 }),
+            Status::Ok
+        );
+        kv.flush().unwrap();
+        let flushed = kv.hlog.get_tail_address().control();
+        kv.checkpoint("hooked").unwrap();
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(events.recv_timeout(timeout), Ok(Seen::Flush(flushed)));
+        // The checkpoint makes the log durable before it completes.
+        assert_eq!(events.recv_timeout(timeout), Ok(Seen::Flush(flushed)));
+        assert_eq!(
+            events.recv_timeout(timeout),
+            Ok(Seen::Checkpoint("hooked".to_string(), flushed))
+        );
+
+        kv.set_hooks(None);
+        assert!(kv.hook_stats().is_none());
+        assert!(events.try_recv().is_err());
+        drop(kv);
+    }
+
+    #[test]
+    fn test_log_capacity_limit_fails_writes_and_warns_once() {
+        type Kv<'a> = RsKv<'a, u64, u64, FileSystemDisk>;
+        let temp = TempDir::new("log_capacity");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = Kv::new(1 << 26, 1 << 10, disk).unwrap();
+        // Two pages of address space without a configured limit
+        assert_eq!(kv.log_capacity_stats().limit, 2 * kv.hlog.page_size);
+        assert_eq!(
+            kv.set_log_capacity(LogCapacityConfig {
+                warn_headroom: 1.5,
+                ..Default::default()
+            }),
+            Err(Status::InvalidConfiguration)
+        );
+
+        let (sender, warnings) = std::sync::mpsc::channel();
+        kv.set_hooks(Some(EventHooks::default().on_log_capacity(move |event| {
+            sender.send(*event).unwrap();
+        })));
+        let limit = kv.log_capacity_stats().written + 10 * Kv::record_slot_size();
+        kv.set_log_capacity(LogCapacityConfig {
+            max_log_size: limit,
+            warn_headroom: 0.5,
+        })
+        .unwrap();
+
+        for key in 0..10 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        assert_eq!(
+            kv.log_capacity_stats(),
+            LogCapacityStats {
+                written: limit,
+                limit,
+                headroom: 0,
+            }
+        );
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 10, value: 10 }),
+            Status::LogCapacityExhausted
+        );
+        assert_eq!(read_value(&kv, 9), Some(9));
+
+        // Raising the limit lets writes go on
+        kv.set_log_capacity(LogCapacityConfig {
+            max_log_size: limit + Kv::record_slot_size(),
+            warn_headroom: 0.0,
+        })
+        .unwrap();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 10, value: 10 }),
+            Status::Ok
+        );
+
+        kv.set_hooks(None);
+        let warnings: Vec<LogCapacityLow> = warnings.try_iter().collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].limit, limit);
+        assert!(limit - warnings[0].written < limit / 2);
+        drop(kv);
+
+        // A log of one page runs out of address space
+        let temp = TempDir::new("log_address_space");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = Kv::new(1 << 25, 1 << 10, disk).unwrap();
+        let half_page = kv.hlog.page_size / 2;
+        assert!(kv.allocate_record(half_page).is_ok());
+        assert_eq!(
+            kv.allocate_record(half_page),
+            Err(Status::LogCapacityExhausted)
+        );
+        assert!(kv.log_capacity_stats().headroom < half_page);
+        drop(kv);
+    }
+
+    #[test]
+    fn test_read_versions_newest_first() {
+        let temp = TempDir::new("versions");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for value in 1..=5 {
+            assert_eq!(kv.upsert(&TestUpsertContext { key: 7, value }), Status::Ok);
+            assert_eq!(
+                kv.upsert(&TestUpsertContext {
+                    key: 8,
+                    value: value * 10
+                }),
+                Status::Ok
+            );
+        }
+
+        let versions = kv.read_versions(&7, 7, 10).unwrap();
+        let values: Vec<u64> = versions.iter().map(|version| version.value).collect();
+        assert_eq!(values, vec![5, 4, 3, 2, 1]);
+        assert!(versions.windows(2).all(|w| w[0].address > w[1].address));
+        assert!(versions.iter().all(|version| version.timestamp.is_some()));
+        assert_eq!(kv.read_versions(&7, 7, 2).unwrap().len(), 2);
+        assert!(kv.read_versions(&9, 9, 10).unwrap().is_empty());
+
+        // A delete hides the versions written before it.
+        kv.flush().unwrap();
+        assert_eq!(kv.delete(&TestDeleteContext { key: 7 }), Status::Ok);
+        assert!(kv.read_versions(&7, 7, 10).unwrap().is_empty());
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 7, value: 6 }),
+            Status::Ok
+        );
+        let values: Vec<u64> = kv
+            .read_versions(&7, 7, 10)
+            .unwrap()
+            .iter()
+            .map(|version| version.value)
+            .collect();
+        assert_eq!(values, vec![6]);
+    }
+
+    #[test]
+    fn test_read_versions_walks_into_evicted_pages() {
+        let temp = TempDir::new("versions_evicted");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for value in 1..=3 {
+            assert_eq!(kv.upsert(&TestUpsertContext { key: 7, value }), Status::Ok);
+            // Flushed records are read-only, so each update appends a version.
+            kv.flush().unwrap();
+        }
+
+        // Move the tail to the second page, then flush the first and drop it
+        // from memory.
+        for _ in 0..2 {
+            kv.hlog.allocate(kv.hlog.page_size / 2).unwrap();
+        }
+        kv.flush().unwrap();
+        kv.shift_head_address(Address::new(1, 0));
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 7, value: 4 }),
+            Status::Ok
+        );
+
+        let versions = kv.read_versions(&7, 7, 10).unwrap();
+        let values: Vec<u64> = versions.iter().map(|version| version.value).collect();
+        assert_eq!(values, vec![4, 3, 2, 1]);
+        assert!(versions.iter().all(|version| version.timestamp.is_some()));
+        assert!(kv.record_at(versions[1].address).is_none());
+    }
+
+    #[test]
+    fn test_read_with_meta_reads_evicted_records() {
+        let temp = TempDir::new("meta_evicted");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        let context = TestUpsertContext { key: 7, value: 70 };
+        kv.upsert_opts(&context, &WriteOptions::default().user_flags(0b0101))
+            .unwrap();
+        let mut find_context = FindContext::new(7);
+        assert_eq!(kv.index.find_entry(&mut find_context), Status::Ok);
+        let address = find_context.entry.address();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 8, value: 80 }),
+            Status::Ok
+        );
+        assert_eq!(kv.delete(&TestDeleteContext { key: 8 }), Status::Ok);
+
+        // Move the tail to the second page, then flush the first and drop it
+        // from memory.
+        for _ in 0..2 {
+            kv.hlog.allocate(kv.hlog.page_size / 2).unwrap();
+        }
+        kv.flush().unwrap();
+        kv.shift_head_address(Address::new(1, 0));
+        assert!(kv.record_at(address).is_none());
+
+        let mut context = TestReadContext {
+            key: 7,
+            value: None,
+        };
+        let meta = kv.read_with_meta(&mut context).unwrap();
+        assert_eq!(context.value, Some(70));
+        assert_eq!(meta.address, address);
+        assert_eq!(meta.user_flags, 0b0101);
+        assert!(meta.timestamp.is_some());
+        let mut deleted = TestReadContext {
+            key: 8,
+            value: None,
+        };
+        assert_eq!(kv.read_with_meta(&mut deleted), Err(Status::NotFound));
+    }
+
+    #[test]
+    fn test_scan_range_orders_keys_by_bytes() {
+        struct ByteKeyDeleteContext {
+            key: [u8; 8],
+        }
+
+        impl DeleteContext for ByteKeyDeleteContext {
+            type Key = [u8; 8];
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                u64::from_be_bytes(self.key)
+            }
+        }
+
+        let upsert = |kv: &RsKv<'_, [u8; 8], u64, FileSystemDisk>, i: u64, value: u64| {
+            let key = encode_u64(i);
+            let context = LoadUpsertContext {
+                key,
+                value,
+                key_hash: u64::from_be_bytes(key),
+            };
+            assert_eq!(kv.upsert(&context), Status::Ok);
+        };
+        let keys = |records: &[([u8; 8], u64)]| -> Vec<u64> {
+            records.iter().map(|(key, _)| decode_u64(*key)).collect()
+        };
+
+        let temp = TempDir::new("scan_range");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<[u8; 8], u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for i in (0..20).rev() {
+            upsert(&kv, i, i);
+        }
+        kv.flush().unwrap();
+        // Push the first page out of memory, so older records come from disk.
+        for _ in 0..2 {
+            kv.hlog.allocate(kv.hlog.page_size / 2).unwrap();
+        }
+        kv.flush().unwrap();
+        kv.shift_head_address(Address::new(1, 0));
+        upsert(&kv, 5, 500);
+        let context = ByteKeyDeleteContext { key: encode_u64(6) };
+        assert_eq!(kv.delete(&context), Status::Ok);
+
+        let (start, end) = (encode_u64(4), encode_u64(8));
+        let records = kv.scan_range(&start[..]..&end[..]).unwrap();
+        assert_eq!(keys(&records), vec![4, 5, 7]);
+        assert_eq!(records[1].1, 500);
+        let records = kv.scan_range(&start[..]..=&end[..]).unwrap();
+        assert_eq!(keys(&records), vec![4, 5, 7, 8]);
+        assert_eq!(kv.scan_range(..).unwrap().len(), 19);
+        assert!(kv.scan_range(&end[..]..&start[..]).unwrap().is_empty());
+        assert!(kv.scan_range(&start[..]..&start[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_write_timestamps_never_decrease_along_a_chain() {
+        let temp = TempDir::new("timestamps");
+        let dir = temp.path();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            let clock = Arc::new(MockClock::new());
+            kv.set_clock(clock.clone());
+            for value in 1..=3 {
+                clock.advance(Duration::from_secs(1));
+                assert_eq!(kv.upsert(&TestUpsertContext { key: 7, value }), Status::Ok);
+                kv.flush().unwrap();
+            }
+        }
+
+        // Writes after a reopen carry on from the newest time in the log.
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, None, &test_options(), |key| *key).unwrap();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 7, value: 4 }),
+            Status::Ok
+        );
+        let versions = kv.read_versions(&7, 7, 10).unwrap();
+        let values: Vec<u64> = versions.iter().map(|version| version.value).collect();
+        assert_eq!(values, vec![4, 3, 2, 1]);
+        assert!(versions.iter().all(|version| version.timestamp.is_some()));
+        assert!(
+            versions
+                .windows(2)
+                .all(|w| w[0].timestamp >= w[1].timestamp)
+        );
+        assert!(versions[0].timestamp > versions[3].timestamp);
+
+        let mut context = TestReadContext {
+            key: 7,
+            value: None,
+        };
+        let meta = kv.read_with_meta(&mut context).unwrap();
+        assert_eq!(context.value, Some(4));
+        assert_eq!(meta.address, versions[0].address);
+        assert_eq!(meta.timestamp, versions[0].timestamp);
+        let mut missing = TestReadContext {
+            key: 9,
+            value: None,
+        };
+        assert_eq!(kv.read_with_meta(&mut missing), Err(Status::NotFound));
+    }
+
+    #[test]
+    fn test_user_flags_survive_flush_reopen_and_compaction() {
+        let temp = TempDir::new("user_flags");
+        let dir = temp.path();
+        let flags_of = |kv: &RsKv<'_, u64, u64, FileSystemDisk>, key: u64| {
+            let mut context = TestReadContext { key, value: None };
+            kv.read_with_meta(&mut context).unwrap().user_flags
+        };
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            for key in 0..16u64 {
+                let options = WriteOptions::default().user_flags(key as u8);
+                let context = TestUpsertContext { key, value: key };
+                kv.upsert_opts(&context, &options).unwrap();
+            }
+            // An overwrite carries the flags of the new write, not the old.
+            let context = TestUpsertContext { key: 3, value: 33 };
+            kv.upsert_opts(&context, &WriteOptions::default().user_flags(0b1010))
+                .unwrap();
+            assert_eq!(
+                kv.upsert_opts(&context, &WriteOptions::default().user_flags(0x10)),
+                Err(Status::InvalidConfiguration)
+            );
+            kv.flush().unwrap();
+            for key in 0..16 {
+                let expected = if key == 3 { 0b1010 } else { key as u8 };
+                assert_eq!(flags_of(&kv, key), expected);
+            }
+            let versions = kv.read_versions(&3, 3, 10).unwrap();
+            let flags: Vec<u8> = versions.iter().map(|version| version.user_flags).collect();
+            assert_eq!(flags, vec![0b1010, 3]);
+            kv.checkpoint("flags").unwrap();
+        }
+
+        // Reopening reads every record back from the log file.
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "flags").unwrap();
+        for key in 0..16 {
+            let expected = if key == 3 { 0b1010 } else { key as u8 };
+            assert_eq!(flags_of(&kv, key), expected);
+        }
+        drop(kv);
+
+        // Compaction relocates every record into a new log.
+        let report = crate::admin::compact::<u64, u64>(
+            dir,
+            &crate::admin::CompactOptions::default(),
+            |key| *key,
+        )
+        .unwrap();
+        assert_eq!(report.records_kept, 16);
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "compacted").unwrap();
+        for key in 0..16 {
+            let expected = if key == 3 { 0b1010 } else { key as u8 };
+            assert_eq!(flags_of(&kv, key), expected);
+        }
+    }
+
+    #[test]
+    fn test_delete_of_key_whose_record_was_evicted() {
+        let temp = TempDir::new("delete_evicted");
+        let dir = temp.path();
+        let address = {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            kv.set_memory_budget(Some(kv.hlog.page_size));
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 7, value: 70 }),
+                Status::Ok
+            );
+            let mut find_context = FindContext::new(7);
+            assert_eq!(kv.index.find_entry(&mut find_context), Status::Ok);
+            let address = find_context.entry.address();
+
+            // Move the tail to the second page; the flush then drops the
+            // first from memory to keep within the budget.
+            for _ in 0..2 {
+                kv.hlog.allocate(kv.hlog.page_size / 2).unwrap();
+            }
+            assert_eq!(kv.hlog.get_tail_address().page(), 1);
+            kv.flush().unwrap();
+            assert_eq!(kv.hlog.get_head_address(), Address::new(1, 0));
+            assert!(kv.record_at(address).is_none());
+
+            assert_eq!(kv.delete(&TestDeleteContext { key: 7 }), Status::Ok);
+            assert_eq!(read_value(&kv, 7), None);
+            assert_eq!(kv.delete(&TestDeleteContext { key: 8 }), Status::NotFound);
+            kv.checkpoint("evicted").unwrap();
+            address
+        };
+
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "evicted").unwrap();
+        assert_eq!(read_value(&kv, 7), None);
+        // The tombstone is chained to the record it deletes.
+        let mut find_context = FindContext::new(7);
+        assert_eq!(kv.index.find_entry(&mut find_context), Status::Ok);
+        let (header, key, _) = kv.record_at(find_context.entry.address()).unwrap();
+        assert!(header.tombstone());
+        assert_eq!(key, 7);
+        assert_eq!(header.previous_address(), address);
+        assert_eq!(kv.record_at(address).unwrap().2, 70);
+    }
+
+    #[test]
+    fn test_memory_budget_drops_flushed_pages() {
+        let temp = TempDir::new("memory_budget");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 27, 1 << 10, disk).unwrap();
+        let page_size = kv.hlog.page_size;
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 10 }),
+            Status::Ok
+        );
+        let mut find_context = FindContext::new(1);
+        assert_eq!(kv.index.find_entry(&mut find_context), Status::Ok);
+        let address = find_context.entry.address();
+        for _ in 0..2 {
+            kv.hlog.allocate(page_size / 2).unwrap();
+        }
+
+        // Nothing is flushed yet, so both pages stay over the budget.
+        kv.set_memory_budget(Some(page_size));
+        assert_eq!(kv.hlog.get_head_address(), Address::from_control(0));
+        kv.flush().unwrap();
+        assert_eq!(kv.hlog.get_head_address(), Address::new(1, 0));
+        assert!(kv.record_at(address).is_none());
+        assert_eq!(read_value(&kv, 1), Some(10));
+
+        // Opening a page drops the pages before it once they are flushed.
+        kv.hlog.allocate(page_size / 2).unwrap();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 2, value: 20 }),
+            Status::Ok
+        );
+        assert_eq!(kv.hlog.get_tail_address().page(), 2);
+        assert_eq!(kv.hlog.get_head_address(), Address::new(1, 0));
+        kv.flush().unwrap();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 3, value: 30 }),
+            Status::Ok
+        );
+        assert_eq!(kv.hlog.get_head_address(), Address::new(2, 0));
+        for (key, value) in [(1, 10), (2, 20), (3, 30)] {
+            assert_eq!(read_value(&kv, key), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_read_cache_serves_evicted_records() {
+        let temp = TempDir::new("read_cache");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        kv.set_memory_budget(Some(kv.hlog.page_size));
+        let mut addresses = Vec::new();
+        for key in 1..=4 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext {
+                    key,
+                    value: key * 10
+                }),
+                Status::Ok
+            );
+            let mut find_context = FindContext::new(key);
+            assert_eq!(kv.index.find_entry(&mut find_context), Status::Ok);
+            addresses.push(find_context.entry.address());
+        }
+        for _ in 0..2 {
+            kv.hlog.allocate(kv.hlog.page_size / 2).unwrap();
+        }
+        kv.flush().unwrap();
+        assert!(kv.record_at(addresses[0]).is_none());
+        let entry_of = |kv: &RsKv<'_, u64, u64, FileSystemDisk>, key: u64| {
+            let mut find_context = FindContext::new(key);
+            assert_eq!(kv.index.find_entry(&mut find_context), Status::Ok);
+            find_context.entry
+        };
+
+        // Without a cache every read goes to disk.
+        assert_eq!(read_value(&kv, 1), Some(10));
+        assert!(!entry_of(&kv, 1).in_readcache());
+
+        kv.set_read_cache(Some(ReadCacheConfig {
+            capacity: 2,
+            ..Default::default()
+        }));
+        assert_eq!(read_value(&kv, 1), Some(10));
+        assert!(entry_of(&kv, 1).in_readcache());
+        assert_eq!(read_value(&kv, 1), Some(10));
+        let stats = kv.read_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.admitted), (1, 1, 1));
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        // Two more copies evict the first, restoring its entry.
+        assert_eq!(read_value(&kv, 2), Some(20));
+        assert_eq!(read_value(&kv, 3), Some(30));
+        assert!(!entry_of(&kv, 1).in_readcache());
+        assert_eq!(entry_of(&kv, 1).address(), addresses[0]);
+        assert_eq!(kv.read_cache_stats().unwrap().evicted, 1);
+
+        // A write skips the copy and chains to the record behind it.
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 2, value: 21 }),
+            Status::Ok
+        );
+        let entry = entry_of(&kv, 2);
+        assert!(!entry.in_readcache());
+        let (header, _, _) = kv.record_at(entry.address()).unwrap();
+        assert_eq!(header.previous_address(), addresses[1]);
+        assert_eq!(read_value(&kv, 2), Some(21));
+
+        // A checkpoint empties the cache, so no cache address is saved.
+        kv.checkpoint("read_cache").unwrap();
+        assert!(!entry_of(&kv, 3).in_readcache());
+        assert_eq!(kv.read_cache_stats().unwrap().resident, 0);
+        assert_eq!(read_value(&kv, 3), Some(30));
+    }
+
+    #[test]
+    fn test_page_cache_reads_ahead_only_while_reads_are_sequential() {
+        let temp = TempDir::new("page_cache");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 27, 1 << 12, disk).unwrap();
+        let page_size = kv.hlog.page_size;
+        for key in 1..=1000 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext {
+                    key,
+                    value: key * 10
+                }),
+                Status::Ok
+            );
+        }
+        for _ in 0..2 {
+            kv.hlog.allocate(page_size / 2).unwrap();
+        }
+        kv.set_memory_budget(Some(page_size));
+        kv.flush().unwrap();
+        assert_eq!(kv.hlog.get_head_address(), Address::new(1, 0));
+        kv.set_page_cache(Some(PageCacheConfig {
+            capacity: 256,
+            page_size: 256,
+            max_read_ahead: 8,
+            history: 8,
+        }));
+
+        // Reading the records in log order soon reads eight pages ahead.
+        for key in 1..=1000 {
+            assert_eq!(read_value(&kv, key), Some(key * 10));
+        }
+        let stats = kv.page_cache_stats().unwrap();
+        assert_eq!(stats.read_ahead_window, 8);
+        assert!(stats.misses < 40, "{stats:?}");
+        assert_eq!(stats.wasted_prefetches, 0);
+
+        // A random read stops the read-ahead at once.
+        kv.set_page_cache(Some(PageCacheConfig::default()));
+        for key in [500, 3, 870, 41, 700, 222] {
+            assert_eq!(read_value(&kv, key), Some(key * 10));
+        }
+        let stats = kv.page_cache_stats().unwrap();
+        assert_eq!(stats.read_ahead_window, 0);
+        assert_eq!(stats.prefetched, 0);
+    }
+
+    #[test]
+    fn test_chain_prefetch_reads_each_hop_ahead() {
+        // Every key shares one index entry, so a read of the first key
+        // written walks back through all the others.
+        struct ChainedUpsertContext {
+            key: u64,
+        }
+
+        impl UpsertContext for ChainedUpsertContext {
+            type Key = u64;
+            type Value = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn value(&self) -> &Self::Value {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                1
+            }
+
+            fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+                false
+            }
+        }
+
+        struct ChainedReadContext {
+            key: u64,
+            value: Option<u64>,
+        }
+
+        impl ReadContext for ChainedReadContext {
+            type Key = u64;
+            type Value = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                1
+            }
+
+            fn get(&mut self, value: &Self::Value) {
+                self.value = Some(*value);
+            }
+        }
+
+        let temp = TempDir::new("chain_prefetch");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 27, 1 << 10, disk).unwrap();
+        let page_size = kv.hlog.page_size;
+        const CHAIN: u64 = 200;
+        for key in 0..CHAIN {
+            assert_eq!(kv.upsert(&ChainedUpsertContext { key }), Status::Ok);
+        }
+        for _ in 0..2 {
+            kv.hlog.allocate(page_size / 2).unwrap();
+        }
+        kv.set_memory_budget(Some(page_size));
+        kv.flush().unwrap();
+        // One record per cached page, so every hop reads a page of its own.
+        let record_size = Record::<u64, u64>::required_size_with_alignment() as u64;
+        kv.set_page_cache(Some(PageCacheConfig {
+            page_size: record_size,
+            ..Default::default()
+        }));
+        kv.set_chain_prefetch(Some(4));
+
+        let mut context = ChainedReadContext {
+            key: 0,
+            value: None,
+        };
+        assert_eq!(kv.read(&mut context), Status::Ok);
+        assert_eq!(context.value, Some(0));
+        // Only the first hop is read in the foreground.
+        let stats = kv.page_cache_stats().unwrap();
+        assert_eq!(stats.misses, 1, "{stats:?}");
+        assert_eq!(stats.prefetch_hits, CHAIN - 1);
+        assert_eq!(stats.read_ahead_window, 0);
+
+        // Without prefetching, every hop is a miss.
+        kv.set_page_cache(Some(PageCacheConfig {
+            page_size: record_size,
+            ..Default::default()
+        }));
+        let mut context = ChainedReadContext {
+            key: 0,
+            value: None,
+        };
+        assert_eq!(kv.read(&mut context), Status::Ok);
+        let stats = kv.page_cache_stats().unwrap();
+        assert_eq!((stats.misses, stats.prefetched), (CHAIN, 0));
+    }
+
+    #[test]
+    fn test_page_tiering_keeps_hot_pages_in_memory_within_the_budget() {
+        let temp = TempDir::new("page_tiering");
+        let disk = FileSystemDisk::new(temp.path()).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 28, 1 << 10, disk).unwrap();
+        let page_size = kv.hlog.page_size;
+        let clock = Arc::new(MockClock::new());
+        kv.set_access_analyzer(Some(Arc::new(AccessAnalyzer::with_clock(
+            AnalyzerConfig {
+                decay_half_life_ms: 1000,
+                hot_threshold: 8.0,
+                ..Default::default()
+            },
+            clock.clone(),
+        ))));
+
+        // Hot keys on page 0, then a cold key on each of pages 1 to 5.
+        let upsert = |kv: &RsKv<'_, u64, u64, FileSystemDisk>, key: u64| {
+            let context = TestUpsertContext {
+                key,
+                value: key * 10,
+            };
+            assert_eq!(kv.upsert(&context), Status::Ok);
+        };
+        for key in 1..=8 {
+            upsert(&kv, key);
+        }
+        for page in 1..=5 {
+            while kv.hlog.get_tail_address().page() < page {
+                if let Err(closed_page) = kv.hlog.allocate(page_size / 4) {
+                    kv.hlog.new_page(closed_page);
+                }
+            }
+            upsert(&kv, 100 + page as u64);
+        }
+        kv.set_memory_budget(Some(3 * page_size));
+        for _ in 0..4 {
+            for key in 1..=8 {
+                assert_eq!(read_value(&kv, key), Some(key * 10));
+            }
+        }
+        for key in 101..=105 {
+            assert_eq!(read_value(&kv, key), Some(key * 10));
+        }
+
+        let kv = Arc::new(kv);
+        let resident_pages = |kv: &RsKv<'_, u64, u64, FileSystemDisk>| {
+            let log_pages = kv.hlog.get_tail_address().page() - kv.hlog.get_head_address().page();
+            log_pages as usize + 1 + kv.hlog.admitted_pages().len()
+        };
+        let tiering = kv.start_page_tiering(PageTieringConfig {
+            interval: Duration::from_secs(3600),
+            ..Default::default()
+        });
+        assert!(tiering.run_once());
+        // The log gives up four pages so the hot page fits beside it.
+        assert_eq!(kv.hlog.get_head_address(), Address::new(4, 0));
+        assert_eq!(kv.hlog.admitted_pages(), vec![0]);
+        assert_eq!(resident_pages(&kv), 3);
+        let stats = tiering.stats();
+        assert_eq!((stats.passes, stats.pages_demoted), (1, 4));
+        assert_eq!(stats.pages_promoted, 1);
+        assert!(stats.bytes_moved >= 4 * page_size);
+        for key in (1..=8).chain(101..=105) {
+            assert_eq!(read_value(&kv, key), Some(key * 10));
+        }
+
+        // The skew moves to page 2, and page 0 cools off.
+        clock.advance(Duration::from_secs(10));
+        for _ in 0..10 {
+            assert_eq!(read_value(&kv, 102), Some(1020));
+        }
+        assert!(tiering.run_once());
+        assert_eq!(kv.hlog.admitted_pages(), vec![2]);
+        assert_eq!(resident_pages(&kv), 3);
+        let stats = tiering.stats();
+        assert_eq!((stats.pages_demoted, stats.pages_promoted), (5, 2));
+
+        // A new tail page stays within the budget by dropping a log page.
+        let tail_page = kv.hlog.get_tail_address().page();
+        while kv.hlog.get_tail_address().page() == tail_page {
+            if let Err(closed_page) = kv.hlog.allocate(page_size / 4) {
+                kv.hlog.new_page(closed_page);
+            }
+        }
+        kv.flush().unwrap();
+        assert_eq!(resident_pages(&kv), 3);
+        tiering.stop();
+    }
+
+    #[test]
+    fn test_read_cache_admits_by_heat() {
+        let temp = TempDir::new("read_cache_heat");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        kv.set_memory_budget(Some(kv.hlog.page_size));
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 5, value: 50 }),
+            Status::Ok
+        );
+        for _ in 0..2 {
+            kv.hlog.allocate(kv.hlog.page_size / 2).unwrap();
+        }
+        kv.flush().unwrap();
+        kv.set_access_analyzer(Some(Arc::new(AccessAnalyzer::new(AnalyzerConfig {
+            warm_threshold: 1.5,
+            ..Default::default()
+        }))));
+        kv.set_read_cache(Some(ReadCacheConfig::default()));
+
+        // The upsert was not seen by the analyzer, so the first read leaves
+        // the key cold, and the second makes it warm.
+        assert_eq!(read_value(&kv, 5), Some(50));
+        let stats = kv.read_cache_stats().unwrap();
+        assert_eq!((stats.admitted, stats.rejected), (0, 1));
+        assert_eq!(read_value(&kv, 5), Some(50));
+        assert_eq!(kv.read_cache_stats().unwrap().admitted, 1);
+        assert_eq!(read_value(&kv, 5), Some(50));
+        assert_eq!(kv.read_cache_stats().unwrap().hits, 1);
+    }
+
+    #[test]
+    fn test_read_batch_matches_single_reads() {
+        // Keys share 64 index entries, so some reads walk past others.
+        struct CollidingReadContext {
+            key: u64,
+            value: Option<u64>,
+        }
+
+        impl ReadContext for CollidingReadContext {
+            type Key = u64;
+            type Value = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                self.key % 64
+            }
+
+            fn get(&mut self, value: &Self::Value) {
+                self.value = Some(*value);
+            }
+        }
+
+        struct CollidingDeleteContext {
+            key: u64,
+        }
+
+        impl DeleteContext for CollidingDeleteContext {
+            type Key = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                self.key % 64
+            }
+        }
+
+        let temp = TempDir::new("read_batch");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        let records = (0..200).map(|key| (key, key * 3));
+        assert_eq!(kv.bulk_load(records, |key| key % 64), Ok(200));
+        kv.flush().unwrap();
+        for key in (0..200).step_by(7) {
+            assert_eq!(kv.delete(&CollidingDeleteContext { key }), Status::Ok);
+        }
+
+        let mut contexts: Vec<CollidingReadContext> = (0..250)
+            .rev()
+            .map(|key| CollidingReadContext { key, value: None })
+            .collect();
+        let statuses = kv.read_batch(&mut contexts);
+        assert_eq!(statuses.len(), contexts.len());
+        for (status, context) in statuses.iter().zip(&contexts) {
+            let key = context.key;
+            let mut single = CollidingReadContext { key, value: None };
+            assert_eq!(*status, kv.read(&mut single), "key {}", key);
+            assert_eq!(context.value, single.value, "key {}", key);
+            let expected = (key < 200 && key % 7 != 0).then_some(key * 3);
+            assert_eq!(context.value, expected, "key {}", key);
+        }
+        assert!(kv.read_batch::<CollidingReadContext>(&mut []).is_empty());
+    }
+
+    #[test]
+    fn test_numa_placement_smoke() {
+        let temp = TempDir::new("numa");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        assert_eq!(kv.memory_metrics(), None);
+        kv.set_numa(Some(NumaConfig {
+            pages: PagePlacement::RoundRobin,
+            partition_index: true,
+        }));
+        kv.hlog.new_page(Address::new(1, 0));
+        for key in 0..100u64 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        assert_eq!(read_value(&kv, 99), Some(99));
+
+        // Only the bookkeeping is checked: placement may be a no-op here.
+        let metrics = kv.memory_metrics().unwrap();
+        assert!(!metrics.nodes.is_empty());
+        let pages: u64 = metrics.nodes.iter().map(|node| node.page_allocations).sum();
+        let index_bytes: u64 = metrics.nodes.iter().map(|node| node.index_bytes).sum();
+        assert_eq!(pages, 2);
+        assert_eq!(index_bytes, kv.index.bucket_memory().1 as u64);
+
+        kv.set_numa(None);
+        assert_eq!(kv.memory_metrics(), None);
+        drop(kv);
+    }
+
+    #[test]
+    fn test_debug_page_map_tracks_page_states() {
+        use crate::hlog::persistent_memory_malloc::PageState;
+        fn assert_serialize<T: serde::Serialize>(_: &T) {}
+
+        let temp = TempDir::new("page_map");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 27, 1 << 10, disk).unwrap();
+        let page_size = kv.hlog.page_size;
+        let states = |kv: &RsKv<'_, u64, u64, FileSystemDisk>| -> Vec<(PageState, bool)> {
+            kv.hlog
+                .debug_page_map()
+                .iter()
+                .map(|page| (page.state, page.dirty))
+                .collect()
+        };
+
+        // Fill the first page and half the second, flush both and drop the
+        // first from memory.
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 1 }),
+            Status::Ok
+        );
+        for _ in 0..2 {
+            kv.hlog.allocate(page_size / 2).unwrap();
+        }
+        kv.flush().unwrap();
+        kv.shift_head_address(Address::new(1, 0));
+
+        // Fill the rest of the second page and start the third.
+        kv.hlog.allocate(page_size / 2).unwrap();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 2, value: 2 }),
+            Status::Ok
+        );
+        let tail = kv.hlog.get_tail_address().control();
+        assert_eq!(tail, 2 * page_size + 32);
+
+        let pages = kv.hlog.debug_page_map();
+        assert_eq!(pages.len(), 4);
+        assert_eq!((pages[0].begin, pages[0].end), (0, page_size));
+        assert_eq!((pages[2].begin, pages[2].end), (2 * page_size, tail));
+        assert_eq!((pages[3].begin, pages[3].end), (tail, tail));
+        assert_eq!(
+            states(&kv),
+            vec![
+                (PageState::OnDisk, false),
+                (PageState::InMemory, true),
+                (PageState::InMemory, true),
+                (PageState::NotAllocated, false),
+            ]
+        );
+        let regions = kv.hlog.region_summary();
+        assert_eq!(regions.on_disk_bytes, page_size);
+        assert_eq!(regions.flushed_bytes, page_size / 2);
+        assert_eq!(regions.unflushed_read_only_bytes, 0);
+        assert_eq!(regions.mutable_bytes, page_size / 2 + 32);
+
+        // Closing the tail to updates leaves both pages waiting for a flush.
+        kv.hlog.shift_read_only_address(Address::from_control(tail));
+        assert_eq!(
+            states(&kv)[1..3],
+            [(PageState::Flushing, true), (PageState::Flushing, true)]
+        );
+        assert_eq!(
+            kv.hlog.region_summary().unflushed_read_only_bytes,
+            page_size / 2 + 32
+        );
+        kv.flush().unwrap();
+        assert_eq!(
+            states(&kv)[1..3],
+            [(PageState::InMemory, false), (PageState::InMemory, false)]
+        );
+        assert_serialize(&kv.hlog.region_summary());
+        assert_serialize(&pages);
+        #[cfg(feature = "debug-introspection")]
+        {
+            let state = kv.debug_log_state();
+            assert_eq!(state.pages, kv.hlog.debug_page_map());
+            assert_serialize(&state);
+        }
+    }
+
+    #[test]
+    fn test_admission_control_under_flush_backlog() {
+        use crate::performance::migration_manager::{OverloadPolicy, PressureLevel};
+
+        let temp = TempDir::new("admission");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        let config = AdmissionConfig {
+            max_flush_backlog_bytes: 64 * RsKv::<u64, u64, FileSystemDisk>::record_slot_size(),
+            ..Default::default()
+        };
+        kv.set_admission_control(Some(config.clone()));
+
+        // Nothing flushes, as with a device that cannot keep up: writes are
+        // shed once the backlog reaches its limit, and every admitted write
+        // stays readable.
+        let mut admitted = 0;
+        let mut shed = 0;
+        for key in 0..200 {
+            match kv.upsert(&TestUpsertContext { key, value: key }) {
+                Status::Ok => admitted += 1,
+                Status::Overloaded => shed += 1,
+                status => panic!("unexpected {:?}", status),
+            }
+        }
+        assert!(admitted > 0 && shed > 0);
+        for key in 0..admitted {
+            assert_eq!(read_value(&kv, key), Some(key));
+        }
+        assert_eq!(read_value(&kv, admitted), None);
+        assert_eq!(kv.delete(&TestDeleteContext { key: 0 }), Status::Overloaded);
+        let stats = kv.admission_stats().unwrap();
+        assert_eq!(stats.level, PressureLevel::Overloaded);
+        assert_eq!((stats.admitted, stats.shed), (admitted, shed + 1));
+        assert_eq!(stats.retry_after, config.retry_after);
+
+        kv.flush().unwrap();
+        assert_eq!(kv.admission_stats().unwrap().level, PressureLevel::Normal);
+        assert_eq!(
+            kv.upsert(&TestUpsertContext {
+                key: 500,
+                value: 500
+            }),
+            Status::Ok
+        );
+
+        // With a delay policy, a write waits for a flush from elsewhere.
+        kv.set_admission_control(Some(AdmissionConfig {
+            policy: OverloadPolicy::Delay {
+                max_wait: Duration::from_secs(10),
+            },
+            ..config
+        }));
+        let mut key = 1000;
+        while kv.admission_stats().unwrap().level != PressureLevel::Overloaded {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+            key += 1;
+        }
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                kv.flush().unwrap();
+            });
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        });
+        assert_eq!(kv.admission_stats().unwrap().delayed, 1);
+        assert_eq!(read_value(&kv, key), Some(key));
+    }
+
+    #[test]
+    fn test_flush_and_checkpoint_sync_to_disk() {
+        let temp = TempDir::new("sync");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        assert_eq!(kv.disk.sync_count(), 0);
+
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 1 }),
+            Status::Ok
+        );
+        kv.flush().unwrap();
+        // The log and its frame log.
+        assert_eq!(kv.disk.sync_count(), 2);
+
+        kv.checkpoint("synced").unwrap();
+        // Log, frames, both index files, the write sequence, the metadata
+        // file and two directories.
+        assert_eq!(kv.disk.sync_count(), 10);
+        assert!(
+            !std::path::Path::new(&format!(
+                "{}checkpoint.dat.tmp",
+                kv.disk.index_checkpoint_path("synced")
+            ))
+            .exists()
+        );
+
+        drop(kv);
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "synced").unwrap();
+        assert_eq!(read_value(&kv, 1), Some(1));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_upsert_span_hierarchy() {
+        use std::sync::Mutex;
+        use tracing::span::{Attributes, Id, Record as SpanValues};
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+        use tracing_subscriber::registry::LookupSpan;
+
+        struct CollectedSpan {
+            id: u64,
+            name: &'static str,
+            parent: Option<&'static str>,
+            fields: Vec<String>,
+        }
+
+        struct Fields<'a>(&'a mut Vec<String>);
+
+        impl tracing::field::Visit for Fields<'_> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        /// Keeps every span opened, with the name of its parent and the
+        /// fields recorded on it.
+        #[derive(Clone, Default)]
+        struct Collector(Arc<Mutex<Vec<CollectedSpan>>>);
+
+        impl<S> Layer<S> for Collector
+        where
+            S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+                let parent = ctx
+                    .span(id)
+                    .and_then(|span| span.parent())
+                    .map(|parent| parent.name());
+                let mut fields = Vec::new();
+                attrs.record(&mut Fields(&mut fields));
+                self.0.lock().unwrap().push(CollectedSpan {
+                    id: id.into_u64(),
+                    name: attrs.metadata().name(),
+                    parent,
+                    fields,
+                });
+            }
+
+            fn on_record(&self, id: &Id, values: &SpanValues<'_>, _ctx: Context<'_, S>) {
+                // Ids are reused once a span closes, so the newest span wins.
+                let mut spans = self.0.lock().unwrap();
+                if let Some(span) = spans.iter_mut().rev().find(|span| span.id == id.into_u64()) {
+                    values.record(&mut Fields(&mut span.fields));
+                }
+            }
+        }
+
+        let temp = TempDir::new("tracing");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        let collector = Collector::default();
+        let subscriber = tracing_subscriber::registry().with(collector.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 1, value: 1 }),
+                Status::Ok
+            );
+            kv.flush().unwrap();
+        });
+
+        let spans = collector.0.lock().unwrap();
+        let shape: Vec<_> = spans.iter().map(|span| (span.name, span.parent)).collect();
+        assert_eq!(
+            shape,
+            vec![
+                ("upsert", None),
+                ("allocate", Some("upsert")),
+                ("flush", None)
+            ]
+        );
+        let bytes = format!(
+            "bytes={}",
+            Record::<u64, u64>::required_size_with_alignment()
+        );
+        let address = spans[1]
+            .fields
+            .iter()
+            .find(|field| field.starts_with("address="))
+            .unwrap();
+        let upsert = &spans[0].fields;
+        for field in ["key_len=8", bytes.as_str(), address.as_str(), "status=Ok"] {
+            assert!(
+                upsert.iter().any(|recorded| recorded == field),
+                "{field} in {upsert:?}"
+            );
+        }
+        assert!(spans[2].fields.iter().any(|field| field == "status=Ok"));
+    }
+
+    #[test]
+    fn test_recovery_report_counts() {
+        let temp = TempDir::new("report");
+        let dir = temp.path();
+        let log_path = format!("{}/hlog.log", dir);
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            for key in 1..=10 {
+                assert_eq!(
+                    kv.upsert(&TestUpsertContext { key, value: key }),
+                    Status::Ok
+                );
+            }
+            kv.checkpoint("report").unwrap();
+            for key in 11..=15 {
+                assert_eq!(
+                    kv.upsert(&TestUpsertContext { key, value: key }),
+                    Status::Ok
+                );
+            }
+            kv.flush().unwrap();
+        }
+        // A crash mid-write leaves part of a record with no flush frame.
+        let mut bytes = fs::read(&log_path).unwrap();
+        bytes.extend_from_slice(&[0xab; 5]);
+        fs::write(&log_path, &bytes).unwrap();
+
+        let record_size = RsKv::<u64, u64, FileSystemDisk>::record_slot_size();
+        let (kv, report) = RsKv::<u64, u64, FileSystemDisk>::open_with_report(
+            dir,
+            Some("report"),
+            &test_options(),
+            |key| *key,
+        )
+        .unwrap();
+        assert_eq!(report.checkpoint_id.as_deref(), Some("report"));
+        assert_eq!(report.index_entries_loaded, 10);
+        assert_eq!(report.records_replayed, 5);
+        assert_eq!(report.log_bytes_replayed, 5 * record_size);
+        assert_eq!(report.torn_records_skipped, 1);
+        let phases: Vec<_> = report
+            .duration_per_phase
+            .iter()
+            .map(|(phase, _)| *phase)
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                RecoveryPhase::LoadCheckpoint,
+                RecoveryPhase::LoadLog,
+                RecoveryPhase::ReplayLog
+            ]
+        );
+        for key in 1..=15 {
+            assert_eq!(read_value(&kv, key), Some(key));
+        }
+        drop(kv);
+
+        // Without the checkpoint the whole log is replayed.
+        let (_, report) =
+            RsKv::<u64, u64, FileSystemDisk>::open_with_report(dir, None, &test_options(), |key| {
+                *key
+            })
+            .unwrap();
+        assert_eq!(report.checkpoint_id, None);
+        assert_eq!(report.index_entries_loaded, 0);
+        assert_eq!(report.records_replayed, 15);
+        assert_eq!(report.log_bytes_replayed, 15 * record_size);
+        assert_eq!(report.torn_records_skipped, 0);
+        assert_eq!(report.duration_per_phase.len(), 2);
+    }
+
+    #[test]
+    fn test_storage_directory_is_locked() {
+        let temp = TempDir::new("lock");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+
+        assert_eq!(FileSystemDisk::new(dir).err(), Some(Status::StoreLocked));
+        assert_eq!(
+            FileSystemDisk::new_read_only(dir).err(),
+            Some(Status::StoreLocked)
+        );
+        assert_eq!(
+            crate::environment::file::DirectoryLock::pid_hint(dir),
+            Some(std::process::id())
+        );
+
+        // The lock goes away with the store.
+        drop(kv);
+        let first = FileSystemDisk::new_read_only(dir).unwrap();
+        let second = FileSystemDisk::new_read_only(dir).unwrap();
+        assert_eq!(FileSystemDisk::new(dir).err(), Some(Status::StoreLocked));
+        drop((first, second));
+        assert!(FileSystemDisk::new(dir).is_ok());
+    }
+
+    #[test]
+    fn test_log_superblock_is_validated() {
+        let temp = TempDir::new("superblock");
+        let dir = temp.path();
+        let other_temp = TempDir::new("superblock_other");
+        let other_dir = other_temp.path();
+        let log_path = format!("{}/hlog.log", dir);
+        let uuid = {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 1, value: 1 }),
+                Status::Ok
+            );
+            kv.checkpoint("cp").unwrap();
+            kv.hlog.superblock.store_uuid
+        };
+
+        // Reopening keeps the identity the store was created with.
+        let kv = RsKv::<u64, u64, FileSystemDisk>::recover(dir, "cp").unwrap();
+        assert_eq!(kv.hlog.superblock.store_uuid, uuid);
+        drop(kv);
+
+        // A checkpoint cannot be applied to another store's log.
+        {
+            let disk = FileSystemDisk::new(other_dir).unwrap();
+            let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 2, value: 2 }),
+                Status::Ok
+            );
+            kv.flush().unwrap();
+        }
+        fs::rename(
+            format!("{}/index-checkpoints", dir),
+            format!("{}/index-checkpoints", other_dir),
+        )
+        .unwrap();
+        assert_eq!(
+            RsKv::<u64, u64, FileSystemDisk>::recover(other_dir, "cp").err(),
+            Some(Status::InvalidDataFormat)
+        );
+
+        // A file that is not an rskv log is rejected up front.
+        let mut bytes = fs::read(&log_path).unwrap();
+        bytes[0..8].copy_from_slice(b"NOTALOG!");
+        fs::write(&log_path, &bytes).unwrap();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        assert_eq!(
+            RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).err(),
+            Some(Status::InvalidDataFormat)
+        );
+    }
+
+    #[test]
+    fn test_verify_store() {
+        let temp = TempDir::new("verify");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for key in 0..100 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        kv.checkpoint("v1").unwrap();
+        for key in 100..150 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        kv.flush().unwrap();
+
+        let report = kv.verify(VerifyLevel::Full, |key| *key).unwrap();
+        assert!(report.is_clean(), "{:?}", report.findings);
+        assert_eq!(report.index_entries_checked, 150);
+        assert_eq!(report.frames_checked, 2);
+        assert_eq!(report.checkpoint_checked.as_deref(), Some("v1"));
+
+        let report = kv.verify(VerifyLevel::Sampled(10), |key| *key).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.index_entries_checked, 15);
+        let report = kv.verify(VerifyLevel::Quick, |key| *key).unwrap();
+        assert_eq!(report.index_entries_checked, 0);
+
+        // Verification runs alongside writers without false alarms.
+        std::thread::scope(|scope| {
+            let kv = &kv;
+            scope.spawn(move || {
+                for key in 150..2_000 {
+                    assert_eq!(
+                        kv.upsert(&TestUpsertContext { key, value: key }),
+                        Status::Ok
+                    );
+                }
+            });
+            for _ in 0..5 {
+                let report = kv.verify(VerifyLevel::Sampled(3), |key| *key).unwrap();
+                assert!(report.is_clean(), "{:?}", report.findings);
+            }
+        });
+
+        // A different hash function puts every key in the wrong bucket.
+        let report = kv
+            .verify(VerifyLevel::Sampled(100), |key| *key + 1)
+            .unwrap();
+        assert!(!report.findings.is_empty());
+        assert!(
+            report
+                .findings
+                .iter()
+                .all(|finding| matches!(finding, VerifyFinding::IndexKeyMismatch(_)))
+        );
+
+        // Damage on disk shows up without being repaired.
+        let log_path = format!("{}/hlog.log", dir);
+        let mut bytes = fs::read(&log_path).unwrap();
+        bytes[100] ^= 0xff;
+        fs::write(&log_path, &bytes).unwrap();
+        let ht_path = format!("{}ht.dat", kv.disk.index_checkpoint_path("v1"));
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&ht_path)
+            .unwrap()
+            .set_len(8)
+            .unwrap();
+        let report = kv.verify(VerifyLevel::Full, |key| *key).unwrap();
+        // The first frame covers the bytes that were flipped.
+        assert!(
+            report
+                .findings
+                .iter()
+                .any(|finding| matches!(finding, VerifyFinding::FrameCrcMismatch { begin: 0, .. }))
+        );
+        assert!(
+            report
+                .findings
+                .contains(&VerifyFinding::CheckpointUnloadable {
+                    token: "v1".to_string(),
+                    status: Status::Corruption,
+                })
+        );
+        assert_eq!(fs::read(&log_path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_verify_reports_index_invariants() {
+        let temp = TempDir::new("verify_invariants");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for key in 0..50 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        assert!(kv.index.check_invariants().is_clean());
+
+        // A second live entry with the same tag in the same bucket
+        let mut first = None;
+        kv.index.for_each_entry(|bucket, entry| {
+            first.get_or_insert((bucket, entry));
+        });
+        let (bucket, entry) = first.unwrap();
+        kv.index.store_entry_for_test(bucket, 6, entry);
+
+        let report = kv.verify(VerifyLevel::Full, |key| *key).unwrap();
+        assert!(report.findings.contains(&VerifyFinding::IndexInvariant(
+            IndexViolation::DuplicateTag {
+                bucket,
+                tag: entry.tag(),
+            }
+        )));
+        // Quick verification skips the index
+        assert!(kv.verify(VerifyLevel::Quick, |key| *key).unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_crash_at_every_kill_point() {
+        use crate::testing::killpoints::{self, KillPoint};
+
+        const KEYS: u64 = 8;
+        const ROUNDS: u64 = 4;
+
+        for point in KillPoint::ALL {
+            for skip in [0, 2] {
+                let temp = TempDir::new("killpoint");
+                let dir = temp.path();
+                // Round whose writes were made durable, per key, and the last
+                // checkpoint that completed.
+                let mut acked: [Option<u64>; KEYS as usize] = [None; KEYS as usize];
+                let mut token: Option<String> = None;
+
+                let killed = killpoints::run_until_killed(point, skip, || {
+                    let disk = FileSystemDisk::new(dir).unwrap();
+                    let mut kv =
+                        RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+                    for round in 0..ROUNDS {
+                        for key in 0..KEYS {
+                            let value = round * 100 + key;
+                            assert_eq!(kv.upsert(&TestUpsertContext { key, value }), Status::Ok);
+                        }
+                        if round % 2 == 0 {
+                            kv.flush().unwrap();
+                        } else {
+                            let name = format!("cp{}", round);
+                            kv.checkpoint(&name).unwrap();
+                            token = Some(name);
+                        }
+                        acked = [Some(round); KEYS as usize];
+                    }
+                });
+                if skip == 0 {
+                    assert_eq!(killed, Some(point));
+                }
+
+                let kv = RsKv::<u64, u64, FileSystemDisk>::open(
+                    dir,
+                    token.as_deref(),
+                    &test_options(),
+                    |key| *key,
+                )
+                .unwrap();
+                for key in 0..KEYS {
+                    match (read_value(&kv, key), acked[key as usize]) {
+                        (Some(value), acked_round) => {
+                            assert_eq!(value % 100, key, "{:?}/{}", point, skip);
+                            assert!(value / 100 < ROUNDS);
+                            assert!(acked_round.is_none_or(|round| value / 100 >= round));
+                        }
+                        (None, acked_round) => {
+                            assert_eq!(acked_round, None, "{:?}/{}: key {} lost", point, skip, key)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hot_keys_under_zipfian_workload() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        const KEYS: usize = 1000;
+        const OPS: usize = 200_000;
+        let temp = TempDir::new("hot_keys");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+
+        // Rank r is drawn with probability proportional to 1 / r^1.1; ranks
+        // are scattered over the key space so hot keys are not adjacent.
+        let mut cdf = Vec::with_capacity(KEYS);
+        let mut total = 0.0;
+        for rank in 1..=KEYS {
+            total += 1.0 / (rank as f64).powf(1.1);
+            cdf.push(total);
+        }
+        let key_of_rank = |rank: usize| (rank as u64 * 7919) % KEYS as u64;
+
+        let mut rng = StdRng::seed_from_u64(1928);
+        let mut counts = vec![0u64; KEYS];
+        for _ in 0..OPS {
+            let draw = rng.random::<f64>() * total;
+            let rank = cdf.partition_point(|p| *p < draw).min(KEYS - 1);
+            let key = key_of_rank(rank);
+            counts[key as usize] += 1;
+            if rng.random_bool(0.5) {
+                assert_eq!(
+                    kv.upsert(&TestUpsertContext { key, value: key }),
+                    Status::Ok
+                );
+            } else {
+                read_value(&kv, key);
+            }
+        }
+
+        let mut by_count: Vec<u64> = (0..KEYS as u64).collect();
+        by_count.sort_by_key(|key| std::cmp::Reverse(counts[*key as usize]));
+        let reported: Vec<u64> = kv.hot_keys(20).into_iter().map(|(key, _)| key).collect();
+        assert_eq!(reported.len(), 20);
+        for key in &by_count[..10] {
+            assert!(
+                reported.contains(key),
+                "key {} with {} accesses missing from {:?}",
+                key,
+                counts[*key as usize],
+                reported
+            );
+        }
+
+        kv.reset_hot_keys();
+        assert!(kv.hot_keys(20).is_empty());
+        kv.set_hot_key_sampling(0);
+        read_value(&kv, by_count[0]);
+        assert!(kv.hot_keys(20).is_empty());
+    }
+
+    #[test]
+    fn test_access_analyzer_sees_reads_and_upserts() {
+        let temp = TempDir::new("access_analyzer");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        let analyzer = Arc::new(AccessAnalyzer::new(Default::default()));
+        kv.set_access_analyzer(Some(Arc::clone(&analyzer)));
+
+        for value in 0..10 {
+            assert_eq!(kv.upsert(&TestUpsertContext { key: 1, value }), Status::Ok);
+            assert_eq!(read_value(&kv, 1), Some(value));
+        }
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 2, value: 0 }),
+            Status::Ok
+        );
+
+        assert_eq!(analyzer.classify(1), Heat::Hot);
+        assert_eq!(analyzer.classify(2), Heat::Cold);
+        assert_eq!(analyzer.classify(3), Heat::Cold);
+        assert_eq!(analyzer.page_heat(0), Heat::Hot);
+
+        kv.set_access_analyzer(None);
+        read_value(&kv, 2);
+        assert_eq!(analyzer.classify(2), Heat::Cold);
+    }
+
+    #[test]
+    fn test_write_combining_upserts() {
+        const THREADS: u64 = 8;
+        const KEYS: u64 = 500;
+        let temp = TempDir::new("write_combining");
+        let dir = temp.path();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            kv.set_write_combining(Some(WriteCombinerConfig {
+                max_batch_size: 32,
+                linger: Duration::from_micros(200),
+            }));
+
+            std::thread::scope(|scope| {
+                for thread in 0..THREADS {
+                    let kv = &kv;
+                    scope.spawn(move || {
+                        for round in 0..2 {
+                            for i in 0..KEYS {
+                                let key = thread * KEYS + i;
+                                let context = TestUpsertContext {
+                                    key,
+                                    value: key + round,
+                                };
+                                assert_eq!(kv.upsert(&context), Status::Ok);
+                            }
+                        }
+                    });
+                }
+            });
+
+            let stats = kv.write_combining_stats().unwrap();
+            assert_eq!(stats.total_ops, THREADS * KEYS * 2);
+            assert!(stats.total_batches < stats.total_ops);
+            for key in 0..THREADS * KEYS {
+                assert_eq!(read_value(&kv, key), Some(key + 1), "key {}", key);
+            }
+            kv.flush().unwrap();
+        }
+
+        // Batched records replay like any others.
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, None, &test_options(), |key| *key).unwrap();
+        for key in 0..THREADS * KEYS {
+            assert_eq!(read_value(&kv, key), Some(key + 1), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn test_mutable_region_follows_workload() {
+        struct InPlaceUpsertContext {
+            key: u64,
+            value: u64,
+        }
+
+        impl UpsertContext for InPlaceUpsertContext {
+            type Key = u64;
+            type Value = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn value(&self) -> &Self::Value {
+                &self.value
+            }
+
+            fn key_hash(&self) -> u64 {
+                self.key
+            }
+
+            fn put_atomic(&self, value: &mut Self::Value) -> bool {
+                *value = self.value;
+                true
+            }
+        }
+
+        let temp = TempDir::new("mutable_region");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 12, disk).unwrap();
+        kv.set_mutable_region_control(Some(MutableRegionConfig {
+            min_fraction: 0.0,
+            max_fraction: 0.5,
+            initial_fraction: 0.25,
+            step: 0.25,
+            window: 1000,
+            ..Default::default()
+        }));
+
+        // Updates to a small key set land in place and grow the region.
+        for i in 0..5000 {
+            let context = InPlaceUpsertContext {
+                key: i % 100,
+                value: i,
+            };
+            assert_eq!(kv.upsert(&context), Status::Ok);
+        }
+        let stats = kv.mutable_region_stats().unwrap();
+        assert_eq!(stats.target_fraction, 0.5);
+        assert!(stats.in_place_hit_rate > 0.9);
+        assert_eq!(kv.hlog.get_read_only_address().control(), 0);
+
+        // Inserts never hit in place, so the boundary closes up on the tail.
+        for key in 1000..6000 {
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key, value: key }),
+                Status::Ok
+            );
+        }
+        let stats = kv.mutable_region_stats().unwrap();
+        assert_eq!(stats.target_fraction, 0.0);
+        assert_eq!(stats.in_place_hit_rate, 0.0);
+        assert!(kv.hlog.get_read_only_address() > Address::from_control(0));
+
+        // Records below the boundary are copied rather than updated in place.
+        let tail = kv.hlog.get_tail_address();
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 1, value: 7 }),
+            Status::Ok
+        );
+        assert!(kv.hlog.get_tail_address() > tail);
+        assert_eq!(read_value(&kv, 1), Some(7));
+    }
+
+    #[test]
+    fn test_bulk_load_chains_colliding_keys() {
+        struct CollidingReadContext {
+            key: u64,
+            value: Option<u64>,
+        }
+
+        impl ReadContext for CollidingReadContext {
+            type Key = u64;
+            type Value = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                self.key % 4
+            }
+
+            fn get(&mut self, value: &Self::Value) {
+                self.value = Some(*value);
+            }
+        }
+
+        let temp = TempDir::new("bulk_load");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+
+        // Every key shares one of four index entries, and the second pass
+        // overwrites the first within the same chunks.
+        let records = (0..3000)
+            .map(|key| (key, key))
+            .chain((0..3000).map(|key| (key, key * 2)));
+        assert_eq!(kv.bulk_load(records, |key| key % 4), Ok(6000));
+        assert_eq!(kv.index.entry_count(), 4);
+        for key in 0..3000 {
+            let mut context = CollidingReadContext { key, value: None };
+            assert_eq!(kv.read(&mut context), Status::Ok);
+            assert_eq!(context.value, Some(key * 2), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn test_lock_key_excludes_other_threads() {
+        let temp = TempDir::new("lock_key");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        assert_eq!(kv.lock_key(&7, 7).err(), Some(Status::InvalidConfiguration));
+
+        kv.set_key_locking(Some(KeyLockOptions {
+            stripes: 64,
+            locked_writes: LockedKeyWrites::Fail,
+        }));
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 7, value: 1 }),
+            Status::Ok
+        );
+
+        let guard = kv.lock_key(&7, 7).unwrap();
+        assert_eq!(*guard.key(), 7);
+        // The holder writes through its own lock
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 7, value: 2 }),
+            Status::Ok
+        );
+
+        std::thread::scope(|scope| {
+            let kv = &kv;
+            scope
+                .spawn(move || {
+                    assert_eq!(
+                        kv.upsert(&TestUpsertContext { key: 7, value: 3 }),
+                        Status::LockTimeout
+                    );
+                    assert_eq!(kv.lock_key(&7, 7).err(), Some(Status::LockTimeout));
+                    // Reads and other stripes are not held up
+                    assert_eq!(read_value(kv, 7), Some(2));
+                    assert_eq!(
+                        kv.upsert(&TestUpsertContext { key: 8, value: 8 }),
+                        Status::Ok
+                    );
+                })
+                .join()
+                .unwrap();
+        });
+        drop(guard);
+        assert_eq!(
+            kv.upsert(&TestUpsertContext { key: 7, value: 4 }),
+            Status::Ok
+        );
+
+        // In wait mode a writer lands once the holder is done
+        kv.set_key_locking(Some(KeyLockOptions::default()));
+        let guard = kv.lock_key(&7, 7).unwrap();
+        std::thread::scope(|scope| {
+            let kv = &kv;
+            let writer = scope.spawn(move || kv.upsert(&TestUpsertContext { key: 7, value: 6 }));
+            assert_eq!(
+                kv.upsert(&TestUpsertContext { key: 7, value: 5 }),
+                Status::Ok
+            );
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(!writer.is_finished());
+            drop(guard);
+            assert_eq!(writer.join().unwrap(), Status::Ok);
+        });
+        assert_eq!(read_value(&kv, 7), Some(6));
+        assert_eq!(read_value(&kv, 8), Some(8));
+    }
+
+    #[test]
+    fn test_concurrent_upserts_to_one_key_keep_its_chain() {
+        const THREADS: u64 = 8;
+        const WRITES: u64 = 200;
+        let temp = TempDir::new("contended_upserts");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+
+        // Every upsert appends, and losing a race for the index entry
+        // appends again on top of the winner.
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let kv = &kv;
+                scope.spawn(move || {
+                    for i in 0..WRITES {
+                        let value = thread * 1000 + i;
+                        assert_eq!(kv.upsert(&TestUpsertContext { key: 7, value }), Status::Ok);
+                    }
+                });
+            }
+        });
+
+        let versions = kv.read_versions(&7, 7, usize::MAX).unwrap();
+        assert_eq!(versions.len() as u64, THREADS * WRITES);
+        // The chain runs strictly back through the log
+        assert!(
+            versions
+                .windows(2)
+                .all(|pair| pair[0].address > pair[1].address)
+        );
+
+        // The newest version is the one read, and the last of its writer's
+        let last = read_value(&kv, 7).unwrap();
+        assert_eq!(versions[0].value, last);
+        assert_eq!(last % 1000, WRITES - 1);
+
+        // Each writer's versions sit in the chain in the order it wrote them
+        for thread in 0..THREADS {
+            let written: Vec<u64> = versions
+                .iter()
+                .rev()
+                .map(|version| version.value)
+                .filter(|value| value / 1000 == thread)
+                .collect();
+            assert_eq!(
+                written,
+                (0..WRITES).map(|i| thread * 1000 + i).collect::<Vec<_>>()
+            );
+        }
+
+        let report = kv.verify(VerifyLevel::Full, |key| *key).unwrap();
+        assert!(report.is_clean(), "{:?}", report.findings);
+    }
+
+    fn by_tens(_key: &u64, value: &u64) -> Option<Vec<u8>> {
+        // Values of 1000 and up are left out of the index
+        (*value < 1000).then(|| (value / 10).to_be_bytes().to_vec())
+    }
+
+    fn tens(group: u64) -> Vec<u8> {
+        group.to_be_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_secondary_index_follows_overwrites_and_deletes() {
+        struct AddTen {
+            key: u64,
+        }
+
+        impl RmwContext for AddTen {
+            type Key = u64;
+            type Value = u64;
+
+            fn key(&self) -> &Self::Key {
+                &self.key
+            }
+
+            fn key_hash(&self) -> u64 {
+                self.key
+            }
+
+            fn rmw_initial(&self, value: &mut Self::Value) {
+                *value = 0;
+            }
+
+            fn rmw_copy(&self, old_value: &Self::Value, new_value: &mut Self::Value) {
+                *new_value = old_value + 10;
+            }
+
+            fn rmw_atomic(&self, _value: &mut Self::Value) -> bool {
+                false
+            }
+        }
+
+        let temp = TempDir::new("secondary_writes");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for key in 0..30 {
+            let context = TestUpsertContext { key, value: key };
+            assert_eq!(kv.upsert(&context), Status::Ok);
+        }
+        assert_eq!(kv.delete(&TestDeleteContext { key: 5 }), Status::Ok);
+        assert_eq!(
+            kv.lookup_secondary("by_tens", &tens(0)),
+            Err(Status::NotFound)
+        );
+
+        // Existing records are backfilled
+        assert_eq!(kv.create_secondary_index("by_tens", by_tens), Ok(29));
+        assert_eq!(
+            kv.create_secondary_index("by_tens", by_tens),
+            Err(Status::InvalidConfiguration)
+        );
+        assert_eq!(kv.secondary_index_names(), vec!["by_tens".to_string()]);
+        let mut group = kv.lookup_secondary("by_tens", &tens(0)).unwrap();
+        group.sort();
+        assert_eq!(group, vec![0, 1, 2, 3, 4, 6, 7, 8, 9]);
+
+        // An overwrite moves the key, a delete and an unindexed value drop it
+        let context = TestUpsertContext { key: 3, value: 25 };
+        assert_eq!(kv.upsert(&context), Status::Ok);
+        assert_eq!(kv.rmw(&mut AddTen { key: 4 }), Status::Ok);
+        assert_eq!(kv.delete(&TestDeleteContext { key: 12 }), Status::Ok);
+        let context = TestUpsertContext {
+            key: 13,
+            value: 1300,
+        };
+        assert_eq!(kv.upsert(&context), Status::Ok);
+        assert_eq!(kv.rmw(&mut AddTen { key: 40 }), Status::Ok);
+        assert_eq!(kv.bulk_load([(50, 19), (51, 5)], |key| *key), Ok(2));
+        assert_eq!(kv.delete(&TestDeleteContext { key: 99 }), Status::NotFound);
+
+        let lookup = |group: u64| {
+            let mut keys = kv.lookup_secondary("by_tens", &tens(group)).unwrap();
+            keys.sort();
+            keys
+        };
+        assert_eq!(lookup(0), vec![0, 1, 2, 6, 7, 8, 9, 40, 51]);
+        assert_eq!(lookup(1), vec![4, 10, 11, 14, 15, 16, 17, 18, 19, 50]);
+        assert_eq!(lookup(2), vec![3, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29]);
+        assert_eq!(lookup(5), Vec::<u64>::new());
+
+        assert!(kv.drop_secondary_index("by_tens"));
+        assert!(!kv.drop_secondary_index("by_tens"));
+        assert_eq!(
+            kv.lookup_secondary("by_tens", &tens(0)),
+            Err(Status::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_secondary_index_persists_through_checkpoint() {
+        let temp = TempDir::new("secondary_checkpoint");
+        let dir = temp.path();
+        {
+            let disk = FileSystemDisk::new(dir).unwrap();
+            let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+            kv.create_secondary_index("by_tens", by_tens).unwrap();
+            for key in 0..40 {
+                let context = TestUpsertContext { key, value: key };
+                assert_eq!(kv.upsert(&context), Status::Ok);
+            }
+            kv.checkpoint("cp").unwrap();
+        }
+
+        // Nothing after the checkpoint: the saved contents are reused
+        let mut kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, Some("cp"), &test_options(), |key| *key)
+                .unwrap();
+        assert_eq!(
+            kv.recovered_secondary_index_names(),
+            vec!["by_tens".to_string()]
+        );
+        assert_eq!(kv.create_secondary_index("by_tens", by_tens), Ok(40));
+        assert!(kv.recovered_secondary_index_names().is_empty());
+        let mut group = kv.lookup_secondary("by_tens", &tens(3)).unwrap();
+        group.sort();
+        assert_eq!(group, (30..40).collect::<Vec<_>>());
+
+        // Records replayed after the checkpoint make the saved copy stale,
+        // so the index is rebuilt from the log
+        assert_eq!(kv.delete(&TestDeleteContext { key: 31 }), Status::Ok);
+        let context = TestUpsertContext { key: 2, value: 35 };
+        assert_eq!(kv.upsert(&context), Status::Ok);
+        kv.flush().unwrap();
+        drop(kv);
+        let mut kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, Some("cp"), &test_options(), |key| *key)
+                .unwrap();
+        assert!(kv.recovered_secondary_index_names().is_empty());
+        assert_eq!(kv.create_secondary_index("by_tens", by_tens), Ok(39));
+        let mut group = kv.lookup_secondary("by_tens", &tens(3)).unwrap();
+        group.sort();
+        assert_eq!(group, vec![2, 30, 32, 33, 34, 35, 36, 37, 38, 39]);
+    }
+
+    #[test]
+    fn test_write_sequence_numbers_and_read_at_least() {
+        let temp = TempDir::new("write_seq");
+        let dir = temp.path();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(
+            1 << 26,
+            1 << 10,
+            FileSystemDisk::new(dir).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(kv.applied_seq(), 0);
+        let context = TestUpsertContext { key: 1, value: 10 };
+        assert_eq!(kv.upsert_with_seq(&context), Ok(1));
+        let context = TestUpsertContext { key: 2, value: 20 };
+        assert_eq!(kv.upsert_with_seq(&context), Ok(2));
+        assert_eq!(kv.delete_with_seq(&TestDeleteContext { key: 1 }), Ok(3));
+        // Failed writes take no number
+        assert_eq!(
+            kv.delete_with_seq(&TestDeleteContext { key: 1 }),
+            Err(Status::NotFound)
+        );
+        assert_eq!(
+            kv.bulk_load((10..20).map(|key| (key, key)), |key| *key),
+            Ok(10)
+        );
+        assert_eq!(kv.applied_seq(), 13);
+
+        let mut context = TestReadContext {
+            key: 2,
+            value: None,
+        };
+        assert_eq!(
+            kv.read_at_least(&mut context, 13, Duration::ZERO),
+            Status::Ok
+        );
+        assert_eq!(context.value, Some(20));
+        let mut context = TestReadContext {
+            key: 3,
+            value: None,
+        };
+        assert_eq!(
+            kv.read_at_least(&mut context, 14, Duration::from_millis(20)),
+            Status::Pending
+        );
+        assert_eq!(context.value, None);
+
+        // A reader holding a token waits for the write it names
+        std::thread::scope(|scope| {
+            let kv = &kv;
+            let reader = scope.spawn(move || {
+                let mut context = TestReadContext {
+                    key: 3,
+                    value: None,
+                };
+                let status = kv.read_at_least(&mut context, 14, Duration::from_secs(5));
+                (status, context.value)
+            });
+            std::thread::sleep(Duration::from_millis(10));
+            let context = TestUpsertContext { key: 3, value: 30 };
+            assert_eq!(kv.upsert_with_seq(&context), Ok(14));
+            assert_eq!(reader.join().unwrap(), (Status::Ok, Some(30)));
+        });
+
+        // Saved by checkpoints and advanced by replayed records
+        kv.checkpoint("seq").unwrap();
+        for key in 40..43 {
+            let context = TestUpsertContext { key, value: key };
+            assert_eq!(kv.upsert(&context), Status::Ok);
+        }
+        kv.flush().unwrap();
+        drop(kv);
+        let kv =
+            RsKv::<u64, u64, FileSystemDisk>::open(dir, Some("seq"), &test_options(), |key| *key)
+                .unwrap();
+        assert_eq!(kv.applied_seq(), 17);
+        let context = TestUpsertContext { key: 50, value: 50 };
+        assert_eq!(kv.upsert_with_seq(&context), Ok(18));
+    }
+
+    #[test]
+    fn test_write_and_read_options() {
+        let temp = TempDir::new("options");
+        let dir = temp.path();
+        let disk = FileSystemDisk::new(dir).unwrap();
+        let kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+
+        // Buffered writes wait for the next flush, synced ones do not
+        let context = TestUpsertContext { key: 1, value: 1 };
+        assert_eq!(kv.upsert_opts(&context, &WriteOptions::default()), Ok(1));
+        assert!(kv.flush_backlog_bytes() > 0);
+        assert_eq!(kv.disk.sync_count(), 0);
+        let sync = WriteOptions::default().durability(Durability::Sync);
+        let context = TestUpsertContext { key: 2, value: 2 };
+        assert_eq!(kv.upsert_opts(&context, &sync), Ok(2));
+        assert_eq!(kv.flush_backlog_bytes(), 0);
+        let syncs = kv.disk.sync_count();
+        assert!(syncs > 0);
+        assert_eq!(kv.delete_opts(&TestDeleteContext { key: 1 }, &sync), Ok(3));
+        assert_eq!(kv.flush_backlog_bytes(), 0);
+        assert!(kv.disk.sync_count() > syncs);
+
+        // A TTL is refused before anything is written
+        let ttl = WriteOptions::default().ttl(Duration::from_secs(60));
+        let context = TestUpsertContext { key: 3, value: 3 };
+        assert_eq!(
+            kv.upsert_opts(&context, &ttl),
+            Err(Status::FeatureNotSupported)
+        );
+        assert_eq!(
+            kv.delete_opts(&TestDeleteContext { key: 2 }, &ttl),
+            Err(Status::FeatureNotSupported)
+        );
+        assert_eq!(read_value(&kv, 3), None);
+        assert_eq!(read_value(&kv, 2), Some(2));
+        assert_eq!(kv.applied_seq(), 3);
+
+        // Untracked reads leave hot key tracking alone
+        kv.bulk_load([(9, 9)], |key| *key).unwrap();
+        kv.set_hot_key_sampling(1);
+        kv.reset_hot_keys();
+        let untracked = ReadOptions::default().track_access(false);
+        for _ in 0..5 {
+            let mut context = TestReadContext {
+                key: 9,
+                value: None,
+            };
+            assert_eq!(kv.read_opts(&mut context, &untracked), Status::Ok);
+            assert_eq!(context.value, Some(9));
+        }
+        assert!(kv.hot_keys(10).is_empty());
+        assert_eq!(read_value(&kv, 9), Some(9));
+        assert_eq!(kv.hot_keys(10), vec![(9, 1)]);
+
+        // A sequence number not yet applied times out without reading
+        let ahead = ReadOptions::default()
+            .min_seq(kv.applied_seq() + 1)
+            .wait_timeout(Duration::from_millis(10));
+        let mut context = TestReadContext {
+            key: 9,
+            value: None,
+        };
+        assert_eq!(kv.read_opts(&mut context, &ahead), Status::Pending);
+        assert_eq!(context.value, None);
     }
 }
@@ -0,0 +1,294 @@
+//! Secondary indexes over value fields.
+//!
+//! An index created with
+//! [`RsKv::create_secondary_index`](crate::rskv_core::RsKv::create_secondary_index)
+//! maps the secondary key its extractor derives from each live record to the
+//! primary keys holding it, and answers
+//! [`RsKv::lookup_secondary`](crate::rskv_core::RsKv::lookup_secondary)
+//! without scanning the log.
+//!
+//! Indexes are updated synchronously with the write that changes them. While
+//! any index exists, every upsert, read-modify-write and delete reads the
+//! previous value of its key, writes, and moves the key from the old
+//! secondary key to the new one while holding the index lock, so writes are
+//! serialized with each other and with lookups. A lookup therefore sees every
+//! write that has returned and none that has not started; a plain `read`
+//! racing a write may see the new value before the lookup does.
+//!
+//! A checkpoint saves the names and contents of all indexes. Extractors are
+//! code and cannot be saved, so after reopening, each index is attached again
+//! with `create_secondary_index`. Its saved contents are reused when no
+//! records were replayed after the checkpoint, and rebuilt from the log
+//! otherwise.
+
+use crate::core::status::Status;
+use crate::core::utility::crc32_update;
+use std::collections::HashMap;
+
+/// Name of the file in a checkpoint directory holding the secondary indexes.
+pub(crate) const SECONDARY_INDEX_FILE: &str = "secondary.idx";
+
+/// Derives the secondary key of a record, or `None` to leave it unindexed.
+pub type SecondaryKeyExtractor<K, V> = fn(&K, &V) -> Option<Vec<u8>>;
+
+/// Primary keys by secondary key.
+pub(crate) type SecondaryEntries<K> = HashMap<Vec<u8>, Vec<K>>;
+
+struct SecondaryIndex<K, V> {
+    name: String,
+    extractor: SecondaryKeyExtractor<K, V>,
+    entries: SecondaryEntries<K>,
+}
+
+impl<K: Copy + PartialEq, V> SecondaryIndex<K, V> {
+    fn update(&mut self, key: &K, old: Option<&V>, new: Option<&V>) {
+        let old = old.and_then(|value| (self.extractor)(key, value));
+        let new = new.and_then(|value| (self.extractor)(key, value));
+        if old == new {
+            return;
+        }
+        if let Some(old) = old
+            && let Some(keys) = self.entries.get_mut(&old)
+        {
+            keys.retain(|indexed| indexed != key);
+            if keys.is_empty() {
+                self.entries.remove(&old);
+            }
+        }
+        if let Some(new) = new {
+            self.entries.entry(new).or_default().push(*key);
+        }
+    }
+}
+
+/// All secondary indexes of one store.
+pub(crate) struct SecondaryIndexes<K, V> {
+    indexes: Vec<SecondaryIndex<K, V>>,
+}
+
+impl<K: Copy + PartialEq, V> SecondaryIndexes<K, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            indexes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.indexes.iter().any(|index| index.name == name)
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.indexes
+            .iter()
+            .map(|index| index.name.clone())
+            .collect()
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        name: &str,
+        extractor: SecondaryKeyExtractor<K, V>,
+        entries: SecondaryEntries<K>,
+    ) {
+        self.indexes.push(SecondaryIndex {
+            name: name.to_string(),
+            extractor,
+            entries,
+        });
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> bool {
+        let before = self.indexes.len();
+        self.indexes.retain(|index| index.name != name);
+        self.indexes.len() < before
+    }
+
+    /// Moves `key` from the secondary keys of `old` to those of `new` in
+    /// every index; `None` means the key was or is absent.
+    pub(crate) fn update(&mut self, key: &K, old: Option<&V>, new: Option<&V>) {
+        for index in &mut self.indexes {
+            index.update(key, old, new);
+        }
+    }
+
+    pub(crate) fn lookup(&self, name: &str, secondary_key: &[u8]) -> Option<Vec<K>> {
+        let index = self.indexes.iter().find(|index| index.name == name)?;
+        Some(
+            index
+                .entries
+                .get(secondary_key)
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Serializes the names and contents of every index, followed by a CRC.
+    /// Keys are stored as their raw bytes.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        put_u32(&mut bytes, self.indexes.len() as u32);
+        for index in &self.indexes {
+            put_bytes(&mut bytes, index.name.as_bytes());
+            put_u32(&mut bytes, index.entries.len() as u32);
+            for (secondary_key, keys) in &index.entries {
+                put_bytes(&mut bytes, secondary_key);
+                put_u32(&mut bytes, keys.len() as u32);
+                for key in keys {
+                    bytes.extend_from_slice(unsafe {
+                        std::slice::from_raw_parts(key as *const K as *const u8, size_of::<K>())
+                    });
+                }
+            }
+        }
+        let crc = crc32_update(0, &bytes);
+        put_u32(&mut bytes, crc);
+        bytes
+    }
+}
+
+/// Contents of an index with `extractor` over `records`, given as the live
+/// key/value pairs of a store.
+pub(crate) fn build_entries<K: Copy + PartialEq, V>(
+    extractor: SecondaryKeyExtractor<K, V>,
+    records: impl IntoIterator<Item = (K, V)>,
+) -> SecondaryEntries<K> {
+    let mut index = SecondaryIndex {
+        name: String::new(),
+        extractor,
+        entries: SecondaryEntries::new(),
+    };
+    for (key, value) in records {
+        index.update(&key, None, Some(&value));
+    }
+    index.entries
+}
+
+/// Reads back what [`SecondaryIndexes::encode`] wrote, as the contents of
+/// each index by name.
+pub(crate) fn decode_entries<K: Copy>(
+    bytes: &[u8],
+) -> Result<HashMap<String, SecondaryEntries<K>>, Status> {
+    let (body, crc) = bytes
+        .split_last_chunk::<4>()
+        .ok_or(Status::InvalidDataFormat)?;
+    if crc32_update(0, body) != u32::from_le_bytes(*crc) {
+        return Err(Status::ChecksumMismatch);
+    }
+    let mut reader = Reader { bytes: body };
+    let mut indexes = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let name =
+            String::from_utf8(reader.bytes()?.to_vec()).map_err(|_| Status::InvalidDataFormat)?;
+        let mut entries = SecondaryEntries::new();
+        for _ in 0..reader.u32()? {
+            let secondary_key = reader.bytes()?.to_vec();
+            let count = reader.u32()? as usize;
+            let raw = reader.take(count * size_of::<K>())?;
+            let keys = raw
+                .chunks_exact(size_of::<K>().max(1))
+                .take(count)
+                .map(|chunk| unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const K) })
+                .collect();
+            entries.insert(secondary_key, keys);
+        }
+        indexes.insert(name, entries);
+    }
+    if !reader.bytes.is_empty() {
+        return Err(Status::InvalidDataFormat);
+    }
+    Ok(indexes)
+}
+
+fn put_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_bytes(bytes: &mut Vec<u8>, data: &[u8]) {
+    put_u32(bytes, data.len() as u32);
+    bytes.extend_from_slice(data);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Status> {
+        if self.bytes.len() < len {
+            return Err(Status::InvalidDataFormat);
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, Status> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], Status> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn by_parity(_key: &u64, value: &u64) -> Option<Vec<u8>> {
+        Some(vec![(value % 2) as u8])
+    }
+
+    fn unindexed(_key: &u64, _value: &u64) -> Option<Vec<u8>> {
+        None
+    }
+
+    #[test]
+    fn test_update_moves_key_between_secondary_keys() {
+        let mut indexes = SecondaryIndexes::<u64, u64>::new();
+        indexes.insert("parity", by_parity, SecondaryEntries::new());
+        indexes.update(&1, None, Some(&10));
+        indexes.update(&2, None, Some(&20));
+        assert_eq!(indexes.lookup("parity", &[0]), Some(vec![1, 2]));
+
+        indexes.update(&1, Some(&10), Some(&11));
+        assert_eq!(indexes.lookup("parity", &[0]), Some(vec![2]));
+        assert_eq!(indexes.lookup("parity", &[1]), Some(vec![1]));
+
+        indexes.update(&2, Some(&20), None);
+        assert_eq!(indexes.lookup("parity", &[0]), Some(vec![]));
+        assert_eq!(indexes.lookup("missing", &[0]), None);
+    }
+
+    #[test]
+    fn test_encode_round_trips_and_detects_damage() {
+        let mut indexes = SecondaryIndexes::<u64, u64>::new();
+        indexes.insert("parity", by_parity, SecondaryEntries::new());
+        indexes.insert("empty", unindexed, SecondaryEntries::new());
+        for key in 0..10 {
+            indexes.update(&key, None, Some(&key));
+        }
+
+        let bytes = indexes.encode();
+        let decoded = decode_entries::<u64>(&bytes).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded["empty"].is_empty());
+        assert_eq!(decoded["parity"][&vec![1]], vec![1, 3, 5, 7, 9]);
+
+        let mut damaged = bytes.clone();
+        damaged[6] ^= 1;
+        assert_eq!(
+            decode_entries::<u64>(&damaged).err(),
+            Some(Status::ChecksumMismatch)
+        );
+        assert_eq!(
+            decode_entries::<u64>(&bytes[..2]).err(),
+            Some(Status::InvalidDataFormat)
+        );
+    }
+}
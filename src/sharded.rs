@@ -0,0 +1,558 @@
+//! Several [`RsKv`] stores behind one handle, each key owned by one shard.
+//!
+//! A sharded store is a directory holding a `SHARDS` manifest and one
+//! subdirectory per shard, each a complete store with its own log, index
+//! and checkpoints. Operations are routed by the context's key hash, so a
+//! key always lands on the same shard as long as the hash function and the
+//! shard count stay the same. The shard count is fixed when the store is
+//! created; opening it with a different count fails with
+//! `Status::InvalidConfiguration` rather than silently misrouting keys.
+
+use crate::core::status::Status;
+use crate::device::file_system_disk::FileSystemDisk;
+use crate::environment::file::{DirectoryLock, sync_directory};
+use crate::keys::KeyBytes;
+use crate::rskv_core::{
+    DeleteContext, ReadContext, RecoveryOptions, RmwContext, RsKv, UpsertContext,
+};
+use std::fs;
+use std::ops::RangeBounds;
+use std::path::Path;
+
+/// Name of the file recording the shard count
+pub const SHARD_MANIFEST: &str = "SHARDS";
+
+/// Directory of shard `index` inside `dir`
+pub fn shard_dir(dir: &str, index: usize) -> String {
+    format!("{}/shard-{:03}", dir.trim_end_matches('/'), index)
+}
+
+/// Shard owning `key_hash`. The hash is mixed first: shards see keys whose
+/// hashes differ in every bit, so each shard's index still uses all of its
+/// buckets.
+pub fn shard_of(key_hash: u64, num_shards: usize) -> usize {
+    // MurmurHash3 finalizer
+    let mut h = key_hash;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    (h % num_shards as u64) as usize
+}
+
+fn read_manifest(dir: &str) -> Result<Option<usize>, Status> {
+    let path = Path::new(dir).join(SHARD_MANIFEST);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    text.trim()
+        .strip_prefix("shards = ")
+        .and_then(|count| count.parse().ok())
+        .map(Some)
+        .ok_or(Status::InvalidDataFormat)
+}
+
+fn write_manifest(dir: &str, num_shards: usize) -> Result<(), Status> {
+    let staging = Path::new(dir).join(format!("{}.tmp", SHARD_MANIFEST));
+    fs::write(&staging, format!("shards = {}\n", num_shards))?;
+    fs::File::open(&staging)?.sync_all()?;
+    fs::rename(&staging, Path::new(dir).join(SHARD_MANIFEST))?;
+    sync_directory(dir)
+}
+
+fn check_shard_count(dir: &str, recorded: usize, requested: usize) -> Result<(), Status> {
+    if recorded != requested {
+        log::error!(
+            "{} holds {} shards but {} were requested; the shard count cannot change",
+            dir,
+            recorded,
+            requested
+        );
+        return Err(Status::InvalidConfiguration);
+    }
+    Ok(())
+}
+
+/// Outcome of [`ShardedRsKv::checkpoint`] on every shard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardedCheckpointReport {
+    pub token: String,
+    /// Indexed by shard
+    pub outcomes: Vec<Result<(), Status>>,
+}
+
+impl ShardedCheckpointReport {
+    /// Every shard took the checkpoint, so the store can be reopened from it
+    pub fn is_complete(&self) -> bool {
+        self.outcomes.iter().all(Result::is_ok)
+    }
+
+    /// Shards whose checkpoint failed, with the reason
+    pub fn failures(&self) -> Vec<(usize, Status)> {
+        self.outcomes
+            .iter()
+            .enumerate()
+            .filter_map(|(shard, outcome)| outcome.err().map(|status| (shard, status)))
+            .collect()
+    }
+}
+
+/// Size and backlog of one shard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShardStats {
+    pub table_size: u64,
+    pub index_entries: u64,
+    /// Bytes of log on disk
+    pub log_bytes: u64,
+    pub flush_backlog_bytes: u64,
+    /// Sequence number of the shard's newest write; see [`RsKv::applied_seq`]
+    pub applied_seq: u64,
+}
+
+/// [`ShardStats`] of every shard and their sums
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShardedStats {
+    pub shards: Vec<ShardStats>,
+    pub total: ShardStats,
+}
+
+/// A fixed number of [`RsKv`] shards under one directory
+pub struct ShardedRsKv<K, V> {
+    dir: String,
+    shards: Vec<RsKv<'static, K, V, FileSystemDisk>>,
+    _lock: DirectoryLock,
+}
+
+impl<K, V> ShardedRsKv<K, V>
+where
+    K: Sized + Copy + 'static + PartialEq,
+    V: Sized + Clone + 'static + Default,
+{
+    /// Creates `num_shards` empty shards in `dir`, each sized by `options`.
+    /// Fails with `Status::InvalidConfiguration` if `dir` already holds a
+    /// sharded store; reopen that with [`Self::open`].
+    pub fn new(dir: &str, options: &RecoveryOptions, num_shards: usize) -> Result<Self, Status> {
+        if num_shards == 0 {
+            return Err(Status::InvalidConfiguration);
+        }
+        fs::create_dir_all(dir)?;
+        let lock = DirectoryLock::acquire(dir, false)?;
+        if let Some(recorded) = read_manifest(dir)? {
+            log::error!(
+                "{} already holds a store of {} shards; reopen it with ShardedRsKv::open",
+                dir,
+                recorded
+            );
+            return Err(Status::InvalidConfiguration);
+        }
+        write_manifest(dir, num_shards)?;
+        let shards = (0..num_shards)
+            .map(|index| {
+                let disk = FileSystemDisk::new(&shard_dir(dir, index))?;
+                RsKv::new(options.log_size, options.table_size, disk)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            dir: dir.to_string(),
+            shards,
+            _lock: lock,
+        })
+    }
+
+    /// Opens every shard of the store in `dir` with [`RsKv::open`], from the
+    /// checkpoint `token` if given. Fails with `Status::NotFound` if `dir`
+    /// is not a sharded store and `Status::InvalidConfiguration` if it has a
+    /// different number of shards.
+    pub fn open(
+        dir: &str,
+        token: Option<&str>,
+        options: &RecoveryOptions,
+        num_shards: usize,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<Self, Status> {
+        if !Path::new(dir).is_dir() {
+            return Err(Status::NotFound);
+        }
+        let lock = DirectoryLock::acquire(dir, false)?;
+        let recorded = read_manifest(dir)?.ok_or(Status::NotFound)?;
+        check_shard_count(dir, recorded, num_shards)?;
+        let shards = (0..num_shards)
+            .map(|index| RsKv::open(&shard_dir(dir, index), token, options, &key_hash))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            dir: dir.to_string(),
+            shards,
+            _lock: lock,
+        })
+    }
+
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Shard that owns keys with hash `key_hash`
+    pub fn shard_for(&self, key_hash: u64) -> &RsKv<'static, K, V, FileSystemDisk> {
+        &self.shards[shard_of(key_hash, self.shards.len())]
+    }
+
+    pub fn shards(&self) -> &[RsKv<'static, K, V, FileSystemDisk>] {
+        &self.shards
+    }
+
+    pub fn upsert(&self, context: &impl UpsertContext<Key = K, Value = V>) -> Status {
+        self.shard_for(context.key_hash()).upsert(context)
+    }
+
+    pub fn read(&self, context: &mut impl ReadContext<Key = K, Value = V>) -> Status {
+        self.shard_for(context.key_hash()).read(context)
+    }
+
+    pub fn rmw(&self, context: &mut impl RmwContext<Key = K, Value = V>) -> Status {
+        self.shard_for(context.key_hash()).rmw(context)
+    }
+
+    pub fn delete(&self, context: &impl DeleteContext<Key = K>) -> Status {
+        self.shard_for(context.key_hash()).delete(context)
+    }
+
+    /// Splits `records` by shard and bulk loads each shard's part. Returns
+    /// the number of records written.
+    pub fn bulk_load(
+        &self,
+        records: impl IntoIterator<Item = (K, V)>,
+        key_hash: impl Fn(&K) -> u64,
+    ) -> Result<u64, Status> {
+        let mut parts: Vec<Vec<(K, V)>> = self.shards.iter().map(|_| Vec::new()).collect();
+        for (key, value) in records {
+            parts[shard_of(key_hash(&key), self.shards.len())].push((key, value));
+        }
+        let mut loaded = 0;
+        for (shard, part) in self.shards.iter().zip(parts) {
+            loaded += shard.bulk_load(part, &key_hash)?;
+        }
+        Ok(loaded)
+    }
+
+    /// [`RsKv::scan_range`] on every shard, merged into one list ordered by
+    /// key bytes. A key lives on one shard only, so nothing is merged away.
+    pub fn scan_range<'r>(&self, range: impl RangeBounds<&'r [u8]>) -> Result<Vec<(K, V)>, Status>
+    where
+        K: KeyBytes,
+    {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut records = Vec::new();
+        for shard in &self.shards {
+            records.extend(shard.scan_range(bounds)?);
+        }
+        records.sort_by(|(a, _), (b, _)| a.key_bytes().cmp(b.key_bytes()));
+        Ok(records)
+    }
+
+    /// Flushes every shard, even after one fails, and returns the first
+    /// failure
+    pub fn flush(&self) -> Result<(), Status> {
+        let mut first_failure = Ok(());
+        for shard in &self.shards {
+            if let Err(status) = shard.flush() {
+                first_failure = first_failure.and(Err(status));
+            }
+        }
+        first_failure
+    }
+
+    /// Checkpoints every shard under `token`. Shards are independent: a
+    /// failure on one does not stop the others, and the report says which
+    /// shards have the checkpoint. Reopen from `token` only if
+    /// [`ShardedCheckpointReport::is_complete`].
+    pub fn checkpoint(&mut self, token: &str) -> ShardedCheckpointReport {
+        let outcomes = self
+            .shards
+            .iter_mut()
+            .enumerate()
+            .map(|(index, shard)| {
+                let outcome = shard.checkpoint(token);
+                if let Err(status) = outcome {
+                    log::warn!(
+                        "checkpoint {} failed on shard {}: {:?}",
+                        token,
+                        index,
+                        status
+                    );
+                }
+                outcome
+            })
+            .collect();
+        ShardedCheckpointReport {
+            token: token.to_string(),
+            outcomes,
+        }
+    }
+
+    pub fn stats(&self) -> ShardedStats {
+        let shards: Vec<ShardStats> = self
+            .shards
+            .iter()
+            .map(|shard| ShardStats {
+                table_size: shard.get_table_size(),
+                index_entries: shard.index.entry_count(),
+                log_bytes: shard.hlog.disk_log_size(),
+                flush_backlog_bytes: shard.flush_backlog_bytes(),
+                applied_seq: shard.applied_seq(),
+            })
+            .collect();
+        let total = shards
+            .iter()
+            .fold(ShardStats::default(), |sum, shard| ShardStats {
+                table_size: sum.table_size + shard.table_size,
+                index_entries: sum.index_entries + shard.index_entries,
+                log_bytes: sum.log_bytes + shard.log_bytes,
+                flush_backlog_bytes: sum.flush_backlog_bytes + shard.flush_backlog_bytes,
+                applied_seq: sum.applied_seq + shard.applied_seq,
+            });
+        ShardedStats { shards, total }
+    }
+
+    /// Up to `k` of the hottest keys across all shards, hottest first
+    pub fn hot_keys(&self, k: usize) -> Vec<(K, u64)> {
+        let mut keys: Vec<(K, u64)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.hot_keys(k))
+            .collect();
+        keys.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+        keys.truncate(k);
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir::TempDir;
+
+    struct Put {
+        key: u64,
+        value: u64,
+    }
+
+    impl UpsertContext for Put {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &u64 {
+            &self.key
+        }
+
+        fn value(&self) -> &u64 {
+            &self.value
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn put_atomic(&self, _value: &mut u64) -> bool {
+            false
+        }
+    }
+
+    struct Get {
+        key: u64,
+        value: Option<u64>,
+    }
+
+    impl ReadContext for Get {
+        type Key = u64;
+        type Value = u64;
+
+        fn key(&self) -> &u64 {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+
+        fn get(&mut self, value: &u64) {
+            self.value = Some(*value);
+        }
+    }
+
+    struct Remove {
+        key: u64,
+    }
+
+    impl DeleteContext for Remove {
+        type Key = u64;
+
+        fn key(&self) -> &u64 {
+            &self.key
+        }
+
+        fn key_hash(&self) -> u64 {
+            self.key
+        }
+    }
+
+    fn options() -> RecoveryOptions {
+        RecoveryOptions {
+            log_size: 1 << 25,
+            table_size: 1 << 10,
+            ..Default::default()
+        }
+    }
+
+    fn get(store: &ShardedRsKv<u64, u64>, key: u64) -> Option<u64> {
+        let mut context = Get { key, value: None };
+        match store.read(&mut context) {
+            Status::Ok => context.value,
+            Status::NotFound => None,
+            status => panic!("read {} failed: {:?}", key, status),
+        }
+    }
+
+    #[test]
+    fn test_shard_of_spreads_sequential_hashes() {
+        let mut counts = [0usize; 8];
+        for key in 0..8_000u64 {
+            counts[shard_of(key, 8)] += 1;
+        }
+        for count in counts {
+            assert!((850..1150).contains(&count), "{:?}", counts);
+        }
+        // Keys on one shard still spread over the low bits the index uses
+        let low_bits: std::collections::HashSet<u64> = (0..8_000u64)
+            .filter(|key| shard_of(*key, 8) == 0)
+            .map(|key| key & 0xff)
+            .collect();
+        assert!(low_bits.len() > 200);
+    }
+
+    #[test]
+    fn test_operations_route_to_one_shard() {
+        let temp = TempDir::new("sharded_routes");
+        let dir = temp.path();
+        let store = ShardedRsKv::<u64, u64>::new(dir, &options(), 3).unwrap();
+        assert_eq!(store.num_shards(), 3);
+
+        assert_eq!(
+            store
+                .bulk_load((0..300).map(|key| (key, key * 10)), |key| *key)
+                .unwrap(),
+            300
+        );
+        for key in 300..330 {
+            assert_eq!(
+                store.upsert(&Put {
+                    key,
+                    value: key * 10
+                }),
+                Status::Ok
+            );
+        }
+        assert_eq!(store.delete(&Remove { key: 7 }), Status::Ok);
+
+        for key in 0..330 {
+            let expected = (key != 7).then_some(key * 10);
+            assert_eq!(get(&store, key), expected, "key {}", key);
+            // The key is only on its own shard
+            let owner = shard_of(key, 3);
+            for (index, shard) in store.shards().iter().enumerate() {
+                let mut context = Get { key, value: None };
+                let status = shard.read(&mut context);
+                if index != owner {
+                    assert_eq!(status, Status::NotFound);
+                }
+            }
+        }
+
+        let stats = store.stats();
+        assert_eq!(stats.shards.len(), 3);
+        assert!(stats.shards.iter().all(|shard| shard.index_entries > 50));
+        assert_eq!(stats.total.index_entries, 330);
+        assert_eq!(stats.total.table_size, 3 << 10);
+        // 330 upserts and one delete
+        assert_eq!(stats.total.applied_seq, 331);
+    }
+
+    #[test]
+    fn test_scan_range_merges_every_shard() {
+        let temp = TempDir::new("sharded_scan_range");
+        let store = ShardedRsKv::<u64, u64>::new(temp.path(), &options(), 3).unwrap();
+        store
+            .bulk_load((0..200).map(|key| (key, key * 10)), |key| *key)
+            .unwrap();
+        assert_eq!(store.delete(&Remove { key: 42 }), Status::Ok);
+
+        let mut live: Vec<u64> = (0..200).filter(|key| *key != 42).collect();
+        live.sort_by(|a, b| a.key_bytes().cmp(b.key_bytes()));
+        let scanned = store.scan_range(..).unwrap();
+        assert_eq!(
+            scanned.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            live
+        );
+        assert!(scanned.iter().all(|(key, value)| *value == key * 10));
+
+        // A bounded range keeps only the keys between its bounds
+        let (low, high) = (live[50], live[120]);
+        let scanned = store.scan_range(low.key_bytes()..high.key_bytes()).unwrap();
+        assert_eq!(
+            scanned.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            live[50..120]
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_reopens_only_with_the_same_shard_count() {
+        let temp = TempDir::new("sharded_reopen");
+        let dir = temp.path();
+        let mut store = ShardedRsKv::<u64, u64>::new(dir, &options(), 2).unwrap();
+        for key in 0..100 {
+            assert_eq!(
+                store.upsert(&Put {
+                    key,
+                    value: key + 1
+                }),
+                Status::Ok
+            );
+        }
+        let report = store.checkpoint("one");
+        assert!(report.is_complete(), "{:?}", report.failures());
+        assert_eq!(report.outcomes.len(), 2);
+        drop(store);
+
+        assert_eq!(
+            fs::read_to_string(Path::new(&dir).join(SHARD_MANIFEST)).unwrap(),
+            "shards = 2\n"
+        );
+        assert_eq!(
+            ShardedRsKv::<u64, u64>::open(dir, Some("one"), &options(), 3, |key| *key).err(),
+            Some(Status::InvalidConfiguration)
+        );
+        // An existing store is never recreated, whatever the shard count
+        for num_shards in [2, 4] {
+            assert_eq!(
+                ShardedRsKv::<u64, u64>::new(dir, &options(), num_shards).err(),
+                Some(Status::InvalidConfiguration)
+            );
+        }
+        let missing = TempDir::new("sharded_none");
+        assert_eq!(
+            ShardedRsKv::<u64, u64>::open(missing.path(), None, &options(), 2, |key| *key).err(),
+            Some(Status::NotFound)
+        );
+
+        let store =
+            ShardedRsKv::<u64, u64>::open(dir, Some("one"), &options(), 2, |key| *key).unwrap();
+        for key in 0..100 {
+            assert_eq!(get(&store, key), Some(key + 1));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! A [`Clock`] that only moves when told to, for testing time-driven code
+//! without sleeping.
+
+use crate::core::clock::Clock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Monotonic and wall time that stand still until
+/// [`advance`](Self::advance) moves both forward together.
+#[derive(Debug)]
+pub struct MockClock {
+    monotonic_origin: Instant,
+    wall_origin: SystemTime,
+    elapsed_ns: AtomicU64,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClock {
+    /// A clock stopped at the current time.
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    /// A clock stopped at wall time `wall`.
+    pub fn starting_at(wall: SystemTime) -> Self {
+        Self {
+            monotonic_origin: Instant::now(),
+            wall_origin: wall,
+            elapsed_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        self.elapsed_ns
+            .fetch_add(by.as_nanos() as u64, Ordering::AcqRel);
+    }
+
+    /// Time advanced since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns.load(Ordering::Acquire))
+    }
+}
+
+impl Clock for MockClock {
+    fn now_monotonic(&self) -> Instant {
+        self.monotonic_origin + self.elapsed()
+    }
+
+    fn now_wall(&self) -> SystemTime {
+        self.wall_origin + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_moves_only_when_advanced() {
+        let clock = MockClock::new();
+        let (monotonic, wall) = (clock.now_monotonic(), clock.now_wall());
+        assert_eq!(clock.now_monotonic(), monotonic);
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now_monotonic() - monotonic, Duration::from_secs(90));
+        assert_eq!(
+            clock.now_wall().duration_since(wall).unwrap(),
+            Duration::from_secs(90)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Named places where a crash test can kill the store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KillPoint {
+    /// A record is in the log but the index does not point to it yet
+    BeforeIndexPublish,
+    /// Flushed log data is on disk but its flush frame is not
+    AfterLogAppend,
+    /// Half of a flush chunk has been written
+    MidFlush,
+    /// The index snapshot is written but the checkpoint metadata is not
+    BetweenCheckpointWrites,
+}
+
+impl KillPoint {
+    pub const ALL: [KillPoint; 4] = [
+        KillPoint::BeforeIndexPublish,
+        KillPoint::AfterLogAppend,
+        KillPoint::MidFlush,
+        KillPoint::BetweenCheckpointWrites,
+    ];
+}
+
+/// Panic payload used to unwind out of the store at a kill point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Killed(pub KillPoint);
+
+thread_local! {
+    static ARMED: Cell<Option<(KillPoint, u32)>> = const { Cell::new(None) };
+}
+
+/// Arm `point` on the current thread. It fires on the hit after `skip` hits.
+pub fn arm(point: KillPoint, skip: u32) {
+    ARMED.with(|armed| armed.set(Some((point, skip))));
+}
+
+/// Disarm any kill point on the current thread.
+pub fn disarm() {
+    ARMED.with(|armed| armed.set(None));
+}
+
+/// Called by the hooks in the store. Unwinds with [`Killed`] when the armed
+/// point fires. The unwind drops the store without closing it, and poisons
+/// the log's disk lock so any further IO through it fails.
+pub fn hit(point: KillPoint) {
+    let fire = ARMED.with(|armed| match armed.get() {
+        Some((armed_point, 0)) if armed_point == point => {
+            armed.set(None);
+            true
+        }
+        Some((armed_point, skip)) if armed_point == point => {
+            armed.set(Some((armed_point, skip - 1)));
+            false
+        }
+        _ => false,
+    });
+    if fire {
+        panic::panic_any(Killed(point));
+    }
+}
+
+/// Run `workload` with `point` armed. Returns the kill point that fired, or
+/// `None` if the workload finished first. Panics other than a kill are
+/// propagated.
+pub fn run_until_killed<F: FnOnce()>(
+    point: KillPoint,
+    skip: u32,
+    workload: F,
+) -> Option<KillPoint> {
+    arm(point, skip);
+    let result = panic::catch_unwind(AssertUnwindSafe(workload));
+    disarm();
+    match result {
+        Ok(()) => None,
+        Err(payload) => match payload.downcast::<Killed>() {
+            Ok(killed) => Some(killed.0),
+            Err(payload) => panic::resume_unwind(payload),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_point_fires_after_skip() {
+        let hits = Cell::new(0);
+        let killed = run_until_killed(KillPoint::MidFlush, 2, || {
+            loop {
+                hit(KillPoint::AfterLogAppend);
+                hit(KillPoint::MidFlush);
+                hits.set(hits.get() + 1);
+            }
+        });
+        assert_eq!(killed, Some(KillPoint::MidFlush));
+        assert_eq!(hits.get(), 2);
+
+        // Disarmed after firing.
+        hit(KillPoint::MidFlush);
+        assert_eq!(run_until_killed(KillPoint::MidFlush, 0, || {}), None);
+    }
+}
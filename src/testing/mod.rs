@@ -0,0 +1,6 @@
+// Test support utilities
+
+pub mod clock;
+pub mod killpoints;
+pub mod model;
+pub mod temp_dir;
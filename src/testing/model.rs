@@ -0,0 +1,485 @@
+//! Model-based testing: run the same operations against a store and a
+//! simple reference model, and report the first step where they disagree.
+//!
+//! Keys and values are `u64`. [`RsKvTarget`] adapts an on-disk [`RsKv`] to
+//! [`KvTarget`]; implement the trait for your own wrapper to run traces
+//! through it. [`random_trace`] generates traces, but any `Vec<Op>` works.
+
+use crate::core::status::Status;
+use crate::device::file_system_disk::FileSystemDisk;
+use crate::rskv_core::{
+    DeleteContext, ReadContext, RecoveryOptions, RmwContext, RsKv, UpsertContext,
+};
+use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One step of a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Upsert {
+        key: u64,
+        value: u64,
+    },
+    Read {
+        key: u64,
+    },
+    Delete {
+        key: u64,
+    },
+    /// Adds `delta` to the value, starting from 0 for a missing key
+    Rmw {
+        key: u64,
+        delta: u64,
+    },
+    /// Checkpoints the store, closes it and opens it again
+    Reopen,
+}
+
+/// Operations shared by the store under test and the model
+pub trait KvTarget {
+    fn upsert(&mut self, key: u64, value: u64) -> Status;
+    /// `Ok(None)` if the key is missing or deleted
+    fn read(&mut self, key: u64) -> Result<Option<u64>, Status>;
+    fn delete(&mut self, key: u64) -> Status;
+    fn rmw(&mut self, key: u64, delta: u64) -> Status;
+    /// Durably persists everything written so far, then reopens from disk
+    fn reopen(&mut self) -> Result<(), Status>;
+}
+
+/// Reference store: a `BTreeMap` with the semantics `RsKv` should have
+#[derive(Debug, Clone, Default)]
+pub struct ModelStore {
+    map: BTreeMap<u64, u64>,
+}
+
+impl ModelStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of live keys
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl KvTarget for ModelStore {
+    fn upsert(&mut self, key: u64, value: u64) -> Status {
+        self.map.insert(key, value);
+        Status::Ok
+    }
+
+    fn read(&mut self, key: u64) -> Result<Option<u64>, Status> {
+        Ok(self.map.get(&key).copied())
+    }
+
+    fn delete(&mut self, key: u64) -> Status {
+        self.map.remove(&key);
+        Status::Ok
+    }
+
+    fn rmw(&mut self, key: u64, delta: u64) -> Status {
+        let value = self.map.entry(key).or_insert(0);
+        *value = value.wrapping_add(delta);
+        Status::Ok
+    }
+
+    fn reopen(&mut self) -> Result<(), Status> {
+        Ok(())
+    }
+}
+
+/// First point where the store and the model disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the op in the trace
+    pub step: usize,
+    pub op: Op,
+    /// Key whose observable state differs
+    pub key: Option<u64>,
+    /// What the model returned
+    pub expected: String,
+    /// What the store returned
+    pub actual: String,
+}
+
+/// Applies `ops` to `store` and `model` in order. After every op, the
+/// results and the value of the touched key must match; after a
+/// [`Op::Reopen`], every key the trace has touched must match.
+pub fn run_trace(
+    store: &mut impl KvTarget,
+    model: &mut impl KvTarget,
+    ops: &[Op],
+) -> Result<(), Divergence> {
+    let mut touched = BTreeSet::new();
+    for (step, op) in ops.iter().enumerate() {
+        let diverged = |key: Option<u64>, expected: String, actual: String| Divergence {
+            step,
+            op: *op,
+            key,
+            expected,
+            actual,
+        };
+        let (key, expected, actual) = match *op {
+            Op::Upsert { key, value } => (key, model.upsert(key, value), store.upsert(key, value)),
+            Op::Delete { key } => (key, model.delete(key), store.delete(key)),
+            Op::Rmw { key, delta } => (key, model.rmw(key, delta), store.rmw(key, delta)),
+            Op::Read { key } => (key, Status::Ok, Status::Ok),
+            Op::Reopen => {
+                let expected = model.reopen();
+                let actual = store.reopen();
+                if expected != actual {
+                    return Err(diverged(
+                        None,
+                        format!("{:?}", expected),
+                        format!("{:?}", actual),
+                    ));
+                }
+                for key in &touched {
+                    compare_key(store, model, *key)
+                        .map_err(|(expected, actual)| diverged(Some(*key), expected, actual))?;
+                }
+                continue;
+            }
+        };
+        if expected != actual {
+            return Err(diverged(
+                Some(key),
+                format!("{:?}", expected),
+                format!("{:?}", actual),
+            ));
+        }
+        touched.insert(key);
+        compare_key(store, model, key)
+            .map_err(|(expected, actual)| diverged(Some(key), expected, actual))?;
+    }
+    Ok(())
+}
+
+fn compare_key(
+    store: &mut impl KvTarget,
+    model: &mut impl KvTarget,
+    key: u64,
+) -> Result<(), (String, String)> {
+    let expected = model.read(key);
+    let actual = store.read(key);
+    if expected == actual {
+        Ok(())
+    } else {
+        Err((format!("{:?}", expected), format!("{:?}", actual)))
+    }
+}
+
+/// Mix of operations produced by [`random_trace`], as relative weights
+#[derive(Debug, Clone, Copy)]
+pub struct TraceWeights {
+    pub upsert: u32,
+    pub read: u32,
+    pub delete: u32,
+    pub rmw: u32,
+    pub reopen: u32,
+}
+
+impl Default for TraceWeights {
+    fn default() -> Self {
+        Self {
+            upsert: 40,
+            read: 30,
+            delete: 15,
+            rmw: 14,
+            reopen: 1,
+        }
+    }
+}
+
+/// `len` random operations over keys `0..key_space`. A small key space
+/// gives more overwrites and deletes of live keys.
+pub fn random_trace(
+    rng: &mut impl Rng,
+    len: usize,
+    key_space: u64,
+    weights: TraceWeights,
+) -> Vec<Op> {
+    let total = weights.upsert + weights.read + weights.delete + weights.rmw + weights.reopen;
+    (0..len)
+        .map(|_| {
+            let key = rng.random_range(0..key_space.max(1));
+            let mut pick = rng.random_range(0..total.max(1));
+            for (weight, op) in [
+                (
+                    weights.upsert,
+                    Op::Upsert {
+                        key,
+                        value: rng.random(),
+                    },
+                ),
+                (weights.read, Op::Read { key }),
+                (weights.delete, Op::Delete { key }),
+                (
+                    weights.rmw,
+                    Op::Rmw {
+                        key,
+                        delta: rng.random_range(1..1000),
+                    },
+                ),
+            ] {
+                if pick < weight {
+                    return op;
+                }
+                pick -= weight;
+            }
+            Op::Reopen
+        })
+        .collect()
+}
+
+struct Put {
+    key: u64,
+    value: u64,
+}
+
+impl UpsertContext for Put {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn value(&self) -> &u64 {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn put_atomic(&self, _value: &mut u64) -> bool {
+        false
+    }
+}
+
+struct Get {
+    key: u64,
+    value: Option<u64>,
+}
+
+impl ReadContext for Get {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn get(&mut self, value: &u64) {
+        self.value = Some(*value);
+    }
+}
+
+struct Remove {
+    key: u64,
+}
+
+impl DeleteContext for Remove {
+    type Key = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+}
+
+struct Add {
+    key: u64,
+    delta: u64,
+}
+
+impl RmwContext for Add {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &u64 {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn rmw_initial(&self, value: &mut u64) {
+        *value = self.delta;
+    }
+
+    fn rmw_copy(&self, old_value: &u64, new_value: &mut u64) {
+        *new_value = old_value.wrapping_add(self.delta);
+    }
+
+    fn rmw_atomic(&self, _value: &mut u64) -> bool {
+        false
+    }
+}
+
+/// An on-disk [`RsKv`] with `u64` keys hashed to themselves. Every write
+/// appends a new record; in-place updates are not exercised.
+pub struct RsKvTarget {
+    dir: String,
+    options: RecoveryOptions,
+    kv: Option<RsKv<'static, u64, u64, FileSystemDisk>>,
+    reopens: u64,
+}
+
+impl RsKvTarget {
+    /// Creates an empty store in `dir`
+    pub fn create(dir: &str, options: RecoveryOptions) -> Result<Self, Status> {
+        let disk = FileSystemDisk::new(dir)?;
+        let kv = RsKv::new(options.log_size, options.table_size, disk)?;
+        Ok(Self {
+            dir: dir.to_string(),
+            options,
+            kv: Some(kv),
+            reopens: 0,
+        })
+    }
+
+    pub fn store(&self) -> &RsKv<'static, u64, u64, FileSystemDisk> {
+        self.kv.as_ref().expect("store is open between operations")
+    }
+}
+
+impl KvTarget for RsKvTarget {
+    fn upsert(&mut self, key: u64, value: u64) -> Status {
+        self.store().upsert(&Put { key, value })
+    }
+
+    fn read(&mut self, key: u64) -> Result<Option<u64>, Status> {
+        let mut context = Get { key, value: None };
+        match self.store().read(&mut context) {
+            Status::Ok => Ok(context.value),
+            Status::NotFound => Ok(None),
+            status => Err(status),
+        }
+    }
+
+    fn delete(&mut self, key: u64) -> Status {
+        match self.store().delete(&Remove { key }) {
+            // Deleting a missing key is not an error for the model either
+            Status::NotFound => Status::Ok,
+            status => status,
+        }
+    }
+
+    fn rmw(&mut self, key: u64, delta: u64) -> Status {
+        self.store().rmw(&mut Add { key, delta })
+    }
+
+    fn reopen(&mut self) -> Result<(), Status> {
+        let token = format!("model-{}", self.reopens);
+        self.reopens += 1;
+        let mut kv = self.kv.take().ok_or(Status::UnexpectedState)?;
+        kv.checkpoint(&token)?;
+        drop(kv);
+        self.kv = Some(RsKv::open(&self.dir, Some(&token), &self.options, |key| {
+            *key
+        })?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir::TempDir;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn options() -> RecoveryOptions {
+        RecoveryOptions {
+            log_size: 1 << 25,
+            table_size: 1 << 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_random_traces_match_model() {
+        for seed in 0..3 {
+            let temp = TempDir::new(&format!("model_{}", seed));
+            let dir = temp.path();
+            let mut rng = StdRng::seed_from_u64(seed);
+            let ops = random_trace(&mut rng, 300, 64, TraceWeights::default());
+            assert!(ops.contains(&Op::Reopen));
+
+            let mut store = RsKvTarget::create(dir, options()).unwrap();
+            let mut model = ModelStore::new();
+            if let Err(divergence) = run_trace(&mut store, &mut model, &ops) {
+                panic!("seed {}: {:?}", seed, divergence);
+            }
+        }
+    }
+
+    /// Stands in for a store with a lost-update bug
+    struct DropsEveryThirdWrite {
+        inner: ModelStore,
+        writes: u64,
+    }
+
+    impl KvTarget for DropsEveryThirdWrite {
+        fn upsert(&mut self, key: u64, value: u64) -> Status {
+            self.writes += 1;
+            if self.writes.is_multiple_of(3) {
+                return Status::Ok;
+            }
+            self.inner.upsert(key, value)
+        }
+
+        fn read(&mut self, key: u64) -> Result<Option<u64>, Status> {
+            self.inner.read(key)
+        }
+
+        fn delete(&mut self, key: u64) -> Status {
+            self.inner.delete(key)
+        }
+
+        fn rmw(&mut self, key: u64, delta: u64) -> Status {
+            self.inner.rmw(key, delta)
+        }
+
+        fn reopen(&mut self) -> Result<(), Status> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_divergence_reports_first_bad_step() {
+        let ops = vec![
+            Op::Upsert { key: 1, value: 10 },
+            Op::Upsert { key: 2, value: 20 },
+            Op::Read { key: 1 },
+            Op::Upsert { key: 1, value: 11 },
+            Op::Delete { key: 2 },
+        ];
+        let mut store = DropsEveryThirdWrite {
+            inner: ModelStore::new(),
+            writes: 0,
+        };
+        let mut model = ModelStore::new();
+        let divergence = run_trace(&mut store, &mut model, &ops).unwrap_err();
+        assert_eq!(divergence.step, 3);
+        assert_eq!(divergence.key, Some(1));
+        assert_eq!(divergence.expected, "Ok(Some(11))");
+        assert_eq!(divergence.actual, "Ok(Some(10))");
+        assert_eq!(model.len(), 2);
+    }
+}
@@ -0,0 +1,38 @@
+//! Scratch directories for tests that open stores on disk.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A uniquely named directory under [`std::env::temp_dir`], removed with
+/// everything in it when dropped.
+///
+/// The directory itself is not created, since stores create their storage
+/// directory when they are opened. Anything left at the path by an earlier
+/// run is removed first.
+#[derive(Debug)]
+pub struct TempDir {
+    path: String,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir()
+            .join(format!("rskv_{}_{}_{}", name, std::process::id(), id))
+            .to_string_lossy()
+            .into_owned();
+        let _ = std::fs::remove_dir_all(&path);
+        Self { path }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
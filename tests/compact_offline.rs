@@ -0,0 +1,141 @@
+use rskv::RsKv;
+use rskv::admin::{DumpOptions, OfflineCompactOptions, compact_offline, dump};
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::rskv_core::{DeleteContext, UpsertContext};
+use std::fs;
+use std::path::Path;
+
+struct U64Upsert {
+    key: u64,
+    value: u64,
+}
+
+impl UpsertContext for U64Upsert {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn value(&self) -> &Self::Value {
+        &self.value
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn put_atomic(&self, _value: &mut Self::Value) -> bool {
+        false
+    }
+}
+
+struct U64Delete {
+    key: u64,
+}
+
+impl DeleteContext for U64Delete {
+    type Key = u64;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+}
+
+fn temp_dir(name: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("/tmp/rskv_{}_{}", name, nanos)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .map(|entry| {
+            if entry.file_type().unwrap().is_dir() {
+                dir_size(&entry.path())
+            } else {
+                entry.metadata().unwrap().len()
+            }
+        })
+        .sum()
+}
+
+/// Newest value of every live key, one `key<TAB>value` line each, sorted.
+fn contents(dir: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    dump::<u64, u64>(dir, &mut out, &DumpOptions::default()).unwrap();
+    let mut lines: Vec<String> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn test_compact_offline_shrinks_a_store_bloated_by_overwrites() {
+    let src = temp_dir("offline_src");
+    let dst = temp_dir("offline_dst");
+    {
+        let disk = FileSystemDisk::new(&src).unwrap();
+        let mut kv = RsKv::<u64, u64, FileSystemDisk>::new(1 << 26, 1 << 10, disk).unwrap();
+        for round in 0..100 {
+            for key in 0..200 {
+                let value = key * 1000 + round;
+                assert_eq!(kv.upsert(&U64Upsert { key, value }), Status::Ok);
+            }
+            kv.flush().unwrap();
+        }
+        for key in 150..200 {
+            assert_eq!(kv.delete(&U64Delete { key }), Status::Ok);
+        }
+        kv.checkpoint("before").unwrap();
+    }
+
+    let report =
+        compact_offline::<u64, u64>(&src, &dst, &OfflineCompactOptions::default(), |key| *key)
+            .unwrap();
+    assert_eq!(report.records_kept, 150);
+    assert_eq!(report.tombstones_dropped, 50);
+    assert_eq!(report.frames_skipped, 0);
+    assert_eq!(
+        report.superseded_dropped,
+        report.records_scanned - report.records_kept - report.tombstones_dropped
+    );
+    assert_eq!(report.bytes_before, dir_size(Path::new(&src)));
+    assert_eq!(report.bytes_after, dir_size(Path::new(&dst)));
+    assert!(
+        report.bytes_after * 4 < report.bytes_before,
+        "{} -> {} bytes",
+        report.bytes_before,
+        report.bytes_after
+    );
+
+    let live = contents(&src);
+    assert_eq!(live.len(), 150);
+    assert_eq!(contents(&dst), live);
+
+    // The new store recovers from its own checkpoint.
+    let kv = RsKv::<u64, u64, FileSystemDisk>::recover(&dst, "compacted").unwrap();
+    drop(kv);
+
+    // A second run into the same directory is refused.
+    assert_eq!(
+        compact_offline::<u64, u64>(&src, &dst, &OfflineCompactOptions::default(), |key| *key),
+        Err(Status::InvalidConfiguration)
+    );
+
+    let _ = fs::remove_dir_all(&src);
+    let _ = fs::remove_dir_all(&dst);
+}
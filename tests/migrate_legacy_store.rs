@@ -0,0 +1,89 @@
+#![cfg(feature = "legacy-format")]
+
+use rskv::RsKv;
+use rskv::core::status::Status;
+use rskv::device::file_system_disk::FileSystemDisk;
+use rskv::migrate::{MigrationOptions, migrate_store};
+use rskv::rskv_core::ReadContext;
+use std::fs;
+
+/// `fixtures/legacy_store` holds a u64 -> u64 log in the format used before
+/// flush frames and the superblock: keys 1..=20 mapped to key * 10, then key 5
+/// updated to 555, a tombstone for key 7 and an invalidated update of key 9.
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/legacy_store");
+
+struct U64ReadContext {
+    key: u64,
+    value: Option<u64>,
+}
+
+impl ReadContext for U64ReadContext {
+    type Key = u64;
+    type Value = u64;
+
+    fn key(&self) -> &Self::Key {
+        &self.key
+    }
+
+    fn key_hash(&self) -> u64 {
+        self.key
+    }
+
+    fn get(&mut self, value: &Self::Value) {
+        self.value = Some(*value);
+    }
+}
+
+fn temp_dir(name: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("/tmp/rskv_{}_{}", name, nanos)
+}
+
+#[test]
+fn test_migrate_legacy_store() {
+    // Work on a copy so the lock file does not land in the fixture.
+    let src = temp_dir("legacy_src");
+    let dst = temp_dir("legacy_dst");
+    fs::create_dir_all(&src).unwrap();
+    fs::copy(format!("{}/hlog.log", FIXTURE), format!("{}/hlog.log", src)).unwrap();
+
+    let report =
+        migrate_store::<u64, u64>(&src, &dst, &MigrationOptions::default(), |key| *key).unwrap();
+    assert_eq!(report.records_migrated, 19);
+    assert_eq!(report.tombstones_skipped, 1);
+    assert_eq!(
+        report.bytes_before,
+        fs::metadata(format!("{}/hlog.log", FIXTURE)).unwrap().len()
+    );
+    assert!(report.bytes_after > 0);
+
+    let kv = RsKv::<u64, u64, FileSystemDisk>::recover(&dst, "migrated").unwrap();
+    for key in 1..=20u64 {
+        let mut context = U64ReadContext { key, value: None };
+        let status = kv.read(&mut context);
+        match key {
+            5 => assert_eq!(context.value, Some(555)),
+            7 => assert_eq!(status, Status::NotFound),
+            _ => assert_eq!(context.value, Some(key * 10)),
+        }
+    }
+    drop(kv);
+
+    // The migrated store is in the current format and is not migrated again.
+    assert_eq!(
+        migrate_store::<u64, u64>(
+            &dst,
+            &temp_dir("legacy_again"),
+            &MigrationOptions::default(),
+            |key| *key
+        )
+        .err(),
+        Some(Status::InvalidDataFormat)
+    );
+
+    let _ = fs::remove_dir_all(&src);
+    let _ = fs::remove_dir_all(&dst);
+}